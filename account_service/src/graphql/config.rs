@@ -15,6 +15,7 @@ use redis::{
     aio::ConnectionManager as RedisManager, 
     Client as RedisClient, 
 };
+use common_utils::metrics::GraphQLMetrics;
 
 
 pub fn configure_service(cfg: &mut web::ServiceConfig) { 
@@ -70,6 +71,7 @@ pub fn create_schema(
     // Add a global data that can be accessed in the Schema
     .data(pool)
     .extension(ApolloTracing)
+    .extension(GraphQLMetrics)
     .finish()
 }
 // Run migrations. TODO: only do this on dev environment
@@ -80,10 +82,10 @@ pub async fn run_migrations(pool: &DbPool) {
         .expect("Failed to run database migrations");
 }
 
-pub fn get_conn_from_ctx(ctx: &Context<'_>) -> DbPool { 
-    let pool = ctx.data::<DbPool>()
-        .expect("Failed to get Db Pool");
-    pool.clone()
+/// Storage handle of a resolver, the `DbPool` unless the schema was built around another store
+pub fn get_store_from_ctx<'a, S: Send + Sync + 'static>(ctx: &Context<'a>) -> &'a S { 
+    ctx.data::<S>()
+        .expect("Failed to get Db Pool")
 }
 
 /// Access Redis from the Context, use 'create_connection' to establish connection asynchronously
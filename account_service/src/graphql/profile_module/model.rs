@@ -1,5 +1,4 @@
 use chrono::{NaiveDateTime, Utc};
-use sqlx::{PgPool};
 use uuid::Uuid;
 use serde::{Deserialize, Serialize};
 use super::schema::{ProfileType, NewProfileInput};
@@ -51,22 +50,22 @@ impl From<&NewProfileInput> for NewProfile {
 
 
 impl Profiles { 
-    pub async fn get_profiles_by_owner<ProfileDatabase: ProfileResolver>(user_id: Uuid, conn: &PgPool) -> QueryResult<Vec<Profiles>> {
+    pub async fn get_profiles_by_owner<ProfileDatabase: ProfileResolver>(user_id: Uuid, conn: &ProfileDatabase::Store) -> QueryResult<Vec<Profiles>> {
         ProfileDatabase::get_profiles_by_owner(user_id, conn).await
     }
-    pub async fn get_profile_by_id<ProfileDatabase: ProfileResolver>(profile_id: Uuid, conn: &PgPool) -> QueryResult<Profiles> {
+    pub async fn get_profile_by_id<ProfileDatabase: ProfileResolver>(profile_id: Uuid, conn: &ProfileDatabase::Store) -> QueryResult<Profiles> {
         ProfileDatabase::get_profile_by_id(profile_id, conn).await
     }
-    pub async fn get_profile_by_name<ProfileDatabase: ProfileResolver>(username: String, conn: &PgPool) -> QueryResult<Profiles> {
+    pub async fn get_profile_by_name<ProfileDatabase: ProfileResolver>(username: String, conn: &ProfileDatabase::Store) -> QueryResult<Profiles> {
         ProfileDatabase::get_profile_by_name(username, conn).await
     }
-    pub async fn create_new_profile<ProfileDatabase: ProfileResolver>(new_profile: NewProfile, conn: &PgPool) -> QueryResult<Profiles> {
+    pub async fn create_new_profile<ProfileDatabase: ProfileResolver>(new_profile: NewProfile, conn: &ProfileDatabase::Store) -> QueryResult<Profiles> {
         ProfileDatabase::create_new_profile(new_profile, conn).await
     }
-    pub async fn update_profile_user<ProfileDatabase: ProfileResolver>(user_id: Uuid, profile_id: Uuid, new_profile: NewProfile, conn: &PgPool) -> QueryResult<Option<Profiles>> {
+    pub async fn update_profile_user<ProfileDatabase: ProfileResolver>(user_id: Uuid, profile_id: Uuid, new_profile: NewProfile, conn: &ProfileDatabase::Store) -> QueryResult<Option<Profiles>> {
         ProfileDatabase::update_profile_user(user_id, profile_id, new_profile, conn).await
     }
-    pub async fn delete_profile_by_user<ProfileDatabase: ProfileResolver>(user_id: Uuid, profile_id: Uuid, conn: &PgPool) -> QueryResult<bool> {
+    pub async fn delete_profile_by_user<ProfileDatabase: ProfileResolver>(user_id: Uuid, profile_id: Uuid, conn: &ProfileDatabase::Store) -> QueryResult<bool> {
         ProfileDatabase::delete_profile_by_user(user_id, profile_id, conn).await
     }
 }
\ No newline at end of file
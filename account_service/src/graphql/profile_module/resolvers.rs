@@ -4,20 +4,25 @@ use uuid::Uuid;
 use sqlx::PgPool;
 use crate::QueryResult;
 use chrono::Utc;
+/// Profiles are read and written through `Store`, the Postgres pool in production
 #[async_trait]
-pub trait ProfileResolver { 
-    async fn get_profiles_by_owner(user_id: Uuid, conn: &PgPool) -> QueryResult<Vec<Profiles>>;
-    async fn get_profile_by_id(profile_id: Uuid, conn: &PgPool) -> QueryResult<Profiles>;
-    async fn get_profile_by_name(username: String, conn: &PgPool) -> QueryResult<Profiles>;
-    async fn create_new_profile(new_profile: NewProfile, conn: &PgPool) -> QueryResult<Profiles>;
-    async fn update_profile_user(user_id: Uuid, profile_id: Uuid, new_profile: NewProfile, conn: &PgPool) -> QueryResult<Option<Profiles>>;
-    async fn delete_profile_by_user(user_id: Uuid, profile_id: Uuid, conn: &PgPool) -> QueryResult<bool>;
+pub trait ProfileResolver: Send + Sync + 'static { 
+    type Store: Send + Sync + 'static;
+    async fn get_profiles_by_owner(user_id: Uuid, conn: &Self::Store) -> QueryResult<Vec<Profiles>>;
+    async fn get_profile_by_id(profile_id: Uuid, conn: &Self::Store) -> QueryResult<Profiles>;
+    async fn get_profile_by_name(username: String, conn: &Self::Store) -> QueryResult<Profiles>;
+    async fn create_new_profile(new_profile: NewProfile, conn: &Self::Store) -> QueryResult<Profiles>;
+    async fn update_profile_user(user_id: Uuid, profile_id: Uuid, new_profile: NewProfile, conn: &Self::Store) -> QueryResult<Option<Profiles>>;
+    async fn delete_profile_by_user(user_id: Uuid, profile_id: Uuid, conn: &Self::Store) -> QueryResult<bool>;
 }
 
+#[derive(Default)]
 pub struct ProfileDatabase;
 
 #[async_trait]
 impl ProfileResolver for ProfileDatabase { 
+    type Store = PgPool;
+
     async fn get_profiles_by_owner(user_id: Uuid, conn: &PgPool) -> QueryResult<Vec<Profiles>> {
         let profile = sqlx::query_as!(Profiles, r#"SELECT * FROM profiles WHERE id = $1"#, user_id)
             .fetch_all(conn)
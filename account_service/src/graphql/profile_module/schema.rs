@@ -1,10 +1,13 @@
 use async_graphql::*;
 use chrono::NaiveDateTime;
-use crate::graphql::{to_uuid, config::get_conn_from_ctx};
-use super::{model::{Profiles, NewProfile}, resolvers::ProfileDatabase};
+use crate::graphql::{to_uuid, config::get_store_from_ctx};
+use super::{model::{Profiles, NewProfile}, resolvers::{ProfileDatabase, ProfileResolver}};
 use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+
+/// `R` defaults to `ProfileDatabase`
 #[derive(Default)]
-pub struct ProfileQuery;
+pub struct ProfileQuery<R = ProfileDatabase>(PhantomData<R>);
 
 #[derive(SimpleObject, Clone, Debug, Serialize, Deserialize)]
 pub struct ProfileType { 
@@ -16,12 +19,12 @@ pub struct ProfileType {
 }
 
 #[Object]
-impl ProfileQuery { 
+impl<R: ProfileResolver> ProfileQuery<R> { 
     #[graphql(name = "getProfilesFromUser")]
     async fn get_profiles(&self, ctx: &Context<'_>, user_id: ID) -> FieldResult<Vec<ProfileType>> { 
-        let profile = Profiles::get_profiles_by_owner::<ProfileDatabase>(
+        let profile = Profiles::get_profiles_by_owner::<R>(
             to_uuid(user_id)?,
-            &get_conn_from_ctx(ctx)
+            get_store_from_ctx(ctx)
         )
         .await
         .expect("Unable to get Profiles under this User ID")
@@ -33,9 +36,9 @@ impl ProfileQuery {
     }
     #[graphql(name = "getProfilesById")]
     async fn get_profile_id(&self, ctx: &Context<'_>, profile_id: ID) -> FieldResult<ProfileType> { 
-        let profile = Profiles::get_profile_by_id::<ProfileDatabase>(
+        let profile = Profiles::get_profile_by_id::<R>(
             to_uuid(profile_id)?,
-            &get_conn_from_ctx(ctx)
+            get_store_from_ctx(ctx)
         )
         .await
         .map(|f| ProfileType::from(&f))
@@ -45,9 +48,9 @@ impl ProfileQuery {
     }
     #[graphql(name = "getProfilesByUsername")]
     async fn get_profile_name(&self, ctx: &Context<'_>, username: String) -> FieldResult<ProfileType>  {
-        let profile = Profiles::get_profile_by_name::<ProfileDatabase>(
+        let profile = Profiles::get_profile_by_name::<R>(
             username,
-            &get_conn_from_ctx(ctx)
+            get_store_from_ctx(ctx)
         )
         .await
         .map(|f| ProfileType::from(&f))
@@ -58,7 +61,7 @@ impl ProfileQuery {
 }
 
 #[derive(Default)]
-pub struct ProfileMutation;
+pub struct ProfileMutation<R = ProfileDatabase>(PhantomData<R>);
 
 #[derive(InputObject, Clone, Debug)]
 pub struct NewProfileInput { 
@@ -69,12 +72,12 @@ pub struct NewProfileInput {
 }
 
 #[Object]
-impl ProfileMutation { 
+impl<R: ProfileResolver> ProfileMutation<R> { 
     #[graphql(name = "createNewProfile")]
     async fn create_new_profile(&self, ctx: &Context<'_>, new_user: NewProfileInput) -> FieldResult<ProfileType> { 
-        let profile = Profiles::create_new_profile::<ProfileDatabase>(
+        let profile = Profiles::create_new_profile::<R>(
             NewProfile::from(&new_user),
-            &get_conn_from_ctx(ctx)
+            get_store_from_ctx(ctx)
         )
         .await
         .map(|f| ProfileType::from(&f))
@@ -85,10 +88,10 @@ impl ProfileMutation {
     
     #[graphql(name = "deleteProfile")]
     async fn delete_profile(&self, ctx: &Context<'_>, user_id: ID, profile_id: ID) -> FieldResult<bool> { 
-        let deleted_profile = Profiles::delete_profile_by_user::<ProfileDatabase>(
+        let deleted_profile = Profiles::delete_profile_by_user::<R>(
             to_uuid(user_id)?,
             to_uuid(profile_id)?,
-            &get_conn_from_ctx(ctx)
+            get_store_from_ctx(ctx)
         )
         .await
         .expect("Unable to delete profile by user");
@@ -97,11 +100,11 @@ impl ProfileMutation {
     }
     #[graphql(name = "updateUserProfile")]
     async fn update_profile(&self, ctx: &Context<'_>, user_id: ID, profile_id: ID, new_profile: NewProfileInput) -> FieldResult<Option<ProfileType>> { 
-        let profile = Profiles::update_profile_user::<ProfileDatabase>(
+        let profile = Profiles::update_profile_user::<R>(
             to_uuid(user_id)?,
             to_uuid(profile_id)?,
             NewProfile::from(&new_profile),
-            &get_conn_from_ctx(ctx)
+            get_store_from_ctx(ctx)
         )
         .await
         .expect("Unable to update")
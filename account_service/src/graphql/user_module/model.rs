@@ -1,6 +1,5 @@
 use async_graphql::Enum;
 use chrono::{NaiveDateTime, Utc};
-use sqlx::{PgPool};
 use uuid::Uuid;
 use serde::{Deserialize, Serialize};
 use super::{
@@ -90,39 +89,39 @@ impl From<&NewUserInput> for NewUser {
 
 impl Users { 
     #[tracing::instrument(skip(conn), err)]
-    pub async fn get_all_users<UserDatabase: UserResolver>(conn: &PgPool) -> QueryResult<Vec<Self>> { 
+    pub async fn get_all_users<UserDatabase: UserResolver>(conn: &UserDatabase::Store) -> QueryResult<Vec<Self>> { 
         UserDatabase::get_all_users(conn).await
     }
     #[tracing::instrument(skip(conn), err)]
-    pub async fn get_user_by_id<UserDatabase: UserResolver>(id: Uuid, conn: &PgPool) -> QueryResult<Option<Self>> { 
+    pub async fn get_user_by_id<UserDatabase: UserResolver>(id: Uuid, conn: &UserDatabase::Store) -> QueryResult<Option<Self>> { 
         UserDatabase::get_user_by_id(id, conn).await
     }
     #[tracing::instrument(skip(conn), err)]
-    pub async fn create_user<UserDatabase: UserResolver>(new_user: NewUser, conn: &PgPool) -> QueryResult<Users> { 
+    pub async fn create_user<UserDatabase: UserResolver>(new_user: NewUser, conn: &UserDatabase::Store) -> QueryResult<Users> { 
         UserDatabase::create_user(new_user, conn).await
     }
     #[tracing::instrument(skip(conn), err)]
-    pub async fn get_user_by_name<UserDatabase: UserResolver>(username: String, conn: &PgPool) -> QueryResult<Option<Self>> { 
+    pub async fn get_user_by_name<UserDatabase: UserResolver>(username: String, conn: &UserDatabase::Store) -> QueryResult<Option<Self>> { 
         UserDatabase::get_user_by_username(username, conn).await
     }
     #[tracing::instrument(skip(conn), err)]
-    pub async fn delete_user<UserDatabase: UserResolver>(user_id: Uuid, conn: &PgPool) -> QueryResult<bool> { 
+    pub async fn delete_user<UserDatabase: UserResolver>(user_id: Uuid, conn: &UserDatabase::Store) -> QueryResult<bool> { 
         UserDatabase::delete_user(user_id, conn).await
     }
     #[tracing::instrument(skip(conn), err)]
-    pub async fn update_user<UserDatabase: UserResolver>(user_id: Uuid, new_user: NewUser, conn: &PgPool) -> QueryResult<Option<Self>> { 
+    pub async fn update_user<UserDatabase: UserResolver>(user_id: Uuid, new_user: NewUser, conn: &UserDatabase::Store) -> QueryResult<Option<Self>> { 
         UserDatabase::update_user(user_id, new_user, conn).await
     }
     #[tracing::instrument(skip(conn), err)]
-    pub async fn update_password<UserDatabase: UserResolver>(user_id: Uuid, password: String, conn: &PgPool) -> QueryResult<Option<Self>> { 
+    pub async fn update_password<UserDatabase: UserResolver>(user_id: Uuid, password: String, conn: &UserDatabase::Store) -> QueryResult<Option<Self>> { 
         UserDatabase::update_password(user_id, password, conn).await
     }
     #[tracing::instrument(skip(conn), err)]
-    pub async fn get_user_by_email<UserDatabase: UserResolver>(email: String, conn: &PgPool) -> QueryResult<Option<Users>> { 
+    pub async fn get_user_by_email<UserDatabase: UserResolver>(email: String, conn: &UserDatabase::Store) -> QueryResult<Option<Users>> { 
         UserDatabase::get_user_by_email(email, conn).await
     }
     #[tracing::instrument(skip(conn), err)]
-    pub async fn update_last_login<UserDatabase: UserResolver>(user_id: Uuid, conn: &PgPool) -> QueryResult<bool> { 
+    pub async fn update_last_login<UserDatabase: UserResolver>(user_id: Uuid, conn: &UserDatabase::Store) -> QueryResult<bool> { 
         UserDatabase::update_last_login(user_id, conn).await
     }
 }
\ No newline at end of file
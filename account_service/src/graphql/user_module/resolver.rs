@@ -6,23 +6,28 @@ use crate::graphql::user_module::model::{Users, NewUser};
 use crate::graphql::utils::hash_password;
 use chrono::Utc;
 
+/// `Store` is the storage handle every call goes through, the Postgres pool for `UserDatabase`
 #[async_trait]
-pub trait UserResolver { 
-    async fn get_all_users(conn: &PgPool) -> QueryResult<Vec<Users>>;
-    async fn get_user_by_id(id: Uuid, conn: &PgPool) -> QueryResult<Option<Users>>;
-    async fn get_user_by_email(email: String, conn: &PgPool) -> QueryResult<Option<Users>>;
-    async fn get_user_by_username(username: String, conn: &PgPool) -> QueryResult<Option<Users>>;
-    async fn create_user(new_user: NewUser, conn: &PgPool) -> QueryResult<Users>;
-    async fn delete_user(user_id: Uuid, conn: &PgPool) -> QueryResult<bool>;
-    async fn update_user(user_id: Uuid, new_user: NewUser, conn: &PgPool) -> QueryResult<Option<Users>>;
-    async fn update_password(user_id: Uuid, password: String, conn: &PgPool) -> QueryResult<Option<Users>>;
-    async fn update_last_login(user_id: Uuid, conn: &PgPool) -> QueryResult<bool>;
+pub trait UserResolver: Send + Sync + 'static { 
+    type Store: Send + Sync + 'static;
+    async fn get_all_users(conn: &Self::Store) -> QueryResult<Vec<Users>>;
+    async fn get_user_by_id(id: Uuid, conn: &Self::Store) -> QueryResult<Option<Users>>;
+    async fn get_user_by_email(email: String, conn: &Self::Store) -> QueryResult<Option<Users>>;
+    async fn get_user_by_username(username: String, conn: &Self::Store) -> QueryResult<Option<Users>>;
+    async fn create_user(new_user: NewUser, conn: &Self::Store) -> QueryResult<Users>;
+    async fn delete_user(user_id: Uuid, conn: &Self::Store) -> QueryResult<bool>;
+    async fn update_user(user_id: Uuid, new_user: NewUser, conn: &Self::Store) -> QueryResult<Option<Users>>;
+    async fn update_password(user_id: Uuid, password: String, conn: &Self::Store) -> QueryResult<Option<Users>>;
+    async fn update_last_login(user_id: Uuid, conn: &Self::Store) -> QueryResult<bool>;
 }
 
+#[derive(Default)]
 pub struct UserDatabase;
 
 #[async_trait]
 impl UserResolver for UserDatabase { 
+    type Store = PgPool;
+
     #[tracing::instrument(skip(conn), fields(repository = "user"))]
     async fn get_all_users(conn: &PgPool) -> QueryResult<Vec<Users>> { 
         let user = sqlx::query_as!(Users, r#"SELECT * FROM users"#)
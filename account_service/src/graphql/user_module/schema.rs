@@ -4,10 +4,9 @@ use async_graphql::*;
 use async_graphql_actix_web::*;
 use common_utils::{error::ServiceError, generate_token};
 use serde::{Serialize, Deserialize};
-use sqlx::PgPool;
 use uuid::{Uuid, Error};
 use crate::graphql::{config::{
-    get_conn_from_ctx,
+    get_store_from_ctx,
     get_redis_conn_from_ctx,
     get_redis_conn_manager
 }, utils::verify_password};
@@ -15,17 +14,20 @@ use chrono::NaiveDateTime;
 use crate::graphql::to_uuid;
 use crate::graphql::user_module::{
     model::{Users, NewUser, Role},
-    resolver::{UserDatabase}
+    resolver::{UserDatabase, UserResolver}
 };
 use redis::{aio::ConnectionManager, Value,  AsyncCommands, RedisError};
 use crate::redis::{ get_post_cache_key, create_connection};
 use async_graphql::{validators::{email, min_length}};
 use common_utils::Role as AuthRole;
+use common_utils::metrics::record_cache_lookup;
+use std::marker::PhantomData;
 
 
 
+/// Generic over the resolver so the schema can be served from an in-memory store
 #[derive(Default)]
-pub struct UserQuery;
+pub struct UserQuery<R = UserDatabase>(PhantomData<R>);
 
 #[derive(SimpleObject, Serialize, Deserialize, Clone)]
 pub struct UserType { 
@@ -45,20 +47,20 @@ pub struct UserType {
 }
 
 #[Object(extends)]
-impl UserQuery { 
+impl<R: UserResolver> UserQuery<R> { 
     async fn test_api(&self) -> String { 
         "This is the User Service".into()
     }
 
     #[graphql(entity, name = "getUserByID")]
     async fn get_user(&self, ctx: &Context<'_>, #[graphql(key)] id: ID) -> FieldResult<UserType> { 
-        find_user_internally(ctx, id).await
+        find_user_internally::<R>(ctx, id).await
     }
 
     /// Get all the users
     #[graphql(name = "getAllUsers")]
     async fn get_all_users(&self, ctx: &Context<'_>) -> FieldResult<Vec<UserType>> { 
-        let user = Users::get_all_users::<UserDatabase>(&get_conn_from_ctx(ctx))
+        let user = Users::get_all_users::<R>(get_store_from_ctx(ctx))
             .await
             .expect("Unable to get Users")
             .iter() 
@@ -80,8 +82,9 @@ impl UserQuery {
         match cached_object { 
             Value::Nil => { 
                 log::info!("Unable to find cache under this id, accessing Database.. 😂");
+                record_cache_lookup("user", false);
 
-                let user = find_user_internally(ctx, user_id)
+                let user = find_user_internally::<R>(ctx, user_id)
                     .await
                     .map_err(|_| ServiceError::DatabaseError)
                     .ok()
@@ -99,6 +102,7 @@ impl UserQuery {
             },
             Value::Data(cache) => { 
                 log::info!("Cache Found Under this Id! 👌");
+                record_cache_lookup("user", true);
                 serde_json::from_slice(&cache).expect("Unable to Deserialize Struct")
             },
             _ => { None }
@@ -118,8 +122,9 @@ impl UserQuery {
         match cached_object { 
             Value::Nil => { 
                 log::info!("Unable to find cache under this id, accessing Database.. 😂");
+                record_cache_lookup("user", false);
 
-                let user = find_user_internally_by_name(ctx, username)
+                let user = find_user_internally_by_name::<R>(ctx, username)
                     .await
                     .map_err(|_| ServiceError::DatabaseError)
                     .ok()
@@ -137,6 +142,7 @@ impl UserQuery {
             },
             Value::Data(cache) => { 
                 log::info!("Cache Found Under this Id! 👌");
+                record_cache_lookup("user", true);
                 serde_json::from_slice(&cache).expect("Unable to Deserialize Struct")
             },
             _ => { None }
@@ -147,7 +153,7 @@ impl UserQuery {
 
 
 #[derive(Default)]
-pub struct UserMutation;
+pub struct UserMutation<R = UserDatabase>(PhantomData<R>);
 
 #[derive(InputObject)]
 pub struct NewUserInput { 
@@ -209,13 +215,13 @@ pub struct UserLogin {
 }
 
 #[Object]
-impl UserMutation { 
+impl<R: UserResolver> UserMutation<R> { 
     /// Create new users using the User Input
     #[graphql(name = "createNewUsers")]
     async fn create_user(&self, ctx: &Context<'_>, new_user: NewUserInput) -> UserType  {
-        Users::create_user::<UserDatabase>(
+        Users::create_user::<R>(
             NewUser::from(&new_user),
-            &get_conn_from_ctx(ctx)
+            get_store_from_ctx(ctx)
         ).await
         .map(|f| UserType::from(&f))
         .expect("")
@@ -223,9 +229,9 @@ impl UserMutation {
     /// Deletes the User from the system
     #[graphql(name = "deleteUser")]
     async fn delete_user(&self, ctx: &Context<'_>, user_id: ID) -> FieldResult<bool> { 
-        let result = Users::delete_user::<UserDatabase>( 
+        let result = Users::delete_user::<R>( 
             to_uuid(user_id.to_owned())?, 
-            &get_conn_from_ctx(ctx)
+            get_store_from_ctx(ctx)
         )
         .await
         .expect("Unable to delete user");
@@ -236,10 +242,10 @@ impl UserMutation {
     /// Update User Detaisl
     #[graphql(name = "updateUserDetails")]
     async fn update_user_details(&self, ctx: &Context<'_>, user_id: ID, new_user: NewUserInput) -> FieldResult<UserType> { 
-        let user = Users::update_user::<UserDatabase>(
+        let user = Users::update_user::<R>(
             to_uuid(user_id.to_owned())?,
             NewUser::from(&new_user),
-            &get_conn_from_ctx(ctx) 
+            get_store_from_ctx(ctx) 
         ).await.expect("Unable to update and retrieve the user").unwrap();
 
         //  Delete the cache under this key 
@@ -249,10 +255,10 @@ impl UserMutation {
     }
     #[graphql(name = "updateUserPassword")]
     async fn update_user_password(&self, ctx: &Context<'_>, user_id: ID, password: String) -> FieldResult<UserType> { 
-        let user = Users::update_password::<UserDatabase>(
+        let user = Users::update_password::<R>(
             to_uuid(user_id.to_owned())?,
             password,
-            &get_conn_from_ctx(ctx)
+            get_store_from_ctx(ctx)
         ).await.expect("Unable to retrieve the password and User details").unwrap();
 
         //  Delete the cache under this key 
@@ -262,16 +268,16 @@ impl UserMutation {
     /// Logins the user, Also Updates the LastUserLogin Row for the Same User
     #[graphql(name = "loginUser")]
     async fn login_user(&self, ctx: &Context<'_>, user: UserLogin) -> Result<String, ServiceError> { 
-        if let Some(user_info) = find_user_internally_by_email(ctx, user.email).await.ok() { 
+        if let Some(user_info) = find_user_internally_by_email::<R>(ctx, user.email).await.ok() { 
             if let Ok(role) = verify_password(&user_info.clone().expect("Missing UserPassword").hash, &user.password) { 
                 if role {
                     let user_role = AuthRole::from_str(user_info.clone().expect("Missing User Role").role.as_str()).expect("");
                     let token = generate_token(user_info.clone().expect("missing Username").email, user_role);
 
                     //  Update the last login
-                    Users::update_last_login::<UserDatabase>(
+                    Users::update_last_login::<R>(
                         to_uuid(user_info.clone().expect("Missing UserId").id)?, 
-                        &get_conn_from_ctx(ctx)
+                        get_store_from_ctx(ctx)
                     ).await?;
                     log::info!("User Login {}", user_info.expect("Missing Username").username);
                     return Ok(token)
@@ -284,23 +290,23 @@ impl UserMutation {
 
 ///  Internal Database Reads
 /// Find the user by id, this function does one job and doesnt check access the caching layer
-async fn find_user_internally(ctx: &Context<'_>, user_id: ID) -> FieldResult<UserType> { 
-    let user = Users::get_user_by_id::<UserDatabase>(to_uuid(user_id)?, &get_conn_from_ctx(ctx))
+async fn find_user_internally<R: UserResolver>(ctx: &Context<'_>, user_id: ID) -> FieldResult<UserType> { 
+    let user = Users::get_user_by_id::<R>(to_uuid(user_id)?, get_store_from_ctx(ctx))
         .await
         .expect("Unable to get Users")
         .map(|f| UserType::from(&f))
         .expect("Unable to convert UserDatabase Type into GraphQL Type");
     Ok(user)
 }
-async fn find_user_internally_by_name(ctx: &Context<'_>, username: String) -> FieldResult<UserType> { 
-    let user = Users::get_user_by_name::<UserDatabase>(username, &get_conn_from_ctx(ctx))
+async fn find_user_internally_by_name<R: UserResolver>(ctx: &Context<'_>, username: String) -> FieldResult<UserType> { 
+    let user = Users::get_user_by_name::<R>(username, get_store_from_ctx(ctx))
         .await?
         .map(|e| UserType::from(&e))
         .expect("Unable to get Username");
     Ok(user)
 }
-async fn find_user_internally_by_email(ctx: &Context<'_>, email: String) -> FieldResult<Option<UserType>> { 
-    let user = Users::get_user_by_email::<UserDatabase>(email, &get_conn_from_ctx(ctx))
+async fn find_user_internally_by_email<R: UserResolver>(ctx: &Context<'_>, email: String) -> FieldResult<Option<UserType>> { 
+    let user = Users::get_user_by_email::<R>(email, get_store_from_ctx(ctx))
         .await
         .expect("Unable to get User by email")
         .map(|f| UserType::from(&f));
@@ -0,0 +1,28 @@
+use common_utils::health::Readiness;
+use redis::aio::ConnectionManager;
+use crate::db::DbPool;
+
+/// Ready only when the Postgres pool hands out a working connection and Redis answers a ping
+pub fn readiness(pool: DbPool, redis_manager: ConnectionManager) -> Readiness { 
+    Readiness::new(env!("CARGO_PKG_NAME"))
+        .probe("postgres", move || { 
+            let pool = pool.clone();
+            async move { 
+                sqlx::query("SELECT 1")
+                    .execute(&pool)
+                    .await
+                    .map(|_| Some(format!("{} connections, {} idle", pool.size(), pool.num_idle())))
+                    .map_err(|e| e.to_string())
+            }
+        })
+        .probe("redis", move || { 
+            let mut connection = redis_manager.clone();
+            async move { 
+                redis::cmd("PING")
+                    .query_async::<_, String>(&mut connection)
+                    .await
+                    .map(|_| None)
+                    .map_err(|e| e.to_string())
+            }
+        })
+}
@@ -5,6 +5,7 @@ extern crate serde_derive;
 
 pub mod graphql;
 pub mod server;
+pub mod health;
 pub mod db;
 pub mod redis;
 pub mod telemetry;
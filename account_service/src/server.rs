@@ -9,6 +9,8 @@ use crate::{graphql::config::{graphql, graphql_playground, create_schema, run_mi
 use crate::db::{DatabaseKind, establish_connection};
 use crate::telemetry::init_telemetry;
 use tracing_actix_web::TracingLogger;
+use common_utils::health::configure_health;
+use crate::health::readiness;
 use std::fs::File;
 use std::io::Write;
 
@@ -30,6 +32,7 @@ pub async fn new_server(port: u32) -> std::io::Result<()> {
         .await
         .expect("Cannot Create Redis Connection Manager");
     //  GraphQl Schema
+    let health = readiness(db_pool.clone(), redis_connection_manager.clone());
     let schema = web::Data::new(create_schema(
         db_pool, 
         redis_client.clone(), 
@@ -68,6 +71,7 @@ pub async fn new_server(port: u32) -> std::io::Result<()> {
         App::new()
             .app_data(schema.clone())
             .configure(configure_service)
+            .configure(configure_health(health.clone()))
             .wrap(Cors::permissive())
             .wrap(Logger::default())
             .wrap(TracingLogger::default())
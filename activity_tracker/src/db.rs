@@ -4,6 +4,7 @@ use url::Url;
 use reqwest::Client;
 use crate::server::recreate_database;
 use once_cell::sync::OnceCell;
+use common_utils::metrics::datastore_timer;
 
 pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
@@ -68,6 +69,7 @@ impl InfluxDBClient {
     /// Write data to InfluxDB
     pub async fn write(&self, point: Point, precision: Option<Precision>, retention_policy: Option<&str>) -> Result<()> { 
         log::info!("Writing Point: {:#?} to Influx DB", point);
+        let _timer = datastore_timer("influxdb", "write");
         // let url = Url::parse(INFLUXDB_URL.as_ref()).expect("Unable to parse INFLUXDB_URL");
         // let client = InfluxClient::new(url, INFLUXDB_BUCKET.as_str().to_string())
         // .set_authentication(INFLUXDB_USER.to_string(),INFLUXDB_PASSWORD.to_string());
@@ -81,6 +83,7 @@ impl InfluxDBClient {
     /// Send a query to Influx DB
     pub async fn query(&self, query: &str, precision: Option<Precision>) -> Result<Option<Vec<Series>>> {
         log::info!("📦 Query results for {query} ");
+        let _timer = datastore_timer("influxdb", "query");
         // let url = Url::parse(INFLUXDB_URL.as_ref()).expect("Unable to parse INFLUXDB_URL");
         // let client = InfluxClient::new(url, INFLUXDB_BUCKET.as_str().to_string());
         let res = influx_client()
@@ -9,6 +9,7 @@ use async_graphql::{
 use async_graphql_actix_web::{GraphQLRequest, GraphQLResponse, GraphQLSubscription};
 use super::root_schema::{Mutation, Query, AppSchema, AppSchemaBuilder};
 use crate::db::{create_client, InfluxDBClient};
+use common_utils::metrics::GraphQLMetrics;
 
 pub fn configure_service(cfg: &mut web::ServiceConfig) { 
     cfg
@@ -53,10 +54,12 @@ pub fn create_schema(pool: InfluxDBClient) -> AppSchema {
     // Add a global data that can be accessed in the Schema
     .data(pool)
     .extension(ApolloTracing)
+    .extension(GraphQLMetrics)
     .finish()
 }
-pub fn get_conn_from_ctx(ctx: &Context<'_>) -> InfluxDBClient { 
-    let influx = ctx.data::<InfluxDBClient>()
+/// Clones the store registered on the schema, the `InfluxDBClient` outside of tests
+pub fn get_store_from_ctx<S: Clone + Send + Sync + 'static>(ctx: &Context<'_>) -> S { 
+    let influx = ctx.data::<S>()
         .expect("Failed to Connect to Database");
     influx.clone()
 }
\ No newline at end of file
@@ -2,10 +2,10 @@ use std::fmt::Display;
 use serde_tuple::*;
 use chrono::{Duration, Local, NaiveDateTime};
 use common_utils::QueryResult;
+use common_utils::events::PartitionKey;
 use influx_db_client::{Series, Point, Value};
 use serde_json::{Value as JsonValue, Number};
 use serde::{Serialize, Deserialize};
-use crate::db::InfluxDBClient;
 use thiserror::Error;
 use super::{resolver::{AnalyticsDatabase, AnalyticsResolver}, schema::{UserInfoInput, UserAnalytics}};
 
@@ -62,6 +62,12 @@ pub struct KafkaType {
     pub user_id: String,
 }
 
+impl PartitionKey for KafkaType { 
+    fn partition_key(&self) -> String {
+        self.user_id.clone()
+    }
+}
+
 impl From<UserAnalytics> for KafkaType { 
     fn from(f: UserAnalytics) -> Self {
         Self {
@@ -103,13 +109,13 @@ impl UserWatchTime {
 }
 
 impl UserWatchTime { 
-    pub async fn record_user_watchtime<Record: AnalyticsResolver>(user_info: UserWatchTime, client: InfluxDBClient) -> QueryResult<Vec<UserWatchTime>> { 
+    pub async fn record_user_watchtime<Record: AnalyticsResolver>(user_info: UserWatchTime, client: Record::Store) -> QueryResult<Vec<UserWatchTime>> { 
         Record::record_user_watchtime(user_info, client).await
     }
-    pub async fn get_all_records<Record: AnalyticsResolver>(client: InfluxDBClient) -> QueryResult<Vec<UserWatchTime>> { 
+    pub async fn get_all_records<Record: AnalyticsResolver>(client: Record::Store) -> QueryResult<Vec<UserWatchTime>> { 
         Record::get_all_records(client).await
     }
-    pub async fn get_user_records<Record: AnalyticsResolver>(user_id: i64, client: InfluxDBClient) -> QueryResult<Vec<UserWatchTime>> { 
+    pub async fn get_user_records<Record: AnalyticsResolver>(user_id: i64, client: Record::Store) -> QueryResult<Vec<UserWatchTime>> { 
         Record::get_user_records(user_id, client).await
     }
 }
\ No newline at end of file
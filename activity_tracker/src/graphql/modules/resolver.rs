@@ -6,19 +6,24 @@ use chrono::Local;
 use super::model::UserWatchTime;
 
 
+/// `Store` is handed over by value, so it has to be as cheap to clone as the `InfluxDBClient`
 #[async_trait]
-pub trait AnalyticsResolver {
-    async fn get_all_records(client: InfluxDBClient) -> QueryResult<Vec<UserWatchTime>>; 
-    async fn get_user_records(user_id: i64, client: InfluxDBClient) -> QueryResult<Vec<UserWatchTime>>;
-    async fn record_user_watchtime(user_info: UserWatchTime, client: InfluxDBClient) -> QueryResult<Vec<UserWatchTime>>;
+pub trait AnalyticsResolver: Send + Sync + 'static { 
+    type Store: Clone + Send + Sync + 'static;
+    async fn get_all_records(client: Self::Store) -> QueryResult<Vec<UserWatchTime>>; 
+    async fn get_user_records(user_id: i64, client: Self::Store) -> QueryResult<Vec<UserWatchTime>>;
+    async fn record_user_watchtime(user_info: UserWatchTime, client: Self::Store) -> QueryResult<Vec<UserWatchTime>>;
 }
 
+#[derive(Default)]
 pub struct AnalyticsDatabase;
 
 static GET_ALL: &str = "SELECT * FROM user_activity";
 
 #[async_trait]
 impl AnalyticsResolver for AnalyticsDatabase { 
+    type Store = InfluxDBClient;
+
     #[tracing::instrument(skip(client), fields(repository = "user_activity"))]
     async fn get_all_records(client: InfluxDBClient) -> QueryResult<Vec<UserWatchTime>> { 
         let res = client
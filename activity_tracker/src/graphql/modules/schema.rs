@@ -3,13 +3,16 @@ use async_graphql_actix_web::*;
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 use crate::graphql::modules::model::KafkaType;
+use common_utils::events::ActivityEvent;
 use crate::{graphql::modules::model::UserWatchTime, kafka};
-use crate::graphql::config::get_conn_from_ctx;
+use crate::graphql::config::get_store_from_ctx;
+use std::marker::PhantomData;
 use serde_json::Value;
 use super::resolver::{AnalyticsResolver, AnalyticsDatabase};
 
+/// `R` decides where watch records are read from and written to
 #[derive(Default)]
-pub struct AnalyticsQuery; 
+pub struct AnalyticsQuery<R = AnalyticsDatabase>(PhantomData<R>); 
 pub struct UserType { 
     pub id: ID
 }
@@ -30,7 +33,7 @@ pub struct UserAnalytics {
 }
 
 #[Object]
-impl AnalyticsQuery { 
+impl<R: AnalyticsResolver> AnalyticsQuery<R> { 
     #[graphql(entity)]
     async fn get_user(&self, #[graphql(key)] id: ID) -> UserType { 
         UserType { id }
@@ -38,7 +41,7 @@ impl AnalyticsQuery {
     #[tracing::instrument(skip(self, ctx))]
     #[graphql(name = "getAllRecords")]
     async fn get_all_records(&self, ctx: &Context<'_>) -> FieldResult<Vec<UserAnalytics>> { 
-        let res = UserWatchTime::get_all_records::<AnalyticsDatabase>(get_conn_from_ctx(ctx))
+        let res = UserWatchTime::get_all_records::<R>(get_store_from_ctx(ctx))
             .await
             .expect("")
             .into_iter()
@@ -48,7 +51,7 @@ impl AnalyticsQuery {
     #[tracing::instrument(skip(self, ctx))]
     #[graphql(name = "getUserRecords")]
     async fn get_user_records(&self, ctx: &Context<'_>, user_id: i64) -> FieldResult<Vec<UserAnalytics>> { 
-        let res = UserWatchTime::get_user_records::<AnalyticsDatabase>(user_id, get_conn_from_ctx(ctx))
+        let res = UserWatchTime::get_user_records::<R>(user_id, get_store_from_ctx(ctx))
             .await
             .expect("")
             .into_iter()
@@ -58,7 +61,7 @@ impl AnalyticsQuery {
 }
 
 #[derive(Default)]
-pub struct AnalyticsMutation;
+pub struct AnalyticsMutation<R = AnalyticsDatabase>(PhantomData<R>);
 
 #[derive(Debug, Clone, InputObject, Serialize, Deserialize)]
 pub struct UserInfoInput { 
@@ -69,13 +72,13 @@ pub struct UserInfoInput {
     pub liked: Option<bool>
 }
 #[Object]
-impl AnalyticsMutation { 
+impl<R: AnalyticsResolver> AnalyticsMutation<R> { 
     #[tracing::instrument(skip(self, ctx))]
     #[graphql(name = "recordUser")]
-    async fn record_user(&self, ctx: &Context<'_>, user_info: UserInfoInput) -> Vec<UserAnalytics> { 
-        let res: Vec<UserAnalytics> = UserWatchTime::record_user_watchtime::<AnalyticsDatabase>(
+    async fn record_user(&self, ctx: &Context<'_>, user_info: UserInfoInput) -> FieldResult<Vec<UserAnalytics>> { 
+        let res: Vec<UserAnalytics> = UserWatchTime::record_user_watchtime::<R>(
             UserWatchTime::from(&user_info),
-            get_conn_from_ctx(ctx)
+            get_store_from_ctx(ctx)
         ).await
         .expect("Unable to get the User Analytics")
         .into_iter()
@@ -91,8 +94,9 @@ impl AnalyticsMutation {
             
         //  Publish new message to kafka so it can be delivered to 
         //  tensorflow to be analysed along with the movie datasets
-        let message = serde_json::to_string(&kafka_type).expect("Unable to serialize movie");
-        kafka::send_message(&message).await;
-        res
+        kafka::send_event(ActivityEvent::WatchRecorded(kafka_type))
+            .await
+            .map_err(|e| e.extend())?;
+        Ok(res)
     }
 }
@@ -0,0 +1,14 @@
+use common_utils::health::{check_kafka_brokers, Readiness};
+use crate::{db::influx_client, kafka::kafka_producer};
+
+/// Ready only when InfluxDB answers a ping and the Kafka brokers can be reached
+pub fn readiness() -> Readiness { 
+    Readiness::new(env!("CARGO_PKG_NAME"))
+        .probe("influxdb", || async { 
+            match influx_client().0.ping().await { 
+                true => Ok(None),
+                false => Err(String::from("Ping was not answered"))
+            }
+        })
+        .probe("kafka", || check_kafka_brokers(kafka_producer().0.producer()))
+}
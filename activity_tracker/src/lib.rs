@@ -5,6 +5,7 @@ extern crate serde_json;
 extern crate serde_derive;
 pub mod graphql;
 pub mod server;
+pub mod health;
 pub mod db;
 pub mod kafka;
 pub mod telemetry;
\ No newline at end of file
@@ -6,6 +6,8 @@ use async_graphql::{
     EmptyMutation, EmptySubscription, Schema,
 };
 use async_graphql_actix_web::{GraphQLRequest, GraphQLResponse};
+use common_utils::health::configure_health;
+use crate::health::readiness;
 use std::fs::File;
 use std::io::Write;
 use crate::{db::{create_client}, kafka::create_producer};
@@ -50,6 +52,7 @@ pub async fn new_server(port: u32) -> std::io::Result<()> {
             .app_data(influx_client.clone())
             .app_data(schema.clone())
             .configure(configure_service)
+            .configure(configure_health(readiness()))
             .wrap(Cors::permissive())
             .wrap(Logger::default())
     })
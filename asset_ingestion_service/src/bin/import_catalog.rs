@@ -0,0 +1,84 @@
+//! Seeds a cluster from a JSONL or CSV catalogue file, without any call to TMDB
+//!
+//!     cargo run --bin import_catalog -- movies.jsonl [--format jsonl|csv] [--concurrency 16]
+//!
+//! Scylla and Kafka are configured from the same `.env` as the server. The report is printed
+//! as JSON, and the exit code is 1 when any row failed. The index events of the imported movies
+//! are relayed from the event outbox before exiting, those Kafka refuses are left to the server
+use std::fs::File;
+use asset_ingestion_service::db::establish_connection;
+use asset_ingestion_service::kafka::{create_producer, send_messages};
+use asset_ingestion_service::graphql::modules::types::{
+    genres::{model::Genre, resolver::GenreDatabase},
+    movies::{
+        import::{import_movies, parse_rows, ImportFormat, IMPORT_CONCURRENCY},
+        resolver::MovieDatabase,
+    },
+    outbox::{relay::{relay_once, RELAY_ID}, resolver::OutboxDatabase},
+};
+
+const USAGE: &str = "Usage: import_catalog <file> [--format jsonl|csv] [--concurrency <rows>]";
+
+struct ImportArgs {
+    path: String,
+    format: ImportFormat,
+    concurrency: usize,
+}
+
+fn parse_args() -> Result<ImportArgs, String> {
+    let mut args = std::env::args().skip(1);
+    let mut path = None;
+    let mut format = None;
+    let mut concurrency = *IMPORT_CONCURRENCY;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => format = match args.next().as_deref() {
+                Some("jsonl") => Some(ImportFormat::Jsonl),
+                Some("csv") => Some(ImportFormat::Csv),
+                _ => return Err(USAGE.to_string()),
+            },
+            "--concurrency" => concurrency = args.next()
+                .and_then(|rows| rows.parse::<usize>().ok())
+                .filter(|rows| *rows > 0)
+                .ok_or("`--concurrency` expects a positive number")?,
+            _ if path.is_none() => path = Some(arg),
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+    let path = path.ok_or(USAGE)?;
+    let format = format
+        .or_else(|| ImportFormat::from_filename(&path))
+        .ok_or_else(|| format!("Unknown format for {}, pass --format", path))?;
+    Ok(ImportArgs { path, format, concurrency })
+}
+
+#[actix_web::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    dotenv::dotenv().ok();
+    env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
+    let ImportArgs { path, format, concurrency } = parse_args()?;
+
+    let rows = parse_rows(format, File::open(&path)?);
+    let session = establish_connection().await?;
+    create_producer();
+    log::info!("📥 Importing {} rows from {} with {} concurrent writes", rows.len(), path, concurrency);
+
+    let taxonomy = Genre::taxonomy::<GenreDatabase>(session).await?;
+    let report = import_movies::<MovieDatabase>(rows, &taxonomy, session, concurrency).await;
+    println!("{}", serde_json::to_string_pretty(&report)?);
+
+    loop {
+        let sweep = relay_once::<OutboxDatabase, _, _>(&send_messages, &RELAY_ID, session).await?;
+        if sweep.pending > 0 || sweep.leased_elsewhere > 0 {
+            log::warn!("📤 Events are still in the outbox, the server's relay will publish them");
+            break
+        }
+        if !sweep.backlog {
+            break
+        }
+    }
+    if report.failed > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
@@ -1,10 +1,13 @@
 use dotenv::dotenv;
 use strum_macros::{EnumString, Display};
-use std::{env, time::Duration, ffi::FromBytesWithNulError, sync::Arc};
-use scylla::{self,IntoTypedRows, query::Query, Session, SessionBuilder, batch::Consistency, load_balancing::{DcAwareRoundRobinPolicy, TokenAwarePolicy}, CachingSession, frame::value::ValueList, QueryResult, SessionConfig, transport::{iterator::RowIterator, session::KnownNode}};
+use std::{env, time::Duration, ffi::FromBytesWithNulError, sync::Arc, collections::HashMap};
+use scylla::{self,IntoTypedRows, query::Query, batch::{Batch, BatchType}, prepared_statement::PreparedStatement, frame::value::SerializedValues, Session, SessionBuilder, batch::Consistency, load_balancing::{DcAwareRoundRobinPolicy, TokenAwarePolicy}, CachingSession, frame::value::ValueList, QueryResult, SessionConfig, transport::{iterator::RowIterator, session::KnownNode}, frame::response::cql_to_rust::FromRow};
 use lazy_static::lazy_static;
 use once_cell::sync::OnceCell;
+use parking_lot::Mutex;
+use common_utils::{metrics::datastore_timer, error::ServiceError};
 use std::fmt::Debug;
+use futures::TryStreamExt;
 
 use crate::server::{is_new_database, enable_tracing};
 
@@ -200,24 +203,38 @@ pub async fn initialise_pool() -> Result<()> {
     Ok(())
 }
 
-// Provides auto caching whiile executing queries
-pub struct CachedSession(pub CachingSession);
+// Provides auto caching whiile executing queries, the statements of logged batches are cached next to it
+pub struct CachedSession(pub CachingSession, Mutex<HashMap<&'static str, PreparedStatement>>);
 impl From<Session> for CachedSession { 
     fn from(f: Session) -> Self {
-        Self(CachingSession::from(f, 100))
+        Self(CachingSession::from(f, 100), Mutex::new(HashMap::new()))
     }
 }
 impl CachedSession { 
+    /// Prepares a statement of a logged batch the first time it is used on this session
+    async fn batch_statement(&self, statement: &'static str) -> common_utils::QueryResult<PreparedStatement> { 
+        let cached = self.1.lock().get(statement).cloned();
+        if let Some(prepared) = cached { 
+            return Ok(prepared)
+        }
+        let prepared = self
+            .0
+            .session
+            .prepare(statement)
+            .await
+            .map_err(|e| ServiceError::ServerError(e.to_string()))?;
+        self.1.lock().insert(statement, prepared.clone());
+        Ok(prepared)
+    }
     /// Simple query
     pub async fn query(&self, query: &str, values: impl ValueList + Debug) -> Result<QueryResult> { 
+        let _timer = datastore_timer("scylla", "query");
         log::info!("Executing query from Scylla Database!");
 
         let mut simple_query = Query::new(query);
         simple_query.set_tracing(true);
 
-        let result = self.0.execute(simple_query, &values)
-            .await
-            .expect("Error executing queries on Scylla DB");
+        let result = self.0.execute(simple_query, &values).await?;
         //  Query tracing info from system_traces.sessions and system_traces.events
         if enable_tracing() { 
             if let Some(id) = result.tracing_id { 
@@ -233,12 +250,12 @@ impl CachedSession {
     }
     ///  Executes a paged query 
     pub async fn query_iter(&self, query: &str, values: impl ValueList + Debug) -> Result<RowIterator> { 
+        let _timer = datastore_timer("scylla", "query_iter");
         log::info!("Preparing and paging new statemet");
         let result = self
             .0
             .execute_iter(Query::from(query), &values)
-            .await
-            .expect("Failed to prepare a query with paging");
+            .await?;
         Ok(result)
     }
     /// Executes a prepared query 
@@ -246,6 +263,7 @@ impl CachedSession {
     /// When using a prepared statements the client does a prepare phase, where the request is parsed and upon 
     /// execution only binds the values to the statemetn identifier
     pub async fn query_prepared(&self, query: &str, values: impl ValueList + Debug) -> Result<QueryResult> { 
+        let _timer = datastore_timer("scylla", "query_prepared");
         log::info!("Preparing and executing statement: {:#?}", query);
 
         let mut prepared = Query::from(query);
@@ -255,8 +273,10 @@ impl CachedSession {
             .0
             .execute(prepared, &values)
             .await
-            .map_err(|f| log::warn!("{:#?}", f))
-            .expect("Error executing a prepared query");
+            .map_err(|e| { 
+                log::warn!("{:#?}", e);
+                e
+            })?;
         //  Query tracing info from system_traces.sessions and system_traces.events
         if enable_tracing() { 
             if let Some(id) = result.tracing_id { 
@@ -272,4 +292,51 @@ impl CachedSession {
     }
 }
 
+/// Serialises bind values up front, so statements with different values can share one batch
+pub fn bind(values: impl ValueList) -> common_utils::QueryResult<SerializedValues> { 
+    values
+        .serialized()
+        .map(|values| values.into_owned())
+        .map_err(|e| ServiceError::ServerError(e.to_string()))
+}
+
+/// The first column of a conditional write (`IF ...`) is `[applied]`
+pub fn is_applied(result: scylla::QueryResult) -> bool { 
+    result.rows_or_empty()
+        .first()
+        .and_then(|row| row.columns.first())
+        .and_then(|column| column.as_ref())
+        .and_then(|value| value.as_boolean())
+        .unwrap_or(false)
+}
 
+/// Every row `query` returns, fetched page by page. `query_prepared` only gives back the first
+/// page, which silently drops the rest once a table outgrows it
+pub async fn read_all_pages<T: FromRow>(query: &'static str, session: &'static CachedSession) -> common_utils::QueryResult<Vec<T>> { 
+    session.query_iter(query, ())
+        .await
+        .map_err(|e| ServiceError::ServerError(e.to_string()))?
+        .into_typed::<T>()
+        .try_collect()
+        .await
+        .map_err(|e| ServiceError::ServerError(e.to_string()))
+}
+
+/// Executes the statements as one logged batch, each distinct statement is prepared once per session.
+/// Used wherever rows in more than one table have to be written together or not at all
+pub async fn write_logged_batch(statements: Vec<(&'static str, SerializedValues)>, session: &'static CachedSession) -> common_utils::QueryResult<()> { 
+    let _timer = datastore_timer("scylla", "batch");
+    let mut batch = Batch::new(BatchType::Logged);
+    let mut values = Vec::with_capacity(statements.len());
+    for (statement, value) in statements { 
+        batch.append_statement(session.batch_statement(statement).await?);
+        values.push(value);
+    }
+    session
+        .0
+        .session
+        .batch(&batch, values)
+        .await
+        .map_err(|e| ServiceError::ServerError(e.to_string()))?;
+    Ok(())
+}
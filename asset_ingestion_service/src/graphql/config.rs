@@ -6,32 +6,34 @@ use actix_web::{get, middleware::Logger, route, web, App, HttpServer, Responder,
 use actix_web_lab::respond::Html;
 use async_graphql::{
     http::{playground_source, GraphQLPlaygroundConfig},
-    EmptyMutation, EmptySubscription, Schema, Context, extensions::ApolloTracing, dataloader::DataLoader,
+    EmptyMutation, Schema, Context, extensions::ApolloTracing, dataloader::DataLoader,
 };
 use async_graphql_actix_web::{GraphQLRequest, GraphQLResponse, GraphQLSubscription};
 use crate::db::{CachedSession, session};
-use super::{root_schema::{Mutation, Query, AppSchema, AppSchemaBuilder}, 
-    modules::types::prod_company::resolver::CompanyDetailsLoader
+use super::{root_schema::{Mutation, Query, Subscription, AppSchema, AppSchemaBuilder}, 
+    modules::types::{prod_company::resolver::CompanyDetailsLoader, ingestion_jobs::source::TmdbSource, release::model::Editor, artwork::storage::LocalImageStore}
 };
+use common_utils::metrics::GraphQLMetrics;
 
 
 pub fn configure_service(cfg: &mut web::ServiceConfig) { 
+    // Websocket upgrades have to be matched before the plain GET on the same path
     cfg
-    .service(graphql)
-    .service(graphql_playground)
     .service(
-        web::resource("/graphiql")
+        web::resource("/graphql")
             .route(web::get()
                 .guard(guard::Header("upgrade", "websocket"))
                 .to(index_ws)
         )
-    );
+    )
+    .service(graphql)
+    .service(graphql_playground);
 }
 
-/// GraphQL endpoint
+/// GraphQL endpoint, status changes are recorded against the request's `Editor`
 #[route("/graphql", method = "GET", method = "POST")]
-pub async fn graphql(schema: web::Data<AppSchema>, req: GraphQLRequest) -> GraphQLResponse {
-    schema.execute(req.into_inner()).await.into()
+pub async fn graphql(schema: web::Data<AppSchema>, http: HttpRequest, req: GraphQLRequest) -> GraphQLResponse {
+    schema.execute(req.into_inner().data(Editor::new(&http))).await.into()
 }
 /// GraphiQL playground UI
 #[get("/graphiql")]
@@ -54,17 +56,20 @@ pub fn create_schema(pool: &'static CachedSession) -> AppSchema {
         CompanyDetailsLoader {pool}, 
         tokio::spawn
     ).max_batch_size(10);
-    Schema::build(Query::default(), Mutation::default(), EmptySubscription)
+    Schema::build(Query::default(), Mutation::default(), Subscription::default())
     // Add a global data that can be accessed in the Schema
     .data(dataloader)
     .data(pool)
+    .data(TmdbSource::shared())
+    .data(LocalImageStore::shared())
     .extension(ApolloTracing)
+    .extension(GraphQLMetrics)
     .finish()
 }
-/// Database Pool Connection 
-pub fn get_conn_from_ctx(ctx: &Context<'_>) -> &'static CachedSession { 
-    let pool = ctx.data::<&'static CachedSession>()
+/// Database Pool Connection, the `CachedSession` unless the schema was built around another store
+pub fn get_store_from_ctx<S: Send + Sync + 'static>(ctx: &Context<'_>) -> &'static S { 
+    let pool = ctx.data::<&'static S>()
         .expect("Failed to Connect to Database");
-    pool.clone()
+    *pool
 }
 
@@ -0,0 +1,6 @@
+pub mod model;
+pub mod resolver;
+pub mod schema;
+pub mod storage;
+pub mod processing;
+pub mod upload;
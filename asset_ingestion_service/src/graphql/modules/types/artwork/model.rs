@@ -0,0 +1,158 @@
+use std::collections::{HashMap, HashSet};
+use common_utils::{QueryResult, artwork::{ImageKind, ImageOwner, aspect_ratio}, error::ServiceError, locale::{language_of, normalise_locale}};
+use scylla::macros::{FromRow, ValueList};
+use serde::{Deserialize, Serialize};
+use crate::generate_unique_id;
+use super::resolver::ArtworkResolver;
+
+// Columns after the primary key are in alphabetical order, the order `SELECT *` returns them in
+/// An image of a movie or a person
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize, ValueList, PartialEq)]
+pub struct Artwork {
+    pub owner_type: String,
+    pub owner_id: i64,
+    pub image_id: i64,
+    pub aspect_ratio: f32,
+    /// Placeholder computed when the image was uploaded
+    pub blurhash: Option<String>,
+    /// TMDB path such as `/kqjL17yufvn9OVLyXYpvtyrFfak.jpg`, or an absolute URL
+    pub file_path: String,
+    pub height: i32,
+    pub is_primary: bool,
+    pub kind: String,
+    /// `None` for images without any text on them
+    pub language: Option<String>,
+    /// URLs of the renditions of an uploaded image by `variant_key`, `None` for imported images
+    pub variants: Option<HashMap<String, String>>,
+    pub width: i32,
+}
+
+/// ISO 639-1 code of `language`, a tag like `pt-BR` is cut down to its language
+fn image_language(language: Option<&str>) -> QueryResult<Option<String>> {
+    language
+        .map(|language| normalise_locale(language).map(|tag| language_of(&tag).to_string()))
+        .transpose()
+}
+
+impl Artwork {
+    /// People only have profile pictures, and only people have them
+    pub fn check_kind(owner: ImageOwner, kind: ImageKind) -> QueryResult<()> {
+        match (owner == ImageOwner::Person) == (kind == ImageKind::Profile) {
+            true => Ok(()),
+            false => Err(ServiceError::BadRequest(format!("A {} can't have a {} image", owner, kind))),
+        }
+    }
+    pub fn new(owner: ImageOwner, owner_id: i64, kind: ImageKind, file_path: &str, language: Option<&str>, width: i32, height: i32) -> QueryResult<Self> {
+        Self::check_kind(owner, kind)?;
+        if file_path.trim().is_empty() || width < 0 || height < 0 {
+            return Err(ServiceError::BadRequest("An image needs a path and dimensions that aren't negative".to_string()))
+        }
+        Ok(Self {
+            owner_type: owner.to_string(),
+            owner_id,
+            image_id: generate_unique_id(),
+            aspect_ratio: aspect_ratio(width, height),
+            blurhash: None,
+            file_path: file_path.trim().to_string(),
+            height,
+            is_primary: false,
+            kind: kind.to_string(),
+            language: image_language(language)?,
+            variants: None,
+            width,
+        })
+    }
+    /// A row with an unknown kind is read as a poster, or a profile picture for a person
+    pub fn kind(&self) -> ImageKind {
+        self.kind.parse().unwrap_or(match self.owner() {
+            ImageOwner::Movie => ImageKind::Poster,
+            ImageOwner::Person => ImageKind::Profile,
+        })
+    }
+    pub fn owner(&self) -> ImageOwner {
+        self.owner_type.parse().unwrap_or(ImageOwner::Movie)
+    }
+    /// Replaces the path and dimensions, the aspect ratio follows. A new path drops the renditions
+    /// and placeholder of the upload it replaces
+    pub fn resize(&mut self, file_path: Option<String>, width: Option<i32>, height: Option<i32>) -> QueryResult<()> {
+        let (width, height) = (width.unwrap_or(self.width), height.unwrap_or(self.height));
+        if width < 0 || height < 0 {
+            return Err(ServiceError::BadRequest("Dimensions can't be negative".to_string()))
+        }
+        if let Some(file_path) = file_path.filter(|file_path| !file_path.trim().is_empty()) {
+            if file_path.trim() != self.file_path {
+                self.blurhash = None;
+                self.variants = None;
+            }
+            self.file_path = file_path.trim().to_string();
+        }
+        self.width = width;
+        self.height = height;
+        self.aspect_ratio = aspect_ratio(width, height);
+        Ok(())
+    }
+    pub fn set_language(&mut self, language: Option<&str>) -> QueryResult<()> {
+        self.language = image_language(language)?;
+        Ok(())
+    }
+}
+
+/// Where the renditions of an uploaded image are stored, e.g. `movie/42/7091`
+pub fn storage_prefix(owner: ImageOwner, owner_id: i64, image_id: i64) -> String {
+    format!("{}/{}/{}", owner.to_string().to_lowercase(), owner_id, image_id)
+}
+
+/// `image` as the primary of its kind, followed by the images of `current` it takes the flag from.
+/// These are the rows to write
+pub fn promote(mut image: Artwork, current: &[Artwork]) -> Vec<Artwork> {
+    image.is_primary = true;
+    let demoted = current
+        .iter()
+        .filter(|f| f.is_primary && f.image_id != image.image_id && f.kind() == image.kind())
+        .map(|f| Artwork { is_primary: false, ..f.clone() });
+    std::iter::once(image.clone()).chain(demoted).collect()
+}
+
+/// The rows to write for `image`. It becomes the primary when asked to, or when it is the first of its kind
+pub fn place(image: Artwork, is_primary: bool, current: &[Artwork]) -> Vec<Artwork> {
+    let first = !current.iter().any(|f| f.kind() == image.kind() && f.image_id != image.image_id);
+    match is_primary || first {
+        true => promote(image, current),
+        false => vec![image],
+    }
+}
+
+/// The images of `imported` whose path isn't already in `current`. The first one of each kind is
+/// made primary when the owner has no primary of that kind yet, so order them best first
+pub fn merge_imported(current: &[Artwork], imported: Vec<Artwork>) -> Vec<Artwork> {
+    let paths = current.iter().map(|f| f.file_path.clone()).collect::<HashSet<_>>();
+    let mut has_primary = current.iter().filter(|f| f.is_primary).map(Artwork::kind).collect::<HashSet<_>>();
+    imported
+        .into_iter()
+        .filter(|image| !paths.contains(&image.file_path))
+        .map(|mut image| {
+            image.is_primary = has_primary.insert(image.kind());
+            image
+        })
+        .collect()
+}
+
+/// Path of the owner's primary poster, what the movie's own `poster` column holds
+pub fn primary_poster(images: &[Artwork]) -> Option<&str> {
+    images
+        .iter()
+        .find(|f| f.is_primary && f.kind() == ImageKind::Poster)
+        .map(|f| f.file_path.as_str())
+}
+
+impl Artwork {
+    pub async fn get_images<ArtworkDatabase: ArtworkResolver>(owner: ImageOwner, owner_id: i64, session: &'static ArtworkDatabase::Store) -> QueryResult<Vec<Artwork>> {
+        ArtworkDatabase::get_images(owner, owner_id, session).await
+    }
+    pub async fn save_images<ArtworkDatabase: ArtworkResolver>(images: Vec<Artwork>, session: &'static ArtworkDatabase::Store) -> QueryResult<Vec<Artwork>> {
+        ArtworkDatabase::save_images(images, session).await
+    }
+    pub async fn delete_image<ArtworkDatabase: ArtworkResolver>(owner: ImageOwner, owner_id: i64, image_id: i64, session: &'static ArtworkDatabase::Store) -> QueryResult<bool> {
+        ArtworkDatabase::delete_image(owner, owner_id, image_id, session).await
+    }
+}
@@ -0,0 +1,127 @@
+use std::io::Cursor;
+use common_utils::{QueryResult, artwork::{ImageFormat, ImageKind, ImageSize, variant_key}, error::ServiceError};
+use image::{DynamicImage, GenericImageView, ImageOutputFormat, imageops::FilterType, io::Reader};
+use lazy_static::lazy_static;
+
+lazy_static! {
+    /// Formats every upload is encoded in besides JPEG, or PNG for images with transparency,
+    /// e.g. `webp,avif`. AVIF is much slower to encode, so it is left out unless asked for
+    pub static ref IMAGE_VARIANT_FORMATS: Vec<ImageFormat> = std::env::var("IMAGE_VARIANT_FORMATS")
+        .unwrap_or_else(|_| "webp".to_string())
+        .split(',')
+        .filter_map(|format| format.trim().parse().ok())
+        .collect();
+    /// Longest side an upload may have, larger ones are rejected before they are decoded
+    pub static ref IMAGE_MAX_SIDE: u32 = std::env::var("IMAGE_MAX_SIDE")
+        .ok()
+        .and_then(|side| side.parse::<u32>().ok())
+        .unwrap_or(8192);
+}
+
+/// Quality of the lossy encodings, out of 100
+const QUALITY: u8 = 85;
+
+/// One size of an upload in one format
+pub struct Rendition {
+    /// `variant_key` of the rendition, e.g. `w500.webp`
+    pub key: String,
+    pub format: ImageFormat,
+    pub bytes: Vec<u8>,
+}
+
+/// An upload that passed validation, ready to be stored
+pub struct ProcessedImage {
+    /// What the renditions without a format of their own were stored as, JPEG or PNG
+    pub format: ImageFormat,
+    pub width: u32,
+    pub height: u32,
+    pub blurhash: String,
+    pub renditions: Vec<Rendition>,
+}
+
+/// The format of an upload's `Content-Type`, only JPEG, PNG and WebP are accepted
+fn upload_format(content_type: &str) -> QueryResult<image::ImageFormat> {
+    match content_type.split(';').next().unwrap_or_default().trim() {
+        "image/jpeg" | "image/jpg" => Ok(image::ImageFormat::Jpeg),
+        "image/png" => Ok(image::ImageFormat::Png),
+        "image/webp" => Ok(image::ImageFormat::WebP),
+        other => Err(ServiceError::BadRequest(format!("Unsupported content type {:?}, upload a JPEG, PNG or WebP image", other))),
+    }
+}
+
+/// The side a size segment bounds and its length, `w500` is a width and `h632` a height
+fn bound(segment: &str) -> Option<(char, u32)> {
+    let mut chars = segment.chars();
+    let axis = chars.next()?;
+    chars.as_str().parse().ok().map(|px| (axis, px))
+}
+
+/// `image` scaled down to fit a size segment such as `w500`. Images are never scaled up
+fn fit(image: &DynamicImage, segment: &str) -> DynamicImage {
+    let (width, height) = image.dimensions();
+    match bound(segment) {
+        Some(('w', px)) if px < width => image.resize(px, u32::MAX, FilterType::Lanczos3),
+        Some(('h', px)) if px < height => image.resize(u32::MAX, px, FilterType::Lanczos3),
+        _ => image.clone(),
+    }
+}
+
+fn encode(image: &DynamicImage, format: ImageFormat) -> QueryResult<Vec<u8>> {
+    let mut bytes = Cursor::new(Vec::new());
+    let written = match format {
+        // The JPEG encoder has no alpha channel
+        ImageFormat::Jpeg => DynamicImage::ImageRgb8(image.to_rgb8()).write_to(&mut bytes, ImageOutputFormat::Jpeg(QUALITY)),
+        ImageFormat::Png => image.write_to(&mut bytes, ImageOutputFormat::Png),
+        ImageFormat::Avif => image.write_to(&mut bytes, ImageOutputFormat::Avif),
+        ImageFormat::Webp => {
+            let image = DynamicImage::ImageRgba8(image.to_rgba8());
+            let encoder = webp::Encoder::from_image(&image).map_err(|e| ServiceError::ServerError(e.to_string()))?;
+            return Ok(encoder.encode(QUALITY as f32).to_vec())
+        }
+    };
+    written.map_err(|e| ServiceError::ServerError(e.to_string()))?;
+    Ok(bytes.into_inner())
+}
+
+/// Checks that `bytes` are the image `content_type` says they are and that it is large enough to
+/// be served as a medium `kind`, then renders every size in the stored format and in `IMAGE_VARIANT_FORMATS`.
+/// This is CPU bound, run it on a blocking thread
+pub fn process(kind: ImageKind, content_type: &str, bytes: &[u8]) -> QueryResult<ProcessedImage> {
+    let declared = upload_format(content_type)?;
+    match image::guess_format(bytes) {
+        Ok(format) if format == declared => (),
+        _ => return Err(ServiceError::BadRequest(format!("The upload is not the {} image its content type says it is", content_type))),
+    }
+    let (width, height) = Reader::with_format(Cursor::new(bytes), declared)
+        .into_dimensions()
+        .map_err(|e| ServiceError::BadRequest(e.to_string()))?;
+    if width.max(height) > *IMAGE_MAX_SIDE {
+        return Err(ServiceError::BadRequest(format!("Images can be at most {}px on a side", *IMAGE_MAX_SIDE)))
+    }
+    let too_small = match bound(kind.size_segment(ImageSize::Medium)) {
+        Some(('w', px)) => width < px,
+        Some(('h', px)) => height < px,
+        _ => false,
+    };
+    if too_small {
+        return Err(ServiceError::BadRequest(format!("A {} has to be at least as large as its {} rendition", kind, kind.size_segment(ImageSize::Medium))))
+    }
+    let image = image::load_from_memory_with_format(bytes, declared).map_err(|e| ServiceError::BadRequest(e.to_string()))?;
+    let stored = match image.color().has_alpha() {
+        true => ImageFormat::Png,
+        false => ImageFormat::Jpeg,
+    };
+    let mut formats = vec![stored];
+    formats.extend(IMAGE_VARIANT_FORMATS.iter().filter(|format| !matches!(format, ImageFormat::Jpeg | ImageFormat::Png)));
+    let mut renditions = Vec::new();
+    for size in [ImageSize::Small, ImageSize::Medium, ImageSize::Large, ImageSize::Original] {
+        let segment = kind.size_segment(size);
+        let sized = fit(&image, segment);
+        for format in &formats {
+            renditions.push(Rendition { key: variant_key(segment, *format), format: *format, bytes: encode(&sized, *format)? });
+        }
+    }
+    let thumbnail = image.thumbnail(32, 32).to_rgba8();
+    let blurhash = blurhash::encode(4, 3, thumbnail.width(), thumbnail.height(), thumbnail.as_raw());
+    Ok(ProcessedImage { format: stored, width, height, blurhash, renditions })
+}
@@ -0,0 +1,65 @@
+use async_trait::async_trait;
+use common_utils::{QueryResult, artwork::ImageOwner, error::ServiceError};
+use scylla::IntoTypedRows;
+use crate::db::{CachedSession, bind, is_applied, write_logged_batch};
+use super::model::Artwork;
+
+/// Images are stored by owner, so a movie or a person is read with a single partition
+#[async_trait]
+pub trait ArtworkResolver: Send + Sync + 'static {
+    type Store: Send + Sync + 'static;
+    async fn get_images(owner: ImageOwner, owner_id: i64, session: &'static Self::Store) -> QueryResult<Vec<Artwork>>;
+    /// Writes every image together, e.g. a new primary and the one it took the flag from
+    async fn save_images(images: Vec<Artwork>, session: &'static Self::Store) -> QueryResult<Vec<Artwork>>;
+    async fn delete_image(owner: ImageOwner, owner_id: i64, image_id: i64, session: &'static Self::Store) -> QueryResult<bool>;
+}
+
+#[derive(Default)]
+pub struct ArtworkDatabase;
+
+static GET_IMAGES: &str = "SELECT * FROM movie_keyspace.artwork WHERE owner_type = ? AND owner_id = ?;";
+static INSERT_IMAGE: &str = "
+    INSERT INTO movie_keyspace.artwork (
+        owner_type, owner_id, image_id, aspect_ratio, blurhash, file_path, height, is_primary, kind, language, variants, width
+    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?);
+";
+static DELETE_IMAGE: &str = "DELETE FROM movie_keyspace.artwork WHERE owner_type = ? AND owner_id = ? AND image_id = ? IF EXISTS;";
+
+#[async_trait]
+impl ArtworkResolver for ArtworkDatabase {
+    type Store = CachedSession;
+
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.artwork"), err)]
+    async fn get_images(owner: ImageOwner, owner_id: i64, session: &'static CachedSession) -> QueryResult<Vec<Artwork>> {
+        session.query_prepared(GET_IMAGES, (owner.to_string(), owner_id))
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
+            .into_typed::<Artwork>()
+            .map(|row| row.map_err(|e| ServiceError::ServerError(e.to_string())))
+            .collect()
+    }
+    #[tracing::instrument(skip(session, images), fields(repository = "movie_keyspace.artwork"), err)]
+    async fn save_images(images: Vec<Artwork>, session: &'static CachedSession) -> QueryResult<Vec<Artwork>> {
+        if images.is_empty() {
+            return Ok(images)
+        }
+        let statements = images
+            .iter()
+            .map(|image| Ok((INSERT_IMAGE, bind(image.clone())?)))
+            .collect::<QueryResult<Vec<_>>>()?;
+        write_logged_batch(statements, session).await?;
+        Ok(images)
+    }
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.artwork"), err)]
+    async fn delete_image(owner: ImageOwner, owner_id: i64, image_id: i64, session: &'static CachedSession) -> QueryResult<bool> {
+        let applied = session.query_prepared(DELETE_IMAGE, (owner.to_string(), owner_id, image_id))
+            .await
+            .map(is_applied)
+            .map_err(|_| ServiceError::DatabaseError)?;
+        match applied {
+            true => Ok(true),
+            false => Err(ServiceError::NotFound),
+        }
+    }
+}
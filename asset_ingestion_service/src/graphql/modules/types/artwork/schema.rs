@@ -0,0 +1,256 @@
+use std::marker::PhantomData;
+use async_graphql::*;
+use common_utils::{QueryResult, artwork::{ImageKind, ImageOwner, ImageSize, sized_url, variant_url}, error::ServiceError};
+use serde::{Deserialize, Serialize};
+use crate::{graphql::config::get_store_from_ctx, to_bigint, to_int};
+use super::super::movies::{model::{Movie, MoviePatch}, resolver::{MovieDatabase, MovieResolver}};
+use super::super::people_module::{model::Person, resolver::{PersonDatabase, PersonResolver}};
+use super::super::tmdb_test::{fetch_movie_images, fetch_person_images};
+use super::model::{Artwork, merge_imported, place, primary_poster, promote, storage_prefix};
+use super::storage::SharedImageStore;
+use super::resolver::{ArtworkDatabase, ArtworkResolver};
+
+/// Images are read through `A`, the movies and people they belong to through `M` and `P`
+#[derive(Default)]
+pub struct ArtworkQuery<A = ArtworkDatabase, M = MovieDatabase, P = PersonDatabase>(PhantomData<(A, M, P)>);
+
+/// Images are written through `A`. A movie's primary poster is copied onto the movie through `M`
+#[derive(Default)]
+pub struct ArtworkMutation<A = ArtworkDatabase, M = MovieDatabase, P = PersonDatabase>(PhantomData<(A, M, P)>);
+
+#[derive(SimpleObject, Debug, Clone, Deserialize, Serialize)]
+pub struct ArtworkType {
+    pub image_id: ID,
+    pub owner: ImageOwner,
+    pub owner_id: ID,
+    pub kind: ImageKind,
+    pub file_path: String,
+    /// Full size URL of the image
+    pub url: String,
+    /// Placeholder to show while the image loads, only uploaded images have one
+    pub blurhash: Option<String>,
+    pub language: Option<String>,
+    pub width: i32,
+    pub height: i32,
+    pub aspect_ratio: f32,
+    pub is_primary: bool,
+}
+
+impl From<&Artwork> for ArtworkType {
+    fn from(f: &Artwork) -> Self {
+        Self {
+            image_id: f.image_id.into(),
+            owner: f.owner(),
+            owner_id: f.owner_id.into(),
+            kind: f.kind(),
+            file_path: f.file_path.clone(),
+            url: f.variants
+                .as_ref()
+                .and_then(|variants| variant_url(variants, f.kind(), ImageSize::Original, None))
+                .unwrap_or_else(|| sized_url(&f.file_path, f.kind(), ImageSize::Original)),
+            blurhash: f.blurhash.clone(),
+            language: f.language.clone(),
+            width: f.width,
+            height: f.height,
+            aspect_ratio: f.aspect_ratio,
+            is_primary: f.is_primary,
+        }
+    }
+}
+
+#[derive(InputObject, Debug, Clone, Deserialize, Serialize)]
+pub struct ArtworkInput {
+    /// `PROFILE` for a person, any other kind for a movie
+    pub kind: ImageKind,
+    /// TMDB path or absolute URL
+    pub file_path: String,
+    /// Language of the text on the image, leave it out for images without any
+    pub language: Option<String>,
+    pub width: i32,
+    pub height: i32,
+    /// The owner's first image of a kind is always made primary
+    #[graphql(default)]
+    pub is_primary: bool,
+}
+
+/// Omitted fields keep their current value
+#[derive(InputObject, Debug, Clone, Deserialize, Serialize, Default)]
+pub struct ArtworkPatchInput {
+    pub file_path: Option<String>,
+    pub language: Option<String>,
+    /// Set to drop the language of an image without any text on it
+    #[graphql(default)]
+    pub clear_language: bool,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+}
+
+/// Not found when the movie or person the images belong to doesn't exist
+pub async fn ensure_owner<M: MovieResolver, P: PersonResolver>(owner: ImageOwner, owner_id: i64, movies: &'static M::Store, people: &'static P::Store) -> QueryResult<()> {
+    match owner {
+        ImageOwner::Movie => Movie::get_movie_id::<M>(owner_id, movies).await.map(|_| ()),
+        ImageOwner::Person => Person::get_person_by_id::<P>(people, owner_id as i32).await.map(|_| ()),
+    }
+}
+
+async fn find_image<A: ArtworkResolver>(ctx: &Context<'_>, owner: ImageOwner, owner_id: i64, image_id: i64) -> QueryResult<(Artwork, Vec<Artwork>)> {
+    let images = Artwork::get_images::<A>(owner, owner_id, get_store_from_ctx(ctx)).await?;
+    let image = images.iter().find(|f| f.image_id == image_id).cloned().ok_or(ServiceError::NotFound)?;
+    Ok((image, images))
+}
+
+/// Copies a movie's primary poster onto its `poster` column, the patch republishes the movie
+async fn sync_poster<A: ArtworkResolver, M: MovieResolver>(owner: ImageOwner, owner_id: i64, images: &'static A::Store, movies: &'static M::Store) -> QueryResult<()> {
+    if owner != ImageOwner::Movie {
+        return Ok(())
+    }
+    let images = Artwork::get_images::<A>(owner, owner_id, images).await?;
+    let movie = Movie::get_movie_id::<M>(owner_id, movies).await?;
+    match primary_poster(&images) {
+        Some(poster) if poster != movie.poster => {
+            let patch = MoviePatch { poster: Some(poster.to_string()), ..MoviePatch::default() };
+            Movie::patch_movie::<M>(owner_id, patch, movies).await.map(|_| ())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Adds `image` to its owner's artwork, as the primary of its kind when `is_primary` is set or it
+/// is the first one, and keeps the movie's poster in step
+pub async fn attach<A: ArtworkResolver, M: MovieResolver>(image: Artwork, is_primary: bool, images: &'static A::Store, movies: &'static M::Store) -> QueryResult<Artwork> {
+    let (owner, owner_id) = (image.owner(), image.owner_id);
+    let current = Artwork::get_images::<A>(owner, owner_id, images).await?;
+    let mut res = Artwork::save_images::<A>(place(image, is_primary, &current), images).await?;
+    sync_poster::<A, M>(owner, owner_id, images, movies).await?;
+    Ok(res.remove(0))
+}
+
+#[Object]
+impl<A: ArtworkResolver, M: MovieResolver, P: PersonResolver> ArtworkQuery<A, M, P> {
+    /// Every image of the movie or person, primaries included
+    #[tracing::instrument(skip(self, ctx))]
+    async fn images(&self, ctx: &Context<'_>, owner: ImageOwner, owner_id: ID) -> FieldResult<Vec<ArtworkType>> {
+        let owner_id = to_bigint(owner_id);
+        ensure_owner::<M, P>(owner, owner_id, get_store_from_ctx(ctx), get_store_from_ctx(ctx)).await.map_err(|e| e.extend())?;
+        let images = Artwork::get_images::<A>(owner, owner_id, get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        Ok(images.iter().map(ArtworkType::from).collect())
+    }
+}
+
+#[Object]
+impl<A: ArtworkResolver, M: MovieResolver, P: PersonResolver> ArtworkMutation<A, M, P> {
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "addImage")]
+    async fn add_image(&self, ctx: &Context<'_>, owner: ImageOwner, owner_id: ID, image: ArtworkInput) -> FieldResult<ArtworkType> {
+        let owner_id = to_bigint(owner_id);
+        ensure_owner::<M, P>(owner, owner_id, get_store_from_ctx(ctx), get_store_from_ctx(ctx)).await.map_err(|e| e.extend())?;
+        let ArtworkInput { kind, file_path, language, width, height, is_primary } = image;
+        let image = Artwork::new(owner, owner_id, kind, &file_path, language.as_deref(), width, height).map_err(|e| e.extend())?;
+        let res = attach::<A, M>(image, is_primary, get_store_from_ctx(ctx), get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        Ok(ArtworkType::from(&res))
+    }
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "updateImage")]
+    async fn update_image(&self, ctx: &Context<'_>, owner: ImageOwner, owner_id: ID, image_id: ID, patch: ArtworkPatchInput) -> FieldResult<ArtworkType> {
+        let owner_id = to_bigint(owner_id);
+        let (mut image, _) = find_image::<A>(ctx, owner, owner_id, to_bigint(image_id)).await.map_err(|e| e.extend())?;
+        let ArtworkPatchInput { file_path, language, clear_language, width, height } = patch;
+        image.resize(file_path, width, height).map_err(|e| e.extend())?;
+        if clear_language || language.is_some() {
+            image.set_language(language.as_deref()).map_err(|e| e.extend())?;
+        }
+        let res = Artwork::save_images::<A>(vec![image], get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        sync_poster::<A, M>(owner, owner_id, get_store_from_ctx(ctx), get_store_from_ctx(ctx)).await.map_err(|e| e.extend())?;
+        Ok(ArtworkType::from(&res[0]))
+    }
+    /// The image becomes the one served for its kind, the previous primary is kept as an alternative
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "setPrimaryImage")]
+    async fn set_primary_image(&self, ctx: &Context<'_>, owner: ImageOwner, owner_id: ID, image_id: ID) -> FieldResult<ArtworkType> {
+        let owner_id = to_bigint(owner_id);
+        let (image, images) = find_image::<A>(ctx, owner, owner_id, to_bigint(image_id)).await.map_err(|e| e.extend())?;
+        let res = Artwork::save_images::<A>(promote(image, &images), get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        sync_poster::<A, M>(owner, owner_id, get_store_from_ctx(ctx), get_store_from_ctx(ctx)).await.map_err(|e| e.extend())?;
+        Ok(ArtworkType::from(&res[0]))
+    }
+    /// Removing a primary image promotes the next image of the same kind. The renditions of an
+    /// uploaded image are deleted with it
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "removeImage")]
+    async fn remove_image(&self, ctx: &Context<'_>, owner: ImageOwner, owner_id: ID, image_id: ID) -> FieldResult<bool> {
+        let owner_id = to_bigint(owner_id);
+        let (image, images) = find_image::<A>(ctx, owner, owner_id, to_bigint(image_id)).await.map_err(|e| e.extend())?;
+        let res = Artwork::delete_image::<A>(owner, owner_id, image.image_id, get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        if image.variants.is_some() {
+            ctx.data::<SharedImageStore>()?
+                .delete_prefix(&storage_prefix(owner, owner_id, image.image_id))
+                .await
+                .map_err(|e| e.extend())?;
+        }
+        let next = images.iter().find(|f| f.image_id != image.image_id && f.kind() == image.kind());
+        if let (true, Some(next)) = (image.is_primary, next) {
+            Artwork::save_images::<A>(vec![Artwork { is_primary: true, ..next.clone() }], get_store_from_ctx(ctx))
+                .await
+                .map_err(|e| e.extend())?;
+        }
+        sync_poster::<A, M>(owner, owner_id, get_store_from_ctx(ctx), get_store_from_ctx(ctx)).await.map_err(|e| e.extend())?;
+        Ok(res)
+    }
+    /// Copies the posters, backdrops and logos TMDB has of the movie and returns the new ones.
+    /// `tmdbId` defaults to `movieId`, images already imported are skipped
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "importMovieImages")]
+    async fn import_movie_images(&self, ctx: &Context<'_>, movie_id: ID, tmdb_id: Option<ID>) -> FieldResult<Vec<ArtworkType>> {
+        let movie_id = to_bigint(movie_id);
+        Movie::get_movie_id::<M>(movie_id, get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        let tmdb_id = tmdb_id.map(to_bigint).unwrap_or(movie_id);
+        let res = import_images::<A>(ImageOwner::Movie, movie_id, fetch_movie_images(movie_id, tmdb_id).await.map_err(|e| e.extend())?, get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        sync_poster::<A, M>(ImageOwner::Movie, movie_id, get_store_from_ctx(ctx), get_store_from_ctx(ctx)).await.map_err(|e| e.extend())?;
+        Ok(res.iter().map(ArtworkType::from).collect())
+    }
+    /// Copies the profile pictures TMDB has of the person and returns the new ones. `tmdbId`
+    /// defaults to the person's `tmdb:` external id
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "importPersonImages")]
+    async fn import_person_images(&self, ctx: &Context<'_>, person_id: ID, tmdb_id: Option<ID>) -> FieldResult<Vec<ArtworkType>> {
+        let person_id = to_int(person_id);
+        Person::get_person_by_id::<P>(get_store_from_ctx(ctx), person_id)
+            .await
+            .map_err(|e| e.extend())?;
+        let tmdb_id = match tmdb_id {
+            Some(tmdb_id) => to_bigint(tmdb_id),
+            None => Person::get_external_ids::<P>(get_store_from_ctx(ctx))
+                .await
+                .map_err(|e| e.extend())?
+                .into_iter()
+                .filter(|f| f.person_id == person_id)
+                .find_map(|f| f.external_id.strip_prefix("tmdb:").and_then(|id| id.parse().ok()))
+                .ok_or_else(|| ServiceError::BadRequest(format!("Person {} has no tmdb external id, pass `tmdbId`", person_id)).extend())?,
+        };
+        let images = fetch_person_images(person_id.into(), tmdb_id).await.map_err(|e| e.extend())?;
+        let res = import_images::<A>(ImageOwner::Person, person_id.into(), images, get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        Ok(res.iter().map(ArtworkType::from).collect())
+    }
+}
+
+/// Writes the images of `imported` the owner doesn't have yet
+pub async fn import_images<A: ArtworkResolver>(owner: ImageOwner, owner_id: i64, imported: Vec<Artwork>, session: &'static A::Store) -> QueryResult<Vec<Artwork>> {
+    let current = Artwork::get_images::<A>(owner, owner_id, session).await?;
+    Artwork::save_images::<A>(merge_imported(&current, imported), session).await
+}
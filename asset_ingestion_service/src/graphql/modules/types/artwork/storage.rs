@@ -0,0 +1,69 @@
+use std::{env::var, path::{Component, Path, PathBuf}, sync::Arc};
+use async_trait::async_trait;
+use common_utils::{QueryResult, error::ServiceError};
+
+/// Where the renditions of uploaded images, and the WebVTT of uploaded subtitles, are kept.
+/// `LocalImageStore` writes them to disk, another backend such as a bucket only has to implement this
+#[async_trait]
+pub trait ImageStore: Send + Sync + 'static {
+    /// Stores `bytes` under `key`, replacing what was there, and returns the URL it is served from
+    async fn put(&self, key: &str, content_type: &str, bytes: Vec<u8>) -> QueryResult<String>;
+    /// Removes every object stored under `prefix/`, nothing to remove is not an error
+    async fn delete_prefix(&self, prefix: &str) -> QueryResult<()>;
+}
+
+/// Kept in the app data, shared by every worker
+pub type SharedImageStore = Arc<dyn ImageStore>;
+
+/// Renditions written under `root` and served by this service at `public_url`
+pub struct LocalImageStore {
+    pub root: PathBuf,
+    pub public_url: String,
+}
+
+impl LocalImageStore {
+    /// `IMAGE_STORAGE_DIR` defaults to `./images`, `IMAGE_STORAGE_URL` to the `/images` route of this service
+    pub fn shared() -> SharedImageStore {
+        let port = var("PORT").unwrap_or_else(|_| "4003".to_string());
+        Arc::new(LocalImageStore {
+            root: Self::root(),
+            public_url: var("IMAGE_STORAGE_URL").unwrap_or_else(|_| format!("http://localhost:{}/images", port)),
+        })
+    }
+    /// Directory the renditions are written to and the `/images` route serves
+    pub fn root() -> PathBuf {
+        var("IMAGE_STORAGE_DIR").unwrap_or_else(|_| "./images".to_string()).into()
+    }
+    /// Keys are relative paths without `..`, so nothing is written outside of `root`
+    fn path(&self, key: &str) -> QueryResult<PathBuf> {
+        let key = Path::new(key);
+        match key.components().all(|part| matches!(part, Component::Normal(_))) {
+            true => Ok(self.root.join(key)),
+            false => Err(ServiceError::BadRequest(format!("Invalid storage key {}", key.display()))),
+        }
+    }
+}
+
+#[async_trait]
+impl ImageStore for LocalImageStore {
+    #[tracing::instrument(skip(self, bytes), err)]
+    async fn put(&self, key: &str, content_type: &str, bytes: Vec<u8>) -> QueryResult<String> {
+        let path = self.path(key)?;
+        if let Some(dir) = path.parent() {
+            tokio::fs::create_dir_all(dir)
+                .await
+                .map_err(|e| ServiceError::ServerError(e.to_string()))?;
+        }
+        tokio::fs::write(&path, bytes)
+            .await
+            .map_err(|e| ServiceError::ServerError(e.to_string()))?;
+        Ok(format!("{}/{}", self.public_url.trim_end_matches('/'), key))
+    }
+    #[tracing::instrument(skip(self), err)]
+    async fn delete_prefix(&self, prefix: &str) -> QueryResult<()> {
+        match tokio::fs::remove_dir_all(self.path(prefix)?).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(ServiceError::ServerError(e.to_string())),
+            _ => Ok(()),
+        }
+    }
+}
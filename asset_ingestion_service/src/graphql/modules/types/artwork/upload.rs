@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+use actix_files::Files;
+use actix_web::{http::header::CONTENT_TYPE, web, HttpRequest, HttpResponse};
+use common_utils::{QueryResult, artwork::{ImageKind, ImageOwner, ImageSize, variant_key}, error::ServiceError};
+use lazy_static::lazy_static;
+use serde::Deserialize;
+use crate::{db::session, generate_unique_id};
+use super::super::{movies::resolver::MovieDatabase, people_module::resolver::PersonDatabase};
+use super::model::{Artwork, storage_prefix};
+use super::processing::{process, ProcessedImage};
+use super::resolver::ArtworkDatabase;
+use super::schema::{attach, ensure_owner, ArtworkType};
+use super::storage::{LocalImageStore, SharedImageStore};
+
+lazy_static! {
+    /// Largest upload accepted, in bytes
+    pub static ref IMAGE_MAX_BYTES: usize = std::env::var("IMAGE_MAX_BYTES")
+        .ok()
+        .and_then(|bytes| bytes.parse::<usize>().ok())
+        .unwrap_or(20 * 1024 * 1024);
+}
+
+/// Image uploads, and the renditions kept by `LocalImageStore` served under `/images`
+pub fn configure_uploads(cfg: &mut web::ServiceConfig) {
+    cfg
+    .service(
+        web::resource("/artwork/{owner}/{owner_id}")
+            .app_data(web::PayloadConfig::new(*IMAGE_MAX_BYTES))
+            .route(web::post().to(upload_image))
+    )
+    .service(Files::new("/images", LocalImageStore::root()));
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UploadParams {
+    pub kind: ImageKind,
+    /// Language of the text on the image, leave it out for images without any
+    pub language: Option<String>,
+    /// The owner's first image of a kind is always made primary
+    #[serde(default)]
+    pub primary: bool,
+}
+
+/// Stores every rendition of `processed` under the image's prefix and returns their URLs by key.
+/// What was stored is removed again when one of them fails
+async fn store_renditions(store: &SharedImageStore, prefix: &str, processed: ProcessedImage) -> QueryResult<HashMap<String, String>> {
+    let mut variants = HashMap::new();
+    for rendition in processed.renditions {
+        let stored = store
+            .put(&format!("{}/{}", prefix, rendition.key), rendition.format.content_type(), rendition.bytes)
+            .await;
+        match stored {
+            Ok(url) => variants.insert(rendition.key, url),
+            Err(e) => {
+                store.delete_prefix(prefix).await.ok();
+                return Err(e)
+            }
+        };
+    }
+    Ok(variants)
+}
+
+/// `POST /artwork/{owner}/{owner_id}?kind=POSTER&language=en&primary=true` with a JPEG, PNG or WebP
+/// image as the body. Every size is rendered, stored and attached to the owner's artwork, which
+/// is answered with `201 Created` and the new image
+pub async fn upload_image(
+    path: web::Path<(String, i64)>,
+    params: web::Query<UploadParams>,
+    http: HttpRequest,
+    body: web::Bytes,
+    store: web::Data<SharedImageStore>,
+) -> Result<HttpResponse, ServiceError> {
+    let (owner, owner_id) = path.into_inner();
+    let owner = owner
+        .to_uppercase()
+        .parse::<ImageOwner>()
+        .map_err(|_| ServiceError::BadRequest(format!("Unknown image owner {}", owner)))?;
+    let UploadParams { kind, language, primary } = params.into_inner();
+    Artwork::check_kind(owner, kind)?;
+    ensure_owner::<MovieDatabase, PersonDatabase>(owner, owner_id, session(), session()).await?;
+    let content_type = http
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    let processed = web::block(move || process(kind, &content_type, &body))
+        .await
+        .map_err(|e| ServiceError::ServerError(e.to_string()))??;
+    let (format, width, height, blurhash) = (processed.format, processed.width as i32, processed.height as i32, processed.blurhash.clone());
+    let image_id = generate_unique_id();
+    let prefix = storage_prefix(owner, owner_id, image_id);
+    let variants = store_renditions(&store, &prefix, processed).await?;
+    let original = variants
+        .get(&variant_key(kind.size_segment(ImageSize::Original), format))
+        .cloned()
+        .ok_or(ServiceError::UnexpectedError)?;
+    let image = Artwork::new(owner, owner_id, kind, &original, language.as_deref(), width, height)
+        .map(|image| Artwork { image_id, blurhash: Some(blurhash), variants: Some(variants), ..image });
+    let attached = match image {
+        Ok(image) => attach::<ArtworkDatabase, MovieDatabase>(image, primary, session(), session()).await,
+        Err(e) => Err(e),
+    };
+    match attached {
+        Ok(image) => Ok(HttpResponse::Created().json(ArtworkType::from(&image))),
+        Err(e) => {
+            store.delete_prefix(&prefix).await.ok();
+            Err(e)
+        }
+    }
+}
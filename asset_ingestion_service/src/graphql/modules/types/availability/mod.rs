@@ -0,0 +1,4 @@
+pub mod model;
+pub mod resolver;
+pub mod schema;
+pub mod scheduler;
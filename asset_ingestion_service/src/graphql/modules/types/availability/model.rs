@@ -0,0 +1,88 @@
+use chrono::{TimeZone, Utc};
+use common_utils::{QueryResult, availability::{Offering, normalise_territory}, error::ServiceError, events::PartitionKey};
+use scylla::macros::{FromRow, ValueList};
+use serde::{Deserialize, Serialize};
+use crate::generate_unique_id;
+use super::resolver::AvailabilityResolver;
+
+const DAY_MILLIS: i64 = 24 * 60 * 60 * 1000;
+
+// Columns after the primary key are in alphabetical order, the order `SELECT *` returns them in
+/// A territory where, and a period when, a movie can be watched with one offering
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize, ValueList, PartialEq)]
+pub struct AvailabilityWindow {
+    pub movie_id: i64,
+    pub window_id: i64,
+    /// Milliseconds since the epoch, `None` never closes
+    pub ends_at: Option<i64>,
+    pub offering: String,
+    /// `None` has always been open
+    pub starts_at: Option<i64>,
+    pub territory: String,
+}
+
+impl PartitionKey for AvailabilityWindow {
+    fn partition_key(&self) -> String {
+        self.movie_id.to_string()
+    }
+}
+
+impl AvailabilityWindow {
+    pub fn new(movie_id: i64, territory: &str, offering: Offering, starts_at: Option<i64>, ends_at: Option<i64>) -> QueryResult<Self> {
+        if let (Some(starts_at), Some(ends_at)) = (starts_at, ends_at) {
+            if ends_at <= starts_at {
+                return Err(ServiceError::BadRequest("A window has to end after it starts".to_string()))
+            }
+        }
+        Ok(Self {
+            movie_id,
+            window_id: generate_unique_id(),
+            ends_at,
+            offering: offering.to_string(),
+            starts_at,
+            territory: normalise_territory(territory)?,
+        })
+    }
+    /// A row with an unknown offering is read as a subscription, the most common one
+    pub fn offering(&self) -> Offering {
+        self.offering.parse().unwrap_or(Offering::Subscription)
+    }
+    /// The times the window opens and closes, the ones the scheduler has to republish it at
+    pub fn boundaries(&self) -> Vec<i64> {
+        self.starts_at.into_iter().chain(self.ends_at).collect()
+    }
+}
+
+/// Partition of `availability_boundaries` a boundary is stored in, its UTC day
+pub fn boundary_day(at: i64) -> String {
+    Utc.timestamp_millis(at).format("%Y-%m-%d").to_string()
+}
+
+/// Every day partition a sweep of `(after, until]` has to read
+pub fn boundary_days(after: i64, until: i64) -> Vec<String> {
+    let mut days = vec![boundary_day(after)];
+    let mut at = after;
+    while days.last() != Some(&boundary_day(until)) {
+        at = (at + DAY_MILLIS).min(until);
+        days.push(boundary_day(at));
+    }
+    days
+}
+
+impl AvailabilityWindow {
+    pub async fn get_windows<AvailabilityDatabase: AvailabilityResolver>(movie_id: i64, session: &'static AvailabilityDatabase::Store) -> QueryResult<Vec<AvailabilityWindow>> {
+        AvailabilityDatabase::get_windows(movie_id, session).await
+    }
+    pub async fn add_window<AvailabilityDatabase: AvailabilityResolver>(window: AvailabilityWindow, session: &'static AvailabilityDatabase::Store) -> QueryResult<AvailabilityWindow> {
+        AvailabilityDatabase::add_window(window, session).await
+    }
+    pub async fn delete_window<AvailabilityDatabase: AvailabilityResolver>(movie_id: i64, window_id: i64, session: &'static AvailabilityDatabase::Store) -> QueryResult<bool> {
+        AvailabilityDatabase::delete_window(movie_id, window_id, session).await
+    }
+    pub async fn get_boundaries<AvailabilityDatabase: AvailabilityResolver>(day: String, after: i64, until: i64, session: &'static AvailabilityDatabase::Store) -> QueryResult<Vec<i64>> {
+        AvailabilityDatabase::get_boundaries(day, after, until, session).await
+    }
+    pub async fn publish_windows<AvailabilityDatabase: AvailabilityResolver>(movie_id: i64, windows: Vec<AvailabilityWindow>, session: &'static AvailabilityDatabase::Store) -> QueryResult<()> {
+        AvailabilityDatabase::publish_windows(movie_id, windows, session).await
+    }
+}
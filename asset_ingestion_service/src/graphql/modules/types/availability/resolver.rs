@@ -0,0 +1,84 @@
+use async_trait::async_trait;
+use common_utils::{QueryResult, error::ServiceError, events::AvailabilityEvent};
+use scylla::IntoTypedRows;
+use crate::db::{CachedSession, bind, is_applied, write_logged_batch};
+use super::super::outbox::resolver::write_events;
+use super::model::{AvailabilityWindow, boundary_day};
+
+/// Windows are stored by movie, and the times they open and close by day for the scheduler
+#[async_trait]
+pub trait AvailabilityResolver: Send + Sync + 'static {
+    type Store: Send + Sync + 'static;
+    async fn get_windows(movie_id: i64, session: &'static Self::Store) -> QueryResult<Vec<AvailabilityWindow>>;
+    /// Writes the window together with its boundaries
+    async fn add_window(window: AvailabilityWindow, session: &'static Self::Store) -> QueryResult<AvailabilityWindow>;
+    /// The window's boundaries are left behind, republishing a movie at one of them is harmless
+    async fn delete_window(movie_id: i64, window_id: i64, session: &'static Self::Store) -> QueryResult<bool>;
+    /// Movies with a window opening or closing on `day` in `(after, until]`, possibly repeated
+    async fn get_boundaries(day: String, after: i64, until: i64, session: &'static Self::Store) -> QueryResult<Vec<i64>>;
+    /// Writes the `AvailabilityChanged` event to the event outbox, the relay publishes it
+    async fn publish_windows(movie_id: i64, windows: Vec<AvailabilityWindow>, session: &'static Self::Store) -> QueryResult<()>;
+}
+
+#[derive(Default)]
+pub struct AvailabilityDatabase;
+
+static GET_WINDOWS: &str = "SELECT * FROM movie_keyspace.movie_availability WHERE movie_id = ?;";
+static INSERT_WINDOW: &str = "
+    INSERT INTO movie_keyspace.movie_availability (
+        movie_id, window_id, ends_at, offering, starts_at, territory
+    ) VALUES (?, ?, ?, ?, ?, ?);
+";
+static INSERT_BOUNDARY: &str = "INSERT INTO movie_keyspace.availability_boundaries (day, at, movie_id) VALUES (?, ?, ?);";
+static DELETE_WINDOW: &str = "DELETE FROM movie_keyspace.movie_availability WHERE movie_id = ? AND window_id = ? IF EXISTS;";
+static GET_BOUNDARIES: &str = "SELECT movie_id FROM movie_keyspace.availability_boundaries WHERE day = ? AND at > ? AND at <= ?;";
+
+#[async_trait]
+impl AvailabilityResolver for AvailabilityDatabase {
+    type Store = CachedSession;
+
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.movie_availability"), err)]
+    async fn get_windows(movie_id: i64, session: &'static CachedSession) -> QueryResult<Vec<AvailabilityWindow>> {
+        session.query_prepared(GET_WINDOWS, (movie_id,))
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
+            .into_typed::<AvailabilityWindow>()
+            .map(|row| row.map_err(|e| ServiceError::ServerError(e.to_string())))
+            .collect()
+    }
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.movie_availability"), err)]
+    async fn add_window(window: AvailabilityWindow, session: &'static CachedSession) -> QueryResult<AvailabilityWindow> {
+        let mut statements = vec![(INSERT_WINDOW, bind(window.clone())?)];
+        for at in window.boundaries() {
+            statements.push((INSERT_BOUNDARY, bind((boundary_day(at), at, window.movie_id))?));
+        }
+        write_logged_batch(statements, session).await?;
+        Ok(window)
+    }
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.movie_availability"), err)]
+    async fn delete_window(movie_id: i64, window_id: i64, session: &'static CachedSession) -> QueryResult<bool> {
+        let applied = session.query_prepared(DELETE_WINDOW, (movie_id, window_id))
+            .await
+            .map(is_applied)
+            .map_err(|_| ServiceError::DatabaseError)?;
+        match applied {
+            true => Ok(true),
+            false => Err(ServiceError::NotFound),
+        }
+    }
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.availability_boundaries"), err)]
+    async fn get_boundaries(day: String, after: i64, until: i64, session: &'static CachedSession) -> QueryResult<Vec<i64>> {
+        session.query_prepared(GET_BOUNDARIES, (day, after, until))
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
+            .into_typed::<(i64,)>()
+            .map(|row| row.map(|(movie_id,)| movie_id).map_err(|e| ServiceError::ServerError(e.to_string())))
+            .collect()
+    }
+    #[tracing::instrument(skip(session, windows), fields(repository = "movie_keyspace.event_outbox"), err)]
+    async fn publish_windows(movie_id: i64, windows: Vec<AvailabilityWindow>, session: &'static CachedSession) -> QueryResult<()> {
+        write_events(vec![AvailabilityEvent::AvailabilityChanged { movie_id, windows }], session).await
+    }
+}
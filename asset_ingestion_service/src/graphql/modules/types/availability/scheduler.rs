@@ -0,0 +1,62 @@
+use std::time::Duration;
+use common_utils::{QueryResult, availability::now_millis};
+use super::super::movies::import::describe;
+use super::{model::{AvailabilityWindow, boundary_days}, resolver::AvailabilityResolver};
+
+/// How often the scheduler looks for windows that have opened or closed
+const SCHEDULER_TICK: Duration = Duration::from_secs(60);
+/// How far back the first sweep reaches, boundaries passed while no scheduler was running are caught up on
+const SCHEDULER_LOOKBACK_MILLIS: i64 = 24 * 60 * 60 * 1000;
+
+/// Publishes every window the movie has left. The event carries no verdict, each consumer
+/// decides what is open when it reads it
+pub async fn publish<A: AvailabilityResolver>(movie_id: i64, session: &'static A::Store) -> QueryResult<Vec<AvailabilityWindow>> {
+    let windows = AvailabilityWindow::get_windows::<A>(movie_id, session).await?;
+    AvailabilityWindow::publish_windows::<A>(movie_id, windows.clone(), session).await?;
+    Ok(windows)
+}
+
+/// Republishes every movie with a window that opened or closed in `(after, until]`, returns how many.
+/// A movie that fails doesn't hold up the others, the first error is returned once every movie has
+/// been tried so the sweep is retried
+pub async fn publish_boundaries<A: AvailabilityResolver>(after: i64, until: i64, session: &'static A::Store) -> QueryResult<usize> {
+    let mut movie_ids = Vec::new();
+    for day in boundary_days(after, until) {
+        movie_ids.extend(AvailabilityWindow::get_boundaries::<A>(day, after, until, session).await?);
+    }
+    movie_ids.sort_unstable();
+    movie_ids.dedup();
+    let mut failure = None;
+    for movie_id in &movie_ids {
+        if let Err(e) = publish::<A>(*movie_id, session).await {
+            log::error!("Unable to republish the availability of movie {}: {}", movie_id, describe(&e));
+            failure.get_or_insert(e);
+        }
+    }
+    match failure {
+        Some(e) => Err(e),
+        None => Ok(movie_ids.len()),
+    }
+}
+
+/// Sweeps for passed boundaries every minute. A sweep that fails is logged and retried from the
+/// same point on the next tick, so a boundary is published late rather than never
+pub fn spawn_scheduler<A: AvailabilityResolver>(session: &'static A::Store) {
+    tokio::spawn(async move {
+        let mut after = now_millis() - SCHEDULER_LOOKBACK_MILLIS;
+        let mut interval = tokio::time::interval(SCHEDULER_TICK);
+        loop {
+            interval.tick().await;
+            let until = now_millis();
+            match publish_boundaries::<A>(after, until, session).await {
+                Ok(published) => {
+                    if published > 0 {
+                        log::info!("🗓️ Republished the availability of {} movies", published);
+                    }
+                    after = until;
+                }
+                Err(e) => log::error!("Unable to publish availability boundaries, retrying on the next tick: {}", describe(&e)),
+            }
+        }
+    });
+}
@@ -0,0 +1,84 @@
+use std::marker::PhantomData;
+use async_graphql::*;
+use chrono::{DateTime, TimeZone, Utc};
+use common_utils::availability::Offering;
+use serde::{Deserialize, Serialize};
+use crate::{graphql::config::get_store_from_ctx, to_bigint};
+use super::super::movies::{model::Movie, resolver::{MovieDatabase, MovieResolver}};
+use super::model::AvailabilityWindow;
+use super::resolver::{AvailabilityDatabase, AvailabilityResolver};
+use super::scheduler::publish;
+
+/// Windows are written through `A`, the movie they belong to is looked up through `M`
+#[derive(Default)]
+pub struct AvailabilityMutation<A = AvailabilityDatabase, M = MovieDatabase>(PhantomData<(A, M)>);
+
+#[derive(SimpleObject, Debug, Clone, Deserialize, Serialize)]
+pub struct AvailabilityWindowType {
+    pub window_id: ID,
+    pub movie_id: ID,
+    pub territory: String,
+    pub offering: Offering,
+    pub starts_at: Option<DateTime<Utc>>,
+    pub ends_at: Option<DateTime<Utc>>,
+}
+
+impl From<&AvailabilityWindow> for AvailabilityWindowType {
+    fn from(f: &AvailabilityWindow) -> Self {
+        Self {
+            window_id: f.window_id.into(),
+            movie_id: f.movie_id.into(),
+            territory: f.territory.clone(),
+            offering: f.offering(),
+            starts_at: f.starts_at.map(|at| Utc.timestamp_millis(at)),
+            ends_at: f.ends_at.map(|at| Utc.timestamp_millis(at)),
+        }
+    }
+}
+
+/// Leave `startsAt` out for a window that is already open, `endsAt` for one that never closes
+#[derive(InputObject, Debug, Clone, Deserialize, Serialize)]
+pub struct AvailabilityWindowInput {
+    /// ISO 3166-1 alpha-2 code, e.g. `GB`, or `WW` for every territory
+    pub territory: String,
+    pub offering: Offering,
+    pub starts_at: Option<DateTime<Utc>>,
+    pub ends_at: Option<DateTime<Utc>>,
+}
+
+#[Object]
+impl<A: AvailabilityResolver, M: MovieResolver> AvailabilityMutation<A, M> {
+    /// The movie's first window restricts it to the territories its windows cover
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "addAvailabilityWindow")]
+    async fn add_availability_window(&self, ctx: &Context<'_>, movie_id: ID, window: AvailabilityWindowInput) -> FieldResult<AvailabilityWindowType> {
+        let movie_id = to_bigint(movie_id);
+        Movie::get_movie_id::<M>(movie_id, get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        let AvailabilityWindowInput { territory, offering, starts_at, ends_at } = window;
+        let window = AvailabilityWindow::new(
+            movie_id,
+            &territory,
+            offering,
+            starts_at.map(|at| at.timestamp_millis()),
+            ends_at.map(|at| at.timestamp_millis()),
+        ).map_err(|e| e.extend())?;
+        let res = AvailabilityWindow::add_window::<A>(window, get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        publish::<A>(movie_id, get_store_from_ctx(ctx)).await.map_err(|e| e.extend())?;
+        Ok(AvailabilityWindowType::from(&res))
+    }
+    /// Removing a movie's last window makes it available everywhere again
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "removeAvailabilityWindow")]
+    async fn remove_availability_window(&self, ctx: &Context<'_>, movie_id: ID, window_id: ID) -> FieldResult<bool> {
+        let movie_id = to_bigint(movie_id);
+        let res = AvailabilityWindow::delete_window::<A>(movie_id, to_bigint(window_id), get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        publish::<A>(movie_id, get_store_from_ctx(ctx)).await.map_err(|e| e.extend())?;
+        Ok(res)
+    }
+}
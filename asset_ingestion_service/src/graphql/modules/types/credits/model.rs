@@ -0,0 +1,62 @@
+use common_utils::QueryResult;
+use scylla::macros::{FromRow, ValueList};
+use serde::{Deserialize, Serialize};
+use crate::{generate_unique_id, to_int};
+use super::resolver::CreditResolver;
+use super::schema::CreditInput;
+
+/// Department of every cast credit, crew credits carry TMDB's department
+pub const ACTING: &str = "Acting";
+
+// Columns after the primary key of `credits_by_movie` are in alphabetical order, the order `SELECT *` returns them in
+/// One person credited on one movie. `credits_by_movie` and `credits_by_person` hold the
+/// same columns, only their primary keys differ
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize, ValueList)]
+pub struct Credit { 
+    pub movie_id: i64,
+    pub credit_id: String,
+    /// Billing order of the cast, `None` for the crew
+    pub billing_order: Option<i32>,
+    pub character_name: Option<String>,
+    pub department: String,
+    pub job: String,
+    /// Name as credited, which may differ from the person's current name
+    pub name: String,
+    pub person_id: i32,
+}
+
+impl Credit { 
+    pub fn is_cast(&self) -> bool { 
+        self.department == ACTING
+    }
+    pub fn from_input(movie_id: i64, f: &CreditInput) -> Self { 
+        Self { 
+            movie_id,
+            credit_id: f.credit_id.clone().unwrap_or_else(|| generate_unique_id().to_string()),
+            billing_order: f.billing_order,
+            character_name: f.character_name.clone(),
+            department: f.department.clone().unwrap_or_else(|| ACTING.to_string()),
+            job: f.job.clone().unwrap_or_else(|| "Actor".to_string()),
+            name: f.name.clone(),
+            person_id: to_int(f.person_id.clone()),
+        }
+    }
+}
+
+impl Credit { 
+    pub async fn get_movie_credits<CreditDatabase: CreditResolver>(movie_id: i64, session: &'static CreditDatabase::Store) -> QueryResult<Vec<Credit>> {
+        CreditDatabase::get_movie_credits(movie_id, session).await
+    }
+    pub async fn get_person_credits<CreditDatabase: CreditResolver>(person_id: i32, session: &'static CreditDatabase::Store) -> QueryResult<Vec<Credit>> {
+        CreditDatabase::get_person_credits(person_id, session).await
+    }
+    pub async fn set_movie_credits<CreditDatabase: CreditResolver>(movie_id: i64, credits: Vec<Credit>, session: &'static CreditDatabase::Store) -> QueryResult<Vec<Credit>> {
+        CreditDatabase::set_movie_credits(movie_id, credits, session).await
+    }
+    pub async fn delete_credit<CreditDatabase: CreditResolver>(movie_id: i64, credit_id: String, session: &'static CreditDatabase::Store) -> QueryResult<bool> {
+        CreditDatabase::delete_credit(movie_id, credit_id, session).await
+    }
+    pub async fn reassign_person<CreditDatabase: CreditResolver>(source_id: i32, target_id: i32, session: &'static CreditDatabase::Store) -> QueryResult<Vec<Credit>> {
+        CreditDatabase::reassign_person(source_id, target_id, session).await
+    }
+}
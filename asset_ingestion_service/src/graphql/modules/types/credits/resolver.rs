@@ -0,0 +1,134 @@
+use async_trait::async_trait;
+use common_utils::{QueryResult, error::ServiceError};
+use scylla::IntoTypedRows;
+use crate::db::{CachedSession, bind, write_logged_batch};
+use super::model::Credit;
+
+/// Credits per logged batch, each one is written to both tables. Big casts are split up
+/// so a batch stays under Scylla's batch size limit
+const CREDIT_BATCH_SIZE: usize = 50;
+
+/// Credits are stored twice, by movie and by person, and every write keeps the two in step
+#[async_trait]
+pub trait CreditResolver: Send + Sync + 'static { 
+    type Store: Send + Sync + 'static;
+    async fn get_movie_credits(movie_id: i64, session: &'static Self::Store) -> QueryResult<Vec<Credit>>;
+    async fn get_person_credits(person_id: i32, session: &'static Self::Store) -> QueryResult<Vec<Credit>>;
+    /// Replaces every credit of the movie
+    async fn set_movie_credits(movie_id: i64, credits: Vec<Credit>, session: &'static Self::Store) -> QueryResult<Vec<Credit>>;
+    async fn delete_credit(movie_id: i64, credit_id: String, session: &'static Self::Store) -> QueryResult<bool>;
+    /// Moves every credit of `source_id` to `target_id`, returning the moved credits
+    async fn reassign_person(source_id: i32, target_id: i32, session: &'static Self::Store) -> QueryResult<Vec<Credit>>;
+}
+
+#[derive(Default)]
+pub struct CreditDatabase;
+
+static GET_MOVIE_CREDITS: &str = "SELECT * FROM movie_keyspace.credits_by_movie WHERE movie_id = ?;";
+static GET_MOVIE_CREDIT: &str = "SELECT * FROM movie_keyspace.credits_by_movie WHERE movie_id = ? AND credit_id = ?;";
+// Same column order as `credits_by_movie`, so both tables read into `Credit`
+static GET_PERSON_CREDITS: &str = "
+    SELECT movie_id, credit_id, billing_order, character_name, department, job, name, person_id 
+    FROM movie_keyspace.credits_by_person WHERE person_id = ?;
+";
+static INSERT_MOVIE_CREDIT: &str = "
+    INSERT INTO movie_keyspace.credits_by_movie (
+        movie_id, credit_id, billing_order, character_name, department, job, name, person_id
+    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?);
+";
+static INSERT_PERSON_CREDIT: &str = "
+    INSERT INTO movie_keyspace.credits_by_person (
+        movie_id, credit_id, billing_order, character_name, department, job, name, person_id
+    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?);
+";
+static DELETE_MOVIE_CREDIT: &str = "DELETE FROM movie_keyspace.credits_by_movie WHERE movie_id = ? AND credit_id = ?;";
+static DELETE_PERSON_CREDIT: &str = "DELETE FROM movie_keyspace.credits_by_person WHERE person_id = ? AND movie_id = ? AND credit_id = ?;";
+
+#[async_trait]
+impl CreditResolver for CreditDatabase { 
+    type Store = CachedSession;
+
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.credits_by_movie"), err)]
+    async fn get_movie_credits(movie_id: i64, session: &'static CachedSession) -> QueryResult<Vec<Credit>> {
+        session.query_prepared(GET_MOVIE_CREDITS, (movie_id,))
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
+            .into_typed::<Credit>()
+            .map(|row| row.map_err(|e| ServiceError::ServerError(e.to_string())))
+            .collect()
+    }
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.credits_by_person"), err)]
+    async fn get_person_credits(person_id: i32, session: &'static CachedSession) -> QueryResult<Vec<Credit>> {
+        session.query_prepared(GET_PERSON_CREDITS, (person_id,))
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
+            .into_typed::<Credit>()
+            .map(|row| row.map_err(|e| ServiceError::ServerError(e.to_string())))
+            .collect()
+    }
+    /// The old credits are removed before the new ones are written, both in chunks of
+    /// `CREDIT_BATCH_SIZE`. A credit is always added to or removed from both tables at once
+    #[tracing::instrument(skip(session, credits), fields(repository = "movie_keyspace.credits_by_movie"), err)]
+    async fn set_movie_credits(movie_id: i64, credits: Vec<Credit>, session: &'static CachedSession) -> QueryResult<Vec<Credit>> {
+        let existing = CreditDatabase::get_movie_credits(movie_id, session).await?;
+        for chunk in existing.chunks(CREDIT_BATCH_SIZE) { 
+            let mut statements = Vec::with_capacity(chunk.len() * 2);
+            for credit in chunk { 
+                statements.push((DELETE_MOVIE_CREDIT, bind((movie_id, credit.credit_id.clone()))?));
+                statements.push((DELETE_PERSON_CREDIT, bind((credit.person_id, movie_id, credit.credit_id.clone()))?));
+            }
+            write_logged_batch(statements, session).await?;
+        }
+        let credits = credits
+            .into_iter()
+            .map(|credit| Credit { movie_id, ..credit })
+            .collect::<Vec<_>>();
+        for chunk in credits.chunks(CREDIT_BATCH_SIZE) { 
+            let mut statements = Vec::with_capacity(chunk.len() * 2);
+            for credit in chunk { 
+                statements.push((INSERT_MOVIE_CREDIT, bind(credit.clone())?));
+                statements.push((INSERT_PERSON_CREDIT, bind(credit.clone())?));
+            }
+            write_logged_batch(statements, session).await?;
+        }
+        log::info!("Replaced {} credits of movie {} with {}", existing.len(), movie_id, credits.len());
+        Ok(credits)
+    }
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.credits_by_movie"), err)]
+    async fn delete_credit(movie_id: i64, credit_id: String, session: &'static CachedSession) -> QueryResult<bool> {
+        let credit = session.query_prepared(GET_MOVIE_CREDIT, (movie_id, credit_id.clone()))
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
+            .into_typed::<Credit>()
+            .next()
+            .ok_or(ServiceError::NotFound)?
+            .map_err(|e| ServiceError::ServerError(e.to_string()))?;
+        write_logged_batch(vec![
+            (DELETE_MOVIE_CREDIT, bind((movie_id, credit_id.clone()))?),
+            (DELETE_PERSON_CREDIT, bind((credit.person_id, movie_id, credit_id))?),
+        ], session).await?;
+        Ok(true)
+    }
+    /// The `credits_by_movie` row is overwritten in place, the `credits_by_person` row moves partition
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.credits_by_person"), err)]
+    async fn reassign_person(source_id: i32, target_id: i32, session: &'static CachedSession) -> QueryResult<Vec<Credit>> {
+        let credits = CreditDatabase::get_person_credits(source_id, session)
+            .await?
+            .into_iter()
+            .map(|credit| Credit { person_id: target_id, ..credit })
+            .collect::<Vec<_>>();
+        for chunk in credits.chunks(CREDIT_BATCH_SIZE) { 
+            let mut statements = Vec::with_capacity(chunk.len() * 3);
+            for credit in chunk { 
+                statements.push((DELETE_PERSON_CREDIT, bind((source_id, credit.movie_id, credit.credit_id.clone()))?));
+                statements.push((INSERT_PERSON_CREDIT, bind(credit.clone())?));
+                statements.push((INSERT_MOVIE_CREDIT, bind(credit.clone())?));
+            }
+            write_logged_batch(statements, session).await?;
+        }
+        Ok(credits)
+    }
+}
@@ -0,0 +1,76 @@
+use async_graphql::*;
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+use crate::{graphql::config::get_store_from_ctx, to_bigint};
+use super::{model::Credit, resolver::{CreditDatabase, CreditResolver}};
+
+/// Credit mutations go through `R`, `CreditDatabase` outside of tests
+#[derive(Default)]
+pub struct CreditMutation<R = CreditDatabase>(PhantomData<R>);
+
+#[derive(SimpleObject, Debug, Clone, Deserialize, Serialize)]
+pub struct CreditType { 
+    pub credit_id: String,
+    pub movie_id: ID,
+    pub person_id: ID,
+    pub name: String,
+    pub department: String,
+    pub job: String,
+    pub character_name: Option<String>,
+    pub billing_order: Option<i32>,
+}
+
+impl From<&Credit> for CreditType { 
+    fn from(f: &Credit) -> Self {
+        Self { 
+            credit_id: f.credit_id.clone(),
+            movie_id: f.movie_id.into(),
+            person_id: f.person_id.into(),
+            name: f.name.clone(),
+            department: f.department.clone(),
+            job: f.job.clone(),
+            character_name: f.character_name.clone(),
+            billing_order: f.billing_order,
+        }
+    }
+}
+
+#[derive(InputObject, Debug, Clone, Deserialize, Serialize)]
+pub struct CreditInput { 
+    /// Generated when left out. TMDB credit ids are kept, so a re-import replaces the same credits
+    pub credit_id: Option<String>,
+    pub person_id: ID,
+    /// Name as credited
+    pub name: String,
+    /// Defaults to "Acting", the department of the cast
+    pub department: Option<String>,
+    /// Defaults to "Actor"
+    pub job: Option<String>,
+    /// Cast only
+    pub character_name: Option<String>,
+    /// Cast only, lower is billed first
+    pub billing_order: Option<i32>,
+}
+
+#[Object]
+impl<R: CreditResolver> CreditMutation<R> { 
+    /// Replaces the whole cast and crew of the movie
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "setMovieCredits")]
+    async fn set_movie_credits(&self, ctx: &Context<'_>, movie_id: ID, credits: Vec<CreditInput>) -> FieldResult<Vec<CreditType>> { 
+        let movie_id = to_bigint(movie_id);
+        let credits = credits.iter().map(|credit| Credit::from_input(movie_id, credit)).collect();
+        let res = Credit::set_movie_credits::<R>(movie_id, credits, get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        Ok(res.iter().map(CreditType::from).collect())
+    }
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "deleteCredit")]
+    async fn delete_credit(&self, ctx: &Context<'_>, movie_id: ID, credit_id: String) -> FieldResult<bool> { 
+        let res = Credit::delete_credit::<R>(to_bigint(movie_id), credit_id, get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        Ok(res)
+    }
+}
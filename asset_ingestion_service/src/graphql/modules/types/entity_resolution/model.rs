@@ -0,0 +1,206 @@
+//! Finds people and companies that were stored more than once.
+//!
+//! Nothing is merged automatically, the matches are listed by `duplicateCandidates` for an
+//! editor to confirm with `mergePeople` or `mergeCompanies`.
+use std::collections::{BTreeMap, BTreeSet};
+use async_graphql::Enum;
+use common_utils::{QueryResult, error::ServiceError};
+use serde::{Deserialize, Serialize};
+use strum_macros::{EnumString, Display};
+use super::super::people_module::model::{NewPerson, Person, PersonExternalId, unknown_date};
+use super::super::prod_company::model::{CompanyExternalId, NewProductionComp, ProductionCompany};
+
+/// Legal suffixes that are dropped from company names before they are compared
+const COMPANY_SUFFIXES: &[&str] = &["inc", "llc", "ltd", "limited", "corp", "corporation", "co", "gmbh", "sa", "plc"];
+
+#[derive(Copy, Clone, Eq, Debug, PartialEq, Ord, PartialOrd, Serialize, Deserialize, Enum, Display, EnumString)]
+#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+pub enum EntityKind { 
+    Person,
+    Company,
+}
+
+/// Strongest evidence first
+#[derive(Copy, Clone, Eq, Debug, PartialEq, Ord, PartialOrd, Serialize, Deserialize, Enum, Display, EnumString)]
+#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+pub enum MatchReason { 
+    /// Several entities carry the same TMDB or IMDb id
+    ExternalId,
+    /// Same normalised name and the same birthday
+    NameAndBirthday,
+    /// Same normalised name, with nothing known that tells them apart
+    Name,
+}
+
+/// A group of entities that look like one and the same
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DuplicateCandidate { 
+    pub kind: EntityKind,
+    pub reason: MatchReason,
+    /// The shared external id, or the normalised name the entities matched on
+    pub key: String,
+    pub entity_ids: Vec<i64>,
+    pub names: Vec<String>,
+}
+
+/// Lower case words without punctuation, " Robert Downey-Jr. " and "robert downey jr" normalise alike
+pub fn normalise_name(name: &str) -> String { 
+    name
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// `normalise_name` without the trailing legal form, "Pixar Animation Studios, Inc." matches "Pixar Animation Studios"
+pub fn normalise_company_name(name: &str) -> String { 
+    let mut words = normalise_name(name)
+        .split(' ')
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    while words.len() > 1 && words.last().map_or(false, |word| COMPANY_SUFFIXES.contains(&word.as_str())) { 
+        words.pop();
+    }
+    words.join(" ")
+}
+
+/// External ids are written as `<source>:<id>`, e.g. "tmdb:287". The source is lower cased
+pub fn parse_external_ids(external_ids: Vec<String>) -> QueryResult<Vec<String>> { 
+    let mut parsed = external_ids
+        .iter()
+        .map(|external_id| match external_id.trim().split_once(':') { 
+            Some((source, id)) if !source.trim().is_empty() && !id.trim().is_empty() => Ok(format!("{}:{}", source.trim().to_lowercase(), id.trim())),
+            _ => Err(ServiceError::BadRequest(format!("External id `{}` is not of the form <source>:<id>", external_id))),
+        })
+        .collect::<QueryResult<Vec<_>>>()?;
+    parsed.sort();
+    parsed.dedup();
+    Ok(parsed)
+}
+
+pub fn person_candidates(people: &[Person], external_ids: &[PersonExternalId]) -> Vec<DuplicateCandidate> { 
+    let names = people
+        .iter()
+        .map(|person| (person.person_id as i64, person.name.clone()))
+        .collect::<Vec<_>>();
+    let mut candidates = external_id_candidates(
+        EntityKind::Person,
+        external_ids.iter().map(|row| (row.external_id.clone(), row.person_id as i64)),
+        &names,
+    );
+    let mut by_name: BTreeMap<String, Vec<&Person>> = BTreeMap::new();
+    people.iter().for_each(|person| by_name.entry(normalise_name(&person.name)).or_default().push(person));
+    for (name, group) in by_name { 
+        let mut by_birthday: BTreeMap<_, BTreeSet<i64>> = BTreeMap::new();
+        let mut unknown = false;
+        for person in &group { 
+            if person.birthday == unknown_date() { 
+                unknown = true;
+            } else { 
+                by_birthday.entry(person.birthday).or_default().insert(person.person_id as i64);
+            }
+        }
+        for (birthday, ids) in by_birthday { 
+            candidates.extend(candidate(EntityKind::Person, MatchReason::NameAndBirthday, format!("{} {}", name, birthday), ids, &names));
+        }
+        // Without a birthday on one side the name is all there is, the editor decides
+        if unknown { 
+            let ids = group.iter().map(|person| person.person_id as i64).collect();
+            candidates.extend(candidate(EntityKind::Person, MatchReason::Name, name, ids, &names));
+        }
+    }
+    candidates
+}
+
+pub fn company_candidates(companies: &[ProductionCompany], external_ids: &[CompanyExternalId]) -> Vec<DuplicateCandidate> { 
+    let names = companies
+        .iter()
+        .map(|company| (company.company_id, company.name.clone()))
+        .collect::<Vec<_>>();
+    let mut candidates = external_id_candidates(
+        EntityKind::Company,
+        external_ids.iter().map(|row| (row.external_id.clone(), row.company_id)),
+        &names,
+    );
+    let mut by_name: BTreeMap<String, BTreeSet<i64>> = BTreeMap::new();
+    companies.iter().for_each(|company| { 
+        by_name.entry(normalise_company_name(&company.name)).or_default().insert(company.company_id);
+    });
+    for (name, ids) in by_name { 
+        candidates.extend(candidate(EntityKind::Company, MatchReason::Name, name, ids, &names));
+    }
+    candidates
+}
+
+fn external_id_candidates(kind: EntityKind, rows: impl Iterator<Item = (String, i64)>, names: &[(i64, String)]) -> Vec<DuplicateCandidate> { 
+    let mut by_external_id: BTreeMap<String, BTreeSet<i64>> = BTreeMap::new();
+    rows.for_each(|(external_id, entity_id)| { 
+        by_external_id.entry(external_id).or_default().insert(entity_id);
+    });
+    by_external_id
+        .into_iter()
+        .filter_map(|(external_id, ids)| candidate(kind, MatchReason::ExternalId, external_id, ids, names))
+        .collect()
+}
+
+/// `None` unless at least two distinct entities matched
+fn candidate(kind: EntityKind, reason: MatchReason, key: String, ids: BTreeSet<i64>, names: &[(i64, String)]) -> Option<DuplicateCandidate> { 
+    if ids.len() < 2 { 
+        return None;
+    }
+    let names = names
+        .iter()
+        .filter(|(id, _)| ids.contains(id))
+        .map(|(_, name)| name.clone())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    Some(DuplicateCandidate { kind, reason, key, entity_ids: ids.into_iter().collect(), names })
+}
+
+/// The target keeps its own values, blanks are filled in from the source and lists are combined
+pub fn merge_person(source: &Person, target: &Person) -> NewPerson { 
+    NewPerson { 
+        person_id: target.person_id,
+        name: target.name.clone(),
+        awards: union(&target.awards, &source.awards),
+        biography: or_else(&target.biography, &source.biography),
+        birthday: if target.birthday == unknown_date() { source.birthday } else { target.birthday },
+        death_date: if target.death_date == unknown_date() { source.death_date } else { target.death_date },
+        gender: or_else(&target.gender, &source.gender),
+        homepage: or_else(&target.homepage, &source.homepage),
+        known_for: union(&target.known_for, &source.known_for),
+        place_of_birth: or_else(&target.place_of_birth, &source.place_of_birth),
+        profile_path: union(&target.profile_path, &source.profile_path),
+    }
+}
+
+pub fn merge_company(source: &ProductionCompany, target: &ProductionCompany) -> NewProductionComp { 
+    NewProductionComp { 
+        company_id: target.company_id,
+        name: target.name.clone(),
+        description: or_else(&target.description, &source.description),
+        headquarter: or_else(&target.headquarter, &source.headquarter),
+        homepage: or_else(&target.homepage, &source.homepage),
+        logo_path: or_else(&target.logo_path, &source.logo_path),
+        movie_id: target.movie_id,
+        origin_country: or_else(&target.origin_country, &source.origin_country),
+        parent_company: or_else(&target.parent_company, &source.parent_company),
+    }
+}
+
+fn or_else(value: &str, fallback: &str) -> String { 
+    if value.trim().is_empty() { fallback.to_string() } else { value.to_string() }
+}
+
+/// Keeps the order of `first`, skipping the blank entries inputs are padded with
+fn union(first: &[String], second: &[String]) -> Vec<String> { 
+    let mut values: Vec<String> = Vec::with_capacity(first.len() + second.len());
+    for value in first.iter().chain(second) { 
+        if !value.trim().is_empty() && !values.contains(value) { 
+            values.push(value.clone());
+        }
+    }
+    values
+}
@@ -0,0 +1,134 @@
+use std::{collections::BTreeSet, marker::PhantomData};
+use async_graphql::*;
+use common_utils::{QueryResult, error::ServiceError};
+use serde::{Deserialize, Serialize};
+use crate::{graphql::config::get_store_from_ctx, to_bigint, to_int};
+use super::super::credits::{model::Credit, resolver::{CreditDatabase, CreditResolver}};
+use super::super::movies::{model::Movie, resolver::{MovieDatabase, MovieResolver}};
+use super::super::people_module::{model::Person, resolver::{PersonDatabase, PersonResolver}, schema::PersonType};
+use super::super::prod_company::{model::ProductionCompany, resolver::{CompanyDatabase, ProdCompanyResolver}, schema::ProductionCompanyType};
+use super::model::{DuplicateCandidate, EntityKind, MatchReason, company_candidates, merge_company, merge_person, person_candidates};
+
+/// People are read through `P` and companies through `Co`
+#[derive(Default)]
+pub struct EntityResolutionQuery<P = PersonDatabase, Co = CompanyDatabase>(PhantomData<(P, Co)>);
+
+/// Merges also rewrite credits through `C` and reindex the affected movies read through `M`
+#[derive(Default)]
+pub struct EntityResolutionMutation<P = PersonDatabase, Co = CompanyDatabase, C = CreditDatabase, M = MovieDatabase>(PhantomData<(P, Co, C, M)>);
+
+#[derive(SimpleObject, Debug, Clone, Deserialize, Serialize)]
+pub struct DuplicateCandidateType { 
+    pub kind: EntityKind,
+    pub reason: MatchReason,
+    /// The shared external id, or the normalised name the entities matched on
+    pub key: String,
+    pub entity_ids: Vec<ID>,
+    pub names: Vec<String>,
+}
+
+impl From<&DuplicateCandidate> for DuplicateCandidateType { 
+    fn from(f: &DuplicateCandidate) -> Self {
+        Self { 
+            kind: f.kind,
+            reason: f.reason,
+            key: f.key.clone(),
+            entity_ids: f.entity_ids.iter().map(|id| ID::from(*id)).collect(),
+            names: f.names.clone(),
+        }
+    }
+}
+
+#[Object]
+impl<P: PersonResolver, Co: ProdCompanyResolver> EntityResolutionQuery<P, Co> { 
+    /// People and companies that look stored more than once, strongest matches first.
+    /// Nothing is changed, confirmed matches are merged with `mergePeople` and `mergeCompanies`
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "duplicateCandidates")]
+    async fn duplicate_candidates(&self, ctx: &Context<'_>, kind: Option<EntityKind>, reason: Option<MatchReason>) -> FieldResult<Vec<DuplicateCandidateType>> { 
+        let mut candidates = Vec::new();
+        if kind.map_or(true, |kind| kind == EntityKind::Person) { 
+            let session = get_store_from_ctx(ctx);
+            let people = Person::get_all_person::<P>(session).await.map_err(|e| e.extend())?;
+            let external_ids = Person::get_external_ids::<P>(session).await.map_err(|e| e.extend())?;
+            candidates.extend(person_candidates(&people, &external_ids));
+        }
+        if kind.map_or(true, |kind| kind == EntityKind::Company) { 
+            let session = get_store_from_ctx(ctx);
+            let companies = ProductionCompany::get_all_companies::<Co>(session).await.map_err(|e| e.extend())?;
+            let external_ids = ProductionCompany::get_external_ids::<Co>(session).await.map_err(|e| e.extend())?;
+            candidates.extend(company_candidates(&companies, &external_ids));
+        }
+        candidates.retain(|candidate| reason.map_or(true, |reason| reason == candidate.reason));
+        candidates.sort_by(|a, b| (a.reason, a.kind, &a.key).cmp(&(b.reason, b.kind, &b.key)));
+        Ok(candidates.iter().map(DuplicateCandidateType::from).collect())
+    }
+}
+
+#[Object]
+impl<P: PersonResolver, Co: ProdCompanyResolver, C: CreditResolver, M: MovieResolver> EntityResolutionMutation<P, Co, C, M> { 
+    /// Folds `sourceId` into `targetId`. The credits and external ids of the source move to the
+    /// target, blanks of the target are filled in from the source and the source is deleted.
+    /// Every movie the source was credited on is sent to be reindexed
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "mergePeople")]
+    async fn merge_people(&self, ctx: &Context<'_>, source_id: ID, target_id: ID) -> FieldResult<PersonType> { 
+        let (source_id, target_id) = (to_int(source_id), to_int(target_id));
+        if source_id == target_id { 
+            return Err(ServiceError::BadRequest(format!("Cannot merge person {} into itself", source_id)).extend());
+        }
+        let session = get_store_from_ctx(ctx);
+        let source = Person::get_person_by_id::<P>(session, source_id).await.map_err(|e| e.extend())?;
+        let target = Person::get_person_by_id::<P>(session, target_id).await.map_err(|e| e.extend())?;
+        // Credits first, were the merge to fail halfway they already point at a person that exists
+        let credits = Credit::reassign_person::<C>(source_id, target_id, get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        let merged = Person::merge_people::<P>(session, source.clone(), merge_person(&source, &target))
+            .await
+            .map_err(|e| e.extend())?;
+        let reindexed = reindex_movies::<M>(credits.iter().map(|credit| credit.movie_id), get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        log::info!("🔗 Merged person {} into {}, moved {} credits and reindexed {} movies", source_id, target_id, credits.len(), reindexed);
+        Ok(PersonType::from(&merged))
+    }
+    /// Folds every row of `sourceId` into `targetId`, the same way `mergePeople` does
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "mergeCompanies")]
+    async fn merge_companies(&self, ctx: &Context<'_>, source_id: ID, target_id: ID) -> FieldResult<ProductionCompanyType> { 
+        let (source_id, target_id) = (to_bigint(source_id), to_bigint(target_id));
+        if source_id == target_id { 
+            return Err(ServiceError::BadRequest(format!("Cannot merge company {} into itself", source_id)).extend());
+        }
+        let session = get_store_from_ctx(ctx);
+        let source = ProductionCompany::get_company_id::<Co>(source_id, session).await.map_err(|e| e.extend())?;
+        let target = ProductionCompany::get_company_id::<Co>(target_id, session).await.map_err(|e| e.extend())?;
+        let removed = ProductionCompany::merge_companies::<Co>(source_id, merge_company(&source, &target), session)
+            .await
+            .map_err(|e| e.extend())?;
+        let merged = ProductionCompany::get_company_id::<Co>(target_id, session).await.map_err(|e| e.extend())?;
+        let reindexed = reindex_movies::<M>(removed.iter().map(|company| company.movie_id), get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        log::info!("🔗 Merged company {} into {}, reindexed {} movies", source_id, target_id, reindexed);
+        Ok(ProductionCompanyType::from(&merged))
+    }
+}
+
+/// Requests a reindex of each distinct movie, skipping ids that are no longer in the catalogue
+/// and soft deleted movies, which are kept out of the index until they are restored
+async fn reindex_movies<M: MovieResolver>(movie_ids: impl Iterator<Item = i64>, session: &'static M::Store) -> QueryResult<usize> { 
+    let mut movies = Vec::new();
+    for movie_id in movie_ids.collect::<BTreeSet<_>>() { 
+        match Movie::get_movie_id::<M>(movie_id, session).await { 
+            Ok(movie) if movie.is_deleted() => continue,
+            Ok(movie) => movies.push(movie),
+            Err(ServiceError::NotFound) => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    let count = movies.len();
+    Movie::request_reindex::<M>(movies, session).await?;
+    Ok(count)
+}
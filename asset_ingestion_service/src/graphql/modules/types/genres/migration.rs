@@ -0,0 +1,97 @@
+//! Rewrites the genres stored before the taxonomy existed, TMDB ids like "28" and free-text
+//! names, to slugs. Running it again only touches rows written since with unknown values
+use std::collections::BTreeSet;
+use async_graphql::SimpleObject;
+use common_utils::QueryResult;
+use serde::Serialize;
+use super::super::movies::{import::describe, model::Movie, resolver::MovieResolver};
+use super::super::series::{model::Series, resolver::SeriesResolver};
+use super::model::{or_blank, Taxonomy};
+
+#[derive(SimpleObject, Debug, Clone, Default, Serialize)]
+pub struct GenreMigrationReport {
+    pub movies_scanned: i32,
+    pub movies_rewritten: i32,
+    pub series_scanned: i32,
+    pub series_rewritten: i32,
+    /// Values that match no genre. Rows holding one are left untouched, add the value as an
+    /// alias of the right genre and run the migration again
+    pub unmapped: Vec<String>,
+    /// Rows that could not be written, one message each
+    pub errors: Vec<String>,
+}
+
+/// The slugs to store instead of `genres`. `None` when nothing changes, or when a value is
+/// unknown, in which case it is added to `unmapped`
+fn migrated(taxonomy: &Taxonomy, genres: &[String], unmapped: &mut BTreeSet<String>) -> Option<Vec<String>> {
+    let (slugs, unknown) = taxonomy.map(genres);
+    if !unknown.is_empty() {
+        unmapped.extend(unknown);
+        return None
+    }
+    // Both columns are sets, the order they come back in says nothing
+    let current: BTreeSet<&String> = genres.iter().filter(|genre| !genre.trim().is_empty()).collect();
+    match slugs.iter().collect::<BTreeSet<_>>() == current {
+        true => None,
+        false => Some(slugs),
+    }
+}
+
+/// Scans every movie and series, rewrites their genres to slugs and publishes the rewritten rows
+/// so the search index drops the old buckets, through the event outbox of each write.
+/// With `dry_run` the report is filled in but nothing is written
+#[tracing::instrument(skip(taxonomy, movie_session, series_session))]
+pub async fn migrate_genres<M: MovieResolver, S: SeriesResolver>(
+    taxonomy: &Taxonomy,
+    dry_run: bool,
+    movie_session: &'static M::Store,
+    series_session: &'static S::Store,
+) -> QueryResult<GenreMigrationReport> {
+    let mut report = GenreMigrationReport::default();
+    let mut unmapped = BTreeSet::new();
+
+    let movies = Movie::get_movies::<M>(movie_session).await?;
+    report.movies_scanned = movies.len() as i32;
+    for movie in movies {
+        let genres = match migrated(taxonomy, &movie.genres, &mut unmapped) {
+            Some(genres) => genres,
+            None => continue,
+        };
+        report.movies_rewritten += 1;
+        if dry_run {
+            continue
+        }
+        // Written through the key the row was read with, rows from before `movies_by_id` have no entry there
+        let movie_id = movie.movie_id;
+        if let Err(e) = Movie::set_genres::<M>(movie, or_blank(genres), movie_session).await {
+            report.movies_rewritten -= 1;
+            report.errors.push(format!("Movie {}: {}", movie_id, describe(&e)));
+        }
+    }
+
+    let series = Series::get_all_series::<S>(series_session).await?;
+    report.series_scanned = series.len() as i32;
+    for series in series {
+        let genres = match migrated(taxonomy, &series.genres, &mut unmapped) {
+            Some(genres) => genres,
+            None => continue,
+        };
+        report.series_rewritten += 1;
+        if dry_run {
+            continue
+        }
+        let series_id = series.series_id;
+        if let Err(e) = Series::update_series::<S>(Series { genres, ..series }, series_session).await {
+            report.series_rewritten -= 1;
+            report.errors.push(format!("Series {}: {}", series_id, describe(&e)));
+        }
+    }
+
+    report.unmapped = unmapped.into_iter().collect();
+    log::info!(
+        "🏷️ Genre migration{}: rewrote {} of {} movies and {} of {} series, {} unmapped values",
+        if dry_run { " (dry run)" } else { "" },
+        report.movies_rewritten, report.movies_scanned, report.series_rewritten, report.series_scanned, report.unmapped.len(),
+    );
+    Ok(report)
+}
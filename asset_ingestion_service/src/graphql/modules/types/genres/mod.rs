@@ -0,0 +1,4 @@
+pub mod migration;
+pub mod model;
+pub mod resolver;
+pub mod schema;
@@ -0,0 +1,263 @@
+//! Genres are managed rows instead of free text.
+//!
+//! Movies and series store the slug of each genre, which is what the search index aggregates
+//! on. TMDB genre ids, display names in any locale and legacy spellings all resolve to a slug.
+use std::collections::{BTreeSet, HashMap};
+use common_utils::{QueryResult, error::ServiceError};
+use scylla::macros::{FromRow, ValueList};
+use serde::{Deserialize, Serialize};
+use super::resolver::GenreResolver;
+
+/// Locale every genre has a name in, and the fallback when a requested locale is missing
+pub const DEFAULT_LOCALE: &str = "en";
+
+// Columns after the primary key are in alphabetical order, the order `SELECT *` returns them in
+#[derive(Debug, FromRow, Clone, PartialEq, Serialize, Deserialize, ValueList)]
+pub struct Genre {
+    pub genre_id: i32,
+    /// Other spellings that resolve to this genre, e.g. the combined TMDB TV genres
+    pub aliases: Option<Vec<String>>,
+    /// Display name per locale
+    pub names: HashMap<String, String>,
+    pub parent_id: Option<i32>,
+    /// Stored on movies and series, never changes once the genre is created
+    pub slug: String,
+    /// TMDB genre ids that map onto this genre
+    pub tmdb_ids: Option<Vec<i32>>,
+}
+
+struct DefaultGenre {
+    genre_id: i32,
+    slug: &'static str,
+    name: &'static str,
+    parent_id: Option<i32>,
+    tmdb_ids: &'static [i32],
+    aliases: &'static [&'static str],
+}
+
+/// TMDB's movie genres, plus its TV genres. The combined TV genres, e.g. "Action & Adventure",
+/// are split so a series tagged with one shows up under both halves
+const DEFAULT_GENRES: &[DefaultGenre] = &[
+    DefaultGenre { genre_id: 1, slug: "action", name: "Action", parent_id: None, tmdb_ids: &[28, 10759], aliases: &["Action & Adventure"] },
+    DefaultGenre { genre_id: 2, slug: "adventure", name: "Adventure", parent_id: None, tmdb_ids: &[12, 10759], aliases: &["Action & Adventure"] },
+    DefaultGenre { genre_id: 3, slug: "animation", name: "Animation", parent_id: None, tmdb_ids: &[16], aliases: &[] },
+    DefaultGenre { genre_id: 4, slug: "comedy", name: "Comedy", parent_id: None, tmdb_ids: &[35], aliases: &[] },
+    DefaultGenre { genre_id: 5, slug: "crime", name: "Crime", parent_id: None, tmdb_ids: &[80], aliases: &[] },
+    DefaultGenre { genre_id: 6, slug: "documentary", name: "Documentary", parent_id: None, tmdb_ids: &[99], aliases: &[] },
+    DefaultGenre { genre_id: 7, slug: "drama", name: "Drama", parent_id: None, tmdb_ids: &[18], aliases: &[] },
+    DefaultGenre { genre_id: 8, slug: "family", name: "Family", parent_id: None, tmdb_ids: &[10751], aliases: &[] },
+    DefaultGenre { genre_id: 9, slug: "fantasy", name: "Fantasy", parent_id: None, tmdb_ids: &[14, 10765], aliases: &["Sci-Fi & Fantasy"] },
+    DefaultGenre { genre_id: 10, slug: "history", name: "History", parent_id: None, tmdb_ids: &[36], aliases: &[] },
+    DefaultGenre { genre_id: 11, slug: "horror", name: "Horror", parent_id: None, tmdb_ids: &[27], aliases: &[] },
+    DefaultGenre { genre_id: 12, slug: "music", name: "Music", parent_id: None, tmdb_ids: &[10402], aliases: &[] },
+    DefaultGenre { genre_id: 13, slug: "mystery", name: "Mystery", parent_id: None, tmdb_ids: &[9648], aliases: &[] },
+    DefaultGenre { genre_id: 14, slug: "romance", name: "Romance", parent_id: None, tmdb_ids: &[10749], aliases: &[] },
+    DefaultGenre { genre_id: 15, slug: "science-fiction", name: "Science Fiction", parent_id: None, tmdb_ids: &[878, 10765], aliases: &["Sci-Fi & Fantasy", "Sci-Fi"] },
+    DefaultGenre { genre_id: 16, slug: "tv-movie", name: "TV Movie", parent_id: None, tmdb_ids: &[10770], aliases: &[] },
+    DefaultGenre { genre_id: 17, slug: "thriller", name: "Thriller", parent_id: None, tmdb_ids: &[53], aliases: &[] },
+    DefaultGenre { genre_id: 18, slug: "war", name: "War", parent_id: None, tmdb_ids: &[10752, 10768], aliases: &["War & Politics"] },
+    DefaultGenre { genre_id: 19, slug: "western", name: "Western", parent_id: None, tmdb_ids: &[37], aliases: &[] },
+    DefaultGenre { genre_id: 20, slug: "politics", name: "Politics", parent_id: None, tmdb_ids: &[10768], aliases: &["War & Politics"] },
+    DefaultGenre { genre_id: 21, slug: "kids", name: "Kids", parent_id: Some(8), tmdb_ids: &[10762], aliases: &[] },
+    DefaultGenre { genre_id: 22, slug: "news", name: "News", parent_id: None, tmdb_ids: &[10763], aliases: &[] },
+    DefaultGenre { genre_id: 23, slug: "reality", name: "Reality", parent_id: None, tmdb_ids: &[10764], aliases: &[] },
+    DefaultGenre { genre_id: 24, slug: "soap", name: "Soap", parent_id: Some(7), tmdb_ids: &[10766], aliases: &[] },
+    DefaultGenre { genre_id: 25, slug: "talk", name: "Talk", parent_id: None, tmdb_ids: &[10767], aliases: &[] },
+];
+
+/// The genres a new cluster is seeded with, in id order
+pub fn default_genres() -> Vec<Genre> {
+    DEFAULT_GENRES
+        .iter()
+        .map(|genre| Genre {
+            genre_id: genre.genre_id,
+            aliases: (!genre.aliases.is_empty()).then(|| genre.aliases.iter().map(|alias| alias.to_string()).collect()),
+            names: HashMap::from([(DEFAULT_LOCALE.to_string(), genre.name.to_string())]),
+            parent_id: genre.parent_id,
+            slug: genre.slug.to_string(),
+            tmdb_ids: Some(genre.tmdb_ids.to_vec()),
+        })
+        .collect()
+}
+
+/// TMDB responses are parsed before any session is at hand, so movies and series fetched from
+/// TMDB carry its genre ids as they are until `resolve_tmdb_genres` maps them through the taxonomy
+pub fn tmdb_genre_ids(tmdb_ids: &[i32]) -> Vec<String> {
+    tmdb_ids.iter().map(|tmdb_id| tmdb_id.to_string()).collect()
+}
+
+/// Slugs of the TMDB genre ids of `title`, see `tmdb_genre_ids`. Ids that no genre lists in its
+/// `tmdb_ids` are left out and reported, so they can be added to one
+pub fn resolve_tmdb_genres(taxonomy: &Taxonomy, title: &str, genres: &[String]) -> Vec<String> {
+    let (slugs, unmapped) = taxonomy.tmdb_slugs(genres);
+    if !unmapped.is_empty() {
+        log::warn!("🏷️ TMDB genre ids {} of {} match no genre, add them to the tmdbIds of one", unmapped.join(", "), title);
+    }
+    slugs
+}
+
+/// `movies_object` stores a single blank for an empty list, like its other list columns
+pub fn or_blank(genres: Vec<String>) -> Vec<String> {
+    if genres.is_empty() { vec![String::new()] } else { genres }
+}
+
+/// Lowercase letters and digits separated by single hyphens, e.g. `science-fiction`
+pub fn is_slug(slug: &str) -> bool {
+    !slug.is_empty()
+        && slug.split('-').all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit()))
+}
+
+fn lookup_key(value: &str) -> String {
+    value.trim().to_lowercase()
+}
+
+/// Every genre, indexed by each value that resolves to it
+pub struct Taxonomy {
+    genres: Vec<Genre>,
+    lookup: HashMap<String, Vec<usize>>,
+}
+
+impl Taxonomy {
+    pub fn new(mut genres: Vec<Genre>) -> Self {
+        genres.sort_by_key(|genre| genre.genre_id);
+        let mut lookup: HashMap<String, Vec<usize>> = HashMap::new();
+        for (index, genre) in genres.iter().enumerate() {
+            let keys = std::iter::once(genre.slug.clone())
+                .chain(genre.names.values().cloned())
+                .chain(genre.aliases.iter().flatten().cloned())
+                .chain(genre.tmdb_ids.iter().flatten().map(|tmdb_id| tmdb_id.to_string()))
+                .map(|key| lookup_key(&key))
+                .collect::<BTreeSet<_>>();
+            for key in keys {
+                lookup.entry(key).or_default().push(index);
+            }
+        }
+        Self { genres, lookup }
+    }
+    /// In id order
+    pub fn genres(&self) -> &[Genre] {
+        &self.genres
+    }
+    pub fn get(&self, genre_id: i32) -> Option<&Genre> {
+        self.genres.iter().find(|genre| genre.genre_id == genre_id)
+    }
+    pub fn by_slug(&self, slug: &str) -> Option<&Genre> {
+        self.genres.iter().find(|genre| genre.slug == slug)
+    }
+    /// Whether `parent_id` is `genre_id` itself or one of its descendants
+    pub fn is_descendant(&self, parent_id: i32, genre_id: i32) -> bool {
+        let mut current = Some(parent_id);
+        // A corrupted table could hold a cycle already, a chain longer than the table is one
+        for _ in 0..=self.genres.len() {
+            match current {
+                Some(id) if id == genre_id => return true,
+                Some(id) => current = self.get(id).and_then(|genre| genre.parent_id),
+                None => return false,
+            }
+        }
+        true
+    }
+    /// The rules a genre has to follow before it is written, `genre` may already be in the taxonomy
+    pub fn check(&self, genre: &Genre) -> QueryResult<()> {
+        if !is_slug(&genre.slug) {
+            return Err(ServiceError::BadRequest(format!("`{}` is not a slug, e.g. science-fiction", genre.slug)))
+        }
+        if self.genres.iter().any(|other| other.slug == genre.slug && other.genre_id != genre.genre_id) {
+            return Err(ServiceError::BadRequest(format!("Slug `{}` is taken", genre.slug)))
+        }
+        if genre.names.get(DEFAULT_LOCALE).map_or(true, |name| name.trim().is_empty()) {
+            return Err(ServiceError::BadRequest(format!("A genre needs a name in `{}`", DEFAULT_LOCALE)))
+        }
+        match genre.parent_id {
+            Some(parent_id) if self.get(parent_id).is_none() =>
+                Err(ServiceError::BadRequest(format!("Parent genre {} does not exist", parent_id))),
+            Some(parent_id) if self.is_descendant(parent_id, genre.genre_id) =>
+                Err(ServiceError::BadRequest(format!("Genre {} cannot be nested under itself", genre.genre_id))),
+            _ => Ok(()),
+        }
+    }
+    /// Maps each value, matched case-insensitively on slug, name, alias or TMDB id, to slugs.
+    /// Returns the slugs without repeats and the values that matched nothing. Blanks are skipped
+    pub fn map(&self, values: &[String]) -> (Vec<String>, Vec<String>) {
+        let mut slugs: Vec<String> = Vec::new();
+        let mut unknown = Vec::new();
+        for value in values.iter().filter(|value| !value.trim().is_empty()) {
+            match self.lookup.get(&lookup_key(value)) {
+                Some(matches) => for index in matches {
+                    let slug = &self.genres[*index].slug;
+                    if !slugs.contains(slug) {
+                        slugs.push(slug.clone());
+                    }
+                },
+                None => unknown.push(value.clone()),
+            }
+        }
+        (slugs, unknown)
+    }
+    /// Maps TMDB genre ids to the slugs of the genres listing them in `tmdb_ids`. Returns the slugs
+    /// without repeats and the ids that matched nothing. Blanks are skipped
+    pub fn tmdb_slugs(&self, tmdb_ids: &[String]) -> (Vec<String>, Vec<String>) {
+        let mut slugs: Vec<String> = Vec::new();
+        let mut unmapped = Vec::new();
+        for tmdb_id in tmdb_ids.iter().filter(|tmdb_id| !tmdb_id.trim().is_empty()) {
+            let parsed = tmdb_id.trim().parse::<i32>().ok();
+            let matching: Vec<&Genre> = self.genres
+                .iter()
+                .filter(|genre| parsed.map_or(false, |parsed| genre.tmdb_ids.iter().flatten().any(|id| *id == parsed)))
+                .collect();
+            if matching.is_empty() {
+                unmapped.push(tmdb_id.clone());
+            }
+            for genre in matching {
+                if !slugs.contains(&genre.slug) {
+                    slugs.push(genre.slug.clone());
+                }
+            }
+        }
+        (slugs, unmapped)
+    }
+    /// Like `map`, but a single unknown value rejects the whole list
+    pub fn canonicalise(&self, values: &[String]) -> QueryResult<Vec<String>> {
+        match self.map(values) {
+            (slugs, unknown) if unknown.is_empty() => Ok(slugs),
+            (_, unknown) => Err(ServiceError::BadRequest(format!("Unknown genres: {}", unknown.join(", ")))),
+        }
+    }
+}
+
+impl Genre {
+    /// The name in `locale`, in the default locale when it has none
+    pub fn name(&self, locale: &str) -> String {
+        self.names.get(locale)
+            .or_else(|| self.names.get(DEFAULT_LOCALE))
+            .cloned()
+            .unwrap_or_else(|| self.slug.clone())
+    }
+    pub async fn get_genres<GenreDatabase: GenreResolver>(session: &'static GenreDatabase::Store) -> QueryResult<Vec<Genre>> {
+        GenreDatabase::get_genres(session).await
+    }
+    pub async fn create_genre<GenreDatabase: GenreResolver>(genre: Genre, session: &'static GenreDatabase::Store) -> QueryResult<bool> {
+        GenreDatabase::create_genre(genre, session).await
+    }
+    pub async fn update_genre<GenreDatabase: GenreResolver>(genre: Genre, session: &'static GenreDatabase::Store) -> QueryResult<Genre> {
+        GenreDatabase::update_genre(genre, session).await
+    }
+    pub async fn taxonomy<GenreDatabase: GenreResolver>(session: &'static GenreDatabase::Store) -> QueryResult<Taxonomy> {
+        GenreDatabase::get_genres(session).await.map(Taxonomy::new)
+    }
+    /// Rewrites `values` to slugs before a movie or series is written
+    pub async fn canonicalise<GenreDatabase: GenreResolver>(values: &[String], session: &'static GenreDatabase::Store) -> QueryResult<Vec<String>> {
+        Self::taxonomy::<GenreDatabase>(session).await?.canonicalise(values)
+    }
+    /// Creates the default genres that are missing. Existing rows are left alone, so
+    /// names and relations edited since are kept. Returns how many were created
+    pub async fn seed<GenreDatabase: GenreResolver>(session: &'static GenreDatabase::Store) -> QueryResult<usize> {
+        let mut created = 0;
+        for genre in default_genres() {
+            if GenreDatabase::create_genre(genre, session).await? {
+                created += 1;
+            }
+        }
+        Ok(created)
+    }
+}
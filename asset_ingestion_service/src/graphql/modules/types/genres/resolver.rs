@@ -0,0 +1,68 @@
+use async_trait::async_trait;
+use common_utils::{QueryResult, error::ServiceError};
+use scylla::IntoTypedRows;
+use crate::db::{CachedSession, is_applied};
+use super::model::Genre;
+
+/// The taxonomy is a few dozen rows, it is always read as a whole
+#[async_trait]
+pub trait GenreResolver: Send + Sync + 'static {
+    type Store: Send + Sync + 'static;
+    async fn get_genres(session: &'static Self::Store) -> QueryResult<Vec<Genre>>;
+    /// Only writes when no genre has that id yet, returns whether it was written
+    async fn create_genre(genre: Genre, session: &'static Self::Store) -> QueryResult<bool>;
+    async fn update_genre(genre: Genre, session: &'static Self::Store) -> QueryResult<Genre>;
+}
+
+#[derive(Default)]
+pub struct GenreDatabase;
+
+static GET_GENRES: &str = "SELECT * FROM movie_keyspace.genres;";
+static INSERT_GENRE: &str = "
+    INSERT INTO movie_keyspace.genres (
+        genre_id, aliases, names, parent_id, slug, tmdb_ids
+    ) VALUES (?, ?, ?, ?, ?, ?)
+    IF NOT EXISTS;
+";
+// `slug` is left out on purpose, it is what movies and series point at
+static UPDATE_GENRE: &str = "
+    UPDATE movie_keyspace.genres
+    SET aliases = ?, names = ?, parent_id = ?, tmdb_ids = ?
+    WHERE genre_id = ?
+    IF EXISTS;
+";
+
+#[async_trait]
+impl GenreResolver for GenreDatabase {
+    type Store = CachedSession;
+
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.genres"), err)]
+    async fn get_genres(session: &'static CachedSession) -> QueryResult<Vec<Genre>> {
+        session.query_prepared(GET_GENRES, ())
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
+            .into_typed::<Genre>()
+            .map(|row| row.map_err(|e| ServiceError::ServerError(e.to_string())))
+            .collect()
+    }
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.genres"), err)]
+    async fn create_genre(genre: Genre, session: &'static CachedSession) -> QueryResult<bool> {
+        session.query_prepared(INSERT_GENRE, genre)
+            .await
+            .map(is_applied)
+            .map_err(|_| ServiceError::DatabaseError)
+    }
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.genres"), err)]
+    async fn update_genre(genre: Genre, session: &'static CachedSession) -> QueryResult<Genre> {
+        let values = (genre.aliases.clone(), genre.names.clone(), genre.parent_id, genre.tmdb_ids.clone(), genre.genre_id);
+        let applied = session.query_prepared(UPDATE_GENRE, values)
+            .await
+            .map(is_applied)
+            .map_err(|_| ServiceError::DatabaseError)?;
+        match applied {
+            true => Ok(genre),
+            false => Err(ServiceError::NotFound),
+        }
+    }
+}
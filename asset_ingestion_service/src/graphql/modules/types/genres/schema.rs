@@ -0,0 +1,176 @@
+use std::{collections::HashMap, marker::PhantomData};
+use async_graphql::*;
+use common_utils::error::ServiceError;
+use serde::{Deserialize, Serialize};
+use crate::{graphql::config::get_store_from_ctx, to_int};
+use super::super::movies::resolver::{MovieDatabase, MovieResolver};
+use super::super::series::resolver::{SeriesDatabase, SeriesResolver};
+use super::migration::{migrate_genres, GenreMigrationReport};
+use super::model::{DEFAULT_LOCALE, Genre};
+use super::resolver::{GenreDatabase, GenreResolver};
+
+#[derive(Default)]
+pub struct GenreQuery<G = GenreDatabase>(PhantomData<G>);
+
+/// The migration rewrites movies through `M` and series through `S`
+#[derive(Default)]
+pub struct GenreMutation<G = GenreDatabase, M = MovieDatabase, S = SeriesDatabase>(PhantomData<(G, M, S)>);
+
+#[derive(SimpleObject, Debug, Clone, Deserialize, Serialize)]
+pub struct LocalizedName {
+    pub locale: String,
+    pub name: String,
+}
+
+#[derive(InputObject, Debug, Clone, Deserialize, Serialize)]
+pub struct LocalizedNameInput {
+    /// BCP 47 language tag, e.g. `en` or `pt-BR`
+    #[graphql(validator(min_length = 2, max_length = 10))]
+    pub locale: String,
+    #[graphql(validator(min_length = 1, max_length = 60))]
+    pub name: String,
+}
+
+#[derive(SimpleObject, Debug, Clone, Deserialize, Serialize)]
+pub struct GenreType {
+    pub genre_id: ID,
+    /// What movies and series store, and what the search index aggregates on
+    pub slug: String,
+    /// In the locale the genres were asked for
+    pub name: String,
+    pub names: Vec<LocalizedName>,
+    pub parent_id: Option<ID>,
+    pub aliases: Vec<String>,
+    pub tmdb_ids: Vec<i32>,
+}
+
+impl GenreType {
+    fn new(genre: &Genre, locale: &str) -> Self {
+        let mut names: Vec<LocalizedName> = genre.names
+            .iter()
+            .map(|(locale, name)| LocalizedName { locale: locale.clone(), name: name.clone() })
+            .collect();
+        names.sort_by(|a, b| a.locale.cmp(&b.locale));
+        Self {
+            genre_id: ID::from(genre.genre_id),
+            slug: genre.slug.clone(),
+            name: genre.name(locale),
+            names,
+            parent_id: genre.parent_id.map(ID::from),
+            aliases: genre.aliases.clone().unwrap_or_default(),
+            tmdb_ids: genre.tmdb_ids.clone().unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(InputObject, Debug, Clone)]
+pub struct GenreInput {
+    /// Lowercase words joined by hyphens. It cannot be changed later
+    #[graphql(validator(min_length = 1, max_length = 40))]
+    pub slug: String,
+    /// Needs at least a name in `en`
+    pub names: Vec<LocalizedNameInput>,
+    pub parent_id: Option<ID>,
+    /// Other spellings that should resolve to this genre
+    #[graphql(validator(list, max_length = 60))]
+    pub aliases: Option<Vec<String>>,
+    pub tmdb_ids: Option<Vec<i32>>,
+}
+
+/// Omitted fields keep their value, `parentId: null` makes the genre top level
+#[derive(InputObject, Debug, Clone, Default)]
+pub struct GenrePatchInput {
+    /// Replaces every name
+    pub names: Option<Vec<LocalizedNameInput>>,
+    pub parent_id: MaybeUndefined<ID>,
+    #[graphql(validator(list, max_length = 60))]
+    pub aliases: Option<Vec<String>>,
+    pub tmdb_ids: Option<Vec<i32>>,
+}
+
+fn names(names: &[LocalizedNameInput]) -> HashMap<String, String> {
+    names.iter()
+        .map(|name| (name.locale.trim().to_string(), name.name.trim().to_string()))
+        .collect()
+}
+
+/// An empty set is stored as null
+fn non_empty<T>(items: Vec<T>) -> Option<Vec<T>> {
+    Some(items).filter(|items| !items.is_empty())
+}
+
+#[Object]
+impl<G: GenreResolver> GenreQuery<G> {
+    /// The whole taxonomy in id order, named in `locale` where a translation exists
+    #[tracing::instrument(skip(self, ctx))]
+    async fn genres(&self, ctx: &Context<'_>, locale: Option<String>) -> FieldResult<Vec<GenreType>> {
+        let locale = locale.unwrap_or_else(|| DEFAULT_LOCALE.to_string());
+        let taxonomy = Genre::taxonomy::<G>(get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        Ok(taxonomy.genres().iter().map(|genre| GenreType::new(genre, &locale)).collect())
+    }
+}
+
+#[Object]
+impl<G: GenreResolver, M: MovieResolver, S: SeriesResolver> GenreMutation<G, M, S> {
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "createGenre")]
+    async fn create_genre(&self, ctx: &Context<'_>, genre: GenreInput) -> FieldResult<GenreType> {
+        let session = get_store_from_ctx(ctx);
+        let taxonomy = Genre::taxonomy::<G>(session).await.map_err(|e| e.extend())?;
+        let genre = Genre {
+            genre_id: taxonomy.genres().iter().map(|genre| genre.genre_id).max().unwrap_or(0) + 1,
+            aliases: genre.aliases.map(non_empty).unwrap_or_default(),
+            names: names(&genre.names),
+            parent_id: genre.parent_id.map(to_int),
+            slug: genre.slug.trim().to_string(),
+            tmdb_ids: genre.tmdb_ids.map(non_empty).unwrap_or_default(),
+        };
+        taxonomy.check(&genre).map_err(|e| e.extend())?;
+        // Two genres created at once can be handed the same id, only the first one is written
+        if !Genre::create_genre::<G>(genre.clone(), session).await.map_err(|e| e.extend())? {
+            return Err(ServiceError::BadRequest(format!("Genre id {} was just taken, try again", genre.genre_id)).extend());
+        }
+        log::info!("🏷️ Created genre {} `{}`", genre.genre_id, genre.slug);
+        Ok(GenreType::new(&genre, DEFAULT_LOCALE))
+    }
+    /// The slug stays the same, so movies and series tagged with the genre need no rewrite
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "updateGenre")]
+    async fn update_genre(&self, ctx: &Context<'_>, genre_id: ID, patch: GenrePatchInput) -> FieldResult<GenreType> {
+        let session = get_store_from_ctx(ctx);
+        let taxonomy = Genre::taxonomy::<G>(session).await.map_err(|e| e.extend())?;
+        let mut genre = taxonomy.get(to_int(genre_id))
+            .cloned()
+            .ok_or_else(|| ServiceError::NotFound.extend())?;
+        if let Some(new_names) = patch.names {
+            genre.names = names(&new_names);
+        }
+        match patch.parent_id {
+            MaybeUndefined::Value(parent_id) => genre.parent_id = Some(to_int(parent_id)),
+            MaybeUndefined::Null => genre.parent_id = None,
+            MaybeUndefined::Undefined => (),
+        }
+        if let Some(aliases) = patch.aliases {
+            genre.aliases = non_empty(aliases);
+        }
+        if let Some(tmdb_ids) = patch.tmdb_ids {
+            genre.tmdb_ids = non_empty(tmdb_ids);
+        }
+        taxonomy.check(&genre).map_err(|e| e.extend())?;
+        let res = Genre::update_genre::<G>(genre, session).await.map_err(|e| e.extend())?;
+        Ok(GenreType::new(&res, DEFAULT_LOCALE))
+    }
+    /// Rewrites the genres of every movie and series to slugs and sends the rewritten rows to be
+    /// reindexed. Values that match no genre are listed in the report and their rows left as
+    /// they are. `dryRun` only fills in the report
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "migrateGenres")]
+    async fn migrate_genres(&self, ctx: &Context<'_>, dry_run: Option<bool>) -> FieldResult<GenreMigrationReport> {
+        let taxonomy = Genre::taxonomy::<G>(get_store_from_ctx(ctx)).await.map_err(|e| e.extend())?;
+        migrate_genres::<M, S>(&taxonomy, dry_run.unwrap_or(false), get_store_from_ctx(ctx), get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())
+    }
+}
@@ -0,0 +1,5 @@
+pub mod model;
+pub mod resolver;
+pub mod schema;
+pub mod source;
+pub mod worker;
@@ -0,0 +1,123 @@
+use async_graphql::Enum;
+use std::time::Duration;
+use chrono::Utc;
+use common_utils::{QueryResult, error::ServiceError};
+use scylla::macros::{FromRow, ValueList};
+use serde::{Deserialize, Serialize};
+use strum_macros::{EnumString, Display};
+use crate::generate_unique_id;
+use super::super::movies::schema::BulkStreamInsertData;
+use super::resolver::JobResolver;
+
+/// Only the first errors are kept on the job row, `failed` still counts every one
+pub const MAX_JOB_ERRORS: usize = 100;
+
+#[derive(Copy, Clone, Eq, Debug, PartialEq, Serialize, Deserialize, Enum, Display, EnumString)]
+#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+pub enum JobStatus { 
+    Pending,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus { 
+    /// A finished job is never picked up again, neither by a worker nor on restart
+    pub fn is_finished(&self) -> bool { 
+        matches!(self, Self::Completed | Self::Failed | Self::Cancelled)
+    }
+}
+
+// Columns after the primary key are in alphabetical order, the order `SELECT *` returns them in
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize, ValueList)]
+pub struct IngestionJob { 
+    pub job_id: i64,
+    /// Milliseconds since the epoch
+    pub created_at: i64,
+    /// Index in `movie_ids` of the next movie to import
+    pub cursor: i32,
+    pub errors: Option<Vec<String>>,
+    pub failed: i32,
+    /// Milliseconds since the epoch, until then only `lease_owner` works on the job
+    pub lease_expires_at: Option<i64>,
+    /// The worker running the job, renewed at every checkpoint
+    pub lease_owner: Option<String>,
+    /// Resolved from the source on the first run, a resumed job works through the same list
+    pub movie_ids: Option<Vec<i64>>,
+    pub processed: i32,
+    /// The `BulkStreamInsertData` the job was started with, as JSON
+    pub request: String,
+    pub status: String,
+    pub total: i32,
+    pub updated_at: i64,
+}
+
+impl IngestionJob { 
+    pub fn new(request: &BulkStreamInsertData) -> QueryResult<Self> { 
+        let now = Utc::now().timestamp_millis();
+        Ok(Self { 
+            job_id: generate_unique_id(),
+            created_at: now,
+            cursor: 0,
+            errors: None,
+            failed: 0,
+            lease_expires_at: None,
+            lease_owner: None,
+            movie_ids: None,
+            processed: 0,
+            request: serde_json::to_string(request).map_err(|e| ServiceError::ServerError(e.to_string()))?,
+            status: JobStatus::Pending.to_string(),
+            total: 0,
+            updated_at: now,
+        })
+    }
+    /// A row with an unknown status is treated as failed so it is not resumed
+    pub fn status(&self) -> JobStatus { 
+        self.status.parse().unwrap_or(JobStatus::Failed)
+    }
+    pub fn set_status(&mut self, status: JobStatus) { 
+        self.status = status.to_string();
+        self.updated_at = Utc::now().timestamp_millis();
+    }
+    pub fn request(&self) -> QueryResult<BulkStreamInsertData> { 
+        serde_json::from_str(&self.request).map_err(|e| ServiceError::ServerError(e.to_string()))
+    }
+    /// Whether a worker other than `owner` holds a lease on the job that hasn't run out at `now`
+    pub fn is_leased_elsewhere(&self, owner: &str, now: i64) -> bool { 
+        self.lease_owner.as_deref().map_or(false, |holder| holder != owner) 
+            && self.lease_expires_at.map_or(false, |expires_at| expires_at > now)
+    }
+    pub fn renew_lease(&mut self, owner: &str, lease: Duration) { 
+        self.lease_owner = Some(owner.to_string());
+        self.lease_expires_at = Some(Utc::now().timestamp_millis() + lease.as_millis() as i64);
+    }
+    pub fn record_error(&mut self, message: String) { 
+        self.failed += 1;
+        let errors = self.errors.get_or_insert_with(Vec::new);
+        if errors.len() < MAX_JOB_ERRORS { 
+            errors.push(message);
+        }
+    }
+}
+
+impl IngestionJob { 
+    pub async fn get_job<JobDatabase: JobResolver>(job_id: i64, session: &'static JobDatabase::Store) -> QueryResult<IngestionJob> {
+        JobDatabase::get_job(job_id, session).await
+    }
+    pub async fn get_jobs<JobDatabase: JobResolver>(session: &'static JobDatabase::Store) -> QueryResult<Vec<IngestionJob>> {
+        JobDatabase::get_jobs(session).await
+    }
+    pub async fn create_job<JobDatabase: JobResolver>(job: IngestionJob, session: &'static JobDatabase::Store) -> QueryResult<IngestionJob> {
+        JobDatabase::create_job(job, session).await
+    }
+    pub async fn claim_job<JobDatabase: JobResolver>(job: IngestionJob, expected: JobStatus, expected_owner: Option<String>, session: &'static JobDatabase::Store) -> QueryResult<bool> {
+        JobDatabase::claim_job(job, expected, expected_owner, session).await
+    }
+    pub async fn save_job<JobDatabase: JobResolver>(job: IngestionJob, expected: JobStatus, session: &'static JobDatabase::Store) -> QueryResult<bool> {
+        JobDatabase::save_job(job, expected, session).await
+    }
+    pub async fn cancel_job<JobDatabase: JobResolver>(job_id: i64, session: &'static JobDatabase::Store) -> QueryResult<bool> {
+        JobDatabase::cancel_job(job_id, session).await
+    }
+}
@@ -0,0 +1,119 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use common_utils::{QueryResult, error::ServiceError};
+use scylla::IntoTypedRows;
+use crate::db::{CachedSession, is_applied};
+use super::model::{IngestionJob, JobStatus};
+
+/// Jobs are checkpointed with a compare-and-set on their status, so a job cancelled from
+/// one instance is never flipped back to `RUNNING` by the worker of another. Workers claim a
+/// job with a compare-and-set on its lease, so two instances resuming it don't both run it
+#[async_trait]
+pub trait JobResolver: Send + Sync + 'static { 
+    type Store: Send + Sync + 'static;
+    async fn get_job(job_id: i64, session: &'static Self::Store) -> QueryResult<IngestionJob>;
+    async fn get_jobs(session: &'static Self::Store) -> QueryResult<Vec<IngestionJob>>;
+    async fn create_job(job: IngestionJob, session: &'static Self::Store) -> QueryResult<IngestionJob>;
+    /// Writes the status and lease of the job only while its stored status is still `expected` and
+    /// its lease is still held by `expected_owner`, returns whether it was written
+    async fn claim_job(job: IngestionJob, expected: JobStatus, expected_owner: Option<String>, session: &'static Self::Store) -> QueryResult<bool>;
+    /// Writes the job only while its stored status is still `expected` and its lease is still held
+    /// by the job's `lease_owner`, returns whether it was written
+    async fn save_job(job: IngestionJob, expected: JobStatus, session: &'static Self::Store) -> QueryResult<bool>;
+    /// Only touches the status, so progress checkpointed in the meantime is kept
+    async fn cancel_job(job_id: i64, session: &'static Self::Store) -> QueryResult<bool>;
+}
+
+#[derive(Default)]
+pub struct JobDatabase;
+
+static GET_JOB: &str = "SELECT * FROM movie_keyspace.ingestion_jobs WHERE job_id = ?;";
+static GET_JOBS: &str = "SELECT * FROM movie_keyspace.ingestion_jobs;";
+static INSERT_JOB: &str = "
+    INSERT INTO movie_keyspace.ingestion_jobs (
+        job_id, created_at, cursor, errors, failed, lease_expires_at, lease_owner, 
+        movie_ids, processed, request, status, total, updated_at
+    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?);
+";
+static CLAIM_JOB: &str = "
+    UPDATE movie_keyspace.ingestion_jobs 
+    SET lease_expires_at = ?, lease_owner = ?, status = ?, updated_at = ?
+    WHERE job_id = ? 
+    IF status = ? AND lease_owner = ?;
+";
+static SAVE_JOB: &str = "
+    UPDATE movie_keyspace.ingestion_jobs 
+    SET cursor = ?, errors = ?, failed = ?, lease_expires_at = ?, movie_ids = ?, processed = ?, status = ?, total = ?, updated_at = ?
+    WHERE job_id = ? 
+    IF status = ? AND lease_owner = ?;
+";
+static CANCEL_JOB: &str = "
+    UPDATE movie_keyspace.ingestion_jobs 
+    SET status = 'CANCELLED', updated_at = ?
+    WHERE job_id = ? 
+    IF status IN ('PENDING', 'RUNNING');
+";
+
+#[async_trait]
+impl JobResolver for JobDatabase { 
+    type Store = CachedSession;
+
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.ingestion_jobs"), err)]
+    async fn get_job(job_id: i64, session: &'static CachedSession) -> QueryResult<IngestionJob> {
+        session.query_prepared(GET_JOB, (job_id,))
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
+            .into_typed::<IngestionJob>()
+            .next()
+            .ok_or(ServiceError::NotFound)?
+            .map_err(|e| ServiceError::ServerError(e.to_string()))
+    }
+    /// Jobs are few and short lived, a full scan is fine for the admin listing and the restart sweep
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.ingestion_jobs"), err)]
+    async fn get_jobs(session: &'static CachedSession) -> QueryResult<Vec<IngestionJob>> {
+        session.query_prepared(GET_JOBS, ())
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
+            .into_typed::<IngestionJob>()
+            .map(|row| row.map_err(|e| ServiceError::ServerError(e.to_string())))
+            .collect()
+    }
+    #[tracing::instrument(skip(session, job), fields(repository = "movie_keyspace.ingestion_jobs"), err)]
+    async fn create_job(job: IngestionJob, session: &'static CachedSession) -> QueryResult<IngestionJob> {
+        session.query_prepared(INSERT_JOB, job.clone())
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?;
+        Ok(job)
+    }
+    #[tracing::instrument(skip(session, job), fields(repository = "movie_keyspace.ingestion_jobs", job_id = job.job_id), err)]
+    async fn claim_job(job: IngestionJob, expected: JobStatus, expected_owner: Option<String>, session: &'static CachedSession) -> QueryResult<bool> {
+        let values = (
+            job.lease_expires_at, job.lease_owner, job.status, job.updated_at, 
+            job.job_id, expected.to_string(), expected_owner,
+        );
+        session.query_prepared(CLAIM_JOB, values)
+            .await
+            .map(is_applied)
+            .map_err(|_| ServiceError::DatabaseError)
+    }
+    #[tracing::instrument(skip(session, job), fields(repository = "movie_keyspace.ingestion_jobs", job_id = job.job_id), err)]
+    async fn save_job(job: IngestionJob, expected: JobStatus, session: &'static CachedSession) -> QueryResult<bool> {
+        let values = (
+            job.cursor, job.errors, job.failed, job.lease_expires_at, job.movie_ids, job.processed, 
+            job.status, job.total, job.updated_at, job.job_id, expected.to_string(), job.lease_owner,
+        );
+        session.query_prepared(SAVE_JOB, values)
+            .await
+            .map(is_applied)
+            .map_err(|_| ServiceError::DatabaseError)
+    }
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.ingestion_jobs"), err)]
+    async fn cancel_job(job_id: i64, session: &'static CachedSession) -> QueryResult<bool> {
+        session.query_prepared(CANCEL_JOB, (Utc::now().timestamp_millis(), job_id))
+            .await
+            .map(is_applied)
+            .map_err(|_| ServiceError::DatabaseError)
+    }
+}
@@ -0,0 +1,123 @@
+use async_graphql::*;
+use chrono::{DateTime, TimeZone, Utc};
+use common_utils::error::ServiceError;
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+use crate::{graphql::{config::get_store_from_ctx, modules::types::{credits::resolver::{CreditDatabase, CreditResolver}, movies::{resolver::{MovieDatabase, MovieResolver}, schema::BulkStreamInsertData}, people_module::resolver::{PersonDatabase, PersonResolver}, genres::resolver::{GenreDatabase, GenreResolver}}}, to_bigint};
+use super::{model::{IngestionJob, JobStatus}, resolver::{JobDatabase, JobResolver}, source::SharedSource, worker::{progress, publish, spawn_job}};
+
+/// Job lookups go through `R`, `JobDatabase` outside of tests
+#[derive(Default)]
+pub struct IngestionJobQuery<R = JobDatabase>(PhantomData<R>);
+
+/// Jobs are stored through `R` and write their movies through `M`, their credits through `C` and
+/// the people credited through `P`. Genres are resolved through the taxonomy of `G`
+#[derive(Default)]
+pub struct IngestionJobMutation<R = JobDatabase, M = MovieDatabase, C = CreditDatabase, P = PersonDatabase, G = GenreDatabase>(PhantomData<(R, M, C, P, G)>);
+
+#[derive(Default)]
+pub struct IngestionJobSubscription<R = JobDatabase>(PhantomData<R>);
+
+#[derive(SimpleObject, Debug, Clone, Deserialize, Serialize)]
+pub struct ImportJobType {
+    pub job_id: ID,
+    pub status: JobStatus,
+    /// Movies the job will import, 0 until the list has been fetched
+    pub total: i32,
+    pub processed: i32,
+    pub failed: i32,
+    /// Movies done so far, imported or failed. A resumed job carries on from here
+    pub cursor: i32,
+    /// The first errors, as "<movie id>: <reason>"
+    pub errors: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<&IngestionJob> for ImportJobType {
+    fn from(f: &IngestionJob) -> Self {
+        Self {
+            job_id: f.job_id.into(),
+            status: f.status(),
+            total: f.total,
+            processed: f.processed,
+            failed: f.failed,
+            cursor: f.cursor,
+            errors: f.errors.clone().unwrap_or_default(),
+            created_at: Utc.timestamp_millis(f.created_at),
+            updated_at: Utc.timestamp_millis(f.updated_at),
+        }
+    }
+}
+
+#[Object]
+impl<R: JobResolver> IngestionJobQuery<R> {
+    /// Newest first, optionally only the jobs with the given status
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "importJobs")]
+    async fn import_jobs(&self, ctx: &Context<'_>, status: Option<JobStatus>) -> FieldResult<Vec<ImportJobType>> {
+        let mut jobs = IngestionJob::get_jobs::<R>(get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        jobs.retain(|job| status.is_none() || status == Some(job.status()));
+        jobs.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        Ok(jobs.iter().map(ImportJobType::from).collect())
+    }
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "importJob")]
+    async fn import_job(&self, ctx: &Context<'_>, job_id: ID) -> FieldResult<ImportJobType> {
+        let job = IngestionJob::get_job::<R>(to_bigint(job_id), get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        Ok(ImportJobType::from(&job))
+    }
+}
+
+#[Object]
+impl<R: JobResolver, M: MovieResolver, C: CreditResolver, P: PersonResolver, G: GenreResolver> IngestionJobMutation<R, M, C, P, G> {
+    /// Background replacement for `batchInsertData`. The job is recorded and returned straight
+    /// away, its progress can be followed with `importJobProgress` or polled with `importJob`
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "startImportJob")]
+    async fn start_import_job(&self, ctx: &Context<'_>, request: BulkStreamInsertData) -> FieldResult<ImportJobType> {
+        let jobs = get_store_from_ctx(ctx);
+        let job = IngestionJob::new(&request).map_err(|e| e.extend())?;
+        let job = IngestionJob::create_job::<R>(job, jobs)
+            .await
+            .map_err(|e| e.extend())?;
+        spawn_job::<R, M, C, P, G>(job.clone(), jobs, get_store_from_ctx(ctx), get_store_from_ctx(ctx), get_store_from_ctx(ctx), get_store_from_ctx(ctx), ctx.data::<SharedSource>()?.clone());
+        Ok(ImportJobType::from(&job))
+    }
+    /// The worker stops at its next checkpoint, movies imported until then are kept
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "cancelImportJob")]
+    async fn cancel_import_job(&self, ctx: &Context<'_>, job_id: ID) -> FieldResult<ImportJobType> {
+        let jobs = get_store_from_ctx(ctx);
+        let job_id = to_bigint(job_id);
+        let cancelled = IngestionJob::cancel_job::<R>(job_id, jobs)
+            .await
+            .map_err(|e| e.extend())?;
+        let job = IngestionJob::get_job::<R>(job_id, jobs)
+            .await
+            .map_err(|e| e.extend())?;
+        if !cancelled {
+            return Err(ServiceError::BadRequest(format!("Import job {} is already {}", job_id, job.status)).extend());
+        }
+        publish(&job);
+        Ok(ImportJobType::from(&job))
+    }
+}
+
+#[Subscription]
+impl<R: JobResolver> IngestionJobSubscription<R> {
+    /// The job as it is now, then every checkpoint until it completes, fails or is cancelled
+    #[graphql(name = "importJobProgress")]
+    async fn import_job_progress(&self, ctx: &Context<'_>, job_id: ID) -> FieldResult<impl Stream<Item = ImportJobType>> {
+        let session = get_store_from_ctx(ctx);
+        let job = IngestionJob::get_job::<R>(to_bigint(job_id), session)
+            .await
+            .map_err(|e| e.extend())?;
+        Ok(progress::<R>(job, session).map(|job| ImportJobType::from(&job)))
+    }
+}
@@ -0,0 +1,42 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use common_utils::QueryResult;
+use super::super::{credits::model::Credit, movies::{model::Movie, schema::BulkStreamInsertData}, tmdb_test::{discover_movie_ids, fetch_movie}};
+
+/// Where an import job gets its movies from. `TmdbSource` outside of tests, which can itself
+/// be pointed at a fixture server through `TMDB_URL`
+#[async_trait]
+pub trait CatalogSource: Send + Sync + 'static { 
+    /// Ids of every movie the job imports, in import order
+    async fn list_movie_ids(&self, request: &BulkStreamInsertData) -> QueryResult<Vec<i64>>;
+    /// The movie with its cast and crew. Its genres are TMDB genre ids, see `tmdb_genre_ids`
+    async fn fetch_movie(&self, movie_id: i64, language: Option<String>) -> QueryResult<(Movie, Vec<Credit>)>;
+}
+
+/// Kept in the schema data and cloned into every worker
+pub type SharedSource = Arc<dyn CatalogSource>;
+
+#[derive(Default)]
+pub struct TmdbSource;
+
+impl TmdbSource { 
+    pub fn shared() -> SharedSource { 
+        Arc::new(TmdbSource)
+    }
+}
+
+#[async_trait]
+impl CatalogSource for TmdbSource { 
+    async fn list_movie_ids(&self, request: &BulkStreamInsertData) -> QueryResult<Vec<i64>> {
+        discover_movie_ids(
+            &request.discover_api, 
+            &request.endpoint_popular, 
+            request.language.clone(), 
+            request.included_with.clone(), 
+            request.number_of_batch.unwrap_or(2).max(0) as usize
+        ).await
+    }
+    async fn fetch_movie(&self, movie_id: i64, language: Option<String>) -> QueryResult<(Movie, Vec<Credit>)> {
+        fetch_movie(movie_id, language).await
+    }
+}
@@ -0,0 +1,257 @@
+use std::{collections::{HashMap, HashSet}, time::Duration};
+use chrono::Utc;
+use common_utils::QueryResult;
+use futures::stream::{self, Stream};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use tokio::sync::broadcast::{self, error::RecvError};
+use super::super::credits::{model::Credit, resolver::CreditResolver};
+use super::super::people_module::{model::Person, resolver::PersonResolver};
+use super::super::genres::{model::{Genre, Taxonomy}, resolver::GenreResolver};
+use crate::generate_unique_id;
+use super::super::movies::{import::describe, model::Movie, resolver::MovieResolver};
+use super::{model::{IngestionJob, JobStatus}, resolver::JobResolver, source::SharedSource};
+
+const PROGRESS_CAPACITY: usize = 64;
+/// A subscriber with no news for this long re-reads the job, it may be running on another instance
+const PROGRESS_POLL: Duration = Duration::from_secs(5);
+
+/// Identifies this instance as the holder of a job lease
+static WORKER_ID: Lazy<String> = Lazy::new(|| generate_unique_id().to_string());
+/// How long a job stays with its worker after a checkpoint. A job whose worker stopped without
+/// finishing it is taken over by the next sweep of any instance once this has passed
+static JOB_LEASE: Lazy<Duration> = Lazy::new(|| std::env::var("IMPORT_JOB_LEASE_SECS")
+    .ok()
+    .and_then(|secs| secs.parse::<u64>().ok())
+    .map(Duration::from_secs)
+    .unwrap_or(Duration::from_secs(300)));
+/// How often each instance sweeps for jobs it can take over, see `spawn_job_sweeper`
+pub static JOB_SWEEP: Lazy<Duration> = Lazy::new(|| std::env::var("IMPORT_JOB_SWEEP_SECS")
+    .ok()
+    .and_then(|secs| secs.parse::<u64>().ok())
+    .map(Duration::from_secs)
+    .unwrap_or(Duration::from_secs(60)));
+
+/// Jobs with a task on this instance, a sweep leaves them to it
+static RUNNING: Lazy<Mutex<HashSet<i64>>> = Lazy::new(Default::default);
+
+/// Takes the job out of `RUNNING` when its task ends, however it ends
+struct RunningJob(i64);
+
+impl Drop for RunningJob {
+    fn drop(&mut self) {
+        RUNNING.lock().remove(&self.0);
+    }
+}
+
+/// Progress of the jobs seen by this instance, one channel per job
+static PROGRESS: Lazy<Mutex<HashMap<i64, broadcast::Sender<IngestionJob>>>> = Lazy::new(Default::default);
+
+fn subscribe(job_id: i64) -> broadcast::Receiver<IngestionJob> {
+    PROGRESS.lock()
+        .entry(job_id)
+        .or_insert_with(|| broadcast::channel(PROGRESS_CAPACITY).0)
+        .subscribe()
+}
+
+/// Sends the job to its subscribers, the channel is dropped once the job is finished
+pub fn publish(job: &IngestionJob) {
+    let mut channels = PROGRESS.lock();
+    let sender = if job.status().is_finished() {
+        channels.remove(&job.job_id)
+    } else {
+        channels.get(&job.job_id).cloned()
+    };
+    if let Some(sender) = sender {
+        // No receivers is not an error, nobody is watching this job
+        let _ = sender.send(job.clone());
+    }
+}
+
+/// Streams `job` and then every update to it, ending after the update that finishes it
+pub fn progress<J: JobResolver>(job: IngestionJob, session: &'static J::Store) -> impl Stream<Item = IngestionJob> {
+    let receiver = subscribe(job.job_id);
+    stream::unfold((Some(job), None, receiver), move |(next, last, mut receiver): (Option<IngestionJob>, Option<IngestionJob>, _)| async move {
+        if let Some(job) = next {
+            return Some((job.clone(), (None, Some(job), receiver)));
+        }
+        let last = last?;
+        if last.status().is_finished() {
+            PROGRESS.lock().remove(&last.job_id);
+            return None;
+        }
+        let job = loop {
+            match tokio::time::timeout(PROGRESS_POLL, receiver.recv()).await {
+                Ok(Ok(job)) => break job,
+                Ok(Err(RecvError::Lagged(_))) => continue,
+                // The worker dropped the channel, the final update is read from the store below
+                Ok(Err(RecvError::Closed)) => receiver = subscribe(last.job_id),
+                Err(_) => {}
+            }
+            match J::get_job(last.job_id, session).await {
+                Ok(job) if job.updated_at != last.updated_at => break job,
+                Ok(_) => continue,
+                Err(_) => return None,
+            }
+        };
+        Some((job.clone(), (None, Some(job), receiver)))
+    })
+}
+
+/// Runs the job in the background, the caller returns as soon as it is spawned. A job that
+/// stops on an error of its own, such as the database being unreachable, is marked failed.
+/// `false` when this instance already runs the job
+pub fn spawn_job<J: JobResolver, M: MovieResolver, C: CreditResolver, P: PersonResolver, G: GenreResolver>(job: IngestionJob, jobs: &'static J::Store, movies: &'static M::Store, credits: &'static C::Store, people: &'static P::Store, genres: &'static G::Store, source: SharedSource) -> bool {
+    if !RUNNING.lock().insert(job.job_id) {
+        return false;
+    }
+    tokio::spawn(async move {
+        let job_id = job.job_id;
+        let _running = RunningJob(job_id);
+        if let Err(e) = run_job::<J, M, C, P, G>(job, jobs, movies, credits, people, genres, source).await {
+            log::error!("Import job {} stopped: {}", job_id, describe(&e));
+            if let Err(e) = fail_job::<J>(job_id, describe(&e), jobs).await {
+                log::error!("Unable to mark import job {} as failed, it is resumed once its lease runs out: {}", job_id, describe(&e));
+            }
+        }
+    });
+    true
+}
+
+/// Picks up every unfinished job that no live worker holds, whether it was left behind by a stop
+/// of this instance or by a crash of another one. Any number of instances can do this at once,
+/// each job is run by the one that claims its lease. Returns how many were picked up
+pub async fn resume_jobs<J: JobResolver, M: MovieResolver, C: CreditResolver, P: PersonResolver, G: GenreResolver>(jobs: &'static J::Store, movies: &'static M::Store, credits: &'static C::Store, people: &'static P::Store, genres: &'static G::Store, source: SharedSource) -> QueryResult<usize> {
+    let now = Utc::now().timestamp_millis();
+    let resumed = IngestionJob::get_jobs::<J>(jobs)
+        .await?
+        .into_iter()
+        .filter(|job| !job.status().is_finished() && !job.is_leased_elsewhere(&WORKER_ID, now))
+        .map(|job| spawn_job::<J, M, C, P, G>(job, jobs, movies, credits, people, genres, source.clone()))
+        .filter(|spawned| *spawned)
+        .count();
+    Ok(resumed)
+}
+
+/// Resumes jobs now and then again every `every`, so a job whose worker died is taken over once
+/// its lease runs out rather than when an instance next starts. A sweep that fails is logged
+/// and tried again on the next tick
+pub fn spawn_job_sweeper<J: JobResolver, M: MovieResolver, C: CreditResolver, P: PersonResolver, G: GenreResolver>(jobs: &'static J::Store, movies: &'static M::Store, credits: &'static C::Store, people: &'static P::Store, genres: &'static G::Store, source: SharedSource, every: Duration) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(every);
+        loop {
+            interval.tick().await;
+            match resume_jobs::<J, M, C, P, G>(jobs, movies, credits, people, genres, source.clone()).await {
+                Ok(0) => {}
+                Ok(resumed) => log::info!("🔁 Resumed {} import jobs", resumed),
+                Err(e) => log::error!("Unable to sweep for import jobs, retrying on the next tick: {}", describe(&e)),
+            }
+        }
+    });
+}
+
+async fn run_job<J: JobResolver, M: MovieResolver, C: CreditResolver, P: PersonResolver, G: GenreResolver>(job: IngestionJob, jobs: &'static J::Store, movies: &'static M::Store, credits: &'static C::Store, people: &'static P::Store, genres: &'static G::Store, source: SharedSource) -> QueryResult<()> {
+    let mut job = match claim::<J>(job, jobs).await? {
+        Some(job) => job,
+        None => return Ok(()),
+    };
+    let taxonomy = Genre::taxonomy::<G>(genres).await?;
+    match import_remaining::<J, M, C, P>(&mut job, jobs, movies, credits, people, &taxonomy, &source).await {
+        Ok(true) => job.set_status(JobStatus::Completed),
+        // Cancelled, the mutation already published the final state
+        Ok(false) => return Ok(()),
+        Err(e) => {
+            job.record_error(describe(&e));
+            job.set_status(JobStatus::Failed);
+        }
+    }
+    if IngestionJob::save_job::<J>(job.clone(), JobStatus::Running, jobs).await? {
+        log::info!("🏁 Import job {} is {}, {} imported and {} failed", job.job_id, job.status, job.processed, job.failed);
+        publish(&job);
+    }
+    Ok(())
+}
+
+/// Takes the lease on the job and marks it running. `None` when the job is finished, when the
+/// lease of another worker hasn't run out yet or when another instance claimed it first
+async fn claim<J: JobResolver>(mut job: IngestionJob, jobs: &'static J::Store) -> QueryResult<Option<IngestionJob>> {
+    let expected = job.status();
+    if expected.is_finished() || job.is_leased_elsewhere(&WORKER_ID, Utc::now().timestamp_millis()) {
+        return Ok(None);
+    }
+    let expected_owner = job.lease_owner.clone();
+    job.renew_lease(&WORKER_ID, *JOB_LEASE);
+    job.set_status(JobStatus::Running);
+    if !IngestionJob::claim_job::<J>(job.clone(), expected, expected_owner, jobs).await? {
+        return Ok(None);
+    }
+    if expected == JobStatus::Running {
+        log::info!("🔁 Resuming import job {} at {}/{}", job.job_id, job.cursor, job.total);
+    }
+    publish(&job);
+    Ok(Some(job))
+}
+
+/// Marks the job failed, unless it finished or was taken over in the meantime
+async fn fail_job<J: JobResolver>(job_id: i64, reason: String, jobs: &'static J::Store) -> QueryResult<()> {
+    let mut job = IngestionJob::get_job::<J>(job_id, jobs).await?;
+    let expected = job.status();
+    if expected.is_finished() || job.lease_owner.as_deref() != Some(WORKER_ID.as_str()) {
+        return Ok(());
+    }
+    job.record_error(reason);
+    job.set_status(JobStatus::Failed);
+    if IngestionJob::save_job::<J>(job.clone(), expected, jobs).await? {
+        publish(&job);
+    }
+    Ok(())
+}
+
+/// Imports from the cursor on, checkpointing after every movie. Returns `false` once the job
+/// turns out to have been cancelled. A movie that fails is recorded and skipped, the job goes on
+async fn import_remaining<J: JobResolver, M: MovieResolver, C: CreditResolver, P: PersonResolver>(job: &mut IngestionJob, jobs: &'static J::Store, movies: &'static M::Store, credits: &'static C::Store, people: &'static P::Store, taxonomy: &Taxonomy, source: &SharedSource) -> QueryResult<bool> {
+    let request = job.request()?;
+    if job.movie_ids.is_none() {
+        let movie_ids = source.list_movie_ids(&request).await?;
+        job.total = movie_ids.len() as i32;
+        job.movie_ids = Some(movie_ids);
+        if !checkpoint::<J>(job, jobs).await? {
+            return Ok(false);
+        }
+    }
+    let movie_ids = job.movie_ids.clone().unwrap_or_default();
+    while let Some(&movie_id) = movie_ids.get(job.cursor as usize) {
+        match import_movie::<M, C, P>(movie_id, request.language.clone(), movies, credits, people, taxonomy, source).await {
+            Ok(_) => job.processed += 1,
+            Err(e) => job.record_error(format!("{}: {}", movie_id, describe(&e))),
+        }
+        job.cursor += 1;
+        if !checkpoint::<J>(job, jobs).await? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+async fn checkpoint<J: JobResolver>(job: &mut IngestionJob, jobs: &'static J::Store) -> QueryResult<bool> {
+    job.renew_lease(&WORKER_ID, *JOB_LEASE);
+    job.set_status(JobStatus::Running);
+    let saved = IngestionJob::save_job::<J>(job.clone(), JobStatus::Running, jobs).await?;
+    if saved {
+        publish(job);
+    }
+    Ok(saved)
+}
+
+/// Writing the same movie twice is harmless, a movie imported just before a crash is simply
+/// imported again when the job resumes from its last checkpoint. The people credited are found
+/// or created through their TMDB ids, so they are never created twice either. Genres are resolved
+/// through the taxonomy as it was when the job was claimed
+async fn import_movie<M: MovieResolver, C: CreditResolver, P: PersonResolver>(movie_id: i64, language: Option<String>, movies: &'static M::Store, credits: &'static C::Store, people: &'static P::Store, taxonomy: &Taxonomy, source: &SharedSource) -> QueryResult<Movie> {
+    let (movie, movie_credits) = source.fetch_movie(movie_id, language).await?;
+    let movie = movie.with_tmdb_genres(taxonomy);
+    let movie_credits = Person::resolve_tmdb_credits::<P>(movie_credits, people).await?;
+    Movie::stream_insert::<M>(vec![movie.clone()], movies).await?;
+    Credit::set_movie_credits::<C>(movie.movie_id, movie_credits, credits).await?;
+    Ok(movie)
+}
@@ -0,0 +1,134 @@
+use common_utils::{QueryResult, error::ServiceError, locale::normalise_locale, playback::{MediaOwner, normalise_checksum}};
+use scylla::macros::{FromRow, ValueList};
+use serde::{Deserialize, Serialize};
+use async_graphql::Enum;
+use strum_macros::{Display, EnumString};
+use super::resolver::MediaResolver;
+
+// Columns after the primary key are in alphabetical order, the order `SELECT *` returns them in
+/// One encode of the video of a movie or an episode
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize, ValueList, PartialEq)]
+pub struct Rendition {
+    pub owner_type: String,
+    pub owner_id: i64,
+    pub rendition_id: i64,
+    /// Average, in kbit/s
+    pub bitrate: i32,
+    pub checksum: String,
+    pub codec: String,
+    pub container: String,
+    pub height: i32,
+    pub location: String,
+    pub width: i32,
+}
+
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize, ValueList, PartialEq)]
+pub struct AudioTrack {
+    pub owner_type: String,
+    pub owner_id: i64,
+    pub track_id: i64,
+    /// Narrates what happens on screen for blind and partially sighted viewers
+    pub audio_description: bool,
+    pub bitrate: i32,
+    pub channel_layout: String,
+    pub checksum: String,
+    pub codec: String,
+    pub language: String,
+    pub location: String,
+}
+
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize, ValueList, PartialEq)]
+pub struct TextTrack {
+    pub owner_type: String,
+    pub owner_id: i64,
+    pub track_id: i64,
+    pub checksum: String,
+    pub format: String,
+    pub kind: String,
+    pub language: String,
+    pub location: String,
+}
+
+/// Everything that can be played of a movie or an episode
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MediaAsset {
+    pub renditions: Vec<Rendition>,
+    pub audio_tracks: Vec<AudioTrack>,
+    pub text_tracks: Vec<TextTrack>,
+}
+
+/// The table a track is kept in
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq, Display, EnumString, Serialize, Deserialize)]
+#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+pub enum TrackType {
+    Video,
+    Audio,
+    Text,
+}
+
+/// Where a file is stored, a URI such as `s3://bucket/key` or `https://cdn.example.com/key`
+fn storage_location(location: &str) -> QueryResult<String> {
+    let location = location.trim();
+    match location.split_once("://") {
+        Some((scheme, path)) if !scheme.is_empty() && scheme.chars().all(|c| c.is_ascii_alphanumeric()) && !path.is_empty() => Ok(location.to_string()),
+        _ => Err(ServiceError::BadRequest(format!("`{}` is not a storage URI like s3://bucket/key", location))),
+    }
+}
+
+impl Rendition {
+    /// Checks the resolution, bitrate, location and checksum, which is stored as `algorithm:digest`
+    pub fn validated(self) -> QueryResult<Self> {
+        if self.width <= 0 || self.height <= 0 || self.bitrate <= 0 {
+            return Err(ServiceError::BadRequest("A rendition needs a resolution and a bitrate".to_string()))
+        }
+        Ok(Self {
+            checksum: normalise_checksum(&self.checksum)?,
+            location: storage_location(&self.location)?,
+            ..self
+        })
+    }
+}
+
+impl AudioTrack {
+    /// Checks the bitrate, location and checksum and normalises the language, e.g. `pt-br` to `pt-BR`
+    pub fn validated(self) -> QueryResult<Self> {
+        if self.bitrate <= 0 {
+            return Err(ServiceError::BadRequest("An audio track needs a bitrate".to_string()))
+        }
+        Ok(Self {
+            checksum: normalise_checksum(&self.checksum)?,
+            language: normalise_locale(&self.language)?,
+            location: storage_location(&self.location)?,
+            ..self
+        })
+    }
+}
+
+impl TextTrack {
+    pub fn validated(self) -> QueryResult<Self> {
+        Ok(Self {
+            checksum: normalise_checksum(&self.checksum)?,
+            language: normalise_locale(&self.language)?,
+            location: storage_location(&self.location)?,
+            ..self
+        })
+    }
+}
+
+impl MediaAsset {
+    pub async fn get_media<MediaDatabase: MediaResolver>(owner: MediaOwner, owner_id: i64, session: &'static MediaDatabase::Store) -> QueryResult<MediaAsset> {
+        MediaDatabase::get_media(owner, owner_id, session).await
+    }
+    pub async fn save_rendition<MediaDatabase: MediaResolver>(rendition: Rendition, session: &'static MediaDatabase::Store) -> QueryResult<Rendition> {
+        MediaDatabase::save_rendition(rendition, session).await
+    }
+    pub async fn save_audio_track<MediaDatabase: MediaResolver>(track: AudioTrack, session: &'static MediaDatabase::Store) -> QueryResult<AudioTrack> {
+        MediaDatabase::save_audio_track(track, session).await
+    }
+    pub async fn save_text_track<MediaDatabase: MediaResolver>(track: TextTrack, session: &'static MediaDatabase::Store) -> QueryResult<TextTrack> {
+        MediaDatabase::save_text_track(track, session).await
+    }
+    pub async fn delete_track<MediaDatabase: MediaResolver>(track_type: TrackType, owner: MediaOwner, owner_id: i64, track_id: i64, session: &'static MediaDatabase::Store) -> QueryResult<bool> {
+        MediaDatabase::delete_track(track_type, owner, owner_id, track_id, session).await
+    }
+}
@@ -0,0 +1,111 @@
+use async_trait::async_trait;
+use common_utils::{QueryResult, error::ServiceError, playback::MediaOwner};
+use scylla::IntoTypedRows;
+use crate::db::{CachedSession, is_applied};
+use super::model::{AudioTrack, MediaAsset, Rendition, TextTrack, TrackType};
+
+/// Renditions and tracks are stored by owner, one table each, so a title's media is three partitions
+#[async_trait]
+pub trait MediaResolver: Send + Sync + 'static {
+    type Store: Send + Sync + 'static;
+    async fn get_media(owner: MediaOwner, owner_id: i64, session: &'static Self::Store) -> QueryResult<MediaAsset>;
+    async fn save_rendition(rendition: Rendition, session: &'static Self::Store) -> QueryResult<Rendition>;
+    async fn save_audio_track(track: AudioTrack, session: &'static Self::Store) -> QueryResult<AudioTrack>;
+    async fn save_text_track(track: TextTrack, session: &'static Self::Store) -> QueryResult<TextTrack>;
+    /// Not found when the owner has no such rendition or track
+    async fn delete_track(track_type: TrackType, owner: MediaOwner, owner_id: i64, track_id: i64, session: &'static Self::Store) -> QueryResult<bool>;
+}
+
+#[derive(Default)]
+pub struct MediaDatabase;
+
+static GET_RENDITIONS: &str = "SELECT * FROM movie_keyspace.media_renditions WHERE owner_type = ? AND owner_id = ?;";
+static GET_AUDIO_TRACKS: &str = "SELECT * FROM movie_keyspace.media_audio_tracks WHERE owner_type = ? AND owner_id = ?;";
+static GET_TEXT_TRACKS: &str = "SELECT * FROM movie_keyspace.media_text_tracks WHERE owner_type = ? AND owner_id = ?;";
+static INSERT_RENDITION: &str = "
+    INSERT INTO movie_keyspace.media_renditions (
+        owner_type, owner_id, rendition_id, bitrate, checksum, codec, container, height, location, width
+    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?);
+";
+static INSERT_AUDIO_TRACK: &str = "
+    INSERT INTO movie_keyspace.media_audio_tracks (
+        owner_type, owner_id, track_id, audio_description, bitrate, channel_layout, checksum, codec, language, location
+    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?);
+";
+static INSERT_TEXT_TRACK: &str = "
+    INSERT INTO movie_keyspace.media_text_tracks (
+        owner_type, owner_id, track_id, checksum, format, kind, language, location
+    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?);
+";
+static DELETE_RENDITION: &str = "DELETE FROM movie_keyspace.media_renditions WHERE owner_type = ? AND owner_id = ? AND rendition_id = ? IF EXISTS;";
+static DELETE_AUDIO_TRACK: &str = "DELETE FROM movie_keyspace.media_audio_tracks WHERE owner_type = ? AND owner_id = ? AND track_id = ? IF EXISTS;";
+static DELETE_TEXT_TRACK: &str = "DELETE FROM movie_keyspace.media_text_tracks WHERE owner_type = ? AND owner_id = ? AND track_id = ? IF EXISTS;";
+
+#[async_trait]
+impl MediaResolver for MediaDatabase {
+    type Store = CachedSession;
+
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.media_renditions"), err)]
+    async fn get_media(owner: MediaOwner, owner_id: i64, session: &'static CachedSession) -> QueryResult<MediaAsset> {
+        let key = (owner.to_string(), owner_id);
+        let renditions = session.query_prepared(GET_RENDITIONS, key.clone())
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
+            .into_typed::<Rendition>()
+            .map(|row| row.map_err(|e| ServiceError::ServerError(e.to_string())))
+            .collect::<QueryResult<Vec<_>>>()?;
+        let audio_tracks = session.query_prepared(GET_AUDIO_TRACKS, key.clone())
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
+            .into_typed::<AudioTrack>()
+            .map(|row| row.map_err(|e| ServiceError::ServerError(e.to_string())))
+            .collect::<QueryResult<Vec<_>>>()?;
+        let text_tracks = session.query_prepared(GET_TEXT_TRACKS, key)
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
+            .into_typed::<TextTrack>()
+            .map(|row| row.map_err(|e| ServiceError::ServerError(e.to_string())))
+            .collect::<QueryResult<Vec<_>>>()?;
+        Ok(MediaAsset { renditions, audio_tracks, text_tracks })
+    }
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.media_renditions"), err)]
+    async fn save_rendition(rendition: Rendition, session: &'static CachedSession) -> QueryResult<Rendition> {
+        session.query_prepared(INSERT_RENDITION, rendition.clone())
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?;
+        Ok(rendition)
+    }
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.media_audio_tracks"), err)]
+    async fn save_audio_track(track: AudioTrack, session: &'static CachedSession) -> QueryResult<AudioTrack> {
+        session.query_prepared(INSERT_AUDIO_TRACK, track.clone())
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?;
+        Ok(track)
+    }
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.media_text_tracks"), err)]
+    async fn save_text_track(track: TextTrack, session: &'static CachedSession) -> QueryResult<TextTrack> {
+        session.query_prepared(INSERT_TEXT_TRACK, track.clone())
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?;
+        Ok(track)
+    }
+    #[tracing::instrument(skip(session), err)]
+    async fn delete_track(track_type: TrackType, owner: MediaOwner, owner_id: i64, track_id: i64, session: &'static CachedSession) -> QueryResult<bool> {
+        let query = match track_type {
+            TrackType::Video => DELETE_RENDITION,
+            TrackType::Audio => DELETE_AUDIO_TRACK,
+            TrackType::Text => DELETE_TEXT_TRACK,
+        };
+        let applied = session.query_prepared(query, (owner.to_string(), owner_id, track_id))
+            .await
+            .map(is_applied)
+            .map_err(|_| ServiceError::DatabaseError)?;
+        match applied {
+            true => Ok(true),
+            false => Err(ServiceError::NotFound),
+        }
+    }
+}
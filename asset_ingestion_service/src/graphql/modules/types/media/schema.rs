@@ -0,0 +1,290 @@
+use std::marker::PhantomData;
+use async_graphql::*;
+use common_utils::{
+    QueryResult, error::ServiceError,
+    playback::{AudioCodec, ChannelLayout, Container, MediaOwner, TextTrackFormat, TextTrackKind, VideoCodec},
+};
+use serde::{Deserialize, Serialize};
+use crate::{graphql::config::get_store_from_ctx, generate_unique_id, to_bigint};
+use super::super::movies::{model::Movie, resolver::{MovieDatabase, MovieResolver}};
+use super::super::series::{model::Episode, resolver::{SeriesDatabase, SeriesResolver}};
+use super::model::{AudioTrack, MediaAsset, Rendition, TextTrack, TrackType};
+use super::resolver::{MediaDatabase, MediaResolver};
+
+/// Media is read through `R`, the movie or episode it belongs to through `M` and `S`
+#[derive(Default)]
+pub struct MediaQuery<R = MediaDatabase, M = MovieDatabase, S = SeriesDatabase>(PhantomData<(R, M, S)>);
+
+#[derive(Default)]
+pub struct MediaMutation<R = MediaDatabase, M = MovieDatabase, S = SeriesDatabase>(PhantomData<(R, M, S)>);
+
+/// A movie by `movieId`, or an episode by `seriesId`, `seasonNumber` and `episodeNumber`
+#[derive(InputObject, Debug, Clone, Deserialize, Serialize)]
+pub struct MediaOwnerInput {
+    pub movie_id: Option<ID>,
+    pub series_id: Option<ID>,
+    pub season_number: Option<i32>,
+    pub episode_number: Option<i32>,
+}
+
+#[derive(InputObject, Debug, Clone, Deserialize, Serialize)]
+pub struct RenditionInput {
+    pub codec: VideoCodec,
+    pub container: Container,
+    pub width: i32,
+    pub height: i32,
+    /// Average, in kbit/s
+    pub bitrate: i32,
+    /// URI of the file, e.g. `s3://bucket/key`
+    pub location: String,
+    /// `sha256:`, `sha1:` or `md5:` followed by the hex digest
+    pub checksum: String,
+}
+
+#[derive(InputObject, Debug, Clone, Deserialize, Serialize)]
+pub struct AudioTrackInput {
+    pub language: String,
+    pub codec: AudioCodec,
+    pub channel_layout: ChannelLayout,
+    #[graphql(default)]
+    pub audio_description: bool,
+    /// Average, in kbit/s
+    pub bitrate: i32,
+    pub location: String,
+    pub checksum: String,
+}
+
+#[derive(InputObject, Debug, Clone, Deserialize, Serialize)]
+pub struct TextTrackInput {
+    pub language: String,
+    pub kind: TextTrackKind,
+    pub format: TextTrackFormat,
+    pub location: String,
+    pub checksum: String,
+}
+
+#[derive(SimpleObject, Debug, Clone, Deserialize, Serialize)]
+pub struct RenditionType {
+    pub rendition_id: ID,
+    pub codec: VideoCodec,
+    pub container: Container,
+    pub width: i32,
+    pub height: i32,
+    pub bitrate: i32,
+    pub location: String,
+    pub checksum: String,
+}
+
+#[derive(SimpleObject, Debug, Clone, Deserialize, Serialize)]
+pub struct AudioTrackType {
+    pub track_id: ID,
+    pub language: String,
+    pub codec: AudioCodec,
+    pub channel_layout: ChannelLayout,
+    pub audio_description: bool,
+    pub bitrate: i32,
+    pub location: String,
+    pub checksum: String,
+}
+
+#[derive(SimpleObject, Debug, Clone, Deserialize, Serialize)]
+pub struct TextTrackType {
+    pub track_id: ID,
+    pub language: String,
+    pub kind: TextTrackKind,
+    pub format: TextTrackFormat,
+    pub location: String,
+    pub checksum: String,
+}
+
+#[derive(SimpleObject, Debug, Clone, Deserialize, Serialize)]
+pub struct MediaAssetType {
+    pub owner: MediaOwner,
+    /// The movie id, or the episode id of an episode
+    pub owner_id: ID,
+    pub renditions: Vec<RenditionType>,
+    pub audio_tracks: Vec<AudioTrackType>,
+    pub text_tracks: Vec<TextTrackType>,
+}
+
+// Stored values were written from these enums, so they always parse
+impl From<&Rendition> for RenditionType {
+    fn from(f: &Rendition) -> Self {
+        Self {
+            rendition_id: f.rendition_id.into(),
+            codec: f.codec.parse().unwrap_or(VideoCodec::H264),
+            container: f.container.parse().unwrap_or(Container::Mp4),
+            width: f.width,
+            height: f.height,
+            bitrate: f.bitrate,
+            location: f.location.clone(),
+            checksum: f.checksum.clone(),
+        }
+    }
+}
+
+impl From<&AudioTrack> for AudioTrackType {
+    fn from(f: &AudioTrack) -> Self {
+        Self {
+            track_id: f.track_id.into(),
+            language: f.language.clone(),
+            codec: f.codec.parse().unwrap_or(AudioCodec::Aac),
+            channel_layout: f.channel_layout.parse().unwrap_or(ChannelLayout::Stereo),
+            audio_description: f.audio_description,
+            bitrate: f.bitrate,
+            location: f.location.clone(),
+            checksum: f.checksum.clone(),
+        }
+    }
+}
+
+impl From<&TextTrack> for TextTrackType {
+    fn from(f: &TextTrack) -> Self {
+        Self {
+            track_id: f.track_id.into(),
+            language: f.language.clone(),
+            kind: f.kind.parse().unwrap_or(TextTrackKind::Subtitles),
+            format: f.format.parse().unwrap_or(TextTrackFormat::Webvtt),
+            location: f.location.clone(),
+            checksum: f.checksum.clone(),
+        }
+    }
+}
+
+impl MediaAssetType {
+    fn new(owner: MediaOwner, owner_id: i64, media: &MediaAsset) -> Self {
+        Self {
+            owner,
+            owner_id: owner_id.into(),
+            renditions: media.renditions.iter().map(RenditionType::from).collect(),
+            audio_tracks: media.audio_tracks.iter().map(AudioTrackType::from).collect(),
+            text_tracks: media.text_tracks.iter().map(TextTrackType::from).collect(),
+        }
+    }
+}
+
+/// The owner type and id media is stored under. Not found when the movie or episode doesn't exist
+pub async fn resolve_owner<M: MovieResolver, S: SeriesResolver>(owner: MediaOwnerInput, movies: &'static M::Store, series: &'static S::Store) -> QueryResult<(MediaOwner, i64)> {
+    match owner {
+        MediaOwnerInput { movie_id: Some(movie_id), series_id: None, season_number: None, episode_number: None } => {
+            let movie = Movie::get_movie_id::<M>(to_bigint(movie_id), movies).await?;
+            Ok((MediaOwner::Movie, movie.movie_id))
+        }
+        MediaOwnerInput { movie_id: None, series_id: Some(series_id), season_number: Some(season_number), episode_number: Some(episode_number) } => {
+            Episode::get_episodes::<S>(to_bigint(series_id), season_number, series)
+                .await?
+                .into_iter()
+                .find(|f| f.episode_number == episode_number)
+                .map(|f| (MediaOwner::Episode, f.episode_id))
+                .ok_or(ServiceError::NotFound)
+        }
+        _ => Err(ServiceError::BadRequest("Pass either movieId, or seriesId, seasonNumber and episodeNumber".to_string())),
+    }
+}
+
+#[Object]
+impl<R: MediaResolver, M: MovieResolver, S: SeriesResolver> MediaQuery<R, M, S> {
+    /// Every rendition and track registered for the movie or episode
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "mediaAsset")]
+    async fn media_asset(&self, ctx: &Context<'_>, owner: MediaOwnerInput) -> FieldResult<MediaAssetType> {
+        let (owner, owner_id) = resolve_owner::<M, S>(owner, get_store_from_ctx(ctx), get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        let media = MediaAsset::get_media::<R>(owner, owner_id, get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        Ok(MediaAssetType::new(owner, owner_id, &media))
+    }
+}
+
+#[Object]
+impl<R: MediaResolver, M: MovieResolver, S: SeriesResolver> MediaMutation<R, M, S> {
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "addRendition")]
+    async fn add_rendition(&self, ctx: &Context<'_>, owner: MediaOwnerInput, rendition: RenditionInput) -> FieldResult<RenditionType> {
+        let (owner, owner_id) = resolve_owner::<M, S>(owner, get_store_from_ctx(ctx), get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        let RenditionInput { codec, container, width, height, bitrate, location, checksum } = rendition;
+        let rendition = Rendition {
+            owner_type: owner.to_string(),
+            owner_id,
+            rendition_id: generate_unique_id(),
+            bitrate,
+            checksum,
+            codec: codec.to_string(),
+            container: container.to_string(),
+            height,
+            location,
+            width,
+        }
+        .validated()
+        .map_err(|e| e.extend())?;
+        let res = MediaAsset::save_rendition::<R>(rendition, get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        Ok(RenditionType::from(&res))
+    }
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "addAudioTrack")]
+    async fn add_audio_track(&self, ctx: &Context<'_>, owner: MediaOwnerInput, track: AudioTrackInput) -> FieldResult<AudioTrackType> {
+        let (owner, owner_id) = resolve_owner::<M, S>(owner, get_store_from_ctx(ctx), get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        let AudioTrackInput { language, codec, channel_layout, audio_description, bitrate, location, checksum } = track;
+        let track = AudioTrack {
+            owner_type: owner.to_string(),
+            owner_id,
+            track_id: generate_unique_id(),
+            audio_description,
+            bitrate,
+            channel_layout: channel_layout.to_string(),
+            checksum,
+            codec: codec.to_string(),
+            language,
+            location,
+        }
+        .validated()
+        .map_err(|e| e.extend())?;
+        let res = MediaAsset::save_audio_track::<R>(track, get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        Ok(AudioTrackType::from(&res))
+    }
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "addTextTrack")]
+    async fn add_text_track(&self, ctx: &Context<'_>, owner: MediaOwnerInput, track: TextTrackInput) -> FieldResult<TextTrackType> {
+        let (owner, owner_id) = resolve_owner::<M, S>(owner, get_store_from_ctx(ctx), get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        let TextTrackInput { language, kind, format, location, checksum } = track;
+        let track = TextTrack {
+            owner_type: owner.to_string(),
+            owner_id,
+            track_id: generate_unique_id(),
+            checksum,
+            format: format.to_string(),
+            kind: kind.to_string(),
+            language,
+            location,
+        }
+        .validated()
+        .map_err(|e| e.extend())?;
+        let res = MediaAsset::save_text_track::<R>(track, get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        Ok(TextTrackType::from(&res))
+    }
+    /// Removes the registration only, the file at its location is left alone
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "removeMediaTrack")]
+    async fn remove_media_track(&self, ctx: &Context<'_>, owner: MediaOwnerInput, track_type: TrackType, track_id: ID) -> FieldResult<bool> {
+        let (owner, owner_id) = resolve_owner::<M, S>(owner, get_store_from_ctx(ctx), get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        MediaAsset::delete_track::<R>(track_type, owner, owner_id, to_bigint(track_id), get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())
+    }
+}
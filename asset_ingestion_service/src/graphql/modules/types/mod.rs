@@ -1,8 +1,30 @@
 pub mod prod_company;
 pub mod people_module;
 pub mod movies;
+pub mod series;
+pub mod ingestion_jobs;
+pub mod credits;
+pub mod entity_resolution;
+pub mod genres;
+pub mod translations;
+pub mod availability;
+pub mod release;
+pub mod artwork;
+pub mod media;
+pub mod subtitles;
+pub mod outbox;
 pub mod tmdb_test;
 
 pub use prod_company::schema::{ProductionCompanyQuery, ProductionCompanyMutation};
 pub use movies::schema::{MovieMutation};
-pub use people_module::schema::{PersonMutation, PersonQuery};
\ No newline at end of file
+pub use people_module::schema::{PersonMutation, PersonQuery};
+pub use series::schema::SeriesMutation;
+pub use credits::schema::CreditMutation;
+pub use entity_resolution::schema::{EntityResolutionQuery, EntityResolutionMutation};
+pub use genres::schema::{GenreQuery, GenreMutation};
+pub use translations::schema::TranslationMutation;
+pub use availability::schema::AvailabilityMutation;
+pub use release::schema::{ReleaseQuery, ReleaseMutation};
+pub use artwork::schema::{ArtworkQuery, ArtworkMutation};
+pub use media::schema::{MediaQuery, MediaMutation};
+pub use ingestion_jobs::schema::{IngestionJobQuery, IngestionJobMutation, IngestionJobSubscription};
@@ -0,0 +1,334 @@
+//! Offline catalogue import, shared by the `importMovies` mutation and the `import_catalog` binary.
+//!
+//! A JSONL file holds one `NewMovieInput` per line, with snake_case keys and an `external_id`.
+//! A CSV file has the same columns flattened: list columns are separated by `|`, `business`
+//! and `rating` are spread over `business_budget`, `rating_imdb_id` and so on, and enum
+//! columns take the GraphQL names, e.g. `PG_13` or `IN_PRODUCTION`.
+use std::collections::HashMap;
+use std::io::{BufRead, Read};
+use std::str::FromStr;
+use async_graphql::{Enum, SimpleObject};
+use chrono::NaiveDate;
+use futures::StreamExt;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use common_utils::error::ServiceError;
+use super::super::genres::model::Taxonomy;
+use super::model::{Movie, NewMovie, Upserted, MediaType, MediaRated, Status};
+use super::resolver::MovieResolver;
+use super::schema::{NewMovieInput, BusinessDataInput, MovieRatingInput};
+
+lazy_static! {
+    /// Rows written to Scylla at the same time
+    pub static ref IMPORT_CONCURRENCY: usize = std::env::var("IMPORT_CONCURRENCY")
+        .ok()
+        .and_then(|concurrency| concurrency.parse::<usize>().ok())
+        .filter(|concurrency| *concurrency > 0)
+        .unwrap_or(8);
+}
+
+const LIST_SEPARATOR: char = '|';
+
+#[derive(Enum, Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub enum ImportFormat {
+    Jsonl,
+    Csv,
+}
+
+impl ImportFormat {
+    /// Guesses the format from a file name, `.jsonl`/`.ndjson` or `.csv`
+    pub fn from_filename(filename: &str) -> Option<Self> {
+        let extension = filename.rsplit_once('.')?.1.to_lowercase();
+        match extension.as_str() {
+            "jsonl" | "ndjson" => Some(Self::Jsonl),
+            "csv" => Some(Self::Csv),
+            _ => None,
+        }
+    }
+}
+
+/// A movie of the file, `external_id` is its id in the catalogue it came from
+#[derive(Debug, Clone, Deserialize)]
+pub struct ImportRow {
+    pub external_id: String,
+    #[serde(flatten)]
+    pub movie: NewMovieInput,
+}
+
+/// A CSV record before its lists, nested objects and enums are parsed
+#[derive(Debug, Deserialize)]
+struct CsvMovieRow {
+    external_id: String,
+    title: String,
+    year: Option<i32>,
+    awards: Option<String>,
+    business_budget: Option<i64>,
+    business_revenue: Option<i64>,
+    countries: Option<String>,
+    genres: Option<String>,
+    homepage: Option<String>,
+    keywords: Option<String>,
+    languages: Option<String>,
+    media_type: Option<String>,
+    movie_casts: Option<String>,
+    movie_company: Option<String>,
+    movie_director: Option<String>,
+    movie_writer: Option<String>,
+    overview: Option<String>,
+    poster: Option<String>,
+    rated: Option<String>,
+    rating_imdb_id: Option<String>,
+    rating_metascore: Option<i32>,
+    rating_popularity: Option<f32>,
+    rating_vote_count: Option<i64>,
+    rating_vote_average: Option<f32>,
+    release_date: Option<NaiveDate>,
+    runtime: Option<i64>,
+    status: Option<String>,
+    video_file: Option<String>,
+}
+
+fn list(column: Option<String>) -> Option<Vec<String>> {
+    column.map(|value| value
+        .split(LIST_SEPARATOR)
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+fn enum_column<T: FromStr>(name: &str, column: Option<String>) -> Result<Option<T>, String> {
+    match column.filter(|value| !value.trim().is_empty()) {
+        Some(value) => T::from_str(value.trim())
+            .map(Some)
+            .map_err(|_| format!("`{}` is not a valid {}", value, name)),
+        None => Ok(None),
+    }
+}
+
+impl TryFrom<CsvMovieRow> for ImportRow {
+    type Error = String;
+
+    fn try_from(f: CsvMovieRow) -> Result<Self, Self::Error> {
+        let business = match (f.business_budget, f.business_revenue) {
+            (None, None) => None,
+            (budget, revenue) => Some(BusinessDataInput { budget, revenue }),
+        };
+        let rating = MovieRatingInput {
+            imdb_id: f.rating_imdb_id,
+            metascore: f.rating_metascore,
+            popularity: f.rating_popularity,
+            vote_count: f.rating_vote_count,
+            vote_average: f.rating_vote_average,
+        };
+        let has_rating = rating.imdb_id.is_some()
+            || rating.metascore.is_some()
+            || rating.popularity.is_some()
+            || rating.vote_count.is_some()
+            || rating.vote_average.is_some();
+        Ok(Self {
+            external_id: f.external_id,
+            movie: NewMovieInput {
+                title: f.title,
+                year: f.year,
+                awards: list(f.awards),
+                business,
+                countries: list(f.countries),
+                genres: list(f.genres),
+                homepage: f.homepage,
+                keywords: list(f.keywords),
+                languages: list(f.languages),
+                media_type: enum_column::<MediaType>("media type", f.media_type)?,
+                movie_casts: list(f.movie_casts),
+                movie_company: list(f.movie_company),
+                movie_director: list(f.movie_director),
+                movie_writer: list(f.movie_writer),
+                overview: f.overview,
+                poster: f.poster,
+                rated: enum_column::<MediaRated>("rating", f.rated)?,
+                rating: if has_rating { Some(rating) } else { None },
+                release_date: f.release_date,
+                runtime: f.runtime,
+                status: enum_column::<Status>("status", f.status)?,
+                video_file: f.video_file,
+            },
+        })
+    }
+}
+
+/// Every row of the file with the line it starts on, rows that could not be parsed carry the reason
+pub type ParsedRows = Vec<(usize, Result<ImportRow, String>)>;
+
+pub fn parse_rows(format: ImportFormat, reader: impl Read) -> ParsedRows {
+    match format {
+        ImportFormat::Jsonl => parse_jsonl(std::io::BufReader::new(reader)),
+        ImportFormat::Csv => parse_csv(reader),
+    }
+}
+
+fn parse_jsonl(reader: impl BufRead) -> ParsedRows {
+    reader
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line))
+        .filter(|(_, line)| !matches!(line, Ok(line) if line.trim().is_empty()))
+        .map(|(line_number, line)| {
+            let row = line
+                .map_err(|e| e.to_string())
+                .and_then(|line| serde_json::from_str::<ImportRow>(&line).map_err(|e| e.to_string()));
+            (line_number, row)
+        })
+        .collect()
+}
+
+fn parse_csv(reader: impl Read) -> ParsedRows {
+    let mut csv_reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(reader);
+    let headers = match csv_reader.headers() {
+        Ok(headers) => headers.clone(),
+        Err(e) => return vec![(1, Err(format!("Unreadable header: {}", e)))],
+    };
+    csv_reader
+        .records()
+        .enumerate()
+        .map(|(index, record)| {
+            //  The header is line 1, a record spanning several lines reports where it starts
+            let line_number = record.as_ref().ok()
+                .and_then(|record| record.position())
+                .map_or(index + 2, |position| position.line() as usize);
+            let row = record
+                .map_err(|e| e.to_string())
+                .and_then(|record| record.deserialize::<CsvMovieRow>(Some(&headers)).map_err(|e| e.to_string()))
+                .and_then(ImportRow::try_from);
+            (line_number, row)
+        })
+        .collect()
+}
+
+fn check_items(field: &str, items: &Option<Vec<String>>, max_length: usize) -> Result<(), String> {
+    match items.iter().flatten().find(|item| item.chars().count() > max_length) {
+        Some(item) => Err(format!("`{}` entry `{}` is longer than {} characters", field, item, max_length)),
+        None => Ok(()),
+    }
+}
+
+/// The constraints `NewMovieInput` enforces through its GraphQL validators, which serde skips
+pub fn validate(row: &ImportRow) -> Result<(), String> {
+    let movie = &row.movie;
+    if row.external_id.trim().is_empty() {
+        return Err("`external_id` is empty".to_string())
+    }
+    if movie.title.trim().is_empty() {
+        return Err("`title` is empty".to_string())
+    }
+    if movie.title.chars().count() > 50 {
+        return Err("`title` is longer than 50 characters".to_string())
+    }
+    if movie.overview.as_ref().map_or(false, |overview| overview.chars().count() > 400) {
+        return Err("`overview` is longer than 400 characters".to_string())
+    }
+    if movie.runtime.map_or(false, |runtime| runtime < 0) {
+        return Err("`runtime` is negative".to_string())
+    }
+    if movie.year.map_or(false, |year| !(1870..=2100).contains(&year)) {
+        return Err("`year` is outside 1870-2100".to_string())
+    }
+    check_items("awards", &movie.awards, 60)?;
+    check_items("countries", &movie.countries, 50)?;
+    check_items("genres", &movie.genres, 200)?;
+    check_items("keywords", &movie.keywords, 200)?;
+    check_items("languages", &movie.languages, 200)?;
+    check_items("movie_casts", &movie.movie_casts, 400)?;
+    check_items("movie_company", &movie.movie_company, 400)?;
+    check_items("movie_writer", &movie.movie_writer, 100)?;
+    Ok(())
+}
+
+fn with_genres(mut row: ImportRow, taxonomy: &Taxonomy) -> Result<ImportRow, String> {
+    if let Some(genres) = row.movie.genres.as_ref() {
+        let slugs = taxonomy.canonicalise(genres).map_err(|e| describe(&e))?;
+        row.movie.genres = Some(slugs).filter(|slugs| !slugs.is_empty());
+    }
+    Ok(row)
+}
+
+#[derive(SimpleObject, Debug, Clone, Serialize)]
+pub struct RowError {
+    /// Line of the file the row starts on
+    pub line: i32,
+    /// `None` when the row could not be parsed far enough to read it
+    pub external_id: Option<String>,
+    pub message: String,
+}
+
+#[derive(SimpleObject, Debug, Clone, Default, Serialize)]
+pub struct ImportReport {
+    pub total: i32,
+    pub created: i32,
+    pub updated: i32,
+    pub failed: i32,
+    /// One entry per rejected or failed row, in file order
+    pub errors: Vec<RowError>,
+}
+
+impl ImportReport {
+    fn reject(&mut self, line: usize, external_id: Option<String>, message: String) {
+        self.failed += 1;
+        self.errors.push(RowError { line: line as i32, external_id, message });
+    }
+}
+
+/// `ServerError` only displays a generic message, the report should say what went wrong
+pub(crate) fn describe(error: &ServiceError) -> String {
+    match error {
+        ServiceError::ServerError(detail) => detail.clone(),
+        error => error.to_string(),
+    }
+}
+
+/// Validates every row and upserts the valid ones through `R` with at most `concurrency` writes
+/// in flight, each with its index event in the event outbox. Genres are stored as the slugs
+/// `taxonomy` resolves them to. A row that fails at any step is reported and never stops the
+/// rest of the file
+#[tracing::instrument(skip(rows, taxonomy, session), fields(rows = rows.len()))]
+pub async fn import_movies<R: MovieResolver>(rows: ParsedRows, taxonomy: &Taxonomy, session: &'static R::Store, concurrency: usize) -> ImportReport {
+    let mut report = ImportReport { total: rows.len() as i32, ..ImportReport::default() };
+    let mut first_seen: HashMap<String, usize> = HashMap::new();
+    let mut valid = Vec::new();
+    for (line, row) in rows {
+        let row = match row.and_then(|row| validate(&row).map(|_| row)).and_then(|row| with_genres(row, taxonomy)) {
+            Ok(row) => row,
+            Err(message) => {
+                report.reject(line, None, message);
+                continue
+            }
+        };
+        if let Some(first) = first_seen.get(&row.external_id) {
+            report.reject(line, Some(row.external_id), format!("Duplicate external id, first seen on line {}", first));
+            continue
+        }
+        first_seen.insert(row.external_id.clone(), line);
+        valid.push((line, row));
+    }
+
+    let written: Vec<(usize, String, _)> = futures::stream::iter(valid)
+        .map(|(line, row)| async move {
+            let result = Movie::upsert_by_external_id::<R>(row.external_id.clone(), NewMovie::from(&row.movie), session).await;
+            (line, row.external_id, result)
+        })
+        .buffer_unordered(concurrency.max(1))
+        .collect()
+        .await;
+
+    for (line, external_id, result) in written {
+        match result {
+            Ok(Upserted::Created(_)) => report.created += 1,
+            Ok(Upserted::Updated(_)) => report.updated += 1,
+            Err(e) => report.reject(line, Some(external_id), describe(&e)),
+        }
+    }
+    report.errors.sort_by_key(|error| error.line);
+    log::info!("📦 Imported {} rows: {} created, {} updated, {} failed", report.total, report.created, report.updated, report.failed);
+    report
+}
@@ -1,6 +1,4 @@
 pub mod model;
 pub mod resolver;
 pub mod schema;
-
-
-
+pub mod import;
\ No newline at end of file
@@ -1,8 +1,9 @@
 use std::collections::HashSet;
 use async_graphql::Enum;
 use async_graphql::*;
-use chrono::{NaiveDate, Utc, Date, Datelike};
+use chrono::{NaiveDate, TimeZone, Utc, Date, Datelike};
 use common_utils::QueryResult;
+use common_utils::events::PartitionKey;
 use scylla::macros::{FromRow, FromUserType, IntoUserType, ValueList};
 use serde::{Deserialize, Serialize};
 use scylla::cql_to_rust::FromCqlVal;
@@ -10,9 +11,9 @@ use strum_macros::{EnumString, Display};
 use crate::{generate_unique_id, to_int};
 use scylla::frame::value::{MaybeUnset, Unset};
 
-use crate::db::CachedSession;
 
-use super::schema::{NewMovieInput, BusinessDataInput, MovieRatingInput};
+use super::schema::{NewMovieInput, BusinessDataInput, MovieRatingInput, MoviePatchInput};
+use super::super::genres::model::{or_blank, resolve_tmdb_genres, Taxonomy};
 use super::{resolver::MovieResolver, schema::MovieType};
 // Define custom struct that matches User Defined Type created earlier
 // wrapping field in Option will gracefully handle null field values
@@ -87,6 +88,8 @@ pub struct Movie {
     pub awards: Vec<String>,
     pub business: BusinessData,
     pub countries: Vec<String>,
+    /// Epoch millis, set while the movie is soft deleted
+    pub deleted_at: Option<i64>,
     pub genres: Vec<String>,
     pub homepage: String,
     pub keywords: Vec<String>,
@@ -106,6 +109,12 @@ pub struct Movie {
     pub video_file: String,
 }
 
+impl PartitionKey for Movie { 
+    fn partition_key(&self) -> String {
+        self.movie_id.to_string()
+    }
+}
+
 
 // Repeating Code** but with a different name, it helps identify  
 // what would be an input type for generating the desired type  
@@ -137,6 +146,250 @@ pub struct NewMovie {
     pub video_file: String,
 }
 
+/// Full primary key of a `movies_object` row, as stored in `movies_by_id`
+#[derive(Debug, Clone, FromRow, ValueList)]
+pub struct MovieKey { 
+    pub movie_id: i64,
+    pub title: String,
+    pub year: i32,
+}
+
+/// Outcome of an upsert keyed on an external id
+#[derive(Debug, Clone)]
+pub enum Upserted { 
+    Created(Movie),
+    Updated(Movie),
+}
+
+/// The movie a `NewMovie` becomes once written, for events built before it can be read back
+impl From<&NewMovie> for Movie { 
+    fn from(f: &NewMovie) -> Self {
+        Self { 
+            movie_id: f.movie_id,
+            title: f.title.clone(),
+            year: f.year,
+            awards: f.awards.clone(),
+            business: f.business.clone(),
+            countries: f.countries.clone(),
+            deleted_at: None,
+            genres: f.genres.clone(),
+            homepage: f.homepage.clone(),
+            keywords: f.keywords.clone(),
+            languages: f.languages.clone(),
+            media_type: f.media_type.clone(),
+            movie_casts: f.movie_casts.clone(),
+            movie_company: f.movie_company.clone(),
+            movie_director: f.movie_director.clone(),
+            movie_writer: f.movie_writer.clone(),
+            overview: f.overview.clone(),
+            poster: f.poster.clone(),
+            rated: f.rated.clone(),
+            rating: f.rating.clone(),
+            release_date: f.release_date,
+            runtime: f.runtime,
+            status: f.status.clone(),
+            video_file: f.video_file.clone(),
+        }
+    }
+}
+
+impl From<&Movie> for MovieKey { 
+    fn from(f: &Movie) -> Self {
+        Self { 
+            movie_id: f.movie_id,
+            title: f.title.clone(),
+            year: f.year,
+        }
+    }
+}
+
+/// Field mask of a movie update, only the fields that are `Some` are written.
+/// `rating` and `business` are frozen UDTs, so they are replaced as a whole
+#[derive(Debug, Clone, Default)]
+pub struct MoviePatch { 
+    pub title: Option<String>,
+    pub year: Option<i32>,
+    pub awards: Option<Vec<String>>,
+    pub business: Option<BusinessData>,
+    pub countries: Option<Vec<String>>,
+    pub genres: Option<Vec<String>>,
+    pub homepage: Option<String>,
+    pub keywords: Option<Vec<String>>,
+    pub languages: Option<Vec<String>>,
+    pub media_type: Option<String>,
+    pub movie_casts: Option<Vec<String>>,
+    pub movie_company: Option<Vec<String>>,
+    pub movie_director: Option<Vec<String>>,
+    pub movie_writer: Option<Vec<String>>,
+    pub overview: Option<String>,
+    pub poster: Option<String>,
+    pub rated: Option<String>,
+    pub rating: Option<MovieRating>,
+    pub release_date: Option<NaiveDate>,
+    pub runtime: Option<i64>,
+    pub status: Option<String>,
+    pub video_file: Option<String>,
+}
+
+/// Bind values of `PATCH_MOVIE`, in the order of its placeholders.
+/// Unset columns are left untouched by Scylla instead of being written as tombstones
+#[derive(Debug, ValueList)]
+pub struct MoviePatchValues { 
+    pub awards: MaybeUnset<Vec<String>>,
+    pub business: MaybeUnset<BusinessData>,
+    pub countries: MaybeUnset<Vec<String>>,
+    pub genres: MaybeUnset<Vec<String>>,
+    pub homepage: MaybeUnset<String>,
+    pub keywords: MaybeUnset<Vec<String>>,
+    pub languages: MaybeUnset<Vec<String>>,
+    pub media_type: MaybeUnset<String>,
+    pub movie_casts: MaybeUnset<Vec<String>>,
+    pub movie_company: MaybeUnset<Vec<String>>,
+    pub movie_director: MaybeUnset<Vec<String>>,
+    pub movie_writer: MaybeUnset<Vec<String>>,
+    pub overview: MaybeUnset<String>,
+    pub poster: MaybeUnset<String>,
+    pub rated: MaybeUnset<String>,
+    pub rating: MaybeUnset<MovieRating>,
+    pub release_date: MaybeUnset<NaiveDate>,
+    pub runtime: MaybeUnset<i64>,
+    pub status: MaybeUnset<String>,
+    pub video_file: MaybeUnset<String>,
+    pub movie_id: i64,
+    pub title: String,
+    pub year: i32,
+}
+
+fn set_or_unset<T>(value: Option<T>) -> MaybeUnset<T> { 
+    value.map_or(MaybeUnset::Unset, MaybeUnset::Set)
+}
+
+impl MoviePatch { 
+    /// `title` and `year` are part of the primary key, changing either one moves the row
+    pub fn changes_key(&self, movie: &Movie) -> bool { 
+        self.title.as_ref().map_or(false, |title| *title != movie.title)
+            || self.year.map_or(false, |year| year != movie.year)
+    }
+    /// The movie as it reads once the patch is written
+    pub fn apply(&self, movie: &Movie) -> Movie { 
+        let patch = self.clone();
+        let movie = movie.clone();
+        Movie { 
+            movie_id: movie.movie_id,
+            title: patch.title.unwrap_or(movie.title),
+            year: patch.year.unwrap_or(movie.year),
+            awards: patch.awards.unwrap_or(movie.awards),
+            business: patch.business.unwrap_or(movie.business),
+            countries: patch.countries.unwrap_or(movie.countries),
+            deleted_at: movie.deleted_at,
+            genres: patch.genres.unwrap_or(movie.genres),
+            homepage: patch.homepage.unwrap_or(movie.homepage),
+            keywords: patch.keywords.unwrap_or(movie.keywords),
+            languages: patch.languages.unwrap_or(movie.languages),
+            media_type: patch.media_type.unwrap_or(movie.media_type),
+            movie_casts: patch.movie_casts.unwrap_or(movie.movie_casts),
+            movie_company: patch.movie_company.unwrap_or(movie.movie_company),
+            movie_director: patch.movie_director.unwrap_or(movie.movie_director),
+            movie_writer: patch.movie_writer.unwrap_or(movie.movie_writer),
+            overview: patch.overview.unwrap_or(movie.overview),
+            poster: patch.poster.unwrap_or(movie.poster),
+            rated: patch.rated.unwrap_or(movie.rated),
+            rating: patch.rating.unwrap_or(movie.rating),
+            release_date: patch.release_date.unwrap_or(movie.release_date),
+            runtime: patch.runtime.unwrap_or(movie.runtime),
+            status: patch.status.unwrap_or(movie.status),
+            video_file: patch.video_file.unwrap_or(movie.video_file),
+        }
+    }
+    /// Values for an in place update of `movie`, the key columns are taken from the current row
+    pub fn into_values(self, movie: &Movie) -> MoviePatchValues { 
+        MoviePatchValues { 
+            awards: set_or_unset(self.awards),
+            business: set_or_unset(self.business),
+            countries: set_or_unset(self.countries),
+            genres: set_or_unset(self.genres),
+            homepage: set_or_unset(self.homepage),
+            keywords: set_or_unset(self.keywords),
+            languages: set_or_unset(self.languages),
+            media_type: set_or_unset(self.media_type),
+            movie_casts: set_or_unset(self.movie_casts),
+            movie_company: set_or_unset(self.movie_company),
+            movie_director: set_or_unset(self.movie_director),
+            movie_writer: set_or_unset(self.movie_writer),
+            overview: set_or_unset(self.overview),
+            poster: set_or_unset(self.poster),
+            rated: set_or_unset(self.rated),
+            rating: set_or_unset(self.rating),
+            release_date: set_or_unset(self.release_date),
+            runtime: set_or_unset(self.runtime),
+            status: set_or_unset(self.status),
+            video_file: set_or_unset(self.video_file),
+            movie_id: movie.movie_id,
+            title: movie.title.clone(),
+            year: movie.year,
+        }
+    }
+}
+
+/// A full update is a patch that sets every field
+impl From<NewMovie> for MoviePatch { 
+    fn from(f: NewMovie) -> Self {
+        Self { 
+            title: Some(f.title),
+            year: Some(f.year),
+            awards: Some(f.awards),
+            business: Some(f.business),
+            countries: Some(f.countries),
+            genres: Some(f.genres),
+            homepage: Some(f.homepage),
+            keywords: Some(f.keywords),
+            languages: Some(f.languages),
+            media_type: Some(f.media_type),
+            movie_casts: Some(f.movie_casts),
+            movie_company: Some(f.movie_company),
+            movie_director: Some(f.movie_director),
+            movie_writer: Some(f.movie_writer),
+            overview: Some(f.overview),
+            poster: Some(f.poster),
+            rated: Some(f.rated),
+            rating: Some(f.rating),
+            release_date: Some(f.release_date),
+            runtime: Some(f.runtime),
+            status: Some(f.status),
+            video_file: Some(f.video_file),
+        }
+    }
+}
+
+impl From<&MoviePatchInput> for MoviePatch { 
+    fn from(f: &MoviePatchInput) -> Self {
+        Self { 
+            title: f.title.clone(),
+            year: f.year,
+            awards: f.awards.clone(),
+            business: f.business.as_ref().map(BusinessData::from),
+            countries: f.countries.clone(),
+            genres: f.genres.clone(),
+            homepage: f.homepage.clone(),
+            keywords: f.keywords.clone(),
+            languages: f.languages.clone(),
+            media_type: f.media_type.map(|media_type| media_type.to_string()),
+            movie_casts: f.movie_casts.clone(),
+            movie_company: f.movie_company.clone(),
+            movie_director: f.movie_director.clone(),
+            movie_writer: f.movie_writer.clone(),
+            overview: f.overview.clone(),
+            poster: f.poster.clone(),
+            rated: f.rated.map(|rated| rated.to_string()),
+            rating: f.rating.as_ref().map(MovieRating::from),
+            release_date: f.release_date,
+            runtime: f.runtime,
+            status: f.status.map(|status| status.to_string()),
+            video_file: f.video_file.clone(),
+        }
+    }
+}
+
 #[derive(Copy, Clone, Eq, Debug, PartialEq, Serialize, SmartDefault, Deserialize, Enum, Display, EnumString)]
 #[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
 pub enum Status { 
@@ -148,6 +401,41 @@ pub enum Status {
     PostProduction,
     Released,
     Canceled,
+    /// Taken out of the catalogue after its release, e.g. once its `unpublishAt` has passed
+    Withdrawn,
+}
+
+impl Status {
+    /// Whether an editor can move a movie from `self` to `next` without an override.
+    /// Production only moves forward, a released movie can be withdrawn and released again,
+    /// and a canceled one stays canceled
+    pub fn can_become(self, next: Status) -> bool {
+        use Status::*;
+        match (self, next) {
+            (current, next) if current == next => true,
+            (Rumoured, Planned | InProduction | PostProduction | Released | Canceled) => true,
+            (Planned, InProduction | PostProduction | Released | Canceled) => true,
+            (InProduction, PostProduction | Released | Canceled) => true,
+            (PostProduction, Released | Canceled) => true,
+            (Released, Withdrawn) | (Withdrawn, Released) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Movie {
+    /// A status that doesn't parse is read as the default, `Rumoured`
+    pub fn status(&self) -> Status {
+        self.status.parse().unwrap_or_default()
+    }
+    pub fn is_deleted(&self) -> bool {
+        self.deleted_at.is_some()
+    }
+    /// The movie as fetched from TMDB, with the genre ids it came with resolved to slugs
+    pub fn with_tmdb_genres(self, taxonomy: &Taxonomy) -> Movie {
+        let genres = or_blank(resolve_tmdb_genres(taxonomy, &self.title, &self.genres));
+        Movie { genres, ..self }
+    }
 }
 #[derive(Copy, Clone, Eq, Debug, PartialEq, Serialize, SmartDefault, Deserialize, Enum, Display, EnumString)]
 #[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
@@ -187,7 +475,8 @@ impl From<&Movie> for MovieType {
             runtime: f.runtime.clone() ,
             status: f.status.clone() ,
             video_file: f.video_file.clone(),
-            
+            is_deleted: f.is_deleted(),
+            deleted_at: f.deleted_at.map(|at| Utc.timestamp_millis(at)),
         }
     }
 }
@@ -249,29 +538,57 @@ pub enum MediaRated {
 
 impl Movie { 
     #[tracing::instrument(skip(session))]
-    pub async fn get_movie_id<MovieDatabase: MovieResolver>(id: i64, session: &'static CachedSession) -> QueryResult<Movie> {
+    pub async fn get_movie_id<MovieDatabase: MovieResolver>(id: i64, session: &'static MovieDatabase::Store) -> QueryResult<Movie> {
         MovieDatabase::get_movie_id(id, session).await
     }
     #[tracing::instrument(skip(session))]
-    pub async fn create_movie<MovieDatabase: MovieResolver>(new_movie: NewMovie, session: &'static CachedSession) -> QueryResult<Movie> {
+    pub async fn get_movies<MovieDatabase: MovieResolver>(session: &'static MovieDatabase::Store) -> QueryResult<Vec<Movie>> {
+        MovieDatabase::get_movies(session).await
+    }
+    #[tracing::instrument(skip(session, movies))]
+    pub async fn request_reindex<MovieDatabase: MovieResolver>(movies: Vec<Movie>, session: &'static MovieDatabase::Store) -> QueryResult<()> {
+        MovieDatabase::request_reindex(movies, session).await
+    }
+    #[tracing::instrument(skip(session))]
+    pub async fn create_movie<MovieDatabase: MovieResolver>(new_movie: NewMovie, session: &'static MovieDatabase::Store) -> QueryResult<Movie> {
         MovieDatabase::create_movie(new_movie, session).await
     }
     #[tracing::instrument(skip(session))]
-    pub async fn update_movie<MovieDatabase: MovieResolver>(id: i64, new_movie: NewMovie, session: &'static CachedSession) -> QueryResult<Movie> {
+    pub async fn update_movie<MovieDatabase: MovieResolver>(id: i64, new_movie: NewMovie, session: &'static MovieDatabase::Store) -> QueryResult<Movie> {
         MovieDatabase::update_movie(id, new_movie, session).await
     }
     #[tracing::instrument(skip(session))]
-    pub async fn delete_movie<MovieDatabase: MovieResolver>(id: i64, title: String, session: &'static CachedSession) -> QueryResult<bool> {
-        MovieDatabase::delete_movie(id, title, session).await
+    pub async fn patch_movie<MovieDatabase: MovieResolver>(id: i64, patch: MoviePatch, session: &'static MovieDatabase::Store) -> QueryResult<Movie> {
+        MovieDatabase::patch_movie(id, patch, session).await
+    }
+    #[tracing::instrument(skip(session))]
+    pub async fn delete_movie<MovieDatabase: MovieResolver>(id: i64, session: &'static MovieDatabase::Store) -> QueryResult<bool> {
+        MovieDatabase::delete_movie(id, session).await
+    }
+    #[tracing::instrument(skip(session))]
+    pub async fn set_deleted<MovieDatabase: MovieResolver>(id: i64, deleted_at: Option<i64>, session: &'static MovieDatabase::Store) -> QueryResult<Movie> {
+        MovieDatabase::set_deleted(id, deleted_at, session).await
+    }
+    #[tracing::instrument(skip(session, movie))]
+    pub async fn set_genres<MovieDatabase: MovieResolver>(movie: Movie, genres: Vec<String>, session: &'static MovieDatabase::Store) -> QueryResult<Movie> {
+        MovieDatabase::set_genres(movie, genres, session).await
     }
     #[tracing::instrument(skip(session))]
-    pub async fn bulk_insert<MovieDatabase: MovieResolver>(movie: Vec<Movie>, session: &'static CachedSession) -> QueryResult<bool> {
+    pub async fn bulk_insert<MovieDatabase: MovieResolver>(movie: Vec<Movie>, session: &'static MovieDatabase::Store) -> QueryResult<bool> {
         MovieDatabase::bulk_insert(movie, session).await
     }
     #[tracing::instrument(skip(session))]
-    pub async fn stream_insert<MovieDatabase: MovieResolver>(movie: Vec<Movie>, session: &'static CachedSession) -> QueryResult<bool> { 
+    pub async fn stream_insert<MovieDatabase: MovieResolver>(movie: Vec<Movie>, session: &'static MovieDatabase::Store) -> QueryResult<bool> { 
         MovieDatabase::stream_insert(movie, session).await
     }
+    #[tracing::instrument(skip(session, new_movie))]
+    pub async fn upsert_by_external_id<MovieDatabase: MovieResolver>(external_id: String, new_movie: NewMovie, session: &'static MovieDatabase::Store) -> QueryResult<Upserted> { 
+        MovieDatabase::upsert_by_external_id(external_id, new_movie, session).await
+    }
+    #[tracing::instrument(skip(session))]
+    pub async fn backfill_movie_keys<MovieDatabase: MovieResolver>(session: &'static MovieDatabase::Store) -> QueryResult<i32> { 
+        MovieDatabase::backfill_movie_keys(session).await
+    }
 
 
 }
\ No newline at end of file
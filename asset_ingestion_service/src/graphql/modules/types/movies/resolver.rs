@@ -1,170 +1,313 @@
 use async_trait::async_trait;
-use common_utils::QueryResult;
-use scylla::{IntoTypedRows, prepared_statement::PreparedStatement};
-use crate::db::CachedSession;
-use super::model::{NewMovie, Movie}; 
-use scylla::batch::Batch;
-use futures::StreamExt;
+use common_utils::{QueryResult, error::ServiceError, events::CatalogEvent};
+use scylla::IntoTypedRows;
+use scylla::frame::value::SerializedValues;
+use crate::db::{CachedSession, bind, is_applied, read_all_pages, write_logged_batch};
+use super::super::outbox::{relay::wake_relay, resolver::{enqueue, write_events}};
+use super::model::{NewMovie, Movie, MovieKey, MoviePatch, Upserted}; 
+use futures::{StreamExt, TryStreamExt};
 
+/// `Store` is the storage handle every call goes through, the Scylla `CachedSession` for `MovieDatabase`.
+/// Writes publish their `CatalogEvent` themselves, through the event outbox
 #[async_trait]
-pub trait MovieResolver { 
-    async fn get_movie_id(id: i64, session: &'static CachedSession) -> QueryResult<Movie>;
-    async fn create_movie(new_movie: NewMovie, session: &'static CachedSession) -> QueryResult<Movie>;
-    async fn update_movie(id: i64, new_movie: NewMovie, session: &'static CachedSession) -> QueryResult<Movie>;
-    async fn delete_movie(id: i64, title: String, session: &'static CachedSession) -> QueryResult<bool>;
-    async fn bulk_insert(movie: Vec<Movie>, session: &'static CachedSession) -> QueryResult<bool>; 
-    async fn stream_insert(movie: Vec<Movie>, session: &'static CachedSession) -> QueryResult<bool>;
+pub trait MovieResolver: Send + Sync + 'static { 
+    type Store: Send + Sync + 'static;
+    async fn get_movie_id(id: i64, session: &'static Self::Store) -> QueryResult<Movie>;
+    /// Every movie in the catalogue, for one-off rewrites such as the genre migration
+    async fn get_movies(session: &'static Self::Store) -> QueryResult<Vec<Movie>>;
+    async fn create_movie(new_movie: NewMovie, session: &'static Self::Store) -> QueryResult<Movie>;
+    async fn update_movie(id: i64, new_movie: NewMovie, session: &'static Self::Store) -> QueryResult<Movie>;
+    async fn patch_movie(id: i64, patch: MoviePatch, session: &'static Self::Store) -> QueryResult<Movie>;
+    /// Looks the row up by id, `false` when there is no such movie
+    async fn delete_movie(id: i64, session: &'static Self::Store) -> QueryResult<bool>;
+    /// Soft deletes the movie when `deleted_at` is set, restores it when it isn't
+    async fn set_deleted(id: i64, deleted_at: Option<i64>, session: &'static Self::Store) -> QueryResult<Movie>;
+    /// Rewrites the genres of `movie` as it was read, addressing the row by its full primary key
+    /// so movies without a `movies_by_id` entry are rewritten too
+    async fn set_genres(movie: Movie, genres: Vec<String>, session: &'static Self::Store) -> QueryResult<Movie>;
+    /// Writes each movie in its own logged batch, a few at a time. A failure leaves the movies
+    /// already written in place
+    async fn bulk_insert(movie: Vec<Movie>, session: &'static Self::Store) -> QueryResult<bool>; 
+    async fn stream_insert(movie: Vec<Movie>, session: &'static Self::Store) -> QueryResult<bool>;
+    /// Creates the movie the first time `external_id` is seen and updates that same movie afterwards
+    async fn upsert_by_external_id(external_id: String, new_movie: NewMovie, session: &'static Self::Store) -> QueryResult<Upserted>;
+    /// Has the search index rebuild the documents of `movies` from the rows as they are now
+    async fn request_reindex(movies: Vec<Movie>, session: &'static Self::Store) -> QueryResult<()>;
+    /// Writes the `movies_by_id` entry of every movie stored before that table existed, returns
+    /// how many were missing. Safe to run again, and while movies are being written
+    async fn backfill_movie_keys(session: &'static Self::Store) -> QueryResult<i32>;
 }
 
+#[derive(Default)]
 pub struct MovieDatabase;
 
+/// Movies `bulk_insert` writes at once. One logged batch for all of them would go over Scylla's
+/// `batch_size_fail_threshold` after a few dozen
+const BULK_INSERT_CONCURRENCY: usize = 16;
+
 static CREATE_MOVIE: &str = "
     INSERT INTO movie_keyspace.movies_object (
-        movie_id, title, year, awards, business, countries, genres, homepage, 
+        movie_id, title, year, awards, business, countries, deleted_at, genres, homepage, 
         keywords, languages, media_type, movie_casts, movie_company, movie_director, 
         movie_writer, overview, poster, rated, rating, release_date, runtime, status, 
         video_file 
-    ) VALUES ( ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?);
+    ) VALUES ( ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?);
 ";
-static DELETE_MOVIE: &str = "DELETE FROM movie_keyspace.movies_object WHERE movie_id = ? AND title = ?;";
-static UPDATE_MOVIE: &str = "UPDATE movie_keyspace.movies_object SET 
-        title = new_title, 
-        year = new_year, 
-        awards = new_award, 
-        business = new_business, 
-        countries = new_couontries, 
-        genres = new_genres, 
-        homepage = new_homepage, 
-        keywords = new_keywords, 
-        languages = new_languages, 
-        media_type = new_media_type, 
-        movie_casts = movie_casts, 
-        movie_company = new_company, 
-        movie_director = new_movie_director, 
-        movie_writer = new_movie_writer, 
-        overview = new_overview, 
-        poster = new_poster, 
-        rated = new_rated, 
-        rating = new_rating, 
-        release_date = new_release_date, 
-        runtime = new_runtime, 
-        status = new_status, 
-        video_file = new_video_file
-    WHERE movie_id = ?
-        AND title <> new_title,
-        AND year <> new_year, 
-        AND awards <> new_award, 
-        AND business <> new_business, 
-        AND countries <> new_couontries, 
-        AND genres <> new_genres, 
-        AND homepage <> new_homepage, 
-        AND keywords <> new_keywords, 
-        AND languages <> new_languages, 
-        AND media_type <> new_media_type, 
-        AND movie_casts <> movie_casts, 
-        AND movie_company <> new_company, 
-        AND movie_director <> new_movie_director, 
-        AND movie_writer <> new_movie_writer, 
-        AND overview <> new_overview, 
-        AND poster <> new_poster, 
-        AND rated <> new_rated, 
-        AND rating <> new_rating, 
-        AND release_date <> new_release_date, 
-        AND runtime <> new_runtime, 
-        AND status <> new_status, 
-        AND video_file <> new_video_file;
+static GET_MOVIES: &str = "SELECT * FROM movie_keyspace.movies_object;";
+static GET_MOVIE: &str = "SELECT * FROM movie_keyspace.movies_object WHERE movie_id = ? AND title = ? AND year = ?;";
+// `movies_by_id` is written in the same logged batch as every `movies_object` write
+static GET_MOVIE_KEY: &str = "SELECT movie_id, title, year FROM movie_keyspace.movies_by_id WHERE movie_id = ?;";
+static INSERT_MOVIE_KEY: &str = "INSERT INTO movie_keyspace.movies_by_id (movie_id, title, year) VALUES (?, ?, ?);";
+// Movies written before `movies_by_id` existed have no entry until `backfill_movie_keys` has run,
+// they are found by scanning for the id as they were before
+static GET_LEGACY_MOVIE: &str = "SELECT * FROM movie_keyspace.movies_object WHERE movie_id = ? ALLOW FILTERING;";
+static GET_MOVIE_KEYS: &str = "SELECT movie_id, title, year, deleted_at FROM movie_keyspace.movies_object;";
+// A rename that lands while the backfill runs has already written the new key, which is kept
+static BACKFILL_MOVIE_KEY: &str = "INSERT INTO movie_keyspace.movies_by_id (movie_id, title, year, deleted_at) VALUES (?, ?, ?, ?) IF NOT EXISTS;";
+static DELETE_MOVIE_KEY: &str = "DELETE FROM movie_keyspace.movies_by_id WHERE movie_id = ?;";
+static DELETE_MOVIE_ROW: &str = "DELETE FROM movie_keyspace.movies_object WHERE movie_id = ? AND title = ? AND year = ?;";
+// A soft delete only sets `deleted_at`, on the movie and on its lookup entry. The entry is written
+// whole, a movie from before `movies_by_id` would otherwise get one without its key
+static SET_DELETED: &str = "UPDATE movie_keyspace.movies_object SET deleted_at = ? WHERE movie_id = ? AND title = ? AND year = ?;";
+static SET_KEY_DELETED: &str = "INSERT INTO movie_keyspace.movies_by_id (movie_id, title, year, deleted_at) VALUES (?, ?, ?, ?);";
+static SET_GENRES: &str = "UPDATE movie_keyspace.movies_object SET genres = ? WHERE movie_id = ? AND title = ? AND year = ?;";
+static GET_EXTERNAL_ID: &str = "SELECT movie_id FROM movie_keyspace.movies_by_external_id WHERE external_id = ?;";
+static INSERT_EXTERNAL_ID: &str = "INSERT INTO movie_keyspace.movies_by_external_id (external_id, movie_id) VALUES (?, ?);";
+// Only non key columns can be SET, `title` and `year` are moved with a delete and an insert
+static PATCH_MOVIE: &str = "UPDATE movie_keyspace.movies_object SET 
+        awards = ?, 
+        business = ?, 
+        countries = ?, 
+        genres = ?, 
+        homepage = ?, 
+        keywords = ?, 
+        languages = ?, 
+        media_type = ?, 
+        movie_casts = ?, 
+        movie_company = ?, 
+        movie_director = ?, 
+        movie_writer = ?, 
+        overview = ?, 
+        poster = ?, 
+        rated = ?, 
+        rating = ?, 
+        release_date = ?, 
+        runtime = ?, 
+        status = ?, 
+        video_file = ?
+    WHERE movie_id = ? AND title = ? AND year = ?;
 ";
 
+/// The movie row and its lookup entry
+fn insert_statements(movie: &Movie) -> QueryResult<Vec<(&'static str, SerializedValues)>> { 
+    Ok(vec![
+        (CREATE_MOVIE, bind(movie.clone())?),
+        (INSERT_MOVIE_KEY, bind(MovieKey::from(movie))?),
+    ])
+}
+
+/// The full key of movie `id` from `movies_by_id`, `None` when it has no entry
+async fn get_movie_key(id: i64, session: &'static CachedSession) -> QueryResult<Option<MovieKey>> { 
+    session.query_prepared(GET_MOVIE_KEY, (id,))
+        .await
+        .map_err(|_| ServiceError::DatabaseError)?
+        .rows_or_empty()
+        .into_typed::<MovieKey>()
+        .next()
+        .transpose()
+        .map_err(|e| ServiceError::ServerError(e.to_string()))
+}
+
+/// `insert_statements` with the `MovieCreated` event of the movie
+fn create_statements(movie: &Movie) -> QueryResult<Vec<(&'static str, SerializedValues)>> { 
+    let mut statements = insert_statements(movie)?;
+    statements.push(enqueue(CatalogEvent::MovieCreated(movie.clone()))?);
+    Ok(statements)
+}
+
 #[async_trait]
 impl MovieResolver for MovieDatabase { 
-    /// Gets a movie using the Movie id 
+    type Store = CachedSession;
+
+    /// Gets a movie using the Movie id, resolving the rest of its primary key through `movies_by_id`.
+    /// Movies without an entry there yet are scanned for
     #[tracing::instrument(skip(session), fields(repository = "asset_ingestion.movies_object"))]
     async fn get_movie_id(id: i64, session: &'static CachedSession) -> QueryResult<Movie> {
-        let response = session.query_prepared("SELECT * FROM movie_keyspace.movies_object WHERE movie_id = ? ALLOW FILTERING;", (id,))
+        let (query, values) = match get_movie_key(id, session).await? { 
+            Some(key) => (GET_MOVIE, bind(key)?),
+            None => (GET_LEGACY_MOVIE, bind((id,))?),
+        };
+        let res = session.query_prepared(query, values)
             .await
-            .expect("")
-            .rows
-            .unwrap_or_default();
-        log::info!("RESPONSE 😂😂 {:#?}", response);
-        let res = response 
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
             .into_typed::<Movie>()
             .next()
-            .expect("")
-            .expect("");
+            .ok_or(ServiceError::NotFound)?
+            .map_err(|e| ServiceError::ServerError(e.to_string()))?;
         Ok(res)
     }
     #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.movies_object"), err)]
+    async fn get_movies(session: &'static CachedSession) -> QueryResult<Vec<Movie>> {
+        read_all_pages::<Movie>(GET_MOVIES, session).await
+    }
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.movies_object"), err)]
     async fn create_movie(new_movie: NewMovie, session: &'static CachedSession) -> QueryResult<Movie> {
         log::info!("ENTERING THE DATABASE {:#?}", new_movie);
-        let response = session
-            .query_prepared(CREATE_MOVIE, new_movie.clone())
-            .await
-            .expect("Unable to create new Movie");
-        log::info!("Database Response {:#?}", response);
-       MovieDatabase::get_movie_id(new_movie.movie_id, session).await        
+        write_logged_batch(create_statements(&Movie::from(&new_movie))?, session).await?;
+        wake_relay();
+        MovieDatabase::get_movie_id(new_movie.movie_id, session).await        
     }
     #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.movies_object"), err)]
     async fn update_movie(id: i64, new_movie: NewMovie, session: &'static CachedSession) -> QueryResult<Movie> {
-        let response = session
-            .query_prepared(UPDATE_MOVIE, (
-                new_movie,
-                id))
-            .await
-            .expect("Unable to update the movie")
-            .rows
-            .unwrap_or_default()
-            .into_typed::<Movie>()
-            .next()
-            .unwrap()
-            .unwrap();
-        Ok(response)
+        MovieDatabase::patch_movie(id, MoviePatch::from(new_movie), session).await
     }
+    /// Writes only the fields set on the patch. Renaming a movie or changing its year rewrites
+    /// the primary key, so the old row is deleted and the new one inserted in a single logged batch.
+    /// `MovieUpdated` is written with it either way, unless the movie is soft deleted: it is out of
+    /// the index until `MovieRestored` brings it back as patched
     #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.movies_object"), err)]
-    async fn delete_movie(id: i64, title: String, session: &'static CachedSession) -> QueryResult<bool> {
-        let res = session
-            .query_prepared(DELETE_MOVIE, (id, title ))
-            .await
-            .unwrap_or_default();
-        log::info!("Deleted Result: {:#?}", res);
+    async fn patch_movie(id: i64, patch: MoviePatch, session: &'static CachedSession) -> QueryResult<Movie> {
+        let current = MovieDatabase::get_movie_id(id, session).await?;
+        let patched = patch.apply(&current);
+
+        let event = match patched.is_deleted() { 
+            true => None,
+            false => Some(enqueue(CatalogEvent::MovieUpdated(patched.clone()))?),
+        };
+        if patch.changes_key(&current) { 
+            let mut statements = vec![(DELETE_MOVIE_ROW, bind(MovieKey::from(&current))?)];
+            statements.extend(insert_statements(&patched)?);
+            statements.extend(event);
+            write_logged_batch(statements, session).await?;
+            log::info!("Moved movie {} from ({}, {}) to ({}, {})", id, current.title, current.year, patched.title, patched.year);
+        } else { 
+            let mut statements = vec![(PATCH_MOVIE, bind(patch.into_values(&current))?)];
+            statements.extend(event);
+            write_logged_batch(statements, session).await?;
+        }
+        wake_relay();
+        Ok(patched)
+    }
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.movies_object"), err)]
+    async fn delete_movie(id: i64, session: &'static CachedSession) -> QueryResult<bool> {
+        let current = match MovieDatabase::get_movie_id(id, session).await { 
+            Ok(current) => current,
+            Err(ServiceError::NotFound) => return Ok(false),
+            Err(e) => return Err(e),
+        };
+        write_logged_batch(vec![
+            (DELETE_MOVIE_ROW, bind((id, current.title, current.year))?),
+            (DELETE_MOVIE_KEY, bind((id,))?),
+            enqueue(CatalogEvent::<Movie>::MovieDeleted { movie_id: id })?,
+        ], session).await?;
+        wake_relay();
+        log::info!("Deleted movie {}", id);
         Ok(true)
     }
+    /// Both rows and the event go in one logged batch. A movie already in the state asked for is
+    /// returned as it is, without publishing anything
     #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.movies_object"), err)]
-    async fn bulk_insert(movie: Vec<Movie>, session: &'static CachedSession) -> QueryResult<bool> { 
-        let mut batch = Batch::default();
-        log::info!("👀 Preparing to make batch call for {:#?}", movie);
-        let len = movie.len();
-        // Build all the statements first
-        for _ in 0..len { 
-            let prepared: PreparedStatement = session
-                .0
-                .session
-                .prepare(CREATE_MOVIE)
-                .await
-                .expect("Unable to batch prepared statments together");
-            batch.append_statement(prepared);
+    async fn set_deleted(id: i64, deleted_at: Option<i64>, session: &'static CachedSession) -> QueryResult<Movie> {
+        let current = MovieDatabase::get_movie_id(id, session).await?;
+        if current.is_deleted() == deleted_at.is_some() { 
+            return Ok(current)
         }
-        batch.set_tracing(true);
-        //  Insert each UDTs as our values 
-        session
-            .0
-            .session
-            .batch(&batch, (movie))
-            .await
-            .expect("Error Executing the batch statement");
+        let movie = Movie { deleted_at, ..current };
+        let event = match deleted_at { 
+            Some(deleted_at) => CatalogEvent::MovieSoftDeleted { movie_id: id, deleted_at },
+            None => CatalogEvent::MovieRestored(movie.clone()),
+        };
+        write_logged_batch(vec![
+            (SET_DELETED, bind((deleted_at, id, movie.title.clone(), movie.year))?),
+            (SET_KEY_DELETED, bind((id, movie.title.clone(), movie.year, deleted_at))?),
+            enqueue(event)?,
+        ], session).await?;
+        wake_relay();
+        log::info!("{} movie {}", if movie.is_deleted() { "Soft deleted" } else { "Restored" }, id);
+        Ok(movie)
+    }
+    /// `MovieUpdated` goes in the same logged batch, unless the movie is soft deleted
+    #[tracing::instrument(skip(session, movie), fields(repository = "movie_keyspace.movies_object"), err)]
+    async fn set_genres(movie: Movie, genres: Vec<String>, session: &'static CachedSession) -> QueryResult<Movie> {
+        let movie = Movie { genres, ..movie };
+        let mut statements = vec![(SET_GENRES, bind((movie.genres.clone(), movie.movie_id, movie.title.clone(), movie.year))?)];
+        if !movie.is_deleted() { 
+            statements.push(enqueue(CatalogEvent::MovieUpdated(movie.clone()))?);
+        }
+        write_logged_batch(statements, session).await?;
+        wake_relay();
+        Ok(movie)
+    }
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.movies_object"), err)]
+    async fn bulk_insert(movie: Vec<Movie>, session: &'static CachedSession) -> QueryResult<bool> { 
+        log::info!("👀 Preparing to make batch call for {} movies", movie.len());
+        let batches = movie.iter().map(create_statements).collect::<QueryResult<Vec<_>>>()?;
+        let written = futures::stream::iter(batches)
+            .map(|statements| write_logged_batch(statements, session))
+            .buffer_unordered(BULK_INSERT_CONCURRENCY)
+            .try_collect::<Vec<()>>()
+            .await;
+        //  Even after a failure, the movies written before it have events waiting
+        wake_relay();
+        written?;
         log::info!("Reached the end of batch Query");
-        log::info!("🤖 Batch Tracing Info: {:#?}", batch.get_tracing());
         Ok(true)
     }
     #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.movies_object"), err)]
     async fn stream_insert(movie: Vec<Movie>, session: &'static CachedSession) -> QueryResult<bool> { 
         let mut stream = futures::stream::iter(movie);
         while let Some(movie) = stream.next().await { 
-            log::info!("🛬 Streaming {:#?} into the Database ", movie.clone());
-            let res = session.query_prepared(CREATE_MOVIE, (movie))
-                .await
-                .expect("Unable to stream movies");
+            log::info!("🛬 Streaming {} into the Database ", movie.movie_id);
+            write_logged_batch(create_statements(&movie)?, session).await?;
+            wake_relay();
         }
         Ok(true)
     }
+    /// A new movie is written together with its external id in one logged batch, so a failed
+    /// import never leaves a movie that the next run would not find and would create again
+    #[tracing::instrument(skip(session, new_movie), fields(repository = "movie_keyspace.movies_by_external_id"), err)]
+    async fn upsert_by_external_id(external_id: String, new_movie: NewMovie, session: &'static CachedSession) -> QueryResult<Upserted> { 
+        let existing = session.query_prepared(GET_EXTERNAL_ID, (external_id.clone(),))
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
+            .into_typed::<(i64,)>()
+            .next()
+            .transpose()
+            .map_err(|e| ServiceError::ServerError(e.to_string()))?;
+        if let Some((movie_id,)) = existing { 
+            let movie = MovieDatabase::patch_movie(movie_id, MoviePatch::from(new_movie), session).await?;
+            return Ok(Upserted::Updated(movie))
+        }
+        let mut statements = create_statements(&Movie::from(&new_movie))?;
+        statements.push((INSERT_EXTERNAL_ID, bind((external_id, new_movie.movie_id))?));
+        write_logged_batch(statements, session).await?;
+        wake_relay();
+        let movie = MovieDatabase::get_movie_id(new_movie.movie_id, session).await?;
+        Ok(Upserted::Created(movie))
+    }
+    #[tracing::instrument(skip(session, movies), fields(repository = "movie_keyspace.event_outbox"), err)]
+    async fn request_reindex(movies: Vec<Movie>, session: &'static CachedSession) -> QueryResult<()> {
+        write_events(movies.into_iter().map(CatalogEvent::MovieReindexRequested).collect(), session).await
+    }
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.movies_by_id"), err)]
+    async fn backfill_movie_keys(session: &'static CachedSession) -> QueryResult<i32> {
+        let keys = read_all_pages::<(i64, String, i32, Option<i64>)>(GET_MOVIE_KEYS, session).await?;
+        let mut written = 0;
+        for (movie_id, title, year, deleted_at) in keys { 
+            if get_movie_key(movie_id, session).await?.is_some() { 
+                continue
+            }
+            let res = session.query_prepared(BACKFILL_MOVIE_KEY, (movie_id, title, year, deleted_at))
+                .await
+                .map_err(|_| ServiceError::DatabaseError)?;
+            if is_applied(res) { 
+                written += 1;
+            }
+        }
+        log::info!("🔑 Wrote the movies_by_id entry of {} movies", written);
+        Ok(written)
+    }
 
-}
\ No newline at end of file
+}
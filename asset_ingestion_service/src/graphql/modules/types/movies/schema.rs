@@ -2,13 +2,19 @@ use async_graphql::*;
 use async_graphql_actix_web::*;
 use futures::StreamExt;
 use serde::{Deserialize, Serialize};
-use chrono::NaiveDate;
-use crate::{graphql::{config::get_conn_from_ctx, modules::types::{prod_company::{schema::ProductionCompanyType, resolver::CompanyDetailsLoader}, tmdb_test::{fetch_movies_externally, fetch_movies_by_list, fetch_movie_details}}}, to_bigint, kafka};
-use super::{model::{BusinessData, MovieRating, MediaType, MediaRated, Status, Movie, NewMovie}, resolver::MovieDatabase};
+use chrono::{DateTime, NaiveDate, Utc};
+use crate::{graphql::{config::get_store_from_ctx, modules::types::{prod_company::{schema::ProductionCompanyType, resolver::CompanyDetailsLoader}, credits::{model::Credit, resolver::{CreditDatabase, CreditResolver}}, genres::{model::{Genre, or_blank}, resolver::{GenreDatabase, GenreResolver}}, release::{model::StatusChange, resolver::{ReleaseDatabase, ReleaseResolver}, schema::editor}, translations::{resolver::{TranslationDatabase, TranslationResolver}, schema::publish as publish_translations}, availability::{resolver::{AvailabilityDatabase, AvailabilityResolver}, scheduler::publish as publish_availability}, people_module::{model::Person, resolver::{PersonDatabase, PersonResolver}}, tmdb_test::{fetch_movies_externally, fetch_movies_by_list, fetch_movie_details, get_credits}}}, to_bigint};
+use super::{model::{BusinessData, MovieRating, MediaType, MediaRated, Status, Movie, NewMovie, MoviePatch}, resolver::{MovieDatabase, MovieResolver}};
+use super::import::{import_movies, parse_rows, ImportFormat, ImportReport, IMPORT_CONCURRENCY};
 use async_graphql::dataloader::*;
+use common_utils::{QueryResult, availability::now_millis, error::ServiceError};
+use std::marker::PhantomData;
 
+/// Mutations run against `R`, an in-memory resolver in tests. The TMDB imports write credits through `C`
+/// and the people credited through `P`, genres are checked against the taxonomy read through `G`, and status changes are recorded through `S`.
+/// Restoring a movie republishes its translations from `T` and its availability from `A`
 #[derive(Default)]
-pub struct MovieMutation;
+pub struct MovieMutation<R = MovieDatabase, C = CreditDatabase, G = GenreDatabase, S = ReleaseDatabase, T = TranslationDatabase, A = AvailabilityDatabase, P = PersonDatabase>(PhantomData<(R, C, G, S, T, A, P)>);
 
 #[derive(SimpleObject,  Debug, Clone, Deserialize, Serialize)]
 pub struct MovieType { 
@@ -35,6 +41,9 @@ pub struct MovieType {
     pub runtime: i64,
     pub status: String,
     pub video_file: String,
+    pub is_deleted: bool,
+    /// When the movie was soft deleted, it is hidden from search and recommendations until restored
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
 // #[Object]
@@ -71,8 +80,8 @@ pub struct NewMovieInput {
     /// A list of countries associated in the movie
     #[graphql(validator(list, max_length = 50))]
     pub countries: Option<Vec<String>>,
-    /// A list of genres, provided no unique Id as Indexing through 
-    /// Elasticsearch will make it easier and cheaper filter through 
+    /// Genre slugs. A name in any locale, an alias or a TMDB genre id is accepted
+    /// and stored as the slug it resolves to, anything else is rejected
     #[graphql(validator(list, max_length = 200))]
     pub genres: Option<Vec<String>>,
     /// URI link to movie's homepage
@@ -118,6 +127,47 @@ pub struct NewMovieInput {
     pub video_file: Option<String>,
 }
 
+/// Partial update of a movie, omitted fields keep their current value
+#[derive(InputObject, Debug, Clone, Deserialize, Serialize, Default)]
+pub struct MoviePatchInput { 
+    /// Renaming a movie moves it to a new row
+    #[graphql(validator(max_length = 50))]
+    pub title: Option<String>,
+    /// Part of the primary key as well
+    pub year: Option<i32>,
+    #[graphql(validator(list, max_length = 60))]
+    pub awards: Option<Vec<String>>,
+    /// Replaces both budget and revenue
+    pub business: Option<BusinessDataInput>,
+    #[graphql(validator(list, max_length = 50))]
+    pub countries: Option<Vec<String>>,
+    #[graphql(validator(list, max_length = 200))]
+    pub genres: Option<Vec<String>>,
+    pub homepage: Option<String>,
+    #[graphql(validator(list, max_length = 200))]
+    pub keywords: Option<Vec<String>>,
+    #[graphql(validator(list, max_length = 200))]
+    pub languages: Option<Vec<String>>,
+    pub media_type: Option<MediaType>,
+    #[graphql(validator(list, max_length = 400))]
+    pub movie_casts: Option<Vec<String>>,
+    #[graphql(validator(list, max_length = 400))]
+    pub movie_company: Option<Vec<String>>,
+    pub movie_director: Option<Vec<String>>,
+    #[graphql(validator(list, max_length = 100))]
+    pub movie_writer: Option<Vec<String>>,
+    #[graphql(validator(max_length = 400))]
+    pub overview: Option<String>,
+    pub poster: Option<String>,
+    pub rated: Option<MediaRated>,
+    /// Replaces the whole rating
+    pub rating: Option<MovieRatingInput>,
+    pub release_date: Option<NaiveDate>,
+    pub runtime: Option<i64>,
+    pub status: Option<Status>,
+    pub video_file: Option<String>,
+}
+
 #[derive(Debug, Clone, InputObject, Serialize, Deserialize, Default)]
 pub struct BusinessDataInput { 
     pub budget: Option<i64>, 
@@ -142,52 +192,117 @@ pub struct BulkStreamInsertData {
 }
 
 #[Object]
-impl MovieMutation { 
+impl<R: MovieResolver, C: CreditResolver, G: GenreResolver, S: ReleaseResolver, T: TranslationResolver, A: AvailabilityResolver, P: PersonResolver> MovieMutation<R, C, G, S, T, A, P> { 
     #[tracing::instrument(skip(self, ctx), fields(new_movie))]
     #[graphql(name = "createMovie")]
     async fn create_movie(&self, ctx: &Context<'_>, new_movie: NewMovieInput) -> FieldResult<MovieType> { 
-        let res = Movie::create_movie::<MovieDatabase>(NewMovie::from(&new_movie), get_conn_from_ctx(ctx))
+        let new_movie = with_genres::<G>(NewMovie::from(&new_movie), get_store_from_ctx(ctx))
             .await
-            .expect("Unable to convert Movie to proper Graphql Type");
-    
-        log::info!("🚢🚢 Sending over to Kafka {:#?}", res);
-        // Publish new message to a specific topic 
-        // This goes into Kafka to be sent to the Elastic Search where Movies can be indexed
-        let message = serde_json::to_string(&res).expect("Unable to serialize movie");
-        kafka::send_message(&message).await;
+            .map_err(|e| e.extend())?;
+        let res = Movie::create_movie::<R>(new_movie, get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
 
+        // `MovieCreated` went into the event outbox with the movie, the relay takes it to Kafka
+        // and on to Elasticsearch where movies are indexed
         Ok(MovieType::from(&res))
     }
     /// From Elastic.co
     /// Overwriting the document in Elasticsearch is just as efficient as an update operation would be, because 
-    /// internally an update would consist of deleting the old document and then indexing an entirely new document 
+    /// internally an update would consist of deleting the old document and then indexing an entirely new document.
+    /// Leaving `status` out keeps the current one, changing it has to follow the transition rules of `setMovieStatus`
     #[tracing::instrument(skip(self, ctx))]
     #[graphql(name = "updateMovie")]
     async fn update_movie(&self, ctx: &Context<'_>, new_movie: NewMovieInput, movie_id: ID) -> FieldResult<MovieType> { 
-        let res = Movie::update_movie::<MovieDatabase>(to_bigint(movie_id), NewMovie::from(&new_movie), get_conn_from_ctx(ctx))
+        let movie_id = to_bigint(movie_id);
+        let (current, change) = status_change::<R>(ctx, movie_id, new_movie.status)
             .await
-            .expect("");
-        // Publish new message to a specific topic 
-        // This goes into Kafka to be sent to the Elastic Search where Movies can be indexed
-        let message = serde_json::to_string(&res).expect("Unable to serialize movie");
-        kafka::send_message(&message).await;
+            .map_err(|e| e.extend())?;
+        let mut new_movie = with_genres::<G>(NewMovie::from(&new_movie), get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        new_movie.status = change.as_ref().map_or(current.status, |change| change.to_status.clone());
+        let res = Movie::update_movie::<R>(movie_id, new_movie, get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        record_change::<S>(ctx, change).await.map_err(|e| e.extend())?;
 
         Ok(MovieType::from(&res))
     }
-    /// There are two ways of doing this:
-    /// One way is to include a field 'is_deleted' to indicate that they are no longer valid
-    /// The other way is, in this context, instead of sending Kafka messages to elasticsearch where ordering or queing isnt necessary
-    /// We can simply call a GRAPHQL API directly that signals to ELasticsearch to delete the corresponding documents
+    /// Updates only the fields present on `patch`, the resulting movie is published through the
+    /// event outbox so the search index picks up the change. A new `status` has to follow the transition rules of `setMovieStatus`
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "patchMovie")]
+    async fn patch_movie(&self, ctx: &Context<'_>, movie_id: ID, patch: MoviePatchInput) -> FieldResult<MovieType> { 
+        let movie_id = to_bigint(movie_id);
+        let (_, change) = status_change::<R>(ctx, movie_id, patch.status)
+            .await
+            .map_err(|e| e.extend())?;
+        let mut patch = MoviePatch::from(&patch);
+        if let Some(genres) = patch.genres { 
+            let genres = Genre::canonicalise::<G>(&genres, get_store_from_ctx(ctx))
+                .await
+                .map_err(|e| e.extend())?;
+            patch.genres = Some(or_blank(genres));
+        }
+        let res = Movie::patch_movie::<R>(movie_id, patch, get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        record_change::<S>(ctx, change).await.map_err(|e| e.extend())?;
+
+        Ok(MovieType::from(&res))
+    }
+    /// Removes the movie for good, `softDeleteMovie` keeps it around to be restored.
     /// Steps: 
-    /// Delete from Scylla DB
-    /// Delete Document from Elasticsearch
+    /// Delete from Scylla DB, `MovieDeleted` is written to the event outbox in the same batch
+    /// The relay publishes it and the search ingest deletes the document from Elasticsearch.
+    /// `false` when there is no movie with that id
     #[tracing::instrument(skip(self, ctx))]
     #[graphql(name = "deleteMovie")]
-    async fn delete_movie(&self, ctx: &Context<'_>, movie_id: ID, title: String) -> FieldResult<bool> { 
+    async fn delete_movie(&self, ctx: &Context<'_>, movie_id: ID) -> FieldResult<bool> { 
         //  First delete from the Scylla Db
-        let res = Movie::delete_movie::<MovieDatabase>(to_bigint(movie_id), title, get_conn_from_ctx(ctx))
+        let res = Movie::delete_movie::<R>(to_bigint(movie_id), get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        Ok(res)
+    }
+    /// Hides the movie from search, the asset service and recommendations while keeping its rows.
+    /// A movie that is already soft deleted keeps the time it was deleted at
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "softDeleteMovie")]
+    async fn soft_delete_movie(&self, ctx: &Context<'_>, movie_id: ID) -> FieldResult<MovieType> { 
+        let res = Movie::set_deleted::<R>(to_bigint(movie_id), Some(now_millis()), get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        Ok(MovieType::from(&res))
+    }
+    /// Brings a soft deleted movie back. The search document was dropped along with the
+    /// translations and availability stored on it, so both are published again
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "restoreMovie")]
+    async fn restore_movie(&self, ctx: &Context<'_>, movie_id: ID) -> FieldResult<MovieType> { 
+        let movie_id = to_bigint(movie_id);
+        let current = Movie::get_movie_id::<R>(movie_id, get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        if !current.is_deleted() { 
+            return Ok(MovieType::from(&current))
+        }
+        let res = Movie::set_deleted::<R>(movie_id, None, get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        publish_translations::<T>(movie_id, get_store_from_ctx(ctx)).await.map_err(|e| e.extend())?;
+        publish_availability::<A>(movie_id, get_store_from_ctx(ctx)).await.map_err(|e| e.extend())?;
+        Ok(MovieType::from(&res))
+    }
+    /// Gives every movie stored before `movies_by_id` existed its entry there, which turns their
+    /// lookups by id back into single partition reads. Returns how many were missing
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "backfillMovieKeys")]
+    async fn backfill_movie_keys(&self, ctx: &Context<'_>) -> FieldResult<i32> { 
+        let res = Movie::backfill_movie_keys::<R>(get_store_from_ctx(ctx))
             .await
-            .expect("Unable to delete the specified rows");
+            .map_err(|e| e.extend())?;
         Ok(res)
     }
     /// Bulk inserting dataset from TMDB, USED FOR database query analysis and optimisation
@@ -219,23 +334,23 @@ impl MovieMutation {
         )
             .await
             .expect("Unable to get each details");
+        let taxonomy = Genre::taxonomy::<G>(get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        let movie_details: Vec<Movie> = movie_details.into_iter().map(|movie| movie.with_tmdb_genres(&taxonomy)).collect();
         
-        let res = Movie::bulk_insert::<MovieDatabase>(
+        let res = Movie::bulk_insert::<R>(
             movie_details.clone(), 
-            get_conn_from_ctx(ctx)
+            get_store_from_ctx(ctx)
         )
             .await
             .expect("Unable to execute batch query");
+        import_credits::<C, P>(&movie_details, get_store_from_ctx(ctx), get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
 
-        let mut stream = futures::stream::iter(movie_details.clone());
-        while let Some(movie) = stream.next().await { 
-            // Publish new message to a specific topic 
-            log::info!("📦 Loading {:#?}", movie.clone());
-            // This goes into Kafka to be sent to the Elastic Search where Movies can be indexed
-            log::info!("🚢 Received Client Request to Sync Data back into Elasticsearch: {:#?}", res);
-            let message = serde_json::to_string(&movie).expect("Unable to serialize movie");
-            kafka::send_message(&message).await;
-        }
+        // Every movie was written with its `MovieCreated` event, the outbox relay syncs them into Elasticsearch
+        log::info!("🚢 Inserted {} movies: {:#?}", movie_details.len(), res);
         Ok(
             movie_details
             .iter()
@@ -271,23 +386,23 @@ impl MovieMutation {
         )
             .await
             .expect("Unable to get each details");
+        let taxonomy = Genre::taxonomy::<G>(get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        let movie_details: Vec<Movie> = movie_details.into_iter().map(|movie| movie.with_tmdb_genres(&taxonomy)).collect();
         
-        let res = Movie::stream_insert::<MovieDatabase>(
+        let res = Movie::stream_insert::<R>(
             movie_details.clone(), 
-            get_conn_from_ctx(ctx)
+            get_store_from_ctx(ctx)
         )
             .await
             .expect("Unable to execute batch query");
+        import_credits::<C, P>(&movie_details, get_store_from_ctx(ctx), get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
         
-        let mut stream = futures::stream::iter(movie_details.clone());
-        while let Some(movie) = stream.next().await { 
-            // Publish new message to a specific topic 
-            log::info!("📦 Loading {:#?}", movie.clone());
-            // This goes into Kafka to be sent to the Elastic Search where Movies can be indexed
-            log::info!("🚢 Received Client Request to Sync Data back into Elasticsearch: {:#?}", res);
-            let message = serde_json::to_string(&movie).expect("Unable to serialize movie");
-            kafka::send_message(&message).await;
-        }
+        // Every movie was written with its `MovieCreated` event, the outbox relay syncs them into Elasticsearch
+        log::info!("🚢 Inserted {} movies: {:#?}", movie_details.len(), res);
         Ok(
             movie_details
             .iter()
@@ -295,5 +410,57 @@ impl MovieMutation {
             .collect()
         )
     }
+    /// Imports an uploaded JSONL or CSV catalogue file without calling out to TMDB.
+    /// Rows are upserted on their `external_id`, so the same file can be imported again safely.
+    /// `format` falls back to the file extension
+    #[tracing::instrument(skip(self, ctx, file))]
+    #[graphql(name = "importMovies")]
+    async fn import_movies(&self, ctx: &Context<'_>, file: Upload, format: Option<ImportFormat>) -> FieldResult<ImportReport> { 
+        let upload = file.value(ctx)?;
+        let format = format
+            .or_else(|| ImportFormat::from_filename(&upload.filename))
+            .ok_or_else(|| ServiceError::BadRequest(format!("Unknown format for {}, pass `format`", upload.filename)).extend())?;
+        log::info!("📥 Importing {} as {:?}", upload.filename, format);
+        let rows = parse_rows(format, upload.into_read());
+        let taxonomy = Genre::taxonomy::<G>(get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        Ok(import_movies::<R>(rows, &taxonomy, get_store_from_ctx(ctx), *IMPORT_CONCURRENCY).await)
+    }
     
-}
\ No newline at end of file
+}
+
+/// Replaces the genres of `new_movie` with their slugs, unknown genres reject the write
+async fn with_genres<G: GenreResolver>(mut new_movie: NewMovie, session: &'static G::Store) -> QueryResult<NewMovie> { 
+    new_movie.genres = or_blank(Genre::canonicalise::<G>(&new_movie.genres, session).await?);
+    Ok(new_movie)
+}
+
+/// The movie as it is now, and the change a write setting `status` would make to it.
+/// Transitions `setMovieStatus` needs an override for are rejected
+async fn status_change<R: MovieResolver>(ctx: &Context<'_>, movie_id: i64, status: Option<Status>) -> QueryResult<(Movie, Option<StatusChange>)> { 
+    let current = Movie::get_movie_id::<R>(movie_id, get_store_from_ctx(ctx)).await?;
+    let change = match status { 
+        Some(status) => StatusChange::check(&current, status, &editor(ctx), false, None)?,
+        None => None,
+    };
+    Ok((current, change))
+}
+
+/// Adds a change made alongside a movie update to the movie's history
+async fn record_change<S: ReleaseResolver>(ctx: &Context<'_>, change: Option<StatusChange>) -> QueryResult<()> { 
+    if let Some(change) = change { 
+        StatusChange::record::<S>(change, get_store_from_ctx(ctx)).await?;
+    }
+    Ok(())
+}
+
+/// Fetches the cast and crew of every movie from TMDB and replaces their credits, the people
+/// credited are found or created through their TMDB ids
+async fn import_credits<C: CreditResolver, P: PersonResolver>(movies: &[Movie], session: &'static C::Store, people: &'static P::Store) -> QueryResult<()> { 
+    for movie in movies { 
+        let credits = Person::resolve_tmdb_credits::<P>(get_credits(movie.movie_id).await?, people).await?;
+        Credit::set_movie_credits::<C>(movie.movie_id, credits, session).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,132 @@
+use std::time::Duration;
+use common_utils::{QueryResult, availability::now_millis, events::Event, kafka::{Delivery, Message}};
+use lazy_static::lazy_static;
+use scylla::macros::{FromRow, ValueList};
+use serde::{Deserialize, Serialize};
+use crate::generate_unique_id;
+use super::resolver::OutboxResolver;
+
+lazy_static! {
+    /// Partitions the outbox is spread over. Every event of a partition key lands in the same
+    /// bucket, so the relay reads them back in the order they were written
+    pub static ref OUTBOX_BUCKETS: i32 = std::env::var("OUTBOX_BUCKETS")
+        .ok()
+        .and_then(|buckets| buckets.parse::<i32>().ok())
+        .filter(|buckets| *buckets > 0)
+        .unwrap_or(16);
+    /// Failed deliveries after which the relay gives up on an entry and moves it to the dead
+    /// letters, so the later events of its key go out
+    pub static ref OUTBOX_MAX_ATTEMPTS: i32 = std::env::var("OUTBOX_MAX_ATTEMPTS")
+        .ok()
+        .and_then(|attempts| attempts.parse::<i32>().ok())
+        .filter(|attempts| *attempts > 0)
+        .unwrap_or(20);
+}
+
+/// FNV-1a, unlike `DefaultHasher` it stays the same across builds, so entries written before
+/// a deploy are found in the same bucket as the ones written after it
+fn bucket_of(partition_key: &str) -> i32 {
+    let hash = partition_key
+        .bytes()
+        .fold(0xcbf29ce484222325_u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3));
+    (hash % *OUTBOX_BUCKETS as u64) as i32
+}
+
+// Columns after the primary key are in alphabetical order, the order `SELECT *` returns them in
+/// An event written in the same logged batch as the rows it describes, waiting to be published
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize, ValueList, PartialEq)]
+pub struct OutboxEntry {
+    pub bucket: i32,
+    /// Snowflake id, so a bucket is read in the order its entries were written
+    pub entry_id: i64,
+    /// Failed deliveries so far
+    pub attempts: i32,
+    pub event_id: String,
+    pub event_type: String,
+    pub last_error: Option<String>,
+    /// The `EventEnvelope` as json
+    pub message: String,
+    pub partition_key: String,
+    /// Epoch millis, after a failed delivery the entry and the later ones of its key wait until then
+    pub retry_at: Option<i64>,
+    pub schema_version: i32,
+}
+
+impl OutboxEntry {
+    pub fn new(message: Message) -> Self {
+        Self {
+            bucket: bucket_of(&message.key),
+            entry_id: generate_unique_id(),
+            attempts: 0,
+            event_id: message.event_id,
+            event_type: message.event_type,
+            last_error: None,
+            message: message.payload,
+            partition_key: message.key,
+            retry_at: None,
+            schema_version: message.schema_version as i32,
+        }
+    }
+    /// `event` serialised into its envelope, see `Message::new`
+    pub fn from_event<E: Event>(event: E) -> QueryResult<Self> {
+        Ok(Self::new(Message::new(env!("CARGO_PKG_NAME"), event)?))
+    }
+    /// Whether the entry is still backing off from its last failed delivery at `now`
+    pub fn is_waiting(&self, now: i64) -> bool {
+        self.retry_at.map_or(false, |retry_at| retry_at > now)
+    }
+    /// The entry as it is handed to the producer
+    pub fn to_message(&self) -> Message {
+        Message {
+            event_id: self.event_id.clone(),
+            key: self.partition_key.clone(),
+            event_type: self.event_type.clone(),
+            schema_version: self.schema_version as u16,
+            payload: self.message.clone(),
+        }
+    }
+}
+
+/// Where a published entry ended up, kept for a week to trace an event to its Kafka offset
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize, ValueList, PartialEq)]
+pub struct SentEntry {
+    pub event_id: String,
+    pub entry_id: i64,
+    pub event_type: String,
+    pub kafka_offset: i64,
+    pub kafka_partition: i32,
+    pub partition_key: String,
+    pub sent_at: i64,
+}
+
+impl SentEntry {
+    pub fn new(entry: &OutboxEntry, (partition, offset): Delivery) -> Self {
+        Self {
+            event_id: entry.event_id.clone(),
+            entry_id: entry.entry_id,
+            event_type: entry.event_type.clone(),
+            kafka_offset: offset,
+            kafka_partition: partition,
+            partition_key: entry.partition_key.clone(),
+            sent_at: now_millis(),
+        }
+    }
+}
+
+impl OutboxEntry {
+    pub async fn get_pending<OutboxDatabase: OutboxResolver>(bucket: i32, limit: i32, session: &'static OutboxDatabase::Store) -> QueryResult<Vec<OutboxEntry>> {
+        OutboxDatabase::get_pending(bucket, limit, session).await
+    }
+    pub async fn mark_sent<OutboxDatabase: OutboxResolver>(entry: &OutboxEntry, delivery: Delivery, session: &'static OutboxDatabase::Store) -> QueryResult<SentEntry> {
+        OutboxDatabase::mark_sent(entry, delivery, session).await
+    }
+    pub async fn record_failure<OutboxDatabase: OutboxResolver>(entry: &OutboxEntry, session: &'static OutboxDatabase::Store) -> QueryResult<()> {
+        OutboxDatabase::record_failure(entry, session).await
+    }
+    pub async fn dead_letter<OutboxDatabase: OutboxResolver>(entry: &OutboxEntry, session: &'static OutboxDatabase::Store) -> QueryResult<()> {
+        OutboxDatabase::dead_letter(entry, session).await
+    }
+    pub async fn claim_bucket<OutboxDatabase: OutboxResolver>(bucket: i32, owner: &str, lease: Duration, session: &'static OutboxDatabase::Store) -> QueryResult<bool> {
+        OutboxDatabase::claim_bucket(bucket, owner, lease, session).await
+    }
+}
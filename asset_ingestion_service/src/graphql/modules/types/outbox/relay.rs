@@ -0,0 +1,190 @@
+use std::{collections::{HashMap, VecDeque}, time::Duration};
+use futures::Future;
+use lazy_static::lazy_static;
+use tokio::sync::Notify;
+use common_utils::{QueryResult, availability::now_millis, kafka::{Delivery, Message}};
+use crate::{generate_unique_id, kafka};
+use super::model::{OutboxEntry, OUTBOX_BUCKETS, OUTBOX_MAX_ATTEMPTS};
+use super::resolver::OutboxResolver;
+
+/// Longest the relay sleeps between sweeps when no write wakes it
+const RELAY_TICK: Duration = Duration::from_secs(5);
+/// Delay after the first failed sweep, or the first failed delivery of an entry, doubled after
+/// each one that follows
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+/// Longest an entry waits after a failed delivery, it only holds back the later entries of its key
+const MAX_ENTRY_RETRY_DELAY: Duration = Duration::from_secs(300);
+/// Entries read from a bucket per sweep, the rest wait for the next one
+const RELAY_BATCH: i32 = 256;
+
+lazy_static! {
+    static ref RELAY_WAKEUP: Notify = Notify::new();
+    /// Identifies this instance as the holder of a bucket lease
+    pub static ref RELAY_ID: String = generate_unique_id().to_string();
+    /// How long a bucket stays with the relay that last swept it. Only the holder publishes the
+    /// bucket, so two instances neither publish an entry twice nor one key out of order. Another
+    /// instance takes the bucket over once its holder hasn't swept it for this long
+    static ref OUTBOX_LEASE: Duration = std::env::var("OUTBOX_LEASE_SECS")
+        .ok()
+        .and_then(|secs| secs.parse::<u64>().ok())
+        .filter(|secs| *secs > RELAY_TICK.as_secs())
+        .map(Duration::from_secs)
+        .unwrap_or(Duration::from_secs(30));
+}
+
+/// Has the relay sweep the outbox now rather than on its next tick. A wake-up sent while a sweep
+/// is running starts another one straight after it
+pub fn wake_relay() {
+    RELAY_WAKEUP.notify_one();
+}
+
+/// How many entries a sweep published, and how many it had to leave pending
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Sweep {
+    pub published: usize,
+    pub pending: usize,
+    /// A bucket had more entries than one sweep reads
+    pub backlog: bool,
+    /// Entries given up on and moved to the dead letters
+    pub dead_lettered: usize,
+    /// Buckets left alone because another relay holds their lease
+    pub leased_elsewhere: usize,
+}
+
+/// `RETRY_DELAY` doubled for every failed delivery after the first, up to `MAX_ENTRY_RETRY_DELAY`
+fn retry_delay(attempts: i32) -> Duration {
+    (RETRY_DELAY * 2_u32.pow((attempts.max(1) - 1).min(16) as u32)).min(MAX_ENTRY_RETRY_DELAY)
+}
+
+/// `entries` split by partition key, each key's entries in the order they were written
+fn by_partition_key(entries: Vec<OutboxEntry>) -> Vec<VecDeque<OutboxEntry>> {
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    let mut queues: Vec<VecDeque<OutboxEntry>> = Vec::new();
+    for entry in entries {
+        let position = *positions.entry(entry.partition_key.clone()).or_insert_with(|| {
+            queues.push(VecDeque::new());
+            queues.len() - 1
+        });
+        queues[position].push_back(entry);
+    }
+    queues
+}
+
+/// Publishes the pending entries of every bucket `relay_id` holds the lease of through `publish`
+/// and marks the delivered ones sent. The entries of a partition key go out one at a time, each
+/// once the one before it was delivered, while different keys go out together. A failed entry
+/// holds back the rest of its key only, until its backoff has passed, so consumers never see a
+/// key's events out of order. After `OUTBOX_MAX_ATTEMPTS` failures it is moved to the dead
+/// letters and the rest of its key goes out. An entry delivered but not marked sent is published
+/// again: delivery is at least once
+pub async fn relay_once<O, P, F>(publish: &P, relay_id: &str, session: &'static O::Store) -> QueryResult<Sweep>
+where
+    O: OutboxResolver,
+    P: Fn(Vec<Message>) -> F,
+    F: Future<Output = Vec<QueryResult<Delivery>>>,
+{
+    let mut sweep = Sweep::default();
+    let lease = *OUTBOX_LEASE;
+    for bucket in 0..*OUTBOX_BUCKETS {
+        if !OutboxEntry::claim_bucket::<O>(bucket, relay_id, lease, session).await? {
+            sweep.leased_elsewhere += 1;
+            continue
+        }
+        let mut claimed_at = now_millis();
+        let entries = OutboxEntry::get_pending::<O>(bucket, RELAY_BATCH, session).await?;
+        if entries.is_empty() {
+            continue
+        }
+        sweep.backlog |= entries.len() >= RELAY_BATCH as usize;
+        //  Only the oldest entry of a key can have failed, the ones after it were never tried
+        let now = now_millis();
+        let (waiting, mut queues): (Vec<_>, Vec<_>) = by_partition_key(entries)
+            .into_iter()
+            .partition(|queue| queue.front().map_or(false, |entry| entry.is_waiting(now)));
+        sweep.pending += waiting.iter().map(VecDeque::len).sum::<usize>();
+        while !queues.is_empty() {
+            //  Renewed halfway through, so a slow broker doesn't let another relay take the bucket mid sweep
+            if now_millis() - claimed_at >= lease.as_millis() as i64 / 2 {
+                if !OutboxEntry::claim_bucket::<O>(bucket, relay_id, lease, session).await? {
+                    sweep.pending += queues.iter().map(VecDeque::len).sum::<usize>();
+                    break
+                }
+                claimed_at = now_millis();
+            }
+            //  The oldest entry left of every key
+            let round: Vec<OutboxEntry> = queues.iter_mut().filter_map(VecDeque::pop_front).collect();
+            let mut deliveries = publish(round.iter().map(OutboxEntry::to_message).collect())
+                .await
+                .into_iter();
+            let mut remaining = Vec::with_capacity(queues.len());
+            for (entry, queue) in round.iter().zip(queues) {
+                match deliveries.next() {
+                    Some(Ok(delivery)) => {
+                        OutboxEntry::mark_sent::<O>(entry, delivery, session).await?;
+                        sweep.published += 1;
+                        if !queue.is_empty() {
+                            remaining.push(queue);
+                        }
+                    }
+                    Some(Err(e)) => {
+                        let attempts = entry.attempts + 1;
+                        let failed = OutboxEntry {
+                            attempts,
+                            last_error: Some(e.to_string()),
+                            retry_at: Some(now_millis() + retry_delay(attempts).as_millis() as i64),
+                            ..entry.clone()
+                        };
+                        if attempts >= *OUTBOX_MAX_ATTEMPTS {
+                            log::error!("Giving up on {} {} after {} attempts, moved to the dead letters: {}", entry.event_type, entry.event_id, attempts, e);
+                            OutboxEntry::dead_letter::<O>(&failed, session).await?;
+                            sweep.dead_lettered += 1;
+                            //  The rest of the key goes out on the next sweep
+                            sweep.pending += queue.len();
+                        } else {
+                            log::warn!("Unable to publish {} {} (attempt {}): {}", entry.event_type, entry.event_id, attempts, e);
+                            OutboxEntry::record_failure::<O>(&failed, session).await?;
+                            sweep.pending += 1 + queue.len();
+                        }
+                    }
+                    //  A short report leaves the entries it is missing for the next sweep
+                    None => sweep.pending += 1 + queue.len(),
+                }
+            }
+            queues = remaining;
+        }
+    }
+    Ok(sweep)
+}
+
+/// Sweeps the outbox whenever a write wakes it, and every few seconds otherwise, which is also
+/// when entries backing off are retried. Sweeps that fail outright back off, up to a minute apart
+pub fn spawn_relay<O: OutboxResolver>(session: &'static O::Store) {
+    tokio::spawn(async move {
+        let mut failures: u32 = 0;
+        loop {
+            if failures == 0 {
+                //  Either a wake-up or the tick, whichever comes first
+                let _ = tokio::time::timeout(RELAY_TICK, RELAY_WAKEUP.notified()).await;
+            } else {
+                tokio::time::sleep((RETRY_DELAY * 2_u32.pow(failures.min(7) - 1)).min(MAX_RETRY_DELAY)).await;
+            }
+            match relay_once::<O, _, _>(&kafka::send_messages, &RELAY_ID, session).await {
+                Ok(sweep) => {
+                    if sweep.published > 0 {
+                        log::debug!("📤 Relayed {} outbox entries", sweep.published);
+                    }
+                    failures = 0;
+                    //  Only while it gets through, a bucket full of entries backing off would spin
+                    if sweep.backlog && sweep.published > 0 {
+                        wake_relay();
+                    }
+                }
+                Err(e) => {
+                    log::error!("Unable to relay the event outbox: {}", e);
+                    failures = failures.saturating_add(1);
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,128 @@
+use std::time::Duration;
+use async_trait::async_trait;
+use common_utils::{QueryResult, error::ServiceError, events::Event};
+use scylla::IntoTypedRows;
+use scylla::frame::value::SerializedValues;
+use crate::db::{CachedSession, bind, is_applied, write_logged_batch};
+use common_utils::kafka::Delivery;
+use super::model::{OutboxEntry, SentEntry};
+use super::relay::wake_relay;
+
+/// Pending outbox entries, read by the relay. Entries are written by the resolvers of the rows
+/// they describe, in the same logged batch, see `enqueue`
+#[async_trait]
+pub trait OutboxResolver: Send + Sync + 'static {
+    type Store: Send + Sync + 'static;
+    /// The oldest `limit` entries of `bucket`
+    async fn get_pending(bucket: i32, limit: i32, session: &'static Self::Store) -> QueryResult<Vec<OutboxEntry>>;
+    /// Removes the entry from the outbox and records where it was delivered
+    async fn mark_sent(entry: &OutboxEntry, delivery: Delivery, session: &'static Self::Store) -> QueryResult<SentEntry>;
+    /// Writes the attempts, error and retry time of `entry` after a failed delivery, the entry
+    /// stays pending
+    async fn record_failure(entry: &OutboxEntry, session: &'static Self::Store) -> QueryResult<()>;
+    /// Moves the entry from the outbox to the dead letters, where it waits to be replayed by hand
+    async fn dead_letter(entry: &OutboxEntry, session: &'static Self::Store) -> QueryResult<()>;
+    /// Takes or renews the lease of `owner` on `bucket` for `lease`, returns false while another
+    /// relay holds it
+    async fn claim_bucket(bucket: i32, owner: &str, lease: Duration, session: &'static Self::Store) -> QueryResult<bool>;
+}
+
+#[derive(Default)]
+pub struct OutboxDatabase;
+
+static INSERT_ENTRY: &str = "
+    INSERT INTO movie_keyspace.event_outbox (
+        bucket, entry_id, attempts, event_id, event_type, last_error, message, partition_key, retry_at, schema_version
+    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?);
+";
+static GET_PENDING: &str = "SELECT * FROM movie_keyspace.event_outbox WHERE bucket = ? LIMIT ?;";
+static DELETE_ENTRY: &str = "DELETE FROM movie_keyspace.event_outbox WHERE bucket = ? AND entry_id = ?;";
+static INSERT_SENT: &str = "
+    INSERT INTO movie_keyspace.event_outbox_sent (
+        event_id, entry_id, event_type, kafka_offset, kafka_partition, partition_key, sent_at
+    ) VALUES (?, ?, ?, ?, ?, ?, ?);
+";
+// Conditional, so an entry another relay has just deleted isn't brought back without its message
+static RECORD_FAILURE: &str = "
+    UPDATE movie_keyspace.event_outbox SET attempts = ?, last_error = ?, retry_at = ?
+    WHERE bucket = ? AND entry_id = ? IF EXISTS;
+";
+static INSERT_DEAD: &str = "
+    INSERT INTO movie_keyspace.event_outbox_dead (
+        bucket, entry_id, attempts, event_id, event_type, last_error, message, partition_key, retry_at, schema_version
+    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?);
+";
+// A lease is a row that expires, renewed only by its owner and taken once it is gone
+static RENEW_LEASE: &str = "UPDATE movie_keyspace.event_outbox_leases USING TTL ? SET owner = ? WHERE bucket = ? IF owner = ?;";
+static TAKE_LEASE: &str = "INSERT INTO movie_keyspace.event_outbox_leases (bucket, owner) VALUES (?, ?) IF NOT EXISTS USING TTL ?;";
+
+/// The statement writing `event` to the outbox, to be added to the logged batch of the rows it
+/// describes. Nothing is published until the batch is written, call `wake_relay` after it
+pub fn enqueue<E: Event>(event: E) -> QueryResult<(&'static str, SerializedValues)> {
+    Ok((INSERT_ENTRY, bind(OutboxEntry::from_event(event)?)?))
+}
+
+/// Writes `events` to the outbox on their own and wakes the relay. For events carrying rows read
+/// back after a write, such as every translation a movie has left, rather than the write itself
+pub async fn write_events<E: Event>(events: Vec<E>, session: &'static CachedSession) -> QueryResult<()> {
+    if events.is_empty() {
+        return Ok(())
+    }
+    let statements = events.into_iter().map(enqueue).collect::<QueryResult<Vec<_>>>()?;
+    write_logged_batch(statements, session).await?;
+    wake_relay();
+    Ok(())
+}
+
+#[async_trait]
+impl OutboxResolver for OutboxDatabase {
+    type Store = CachedSession;
+
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.event_outbox"), err)]
+    async fn get_pending(bucket: i32, limit: i32, session: &'static CachedSession) -> QueryResult<Vec<OutboxEntry>> {
+        session.query_prepared(GET_PENDING, (bucket, limit))
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
+            .into_typed::<OutboxEntry>()
+            .map(|row| row.map_err(|e| ServiceError::ServerError(e.to_string())))
+            .collect()
+    }
+    #[tracing::instrument(skip(session, entry), fields(repository = "movie_keyspace.event_outbox", event_id = entry.event_id.as_str()), err)]
+    async fn mark_sent(entry: &OutboxEntry, delivery: Delivery, session: &'static CachedSession) -> QueryResult<SentEntry> {
+        let sent = SentEntry::new(entry, delivery);
+        write_logged_batch(vec![
+            (DELETE_ENTRY, bind((entry.bucket, entry.entry_id))?),
+            (INSERT_SENT, bind(sent.clone())?),
+        ], session).await?;
+        Ok(sent)
+    }
+    #[tracing::instrument(skip(session, entry), fields(repository = "movie_keyspace.event_outbox", event_id = entry.event_id.as_str()), err)]
+    async fn record_failure(entry: &OutboxEntry, error: String, session: &'static CachedSession) -> QueryResult<()> {
+        session.query_prepared(RECORD_FAILURE, (entry.attempts, entry.last_error.clone(), entry.retry_at, entry.bucket, entry.entry_id))
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?;
+        Ok(())
+    }
+    #[tracing::instrument(skip(session, entry), fields(repository = "movie_keyspace.event_outbox_dead", event_id = entry.event_id.as_str()), err)]
+    async fn dead_letter(entry: &OutboxEntry, session: &'static CachedSession) -> QueryResult<()> {
+        write_logged_batch(vec![
+            (DELETE_ENTRY, bind((entry.bucket, entry.entry_id))?),
+            (INSERT_DEAD, bind(entry.clone())?),
+        ], session).await
+    }
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.event_outbox_leases"), err)]
+    async fn claim_bucket(bucket: i32, owner: &str, lease: Duration, session: &'static CachedSession) -> QueryResult<bool> {
+        let ttl = lease.as_secs().max(1) as i32;
+        let renewed = session.query_prepared(RENEW_LEASE, (ttl, owner, bucket, owner))
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?;
+        if is_applied(renewed) {
+            return Ok(true)
+        }
+        session.query_prepared(TAKE_LEASE, (bucket, owner, ttl))
+            .await
+            .map(is_applied)
+            .map_err(|_| ServiceError::DatabaseError)
+    }
+}
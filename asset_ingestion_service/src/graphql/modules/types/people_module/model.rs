@@ -1,11 +1,12 @@
-use common_utils::{QueryResult, inc_person_id};
+use std::collections::HashMap;
+use common_utils::QueryResult;
 use scylla::{ValueList, FromRow, Session, IntoUserType, FromUserType};
 use serde::{Deserialize, Serialize};
 use chrono::NaiveDate;
 use strum_macros::{EnumString, Display};
-use crate::db::CachedSession;
 use async_graphql::Enum;
 
+use super::super::credits::model::Credit;
 use super::{resolver::PersonResolver, schema::{PersonType, PersonInput}};
 
 #[derive(Debug, FromRow, Clone, Deserialize, Serialize, ValueList)]
@@ -39,6 +40,23 @@ pub struct NewPerson {
     pub profile_path: Vec<String>,
 }
 
+/// Row of `people_by_external_id`
+#[derive(Debug, FromRow, Clone, Deserialize, Serialize, ValueList)]
+pub struct PersonExternalId { 
+    pub external_id: String,
+    pub person_id: i32,
+}
+
+/// External id of the TMDB person `tmdb_id`, in the form `parse_external_ids` writes
+pub fn tmdb_external_id(tmdb_id: i32) -> String { 
+    format!("tmdb:{}", tmdb_id)
+}
+
+/// Stored in place of a birthday or death date that was not given
+pub fn unknown_date() -> NaiveDate { 
+    NaiveDate::from_ymd(2015, 9, 8)
+}
+
 #[derive(Copy, Clone, Eq, Debug, PartialEq, Serialize, SmartDefault, Deserialize, Enum, Display, EnumString)]
 #[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
 pub enum Gender { 
@@ -69,12 +87,29 @@ impl From<&Person> for PersonType {
     }
 }
 
-impl From<&PersonInput> for NewPerson { 
-    fn from(f: &PersonInput) -> Self {
-        let id = inc_person_id();
-        let default_value = NaiveDate::from_ymd(2015, 9, 8);
+impl NewPerson { 
+    /// Someone known only by the name they were credited under
+    pub fn named(person_id: i32, name: &str) -> Self {
+        let default_value = unknown_date();
+        Self { 
+            person_id,
+            name: name.to_string(),
+            awards: vec![String::new()],
+            biography: String::new(),
+            birthday: default_value,
+            death_date: default_value,
+            gender: Gender::default().to_string(),
+            homepage: String::new(),
+            known_for: vec![String::new()],
+            place_of_birth: String::new(),
+            profile_path: vec![String::new()],
+        }
+    }
+    /// `person_id` comes from `Person::next_person_id` for a new person
+    pub fn new(person_id: i32, f: &PersonInput) -> Self {
+        let default_value = unknown_date();
         Self { 
-            person_id: id.clone() ,
+            person_id,
             name: f.name.clone(),
             awards: f.awards.clone().unwrap_or(vec![String::new()]) ,
             biography: f.biography.clone().unwrap_or_default() ,
@@ -91,22 +126,70 @@ impl From<&PersonInput> for NewPerson {
 
 
 impl Person { 
-    pub async fn get_person_by_id<PersonDatabase: PersonResolver>(session: &'static CachedSession, person_id: i32) -> QueryResult<Person> {
+    pub async fn get_person_by_id<PersonDatabase: PersonResolver>(session: &'static PersonDatabase::Store, person_id: i32) -> QueryResult<Person> {
         PersonDatabase::get_person_by_id(session, person_id).await
     }
-    pub async fn get_all_person<PersonDatabase: PersonResolver>(session: &'static CachedSession) -> QueryResult<Vec<Person>> {
+    pub async fn get_all_person<PersonDatabase: PersonResolver>(session: &'static PersonDatabase::Store) -> QueryResult<Vec<Person>> {
         PersonDatabase::get_all_person(session).await
     }
-    pub async fn get_person_by_name<PersonDatabase: PersonResolver>(session: &'static CachedSession, name: String) -> QueryResult<Person> {
+    pub async fn get_person_by_name<PersonDatabase: PersonResolver>(session: &'static PersonDatabase::Store, name: String) -> QueryResult<Person> {
         PersonDatabase::get_person_by_name(session, name).await
     }
-    pub async fn create_movie_person<PersonDatabase: PersonResolver>(session: &'static CachedSession, new_person: NewPerson) -> QueryResult<Person> {
+    pub async fn next_person_id<PersonDatabase: PersonResolver>(session: &'static PersonDatabase::Store) -> QueryResult<i32> {
+        PersonDatabase::next_person_id(session).await
+    }
+    pub async fn create_movie_person<PersonDatabase: PersonResolver>(session: &'static PersonDatabase::Store, new_person: NewPerson) -> QueryResult<Person> {
         PersonDatabase::create_movie_person(session, new_person).await
     }
-    pub async fn update_movie_person<PersonDatabase: PersonResolver>(session: &'static CachedSession, person_id: i32, new_person: NewPerson) -> QueryResult<Person> {
+    pub async fn update_movie_person<PersonDatabase: PersonResolver>(session: &'static PersonDatabase::Store, person_id: i32, new_person: NewPerson) -> QueryResult<Person> {
         PersonDatabase::update_movie_person(session, person_id, new_person).await
     }
-    pub async fn delete_movie_person<PersonDatabase: PersonResolver>(session: &'static CachedSession, person_id: i32, person_name: String) -> QueryResult<bool> {
+    pub async fn delete_movie_person<PersonDatabase: PersonResolver>(session: &'static PersonDatabase::Store, person_id: i32, person_name: String) -> QueryResult<bool> {
         PersonDatabase::delete_movie_person(session, person_id, person_name).await
     }
-}
\ No newline at end of file
+    pub async fn get_external_ids<PersonDatabase: PersonResolver>(session: &'static PersonDatabase::Store) -> QueryResult<Vec<PersonExternalId>> {
+        PersonDatabase::get_external_ids(session).await
+    }
+    pub async fn find_by_external_ids<PersonDatabase: PersonResolver>(session: &'static PersonDatabase::Store, external_ids: Vec<String>) -> QueryResult<Vec<PersonExternalId>> {
+        PersonDatabase::find_by_external_ids(session, external_ids).await
+    }
+    pub async fn add_external_ids<PersonDatabase: PersonResolver>(session: &'static PersonDatabase::Store, person_id: i32, external_ids: Vec<String>) -> QueryResult<()> {
+        PersonDatabase::add_external_ids(session, person_id, external_ids).await
+    }
+    pub async fn merge_people<PersonDatabase: PersonResolver>(session: &'static PersonDatabase::Store, source: Person, target: NewPerson) -> QueryResult<Person> {
+        PersonDatabase::merge_people(session, source, target).await
+    }
+}
+
+impl Person { 
+    /// Swaps the TMDB person ids of imported `credits` for local ones. Someone TMDB credits for the
+    /// first time is created from the name on the credit and mapped through `tmdb:<id>`, so later
+    /// imports find them. An id mapped to more than one person goes to the oldest, like `createPerson`
+    pub async fn resolve_tmdb_credits<PersonDatabase: PersonResolver>(mut credits: Vec<Credit>, session: &'static PersonDatabase::Store) -> QueryResult<Vec<Credit>> {
+        let mut external_ids = credits.iter().map(|credit| tmdb_external_id(credit.person_id)).collect::<Vec<_>>();
+        external_ids.sort();
+        external_ids.dedup();
+        if external_ids.is_empty() { 
+            return Ok(credits);
+        }
+        let mut local_ids: HashMap<String, i32> = HashMap::new();
+        for row in PersonDatabase::find_by_external_ids(session, external_ids).await? { 
+            let person_id = local_ids.entry(row.external_id).or_insert(row.person_id);
+            *person_id = (*person_id).min(row.person_id);
+        }
+        for credit in credits.iter_mut() { 
+            let external_id = tmdb_external_id(credit.person_id);
+            credit.person_id = match local_ids.get(&external_id) { 
+                Some(person_id) => *person_id,
+                None => { 
+                    let person_id = PersonDatabase::next_person_id(session).await?;
+                    PersonDatabase::create_movie_person(session, NewPerson::named(person_id, &credit.name)).await?;
+                    PersonDatabase::add_external_ids(session, person_id, vec![external_id.clone()]).await?;
+                    local_ids.insert(external_id, person_id);
+                    person_id
+                }
+            };
+        }
+        Ok(credits)
+    }
+}
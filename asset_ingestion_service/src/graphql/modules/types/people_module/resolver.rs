@@ -1,18 +1,31 @@
+use std::ops::Range;
 use async_trait::async_trait;
-use common_utils::QueryResult;
+use common_utils::{QueryResult, error::ServiceError};
+use once_cell::sync::Lazy;
 use scylla::IntoTypedRows;
-use super::model::{NewPerson, Person};
-use crate::db::CachedSession;
+use tokio::sync::Mutex;
+use super::model::{NewPerson, Person, PersonExternalId};
+use crate::db::{CachedSession, bind, is_applied, write_logged_batch};
 
 
+/// `Store` is the Scylla session for `PersonDatabase`, or an in-memory store in tests
 #[async_trait]
-pub trait PersonResolver { 
-    async fn get_person_by_id(session: &'static CachedSession, person_id: i32) -> QueryResult<Person>;
-    async fn get_all_person(session: &'static CachedSession) -> QueryResult<Vec<Person>>;
-    async fn get_person_by_name(session: &'static CachedSession, name: String) -> QueryResult<Person>;
-    async fn create_movie_person(session: &'static CachedSession, new_person: NewPerson) -> QueryResult<Person>;
-    async fn update_movie_person(session: &'static CachedSession, person_id: i32, new_person: NewPerson) -> QueryResult<Person>;
-    async fn delete_movie_person(session: &'static CachedSession, person_id: i32, person_name: String) -> QueryResult<bool>;
+pub trait PersonResolver: Send + Sync + 'static { 
+    type Store: Send + Sync + 'static;
+    async fn get_person_by_id(session: &'static Self::Store, person_id: i32) -> QueryResult<Person>;
+    async fn get_all_person(session: &'static Self::Store) -> QueryResult<Vec<Person>>;
+    async fn get_person_by_name(session: &'static Self::Store, name: String) -> QueryResult<Person>;
+    /// An id no person has had yet, on this instance or any other, before or after a restart
+    async fn next_person_id(session: &'static Self::Store) -> QueryResult<i32>;
+    async fn create_movie_person(session: &'static Self::Store, new_person: NewPerson) -> QueryResult<Person>;
+    async fn update_movie_person(session: &'static Self::Store, person_id: i32, new_person: NewPerson) -> QueryResult<Person>;
+    async fn delete_movie_person(session: &'static Self::Store, person_id: i32, person_name: String) -> QueryResult<bool>;
+    async fn get_external_ids(session: &'static Self::Store) -> QueryResult<Vec<PersonExternalId>>;
+    /// Everyone known under any of `external_ids`
+    async fn find_by_external_ids(session: &'static Self::Store, external_ids: Vec<String>) -> QueryResult<Vec<PersonExternalId>>;
+    async fn add_external_ids(session: &'static Self::Store, person_id: i32, external_ids: Vec<String>) -> QueryResult<()>;
+    /// Writes the merged `target`, removes `source` and moves its external ids over to `target`
+    async fn merge_people(session: &'static Self::Store, source: Person, target: NewPerson) -> QueryResult<Person>;
 }
 
 static GET_ALL_PERSON: &str = "SELECT * FROM movie_keyspace.person_object;";
@@ -46,11 +59,79 @@ static UPDATE_MOVIE_PERSON: &str = "
         WHERE person_id = ? ;
 ";
 static DELETE_MOVIE_PERSON: &str = "DELETE FROM movie_keyspace.person_object WHERE person_id = ? AND name = ?;";
+static GET_ALL_EXTERNAL_IDS: &str = "SELECT * FROM movie_keyspace.people_by_external_id;";
+static GET_BY_EXTERNAL_IDS: &str = "SELECT * FROM movie_keyspace.people_by_external_id WHERE external_id IN ?;";
+// Served by the secondary index on person_id
+static GET_EXTERNAL_IDS_OF_PERSON: &str = "SELECT * FROM movie_keyspace.people_by_external_id WHERE person_id = ?;";
+static INSERT_EXTERNAL_ID: &str = "INSERT INTO movie_keyspace.people_by_external_id (external_id, person_id) VALUES (?, ?);";
+static DELETE_EXTERNAL_ID: &str = "DELETE FROM movie_keyspace.people_by_external_id WHERE external_id = ? AND person_id = ?;";
 
+static GET_ID_COUNTER: &str = "SELECT next_id FROM movie_keyspace.id_counters WHERE name = ?;";
+static INIT_ID_COUNTER: &str = "INSERT INTO movie_keyspace.id_counters (name, next_id) VALUES (?, ?) IF NOT EXISTS;";
+static ADVANCE_ID_COUNTER: &str = "UPDATE movie_keyspace.id_counters SET next_id = ? WHERE name = ? IF next_id = ?;";
+static GET_MAX_PERSON_ID: &str = "SELECT MAX(person_id) FROM movie_keyspace.person_object;";
+
+/// Row of `id_counters` that person ids are handed out from
+const PERSON_COUNTER: &str = "person";
+/// Person ids are claimed this many at a time, ids left in a block when the instance stops are skipped
+const PERSON_ID_BLOCK: i32 = 100;
+/// What is left of the block of person ids this instance claimed last
+static PERSON_IDS: Lazy<Mutex<Range<i32>>> = Lazy::new(|| Mutex::new(0..0));
+
+#[derive(Default)]
 pub struct PersonDatabase;
 
+impl PersonDatabase { 
+    /// Claims the next `PERSON_ID_BLOCK` ids with a compare-and-set on the counter, retrying when
+    /// another instance claimed them first. A missing counter starts after the highest id stored,
+    /// so the people created before it existed keep theirs
+    async fn claim_person_ids(session: &'static CachedSession) -> QueryResult<Range<i32>> { 
+        loop { 
+            let next_id = session.query_prepared(GET_ID_COUNTER, (PERSON_COUNTER,))
+                .await
+                .map_err(|_| ServiceError::DatabaseError)?
+                .rows_or_empty()
+                .into_typed::<(i32,)>()
+                .next()
+                .transpose()
+                .map_err(|e| ServiceError::ServerError(e.to_string()))?;
+            let next_id = match next_id { 
+                Some((next_id,)) => next_id,
+                None => { 
+                    let first = Self::max_person_id(session).await? + 1;
+                    session.query_prepared(INIT_ID_COUNTER, (PERSON_COUNTER, first))
+                        .await
+                        .map_err(|_| ServiceError::DatabaseError)?;
+                    continue;
+                }
+            };
+            let end = next_id.checked_add(PERSON_ID_BLOCK).ok_or_else(|| ServiceError::ServerError("Person ids have run out".to_string()))?;
+            let claimed = session.query_prepared(ADVANCE_ID_COUNTER, (end, PERSON_COUNTER, next_id))
+                .await
+                .map(is_applied)
+                .map_err(|_| ServiceError::DatabaseError)?;
+            if claimed { 
+                return Ok(next_id..end)
+            }
+        }
+    }
+    async fn max_person_id(session: &'static CachedSession) -> QueryResult<i32> { 
+        session.query_prepared(GET_MAX_PERSON_ID, ())
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
+            .into_typed::<(Option<i32>,)>()
+            .next()
+            .transpose()
+            .map_err(|e| ServiceError::ServerError(e.to_string()))
+            .map(|row| row.and_then(|(max,)| max).unwrap_or(0))
+    }
+}
+
 #[async_trait]
 impl PersonResolver for PersonDatabase { 
+    type Store = CachedSession;
+
     #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.person_object"))]
     async fn get_person_by_id(session: &'static CachedSession, person_id: i32) -> QueryResult<Person> {
         let response = session
@@ -61,8 +142,8 @@ impl PersonResolver for PersonDatabase {
             .unwrap_or_default()
             .into_typed::<Person>()
             .next()
-            .unwrap()
-            .unwrap();
+            .ok_or(ServiceError::NotFound)?
+            .map_err(|e| ServiceError::ServerError(e.to_string()))?;
         Ok(response)
 
     }
@@ -98,6 +179,14 @@ impl PersonResolver for PersonDatabase {
         log::info!("Database response {:#?}", response);
         Ok(response)
     }
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.id_counters"), err)]
+    async fn next_person_id(session: &'static CachedSession) -> QueryResult<i32> {
+        let mut ids = PERSON_IDS.lock().await;
+        if ids.is_empty() { 
+            *ids = PersonDatabase::claim_person_ids(session).await?;
+        }
+        ids.next().ok_or(ServiceError::DatabaseError)
+    }
     #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.person_object"))]
     async fn create_movie_person(session: &'static CachedSession, new_person: NewPerson) -> QueryResult<Person> {
         let response = session 
@@ -136,7 +225,56 @@ impl PersonResolver for PersonDatabase {
             .is_ok();
         Ok(response)
     }
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.people_by_external_id"), err)]
+    async fn get_external_ids(session: &'static CachedSession) -> QueryResult<Vec<PersonExternalId>> {
+        session.query_prepared(GET_ALL_EXTERNAL_IDS, ())
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
+            .into_typed::<PersonExternalId>()
+            .map(|row| row.map_err(|e| ServiceError::ServerError(e.to_string())))
+            .collect()
+    }
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.people_by_external_id"), err)]
+    async fn find_by_external_ids(session: &'static CachedSession, external_ids: Vec<String>) -> QueryResult<Vec<PersonExternalId>> {
+        session.query_prepared(GET_BY_EXTERNAL_IDS, (external_ids,))
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
+            .into_typed::<PersonExternalId>()
+            .map(|row| row.map_err(|e| ServiceError::ServerError(e.to_string())))
+            .collect()
+    }
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.people_by_external_id"), err)]
+    async fn add_external_ids(session: &'static CachedSession, person_id: i32, external_ids: Vec<String>) -> QueryResult<()> {
+        if external_ids.is_empty() { 
+            return Ok(());
+        }
+        let statements = external_ids
+            .into_iter()
+            .map(|external_id| Ok((INSERT_EXTERNAL_ID, bind((external_id, person_id))?)))
+            .collect::<QueryResult<Vec<_>>>()?;
+        write_logged_batch(statements, session).await
+    }
+    /// One logged batch, the source never disappears without the target being written
+    #[tracing::instrument(skip(session, target), fields(repository = "movie_keyspace.person_object"), err)]
+    async fn merge_people(session: &'static CachedSession, source: Person, target: NewPerson) -> QueryResult<Person> {
+        let external_ids = session.query_prepared(GET_EXTERNAL_IDS_OF_PERSON, (source.person_id,))
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
+            .into_typed::<PersonExternalId>()
+            .map(|row| row.map_err(|e| ServiceError::ServerError(e.to_string())))
+            .collect::<QueryResult<Vec<_>>>()?;
+        let mut statements = vec![
+            (CREATE_MOVIE_PERSON, bind(target.clone())?),
+            (DELETE_MOVIE_PERSON, bind((source.person_id, source.name.clone()))?),
+        ];
+        for row in external_ids { 
+            statements.push((DELETE_EXTERNAL_ID, bind((row.external_id.clone(), source.person_id))?));
+            statements.push((INSERT_EXTERNAL_ID, bind((row.external_id, target.person_id))?));
+        }
+        write_logged_batch(statements, session).await?;
+        PersonDatabase::get_person_by_id(session, target.person_id).await
+    }
 }
-
-
-
@@ -1,12 +1,15 @@
 use async_graphql::*;
 use serde::{Deserialize, Serialize};
-use crate::graphql::config::get_conn_from_ctx;
+use crate::graphql::config::get_store_from_ctx;
 use crate::{to_int, to_bigint};
 use chrono::NaiveDate;
-use super::resolver::PersonDatabase;
+use super::resolver::{PersonDatabase, PersonResolver};
+use std::marker::PhantomData;
 use super::model::{Person, Gender, NewPerson};
+use super::super::entity_resolution::model::parse_external_ids;
+/// `R` picks the storage the person queries go through
 #[derive(Default)]
-pub struct PersonQuery;
+pub struct PersonQuery<R = PersonDatabase>(PhantomData<R>);
 #[derive(SimpleObject, Clone, Debug, Serialize, Deserialize)]
 pub struct PersonType { 
     pub person_id: ID,
@@ -22,11 +25,11 @@ pub struct PersonType {
     pub profile_path: Vec<String>,
 }
 #[Object(extends, cache_control(max_age = 40))]
-impl PersonQuery { 
+impl<R: PersonResolver> PersonQuery<R> { 
     #[tracing::instrument(skip(self, ctx))]
     #[graphql(name = "getAllPersons")]
     async fn get_all_person(&self, ctx: &Context<'_>) -> FieldResult<Vec<PersonType>> { 
-        let response = Person::get_all_person::<PersonDatabase>(get_conn_from_ctx(ctx))
+        let response = Person::get_all_person::<R>(get_store_from_ctx(ctx))
             .await
             .expect("")
             .iter()
@@ -37,7 +40,7 @@ impl PersonQuery {
     #[tracing::instrument(skip(self, ctx))]
     #[graphql(name = "getPersonByName")]
     async fn get_person_info_by_name(&self, ctx: &Context<'_>, person_name: String) -> FieldResult<PersonType> { 
-        let response = Person::get_person_by_name::<PersonDatabase>(get_conn_from_ctx(ctx), person_name)
+        let response = Person::get_person_by_name::<R>(get_store_from_ctx(ctx), person_name)
             .await
             .ok()
             .map(|f| PersonType::from(&f))
@@ -47,7 +50,7 @@ impl PersonQuery {
     #[tracing::instrument(skip(self, ctx))]
     #[graphql(name = "getPersonById")]
     async fn get_person_info_by_id(&self, ctx: &Context<'_>, person_id: ID) -> FieldResult<PersonType> { 
-        let response = Person::get_person_by_id::<PersonDatabase>(get_conn_from_ctx(ctx), to_int(person_id))
+        let response = Person::get_person_by_id::<R>(get_store_from_ctx(ctx), to_int(person_id))
             .await
             .ok()
             .map(|f| PersonType::from(&f))
@@ -58,7 +61,7 @@ impl PersonQuery {
 }
 
 #[derive(Default)]
-pub struct PersonMutation;
+pub struct PersonMutation<R = PersonDatabase>(PhantomData<R>);
 
 
 #[derive(InputObject, Debug, Deserialize, Serialize)]
@@ -83,27 +86,52 @@ pub struct PersonInput {
     pub place_of_birth: Option<String>,
     /// S3 profile image link
     pub profile_path: Option<Vec<String>>,
+    /// TMDB or IMDb ids written as <source>:<id>, e.g. tmdb:287 or imdb:nm0000093
+    pub external_ids: Option<Vec<String>>,
 }
 #[Object]
-impl PersonMutation {   
+impl<R: PersonResolver> PersonMutation<R> {   
+    /// A person already known under one of the `externalIds` is returned, with any new ids
+    /// added to them, instead of being created a second time
     #[tracing::instrument(skip(self, ctx))]
     #[graphql(name = "createPerson")]
     async fn create_person(&self, ctx: &Context<'_>, new_person: PersonInput) -> FieldResult<PersonType> { 
-        let new_person = Person::create_movie_person::<PersonDatabase>(
-            get_conn_from_ctx(ctx), 
-        NewPerson::from(&new_person))
-        .await
-        .expect("Unable to get any response from the server");
-        Ok(PersonType::from(&new_person))
+        let session = get_store_from_ctx(ctx);
+        let external_ids = parse_external_ids(new_person.external_ids.clone().unwrap_or_default())
+            .map_err(|e| e.extend())?;
+        let known = Person::find_by_external_ids::<R>(session, external_ids.clone())
+            .await
+            .map_err(|e| e.extend())?
+            .into_iter()
+            .map(|row| row.person_id)
+            .min();
+        let person = match known { 
+            Some(person_id) => Person::get_person_by_id::<R>(session, person_id)
+                .await
+                .map_err(|e| e.extend())?,
+            None => { 
+                let person_id = Person::next_person_id::<R>(session)
+                    .await
+                    .map_err(|e| e.extend())?;
+                Person::create_movie_person::<R>(session, NewPerson::new(person_id, &new_person))
+                    .await
+                    .map_err(|e| e.extend())?
+            }
+        };
+        Person::add_external_ids::<R>(session, person.person_id, external_ids)
+            .await
+            .map_err(|e| e.extend())?;
+        Ok(PersonType::from(&person))
     }
 
     #[tracing::instrument(skip(self, ctx))]
     #[graphql(name = "updatePerson")]
     pub async fn update_person(&self, ctx: &Context<'_>, person_id: ID, new_person: PersonInput)  -> FieldResult<PersonType> { 
-        let res = Person::update_movie_person::<PersonDatabase>(
-            get_conn_from_ctx(ctx),
-            to_int(person_id),
-            NewPerson::from(&new_person))
+        let person_id = to_int(person_id);
+        let res = Person::update_movie_person::<R>(
+            get_store_from_ctx(ctx),
+            person_id,
+            NewPerson::new(person_id, &new_person))
             .await
             .expect("Unable to get any response from the database");
         Ok(PersonType::from(&res))
@@ -111,8 +139,8 @@ impl PersonMutation {
     #[tracing::instrument(skip(self, ctx))]
     #[graphql(name = "deleteGenre")]
     pub async fn delete_genre(&self, ctx: &Context<'_>, person_id: ID, person_name: String) -> FieldResult<bool> { 
-        let res = Person::delete_movie_person::<PersonDatabase>(
-            get_conn_from_ctx(ctx),
+        let res = Person::delete_movie_person::<R>(
+            get_store_from_ctx(ctx),
             to_int(person_id),
             person_name)
             .await
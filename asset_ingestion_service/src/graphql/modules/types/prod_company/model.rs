@@ -5,7 +5,6 @@ use scylla::{FromRow, ValueList};
 use scylla::macros::{FromUserType, IntoUserType};
 use serde::{Serialize, Deserialize};
 use strum_macros::{EnumString, Display};
-use crate::db::CachedSession;
 use super::resolver::ProdCompanyResolver;
 use super::schema::{ProductionCompanyType, InputProductionCompany};
 // use scylla::frame::value::{MaybeUnset, Unset};
@@ -39,6 +38,13 @@ pub struct NewProductionComp {
     pub parent_company: String
 }
 
+/// Row of `companies_by_external_id`
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize, ValueList)]
+pub struct CompanyExternalId { 
+    pub external_id: String,
+    pub company_id: i64,
+}
+
 #[derive(Copy, Clone, Eq, SmartDefault, PartialEq, Serialize, Deserialize, Enum, Display, EnumString)]
 #[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
 pub enum OriginCountry { 
@@ -87,32 +93,44 @@ impl From<&InputProductionCompany> for NewProductionComp {
 
 impl ProductionCompany { 
     #[tracing::instrument(skip(session))]
-    pub async fn get_company_id<CompanyDatabase: ProdCompanyResolver>(id: i64, session: &'static CachedSession) -> QueryResult<ProductionCompany> {
+    pub async fn get_company_id<CompanyDatabase: ProdCompanyResolver>(id: i64, session: &'static CompanyDatabase::Store) -> QueryResult<ProductionCompany> {
         CompanyDatabase::get_company_id(id, session).await
     }
     #[tracing::instrument(skip(session))]
-    pub async fn get_company_details<CompanyDatabase: ProdCompanyResolver>(name: String, session: &'static CachedSession) -> QueryResult<ProductionCompany> {
+    pub async fn get_company_details<CompanyDatabase: ProdCompanyResolver>(name: String, session: &'static CompanyDatabase::Store) -> QueryResult<ProductionCompany> {
         CompanyDatabase::get_company_details(name, session).await
     }
 
     #[tracing::instrument(skip(session))]
-    pub async fn get_all_companies<CompanyDatabase: ProdCompanyResolver>(session: &'static CachedSession) -> QueryResult<Vec<ProductionCompany>> {
+    pub async fn get_all_companies<CompanyDatabase: ProdCompanyResolver>(session: &'static CompanyDatabase::Store) -> QueryResult<Vec<ProductionCompany>> {
         CompanyDatabase::get_all_companies(session).await
     }
     #[tracing::instrument(skip(session))]
-    pub async fn get_companies_movie<CompanyDatabase: ProdCompanyResolver>(session: &'static CachedSession, movie_id: i64) -> QueryResult<Vec<ProductionCompany>>  {
+    pub async fn get_companies_movie<CompanyDatabase: ProdCompanyResolver>(session: &'static CompanyDatabase::Store, movie_id: i64) -> QueryResult<Vec<ProductionCompany>>  {
         CompanyDatabase::get_companies_movie(session, movie_id).await
     }
     #[tracing::instrument(skip(session))]
-    pub async fn create_movie_company<CompanyDatabase: ProdCompanyResolver>(new_company: NewProductionComp, session: &'static CachedSession) -> QueryResult<ProductionCompany> {
+    pub async fn create_movie_company<CompanyDatabase: ProdCompanyResolver>(new_company: NewProductionComp, session: &'static CompanyDatabase::Store) -> QueryResult<ProductionCompany> {
         CompanyDatabase::create_movie_company(new_company, session).await
     }
     #[tracing::instrument(skip(session))]
-    pub async fn update_movie_company<CompanyDatabase: ProdCompanyResolver>(new_company: NewProductionComp, id: i64, session: &'static CachedSession) -> QueryResult<ProductionCompany> {
+    pub async fn update_movie_company<CompanyDatabase: ProdCompanyResolver>(new_company: NewProductionComp, id: i64, session: &'static CompanyDatabase::Store) -> QueryResult<ProductionCompany> {
         CompanyDatabase::update_movie_company(new_company, id, session).await
     }
     #[tracing::instrument(skip(session))]
-    pub async fn delete_movie_company<CompanyDatabase: ProdCompanyResolver>(id: i64, company_name: String, session: &'static CachedSession) -> QueryResult<bool> {
+    pub async fn delete_movie_company<CompanyDatabase: ProdCompanyResolver>(id: i64, company_name: String, session: &'static CompanyDatabase::Store) -> QueryResult<bool> {
         CompanyDatabase::delete_movie_company(id, company_name, session).await
     }
+    pub async fn get_external_ids<CompanyDatabase: ProdCompanyResolver>(session: &'static CompanyDatabase::Store) -> QueryResult<Vec<CompanyExternalId>> {
+        CompanyDatabase::get_external_ids(session).await
+    }
+    pub async fn find_by_external_ids<CompanyDatabase: ProdCompanyResolver>(external_ids: Vec<String>, session: &'static CompanyDatabase::Store) -> QueryResult<Vec<CompanyExternalId>> {
+        CompanyDatabase::find_by_external_ids(external_ids, session).await
+    }
+    pub async fn add_external_ids<CompanyDatabase: ProdCompanyResolver>(company_id: i64, external_ids: Vec<String>, session: &'static CompanyDatabase::Store) -> QueryResult<()> {
+        CompanyDatabase::add_external_ids(company_id, external_ids, session).await
+    }
+    pub async fn merge_companies<CompanyDatabase: ProdCompanyResolver>(source_id: i64, target: NewProductionComp, session: &'static CompanyDatabase::Store) -> QueryResult<Vec<ProductionCompany>> {
+        CompanyDatabase::merge_companies(source_id, target, session).await
+    }
 }
@@ -3,8 +3,8 @@ use std::collections::HashMap;
 use async_graphql::dataloader::Loader;
 use async_trait::async_trait;
 use scylla::IntoTypedRows;
-use crate::db::CachedSession;
-use super::model::{NewProductionComp, ProductionCompany};
+use crate::db::{CachedSession, bind, write_logged_batch};
+use super::model::{CompanyExternalId, NewProductionComp, ProductionCompany};
 use common_utils::{QueryResult, error::ServiceError};
 use super::schema::ProductionCompanyType;
 
@@ -38,16 +38,26 @@ impl Loader<String> for CompanyDetailsLoader {
     }
 }
 
+/// Companies live behind `Store`, which lets the resolver run without a Scylla cluster
 #[async_trait]
-pub trait ProdCompanyResolver { 
-    async fn get_company_id(id: i64, session: &'static CachedSession) -> QueryResult<ProductionCompany>; 
-    async fn get_all_companies(session: &'static CachedSession) -> QueryResult<Vec<ProductionCompany>>;
-    async fn get_company_details(name: String, session: &'static CachedSession) -> QueryResult<ProductionCompany>;
-    async fn get_companies_movie(session: &'static CachedSession, movie_id: i64) -> QueryResult<Vec<ProductionCompany>>;
-    async fn create_movie_company(new_company: NewProductionComp, session: &'static CachedSession) -> QueryResult<ProductionCompany>;
-    async fn update_movie_company(new_company: NewProductionComp, id: i64, session: &'static CachedSession) -> QueryResult<ProductionCompany>;
-    async fn delete_movie_company(id: i64, company_name: String, session: &'static CachedSession) -> QueryResult<bool>;
+pub trait ProdCompanyResolver: Send + Sync + 'static { 
+    type Store: Send + Sync + 'static;
+    async fn get_company_id(id: i64, session: &'static Self::Store) -> QueryResult<ProductionCompany>; 
+    async fn get_all_companies(session: &'static Self::Store) -> QueryResult<Vec<ProductionCompany>>;
+    async fn get_company_details(name: String, session: &'static Self::Store) -> QueryResult<ProductionCompany>;
+    async fn get_companies_movie(session: &'static Self::Store, movie_id: i64) -> QueryResult<Vec<ProductionCompany>>;
+    async fn create_movie_company(new_company: NewProductionComp, session: &'static Self::Store) -> QueryResult<ProductionCompany>;
+    async fn update_movie_company(new_company: NewProductionComp, id: i64, session: &'static Self::Store) -> QueryResult<ProductionCompany>;
+    async fn delete_movie_company(id: i64, company_name: String, session: &'static Self::Store) -> QueryResult<bool>;
+    async fn get_external_ids(session: &'static Self::Store) -> QueryResult<Vec<CompanyExternalId>>;
+    /// Every company known under any of `external_ids`
+    async fn find_by_external_ids(external_ids: Vec<String>, session: &'static Self::Store) -> QueryResult<Vec<CompanyExternalId>>;
+    async fn add_external_ids(company_id: i64, external_ids: Vec<String>, session: &'static Self::Store) -> QueryResult<()>;
+    /// Writes the merged `target` and removes every row of the source company, moving its external
+    /// ids to the target. Returns the removed rows
+    async fn merge_companies(source_id: i64, target: NewProductionComp, session: &'static Self::Store) -> QueryResult<Vec<ProductionCompany>>;
 }
+#[derive(Default)]
 pub struct CompanyDatabase;
 static GET_COMPANY_BY_ID: &str = "SELECT * FROM movie_keyspace.movie_company WHERE company_id = ?;";
 static GET_ALL_COMPANY: &str = "SELECT * FROM movie_keyspace.movie_company;";
@@ -76,9 +86,18 @@ static UPDATE_COMPANY_DETAILS: &str = "
 ";
 static DELETE_COMPANY: &str = "DELETE FROM movies_keyspace.movie_company WHERE company_id = ? AND name = ?";
 static GET_COMPANY_DETAILS: &str = "SELECT * FROM from movie_keyspace.movie_company WHERE name = ?;";
+static DELETE_COMPANY_ROW: &str = "DELETE FROM movie_keyspace.movie_company WHERE company_id = ? AND name = ?;";
+static GET_ALL_EXTERNAL_IDS: &str = "SELECT * FROM movie_keyspace.companies_by_external_id;";
+static GET_BY_EXTERNAL_IDS: &str = "SELECT * FROM movie_keyspace.companies_by_external_id WHERE external_id IN ?;";
+// Served by the secondary index on company_id
+static GET_EXTERNAL_IDS_OF_COMPANY: &str = "SELECT * FROM movie_keyspace.companies_by_external_id WHERE company_id = ?;";
+static INSERT_EXTERNAL_ID: &str = "INSERT INTO movie_keyspace.companies_by_external_id (external_id, company_id) VALUES (?, ?);";
+static DELETE_EXTERNAL_ID: &str = "DELETE FROM movie_keyspace.companies_by_external_id WHERE external_id = ? AND company_id = ?;";
 
 #[async_trait]
 impl ProdCompanyResolver for CompanyDatabase { 
+    type Store = CachedSession;
+
     
     #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.movie_company"))]
     async fn get_company_id(id: i64, session: &'static CachedSession) -> QueryResult<ProductionCompany> { 
@@ -88,8 +107,8 @@ impl ProdCompanyResolver for CompanyDatabase {
             .rows_or_empty()
             .into_typed::<ProductionCompany>()
             .next()
-            .expect("")
-            .expect("");
+            .ok_or(ServiceError::NotFound)?
+            .map_err(|e| ServiceError::ServerError(e.to_string()))?;
         Ok(res)
     }
     #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.movie_company"))]
@@ -170,4 +189,62 @@ impl ProdCompanyResolver for CompanyDatabase {
         log::info!("The company details 🍺🍺 {:#?}", response);
         Ok(true)
     }
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.companies_by_external_id"), err)]
+    async fn get_external_ids(session: &'static CachedSession) -> QueryResult<Vec<CompanyExternalId>> {
+        session.query_prepared(GET_ALL_EXTERNAL_IDS, ())
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
+            .into_typed::<CompanyExternalId>()
+            .map(|row| row.map_err(|e| ServiceError::ServerError(e.to_string())))
+            .collect()
+    }
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.companies_by_external_id"), err)]
+    async fn find_by_external_ids(external_ids: Vec<String>, session: &'static CachedSession) -> QueryResult<Vec<CompanyExternalId>> {
+        session.query_prepared(GET_BY_EXTERNAL_IDS, (external_ids,))
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
+            .into_typed::<CompanyExternalId>()
+            .map(|row| row.map_err(|e| ServiceError::ServerError(e.to_string())))
+            .collect()
+    }
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.companies_by_external_id"), err)]
+    async fn add_external_ids(company_id: i64, external_ids: Vec<String>, session: &'static CachedSession) -> QueryResult<()> {
+        if external_ids.is_empty() { 
+            return Ok(());
+        }
+        let statements = external_ids
+            .into_iter()
+            .map(|external_id| Ok((INSERT_EXTERNAL_ID, bind((external_id, company_id))?)))
+            .collect::<QueryResult<Vec<_>>>()?;
+        write_logged_batch(statements, session).await
+    }
+    #[tracing::instrument(skip(session, target), fields(repository = "movie_keyspace.movie_company"), err)]
+    async fn merge_companies(source_id: i64, target: NewProductionComp, session: &'static CachedSession) -> QueryResult<Vec<ProductionCompany>> {
+        let source_rows = session.query_prepared(GET_COMPANY_BY_ID, (source_id,))
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
+            .into_typed::<ProductionCompany>()
+            .map(|row| row.map_err(|e| ServiceError::ServerError(e.to_string())))
+            .collect::<QueryResult<Vec<_>>>()?;
+        let external_ids = session.query_prepared(GET_EXTERNAL_IDS_OF_COMPANY, (source_id,))
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
+            .into_typed::<CompanyExternalId>()
+            .map(|row| row.map_err(|e| ServiceError::ServerError(e.to_string())))
+            .collect::<QueryResult<Vec<_>>>()?;
+        let mut statements = vec![(CREATE_COMPANY, bind(target.clone())?)];
+        for row in &source_rows { 
+            statements.push((DELETE_COMPANY_ROW, bind((row.company_id, row.name.clone()))?));
+        }
+        for row in external_ids { 
+            statements.push((DELETE_EXTERNAL_ID, bind((row.external_id.clone(), source_id))?));
+            statements.push((INSERT_EXTERNAL_ID, bind((row.external_id, target.company_id))?));
+        }
+        write_logged_batch(statements, session).await?;
+        Ok(source_rows)
+    }
 }
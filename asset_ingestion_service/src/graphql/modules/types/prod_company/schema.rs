@@ -2,11 +2,14 @@ use async_graphql::*;
 use async_graphql_actix_web::*;
 use super::{resolver::{ProdCompanyResolver, CompanyDatabase}, 
     model::{ProductionCompany, NewProductionComp, OriginCountry}};
-use crate::{graphql::{config::get_conn_from_ctx}, to_bigint};
+use crate::{graphql::{config::get_store_from_ctx}, to_bigint};
+use super::super::entity_resolution::model::parse_external_ids;
 use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
 
+/// Generic over the resolver, `CompanyDatabase` unless the schema is built for tests
 #[derive(Default)]
-pub struct ProductionCompanyQuery;
+pub struct ProductionCompanyQuery<R = CompanyDatabase>(PhantomData<R>);
 #[derive(SimpleObject, Clone, Debug, Serialize, Deserialize)]
 pub struct ProductionCompanyType { 
     pub company_id: ID,
@@ -21,17 +24,17 @@ pub struct ProductionCompanyType {
 }
 
 #[Object(extends, cache_control(max_age = 60))]
-impl ProductionCompanyQuery { 
+impl<R: ProdCompanyResolver> ProductionCompanyQuery<R> { 
     /// Resolver Reference for CompanyType
     #[tracing::instrument(skip(self, ctx))]
     #[graphql(entity, name = "getCompanyEntity")]
     async fn get_company_by_id(&self, ctx: &Context<'_>, company_id: ID) -> FieldResult<ProductionCompanyType> { 
-        find_company_internally(ctx, company_id).await
+        find_company_internally::<R>(ctx, company_id).await
     }
     #[tracing::instrument(skip(self, ctx))]
     #[graphql(name = "getAllCompanies")]
     async fn get_all_companies(&self, ctx: &Context<'_>) -> FieldResult<Vec<ProductionCompanyType>>  {
-        let res = ProductionCompany::get_all_companies::<CompanyDatabase>(get_conn_from_ctx(ctx))
+        let res = ProductionCompany::get_all_companies::<R>(get_store_from_ctx(ctx))
             .await
             .expect("Unable to get all the companies")
             .iter()
@@ -43,12 +46,12 @@ impl ProductionCompanyQuery {
     #[tracing::instrument(skip(self, ctx))]
     #[graphql(name = "getCompaniesByMovie")]
     async fn get_companies_by_movie(&self, ctx: &Context<'_>, movie_id: ID) -> Vec<ProductionCompanyType> { 
-        find_companies_by_movies(ctx, movie_id).await
+        find_companies_by_movies::<R>(ctx, movie_id).await
     }
 }
 
 #[derive(Default)]
-pub struct ProductionCompanyMutation;
+pub struct ProductionCompanyMutation<R = CompanyDatabase>(PhantomData<R>);
 
 #[derive(InputObject, Debug, Deserialize, Clone)]
 pub struct InputProductionCompany { 
@@ -75,37 +78,55 @@ pub struct InputProductionCompany {
     /// Parent Company
     #[graphql(validator(max_length = 25))]
     pub parent_company: Option<String>,
+    /// TMDB or IMDb ids written as <source>:<id>, e.g. tmdb:3
+    pub external_ids: Option<Vec<String>>,
 }
 
 #[Object(extends)]
-impl ProductionCompanyMutation { 
+impl<R: ProdCompanyResolver> ProductionCompanyMutation<R> { 
+    /// Returns the company already known under one of the `externalIds` rather than adding it again
     #[tracing::instrument(skip(self, ctx))]
     #[graphql(name = "createCompany")]
     async fn create_new_company(&self, ctx: &Context<'_>, new_product: InputProductionCompany) -> FieldResult<ProductionCompanyType> { 
-        let res = ProductionCompany::create_movie_company::<CompanyDatabase>(
-            NewProductionComp::from(&new_product),
-            get_conn_from_ctx(ctx))
+        let session = get_store_from_ctx(ctx);
+        let external_ids = parse_external_ids(new_product.external_ids.clone().unwrap_or_default())
+            .map_err(|e| e.extend())?;
+        let known = ProductionCompany::find_by_external_ids::<R>(external_ids.clone(), session)
             .await
-            .expect("");
+            .map_err(|e| e.extend())?
+            .into_iter()
+            .map(|row| row.company_id)
+            .min();
+        let res = match known { 
+            Some(company_id) => ProductionCompany::get_company_id::<R>(company_id, session)
+                .await
+                .map_err(|e| e.extend())?,
+            None => ProductionCompany::create_movie_company::<R>(NewProductionComp::from(&new_product), session)
+                .await
+                .map_err(|e| e.extend())?,
+        };
+        ProductionCompany::add_external_ids::<R>(res.company_id, external_ids, session)
+            .await
+            .map_err(|e| e.extend())?;
         Ok(ProductionCompanyType::from(&res))
     }
     #[tracing::instrument(skip(self, ctx))]
     #[graphql(name = "updateCompany")]
     async fn update_prod_company(&self, ctx: &Context<'_>, id: ID, new_company: InputProductionCompany) -> FieldResult<ProductionCompanyType> { 
-        let res = ProductionCompany::update_movie_company::<CompanyDatabase>(
+        let res = ProductionCompany::update_movie_company::<R>(
             NewProductionComp::from(&new_company),
             to_bigint(id),
-            get_conn_from_ctx(ctx)
+            get_store_from_ctx(ctx)
         ).await.expect("");
         Ok(ProductionCompanyType::from(&res))
     }
     #[tracing::instrument(skip(self, ctx))]
     #[graphql(name = "deleteCompany")]
     async fn delete_prod_company(&self, ctx: &Context<'_>, id: ID, company_name: String) -> FieldResult<bool> { 
-        let res = ProductionCompany::delete_movie_company::<CompanyDatabase>(
+        let res = ProductionCompany::delete_movie_company::<R>(
             to_bigint(id),
             company_name,
-            get_conn_from_ctx(ctx)
+            get_store_from_ctx(ctx)
         )
         .await
         .expect("");
@@ -115,8 +136,8 @@ impl ProductionCompanyMutation {
 
 /// Helper functions
 #[tracing::instrument(skip(ctx))]
-async fn find_company_internally(ctx: &Context<'_>, id: ID) -> FieldResult<ProductionCompanyType> { 
-    let company = ProductionCompany::get_company_id::<CompanyDatabase>(to_bigint(id), &get_conn_from_ctx(ctx))
+async fn find_company_internally<R: ProdCompanyResolver>(ctx: &Context<'_>, id: ID) -> FieldResult<ProductionCompanyType> { 
+    let company = ProductionCompany::get_company_id::<R>(to_bigint(id), get_store_from_ctx(ctx))
         .await
         .ok()
         .map(|f| ProductionCompanyType::from(&f))
@@ -125,9 +146,9 @@ async fn find_company_internally(ctx: &Context<'_>, id: ID) -> FieldResult<Produ
 }
 
 #[tracing::instrument(skip(ctx))]
-pub async fn find_companies_by_movies(ctx: &Context<'_>, movie_id: ID) -> Vec<ProductionCompanyType> { 
-    let company = ProductionCompany::get_companies_movie::<CompanyDatabase>(
-        get_conn_from_ctx(ctx), 
+pub async fn find_companies_by_movies<R: ProdCompanyResolver>(ctx: &Context<'_>, movie_id: ID) -> Vec<ProductionCompanyType> { 
+    let company = ProductionCompany::get_companies_movie::<R>(
+        get_store_from_ctx(ctx), 
         to_bigint(movie_id))
         .await
         .expect("")
@@ -137,10 +158,10 @@ pub async fn find_companies_by_movies(ctx: &Context<'_>, movie_id: ID) -> Vec<Pr
     company
 }
 
-pub async fn create_new_company(ctx: &Context<'_>, new_product: InputProductionCompany) -> ProductionCompanyType { 
-    let res = ProductionCompany::create_movie_company::<CompanyDatabase>(
+pub async fn create_new_company<R: ProdCompanyResolver>(ctx: &Context<'_>, new_product: InputProductionCompany) -> ProductionCompanyType { 
+    let res = ProductionCompany::create_movie_company::<R>(
         NewProductionComp::from(&new_product),
-        get_conn_from_ctx(ctx))
+        get_store_from_ctx(ctx))
         .await.expect("");
     ProductionCompanyType::from(&res)
 }
\ No newline at end of file
@@ -0,0 +1,135 @@
+use actix_web::HttpRequest;
+use common_utils::{QueryResult, availability::now_millis, decode_token, error::ServiceError};
+use scylla::macros::{FromRow, ValueList};
+use serde::{Deserialize, Serialize};
+use crate::generate_unique_id;
+use super::super::movies::model::{Movie, Status};
+use super::resolver::ReleaseResolver;
+
+/// When a movie is due to be released and withdrawn again, in epoch millis
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize, ValueList, PartialEq, Default)]
+pub struct ReleaseSchedule {
+    pub movie_id: i64,
+    pub publish_at: Option<i64>,
+    pub unpublish_at: Option<i64>,
+}
+
+impl ReleaseSchedule {
+    pub fn new(movie_id: i64, publish_at: Option<i64>, unpublish_at: Option<i64>) -> QueryResult<Self> {
+        if let (Some(publish_at), Some(unpublish_at)) = (publish_at, unpublish_at) {
+            if unpublish_at <= publish_at {
+                return Err(ServiceError::BadRequest("A movie has to be unpublished after it is published".to_string()))
+            }
+        }
+        Ok(Self { movie_id, publish_at, unpublish_at })
+    }
+    /// The times the scheduler has to change the movie's status at
+    pub fn boundaries(&self) -> Vec<i64> {
+        self.publish_at.into_iter().chain(self.unpublish_at).collect()
+    }
+    /// The status the movie is due to be in once `(after, until]` has passed, if the schedule
+    /// changes anything in it. Unpublishing wins when both fall in the same sweep
+    pub fn due(&self, after: i64, until: i64) -> Option<Status> {
+        let passed = |at: Option<i64>| at.map_or(false, |at| after < at && at <= until);
+        match (passed(self.publish_at), passed(self.unpublish_at)) {
+            (_, true) => Some(Status::Withdrawn),
+            (true, false) => Some(Status::Released),
+            _ => None,
+        }
+    }
+}
+
+// Columns after the primary key are in alphabetical order, the order `SELECT *` returns them in
+/// One entry of a movie's editorial history
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize, ValueList, PartialEq)]
+pub struct StatusChange {
+    pub movie_id: i64,
+    /// Snowflake id, so the history is ordered by when the change was made
+    pub change_id: i64,
+    pub changed_at: i64,
+    pub changed_by: String,
+    pub from_status: String,
+    /// The change broke the transition rules, see `Status::can_become`
+    pub overridden: bool,
+    pub reason: Option<String>,
+    pub to_status: String,
+}
+
+impl StatusChange {
+    /// The change moving `movie` to `to`, `None` when it is there already. A transition
+    /// `Status::can_become` doesn't allow is rejected unless `force` is set
+    pub fn check(movie: &Movie, to: Status, editor: &Editor, force: bool, reason: Option<String>) -> QueryResult<Option<Self>> {
+        let from = movie.status();
+        if from == to {
+            return Ok(None)
+        }
+        let allowed = from.can_become(to);
+        if !allowed && !force {
+            return Err(ServiceError::BadRequest(format!("A {} movie can't become {}, set `override` on setMovieStatus to force it", from, to)))
+        }
+        Ok(Some(Self::new(movie.movie_id, from, to, editor, !allowed, reason)))
+    }
+    pub fn new(movie_id: i64, from: Status, to: Status, editor: &Editor, overridden: bool, reason: Option<String>) -> Self {
+        Self {
+            movie_id,
+            change_id: generate_unique_id(),
+            changed_at: now_millis(),
+            changed_by: editor.name.clone(),
+            from_status: from.to_string(),
+            overridden,
+            reason,
+            to_status: to.to_string(),
+        }
+    }
+}
+
+/// Who a status change is recorded against, attached to each request by the `/graphql` handler
+#[derive(Debug, Clone)]
+pub struct Editor {
+    pub name: String,
+}
+
+impl Default for Editor {
+    /// Requests without a valid bearer token
+    fn default() -> Self {
+        Self { name: "anonymous".to_string() }
+    }
+}
+
+impl Editor {
+    /// Changes made by the release scheduler
+    pub fn scheduler() -> Self {
+        Self { name: "scheduler".to_string() }
+    }
+    /// The subject of the request's bearer token
+    pub fn new(req: &HttpRequest) -> Self {
+        req.headers()
+            .get("Authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer"))
+            .and_then(|token| decode_token(token.trim()).ok())
+            .map(|token| Self { name: token.claims.subject().to_string() })
+            .unwrap_or_default()
+    }
+}
+
+impl ReleaseSchedule {
+    pub async fn get_schedule<ReleaseDatabase: ReleaseResolver>(movie_id: i64, session: &'static ReleaseDatabase::Store) -> QueryResult<Option<ReleaseSchedule>> {
+        ReleaseDatabase::get_schedule(movie_id, session).await
+    }
+    pub async fn set_schedule<ReleaseDatabase: ReleaseResolver>(schedule: ReleaseSchedule, session: &'static ReleaseDatabase::Store) -> QueryResult<ReleaseSchedule> {
+        ReleaseDatabase::set_schedule(schedule, session).await
+    }
+    pub async fn get_boundaries<ReleaseDatabase: ReleaseResolver>(day: String, after: i64, until: i64, session: &'static ReleaseDatabase::Store) -> QueryResult<Vec<i64>> {
+        ReleaseDatabase::get_boundaries(day, after, until, session).await
+    }
+}
+
+impl StatusChange {
+    pub async fn record<ReleaseDatabase: ReleaseResolver>(change: StatusChange, session: &'static ReleaseDatabase::Store) -> QueryResult<StatusChange> {
+        ReleaseDatabase::record_change(change, session).await
+    }
+    pub async fn get_history<ReleaseDatabase: ReleaseResolver>(movie_id: i64, session: &'static ReleaseDatabase::Store) -> QueryResult<Vec<StatusChange>> {
+        ReleaseDatabase::get_history(movie_id, session).await
+    }
+}
@@ -0,0 +1,87 @@
+use async_trait::async_trait;
+use common_utils::{QueryResult, error::ServiceError};
+use scylla::IntoTypedRows;
+use crate::db::{CachedSession, bind, write_logged_batch};
+use super::super::availability::model::boundary_day;
+use super::model::{ReleaseSchedule, StatusChange};
+
+/// Release schedules with the days their changes fall on, and the status history of each movie
+#[async_trait]
+pub trait ReleaseResolver: Send + Sync + 'static {
+    type Store: Send + Sync + 'static;
+    async fn get_schedule(movie_id: i64, session: &'static Self::Store) -> QueryResult<Option<ReleaseSchedule>>;
+    /// Replaces the movie's schedule, the boundaries of the previous one are left behind
+    async fn set_schedule(schedule: ReleaseSchedule, session: &'static Self::Store) -> QueryResult<ReleaseSchedule>;
+    /// Movies with a scheduled change on `day` in `(after, until]`, possibly stale or repeated
+    async fn get_boundaries(day: String, after: i64, until: i64, session: &'static Self::Store) -> QueryResult<Vec<i64>>;
+    async fn record_change(change: StatusChange, session: &'static Self::Store) -> QueryResult<StatusChange>;
+    /// Newest change first
+    async fn get_history(movie_id: i64, session: &'static Self::Store) -> QueryResult<Vec<StatusChange>>;
+}
+
+#[derive(Default)]
+pub struct ReleaseDatabase;
+
+static GET_SCHEDULE: &str = "SELECT * FROM movie_keyspace.movie_release_schedule WHERE movie_id = ?;";
+static SET_SCHEDULE: &str = "INSERT INTO movie_keyspace.movie_release_schedule (movie_id, publish_at, unpublish_at) VALUES (?, ?, ?);";
+static INSERT_BOUNDARY: &str = "INSERT INTO movie_keyspace.release_boundaries (day, at, movie_id) VALUES (?, ?, ?);";
+static GET_BOUNDARIES: &str = "SELECT movie_id FROM movie_keyspace.release_boundaries WHERE day = ? AND at > ? AND at <= ?;";
+static INSERT_CHANGE: &str = "
+    INSERT INTO movie_keyspace.movie_status_history (
+        movie_id, change_id, changed_at, changed_by, from_status, overridden, reason, to_status
+    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?);
+";
+static GET_HISTORY: &str = "SELECT * FROM movie_keyspace.movie_status_history WHERE movie_id = ?;";
+
+#[async_trait]
+impl ReleaseResolver for ReleaseDatabase {
+    type Store = CachedSession;
+
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.movie_release_schedule"), err)]
+    async fn get_schedule(movie_id: i64, session: &'static CachedSession) -> QueryResult<Option<ReleaseSchedule>> {
+        session.query_prepared(GET_SCHEDULE, (movie_id,))
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
+            .into_typed::<ReleaseSchedule>()
+            .next()
+            .transpose()
+            .map_err(|e| ServiceError::ServerError(e.to_string()))
+    }
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.movie_release_schedule"), err)]
+    async fn set_schedule(schedule: ReleaseSchedule, session: &'static CachedSession) -> QueryResult<ReleaseSchedule> {
+        let mut statements = vec![(SET_SCHEDULE, bind(schedule.clone())?)];
+        for at in schedule.boundaries() {
+            statements.push((INSERT_BOUNDARY, bind((boundary_day(at), at, schedule.movie_id))?));
+        }
+        write_logged_batch(statements, session).await?;
+        Ok(schedule)
+    }
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.release_boundaries"), err)]
+    async fn get_boundaries(day: String, after: i64, until: i64, session: &'static CachedSession) -> QueryResult<Vec<i64>> {
+        session.query_prepared(GET_BOUNDARIES, (day, after, until))
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
+            .into_typed::<(i64,)>()
+            .map(|row| row.map(|(movie_id,)| movie_id).map_err(|e| ServiceError::ServerError(e.to_string())))
+            .collect()
+    }
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.movie_status_history"), err)]
+    async fn record_change(change: StatusChange, session: &'static CachedSession) -> QueryResult<StatusChange> {
+        session.query_prepared(INSERT_CHANGE, change.clone())
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?;
+        Ok(change)
+    }
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.movie_status_history"), err)]
+    async fn get_history(movie_id: i64, session: &'static CachedSession) -> QueryResult<Vec<StatusChange>> {
+        session.query_prepared(GET_HISTORY, (movie_id,))
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
+            .into_typed::<StatusChange>()
+            .map(|row| row.map_err(|e| ServiceError::ServerError(e.to_string())))
+            .collect()
+    }
+}
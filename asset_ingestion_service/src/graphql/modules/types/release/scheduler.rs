@@ -0,0 +1,98 @@
+use std::time::Duration;
+use common_utils::{QueryResult, availability::now_millis, error::ServiceError};
+use super::super::availability::model::boundary_days;
+use super::super::movies::{import::describe, model::{Movie, MoviePatch}, resolver::MovieResolver};
+use super::{model::{Editor, ReleaseSchedule, StatusChange}, resolver::ReleaseResolver};
+
+/// How often the scheduler looks for movies due to be published or unpublished
+const SCHEDULER_TICK: Duration = Duration::from_secs(60);
+/// Changes that fell due while no scheduler was running are caught up on for this long
+const SCHEDULER_LOOKBACK_MILLIS: i64 = 24 * 60 * 60 * 1000;
+
+/// Writes the new status and records who changed it, the patch publishes the movie for the search index
+pub async fn apply_change<M: MovieResolver, S: ReleaseResolver>(change: StatusChange, movies: &'static M::Store, releases: &'static S::Store) -> QueryResult<Movie> {
+    let patch = MoviePatch { status: Some(change.to_status.clone()), ..MoviePatch::default() };
+    let movie = Movie::patch_movie::<M>(change.movie_id, patch, movies).await?;
+    StatusChange::record::<S>(change, releases).await?;
+    Ok(movie)
+}
+
+/// Applies every schedule that fell due in `(after, until]`, returns how many movies changed.
+/// A change the transition rules forbid, such as releasing a canceled movie, is skipped. A movie
+/// that can't be read or written doesn't hold up the others, the first such error is returned
+/// once every movie has been tried so the sweep is retried
+pub async fn apply_schedules<M: MovieResolver, S: ReleaseResolver>(after: i64, until: i64, movies: &'static M::Store, releases: &'static S::Store) -> QueryResult<usize> {
+    let mut movie_ids = Vec::new();
+    for day in boundary_days(after, until) {
+        movie_ids.extend(ReleaseSchedule::get_boundaries::<S>(day, after, until, releases).await?);
+    }
+    movie_ids.sort_unstable();
+    movie_ids.dedup();
+
+    let mut changed = 0;
+    let mut failure = None;
+    for movie_id in movie_ids {
+        match apply_schedule::<M, S>(movie_id, after, until, movies, releases).await {
+            Ok(true) => changed += 1,
+            Ok(false) => {}
+            Err(e) => {
+                log::error!("Unable to apply the release schedule of movie {}: {}", movie_id, describe(&e));
+                failure.get_or_insert(e);
+            }
+        }
+    }
+    match failure {
+        Some(e) => Err(e),
+        None => Ok(changed),
+    }
+}
+
+/// Moves the movie to the status its schedule is due to put it in, returns whether it changed
+async fn apply_schedule<M: MovieResolver, S: ReleaseResolver>(movie_id: i64, after: i64, until: i64, movies: &'static M::Store, releases: &'static S::Store) -> QueryResult<bool> {
+    //  Boundaries of a schedule that has since been replaced are no longer due
+    let due = ReleaseSchedule::get_schedule::<S>(movie_id, releases)
+        .await?
+        .and_then(|schedule| schedule.due(after, until));
+    let to = match due {
+        Some(to) => to,
+        None => return Ok(false),
+    };
+    let movie = match Movie::get_movie_id::<M>(movie_id, movies).await {
+        Ok(movie) => movie,
+        Err(ServiceError::NotFound) => return Ok(false),
+        Err(e) => return Err(e),
+    };
+    match StatusChange::check(&movie, to, &Editor::scheduler(), false, Some("Scheduled".to_string())) {
+        Ok(Some(change)) => {
+            apply_change::<M, S>(change, movies, releases).await?;
+            Ok(true)
+        }
+        Ok(None) => Ok(false),
+        Err(e) => {
+            log::warn!("Skipped the scheduled change of movie {}: {}", movie_id, e);
+            Ok(false)
+        }
+    }
+}
+
+/// Sweeps for due schedules every minute. A sweep that fails is logged and retried from the same
+/// point on the next tick, changes that went through are not repeated as the movie is already there
+pub fn spawn_scheduler<M: MovieResolver, S: ReleaseResolver>(movies: &'static M::Store, releases: &'static S::Store) {
+    tokio::spawn(async move {
+        let mut after = now_millis() - SCHEDULER_LOOKBACK_MILLIS;
+        let mut interval = tokio::time::interval(SCHEDULER_TICK);
+        loop {
+            interval.tick().await;
+            let until = now_millis();
+            match apply_schedules::<M, S>(after, until, movies, releases).await {
+                Ok(changed) => {
+                    if changed > 0 {
+                        log::info!("🗓️ Changed the status of {} scheduled movies", changed);
+                    }
+                    after = until;
+                }
+                Err(e) => log::error!("Unable to apply release schedules, retrying on the next tick: {}", describe(&e)),
+            }
+        }
+    });
+}
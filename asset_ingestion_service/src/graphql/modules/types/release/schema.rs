@@ -0,0 +1,151 @@
+use std::marker::PhantomData;
+use async_graphql::*;
+use chrono::{DateTime, TimeZone, Utc};
+use common_utils::{availability::now_millis, error::ServiceError};
+use serde::{Deserialize, Serialize};
+use crate::{graphql::config::get_store_from_ctx, to_bigint};
+use super::super::movies::{model::{Movie, Status}, resolver::{MovieDatabase, MovieResolver}, schema::MovieType};
+use super::model::{Editor, ReleaseSchedule, StatusChange};
+use super::resolver::{ReleaseDatabase, ReleaseResolver};
+use super::scheduler::apply_change;
+
+/// Schedules and history are read through `S`, the movies they belong to through `M`
+#[derive(Default)]
+pub struct ReleaseQuery<S = ReleaseDatabase, M = MovieDatabase>(PhantomData<(S, M)>);
+
+/// Status changes and schedules are written through `S`, the movie itself through `M`
+#[derive(Default)]
+pub struct ReleaseMutation<S = ReleaseDatabase, M = MovieDatabase>(PhantomData<(S, M)>);
+
+#[derive(SimpleObject, Debug, Clone, Deserialize, Serialize)]
+pub struct ReleaseScheduleType {
+    pub movie_id: ID,
+    /// When the movie becomes `RELEASED`
+    pub publish_at: Option<DateTime<Utc>>,
+    /// When the movie becomes `WITHDRAWN`
+    pub unpublish_at: Option<DateTime<Utc>>,
+}
+
+impl From<&ReleaseSchedule> for ReleaseScheduleType {
+    fn from(f: &ReleaseSchedule) -> Self {
+        Self {
+            movie_id: f.movie_id.into(),
+            publish_at: f.publish_at.map(|at| Utc.timestamp_millis(at)),
+            unpublish_at: f.unpublish_at.map(|at| Utc.timestamp_millis(at)),
+        }
+    }
+}
+
+#[derive(SimpleObject, Debug, Clone, Deserialize, Serialize)]
+pub struct StatusChangeType {
+    pub change_id: ID,
+    pub movie_id: ID,
+    pub from_status: Status,
+    pub to_status: Status,
+    pub changed_at: DateTime<Utc>,
+    /// Username of the editor, `scheduler` for scheduled changes
+    pub changed_by: String,
+    /// The change broke the transition rules
+    pub overridden: bool,
+    pub reason: Option<String>,
+}
+
+impl From<&StatusChange> for StatusChangeType {
+    fn from(f: &StatusChange) -> Self {
+        Self {
+            change_id: f.change_id.into(),
+            movie_id: f.movie_id.into(),
+            from_status: f.from_status.parse().unwrap_or_default(),
+            to_status: f.to_status.parse().unwrap_or_default(),
+            changed_at: Utc.timestamp_millis(f.changed_at),
+            changed_by: f.changed_by.clone(),
+            overridden: f.overridden,
+            reason: f.reason.clone(),
+        }
+    }
+}
+
+/// The editor the request is made by, requests built without one are anonymous
+pub fn editor(ctx: &Context<'_>) -> Editor {
+    ctx.data_opt::<Editor>().cloned().unwrap_or_default()
+}
+
+#[Object]
+impl<S: ReleaseResolver, M: MovieResolver> ReleaseQuery<S, M> {
+    /// Empty for a movie without a schedule
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "movieReleaseSchedule")]
+    async fn movie_release_schedule(&self, ctx: &Context<'_>, movie_id: ID) -> FieldResult<ReleaseScheduleType> {
+        let movie_id = to_bigint(movie_id);
+        Movie::get_movie_id::<M>(movie_id, get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        let schedule = ReleaseSchedule::get_schedule::<S>(movie_id, get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?
+            .unwrap_or(ReleaseSchedule { movie_id, ..ReleaseSchedule::default() });
+        Ok(ReleaseScheduleType::from(&schedule))
+    }
+    /// Every status change of the movie, newest first
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "movieStatusHistory")]
+    async fn movie_status_history(&self, ctx: &Context<'_>, movie_id: ID) -> FieldResult<Vec<StatusChangeType>> {
+        let history = StatusChange::get_history::<S>(to_bigint(movie_id), get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        Ok(history.iter().map(StatusChangeType::from).collect())
+    }
+}
+
+#[Object]
+impl<S: ReleaseResolver, M: MovieResolver> ReleaseMutation<S, M> {
+    /// Moves the movie to `status`. Transitions such as reviving a canceled movie are rejected
+    /// unless `override` is set, which is recorded in its history along with `reason`
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "setMovieStatus")]
+    async fn set_movie_status(
+        &self,
+        ctx: &Context<'_>,
+        movie_id: ID,
+        status: Status,
+        reason: Option<String>,
+        #[graphql(name = "override", default)] force: bool,
+    ) -> FieldResult<MovieType> {
+        let movie = Movie::get_movie_id::<M>(to_bigint(movie_id), get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        let change = StatusChange::check(&movie, status, &editor(ctx), force, reason).map_err(|e| e.extend())?;
+        let movie = match change {
+            Some(change) => apply_change::<M, S>(change, get_store_from_ctx(ctx), get_store_from_ctx(ctx))
+                .await
+                .map_err(|e| e.extend())?,
+            None => movie,
+        };
+        Ok(MovieType::from(&movie))
+    }
+    /// Replaces the movie's schedule, leave a time out to clear it. The scheduler releases the
+    /// movie at `publishAt` and withdraws it at `unpublishAt`, both have to be in the future
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "scheduleMovieRelease")]
+    async fn schedule_movie_release(
+        &self,
+        ctx: &Context<'_>,
+        movie_id: ID,
+        publish_at: Option<DateTime<Utc>>,
+        unpublish_at: Option<DateTime<Utc>>,
+    ) -> FieldResult<ReleaseScheduleType> {
+        let movie_id = to_bigint(movie_id);
+        Movie::get_movie_id::<M>(movie_id, get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        let (publish_at, unpublish_at) = (publish_at.map(|at| at.timestamp_millis()), unpublish_at.map(|at| at.timestamp_millis()));
+        if publish_at.into_iter().chain(unpublish_at).any(|at| at <= now_millis()) {
+            return Err(ServiceError::BadRequest("A schedule can't be in the past, use setMovieStatus instead".to_string()).extend())
+        }
+        let schedule = ReleaseSchedule::new(movie_id, publish_at, unpublish_at).map_err(|e| e.extend())?;
+        let res = ReleaseSchedule::set_schedule::<S>(schedule, get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        Ok(ReleaseScheduleType::from(&res))
+    }
+}
@@ -0,0 +1,196 @@
+use chrono::NaiveDate;
+use common_utils::QueryResult;
+use common_utils::events::{PartitionKey, SeriesEvent};
+use scylla::macros::{FromRow, ValueList};
+use serde::{Deserialize, Serialize};
+use strum_macros::{EnumString, Display};
+use async_graphql::Enum;
+use crate::generate_unique_id;
+
+use super::resolver::SeriesResolver;
+use super::schema::{SeriesInput, SeasonInput, EpisodeInput};
+
+/// Written to the event outbox by every series, season and episode write
+pub type CatalogSeriesEvent = SeriesEvent<Series, Season, Episode>;
+
+// Columns after the primary key are in alphabetical order, the order `SELECT *` returns them in
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize, ValueList)]
+#[serde(rename_all(serialize = "snake_case", deserialize = "camelCase"))]
+pub struct Series { 
+    pub series_id: i64,
+    pub created_by: Vec<String>,
+    pub first_air_date: NaiveDate,
+    pub genres: Vec<String>,
+    pub homepage: String,
+    pub languages: Vec<String>,
+    /// `None` while the series is still airing
+    pub last_air_date: Option<NaiveDate>,
+    pub overview: String,
+    pub poster: String,
+    pub rated: String,
+    pub status: String,
+    pub title: String,
+}
+
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize, ValueList)]
+#[serde(rename_all(serialize = "snake_case", deserialize = "camelCase"))]
+pub struct Season { 
+    pub series_id: i64,
+    pub season_number: i32,
+    pub air_date: Option<NaiveDate>,
+    pub name: String,
+    pub overview: String,
+    pub poster: String,
+    pub season_id: i64,
+}
+
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize, ValueList)]
+#[serde(rename_all(serialize = "snake_case", deserialize = "camelCase"))]
+pub struct Episode { 
+    pub series_id: i64,
+    pub season_number: i32,
+    pub episode_number: i32,
+    pub air_date: Option<NaiveDate>,
+    pub episode_id: i64,
+    pub overview: String,
+    pub runtime: i64,
+    pub still_path: String,
+    pub title: String,
+    pub video_file: String,
+}
+
+/// Airing status of a series, TMDB reports the same set
+#[derive(Copy, Clone, Eq, Debug, PartialEq, Serialize, SmartDefault, Deserialize, Enum, Display, EnumString)]
+#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+pub enum SeriesStatus { 
+    #[default]
+    Planned,
+    InProduction,
+    ReturningSeries,
+    Ended,
+    Canceled,
+}
+
+impl SeriesStatus { 
+    /// Maps TMDB's display names, e.g. "Returning Series"
+    pub fn from_tmdb(status: &str) -> Self { 
+        match status { 
+            "In Production" => Self::InProduction,
+            "Returning Series" => Self::ReturningSeries,
+            "Ended" => Self::Ended,
+            "Canceled" | "Cancelled" => Self::Canceled,
+            _ => Self::Planned,
+        }
+    }
+}
+
+impl PartitionKey for Series { 
+    fn partition_key(&self) -> String {
+        self.series_id.to_string()
+    }
+}
+
+impl PartitionKey for Season { 
+    fn partition_key(&self) -> String {
+        self.series_id.to_string()
+    }
+}
+
+impl PartitionKey for Episode { 
+    fn partition_key(&self) -> String {
+        self.series_id.to_string()
+    }
+}
+
+impl Series { 
+    /// `series_id` is only passed on updates, new series get a fresh id
+    pub fn from_input(series_id: Option<i64>, f: &SeriesInput) -> Self { 
+        Self { 
+            series_id: series_id.unwrap_or_else(generate_unique_id),
+            created_by: f.created_by.clone().unwrap_or_default(),
+            first_air_date: f.first_air_date,
+            genres: f.genres.clone().unwrap_or_default(),
+            homepage: f.homepage.clone().unwrap_or_default(),
+            languages: f.languages.clone().unwrap_or_default(),
+            last_air_date: f.last_air_date,
+            overview: f.overview.clone().unwrap_or_default(),
+            poster: f.poster.clone().unwrap_or_default(),
+            rated: f.rated.unwrap_or_default().to_string(),
+            status: f.status.unwrap_or_default().to_string(),
+            title: f.title.clone(),
+        }
+    }
+}
+
+impl Season { 
+    pub fn from_input(series_id: i64, season_id: Option<i64>, f: &SeasonInput) -> Self { 
+        Self { 
+            series_id,
+            season_number: f.season_number,
+            air_date: f.air_date,
+            name: f.name.clone().unwrap_or_else(|| format!("Season {}", f.season_number)),
+            overview: f.overview.clone().unwrap_or_default(),
+            poster: f.poster.clone().unwrap_or_default(),
+            season_id: season_id.unwrap_or_else(generate_unique_id),
+        }
+    }
+}
+
+impl Episode { 
+    pub fn from_input(series_id: i64, episode_id: Option<i64>, f: &EpisodeInput) -> Self { 
+        Self { 
+            series_id,
+            season_number: f.season_number,
+            episode_number: f.episode_number,
+            air_date: f.air_date,
+            episode_id: episode_id.unwrap_or_else(generate_unique_id),
+            overview: f.overview.clone().unwrap_or_default(),
+            runtime: f.runtime.unwrap_or_default(),
+            still_path: f.still_path.clone().unwrap_or_default(),
+            title: f.title.clone(),
+            video_file: f.video_file.clone().unwrap_or_default(),
+        }
+    }
+}
+
+impl Series { 
+    pub async fn get_series<SeriesDatabase: SeriesResolver>(series_id: i64, session: &'static SeriesDatabase::Store) -> QueryResult<Series> {
+        SeriesDatabase::get_series(series_id, session).await
+    }
+    pub async fn get_all_series<SeriesDatabase: SeriesResolver>(session: &'static SeriesDatabase::Store) -> QueryResult<Vec<Series>> {
+        SeriesDatabase::get_all_series(session).await
+    }
+    pub async fn create_series<SeriesDatabase: SeriesResolver>(series: Series, session: &'static SeriesDatabase::Store) -> QueryResult<Series> {
+        SeriesDatabase::create_series(series, session).await
+    }
+    pub async fn update_series<SeriesDatabase: SeriesResolver>(series: Series, session: &'static SeriesDatabase::Store) -> QueryResult<Series> {
+        SeriesDatabase::update_series(series, session).await
+    }
+    pub async fn delete_series<SeriesDatabase: SeriesResolver>(series_id: i64, session: &'static SeriesDatabase::Store) -> QueryResult<bool> {
+        SeriesDatabase::delete_series(series_id, session).await
+    }
+}
+
+impl Season { 
+    pub async fn get_seasons<SeriesDatabase: SeriesResolver>(series_id: i64, session: &'static SeriesDatabase::Store) -> QueryResult<Vec<Season>> {
+        SeriesDatabase::get_seasons(series_id, session).await
+    }
+    pub async fn upsert_season<SeriesDatabase: SeriesResolver>(season: Season, session: &'static SeriesDatabase::Store) -> QueryResult<Season> {
+        SeriesDatabase::upsert_season(season, session).await
+    }
+    pub async fn delete_season<SeriesDatabase: SeriesResolver>(series_id: i64, season_number: i32, session: &'static SeriesDatabase::Store) -> QueryResult<bool> {
+        SeriesDatabase::delete_season(series_id, season_number, session).await
+    }
+}
+
+impl Episode { 
+    pub async fn get_episodes<SeriesDatabase: SeriesResolver>(series_id: i64, season_number: i32, session: &'static SeriesDatabase::Store) -> QueryResult<Vec<Episode>> {
+        SeriesDatabase::get_episodes(series_id, season_number, session).await
+    }
+    pub async fn upsert_episode<SeriesDatabase: SeriesResolver>(episode: Episode, session: &'static SeriesDatabase::Store) -> QueryResult<Episode> {
+        SeriesDatabase::upsert_episode(episode, session).await
+    }
+    pub async fn delete_episode<SeriesDatabase: SeriesResolver>(series_id: i64, season_number: i32, episode_number: i32, session: &'static SeriesDatabase::Store) -> QueryResult<bool> {
+        SeriesDatabase::delete_episode(series_id, season_number, episode_number, session).await
+    }
+}
@@ -0,0 +1,173 @@
+use async_trait::async_trait;
+use common_utils::{QueryResult, error::ServiceError};
+use scylla::IntoTypedRows;
+use crate::db::{CachedSession, bind, read_all_pages, write_logged_batch};
+use super::super::outbox::{relay::wake_relay, resolver::enqueue};
+use super::model::{CatalogSeriesEvent, Series, Season, Episode};
+
+/// Seasons and episodes are keyed by their series and their number, so inserting
+/// one with a number that already exists replaces it. Writes publish their `SeriesEvent`
+/// themselves, through the event outbox
+#[async_trait]
+pub trait SeriesResolver: Send + Sync + 'static { 
+    type Store: Send + Sync + 'static;
+    async fn get_series(series_id: i64, session: &'static Self::Store) -> QueryResult<Series>;
+    /// Every series, for one-off rewrites such as the genre migration
+    async fn get_all_series(session: &'static Self::Store) -> QueryResult<Vec<Series>>;
+    async fn create_series(series: Series, session: &'static Self::Store) -> QueryResult<Series>;
+    async fn update_series(series: Series, session: &'static Self::Store) -> QueryResult<Series>;
+    async fn delete_series(series_id: i64, session: &'static Self::Store) -> QueryResult<bool>;
+    async fn get_seasons(series_id: i64, session: &'static Self::Store) -> QueryResult<Vec<Season>>;
+    async fn upsert_season(season: Season, session: &'static Self::Store) -> QueryResult<Season>;
+    async fn delete_season(series_id: i64, season_number: i32, session: &'static Self::Store) -> QueryResult<bool>;
+    async fn get_episodes(series_id: i64, season_number: i32, session: &'static Self::Store) -> QueryResult<Vec<Episode>>;
+    async fn upsert_episode(episode: Episode, session: &'static Self::Store) -> QueryResult<Episode>;
+    async fn delete_episode(series_id: i64, season_number: i32, episode_number: i32, session: &'static Self::Store) -> QueryResult<bool>;
+}
+
+#[derive(Default)]
+pub struct SeriesDatabase;
+
+static GET_SERIES: &str = "SELECT * FROM movie_keyspace.series WHERE series_id = ?;";
+static GET_ALL_SERIES: &str = "SELECT * FROM movie_keyspace.series;";
+static INSERT_SERIES: &str = "
+    INSERT INTO movie_keyspace.series (
+        series_id, created_by, first_air_date, genres, homepage, languages, 
+        last_air_date, overview, poster, rated, status, title
+    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?);
+";
+static DELETE_SERIES: &str = "DELETE FROM movie_keyspace.series WHERE series_id = ?;";
+static GET_SEASONS: &str = "SELECT * FROM movie_keyspace.seasons WHERE series_id = ?;";
+static INSERT_SEASON: &str = "
+    INSERT INTO movie_keyspace.seasons (
+        series_id, season_number, air_date, name, overview, poster, season_id
+    ) VALUES (?, ?, ?, ?, ?, ?, ?);
+";
+static DELETE_SEASON: &str = "DELETE FROM movie_keyspace.seasons WHERE series_id = ? AND season_number = ?;";
+static DELETE_SEASONS: &str = "DELETE FROM movie_keyspace.seasons WHERE series_id = ?;";
+static GET_EPISODES: &str = "SELECT * FROM movie_keyspace.episodes WHERE series_id = ? AND season_number = ?;";
+static INSERT_EPISODE: &str = "
+    INSERT INTO movie_keyspace.episodes (
+        series_id, season_number, episode_number, air_date, episode_id, 
+        overview, runtime, still_path, title, video_file
+    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?);
+";
+static DELETE_EPISODE: &str = "DELETE FROM movie_keyspace.episodes WHERE series_id = ? AND season_number = ? AND episode_number = ?;";
+// Drops the whole partition of a season
+static DELETE_EPISODES: &str = "DELETE FROM movie_keyspace.episodes WHERE series_id = ? AND season_number = ?;";
+
+#[async_trait]
+impl SeriesResolver for SeriesDatabase { 
+    type Store = CachedSession;
+
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.series"), err)]
+    async fn get_series(series_id: i64, session: &'static CachedSession) -> QueryResult<Series> {
+        session.query_prepared(GET_SERIES, (series_id,))
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
+            .into_typed::<Series>()
+            .next()
+            .ok_or(ServiceError::NotFound)?
+            .map_err(|e| ServiceError::ServerError(e.to_string()))
+    }
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.series"), err)]
+    async fn get_all_series(session: &'static CachedSession) -> QueryResult<Vec<Series>> {
+        read_all_pages::<Series>(GET_ALL_SERIES, session).await
+    }
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.series"), err)]
+    async fn create_series(series: Series, session: &'static CachedSession) -> QueryResult<Series> {
+        write_logged_batch(vec![
+            (INSERT_SERIES, bind(series.clone())?),
+            enqueue(CatalogSeriesEvent::SeriesUpserted(series.clone()))?,
+        ], session).await?;
+        wake_relay();
+        Ok(series)
+    }
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.series"), err)]
+    async fn update_series(series: Series, session: &'static CachedSession) -> QueryResult<Series> {
+        SeriesDatabase::get_series(series.series_id, session).await?;
+        SeriesDatabase::create_series(series, session).await
+    }
+    /// Removes the series together with every season and episode under it
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.series"), err)]
+    async fn delete_series(series_id: i64, session: &'static CachedSession) -> QueryResult<bool> {
+        SeriesDatabase::get_series(series_id, session).await?;
+        let seasons = SeriesDatabase::get_seasons(series_id, session).await?;
+        let mut statements = vec![
+            (DELETE_SERIES, bind((series_id,))?),
+            (DELETE_SEASONS, bind((series_id,))?),
+        ];
+        for season in seasons.iter() { 
+            statements.push((DELETE_EPISODES, bind((series_id, season.season_number))?));
+        }
+        statements.push(enqueue(CatalogSeriesEvent::SeriesDeleted { series_id })?);
+        write_logged_batch(statements, session).await?;
+        wake_relay();
+        log::info!("Deleted series {} and its {} seasons", series_id, seasons.len());
+        Ok(true)
+    }
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.seasons"), err)]
+    async fn get_seasons(series_id: i64, session: &'static CachedSession) -> QueryResult<Vec<Season>> {
+        session.query_prepared(GET_SEASONS, (series_id,))
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
+            .into_typed::<Season>()
+            .map(|row| row.map_err(|e| ServiceError::ServerError(e.to_string())))
+            .collect()
+    }
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.seasons"), err)]
+    async fn upsert_season(season: Season, session: &'static CachedSession) -> QueryResult<Season> {
+        SeriesDatabase::get_series(season.series_id, session).await?;
+        write_logged_batch(vec![
+            (INSERT_SEASON, bind(season.clone())?),
+            enqueue(CatalogSeriesEvent::SeasonUpserted(season.clone()))?,
+        ], session).await?;
+        wake_relay();
+        Ok(season)
+    }
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.seasons"), err)]
+    async fn delete_season(series_id: i64, season_number: i32, session: &'static CachedSession) -> QueryResult<bool> {
+        write_logged_batch(vec![
+            (DELETE_SEASON, bind((series_id, season_number))?),
+            (DELETE_EPISODES, bind((series_id, season_number))?),
+            enqueue(CatalogSeriesEvent::SeasonDeleted { series_id, season_number })?,
+        ], session).await?;
+        wake_relay();
+        Ok(true)
+    }
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.episodes"), err)]
+    async fn get_episodes(series_id: i64, season_number: i32, session: &'static CachedSession) -> QueryResult<Vec<Episode>> {
+        session.query_prepared(GET_EPISODES, (series_id, season_number))
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
+            .into_typed::<Episode>()
+            .map(|row| row.map_err(|e| ServiceError::ServerError(e.to_string())))
+            .collect()
+    }
+    /// Episodes can only be added to a season that exists
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.episodes"), err)]
+    async fn upsert_episode(episode: Episode, session: &'static CachedSession) -> QueryResult<Episode> {
+        let seasons = SeriesDatabase::get_seasons(episode.series_id, session).await?;
+        if !seasons.iter().any(|season| season.season_number == episode.season_number) { 
+            return Err(ServiceError::NotFound)
+        }
+        write_logged_batch(vec![
+            (INSERT_EPISODE, bind(episode.clone())?),
+            enqueue(CatalogSeriesEvent::EpisodeUpserted(episode.clone()))?,
+        ], session).await?;
+        wake_relay();
+        Ok(episode)
+    }
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.episodes"), err)]
+    async fn delete_episode(series_id: i64, season_number: i32, episode_number: i32, session: &'static CachedSession) -> QueryResult<bool> {
+        write_logged_batch(vec![
+            (DELETE_EPISODE, bind((series_id, season_number, episode_number))?),
+            enqueue(CatalogSeriesEvent::EpisodeDeleted { series_id, season_number, episode_number })?,
+        ], session).await?;
+        wake_relay();
+        Ok(true)
+    }
+}
@@ -0,0 +1,265 @@
+use async_graphql::*;
+use serde::{Deserialize, Serialize};
+use chrono::NaiveDate;
+use std::marker::PhantomData;
+use crate::{graphql::{config::get_store_from_ctx, modules::types::{genres::{model::{resolve_tmdb_genres, Genre}, resolver::{GenreDatabase, GenreResolver}}, movies::model::MediaRated, tmdb_test::fetch_tv_series}}, to_bigint};
+use super::{model::{Series, Season, Episode, SeriesStatus}, resolver::{SeriesDatabase, SeriesResolver}};
+
+/// Series mutations go through `R`, `SeriesDatabase` outside of tests. Genres are checked against `G`
+#[derive(Default)]
+pub struct SeriesMutation<R = SeriesDatabase, G = GenreDatabase>(PhantomData<(R, G)>);
+
+#[derive(SimpleObject, Debug, Clone, Deserialize, Serialize)]
+pub struct SeriesType { 
+    pub series_id: ID,
+    pub title: String,
+    pub overview: String,
+    pub poster: String,
+    pub homepage: String,
+    pub rated: String,
+    pub status: String,
+    pub first_air_date: NaiveDate,
+    pub last_air_date: Option<NaiveDate>,
+    pub genres: Vec<String>,
+    pub languages: Vec<String>,
+    pub created_by: Vec<String>,
+}
+
+#[derive(SimpleObject, Debug, Clone, Deserialize, Serialize)]
+pub struct SeasonType { 
+    pub season_id: ID,
+    pub series_id: ID,
+    pub season_number: i32,
+    pub name: String,
+    pub overview: String,
+    pub poster: String,
+    pub air_date: Option<NaiveDate>,
+}
+
+#[derive(SimpleObject, Debug, Clone, Deserialize, Serialize)]
+pub struct EpisodeType { 
+    pub episode_id: ID,
+    pub series_id: ID,
+    pub season_number: i32,
+    pub episode_number: i32,
+    pub title: String,
+    pub overview: String,
+    pub air_date: Option<NaiveDate>,
+    pub runtime: i64,
+    pub still_path: String,
+    pub video_file: String,
+}
+
+impl From<&Series> for SeriesType { 
+    fn from(f: &Series) -> Self {
+        Self { 
+            series_id: f.series_id.into(),
+            title: f.title.clone(),
+            overview: f.overview.clone(),
+            poster: f.poster.clone(),
+            homepage: f.homepage.clone(),
+            rated: f.rated.clone(),
+            status: f.status.clone(),
+            first_air_date: f.first_air_date,
+            last_air_date: f.last_air_date,
+            genres: f.genres.clone(),
+            languages: f.languages.clone(),
+            created_by: f.created_by.clone(),
+        }
+    }
+}
+
+impl From<&Season> for SeasonType { 
+    fn from(f: &Season) -> Self {
+        Self { 
+            season_id: f.season_id.into(),
+            series_id: f.series_id.into(),
+            season_number: f.season_number,
+            name: f.name.clone(),
+            overview: f.overview.clone(),
+            poster: f.poster.clone(),
+            air_date: f.air_date,
+        }
+    }
+}
+
+impl From<&Episode> for EpisodeType { 
+    fn from(f: &Episode) -> Self {
+        Self { 
+            episode_id: f.episode_id.into(),
+            series_id: f.series_id.into(),
+            season_number: f.season_number,
+            episode_number: f.episode_number,
+            title: f.title.clone(),
+            overview: f.overview.clone(),
+            air_date: f.air_date,
+            runtime: f.runtime,
+            still_path: f.still_path.clone(),
+            video_file: f.video_file.clone(),
+        }
+    }
+}
+
+#[derive(InputObject, Debug, Deserialize, Serialize)]
+pub struct SeriesInput { 
+    pub title: String,
+    /// First air date YYYY-MM-DD
+    pub first_air_date: NaiveDate,
+    /// Left empty while the series is still airing
+    pub last_air_date: Option<NaiveDate>,
+    pub overview: Option<String>,
+    /// S3-CDN poster link
+    pub poster: Option<String>,
+    pub homepage: Option<String>,
+    pub rated: Option<MediaRated>,
+    pub status: Option<SeriesStatus>,
+    pub genres: Option<Vec<String>>,
+    pub languages: Option<Vec<String>>,
+    pub created_by: Option<Vec<String>>,
+}
+
+#[derive(InputObject, Debug, Deserialize, Serialize)]
+pub struct SeasonInput { 
+    /// Specials are usually season 0
+    pub season_number: i32,
+    /// Defaults to "Season <number>"
+    pub name: Option<String>,
+    pub overview: Option<String>,
+    pub poster: Option<String>,
+    pub air_date: Option<NaiveDate>,
+}
+
+#[derive(InputObject, Debug, Deserialize, Serialize)]
+pub struct EpisodeInput { 
+    pub season_number: i32,
+    pub episode_number: i32,
+    pub title: String,
+    pub overview: Option<String>,
+    pub air_date: Option<NaiveDate>,
+    /// Runtime in minutes
+    pub runtime: Option<i64>,
+    /// S3-CDN thumbnail link
+    pub still_path: Option<String>,
+    /// S3 video link
+    pub video_file: Option<String>,
+}
+
+#[Object]
+impl<R: SeriesResolver, G: GenreResolver> SeriesMutation<R, G> { 
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "createSeries")]
+    async fn create_series(&self, ctx: &Context<'_>, new_series: SeriesInput) -> FieldResult<SeriesType> { 
+        let mut series = Series::from_input(None, &new_series);
+        series.genres = Genre::canonicalise::<G>(&series.genres, get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        let res = Series::create_series::<R>(series, get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        Ok(SeriesType::from(&res))
+    }
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "updateSeries")]
+    async fn update_series(&self, ctx: &Context<'_>, series_id: ID, new_series: SeriesInput) -> FieldResult<SeriesType> { 
+        let mut series = Series::from_input(Some(to_bigint(series_id)), &new_series);
+        series.genres = Genre::canonicalise::<G>(&series.genres, get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        let res = Series::update_series::<R>(series, get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        Ok(SeriesType::from(&res))
+    }
+    /// Deletes the series with all of its seasons and episodes. The search consumer
+    /// drops their documents when it sees the `SeriesDeleted` event
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "deleteSeries")]
+    async fn delete_series(&self, ctx: &Context<'_>, series_id: ID) -> FieldResult<bool> { 
+        let series_id = to_bigint(series_id);
+        let res = Series::delete_series::<R>(series_id, get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        Ok(res)
+    }
+    /// Creates the season, or replaces the one with the same number while keeping its id
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "upsertSeason")]
+    async fn upsert_season(&self, ctx: &Context<'_>, series_id: ID, season: SeasonInput) -> FieldResult<SeasonType> { 
+        let session = get_store_from_ctx(ctx);
+        let series_id = to_bigint(series_id);
+        let season_id = Season::get_seasons::<R>(series_id, session)
+            .await
+            .map_err(|e| e.extend())?
+            .into_iter()
+            .find(|existing| existing.season_number == season.season_number)
+            .map(|existing| existing.season_id);
+        let res = Season::upsert_season::<R>(Season::from_input(series_id, season_id, &season), session)
+            .await
+            .map_err(|e| e.extend())?;
+        Ok(SeasonType::from(&res))
+    }
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "deleteSeason")]
+    async fn delete_season(&self, ctx: &Context<'_>, series_id: ID, season_number: i32) -> FieldResult<bool> { 
+        let series_id = to_bigint(series_id);
+        let res = Season::delete_season::<R>(series_id, season_number, get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        Ok(res)
+    }
+    /// Creates the episode, or replaces the one with the same number in that season
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "upsertEpisode")]
+    async fn upsert_episode(&self, ctx: &Context<'_>, series_id: ID, episode: EpisodeInput) -> FieldResult<EpisodeType> { 
+        let session = get_store_from_ctx(ctx);
+        let series_id = to_bigint(series_id);
+        let episode_id = Episode::get_episodes::<R>(series_id, episode.season_number, session)
+            .await
+            .map_err(|e| e.extend())?
+            .into_iter()
+            .find(|existing| existing.episode_number == episode.episode_number)
+            .map(|existing| existing.episode_id);
+        let res = Episode::upsert_episode::<R>(Episode::from_input(series_id, episode_id, &episode), session)
+            .await
+            .map_err(|e| e.extend())?;
+        Ok(EpisodeType::from(&res))
+    }
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "deleteEpisode")]
+    async fn delete_episode(&self, ctx: &Context<'_>, series_id: ID, season_number: i32, episode_number: i32) -> FieldResult<bool> { 
+        let series_id = to_bigint(series_id);
+        let res = Episode::delete_episode::<R>(series_id, season_number, episode_number, get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        Ok(res)
+    }
+    /// Imports a series from TMDB by its TMDB id, with all of its seasons and episodes.
+    /// Each write queues its own event, so they are published in the order they were written, series first
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "importTvSeries")]
+    async fn import_tv_series(&self, ctx: &Context<'_>, tmdb_id: ID, language: Option<String>) -> FieldResult<SeriesType> { 
+        let session = get_store_from_ctx(ctx);
+        let (mut series, seasons, episodes) = fetch_tv_series(to_bigint(tmdb_id), language)
+            .await
+            .map_err(|e| e.extend())?;
+        let taxonomy = Genre::taxonomy::<G>(get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        series.genres = resolve_tmdb_genres(&taxonomy, &series.title, &series.genres);
+
+        let series = Series::create_series::<R>(series, session)
+            .await
+            .map_err(|e| e.extend())?;
+        for season in seasons { 
+            Season::upsert_season::<R>(season, session)
+                .await
+                .map_err(|e| e.extend())?;
+        }
+        for episode in episodes { 
+            Episode::upsert_episode::<R>(episode, session)
+                .await
+                .map_err(|e| e.extend())?;
+        }
+        Ok(SeriesType::from(&series))
+    }
+}
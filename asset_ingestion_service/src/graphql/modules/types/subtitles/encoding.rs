@@ -0,0 +1,59 @@
+//! Subtitle files come in whatever encoding the tool that wrote them used, most often UTF-8,
+//! UTF-16 or a Windows code page. Everything is stored as UTF-8
+use chardetng::EncodingDetector;
+use common_utils::{QueryResult, error::ServiceError};
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+
+/// Bytes looked at to tell UTF-16 without a byte order mark from other encodings
+const UTF_16_SAMPLE: usize = 512;
+
+/// UTF-16 without a byte order mark, which puts a zero next to every ASCII character
+fn utf_16(bytes: &[u8]) -> Option<&'static Encoding> {
+    let sample = &bytes[..bytes.len().min(UTF_16_SAMPLE) & !1];
+    if sample.is_empty() {
+        return None
+    }
+    let zeros = |offset: usize| sample.iter().skip(offset).step_by(2).all(|byte| *byte == 0);
+    match (zeros(0), zeros(1)) {
+        (false, true) => Some(UTF_16LE),
+        (true, false) => Some(UTF_16BE),
+        _ => None,
+    }
+}
+
+/// The encoding of `bytes`: the one its byte order mark names, then `charset` when the uploader
+/// gave one, then UTF-8 when it is valid, and otherwise the most likely guess
+pub fn detect(bytes: &[u8], charset: Option<&str>) -> QueryResult<&'static Encoding> {
+    if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+        return Ok(encoding)
+    }
+    if let Some(charset) = charset {
+        return Encoding::for_label(charset.trim().as_bytes())
+            .ok_or_else(|| ServiceError::BadRequest(format!("Unknown charset {:?}", charset)))
+    }
+    if let Some(encoding) = utf_16(bytes) {
+        return Ok(encoding)
+    }
+    if std::str::from_utf8(bytes).is_ok() {
+        return Ok(UTF_8)
+    }
+    let mut detector = EncodingDetector::new();
+    detector.feed(bytes, true);
+    Ok(detector.guess(None, false))
+}
+
+/// `bytes` as UTF-8 without a byte order mark, NUL characters or `\r`, see `detect`. Bytes that
+/// aren't valid in the encoding are rejected rather than replaced
+pub fn decode(bytes: &[u8], charset: Option<&str>) -> QueryResult<(String, &'static Encoding)> {
+    let encoding = detect(bytes, charset)?;
+    let (text, used, malformed) = encoding.decode(bytes);
+    if malformed {
+        return Err(ServiceError::BadRequest(format!("The file is not valid {}, pass its charset", used.name())))
+    }
+    let text = text
+        .trim_start_matches('\u{feff}')
+        .replace("\r\n", "\n")
+        .replace('\r', "\n")
+        .replace('\0', "");
+    Ok((text, used))
+}
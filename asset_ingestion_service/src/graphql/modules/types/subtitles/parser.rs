@@ -0,0 +1,314 @@
+//! SubRip (`.srt`) and WebVTT cues. Files are read into `Cue`s, checked, and written back in either
+//! format, so that reading what was written gives the same cues
+use std::fmt::Write;
+use common_utils::{QueryResult, error::ServiceError};
+use serde::Deserialize;
+use strum_macros::{Display, EnumString};
+
+/// Problems reported for one file, a file with more is most likely not subtitles at all
+const MAX_PROBLEMS: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumString, Deserialize)]
+#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SubtitleFormat {
+    Srt,
+    Webvtt,
+}
+
+impl SubtitleFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            SubtitleFormat::Srt => "application/x-subrip",
+            SubtitleFormat::Webvtt => "text/vtt",
+        }
+    }
+    /// WebVTT files start with a `WEBVTT` line, anything else is read as SubRip
+    pub fn detect(text: &str) -> Self {
+        match is_signature(text.lines().next().unwrap_or_default()) {
+            true => SubtitleFormat::Webvtt,
+            false => SubtitleFormat::Srt,
+        }
+    }
+}
+
+/// One subtitle, times are in milliseconds from the start of the title
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cue {
+    /// WebVTT cue identifier. SubRip counters aren't kept, cues are numbered when written
+    pub id: Option<String>,
+    pub start: u64,
+    pub end: u64,
+    /// WebVTT cue settings such as `line:0 align:start`, SubRip has none
+    pub settings: Option<String>,
+    /// Lines of the cue joined by `\n`, never empty
+    pub text: String,
+}
+
+/// The `WEBVTT` line, optionally followed by a space or tab and a description
+fn is_signature(line: &str) -> bool {
+    match line.strip_prefix("WEBVTT") {
+        Some(rest) => rest.is_empty() || rest.starts_with(|c| c == ' ' || c == '\t'),
+        None => false,
+    }
+}
+
+/// Blocks that aren't cues, which WebVTT files may have between them
+fn is_comment_or_definition(line: &str) -> bool {
+    ["NOTE", "STYLE", "REGION"]
+        .iter()
+        .any(|keyword| line.strip_prefix(keyword).map(|rest| rest.is_empty() || rest.starts_with(|c: char| c.is_whitespace())).unwrap_or(false))
+}
+
+/// `[hh:]mm:ss.ttt` of WebVTT, or `hh:mm:ss,ttt` of SubRip, which is often written with a `.` as well
+fn parse_timestamp(value: &str, format: SubtitleFormat) -> Option<u64> {
+    let (clock, millis) = match format {
+        SubtitleFormat::Srt => value.rsplit_once(|c| c == ',' || c == '.')?,
+        SubtitleFormat::Webvtt => value.rsplit_once('.')?,
+    };
+    let digits = |part: &str| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit());
+    let parts: Vec<&str> = clock.split(':').collect();
+    if millis.len() != 3 || !digits(millis) || !parts.iter().all(|part| digits(part)) {
+        return None
+    }
+    let (hours, minutes, seconds) = match (format, parts.as_slice()) {
+        (SubtitleFormat::Webvtt, [hours, ..]) if parts.len() == 3 && hours.len() < 2 => return None,
+        (_, [hours, minutes, seconds]) => (hours.parse::<u64>().ok()?, *minutes, *seconds),
+        (SubtitleFormat::Webvtt, [minutes, seconds]) => (0, *minutes, *seconds),
+        _ => return None,
+    };
+    if minutes.len() != 2 || seconds.len() != 2 {
+        return None
+    }
+    let (minutes, seconds) = (minutes.parse::<u64>().ok()?, seconds.parse::<u64>().ok()?);
+    if minutes > 59 || seconds > 59 {
+        return None
+    }
+    hours
+        .checked_mul(3_600_000)?
+        .checked_add((minutes * 60 + seconds) * 1000 + millis.parse::<u64>().ok()?)
+}
+
+/// Always with hours, which both formats accept
+fn format_timestamp(millis: u64, format: SubtitleFormat) -> String {
+    let seconds = millis / 1000;
+    format!("{:02}:{:02}:{:02}{}{:03}", seconds / 3600, seconds / 60 % 60, seconds % 60, separator(format), millis % 1000)
+}
+
+/// Start, end and the WebVTT settings of a `start --> end` line. SubRip coordinates after the
+/// end are dropped
+fn parse_timing(line: &str, format: SubtitleFormat) -> Option<(u64, u64, Option<String>)> {
+    let (start, rest) = line.split_once("-->")?;
+    let rest = rest.trim();
+    let (end, settings) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    let settings = match format {
+        SubtitleFormat::Webvtt if !settings.trim().is_empty() => Some(settings.split_whitespace().collect::<Vec<&str>>().join(" ")),
+        _ => None,
+    };
+    Some((parse_timestamp(start.trim(), format)?, parse_timestamp(end, format)?, settings))
+}
+
+/// Non-empty lines with their 1-based line numbers, split at blank lines
+fn blocks(text: &str) -> Vec<Vec<(usize, &str)>> {
+    let mut blocks = vec![];
+    let mut block = vec![];
+    for (number, line) in text.lines().enumerate() {
+        match line.trim().is_empty() {
+            true if !block.is_empty() => blocks.push(std::mem::take(&mut block)),
+            true => {}
+            false => block.push((number + 1, line.trim_end())),
+        }
+    }
+    if !block.is_empty() {
+        blocks.push(block);
+    }
+    blocks
+}
+
+fn problems(mut problems: Vec<String>) -> ServiceError {
+    let more = problems.len().saturating_sub(MAX_PROBLEMS);
+    problems.truncate(MAX_PROBLEMS);
+    if more > 0 {
+        problems.push(format!("and {} more", more));
+    }
+    ServiceError::BadRequest(problems.join("; "))
+}
+
+/// The cues of `text`, which has to be UTF-8 with `\n` line endings, see `encoding::decode`.
+/// SubRip override codes such as `{\an8}` are dropped and cues without text are left out. Bad
+/// timestamps, cues that end before they start and cues that overlap are all reported at once
+pub fn parse(text: &str, format: SubtitleFormat) -> QueryResult<Vec<Cue>> {
+    let mut blocks = blocks(text).into_iter();
+    let mut problems_found = vec![];
+    if format == SubtitleFormat::Webvtt {
+        match blocks.next() {
+            Some(header) if is_signature(header[0].1) => {}
+            _ => return Err(ServiceError::BadRequest("A WebVTT file has to start with a WEBVTT line".to_string())),
+        }
+    }
+    let mut cues = vec![];
+    for block in blocks {
+        let (number, first) = block[0];
+        if format == SubtitleFormat::Webvtt && is_comment_or_definition(first) {
+            continue
+        }
+        // The timing line may come after a SubRip counter or a WebVTT identifier
+        let (id, timing, text) = match first.contains("-->") {
+            true => (None, Some(block[0]), &block[1..]),
+            false => (Some(first.trim()), block.get(1).copied(), block.get(2..).unwrap_or_default()),
+        };
+        let (timing_number, timing) = match timing {
+            Some((timing_number, timing)) if timing.contains("-->") => (timing_number, timing),
+            _ => {
+                problems_found.push(format!("line {}: expected a cue timing like 00:00:01{}000 --> 00:00:02{}000", number, separator(format), separator(format)));
+                continue
+            }
+        };
+        if format == SubtitleFormat::Srt && id.map(|id| !id.chars().all(|c| c.is_ascii_digit())).unwrap_or(false) {
+            problems_found.push(format!("line {}: expected a cue number", number));
+            continue
+        }
+        let (start, end, settings) = match parse_timing(timing, format) {
+            Some(timing) => timing,
+            None => {
+                problems_found.push(format!("line {}: bad timestamp in `{}`", timing_number, timing.trim()));
+                continue
+            }
+        };
+        if let Some((line, _)) = text.iter().find(|(_, line)| line.contains("-->")) {
+            problems_found.push(format!("line {}: cue text can't contain -->", line));
+            continue
+        }
+        let lines: Vec<String> = text
+            .iter()
+            .map(|(_, line)| match format {
+                SubtitleFormat::Srt => strip_override_codes(line),
+                SubtitleFormat::Webvtt => line.to_string(),
+            })
+            .filter(|line| !line.trim().is_empty())
+            .collect();
+        if lines.is_empty() {
+            continue
+        }
+        cues.push(Cue {
+            id: id.filter(|_| format == SubtitleFormat::Webvtt).map(str::to_string),
+            start,
+            end,
+            settings,
+            text: lines.join("\n"),
+        });
+    }
+    cues.sort_by(|a, b| (a.start, a.end).cmp(&(b.start, b.end)));
+    problems_found.extend(timing_problems(&cues));
+    match problems_found.is_empty() {
+        true => Ok(cues),
+        false => Err(problems(problems_found)),
+    }
+}
+
+fn separator(format: SubtitleFormat) -> char {
+    match format {
+        SubtitleFormat::Srt => ',',
+        SubtitleFormat::Webvtt => '.',
+    }
+}
+
+/// `{\an8}` and the like, ASS positioning a player would otherwise show as text
+fn strip_override_codes(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(open) = rest.find("{\\") {
+        match rest[open..].find('}') {
+            Some(close) => {
+                out.push_str(&rest[..open]);
+                rest = &rest[open + close + 1..];
+            }
+            None => break,
+        }
+    }
+    out.push_str(rest);
+    out.trim_end().to_string()
+}
+
+/// Cues that end before they start, or start before an earlier one has ended. `cues` are sorted
+/// by start
+fn timing_problems(cues: &[Cue]) -> Vec<String> {
+    let mut problems = vec![];
+    // The cue that ends last so far
+    let mut previous: Option<&Cue> = None;
+    for cue in cues {
+        let at = format_timestamp(cue.start, SubtitleFormat::Webvtt);
+        if cue.end <= cue.start {
+            problems.push(format!("cue at {} ends before it starts", at));
+            continue
+        }
+        match previous {
+            Some(previous) if cue.start < previous.end => {
+                problems.push(format!("cue at {} overlaps the cue at {}", at, format_timestamp(previous.start, SubtitleFormat::Webvtt)));
+            }
+            _ => {}
+        }
+        if previous.map(|previous| previous.end < cue.end).unwrap_or(true) {
+            previous = Some(cue);
+        }
+    }
+    problems
+}
+
+/// Every cue `offset` milliseconds later, or earlier when it is negative. A cue can't be moved
+/// before the start of the title
+pub fn shift(cues: Vec<Cue>, offset: i64) -> QueryResult<Vec<Cue>> {
+    let moved = |time: u64| match offset < 0 {
+        true => time.checked_sub(offset.unsigned_abs()),
+        false => time.checked_add(offset as u64),
+    };
+    cues.into_iter()
+        .map(|cue| match (moved(cue.start), moved(cue.end)) {
+            (Some(start), Some(end)) => Ok(Cue { start, end, ..cue }),
+            _ => Err(ServiceError::BadRequest(format!(
+                "An offset of {}ms moves the cue at {} before the start",
+                offset,
+                format_timestamp(cue.start, SubtitleFormat::Webvtt),
+            ))),
+        })
+        .collect()
+}
+
+pub fn to_webvtt(cues: &[Cue]) -> String {
+    let mut out = String::from("WEBVTT\n");
+    for cue in cues {
+        out.push('\n');
+        if let Some(id) = &cue.id {
+            let _ = writeln!(out, "{}", id);
+        }
+        let _ = write!(out, "{} --> {}", format_timestamp(cue.start, SubtitleFormat::Webvtt), format_timestamp(cue.end, SubtitleFormat::Webvtt));
+        if let Some(settings) = &cue.settings {
+            let _ = write!(out, " {}", settings);
+        }
+        let _ = writeln!(out, "\n{}", cue.text);
+    }
+    out
+}
+
+/// Cues are numbered from 1, identifiers and settings are dropped
+pub fn to_srt(cues: &[Cue]) -> String {
+    let mut out = String::new();
+    for (i, cue) in cues.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        let _ = writeln!(
+            out,
+            "{}\n{} --> {}\n{}",
+            i + 1, format_timestamp(cue.start, SubtitleFormat::Srt), format_timestamp(cue.end, SubtitleFormat::Srt), cue.text,
+        );
+    }
+    out
+}
+
+pub fn write(cues: &[Cue], format: SubtitleFormat) -> String {
+    match format {
+        SubtitleFormat::Srt => to_srt(cues),
+        SubtitleFormat::Webvtt => to_webvtt(cues),
+    }
+}
@@ -0,0 +1,182 @@
+use actix_web::{http::header::CONTENT_TYPE, web, HttpRequest, HttpResponse};
+use common_utils::{
+    QueryResult,
+    error::ServiceError,
+    locale::normalise_locale,
+    playback::{MediaOwner, TextTrackFormat, TextTrackKind},
+};
+use lazy_static::lazy_static;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use crate::{db::session, generate_unique_id};
+use super::super::{movies::resolver::MovieDatabase, series::resolver::SeriesDatabase};
+use super::super::artwork::storage::SharedImageStore;
+use super::super::media::{model::{MediaAsset, TextTrack, TrackType}, resolver::MediaDatabase, schema::{resolve_owner, MediaOwnerInput, TextTrackType}};
+use super::encoding::decode;
+use super::parser::{parse, shift, to_webvtt, write, Cue, SubtitleFormat};
+
+lazy_static! {
+    /// Largest subtitle file accepted, in bytes
+    pub static ref SUBTITLE_MAX_BYTES: usize = std::env::var("SUBTITLE_MAX_BYTES")
+        .ok()
+        .and_then(|bytes| bytes.parse::<usize>().ok())
+        .unwrap_or(2 * 1024 * 1024);
+}
+
+/// Subtitle uploads of movies and episodes, and conversion between SubRip and WebVTT
+pub fn configure_subtitles(cfg: &mut web::ServiceConfig) {
+    cfg
+    .service(
+        web::resource("/subtitles/movies/{movie_id}")
+            .app_data(web::PayloadConfig::new(*SUBTITLE_MAX_BYTES))
+            .route(web::post().to(upload_movie_subtitles))
+    )
+    .service(
+        web::resource("/subtitles/episodes/{series_id}/{season_number}/{episode_number}")
+            .app_data(web::PayloadConfig::new(*SUBTITLE_MAX_BYTES))
+            .route(web::post().to(upload_episode_subtitles))
+    )
+    .service(
+        web::resource("/subtitles/convert")
+            .app_data(web::PayloadConfig::new(*SUBTITLE_MAX_BYTES))
+            .route(web::post().to(convert_subtitles))
+    );
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UploadParams {
+    pub language: String,
+    pub kind: Option<TextTrackKind>,
+    /// Format of the file, detected from its first line when left out
+    pub format: Option<SubtitleFormat>,
+    /// Encoding of the file, e.g. `windows-1252`, when neither a byte order mark nor the
+    /// `Content-Type` names it and detection guesses wrong
+    pub charset: Option<String>,
+    /// Milliseconds added to every cue, negative to show them earlier
+    #[serde(default)]
+    pub offset: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConvertParams {
+    pub to: SubtitleFormat,
+    pub format: Option<SubtitleFormat>,
+    pub charset: Option<String>,
+    #[serde(default)]
+    pub offset: i64,
+}
+
+/// `charset` of a `Content-Type` such as `text/plain; charset=windows-1252`
+fn content_charset(http: &HttpRequest) -> Option<String> {
+    let content_type = http.headers().get(CONTENT_TYPE)?.to_str().ok()?;
+    content_type
+        .split(';')
+        .skip(1)
+        .filter_map(|param| param.split_once('='))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("charset"))
+        .map(|(_, charset)| charset.trim().trim_matches('"').to_string())
+}
+
+/// The cues of an upload, decoded, checked and moved by `offset`
+fn read(body: &[u8], format: Option<SubtitleFormat>, charset: Option<String>, offset: i64, http: &HttpRequest) -> QueryResult<Vec<Cue>> {
+    let charset = charset.or_else(|| content_charset(http));
+    let (text, encoding) = decode(body, charset.as_deref())?;
+    let format = format.unwrap_or_else(|| SubtitleFormat::detect(&text));
+    tracing::debug!("Reading {} subtitles encoded in {}", format, encoding.name());
+    let cues = parse(&text, format)?;
+    if cues.is_empty() {
+        return Err(ServiceError::BadRequest("The file has no cues".to_string()))
+    }
+    shift(cues, offset)
+}
+
+/// Where the WebVTT of a text track is stored, every upload has a prefix of its own
+fn storage_prefix(owner: MediaOwner, owner_id: i64, track_id: i64) -> String {
+    format!("subtitles/{}/{}/{}", owner.to_string().to_lowercase(), owner_id, track_id)
+}
+
+/// Stores the upload as WebVTT and registers it as the owner's text track of its language and
+/// kind, replacing the one uploaded before. Answered with `201 Created` and the new track
+async fn upload(owner: MediaOwnerInput, params: UploadParams, http: HttpRequest, body: web::Bytes, store: web::Data<SharedImageStore>) -> Result<HttpResponse, ServiceError> {
+    let (owner, owner_id) = resolve_owner::<MovieDatabase, SeriesDatabase>(owner, session(), session()).await?;
+    let UploadParams { language, kind, format, charset, offset } = params;
+    let language = normalise_locale(&language)?;
+    let kind = kind.unwrap_or(TextTrackKind::Subtitles);
+    let webvtt = to_webvtt(&read(&body, format, charset, offset, &http)?);
+    let checksum = format!("sha256:{}", hex::encode(Sha256::digest(webvtt.as_bytes())));
+    let track_id = generate_unique_id();
+    let prefix = storage_prefix(owner, owner_id, track_id);
+    let location = store
+        .put(&format!("{}/{}.vtt", prefix, language), SubtitleFormat::Webvtt.content_type(), webvtt.into_bytes())
+        .await?;
+    let track = TextTrack {
+        owner_type: owner.to_string(),
+        owner_id,
+        track_id,
+        checksum,
+        format: TextTrackFormat::Webvtt.to_string(),
+        kind: kind.to_string(),
+        language,
+        location,
+    }
+    .validated();
+    let saved = match track {
+        Ok(track) => MediaAsset::save_text_track::<MediaDatabase>(track, session()).await,
+        Err(e) => Err(e),
+    };
+    let track = match saved {
+        Ok(track) => track,
+        Err(e) => {
+            store.delete_prefix(&prefix).await.ok();
+            return Err(e)
+        }
+    };
+    let replaced: Vec<i64> = MediaAsset::get_media::<MediaDatabase>(owner, owner_id, session())
+        .await?
+        .text_tracks
+        .into_iter()
+        .filter(|f| f.track_id != track.track_id && f.language == track.language && f.kind == track.kind)
+        .map(|f| f.track_id)
+        .collect();
+    for track_id in replaced {
+        MediaAsset::delete_track::<MediaDatabase>(TrackType::Text, owner, owner_id, track_id, session()).await?;
+        store.delete_prefix(&storage_prefix(owner, owner_id, track_id)).await.ok();
+    }
+    Ok(HttpResponse::Created().json(TextTrackType::from(&track)))
+}
+
+/// `POST /subtitles/movies/{movie_id}?language=en&kind=CAPTIONS&offset=-500` with a SubRip or
+/// WebVTT file as the body
+pub async fn upload_movie_subtitles(
+    path: web::Path<i64>,
+    params: web::Query<UploadParams>,
+    http: HttpRequest,
+    body: web::Bytes,
+    store: web::Data<SharedImageStore>,
+) -> Result<HttpResponse, ServiceError> {
+    let owner = MediaOwnerInput { movie_id: Some(path.into_inner().into()), series_id: None, season_number: None, episode_number: None };
+    upload(owner, params.into_inner(), http, body, store).await
+}
+
+/// `POST /subtitles/episodes/{series_id}/{season_number}/{episode_number}?language=en`
+pub async fn upload_episode_subtitles(
+    path: web::Path<(i64, i32, i32)>,
+    params: web::Query<UploadParams>,
+    http: HttpRequest,
+    body: web::Bytes,
+    store: web::Data<SharedImageStore>,
+) -> Result<HttpResponse, ServiceError> {
+    let (series_id, season_number, episode_number) = path.into_inner();
+    let owner = MediaOwnerInput { movie_id: None, series_id: Some(series_id.into()), season_number: Some(season_number), episode_number: Some(episode_number) };
+    upload(owner, params.into_inner(), http, body, store).await
+}
+
+/// `POST /subtitles/convert?to=SRT` with a SubRip or WebVTT file as the body, answered with the
+/// file in UTF-8 and the format asked for. Nothing is stored
+pub async fn convert_subtitles(params: web::Query<ConvertParams>, http: HttpRequest, body: web::Bytes) -> Result<HttpResponse, ServiceError> {
+    let ConvertParams { to, format, charset, offset } = params.into_inner();
+    let cues = read(&body, format, charset, offset, &http)?;
+    Ok(HttpResponse::Ok()
+        .content_type(format!("{}; charset=utf-8", to.content_type()))
+        .body(write(&cues, to)))
+}
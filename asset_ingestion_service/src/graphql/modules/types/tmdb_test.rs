@@ -8,11 +8,18 @@ use chrono::{NaiveDate, Datelike, Utc};
 use crate::generate_unique_id;
 use futures::stream::StreamExt;
 use super::movies::model::{Movie, NewMovie, BusinessData, MediaType, Status, MediaRated, MovieRating};
+use super::series::model::{Series, Season, Episode, SeriesStatus};
+use super::credits::model::{Credit, ACTING};
+use super::genres::model::{or_blank, tmdb_genre_ids};
+use super::translations::model::MovieTranslation;
+use super::artwork::model::Artwork;
+use common_utils::artwork::{ImageKind, ImageOwner};
+use common_utils::error::ServiceError;
 use lazy_static::lazy_static;
 use parking_lot::Mutex;
-const TMDB_URL: &str = "https://api.themoviedb.org/3";
-
 lazy_static! { 
+    // Overridable so ingestion jobs can be run against a local fixture server
+    static ref TMDB_URL: String = std::env::var("TMDB_URL").unwrap_or_else(|_| "https://api.themoviedb.org/3".to_string());
     static ref TMDB_API_KEY: String = std::env::var("TMDB_API_KEY").expect("Unable to get a value api key");
 }
 #[derive(Debug, PartialEq, Deserialize, Serialize, Clone)]
@@ -45,7 +52,7 @@ pub struct TmdbMovie {
 pub async fn fetch_movies_externally(discover_api: String, endpoint_popular: String, language: String, included_with: String,page: i32, ) -> QueryResult<Vec<Movie>> {
     log::info!("Fetching Movies From TDb");
     let tmdb_url = format!("{api}/{discover_api}/{endpoint_popular}?api_key={key}&language={language}&{included_with}={page}",
-                            api = &TMDB_URL,
+                            api = TMDB_URL.as_str(),
                             // discover_api = discover_api.as_str(),
                             // endpoint_popular = endpoint_popular.as_str(),
                             // language = language.as_str9)
@@ -78,7 +85,8 @@ impl From<&TmdbMovie> for Movie {
             awards: vec![String::new()],
             business: BusinessData::default(),
             countries: vec![String::new()],
-            genres: f.genre_ids.iter().map(|f| f.to_string()).collect::<Vec<String>>(),
+            deleted_at: None,
+            genres: tmdb_genre_ids(&f.genre_ids),
             homepage: String::new(),
             keywords: vec![String::new()],
             languages: vec![String::new()],
@@ -89,14 +97,14 @@ impl From<&TmdbMovie> for Movie {
             movie_writer: vec![String::new()],
 
             overview: f.overview.clone(),
-            poster: f.backdrop_path.clone(),
+            poster: f.poster_path.clone(),
             rated: MediaRated::default().to_string(),
             rating: MovieRating::new(None, None, Some(f.popularity as f32), Some(f.vote_count.into()), Some(f.vote_average as f32)),
             release_date: release.clone(),
             runtime: 120,
             status: Status::default().to_string(),
-            
-            video_file: f.backdrop_path.clone(),
+            //  Trailers come from `/videos`, backdrops are imported as artwork
+            video_file: String::new(),
         }
     }
 }
@@ -211,20 +219,8 @@ impl From<&MovieDetails> for Movie {
         } else { 
             vec![String::new()]
         };
-        let default_genre = if f.genres.is_some() { 
-            if !f.genres.clone().unwrap().is_empty() {
-                f.genres
-                    .clone()
-                    .unwrap_or_default()             
-                    .iter()
-                    .map(|f| f.name.clone().unwrap_or(String::new()))
-                    .collect::<Vec<String>>()
-            } else { 
-                vec![String::new()]
-            }
-        } else { 
-            vec![String::new()]
-        };
+        let genre_ids = f.genres.clone().unwrap_or_default().iter().map(|genre| genre.id).collect::<Vec<i32>>();
+        let default_genre = tmdb_genre_ids(&genre_ids);
         let default_lang = if f.spoken_languages.is_some() { 
             if !f.spoken_languages.clone().unwrap().is_empty() {
                 f.spoken_languages
@@ -266,6 +262,7 @@ impl From<&MovieDetails> for Movie {
             awards: vec![String::new()],
             business: BusinessData::new(f.budget, f.revenue),
             countries: default_countries,
+            deleted_at: None,
             genres: default_genre,
             homepage: f.homepage.clone().unwrap_or_default(),
             keywords: vec![String::new()],
@@ -316,7 +313,7 @@ pub async fn fetch_movies_by_list(
 ) -> QueryResult<Vec<i64>> {
     log::info!("👷 Fetching Movie Ids from List");
     let tmdb_url = format!("{api}/{discover_api}/{endpoint_popular}?api_key={key}&language={language}&{included_with}",
-                            api = &TMDB_URL,
+                            api = TMDB_URL.as_str(),
                             key = TMDB_API_KEY.as_str(),
                             language = language.clone().unwrap_or("en-US".to_string()),
                             included_with = included_with.unwrap_or("".to_string()),
@@ -361,7 +358,7 @@ pub async fn fetch_movie_details(
     while let Some(mov_id) = stream.next().await { 
         //https://api.themoviedb.org/3/movie/453395?api_key=82f649eeb0cc9fb9e6ad4785c48623ac&language=en-US
         let tmurl = format!("{url}/movie/{mov_id}?api_key={api}&language={movielanguage}", 
-                            url = &TMDB_URL, 
+                            url = TMDB_URL.as_str(), 
                             api = TMDB_API_KEY.as_str(),
                             movielanguage = language.clone().unwrap_or("en-US".to_string())
         );
@@ -425,6 +422,7 @@ pub async fn fetch_movie_details(
             awards: movie.awards,
             business: movie.business,
             countries: movie.countries,
+            deleted_at: movie.deleted_at,
             genres: movie.genres,
             homepage: movie.homepage,
             keywords: keyword.clone(),
@@ -467,7 +465,7 @@ pub async fn get_keywords(movie_id: Arc<Mutex<i64>>) -> QueryResult<Vec<String>>
     
     log::info!("👷 Getting Keywords");
     let url = format!("{url}/movie/{movie_id}/keywords?api_key={api}", 
-            url = &TMDB_URL, 
+            url = TMDB_URL.as_str(), 
             api = TMDB_API_KEY.as_str(),
     );
     //  Take the words 
@@ -527,37 +525,55 @@ pub struct MovieCreditResponse {
     crew: Vec<MovieCrew>
 }
 
-/// Find movie writer asynchronosuly
-pub async fn get_writers(movie_id: Arc<Mutex<i64>>) -> QueryResult<Vec<String>> { 
-    let movie_id = *movie_id.lock();
-
-    log::info!("👷 Getting Movie Peoples");
-    let url = format!(
+/// Cast and crew of a movie as credits, the person ids are TMDB's until `Person::resolve_tmdb_credits`
+pub async fn get_credits(movie_id: i64) -> QueryResult<Vec<Credit>> { 
+    log::info!("👷 Getting Movie Credits");
+    let response: MovieCreditResponse = get_tmdb(format!(
         "{url}/movie/{movie_id}/credits?api_key={api}&language=en-US",
-        url = &TMDB_URL,
+        url = TMDB_URL.as_str(),
         api = TMDB_API_KEY.as_str(),
-    );
+    )).await?;
+    Ok(to_credits(movie_id, &response.cast, &response.crew))
+}
 
-    // Issue: The items for movie cast is replicated in other sets
-    let response: MovieCreditResponse = tokio::spawn( async move{reqwest::get(&url)
-        .await
-        .expect("Unable to get valid request body")
-        .json()
-        .await
-        .map(|t: MovieCreditResponse| t)
-        .expect("")}).await.unwrap();
-    let get_writers: Vec<String> = response
-        .crew
-        .into_iter()
-        .filter(|f| f.known_for_department == "Writing")
-        .map(|n| n.original_name)
-        .collect();
-    let test_writers = if !get_writers.is_empty() { 
-        get_writers
-    } else { 
-        vec![String::new()]
-    };
-    Ok(test_writers)
+fn to_credits(movie_id: i64, cast: &[MovieCast], crew: &[MovieCrew]) -> Vec<Credit> { 
+    let cast = cast.iter().map(|f| Credit { 
+        movie_id,
+        credit_id: f.credit_id.clone(),
+        billing_order: Some(f.order),
+        character_name: Some(f.character.clone()),
+        department: ACTING.to_string(),
+        job: "Actor".to_string(),
+        name: f.name.clone(),
+        person_id: f.id,
+    });
+    let crew = crew.iter().map(|f| Credit { 
+        movie_id,
+        credit_id: f.credit_id.clone(),
+        billing_order: None,
+        character_name: None,
+        department: f.department.clone(),
+        job: f.job.clone(),
+        name: f.name.clone(),
+        person_id: f.id,
+    });
+    cast.chain(crew).collect()
+}
+
+/// Names of the crew credited in `department`, the way `movie_writer` and `movie_director` store them
+fn crew_names(credits: &[Credit], department: &str) -> Vec<String> { 
+    or_blank(credits.iter().filter(|f| f.department == department).map(|f| f.name.clone()).collect())
+}
+
+/// Characters of the cast, the way `movie_casts` stores them
+fn cast_characters(credits: &[Credit]) -> Vec<String> { 
+    or_blank(credits.iter().filter(|f| f.is_cast()).filter_map(|f| f.character_name.clone()).collect())
+}
+
+/// Find movie writer asynchronosuly
+pub async fn get_writers(movie_id: Arc<Mutex<i64>>) -> QueryResult<Vec<String>> { 
+    let movie_id = *movie_id.lock();
+    Ok(crew_names(&get_credits(movie_id).await?, "Writing"))
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -587,7 +603,7 @@ pub async fn get_trailer(movie_id: Arc<Mutex<i64>>) -> QueryResult<String> {
 
     let url = format!(
         "{url}/movie/{movie_id}/videos?api_key={api}&language=en-US&append_to_response=videos",
-        url = &TMDB_URL,
+        url = TMDB_URL.as_str(),
         api = TMDB_API_KEY.as_str(),
     );
 
@@ -611,64 +627,361 @@ pub async fn get_trailer(movie_id: Arc<Mutex<i64>>) -> QueryResult<String> {
 /// Find the movie actors asynchronously
 pub async fn get_cast(movie_id: Arc<Mutex<i64>>) -> QueryResult<Vec<String>> { 
     let movie_id = *movie_id.lock();
-
-    log::info!("👷 Getting Movie actors");
-    let url = format!(
-        "{url}/movie/{movie_id}/credits?api_key={api}&language=en-US",
-        url = &TMDB_URL,
-        api = TMDB_API_KEY.as_str(),
-    );
-    // Issue: The items for movie cast is replicated in other sets
-    let response: MovieCreditResponse = reqwest::get(&url)
-        .await
-        .expect("Unable to get valid request body")
-        .json()
-        .await
-        .map(|t: MovieCreditResponse| t)
-        .expect("");
-    let casts: Vec<String> = response
-        .cast
-        .clone()
-        .into_iter()
-        .filter(|f| f.known_for_department == "Acting")
-        .map(|n| n.character)
-        .collect(); 
-    let test_cast = if !casts.is_empty() { 
-        casts.clone()
-    } else { 
-        vec![String::new()]
-    };
-    Ok(test_cast)
+    Ok(cast_characters(&get_credits(movie_id).await?))
 }
 /// Find the movie director asynchronously
 pub async fn get_director(movie_id: Arc<Mutex<i64>>) -> QueryResult<Vec<String>>{ 
     let movie_id = *movie_id.lock();
+    Ok(crew_names(&get_credits(movie_id).await?, "Directing"))
+}
 
-    log::info!("👷 Getting Movie actors");
-    let url = format!(
-        "{url}/movie/{movie_id}/credits?api_key={api}&language=en-US",
-        url = &TMDB_URL,
-        api = TMDB_API_KEY.as_str(),
-    );
-    // Issue: The items for movie cast is replicated in other sets
-    let response: MovieCreditResponse = reqwest::get(&url)
+#[derive(Debug, PartialEq, Deserialize, Serialize, Clone, Default)]
+pub struct TvCreator { 
+    id: i64,
+    name: Option<String>,
+}
+
+/// A season as listed on `/tv/{id}`, without its episodes
+#[derive(Debug, PartialEq, Deserialize, Serialize, Clone, Default)]
+pub struct TvSeasonSummary { 
+    id: i64,
+    season_number: i32,
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize, Clone, Default)]
+pub struct TvDetails { 
+    id: i64,
+    name: String,
+    overview: Option<String>,
+    poster_path: Option<String>,
+    homepage: Option<String>,
+    status: Option<String>,
+    in_production: Option<bool>,
+    first_air_date: Option<String>,
+    last_air_date: Option<String>,
+    genres: Option<Vec<GenreType>>,
+    languages: Option<Vec<String>>,
+    created_by: Option<Vec<TvCreator>>,
+    seasons: Option<Vec<TvSeasonSummary>>,
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize, Clone, Default)]
+pub struct TvEpisode { 
+    id: i64,
+    season_number: i32,
+    episode_number: i32,
+    name: Option<String>,
+    overview: Option<String>,
+    air_date: Option<String>,
+    runtime: Option<i64>,
+    still_path: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize, Clone, Default)]
+pub struct TvSeasonDetails { 
+    id: i64,
+    season_number: i32,
+    name: Option<String>,
+    overview: Option<String>,
+    poster_path: Option<String>,
+    air_date: Option<String>,
+    episodes: Option<Vec<TvEpisode>>,
+}
+
+/// TMDB sends an empty string for dates that are not announced yet
+fn parse_tmdb_date(date: &Option<String>) -> Option<NaiveDate> { 
+    date.as_deref().and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+}
+
+impl From<&TvDetails> for Series { 
+    fn from(f: &TvDetails) -> Self {
+        Self { 
+            series_id: f.id,
+            created_by: f.created_by.clone().unwrap_or_default().into_iter().filter_map(|creator| creator.name).collect(),
+            first_air_date: parse_tmdb_date(&f.first_air_date).unwrap_or_else(|| Utc::now().naive_utc().date()),
+            genres: tmdb_genre_ids(&f.genres.clone().unwrap_or_default().iter().map(|genre| genre.id).collect::<Vec<i32>>()),
+            homepage: f.homepage.clone().unwrap_or_default(),
+            languages: f.languages.clone().unwrap_or_default(),
+            //  The last air date of a running series is its latest episode, not its end
+            last_air_date: match f.in_production { 
+                Some(true) => None,
+                _ => parse_tmdb_date(&f.last_air_date),
+            },
+            overview: f.overview.clone().unwrap_or_default(),
+            poster: f.poster_path.clone().unwrap_or_default(),
+            rated: MediaRated::default().to_string(),
+            status: SeriesStatus::from_tmdb(f.status.as_deref().unwrap_or_default()).to_string(),
+            title: f.name.clone(),
+        }
+    }
+}
+
+impl TvSeasonDetails { 
+    fn to_season(&self, series_id: i64) -> Season { 
+        Season { 
+            series_id,
+            season_number: self.season_number,
+            air_date: parse_tmdb_date(&self.air_date),
+            name: self.name.clone().unwrap_or_else(|| format!("Season {}", self.season_number)),
+            overview: self.overview.clone().unwrap_or_default(),
+            poster: self.poster_path.clone().unwrap_or_default(),
+            season_id: self.id,
+        }
+    }
+}
+
+impl TvEpisode { 
+    fn to_episode(&self, series_id: i64) -> Episode { 
+        Episode { 
+            series_id,
+            season_number: self.season_number,
+            episode_number: self.episode_number,
+            air_date: parse_tmdb_date(&self.air_date),
+            episode_id: self.id,
+            overview: self.overview.clone().unwrap_or_default(),
+            runtime: self.runtime.unwrap_or_default(),
+            still_path: self.still_path.clone().unwrap_or_default(),
+            title: self.name.clone().unwrap_or_else(|| format!("Episode {}", self.episode_number)),
+            video_file: String::new(),
+        }
+    }
+}
+
+async fn get_tmdb<T: serde::de::DeserializeOwned>(url: String) -> QueryResult<T> { 
+    reqwest::get(&url)
         .await
-        .expect("Unable to get valid request body")
-        .json()
+        .map_err(|e| ServiceError::ServerError(e.to_string()))?
+        .error_for_status()
+        .map_err(|e| ServiceError::BadRequest(e.to_string()))?
+        .json::<T>()
         .await
-        .map(|t: MovieCreditResponse| t)
-        .expect("");
-    let get_directors: Vec<String> = response
-        .crew.clone()
-        .into_iter()
-        .filter(|f| f.known_for_department == "Directing")
-        .map(|n| n.original_name)
-        .collect();
+        .map_err(|e| ServiceError::ServerError(e.to_string()))
+}
 
-    let test_directors = if !get_directors.is_empty() { 
-        get_directors.clone()
-    } else { 
-        vec![String::new()]
+/// Fetches a TV series with every season and episode, one request for the series and one per season.
+/// Episodes have no video yet, `video_file` is left empty until the media is uploaded
+pub async fn fetch_tv_series(tv_id: i64, language: Option<String>) -> QueryResult<(Series, Vec<Season>, Vec<Episode>)> { 
+    let language = language.unwrap_or("en-US".to_string());
+    log::info!("📺 Fetching TV series {} from TMDB", tv_id);
+    let details: TvDetails = get_tmdb(format!("{url}/tv/{tv_id}?api_key={api}&language={language}",
+                                    url = TMDB_URL.as_str(),
+                                    api = TMDB_API_KEY.as_str()
+    )).await?;
+
+    let series = Series::from(&details);
+    let mut seasons = Vec::new();
+    let mut episodes = Vec::new();
+    let mut stream = futures::stream::iter(details.seasons.unwrap_or_default());
+    while let Some(summary) = stream.next().await { 
+        let season: TvSeasonDetails = get_tmdb(format!("{url}/tv/{tv_id}/season/{season_number}?api_key={api}&language={language}",
+                                        url = TMDB_URL.as_str(),
+                                        season_number = summary.season_number,
+                                        api = TMDB_API_KEY.as_str()
+        )).await?;
+        episodes.extend(season.episodes.iter().flatten().map(|episode| episode.to_episode(series.series_id)));
+        seasons.push(season.to_season(series.series_id));
+    }
+    log::info!("Fetched {} seasons and {} episodes of {}", seasons.len(), episodes.len(), series.title);
+    Ok((series, seasons, episodes))
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct TmdbMovieId { 
+    id: i64,
+}
+
+/// Keywords, credits and videos appended to `/movie/{id}`, they come without the movie id
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct AppendedKeywords { 
+    keywords: Option<Vec<Keywords>>,
+}
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct AppendedCredits { 
+    #[serde(default)]
+    cast: Vec<MovieCast>,
+    #[serde(default)]
+    crew: Vec<MovieCrew>,
+}
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct AppendedVideos { 
+    results: Option<Vec<VideosResult>>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct MovieDetailsWithExtras { 
+    #[serde(flatten)]
+    details: MovieDetails,
+    #[serde(default)]
+    keywords: AppendedKeywords,
+    #[serde(default)]
+    credits: AppendedCredits,
+    #[serde(default)]
+    videos: AppendedVideos,
+}
+
+/// Pages through a TMDB list until `limit` ids are collected or the list runs out.
+/// Unlike `fetch_movies_by_list` a bad response is returned as an error instead of panicking
+pub async fn discover_movie_ids(
+    discover_api: &str, 
+    endpoint_popular: &str, 
+    language: Option<String>, 
+    included_with: Option<String>, 
+    limit: usize
+) -> QueryResult<Vec<i64>> { 
+    let mut movie_ids = Vec::with_capacity(limit);
+    let mut page = 1;
+    while movie_ids.len() < limit { 
+        let response: MoviesResponse<TmdbMovieId> = get_tmdb(format!("{url}/{discover_api}/{endpoint_popular}?api_key={api}&language={language}&page={page}&{included_with}",
+                                                        url = TMDB_URL.as_str(),
+                                                        api = TMDB_API_KEY.as_str(),
+                                                        language = language.clone().unwrap_or("en-US".to_string()),
+                                                        included_with = included_with.clone().unwrap_or_default(),
+        )).await?;
+        let remaining = limit - movie_ids.len();
+        movie_ids.extend(response.results.unwrap_or_default().iter().take(remaining).map(|movie| movie.id));
+        if page >= response.total_pages { 
+            break;
+        }
+        page += 1;
+    }
+    Ok(movie_ids)
+}
+
+/// Fetches one movie with its keywords, credits and trailer in a single request
+pub async fn fetch_movie(movie_id: i64, language: Option<String>) -> QueryResult<(Movie, Vec<Credit>)> { 
+    let response: MovieDetailsWithExtras = get_tmdb(format!("{url}/movie/{movie_id}?api_key={api}&language={language}&append_to_response=keywords,credits,videos",
+                                                url = TMDB_URL.as_str(),
+                                                api = TMDB_API_KEY.as_str(),
+                                                language = language.unwrap_or("en-US".to_string()),
+    )).await?;
+    if response.details.id.is_none() { 
+        return Err(ServiceError::NotFound);
+    }
+    let MovieDetailsWithExtras { details, keywords, credits, videos } = response;
+    let credits = to_credits(movie_id, &credits.cast, &credits.crew);
+    let movie = Movie { 
+        keywords: or_blank(keywords.keywords.unwrap_or_default().into_iter().map(|f| f.name.unwrap_or_default()).collect()),
+        movie_casts: cast_characters(&credits),
+        movie_director: crew_names(&credits, "Directing"),
+        movie_writer: crew_names(&credits, "Writing"),
+        video_file: videos.results
+            .unwrap_or_default()
+            .into_iter()
+            .find(|video| video.video_type == "Trailer")
+            .map(|video| video.id)
+            .unwrap_or_default(),
+        ..Movie::from(&details)
     };
-    Ok(test_directors)
-}
\ No newline at end of file
+    Ok((movie, credits))
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct TranslationData { 
+    overview: Option<String>,
+    tagline: Option<String>,
+    title: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct TmdbTranslation { 
+    iso_3166_1: String,
+    iso_639_1: String,
+    #[serde(default)]
+    data: TranslationData,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct TranslationsResponse { 
+    #[serde(default)]
+    translations: Vec<TmdbTranslation>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct TmdbImage { 
+    file_path: String,
+    iso_639_1: Option<String>,
+    #[serde(default)]
+    vote_average: f64,
+    #[serde(default)]
+    width: i32,
+    #[serde(default)]
+    height: i32,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ImagesResponse { 
+    #[serde(default)]
+    posters: Vec<TmdbImage>,
+    #[serde(default)]
+    backdrops: Vec<TmdbImage>,
+    #[serde(default)]
+    logos: Vec<TmdbImage>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct PersonImagesResponse { 
+    #[serde(default)]
+    profiles: Vec<TmdbImage>,
+}
+
+/// `images` as artwork of one kind, best voted first. TMDB marks images without text with an `xx` language
+fn to_artwork(owner: ImageOwner, owner_id: i64, kind: ImageKind, mut images: Vec<TmdbImage>) -> QueryResult<Vec<Artwork>> { 
+    images.sort_by(|a, b| b.vote_average.total_cmp(&a.vote_average));
+    images
+        .iter()
+        .map(|image| { 
+            let language = image.iso_639_1.as_deref().filter(|language| *language != "xx");
+            Artwork::new(owner, owner_id, kind, &image.file_path, language, image.width, image.height)
+        })
+        .collect()
+}
+
+/// Posters, backdrops and logos of the TMDB movie `tmdb_id`, as artwork of `movie_id`
+pub async fn fetch_movie_images(movie_id: i64, tmdb_id: i64) -> QueryResult<Vec<Artwork>> { 
+    let images: ImagesResponse = get_tmdb(format!("{url}/movie/{tmdb_id}/images?api_key={api}",
+                                    url = TMDB_URL.as_str(),
+                                    api = TMDB_API_KEY.as_str(),
+    )).await?;
+    let mut artwork = to_artwork(ImageOwner::Movie, movie_id, ImageKind::Poster, images.posters)?;
+    artwork.extend(to_artwork(ImageOwner::Movie, movie_id, ImageKind::Backdrop, images.backdrops)?);
+    artwork.extend(to_artwork(ImageOwner::Movie, movie_id, ImageKind::Logo, images.logos)?);
+    log::info!("🖼️ Fetched {} images of movie {}", artwork.len(), movie_id);
+    Ok(artwork)
+}
+
+/// Profile pictures of the TMDB person `tmdb_id`, as artwork of `person_id`
+pub async fn fetch_person_images(person_id: i64, tmdb_id: i64) -> QueryResult<Vec<Artwork>> { 
+    let images: PersonImagesResponse = get_tmdb(format!("{url}/person/{tmdb_id}/images?api_key={api}",
+                                    url = TMDB_URL.as_str(),
+                                    api = TMDB_API_KEY.as_str(),
+    )).await?;
+    to_artwork(ImageOwner::Person, person_id, ImageKind::Profile, images.profiles)
+}
+
+/// Every translation TMDB has of a movie, keyed by language and region, e.g. `pt-BR`.
+/// Each one gets the best voted poster in its language, since TMDB does not tie posters to a region
+pub async fn fetch_movie_translations(movie_id: i64) -> QueryResult<Vec<MovieTranslation>> { 
+    let response: TranslationsResponse = get_tmdb(format!("{url}/movie/{movie_id}/translations?api_key={api}",
+                                            url = TMDB_URL.as_str(),
+                                            api = TMDB_API_KEY.as_str(),
+    )).await?;
+    let images: ImagesResponse = get_tmdb(format!("{url}/movie/{movie_id}/images?api_key={api}",
+                                    url = TMDB_URL.as_str(),
+                                    api = TMDB_API_KEY.as_str(),
+    )).await?;
+    let poster = |language: &str| images.posters
+        .iter()
+        .filter(|image| image.iso_639_1.as_deref() == Some(language))
+        .max_by(|a, b| a.vote_average.total_cmp(&b.vote_average))
+        .map(|image| image.file_path.clone());
+    let mut translations = Vec::with_capacity(response.translations.len());
+    for f in response.translations { 
+        let locale = format!("{}-{}", f.iso_639_1, f.iso_3166_1);
+        let translation = MovieTranslation::new(movie_id, &locale, f.data.title, f.data.overview, f.data.tagline, poster(&f.iso_639_1))?;
+        if !translation.is_empty() { 
+            translations.push(translation);
+        }
+    }
+    log::info!("🌐 Fetched {} translations of movie {}", translations.len(), movie_id);
+    Ok(translations)
+}
@@ -0,0 +1,56 @@
+use common_utils::{QueryResult, events::PartitionKey, locale::normalise_locale};
+use scylla::macros::{FromRow, ValueList};
+use serde::{Deserialize, Serialize};
+use super::resolver::TranslationResolver;
+
+// Columns after the primary key are in alphabetical order, the order `SELECT *` returns them in
+/// A movie's title, overview, tagline and poster in one locale. Anything left `None` is read
+/// from the canonical movie instead
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize, ValueList, PartialEq)]
+pub struct MovieTranslation {
+    pub movie_id: i64,
+    pub locale: String,
+    pub overview: Option<String>,
+    pub poster: Option<String>,
+    pub tagline: Option<String>,
+    pub title: Option<String>,
+}
+
+impl PartitionKey for MovieTranslation {
+    fn partition_key(&self) -> String {
+        self.movie_id.to_string()
+    }
+}
+
+impl MovieTranslation {
+    /// Blank fields are dropped so they fall back rather than hide the canonical value
+    pub fn new(movie_id: i64, locale: &str, title: Option<String>, overview: Option<String>, tagline: Option<String>, poster: Option<String>) -> QueryResult<Self> {
+        let present = |value: Option<String>| value.map(|value| value.trim().to_string()).filter(|value| !value.is_empty());
+        Ok(Self {
+            movie_id,
+            locale: normalise_locale(locale)?,
+            overview: present(overview),
+            poster: present(poster),
+            tagline: present(tagline),
+            title: present(title),
+        })
+    }
+    pub fn is_empty(&self) -> bool {
+        self.overview.is_none() && self.poster.is_none() && self.tagline.is_none() && self.title.is_none()
+    }
+}
+
+impl MovieTranslation {
+    pub async fn get_translations<TranslationDatabase: TranslationResolver>(movie_id: i64, session: &'static TranslationDatabase::Store) -> QueryResult<Vec<MovieTranslation>> {
+        TranslationDatabase::get_translations(movie_id, session).await
+    }
+    pub async fn upsert_translation<TranslationDatabase: TranslationResolver>(translation: MovieTranslation, session: &'static TranslationDatabase::Store) -> QueryResult<MovieTranslation> {
+        TranslationDatabase::upsert_translation(translation, session).await
+    }
+    pub async fn delete_translation<TranslationDatabase: TranslationResolver>(movie_id: i64, locale: String, session: &'static TranslationDatabase::Store) -> QueryResult<bool> {
+        TranslationDatabase::delete_translation(movie_id, locale, session).await
+    }
+    pub async fn publish_translations<TranslationDatabase: TranslationResolver>(movie_id: i64, translations: Vec<MovieTranslation>, session: &'static TranslationDatabase::Store) -> QueryResult<()> {
+        TranslationDatabase::publish_translations(movie_id, translations, session).await
+    }
+}
@@ -0,0 +1,67 @@
+use async_trait::async_trait;
+use common_utils::{QueryResult, error::ServiceError, events::TranslationEvent};
+use scylla::IntoTypedRows;
+use crate::db::{CachedSession, is_applied};
+use super::super::outbox::resolver::write_events;
+use super::model::MovieTranslation;
+
+/// Translations of a movie share its partition, a movie's translations are always read together
+#[async_trait]
+pub trait TranslationResolver: Send + Sync + 'static {
+    type Store: Send + Sync + 'static;
+    async fn get_translations(movie_id: i64, session: &'static Self::Store) -> QueryResult<Vec<MovieTranslation>>;
+    /// Replaces the translation of the movie in that locale, if it has one
+    async fn upsert_translation(translation: MovieTranslation, session: &'static Self::Store) -> QueryResult<MovieTranslation>;
+    async fn delete_translation(movie_id: i64, locale: String, session: &'static Self::Store) -> QueryResult<bool>;
+    /// Writes the `MovieTranslated` event to the event outbox, the relay publishes it
+    async fn publish_translations(movie_id: i64, translations: Vec<MovieTranslation>, session: &'static Self::Store) -> QueryResult<()>;
+}
+
+#[derive(Default)]
+pub struct TranslationDatabase;
+
+static GET_TRANSLATIONS: &str = "SELECT * FROM movie_keyspace.movie_translations WHERE movie_id = ?;";
+static INSERT_TRANSLATION: &str = "
+    INSERT INTO movie_keyspace.movie_translations (
+        movie_id, locale, overview, poster, tagline, title
+    ) VALUES (?, ?, ?, ?, ?, ?);
+";
+static DELETE_TRANSLATION: &str = "DELETE FROM movie_keyspace.movie_translations WHERE movie_id = ? AND locale = ? IF EXISTS;";
+
+#[async_trait]
+impl TranslationResolver for TranslationDatabase {
+    type Store = CachedSession;
+
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.movie_translations"), err)]
+    async fn get_translations(movie_id: i64, session: &'static CachedSession) -> QueryResult<Vec<MovieTranslation>> {
+        session.query_prepared(GET_TRANSLATIONS, (movie_id,))
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
+            .into_typed::<MovieTranslation>()
+            .map(|row| row.map_err(|e| ServiceError::ServerError(e.to_string())))
+            .collect()
+    }
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.movie_translations"), err)]
+    async fn upsert_translation(translation: MovieTranslation, session: &'static CachedSession) -> QueryResult<MovieTranslation> {
+        session.query_prepared(INSERT_TRANSLATION, translation.clone())
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?;
+        Ok(translation)
+    }
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.movie_translations"), err)]
+    async fn delete_translation(movie_id: i64, locale: String, session: &'static CachedSession) -> QueryResult<bool> {
+        let applied = session.query_prepared(DELETE_TRANSLATION, (movie_id, locale))
+            .await
+            .map(is_applied)
+            .map_err(|_| ServiceError::DatabaseError)?;
+        match applied {
+            true => Ok(true),
+            false => Err(ServiceError::NotFound),
+        }
+    }
+    #[tracing::instrument(skip(session, translations), fields(repository = "movie_keyspace.event_outbox"), err)]
+    async fn publish_translations(movie_id: i64, translations: Vec<MovieTranslation>, session: &'static CachedSession) -> QueryResult<()> {
+        write_events(vec![TranslationEvent::MovieTranslated { movie_id, translations }], session).await
+    }
+}
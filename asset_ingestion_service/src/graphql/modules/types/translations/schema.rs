@@ -0,0 +1,106 @@
+use std::marker::PhantomData;
+use async_graphql::*;
+use common_utils::{QueryResult, locale::normalise_locale};
+use serde::{Deserialize, Serialize};
+use crate::{graphql::{config::get_store_from_ctx, modules::types::tmdb_test::fetch_movie_translations}, to_bigint};
+use super::super::movies::{model::Movie, resolver::{MovieDatabase, MovieResolver}};
+use super::model::MovieTranslation;
+use super::resolver::{TranslationDatabase, TranslationResolver};
+
+/// Translations are written through `T`, the movie they belong to is looked up through `M`
+#[derive(Default)]
+pub struct TranslationMutation<T = TranslationDatabase, M = MovieDatabase>(PhantomData<(T, M)>);
+
+#[derive(SimpleObject, Debug, Clone, Deserialize, Serialize)]
+pub struct MovieTranslationType {
+    pub movie_id: ID,
+    pub locale: String,
+    pub title: Option<String>,
+    pub overview: Option<String>,
+    pub tagline: Option<String>,
+    pub poster: Option<String>,
+}
+
+impl From<&MovieTranslation> for MovieTranslationType {
+    fn from(f: &MovieTranslation) -> Self {
+        Self {
+            movie_id: f.movie_id.into(),
+            locale: f.locale.clone(),
+            title: f.title.clone(),
+            overview: f.overview.clone(),
+            tagline: f.tagline.clone(),
+            poster: f.poster.clone(),
+        }
+    }
+}
+
+/// Fields left out, or blank, fall back to the canonical movie
+#[derive(InputObject, Debug, Clone, Deserialize, Serialize)]
+pub struct MovieTranslationInput {
+    /// BCP 47 language tag, e.g. `pt-BR` or `de`
+    #[graphql(validator(min_length = 2, max_length = 16))]
+    pub locale: String,
+    #[graphql(validator(max_length = 250))]
+    pub title: Option<String>,
+    pub overview: Option<String>,
+    #[graphql(validator(max_length = 250))]
+    pub tagline: Option<String>,
+    pub poster: Option<String>,
+}
+
+/// Publishes every translation the movie has left, which is what the search index replaces its own with
+pub(crate) async fn publish<T: TranslationResolver>(movie_id: i64, session: &'static T::Store) -> QueryResult<Vec<MovieTranslation>> {
+    let translations = MovieTranslation::get_translations::<T>(movie_id, session).await?;
+    MovieTranslation::publish_translations::<T>(movie_id, translations.clone(), session).await?;
+    Ok(translations)
+}
+
+#[Object]
+impl<T: TranslationResolver, M: MovieResolver> TranslationMutation<T, M> {
+    /// Adds the translation of the movie in `translation.locale`, or replaces the one it had
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "upsertMovieTranslation")]
+    async fn upsert_movie_translation(&self, ctx: &Context<'_>, movie_id: ID, translation: MovieTranslationInput) -> FieldResult<MovieTranslationType> {
+        let movie_id = to_bigint(movie_id);
+        Movie::get_movie_id::<M>(movie_id, get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        let MovieTranslationInput { locale, title, overview, tagline, poster } = translation;
+        let translation = MovieTranslation::new(movie_id, &locale, title, overview, tagline, poster).map_err(|e| e.extend())?;
+        let res = MovieTranslation::upsert_translation::<T>(translation, get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        publish::<T>(movie_id, get_store_from_ctx(ctx)).await.map_err(|e| e.extend())?;
+        Ok(MovieTranslationType::from(&res))
+    }
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "deleteMovieTranslation")]
+    async fn delete_movie_translation(&self, ctx: &Context<'_>, movie_id: ID, locale: String) -> FieldResult<bool> {
+        let movie_id = to_bigint(movie_id);
+        let locale = normalise_locale(&locale).map_err(|e| e.extend())?;
+        let res = MovieTranslation::delete_translation::<T>(movie_id, locale, get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        publish::<T>(movie_id, get_store_from_ctx(ctx)).await.map_err(|e| e.extend())?;
+        Ok(res)
+    }
+    /// Copies every translation TMDB has of the movie. `tmdbId` defaults to `movieId`, which is
+    /// the TMDB id for movies imported from TMDB. Translations added by hand in other locales are kept
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "importMovieTranslations")]
+    async fn import_movie_translations(&self, ctx: &Context<'_>, movie_id: ID, tmdb_id: Option<ID>) -> FieldResult<Vec<MovieTranslationType>> {
+        let movie_id = to_bigint(movie_id);
+        Movie::get_movie_id::<M>(movie_id, get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        let tmdb_id = tmdb_id.map(to_bigint).unwrap_or(movie_id);
+        let translations = fetch_movie_translations(tmdb_id).await.map_err(|e| e.extend())?;
+        for translation in translations {
+            MovieTranslation::upsert_translation::<T>(MovieTranslation { movie_id, ..translation }, get_store_from_ctx(ctx))
+                .await
+                .map_err(|e| e.extend())?;
+        }
+        let res = publish::<T>(movie_id, get_store_from_ctx(ctx)).await.map_err(|e| e.extend())?;
+        Ok(res.iter().map(MovieTranslationType::from).collect())
+    }
+}
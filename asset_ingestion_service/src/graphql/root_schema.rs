@@ -1,15 +1,20 @@
-use async_graphql::{EmptySubscription, 
-    MergedObject, Schema, SchemaBuilder, EmptyMutation};
+use async_graphql::{MergedObject, MergedSubscription, Schema, SchemaBuilder, EmptyMutation};
 use super::modules::types::{
     ProductionCompanyQuery, ProductionCompanyMutation,
-    MovieMutation, PersonQuery, PersonMutation
+    MovieMutation, PersonQuery, PersonMutation, SeriesMutation,
+    IngestionJobQuery, IngestionJobMutation, IngestionJobSubscription, CreditMutation,
+    EntityResolutionQuery, EntityResolutionMutation, GenreQuery, GenreMutation, TranslationMutation, AvailabilityMutation,
+    ReleaseQuery, ReleaseMutation, ArtworkQuery, ArtworkMutation, MediaQuery, MediaMutation
 };
 
 #[derive(MergedObject, Default)]
-pub struct Query(ProductionCompanyQuery, PersonQuery);
+pub struct Query(ProductionCompanyQuery, PersonQuery, IngestionJobQuery, EntityResolutionQuery, GenreQuery, ReleaseQuery, ArtworkQuery, MediaQuery);
 
 #[derive(MergedObject, Default)]
-pub struct Mutation(ProductionCompanyMutation, MovieMutation, PersonMutation);
+pub struct Mutation(ProductionCompanyMutation, MovieMutation, PersonMutation, SeriesMutation, IngestionJobMutation, CreditMutation, EntityResolutionMutation, GenreMutation, TranslationMutation, AvailabilityMutation, ReleaseMutation, ArtworkMutation, MediaMutation);
 
-pub type AppSchema = Schema<Query, Mutation, EmptySubscription>;
-pub type AppSchemaBuilder = SchemaBuilder<Query, Mutation, EmptySubscription>;
+#[derive(MergedSubscription, Default)]
+pub struct Subscription(IngestionJobSubscription);
+
+pub type AppSchema = Schema<Query, Mutation, Subscription>;
+pub type AppSchemaBuilder = SchemaBuilder<Query, Mutation, Subscription>;
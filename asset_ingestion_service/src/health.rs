@@ -0,0 +1,9 @@
+use common_utils::health::{check_kafka_brokers, check_scylla, Readiness};
+use crate::{db::session, kafka::kafka_producer};
+
+/// Ready only when Scylla answers a query and the Kafka brokers can be reached
+pub fn readiness() -> Readiness { 
+    Readiness::new(env!("CARGO_PKG_NAME"))
+        .probe("scylla", || check_scylla(&session().0))
+        .probe("kafka", || check_kafka_brokers(kafka_producer().0.producer()))
+}
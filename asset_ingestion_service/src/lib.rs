@@ -16,6 +16,7 @@ extern crate derivative;
 // pub mod bucket;
 pub mod graphql;
 pub mod server;
+pub mod health;
 pub mod db;
 pub mod telemetry;
 pub mod kafka;
@@ -26,7 +27,7 @@ use common_utils::error::ServiceError;
 use lazy_static::lazy_static;
 
 
-use common_utils::{unique_id, inc_genre};
+use common_utils::unique_id;
 
 lazy_static! { 
     static ref MACHINE_ID: i32 = std::env::var("MACHINE_ID")
@@ -7,6 +7,20 @@ use crate::db::{establish_connection, initialise_pool, session, DBCONN};
 use crate::kafka::create_producer;
 use crate::telemetry::init_telemetry;
 use tracing_actix_web::TracingLogger;
+use common_utils::health::configure_health;
+use crate::health::readiness;
+use crate::graphql::modules::types::{
+    availability::{resolver::AvailabilityDatabase, scheduler::spawn_scheduler},
+    release::{resolver::ReleaseDatabase, scheduler as release_scheduler},
+    ingestion_jobs::{resolver::JobDatabase, source::TmdbSource, worker::{spawn_job_sweeper, JOB_SWEEP}},
+    credits::resolver::CreditDatabase,
+    genres::{model::Genre, resolver::GenreDatabase},
+    movies::resolver::MovieDatabase,
+    people_module::resolver::PersonDatabase,
+    artwork::{storage::LocalImageStore, upload::configure_uploads},
+    subtitles::upload::configure_subtitles,
+    outbox::{relay::spawn_relay, resolver::OutboxDatabase},
+};
 use std::fs::File;
 use std::io::Write;
 /// Instantiate the server 
@@ -19,11 +33,38 @@ pub async fn new_server(port: u32) -> std::io::Result<()> {
         .await
         .expect("Unable to establish ScyllaDB connection");
     let schema = web::Data::new(create_schema(db_pool));
+    let image_store = web::Data::new(LocalImageStore::shared());
 
     // Initialise Kafka Producer
     let kafka_producer = create_producer();
     log::info!("Welcome to Apache Kafka 🦿");
 
+    // Movie and series writes are checked against the taxonomy, a new cluster starts with the TMDB genres
+    match Genre::seed::<GenreDatabase>(db_pool).await { 
+        Ok(created) => log::info!("🏷️ Seeded {} genres", created),
+        Err(e) => log::error!("Unable to seed the genre taxonomy: {}", e),
+    }
+
+    // Import jobs interrupted by the last shutdown, or left by an instance that died, carry on from their checkpoint
+    if resume_import_jobs() { 
+        spawn_job_sweeper::<JobDatabase, MovieDatabase, CreditDatabase, PersonDatabase, GenreDatabase>(db_pool, db_pool, db_pool, db_pool, db_pool, TmdbSource::shared(), *JOB_SWEEP);
+    }
+
+    // Windows opening and closing are published as they pass, so the search index follows them
+    if schedule_availability() { 
+        spawn_scheduler::<AvailabilityDatabase>(db_pool);
+    }
+
+    // Movies are released and withdrawn at their `publishAt` and `unpublishAt`
+    if schedule_releases() { 
+        release_scheduler::spawn_scheduler::<MovieDatabase, ReleaseDatabase>(db_pool, db_pool);
+    }
+
+    // Movie events are written to the outbox with the movie, and reach Kafka from here
+    if relay_outbox() { 
+        spawn_relay::<OutboxDatabase>(db_pool);
+    }
+
     //  Automate writing new subgraphs
     let app_name = format!("{}.graphql", env!("CARGO_PKG_NAME"));
     let mut subgraph = File::create(app_name.clone())
@@ -48,7 +89,11 @@ pub async fn new_server(port: u32) -> std::io::Result<()> {
         App::new()
             .app_data(kafka_producer.clone())
             .app_data(schema.clone())
+            .app_data(image_store.clone())
             .configure(configure_service)
+            .configure(configure_health(readiness()))
+            .configure(configure_uploads)
+            .configure(configure_subtitles)
             .wrap(Cors::permissive())
             .wrap(Logger::default())
             // .wrap(TracingLogger::default())
@@ -79,4 +124,33 @@ pub fn enable_tracing() -> bool {
     } else { 
         return false;
     }
-}
\ No newline at end of file
+}
+
+/// Instances with this set sweep for unfinished import jobs every `IMPORT_JOB_SWEEP_SECS`, each job
+/// is run by the one that claims its lease
+pub fn resume_import_jobs() -> bool { 
+    std::env::var("RESUME_IMPORT_JOBS")
+        .map(|value| value != "false")
+        .unwrap_or(true)
+}
+
+/// Every instance can run the availability scheduler, their events are idempotent, but one is enough
+pub fn schedule_availability() -> bool { 
+    std::env::var("SCHEDULE_AVAILABILITY")
+        .map(|value| value != "false")
+        .unwrap_or(true)
+}
+
+/// Two release schedulers would both record each change in the history, so one instance sets this
+pub fn schedule_releases() -> bool { 
+    std::env::var("SCHEDULE_RELEASES")
+        .map(|value| value != "false")
+        .unwrap_or(true)
+}
+
+/// Every instance can relay the outbox, each bucket is published by the relay holding its lease
+pub fn relay_outbox() -> bool { 
+    std::env::var("RELAY_OUTBOX")
+        .map(|value| value != "false")
+        .unwrap_or(true)
+}
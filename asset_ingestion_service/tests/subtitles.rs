@@ -0,0 +1,82 @@
+use asset_ingestion_service::graphql::modules::types::subtitles::parser::{parse, shift, to_srt, to_webvtt, Cue, SubtitleFormat};
+use proptest::{collection::vec, option, prelude::*};
+
+/// A line of cue text as the parser keeps it: not blank, no trailing whitespace
+fn line() -> impl Strategy<Value = String> {
+    "[\\p{L}\\p{N}][\\p{L}\\p{N} ,.!?']{0,30}".prop_map(|line| line.trim_end().to_string())
+}
+
+fn identifier() -> impl Strategy<Value = String> {
+    "[a-z0-9][a-z0-9_-]{0,10}"
+}
+
+fn settings() -> impl Strategy<Value = String> {
+    vec("(line|position|size|align):[a-z0-9%]{1,5}", 1..4).prop_map(|settings| settings.join(" "))
+}
+
+/// Cues in order that don't overlap, each starting `gap` after the previous one ends
+fn cues() -> impl Strategy<Value = Vec<Cue>> {
+    let cue = (0u64..10_000_000, 1u64..10_000_000, option::of(identifier()), option::of(settings()), vec(line(), 1..4));
+    vec(cue, 0..8).prop_map(|cues| {
+        let mut end = 0;
+        cues.into_iter()
+            .map(|(gap, duration, id, settings, lines)| {
+                let start = end + gap;
+                end = start + duration;
+                Cue { id, start, end, settings, text: lines.join("\n") }
+            })
+            .collect()
+    })
+}
+
+/// What SubRip keeps of `cues`, it has no identifiers or settings
+fn without_webvtt_parts(cues: Vec<Cue>) -> Vec<Cue> {
+    cues.into_iter().map(|cue| Cue { id: None, settings: None, ..cue }).collect()
+}
+
+proptest! {
+    #[test]
+    fn webvtt_written_is_read_back_as_the_same_cues(cues in cues()) {
+        let text = to_webvtt(&cues);
+
+        prop_assert_eq!(SubtitleFormat::detect(&text), SubtitleFormat::Webvtt);
+        prop_assert_eq!(parse(&text, SubtitleFormat::Webvtt).unwrap(), cues);
+    }
+
+    #[test]
+    fn srt_written_is_read_back_as_the_same_cues(cues in cues().prop_map(without_webvtt_parts)) {
+        let text = to_srt(&cues);
+
+        prop_assert_eq!(SubtitleFormat::detect(&text), SubtitleFormat::Srt);
+        prop_assert_eq!(parse(&text, SubtitleFormat::Srt).unwrap(), cues);
+    }
+
+    #[test]
+    fn srt_drops_only_identifiers_and_settings(cues in cues()) {
+        prop_assert_eq!(parse(&to_srt(&cues), SubtitleFormat::Srt).unwrap(), without_webvtt_parts(cues));
+    }
+
+    #[test]
+    fn shifting_back_gives_the_original_cues(cues in cues(), offset in 0i64..1_000_000_000) {
+        let shifted = shift(cues.clone(), offset).unwrap();
+
+        prop_assert_eq!(parse(&to_webvtt(&shifted), SubtitleFormat::Webvtt).unwrap(), shifted.clone());
+        prop_assert_eq!(shift(shifted, -offset).unwrap(), cues);
+    }
+
+    #[test]
+    fn shifting_keeps_every_duration(cues in cues(), offset in -1_000_000i64..1_000_000) {
+        if let Ok(shifted) = shift(cues.clone(), offset) {
+            let durations = |cues: &[Cue]| cues.iter().map(|cue| cue.end - cue.start).collect::<Vec<u64>>();
+            prop_assert_eq!(durations(&shifted), durations(&cues));
+        }
+    }
+
+    #[test]
+    fn cues_cannot_be_shifted_before_the_start(cues in cues().prop_filter("needs a cue", |cues| !cues.is_empty())) {
+        let first = cues[0].start as i64;
+
+        prop_assert_eq!(shift(cues.clone(), -first).unwrap()[0].start, 0);
+        prop_assert!(shift(cues, -first - 1).is_err());
+    }
+}
@@ -6,15 +6,20 @@ use actix_web::{get, middleware::Logger, route, web, App, HttpServer, Responder,
 use actix_web_lab::respond::Html;
 use async_graphql::{
     http::{playground_source, GraphQLPlaygroundConfig},
-    EmptyMutation, EmptySubscription, Schema, Context, extensions::ApolloTracing,
+    EmptyMutation, EmptySubscription, Schema, Context, extensions::ApolloTracing, dataloader::DataLoader,
 };
 use async_graphql_actix_web::{GraphQLRequest, GraphQLResponse, GraphQLSubscription};
-use common_utils::KAFKA_CONSUMER_COUNTER;
+use common_utils::{KAFKA_CONSUMER_COUNTER, availability::RequestRegion, playback::RequestPlan, signing::{PlaybackClient, UrlSigner}};
 use scylla::Session;
 use crate::{db::{CachedSession, session}, kafka};
+use super::modules::credits::resolver::{CreditDatabase, CreditLoader, PersonLoader};
+use super::modules::translations::{model::RequestLocale, resolver::{TranslationDatabase, TranslationLoader}};
+use super::modules::availability::resolver::{AvailabilityDatabase, AvailabilityLoader};
+use super::modules::artwork::resolver::{ArtworkDatabase, ArtworkLoader};
 
 
 use super::root_schema::{Mutation, Query, AppSchema, AppSchemaBuilder};
+use common_utils::metrics::GraphQLMetrics;
 
 pub fn configure_service(cfg: &mut web::ServiceConfig) { 
     cfg
@@ -29,10 +34,19 @@ pub fn configure_service(cfg: &mut web::ServiceConfig) {
     );
 }
 
-/// GraphQL endpoint
+/// GraphQL endpoint, movies are localized for the locale preferred by the request, see `RequestLocale::new`,
+/// and only the ones licensed in its region are served, see `RequestRegion::new`. Playback is capped
+/// by the caller's plan, see `RequestPlan::new`, and its URLs are signed for the caller, see `PlaybackClient::new`
 #[route("/graphql", method = "GET", method = "POST")]
-pub async fn graphql(schema: web::Data<AppSchema>, req: GraphQLRequest) -> GraphQLResponse {
-    schema.execute(req.into_inner()).await.into()
+pub async fn graphql(schema: web::Data<AppSchema>, http: HttpRequest, req: GraphQLRequest) -> GraphQLResponse {
+    let locale = RequestLocale::new(
+        http.headers().get("x-preferred-locale").and_then(|value| value.to_str().ok()),
+        http.headers().get("accept-language").and_then(|value| value.to_str().ok()),
+    );
+    let region = RequestRegion::new(&http);
+    let plan = RequestPlan::new(&http);
+    let client = PlaybackClient::new(&http);
+    schema.execute(req.into_inner().data(locale).data(region).data(plan).data(client)).await.into()
 }
 /// GraphiQL playground UI
 #[get("/graphiql")]
@@ -58,13 +72,21 @@ pub fn create_schema(pool: &'static CachedSession) -> AppSchema {
         EmptySubscription
     )
     // Add a global data that can be accessed in the Schema
+    .data(DataLoader::new(CreditLoader::new::<CreditDatabase>(pool), tokio::spawn))
+    .data(DataLoader::new(PersonLoader::new::<CreditDatabase>(pool), tokio::spawn))
+    .data(DataLoader::new(TranslationLoader::new::<TranslationDatabase>(pool), tokio::spawn))
+    .data(DataLoader::new(AvailabilityLoader::new::<AvailabilityDatabase>(pool), tokio::spawn))
+    .data(DataLoader::new(ArtworkLoader::new::<ArtworkDatabase>(pool), tokio::spawn))
+    .data(UrlSigner::from_env())
     .data(pool)
     .extension(ApolloTracing)
+    .extension(GraphQLMetrics)
     .finish()
 }
-/// Global access to the applications's contextual data, accessible at runtime 
-pub fn get_conn_from_ctx(ctx: &Context<'_>) -> &'static CachedSession { 
-    let pool = ctx.data::<&'static CachedSession>()
+/// Global access to the applications's contextual data, accessible at runtime.
+/// Resolves to the `CachedSession` unless the schema was built around another store
+pub fn get_store_from_ctx<S: Send + Sync + 'static>(ctx: &Context<'_>) -> &'static S { 
+    let pool = ctx.data::<&'static S>()
         .expect("Failed to Connect to Database");
-    pool.clone()
+    *pool
 }
\ No newline at end of file
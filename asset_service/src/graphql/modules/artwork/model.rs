@@ -0,0 +1,40 @@
+use std::collections::HashMap;
+use common_utils::{QueryResult, artwork::{ImageKind, ImageOwner}};
+use scylla::macros::FromRow;
+use serde::{Deserialize, Serialize};
+use super::resolver::ArtworkResolver;
+
+// Columns after the primary key of `artwork` are in alphabetical order, the order `SELECT *` returns them in
+/// An image of a movie or a person, written by the ingestion service
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize)]
+pub struct Artwork { 
+    pub owner_type: String,
+    pub owner_id: i64,
+    pub image_id: i64,
+    pub aspect_ratio: f32,
+    pub blurhash: Option<String>,
+    pub file_path: String,
+    pub height: i32,
+    pub is_primary: bool,
+    pub kind: String,
+    pub language: Option<String>,
+    /// Renditions of an uploaded image by `variant_key`
+    pub variants: Option<HashMap<String, String>>,
+    pub width: i32,
+}
+
+impl Artwork { 
+    pub async fn get_images_by_owners<ArtworkDatabase: ArtworkResolver>(owner: ImageOwner, owner_ids: Vec<i64>, session: &'static ArtworkDatabase::Store) -> QueryResult<Vec<Artwork>> {
+        ArtworkDatabase::get_images_by_owners(owner, owner_ids, session).await
+    }
+    pub fn owner(&self) -> ImageOwner { 
+        self.owner_type.parse().unwrap_or(ImageOwner::Movie)
+    }
+    /// Rows of a kind this service doesn't know yet are served as posters, or profile pictures for a person
+    pub fn kind(&self) -> ImageKind { 
+        self.kind.parse().unwrap_or(match self.owner() {
+            ImageOwner::Movie => ImageKind::Poster,
+            ImageOwner::Person => ImageKind::Profile,
+        })
+    }
+}
@@ -0,0 +1,72 @@
+use std::collections::{HashMap, HashSet};
+use async_graphql::dataloader::Loader;
+use async_trait::async_trait;
+use common_utils::{QueryResult, artwork::ImageOwner, error::ServiceError};
+use futures::future::BoxFuture;
+use scylla::IntoTypedRows;
+use crate::db::CachedSession;
+use super::model::Artwork;
+
+/// Read side of the images managed by the ingestion service
+#[async_trait]
+pub trait ArtworkResolver: Send + Sync + 'static { 
+    type Store: Send + Sync + 'static;
+    async fn get_images_by_owners(owner: ImageOwner, owner_ids: Vec<i64>, session: &'static Self::Store) -> QueryResult<Vec<Artwork>>;
+}
+
+#[derive(Default)]
+pub struct ArtworkDatabase;
+
+static GET_IMAGES_BY_OWNERS: &str = "SELECT * FROM movie_keyspace.artwork WHERE owner_type = ? AND owner_id IN ?;";
+
+#[async_trait]
+impl ArtworkResolver for ArtworkDatabase { 
+    type Store = CachedSession;
+
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.artwork"))]
+    async fn get_images_by_owners(owner: ImageOwner, owner_ids: Vec<i64>, session: &'static CachedSession) -> QueryResult<Vec<Artwork>> { 
+        session
+            .query_prepared(GET_IMAGES_BY_OWNERS, (owner.to_string(), owner_ids))
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
+            .into_typed::<Artwork>()
+            .map(|row| row.map_err(|e| ServiceError::ServerError(e.to_string())))
+            .collect()
+    }
+}
+
+type LoadFn = Box<dyn Fn(ImageOwner, Vec<i64>) -> BoxFuture<'static, QueryResult<Vec<Artwork>>> + Send + Sync>;
+
+/// Loads the images of every movie and person in a response with one query per kind of owner.
+/// An owner without images maps to an empty list
+pub struct ArtworkLoader { 
+    load: LoadFn,
+}
+
+impl ArtworkLoader { 
+    pub fn new<R: ArtworkResolver>(session: &'static R::Store) -> Self { 
+        Self { load: Box::new(move |owner, owner_ids| R::get_images_by_owners(owner, owner_ids, session)) }
+    }
+}
+
+#[async_trait]
+impl Loader<(ImageOwner, i64)> for ArtworkLoader { 
+    type Value = Vec<Artwork>;
+    type Error = ServiceError;
+
+    async fn load(&self, keys: &[(ImageOwner, i64)]) -> Result<HashMap<(ImageOwner, i64), Self::Value>, Self::Error> { 
+        let mut images = keys
+            .iter()
+            .map(|key| (*key, Vec::new()))
+            .collect::<HashMap<_, _>>();
+        let owners = keys.iter().map(|(owner, _)| *owner).collect::<HashSet<_>>();
+        for owner in owners { 
+            let owner_ids = keys.iter().filter(|(f, _)| *f == owner).map(|(_, owner_id)| *owner_id).collect();
+            for image in (self.load)(owner, owner_ids).await? { 
+                images.entry((owner, image.owner_id)).or_default().push(image);
+            }
+        }
+        Ok(images)
+    }
+}
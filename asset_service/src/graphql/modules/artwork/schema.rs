@@ -0,0 +1,58 @@
+use async_graphql::{*, dataloader::DataLoader};
+use common_utils::artwork::{ImageFormat, ImageKind, ImageOwner, ImageSize, sized_url, variant_url};
+use serde::{Deserialize, Serialize};
+use crate::to_bigint;
+use super::{model::Artwork, resolver::ArtworkLoader};
+
+#[derive(SimpleObject, Debug, Clone, Deserialize, Serialize)]
+pub struct ImageType { 
+    pub image_id: ID,
+    pub kind: ImageKind,
+    /// URL of the image at the size that was asked for
+    pub url: String,
+    /// Placeholder to show while the image loads, only uploaded images have one
+    pub blurhash: Option<String>,
+    /// ISO 639-1 code of the text on the image, `null` when there is none
+    pub language: Option<String>,
+    /// Of the original, sized URLs keep its aspect ratio
+    pub width: i32,
+    pub height: i32,
+    pub aspect_ratio: f32,
+    pub is_primary: bool,
+}
+
+impl ImageType { 
+    fn new(f: &Artwork, size: ImageSize, format: Option<ImageFormat>) -> Self { 
+        Self { 
+            image_id: f.image_id.into(),
+            kind: f.kind(),
+            url: f.variants
+                .as_ref()
+                .and_then(|variants| variant_url(variants, f.kind(), size, format))
+                .unwrap_or_else(|| sized_url(&f.file_path, f.kind(), size)),
+            blurhash: f.blurhash.clone(),
+            language: f.language.clone(),
+            width: f.width,
+            height: f.height,
+            aspect_ratio: f.aspect_ratio,
+            is_primary: f.is_primary,
+        }
+    }
+}
+
+/// Images of the owner, only those of `kind` when set. The primary image of each kind comes
+/// before the alternatives, which keep the order they were added in. Uploaded images are served
+/// in `format` when they were encoded in it, imported ones only come in the format TMDB has
+pub(crate) async fn owner_images(ctx: &Context<'_>, owner: ImageOwner, owner_id: &ID, kind: Option<ImageKind>, size: ImageSize, format: Option<ImageFormat>) -> FieldResult<Vec<ImageType>> { 
+    let images = ctx.data::<DataLoader<ArtworkLoader>>()?
+        .load_one((owner, to_bigint(owner_id.clone())))
+        .await
+        .map_err(|e| e.extend())?
+        .unwrap_or_default();
+    let mut images = images
+        .iter()
+        .filter(|image| kind.map_or(true, |kind| image.kind() == kind))
+        .collect::<Vec<_>>();
+    images.sort_by_key(|image| !image.is_primary);
+    Ok(images.into_iter().map(|image| ImageType::new(image, size, format)).collect())
+}
@@ -0,0 +1,29 @@
+use common_utils::{QueryResult, availability::Offering};
+use scylla::macros::FromRow;
+use serde::{Deserialize, Serialize};
+use super::resolver::AvailabilityResolver;
+
+// Columns after the primary key of `movie_availability` are in alphabetical order, the order `SELECT *` returns them in
+/// A territory and period a movie can be watched in, written by the ingestion service
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize)]
+pub struct AvailabilityWindow { 
+    pub movie_id: i64,
+    pub window_id: i64,
+    pub ends_at: Option<i64>,
+    pub offering: String,
+    pub starts_at: Option<i64>,
+    pub territory: String,
+}
+
+impl AvailabilityWindow { 
+    pub async fn get_windows_by_movies<AvailabilityDatabase: AvailabilityResolver>(movie_ids: Vec<i64>, session: &'static AvailabilityDatabase::Store) -> QueryResult<Vec<AvailabilityWindow>> {
+        AvailabilityDatabase::get_windows_by_movies(movie_ids, session).await
+    }
+    pub fn offering(&self) -> Offering { 
+        self.offering.parse().unwrap_or(Offering::Subscription)
+    }
+    /// `(territory, starts_at, ends_at)`, what `common_utils::availability` decides on
+    pub fn span(&self) -> (&str, Option<i64>, Option<i64>) { 
+        (self.territory.as_str(), self.starts_at, self.ends_at)
+    }
+}
@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+use async_graphql::dataloader::Loader;
+use async_trait::async_trait;
+use common_utils::{QueryResult, error::ServiceError};
+use futures::future::BoxFuture;
+use scylla::IntoTypedRows;
+use crate::db::CachedSession;
+use super::model::AvailabilityWindow;
+
+/// Read side of licensing windows, written by the ingestion service
+#[async_trait]
+pub trait AvailabilityResolver: Send + Sync + 'static { 
+    type Store: Send + Sync + 'static;
+    async fn get_windows_by_movies(movie_ids: Vec<i64>, session: &'static Self::Store) -> QueryResult<Vec<AvailabilityWindow>>;
+}
+
+#[derive(Default)]
+pub struct AvailabilityDatabase;
+
+static GET_WINDOWS_BY_MOVIES: &str = "SELECT * FROM movie_keyspace.movie_availability WHERE movie_id IN ?;";
+
+#[async_trait]
+impl AvailabilityResolver for AvailabilityDatabase { 
+    type Store = CachedSession;
+
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.movie_availability"))]
+    async fn get_windows_by_movies(movie_ids: Vec<i64>, session: &'static CachedSession) -> QueryResult<Vec<AvailabilityWindow>> { 
+        session
+            .query_prepared(GET_WINDOWS_BY_MOVIES, (movie_ids,))
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
+            .into_typed::<AvailabilityWindow>()
+            .map(|row| row.map_err(|e| ServiceError::ServerError(e.to_string())))
+            .collect()
+    }
+}
+
+type LoadFn = Box<dyn Fn(Vec<i64>) -> BoxFuture<'static, QueryResult<Vec<AvailabilityWindow>>> + Send + Sync>;
+
+/// Loads the windows of every movie in a response with one query. A movie without windows maps to an empty list
+pub struct AvailabilityLoader { 
+    load: LoadFn,
+}
+
+impl AvailabilityLoader { 
+    pub fn new<R: AvailabilityResolver>(session: &'static R::Store) -> Self { 
+        Self { load: Box::new(move |movie_ids| R::get_windows_by_movies(movie_ids, session)) }
+    }
+}
+
+#[async_trait]
+impl Loader<i64> for AvailabilityLoader { 
+    type Value = Vec<AvailabilityWindow>;
+    type Error = ServiceError;
+
+    async fn load(&self, keys: &[i64]) -> Result<HashMap<i64, Self::Value>, Self::Error> { 
+        let mut windows = keys
+            .iter()
+            .map(|movie_id| (*movie_id, Vec::new()))
+            .collect::<HashMap<_, _>>();
+        for window in (self.load)(keys.to_vec()).await? { 
+            windows.entry(window.movie_id).or_default().push(window);
+        }
+        Ok(windows)
+    }
+}
@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+use async_graphql::{*, dataloader::DataLoader};
+use chrono::{DateTime, TimeZone, Utc};
+use common_utils::{availability::{Offering, RequestRegion, covers}, error::ServiceError};
+use serde::{Deserialize, Serialize};
+use crate::to_bigint;
+use super::super::model::Movie;
+use super::{model::AvailabilityWindow, resolver::AvailabilityLoader};
+
+#[derive(SimpleObject, Debug, Clone, Deserialize, Serialize)]
+pub struct AvailabilityWindowType { 
+    pub window_id: ID,
+    /// ISO 3166-1 alpha-2 code, or `WW` for every territory
+    pub territory: String,
+    pub offering: Offering,
+    /// `null` when the window has always been open
+    pub starts_at: Option<DateTime<Utc>>,
+    /// `null` when the window never closes
+    pub ends_at: Option<DateTime<Utc>>,
+}
+
+impl From<&AvailabilityWindow> for AvailabilityWindowType { 
+    fn from(f: &AvailabilityWindow) -> Self {
+        Self { 
+            window_id: f.window_id.into(),
+            territory: f.territory.clone(),
+            offering: f.offering(),
+            starts_at: f.starts_at.map(|at| Utc.timestamp_millis(at)),
+            ends_at: f.ends_at.map(|at| Utc.timestamp_millis(at)),
+        }
+    }
+}
+
+/// Schemas built without a `RequestRegion`, like the ones in tests, serve a caller whose region is unknown
+fn request_region(ctx: &Context<'_>) -> RequestRegion { 
+    ctx.data_opt::<RequestRegion>().cloned().unwrap_or_default()
+}
+
+async fn movie_windows(ctx: &Context<'_>, movie_ids: Vec<i64>) -> FieldResult<HashMap<i64, Vec<AvailabilityWindow>>> { 
+    ctx.data::<DataLoader<AvailabilityLoader>>()?
+        .load_many(movie_ids)
+        .await
+        .map_err(|e| e.extend())
+}
+
+/// `movies` less the ones that can't be watched in the request's region right now, order is kept
+pub(crate) async fn retain_available(ctx: &Context<'_>, movies: Vec<Movie>) -> FieldResult<Vec<Movie>> { 
+    let region = request_region(ctx);
+    let windows = movie_windows(ctx, movies.iter().map(|movie| movie.movie_id).collect()).await?;
+    Ok(movies
+        .into_iter()
+        .filter(|movie| region.allows(windows.get(&movie.movie_id).into_iter().flatten().map(AvailabilityWindow::span)))
+        .collect())
+}
+
+/// A movie that can't be watched in the request's region is reported as not found, the same as
+/// one that isn't in the catalogue
+pub(crate) async fn ensure_available(ctx: &Context<'_>, movie: Movie) -> FieldResult<Movie> { 
+    retain_available(ctx, vec![movie])
+        .await?
+        .pop()
+        .ok_or_else(|| ServiceError::NotFound.extend())
+}
+
+/// A series that can't be watched in the request's region is reported as not found, its episodes
+/// with it. A series is licensed by the windows stored under its series id, one without any is
+/// available everywhere like a movie
+pub(crate) async fn ensure_series_available(ctx: &Context<'_>, series_id: i64) -> FieldResult<()> { 
+    let windows = movie_windows(ctx, vec![series_id]).await?;
+    match request_region(ctx).allows(windows.get(&series_id).into_iter().flatten().map(AvailabilityWindow::span)) {
+        true => Ok(()),
+        false => Err(ServiceError::NotFound.extend()),
+    }
+}
+
+/// The movie's windows in the request's region, past and upcoming ones included
+pub(crate) async fn movie_availability(ctx: &Context<'_>, movie_id: &ID) -> FieldResult<Vec<AvailabilityWindowType>> { 
+    let region = request_region(ctx);
+    let movie_id = to_bigint(movie_id.clone());
+    let windows = movie_windows(ctx, vec![movie_id]).await?;
+    Ok(windows
+        .get(&movie_id)
+        .into_iter()
+        .flatten()
+        .filter(|window| covers(&window.territory, region.region.as_deref()))
+        .map(AvailabilityWindowType::from)
+        .collect())
+}
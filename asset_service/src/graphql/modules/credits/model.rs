@@ -0,0 +1,59 @@
+use chrono::NaiveDate;
+use common_utils::QueryResult;
+use scylla::macros::FromRow;
+use serde::{Deserialize, Serialize};
+use super::resolver::CreditResolver;
+
+/// Department of every cast credit
+pub const ACTING: &str = "Acting";
+
+// Columns after the primary key of `credits_by_movie` are in alphabetical order, the order `SELECT *` returns them in
+/// One person credited on one movie, written by the ingestion service
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize)]
+pub struct Credit { 
+    pub movie_id: i64,
+    pub credit_id: String,
+    pub billing_order: Option<i32>,
+    pub character_name: Option<String>,
+    pub department: String,
+    pub job: String,
+    pub name: String,
+    pub person_id: i32,
+}
+
+/// Row of `person_object`
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize)]
+pub struct Person { 
+    pub person_id: i32,
+    pub name: String,
+    pub awards: Vec<String>,
+    pub biography: String,
+    pub birthday: NaiveDate,
+    pub death_date: NaiveDate,
+    pub gender: String,
+    pub homepage: String,
+    pub known_for: Vec<String>,
+    pub place_of_birth: String,
+    pub profile_path: Vec<String>,
+}
+
+impl Credit { 
+    pub fn is_cast(&self) -> bool { 
+        self.department == ACTING
+    }
+}
+
+impl Credit { 
+    pub async fn get_credits_by_movies<CreditDatabase: CreditResolver>(movie_ids: Vec<i64>, session: &'static CreditDatabase::Store) -> QueryResult<Vec<Credit>> {
+        CreditDatabase::get_credits_by_movies(movie_ids, session).await
+    }
+    pub async fn get_person_credits<CreditDatabase: CreditResolver>(person_id: i32, session: &'static CreditDatabase::Store) -> QueryResult<Vec<Credit>> {
+        CreditDatabase::get_person_credits(person_id, session).await
+    }
+}
+
+impl Person { 
+    pub async fn get_people<CreditDatabase: CreditResolver>(person_ids: Vec<i32>, session: &'static CreditDatabase::Store) -> QueryResult<Vec<Person>> {
+        CreditDatabase::get_people(person_ids, session).await
+    }
+}
@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+use async_graphql::dataloader::Loader;
+use async_trait::async_trait;
+use common_utils::{QueryResult, error::ServiceError};
+use futures::future::BoxFuture;
+use scylla::IntoTypedRows;
+use crate::db::CachedSession;
+use super::model::{Credit, Person};
+
+/// Read side of movie credits, written by the ingestion service
+#[async_trait]
+pub trait CreditResolver: Send + Sync + 'static { 
+    type Store: Send + Sync + 'static;
+    /// Credits of every movie in `movie_ids`, in no particular order
+    async fn get_credits_by_movies(movie_ids: Vec<i64>, session: &'static Self::Store) -> QueryResult<Vec<Credit>>;
+    async fn get_person_credits(person_id: i32, session: &'static Self::Store) -> QueryResult<Vec<Credit>>;
+    async fn get_people(person_ids: Vec<i32>, session: &'static Self::Store) -> QueryResult<Vec<Person>>;
+}
+
+#[derive(Default)]
+pub struct CreditDatabase;
+
+static GET_CREDITS_BY_MOVIES: &str = "SELECT * FROM movie_keyspace.credits_by_movie WHERE movie_id IN ?;";
+// Same column order as `credits_by_movie`, so both tables read into `Credit`
+static GET_PERSON_CREDITS: &str = "SELECT movie_id, credit_id, billing_order, character_name, department, job, name, person_id FROM movie_keyspace.credits_by_person WHERE person_id = ?;";
+static GET_PEOPLE: &str = "SELECT * FROM movie_keyspace.person_object WHERE person_id IN ?;";
+
+#[async_trait]
+impl CreditResolver for CreditDatabase { 
+    type Store = CachedSession;
+
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.credits_by_movie"))]
+    async fn get_credits_by_movies(movie_ids: Vec<i64>, session: &'static CachedSession) -> QueryResult<Vec<Credit>> { 
+        session
+            .query_prepared(GET_CREDITS_BY_MOVIES, (movie_ids,))
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
+            .into_typed::<Credit>()
+            .map(|row| row.map_err(|e| ServiceError::ServerError(e.to_string())))
+            .collect()
+    }
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.credits_by_person"))]
+    async fn get_person_credits(person_id: i32, session: &'static CachedSession) -> QueryResult<Vec<Credit>> { 
+        session
+            .query_prepared(GET_PERSON_CREDITS, (person_id,))
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
+            .into_typed::<Credit>()
+            .map(|row| row.map_err(|e| ServiceError::ServerError(e.to_string())))
+            .collect()
+    }
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.person_object"))]
+    async fn get_people(person_ids: Vec<i32>, session: &'static CachedSession) -> QueryResult<Vec<Person>> { 
+        session
+            .query_prepared(GET_PEOPLE, (person_ids,))
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
+            .into_typed::<Person>()
+            .map(|row| row.map_err(|e| ServiceError::ServerError(e.to_string())))
+            .collect()
+    }
+}
+
+type LoadFn<K, V> = Box<dyn Fn(Vec<K>) -> BoxFuture<'static, QueryResult<Vec<V>>> + Send + Sync>;
+
+// GraphQL DataLoaders
+// The loaders hold on to the resolver's queries rather than to a session, so `MovieType`
+// can reach them without knowing which store the schema was built around
+/// Loads the credits of every movie in a response with one query
+pub struct CreditLoader { 
+    load: LoadFn<i64, Credit>,
+}
+
+impl CreditLoader { 
+    pub fn new<R: CreditResolver>(session: &'static R::Store) -> Self { 
+        Self { load: Box::new(move |movie_ids| R::get_credits_by_movies(movie_ids, session)) }
+    }
+}
+
+#[async_trait]
+impl Loader<i64> for CreditLoader { 
+    type Value = Vec<Credit>;
+    type Error = ServiceError;
+
+    async fn load(&self, keys: &[i64]) -> Result<HashMap<i64, Self::Value>, Self::Error> { 
+        // A movie without credits still resolves, to an empty list
+        let mut credits = keys
+            .iter()
+            .map(|movie_id| (*movie_id, Vec::new()))
+            .collect::<HashMap<_, _>>();
+        for credit in (self.load)(keys.to_vec()).await? { 
+            credits.entry(credit.movie_id).or_default().push(credit);
+        }
+        Ok(credits)
+    }
+}
+
+/// Loads the people behind a page of credits with one query
+pub struct PersonLoader { 
+    load: LoadFn<i32, Person>,
+}
+
+impl PersonLoader { 
+    pub fn new<R: CreditResolver>(session: &'static R::Store) -> Self { 
+        Self { load: Box::new(move |person_ids| R::get_people(person_ids, session)) }
+    }
+}
+
+#[async_trait]
+impl Loader<i32> for PersonLoader { 
+    type Value = Person;
+    type Error = ServiceError;
+
+    async fn load(&self, keys: &[i32]) -> Result<HashMap<i32, Self::Value>, Self::Error> { 
+        let people = (self.load)(keys.to_vec())
+            .await?
+            .into_iter()
+            .map(|person| (person.person_id, person))
+            .collect();
+        Ok(people)
+    }
+}
@@ -0,0 +1,167 @@
+use std::{cmp::Reverse, collections::HashMap, marker::PhantomData};
+use async_graphql::{*, dataloader::DataLoader};
+use chrono::NaiveDate;
+use futures::future::join_all;
+use serde::{Deserialize, Serialize};
+use crate::{graphql::config::get_store_from_ctx, to_bigint, to_int};
+use super::super::{model::Movie, resolver::{MovieDatabase, MovieResolver}, schema::MovieType};
+use super::super::availability::schema::retain_available;
+use super::super::artwork::schema::{owner_images, ImageType};
+use common_utils::artwork::{ImageFormat, ImageKind, ImageOwner, ImageSize};
+use super::{model::{Credit, Person}, resolver::{CreditDatabase, CreditLoader, CreditResolver, PersonLoader}};
+
+/// Filmographies are read through `R`, the movies they list through `M`
+#[derive(Default)]
+pub struct CreditQuery<R = CreditDatabase, M = MovieDatabase>(PhantomData<(R, M)>);
+
+#[derive(Debug, Clone, SimpleObject, Deserialize, Serialize)]
+#[graphql(complex)]
+pub struct CreditType { 
+    pub credit_id: ID,
+    pub movie_id: ID,
+    pub person_id: ID,
+    /// Name as credited on this movie
+    pub name: String,
+    pub department: String,
+    pub job: String,
+    /// Character played, `null` for the crew
+    pub character: Option<String>,
+    pub billing_order: Option<i32>,
+}
+
+#[derive(Debug, Clone, SimpleObject, Deserialize, Serialize)]
+#[graphql(complex)]
+pub struct PersonType { 
+    pub person_id: ID,
+    pub name: String,
+    pub awards: Vec<String>,
+    pub biography: String,
+    pub birthday: NaiveDate,
+    pub death_date: NaiveDate,
+    pub gender: String,
+    pub homepage: String,
+    pub known_for: Vec<String>,
+    pub place_of_birth: String,
+    pub profile_path: Vec<String>,
+}
+
+#[derive(Debug, Clone, SimpleObject, Deserialize, Serialize)]
+pub struct FilmographyCreditType { 
+    pub credit: CreditType,
+    /// `null` when the movie has been removed from the catalogue since, or can't be watched in the request's region
+    pub movie: Option<MovieType>,
+}
+
+impl From<&Credit> for CreditType { 
+    fn from(f: &Credit) -> Self {
+        Self { 
+            credit_id: f.credit_id.clone().into(),
+            movie_id: f.movie_id.into(),
+            person_id: f.person_id.into(),
+            name: f.name.clone(),
+            department: f.department.clone(),
+            job: f.job.clone(),
+            character: f.character_name.clone(),
+            billing_order: f.billing_order,
+        }
+    }
+}
+
+impl From<&Person> for PersonType { 
+    fn from(f: &Person) -> Self {
+        Self { 
+            person_id: f.person_id.into(),
+            name: f.name.clone(),
+            awards: f.awards.clone(),
+            biography: f.biography.clone(),
+            birthday: f.birthday,
+            death_date: f.death_date,
+            gender: f.gender.clone(),
+            homepage: f.homepage.clone(),
+            known_for: f.known_for.clone(),
+            place_of_birth: f.place_of_birth.clone(),
+            profile_path: f.profile_path.clone(),
+        }
+    }
+}
+
+#[ComplexObject]
+impl CreditType { 
+    /// `null` until the person has been added to the catalogue
+    async fn person(&self, ctx: &Context<'_>) -> FieldResult<Option<PersonType>> { 
+        let person = ctx.data::<DataLoader<PersonLoader>>()?
+            .load_one(to_int(self.person_id.clone()))
+            .await
+            .map_err(|e| e.extend())?;
+        Ok(person.as_ref().map(PersonType::from))
+    }
+}
+
+#[ComplexObject]
+impl PersonType { 
+    /// Profile pictures, the primary one first. URLs are sized to `size`, the original when it is left out
+    async fn images(&self, ctx: &Context<'_>, size: Option<ImageSize>, format: Option<ImageFormat>) -> FieldResult<Vec<ImageType>> { 
+        owner_images(ctx, ImageOwner::Person, &self.person_id, Some(ImageKind::Profile), size.unwrap_or(ImageSize::Original), format).await
+    }
+}
+
+/// Cast of the movie in billing order
+pub(crate) async fn movie_cast(ctx: &Context<'_>, movie_id: &ID) -> FieldResult<Vec<CreditType>> { 
+    let mut cast = movie_credits(ctx, movie_id).await?;
+    cast.retain(Credit::is_cast);
+    cast.sort_by_key(|credit| (credit.billing_order.is_none(), credit.billing_order));
+    Ok(cast.iter().map(CreditType::from).collect())
+}
+
+/// Crew of the movie in `department`, or all of it grouped by department
+pub(crate) async fn movie_crew(ctx: &Context<'_>, movie_id: &ID, department: Option<String>) -> FieldResult<Vec<CreditType>> { 
+    let mut crew = movie_credits(ctx, movie_id).await?;
+    crew.retain(|credit| !credit.is_cast() && department.as_ref().map_or(true, |d| *d == credit.department));
+    crew.sort_by(|a, b| (&a.department, &a.job, &a.name).cmp(&(&b.department, &b.job, &b.name)));
+    Ok(crew.iter().map(CreditType::from).collect())
+}
+
+async fn movie_credits(ctx: &Context<'_>, movie_id: &ID) -> FieldResult<Vec<Credit>> { 
+    let credits = ctx.data::<DataLoader<CreditLoader>>()?
+        .load_one(to_bigint(movie_id.clone()))
+        .await
+        .map_err(|e| e.extend())?;
+    Ok(credits.unwrap_or_default())
+}
+
+#[Object(extends, cache_control(max_age = 180))]
+impl<R: CreditResolver, M: MovieResolver> CreditQuery<R, M> { 
+    /// Every credit of a person, newest movie first
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "filmography")]
+    async fn filmography(&self, ctx: &Context<'_>, person_id: ID) -> FieldResult<Vec<FilmographyCreditType>> { 
+        let credits = Credit::get_person_credits::<R>(to_int(person_id), get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        let mut movie_ids = credits.iter().map(|credit| credit.movie_id).collect::<Vec<_>>();
+        movie_ids.sort_unstable();
+        movie_ids.dedup();
+        let movies = join_all(movie_ids.into_iter().map(|movie_id| Movie::get_movie_by_id::<M>(movie_id, get_store_from_ctx(ctx))))
+            .await
+            .into_iter()
+            .filter_map(Result::ok)
+            .collect::<Vec<_>>();
+        let movies = retain_available(ctx, movies)
+            .await?
+            .into_iter()
+            .map(|movie| (movie.movie_id, movie))
+            .collect::<HashMap<_, _>>();
+        let mut filmography = credits
+            .iter()
+            .map(|credit| (credit, movies.get(&credit.movie_id)))
+            .collect::<Vec<_>>();
+        filmography.sort_by_key(|(_, movie)| Reverse(movie.map(|movie| movie.release_date)));
+        Ok(filmography
+            .into_iter()
+            .map(|(credit, movie)| FilmographyCreditType { 
+                credit: CreditType::from(credit),
+                movie: movie.map(MovieType::from),
+            })
+            .collect())
+    }
+}
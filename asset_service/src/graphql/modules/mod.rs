@@ -1,3 +1,9 @@
 pub mod model;
 pub mod resolver;
-pub mod schema;
\ No newline at end of file
+pub mod schema;
+pub mod series;
+pub mod credits;
+pub mod translations;
+pub mod availability;
+pub mod artwork;
+pub mod playback;
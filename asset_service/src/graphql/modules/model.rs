@@ -3,12 +3,12 @@ use scylla::macros::FromRow;
 use async_graphql::{Enum, SimpleObject};
 use chrono::naive::{NaiveDate};
 use common_utils::{QueryResult, default_date};
+use common_utils::events::PartitionKey;
 use scylla::{ValueList};
 use scylla::macros::{FromUserType, IntoUserType};
 use serde::{Deserialize, Serialize};
 use strum_macros::{EnumString, Display};
 use chrono::Utc;
-use crate::db::CachedSession;
 use crate::{generate_unique_id, to_int};
 use super::resolver::MovieResolver;
 use super::schema::MovieType;
@@ -46,6 +46,8 @@ pub struct Movie {
     pub awards: Vec<String>,
     pub business: BusinessData,
     pub countries: Vec<String>,
+    /// Set while the movie is soft deleted, the resolvers never hand out such a movie
+    pub deleted_at: Option<i64>,
     pub genres: Vec<String>,
     pub homepage: String,
     pub keywords: Vec<String>,
@@ -65,6 +67,20 @@ pub struct Movie {
     pub video_file: String,
 }
 
+/// Row of `movies_by_id`, the full key of a movie looked up from its id
+#[derive(Debug, Clone, FromRow, ValueList)]
+pub struct MovieKey { 
+    pub movie_id: i64,
+    pub title: String,
+    pub year: i32,
+}
+
+impl PartitionKey for Movie { 
+    fn partition_key(&self) -> String {
+        self.movie_id.to_string()
+    }
+}
+
 #[derive(Copy, Clone, Eq, Debug, PartialEq, Serialize, Deserialize, Enum, Display, EnumString)]
 #[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
 pub enum Status { 
@@ -105,19 +121,26 @@ impl From<&Movie> for MovieType {
             runtime: f.runtime.clone() ,
             status: f.status.clone() ,
             video_file: f.video_file.clone() ,
-            
+            requested_locale: None,
         }
     }
 }
 
 
 impl Movie { 
+    pub fn is_deleted(&self) -> bool { 
+        self.deleted_at.is_some()
+    }
     #[tracing::instrument(skip(session))]
-    pub async fn get_all_movie<MovieDatabase: MovieResolver>(session: &'static CachedSession, page_size: Option<i32>) -> QueryResult<Vec<Movie>> {
+    pub async fn get_all_movie<MovieDatabase: MovieResolver>(session: &'static MovieDatabase::Store, page_size: Option<i32>) -> QueryResult<Vec<Movie>> {
         MovieDatabase::get_all_movie(session, page_size).await
     }
     #[tracing::instrument(skip(session))]
-    pub async fn get_movie_id_title<MovieDatabase: MovieResolver>(title: String, movie_id: i64, session: &'static CachedSession) -> QueryResult<Movie> {
+    pub async fn get_movie_by_id<MovieDatabase: MovieResolver>(movie_id: i64, session: &'static MovieDatabase::Store) -> QueryResult<Movie> {
+        MovieDatabase::get_movie_by_id(movie_id, session).await
+    }
+    #[tracing::instrument(skip(session))]
+    pub async fn get_movie_id_title<MovieDatabase: MovieResolver>(title: String, movie_id: i64, session: &'static MovieDatabase::Store) -> QueryResult<Movie> {
         MovieDatabase::get_movie_by_id_title(title, movie_id, session).await
     }
 }
\ No newline at end of file
@@ -0,0 +1,215 @@
+//! HLS master playlists (RFC 8216) and DASH MPDs (ISO/IEC 23009-1) of what a caller may play,
+//! generated from the stored rendition and track metadata. The input is expected to be filtered
+//! and ordered by `MediaAsset::for_plan`, every URL in the output is signed by `MediaUrls`
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use common_utils::playback::{Container, TextTrackFormat, TextTrackKind};
+use super::model::{AudioTrack, MediaAsset, MediaUrls, Rendition, TextTrack, hls_playlist};
+
+pub const HLS_MANIFEST: &str = "master.m3u8";
+pub const DASH_MANIFEST: &str = "manifest.mpd";
+pub const HLS_CONTENT_TYPE: &str = "application/vnd.apple.mpegurl";
+pub const DASH_CONTENT_TYPE: &str = "application/dash+xml";
+
+/// Group every subtitle rendition of a master playlist belongs to
+const SUBTITLE_GROUP: &str = "subs";
+/// Nominal `bandwidth` of a text track, DASH requires one on every representation
+const TEXT_BANDWIDTH: i32 = 256;
+
+/// Bitrates are stored in kbit/s, manifests are in bit/s
+fn bits(kbits: i32) -> i64 {
+    i64::from(kbits) * 1000
+}
+
+/// Renditions cheapest first, the first variant of a playlist is the one clients start with
+fn by_bandwidth(renditions: &[Rendition]) -> Vec<&Rendition> {
+    let mut renditions: Vec<&Rendition> = renditions.iter().collect();
+    renditions.sort_by(|a, b| a.bitrate.cmp(&b.bitrate).then(a.height.cmp(&b.height)).then(a.rendition_id.cmp(&b.rendition_id)));
+    renditions
+}
+
+fn audio_name(track: &AudioTrack) -> String {
+    let mut name = format!("{} {}", track.language, track.channel_layout().label());
+    if track.audio_description {
+        name.push_str(" (Audio description)");
+    }
+    name
+}
+
+fn text_name(track: &TextTrack) -> String {
+    match track.kind() {
+        TextTrackKind::Subtitles => track.language.clone(),
+        TextTrackKind::Captions => format!("{} (CC)", track.language),
+        TextTrackKind::Forced => format!("{} (Forced)", track.language),
+    }
+}
+
+/// HLS plays MPEG-TS and fragmented MP4, not WebM
+fn hls_container(container: Container) -> bool {
+    !matches!(container, Container::Webm)
+}
+
+/// Master playlist with one variant per rendition and audio group. Audio tracks are grouped by
+/// codec, the first track of a group that isn't audio description is its default. Only WebVTT
+/// subtitles are listed, TTML has to be packaged into the segments. `None` when there is no
+/// rendition HLS can play
+pub fn hls_master_playlist(media: &MediaAsset, urls: &MediaUrls) -> Option<String> {
+    let renditions: Vec<&Rendition> = by_bandwidth(&media.renditions)
+        .into_iter()
+        .filter(|rendition| hls_container(rendition.container()))
+        .collect();
+    if renditions.is_empty() {
+        return None
+    }
+    let mut groups: BTreeMap<String, Vec<&AudioTrack>> = BTreeMap::new();
+    for track in &media.audio_tracks {
+        groups.entry(format!("audio-{}", track.codec().to_string().to_lowercase())).or_default().push(track);
+    }
+    let subtitles: Vec<&TextTrack> = media.text_tracks.iter().filter(|track| track.format() == TextTrackFormat::Webvtt).collect();
+
+    let mut out = String::from("#EXTM3U\n#EXT-X-VERSION:6\n#EXT-X-INDEPENDENT-SEGMENTS\n");
+    if !groups.is_empty() {
+        out.push('\n');
+    }
+    for (group, tracks) in &groups {
+        let default = tracks.iter().position(|track| !track.audio_description).unwrap_or(0);
+        for (i, track) in tracks.iter().enumerate() {
+            let _ = write!(
+                out,
+                "#EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"{}\",NAME=\"{}\",LANGUAGE=\"{}\",DEFAULT={},AUTOSELECT=YES,CHANNELS=\"{}\"",
+                group, audio_name(track), track.language, if i == default { "YES" } else { "NO" }, track.channel_layout().hls_channels(),
+            );
+            if track.audio_description {
+                out.push_str(",CHARACTERISTICS=\"public.accessibility.describes-video\"");
+            }
+            let _ = writeln!(out, ",URI=\"{}\"", urls.media(&hls_playlist(&track.location)));
+        }
+    }
+    if !subtitles.is_empty() {
+        out.push('\n');
+    }
+    for track in &subtitles {
+        let _ = write!(
+            out,
+            "#EXT-X-MEDIA:TYPE=SUBTITLES,GROUP-ID=\"{}\",NAME=\"{}\",LANGUAGE=\"{}\",DEFAULT=NO,AUTOSELECT=YES,FORCED={}",
+            SUBTITLE_GROUP, text_name(track), track.language, if track.kind() == TextTrackKind::Forced { "YES" } else { "NO" },
+        );
+        if track.kind() == TextTrackKind::Captions {
+            out.push_str(",CHARACTERISTICS=\"public.accessibility.transcribes-spoken-dialog,public.accessibility.describes-music-and-sound\"");
+        }
+        let _ = writeln!(out, ",URI=\"{}\"", urls.media(&hls_playlist(&track.location)));
+    }
+
+    // A variant without audio groups still has to be listed once
+    let variants: Vec<(Option<&String>, i32, Vec<&str>)> = match groups.is_empty() {
+        true => vec![(None, 0, Vec::new())],
+        false => groups
+            .iter()
+            .map(|(group, tracks)| {
+                let bitrate = tracks.iter().map(|track| track.bitrate).max().unwrap_or_default();
+                (Some(group), bitrate, vec![tracks[0].codec().codecs()])
+            })
+            .collect(),
+    };
+    for (group, audio_bitrate, audio_codecs) in &variants {
+        out.push('\n');
+        for rendition in &renditions {
+            let bandwidth = bits(rendition.bitrate + audio_bitrate);
+            let mut codecs = vec![rendition.codec().codecs(rendition.height)];
+            codecs.extend(audio_codecs);
+            let _ = write!(
+                out,
+                "#EXT-X-STREAM-INF:BANDWIDTH={},AVERAGE-BANDWIDTH={},CODECS=\"{}\",RESOLUTION={}x{}",
+                bandwidth, bandwidth, codecs.join(","), rendition.width, rendition.height,
+            );
+            if let Some(group) = group {
+                let _ = write!(out, ",AUDIO=\"{}\"", group);
+            }
+            if !subtitles.is_empty() {
+                let _ = write!(out, ",SUBTITLES=\"{}\"", SUBTITLE_GROUP);
+            }
+            let _ = writeln!(out, "\n{}", urls.media(&hls_playlist(&rendition.location)));
+        }
+    }
+    Some(out)
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn video_mime(container: Container) -> &'static str {
+    match container {
+        Container::Mp4 | Container::Cmaf => "video/mp4",
+        Container::Webm => "video/webm",
+        Container::Ts => "video/mp2t",
+    }
+}
+
+/// MPD of a single period with every rendition, audio and text track as a self-initialising
+/// file under its `BaseURL`. Video is split in adaptation sets by container and codec, each audio
+/// and text track has its own, tagged with its language and role. `duration` is in seconds, `None`
+/// when there is no rendition
+pub fn dash_mpd(media: &MediaAsset, urls: &MediaUrls, duration: i64) -> Option<String> {
+    if media.renditions.is_empty() {
+        return None
+    }
+    let mut video: BTreeMap<(&str, String), Vec<&Rendition>> = BTreeMap::new();
+    for rendition in by_bandwidth(&media.renditions) {
+        video.entry((video_mime(rendition.container()), rendition.codec().to_string())).or_default().push(rendition);
+    }
+
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<MPD xmlns=\"urn:mpeg:dash:schema:mpd:2011\" profiles=\"urn:mpeg:dash:profile:full:2011\" type=\"static\" minBufferTime=\"PT2S\"");
+    if duration > 0 {
+        let _ = write!(out, " mediaPresentationDuration=\"PT{}S\"", duration);
+    }
+    out.push_str(">\n  <Period id=\"1\" start=\"PT0S\">\n");
+    let mut set = 0;
+    for ((mime, _), renditions) in &video {
+        set += 1;
+        let _ = writeln!(out, "    <AdaptationSet id=\"{}\" contentType=\"video\" mimeType=\"{}\" startWithSAP=\"1\">", set, mime);
+        for rendition in renditions {
+            let _ = writeln!(
+                out,
+                "      <Representation id=\"v{}\" bandwidth=\"{}\" codecs=\"{}\" width=\"{}\" height=\"{}\">",
+                rendition.rendition_id, bits(rendition.bitrate), rendition.codec().codecs(rendition.height), rendition.width, rendition.height,
+            );
+            let _ = writeln!(out, "        <BaseURL>{}</BaseURL>\n      </Representation>", escape(&urls.media(&rendition.location)));
+        }
+        out.push_str("    </AdaptationSet>\n");
+    }
+    for track in &media.audio_tracks {
+        set += 1;
+        let _ = writeln!(out, "    <AdaptationSet id=\"{}\" contentType=\"audio\" mimeType=\"audio/mp4\" lang=\"{}\">", set, escape(&track.language));
+        if track.audio_description {
+            out.push_str("      <Accessibility schemeIdUri=\"urn:tva:metadata:cs:AudioPurposeCS:2007\" value=\"1\"/>\n");
+        }
+        let _ = writeln!(out, "      <Role schemeIdUri=\"urn:mpeg:dash:role:2011\" value=\"{}\"/>", if track.audio_description { "description" } else { "main" });
+        let _ = writeln!(out, "      <Representation id=\"a{}\" bandwidth=\"{}\" codecs=\"{}\">", track.track_id, bits(track.bitrate), track.codec().codecs());
+        let _ = writeln!(out, "        <AudioChannelConfiguration schemeIdUri=\"urn:mpeg:mpegB:cicp:ChannelConfiguration\" value=\"{}\"/>", track.channel_layout().cicp());
+        let _ = writeln!(out, "        <BaseURL>{}</BaseURL>\n      </Representation>\n    </AdaptationSet>", escape(&urls.media(&track.location)));
+    }
+    for track in &media.text_tracks {
+        set += 1;
+        let mime = match track.format() {
+            TextTrackFormat::Webvtt => "text/vtt",
+            TextTrackFormat::Ttml => "application/ttml+xml",
+        };
+        let role = match track.kind() {
+            TextTrackKind::Subtitles => "subtitle",
+            TextTrackKind::Captions => "caption",
+            TextTrackKind::Forced => "forced-subtitle",
+        };
+        let _ = writeln!(out, "    <AdaptationSet id=\"{}\" contentType=\"text\" mimeType=\"{}\" lang=\"{}\">", set, mime, escape(&track.language));
+        let _ = writeln!(out, "      <Role schemeIdUri=\"urn:mpeg:dash:role:2011\" value=\"{}\"/>", role);
+        let _ = writeln!(out, "      <Representation id=\"t{}\" bandwidth=\"{}\">", track.track_id, TEXT_BANDWIDTH);
+        let _ = writeln!(out, "        <BaseURL>{}</BaseURL>\n      </Representation>\n    </AdaptationSet>", escape(&urls.media(&track.location)));
+    }
+    out.push_str("  </Period>\n</MPD>\n");
+    Some(out)
+}
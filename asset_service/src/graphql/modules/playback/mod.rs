@@ -0,0 +1,5 @@
+pub mod manifest;
+pub mod model;
+pub mod resolver;
+pub mod routes;
+pub mod schema;
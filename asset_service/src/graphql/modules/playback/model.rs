@@ -0,0 +1,162 @@
+use std::env::var;
+use chrono::Utc;
+use common_utils::{
+    QueryResult,
+    playback::{AudioCodec, ChannelLayout, Container, MediaOwner, Plan, TextTrackFormat, TextTrackKind, VideoCodec},
+    signing::{PlaybackClient, SigningMode, UrlSigner},
+};
+use lazy_static::lazy_static;
+use scylla::macros::FromRow;
+use serde::{Deserialize, Serialize};
+use super::resolver::PlaybackResolver;
+
+lazy_static! {
+    /// Where the files of `s3://bucket/key` locations are delivered from, by key
+    static ref MEDIA_BASE_URL: String = var("MEDIA_BASE_URL").unwrap_or_else(|_| "http://localhost:8080/media".to_string());
+    /// Public address of this service, the manifests are served under it
+    static ref PLAYBACK_BASE_URL: String = var("PLAYBACK_BASE_URL")
+        .unwrap_or_else(|_| format!("http://localhost:{}", var("PORT").unwrap_or_else(|_| "4004".to_string())));
+}
+
+// Columns after the primary key are in alphabetical order, the order `SELECT *` returns them in
+/// One encode of a movie or an episode, written by the ingestion service
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize)]
+pub struct Rendition { 
+    pub owner_type: String,
+    pub owner_id: i64,
+    pub rendition_id: i64,
+    /// Average, in kbit/s
+    pub bitrate: i32,
+    pub checksum: String,
+    pub codec: String,
+    pub container: String,
+    pub height: i32,
+    pub location: String,
+    pub width: i32,
+}
+
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize)]
+pub struct AudioTrack { 
+    pub owner_type: String,
+    pub owner_id: i64,
+    pub track_id: i64,
+    pub audio_description: bool,
+    pub bitrate: i32,
+    pub channel_layout: String,
+    pub checksum: String,
+    pub codec: String,
+    pub language: String,
+    pub location: String,
+}
+
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize)]
+pub struct TextTrack { 
+    pub owner_type: String,
+    pub owner_id: i64,
+    pub track_id: i64,
+    pub checksum: String,
+    pub format: String,
+    pub kind: String,
+    pub language: String,
+    pub location: String,
+}
+
+/// Everything registered for a movie or an episode, before the caller's plan is applied
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MediaAsset { 
+    pub renditions: Vec<Rendition>,
+    pub audio_tracks: Vec<AudioTrack>,
+    pub text_tracks: Vec<TextTrack>,
+}
+
+// Stored values were written from these enums by the ingestion service, so they always parse
+impl Rendition { 
+    pub fn codec(&self) -> VideoCodec { 
+        self.codec.parse().unwrap_or(VideoCodec::H264)
+    }
+    pub fn container(&self) -> Container { 
+        self.container.parse().unwrap_or(Container::Mp4)
+    }
+}
+
+impl AudioTrack { 
+    pub fn codec(&self) -> AudioCodec { 
+        self.codec.parse().unwrap_or(AudioCodec::Aac)
+    }
+    pub fn channel_layout(&self) -> ChannelLayout { 
+        self.channel_layout.parse().unwrap_or(ChannelLayout::Stereo)
+    }
+}
+
+impl TextTrack { 
+    pub fn kind(&self) -> TextTrackKind { 
+        self.kind.parse().unwrap_or(TextTrackKind::Subtitles)
+    }
+    pub fn format(&self) -> TextTrackFormat { 
+        self.format.parse().unwrap_or(TextTrackFormat::Webvtt)
+    }
+}
+
+impl MediaAsset { 
+    pub async fn get_media<PlaybackDatabase: PlaybackResolver>(owner: MediaOwner, owner_id: i64, session: &'static PlaybackDatabase::Store) -> QueryResult<MediaAsset> {
+        PlaybackDatabase::get_media(owner, owner_id, session).await
+    }
+    /// What `plan` may play: the renditions no taller than its cap, best first, then every audio
+    /// and text track by language. The order is stable, manifests are generated from it
+    pub fn for_plan(&self, plan: Plan) -> MediaAsset { 
+        let mut renditions: Vec<Rendition> = self.renditions.iter().filter(|f| f.height <= plan.max_height()).cloned().collect();
+        renditions.sort_by(|a, b| b.height.cmp(&a.height).then(b.bitrate.cmp(&a.bitrate)).then(a.rendition_id.cmp(&b.rendition_id)));
+        let mut audio_tracks = self.audio_tracks.clone();
+        audio_tracks.sort_by(|a, b| (&a.language, a.audio_description, a.track_id).cmp(&(&b.language, b.audio_description, b.track_id)));
+        let mut text_tracks = self.text_tracks.clone();
+        text_tracks.sort_by(|a, b| (&a.language, a.kind(), a.track_id).cmp(&(&b.language, b.kind(), b.track_id)));
+        MediaAsset { renditions, audio_tracks, text_tracks }
+    }
+}
+
+/// Location of the HLS media playlist the packager writes next to a file, e.g. `video_1080.m3u8`
+/// for `video_1080.mp4`
+pub fn hls_playlist(location: &str) -> String { 
+    let name = location.rfind('/').map(|slash| slash + 1).unwrap_or(0);
+    match location[name..].rfind('.') {
+        Some(dot) => format!("{}.m3u8", &location[..name + dot]),
+        None => format!("{}.m3u8", location),
+    }
+}
+
+/// Signs every URL handed out for one playback: the files, and the manifests of the owner
+pub struct MediaUrls<'a> { 
+    pub signer: &'a UrlSigner,
+    pub client: &'a PlaybackClient,
+    /// When the URLs stop working, in seconds since the epoch
+    pub expires: i64,
+}
+
+impl<'a> MediaUrls<'a> { 
+    /// URL a stored file is fetched from. `s3://bucket/key` is presigned for the bucket in
+    /// `SigningMode::S3`, and otherwise delivered from `MEDIA_BASE_URL/key` with a token
+    pub fn media(&self, location: &str) -> String { 
+        let url = match location.split_once("://") {
+            Some(("http" | "https", _)) | None => location.to_string(),
+            Some((_, path)) => {
+                let (bucket, key) = path.split_once('/').unwrap_or((path, ""));
+                if self.signer.mode == SigningMode::S3 {
+                    if let Some(url) = self.signer.presign_s3(bucket, key, self.expires, Utc::now().timestamp()) {
+                        return url
+                    }
+                }
+                format!("{}/{}", MEDIA_BASE_URL.trim_end_matches('/'), key)
+            }
+        };
+        self.signer.sign(&url, self.expires, self.client)
+    }
+    /// A manifest of the owner at `owner_path`, e.g. `movies/42`, under this service. The plan and
+    /// region it is generated for are signed into it, see `routes`
+    pub fn manifest(&self, owner_path: &str, file: &str, plan: Plan, region: Option<&str>) -> String { 
+        let mut url = format!("{}/playback/{}/{}?plan={}", PLAYBACK_BASE_URL.trim_end_matches('/'), owner_path, file, plan);
+        if let Some(region) = region {
+            url.push_str(&format!("&region={}", region));
+        }
+        self.signer.sign(&url, self.expires, self.client)
+    }
+}
@@ -0,0 +1,54 @@
+use async_trait::async_trait;
+use common_utils::{QueryResult, error::ServiceError, playback::MediaOwner};
+use scylla::IntoTypedRows;
+use crate::db::CachedSession;
+use super::model::{AudioTrack, MediaAsset, Rendition, TextTrack};
+
+/// Read side of the renditions and tracks registered by the ingestion service
+#[async_trait]
+pub trait PlaybackResolver: Send + Sync + 'static { 
+    type Store: Send + Sync + 'static;
+    async fn get_media(owner: MediaOwner, owner_id: i64, session: &'static Self::Store) -> QueryResult<MediaAsset>;
+}
+
+#[derive(Default)]
+pub struct PlaybackDatabase;
+
+static GET_RENDITIONS: &str = "SELECT * FROM movie_keyspace.media_renditions WHERE owner_type = ? AND owner_id = ?;";
+static GET_AUDIO_TRACKS: &str = "SELECT * FROM movie_keyspace.media_audio_tracks WHERE owner_type = ? AND owner_id = ?;";
+static GET_TEXT_TRACKS: &str = "SELECT * FROM movie_keyspace.media_text_tracks WHERE owner_type = ? AND owner_id = ?;";
+
+#[async_trait]
+impl PlaybackResolver for PlaybackDatabase { 
+    type Store = CachedSession;
+
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.media_renditions"))]
+    async fn get_media(owner: MediaOwner, owner_id: i64, session: &'static CachedSession) -> QueryResult<MediaAsset> { 
+        let key = (owner.to_string(), owner_id);
+        let renditions = session
+            .query_prepared(GET_RENDITIONS, key.clone())
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
+            .into_typed::<Rendition>()
+            .map(|row| row.map_err(|e| ServiceError::ServerError(e.to_string())))
+            .collect::<QueryResult<Vec<_>>>()?;
+        let audio_tracks = session
+            .query_prepared(GET_AUDIO_TRACKS, key.clone())
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
+            .into_typed::<AudioTrack>()
+            .map(|row| row.map_err(|e| ServiceError::ServerError(e.to_string())))
+            .collect::<QueryResult<Vec<_>>>()?;
+        let text_tracks = session
+            .query_prepared(GET_TEXT_TRACKS, key)
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
+            .into_typed::<TextTrack>()
+            .map(|row| row.map_err(|e| ServiceError::ServerError(e.to_string())))
+            .collect::<QueryResult<Vec<_>>>()?;
+        Ok(MediaAsset { renditions, audio_tracks, text_tracks })
+    }
+}
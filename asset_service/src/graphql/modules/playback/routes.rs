@@ -0,0 +1,130 @@
+use actix_web::{get, http::header::{CacheControl, CacheDirective}, web, HttpRequest, HttpResponse};
+use chrono::Utc;
+use common_utils::{
+    availability::RequestRegion,
+    error::ServiceError,
+    playback::{MediaOwner, Plan},
+    signing::{PlaybackClient, UrlSigner},
+};
+use serde::Deserialize;
+use crate::db::session;
+use super::super::{model::Movie, resolver::MovieDatabase};
+use super::super::availability::{model::AvailabilityWindow, resolver::AvailabilityDatabase};
+use super::super::series::{model::Episode, resolver::SeriesDatabase};
+use super::manifest::{dash_mpd, hls_master_playlist, DASH_CONTENT_TYPE, DASH_MANIFEST, HLS_CONTENT_TYPE, HLS_MANIFEST};
+use super::model::{MediaAsset, MediaUrls};
+use super::resolver::PlaybackDatabase;
+
+/// HLS and DASH manifests, generated per request for the plan and region signed into their URL,
+/// and the check an edge runs before it serves a media file
+pub fn configure_manifests(cfg: &mut web::ServiceConfig) { 
+    cfg
+    .service(movie_manifest)
+    .service(episode_manifest)
+    .service(authorize);
+}
+
+/// Query of a manifest URL issued by `playbackInfo`, less the token
+#[derive(Debug, Deserialize)]
+pub struct ManifestParams {
+    pub plan: Plan,
+    pub region: Option<String>,
+    pub exp: i64,
+}
+
+/// The client presenting a manifest URL, which has to be signed for it and unexpired
+fn verify(http: &HttpRequest, signer: &UrlSigner) -> Result<PlaybackClient, ServiceError> { 
+    let client = PlaybackClient::new(http);
+    let path_and_query = http.uri().path_and_query().map(|path| path.as_str()).unwrap_or_default();
+    signer
+        .verify(path_and_query, &client, Utc::now().timestamp())
+        .map_err(|_| ServiceError::Forbidden)?;
+    Ok(client)
+}
+
+/// Writes the manifest `file` names of `media`, which has to be filtered for the caller already.
+/// The URLs in it expire with the manifest's. Not found for other file names and when nothing in
+/// `media` can be played with the format
+fn manifest_response(file: &str, media: &MediaAsset, urls: &MediaUrls, duration: i64) -> Result<HttpResponse, ServiceError> { 
+    let (content_type, manifest) = match file {
+        HLS_MANIFEST => (HLS_CONTENT_TYPE, hls_master_playlist(media, urls)),
+        DASH_MANIFEST => (DASH_CONTENT_TYPE, dash_mpd(media, urls, duration)),
+        _ => return Err(ServiceError::NotFound),
+    };
+    let manifest = manifest.ok_or(ServiceError::NotFound)?;
+    // What is listed depends on who asked, so shared caches must not keep it
+    Ok(HttpResponse::Ok()
+        .content_type(content_type)
+        .insert_header(CacheControl(vec![CacheDirective::Private, CacheDirective::MaxAge(60)]))
+        .body(manifest))
+}
+
+/// Not found when the title can't be watched in `region` right now. Series are licensed by the
+/// windows stored under their series id, the same way as movies
+async fn ensure_available(title_id: i64, region: Option<String>) -> Result<(), ServiceError> { 
+    let windows = AvailabilityWindow::get_windows_by_movies::<AvailabilityDatabase>(vec![title_id], session()).await?;
+    match RequestRegion { region }.allows(windows.iter().map(AvailabilityWindow::span)) {
+        true => Ok(()),
+        false => Err(ServiceError::NotFound),
+    }
+}
+
+/// `GET /playback/movies/{movie_id}/master.m3u8` or `manifest.mpd` with the token of `playbackInfo`.
+/// Forbidden without a valid one, not found when the movie can no longer be watched in the region
+/// it was issued for
+#[get("/playback/movies/{movie_id}/{file}")]
+pub async fn movie_manifest(
+    path: web::Path<(i64, String)>,
+    params: web::Query<ManifestParams>,
+    http: HttpRequest,
+    signer: web::Data<UrlSigner>,
+) -> Result<HttpResponse, ServiceError> { 
+    let client = verify(&http, &signer)?;
+    let (movie_id, file) = path.into_inner();
+    let ManifestParams { plan, region, exp } = params.into_inner();
+    let movie = Movie::get_movie_by_id::<MovieDatabase>(movie_id, session()).await?;
+    ensure_available(movie_id, region).await?;
+    let media = MediaAsset::get_media::<PlaybackDatabase>(MediaOwner::Movie, movie_id, session()).await?;
+    let urls = MediaUrls { signer: &signer, client: &client, expires: exp };
+    // Runtimes are in minutes
+    manifest_response(&file, &media.for_plan(plan), &urls, movie.runtime * 60)
+}
+
+/// `GET /playback/episodes/{series_id}/{season_number}/{episode_number}/master.m3u8` or `manifest.mpd`
+/// with the token of `episodePlaybackInfo`. Not found when the series can no longer be watched in
+/// the region it was issued for
+#[get("/playback/episodes/{series_id}/{season_number}/{episode_number}/{file}")]
+pub async fn episode_manifest(
+    path: web::Path<(i64, i32, i32, String)>,
+    params: web::Query<ManifestParams>,
+    http: HttpRequest,
+    signer: web::Data<UrlSigner>,
+) -> Result<HttpResponse, ServiceError> { 
+    let client = verify(&http, &signer)?;
+    let (series_id, season_number, episode_number, file) = path.into_inner();
+    let ManifestParams { plan, region, exp } = params.into_inner();
+    ensure_available(series_id, region).await?;
+    let episode = Episode::get_episodes::<SeriesDatabase>(series_id, season_number, session())
+        .await?
+        .into_iter()
+        .find(|episode| episode.episode_number == episode_number)
+        .ok_or(ServiceError::NotFound)?;
+    let media = MediaAsset::get_media::<PlaybackDatabase>(MediaOwner::Episode, episode.episode_id, session()).await?;
+    let urls = MediaUrls { signer: &signer, client: &client, expires: exp };
+    manifest_response(&file, &media.for_plan(plan), &urls, episode.runtime * 60)
+}
+
+/// `GET /playback/authorize` for an nginx `auth_request` in front of `MEDIA_BASE_URL`, with the
+/// media URL in `X-Original-URI`. No content when its token is valid for the client, forbidden
+/// otherwise
+#[get("/playback/authorize")]
+pub async fn authorize(http: HttpRequest, signer: web::Data<UrlSigner>) -> Result<HttpResponse, ServiceError> { 
+    let uri = http.headers()
+        .get("X-Original-URI")
+        .and_then(|value| value.to_str().ok())
+        .ok_or(ServiceError::Forbidden)?;
+    signer
+        .verify(uri, &PlaybackClient::new(&http), Utc::now().timestamp())
+        .map_err(|_| ServiceError::Forbidden)?;
+    Ok(HttpResponse::NoContent().finish())
+}
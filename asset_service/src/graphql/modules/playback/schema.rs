@@ -0,0 +1,202 @@
+use std::marker::PhantomData;
+use async_graphql::*;
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use common_utils::{
+    availability::RequestRegion,
+    error::ServiceError,
+    playback::{AudioCodec, ChannelLayout, Container, MediaOwner, Plan, RequestPlan, TextTrackFormat, TextTrackKind, VideoCodec},
+    signing::{new_session, PlaybackClient, UrlSigner},
+};
+use serde::{Deserialize, Serialize};
+use crate::{graphql::config::get_store_from_ctx, to_bigint};
+use super::super::{model::Movie, resolver::{MovieDatabase, MovieResolver}};
+use super::super::availability::schema::{ensure_available, ensure_series_available};
+use super::super::series::{model::Episode, resolver::{SeriesDatabase, SeriesResolver}};
+use super::model::{AudioTrack, MediaAsset, MediaUrls, Rendition, TextTrack};
+use super::manifest::{DASH_MANIFEST, HLS_MANIFEST};
+use super::resolver::{PlaybackDatabase, PlaybackResolver};
+
+/// What a caller may play, media read through `R`, movies through `M` and episodes through `S`
+#[derive(Default)]
+pub struct PlaybackQuery<R = PlaybackDatabase, M = MovieDatabase, S = SeriesDatabase>(PhantomData<(R, M, S)>);
+
+#[derive(Debug, Clone, SimpleObject, Deserialize, Serialize)]
+pub struct PlaybackRenditionType { 
+    pub rendition_id: ID,
+    pub codec: VideoCodec,
+    pub container: Container,
+    pub width: i32,
+    pub height: i32,
+    /// Average, in kbit/s
+    pub bitrate: i32,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, SimpleObject, Deserialize, Serialize)]
+pub struct PlaybackAudioTrackType { 
+    pub track_id: ID,
+    pub language: String,
+    pub codec: AudioCodec,
+    pub channel_layout: ChannelLayout,
+    /// Narrates what happens on screen for blind and partially sighted viewers
+    pub audio_description: bool,
+    pub bitrate: i32,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, SimpleObject, Deserialize, Serialize)]
+pub struct PlaybackTextTrackType { 
+    pub track_id: ID,
+    pub language: String,
+    pub kind: TextTrackKind,
+    pub format: TextTrackFormat,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, SimpleObject, Deserialize, Serialize)]
+pub struct PlaybackInfoType { 
+    pub owner: MediaOwner,
+    /// The movie id, or the episode id of an episode
+    pub owner_id: ID,
+    /// Plan the renditions were picked for
+    pub plan: Plan,
+    /// Tallest rendition the plan may play
+    pub max_height: i32,
+    /// HLS master playlist of what the plan may play, with alternate audio and subtitles
+    pub hls_url: String,
+    /// DASH MPD of what the plan may play
+    pub dash_url: String,
+    /// Players send it back as `x-playback-session` with every media request, URLs may be bound to it
+    pub playback_session: String,
+    /// When the URLs stop working, a new `playbackInfo` has to be requested then
+    pub expires_at: DateTime<Utc>,
+    /// Best first. Empty when the title has no rendition the plan may play
+    pub renditions: Vec<PlaybackRenditionType>,
+    pub audio_tracks: Vec<PlaybackAudioTrackType>,
+    pub text_tracks: Vec<PlaybackTextTrackType>,
+}
+
+impl PlaybackRenditionType { 
+    pub fn new(f: &Rendition, urls: &MediaUrls) -> Self { 
+        Self {
+            rendition_id: f.rendition_id.into(),
+            codec: f.codec(),
+            container: f.container(),
+            width: f.width,
+            height: f.height,
+            bitrate: f.bitrate,
+            url: urls.media(&f.location),
+        }
+    }
+}
+
+impl PlaybackAudioTrackType { 
+    pub fn new(f: &AudioTrack, urls: &MediaUrls) -> Self { 
+        Self {
+            track_id: f.track_id.into(),
+            language: f.language.clone(),
+            codec: f.codec(),
+            channel_layout: f.channel_layout(),
+            audio_description: f.audio_description,
+            bitrate: f.bitrate,
+            url: urls.media(&f.location),
+        }
+    }
+}
+
+impl PlaybackTextTrackType { 
+    pub fn new(f: &TextTrack, urls: &MediaUrls) -> Self { 
+        Self {
+            track_id: f.track_id.into(),
+            language: f.language.clone(),
+            kind: f.kind(),
+            format: f.format(),
+            url: urls.media(&f.location),
+        }
+    }
+}
+
+impl PlaybackInfoType { 
+    /// `media` with the renditions above the plan's quality cap left out, every URL signed by
+    /// `urls`. `owner_path` locates the owner's manifests, e.g. `movies/42`, which are signed with
+    /// the plan and region they are generated for
+    pub fn new(owner: MediaOwner, owner_id: i64, owner_path: &str, plan: Plan, region: Option<&str>, media: &MediaAsset, urls: &MediaUrls) -> Self { 
+        let media = media.for_plan(plan);
+        Self {
+            owner,
+            owner_id: owner_id.into(),
+            plan,
+            max_height: plan.max_height(),
+            hls_url: urls.manifest(owner_path, HLS_MANIFEST, plan, region),
+            dash_url: urls.manifest(owner_path, DASH_MANIFEST, plan, region),
+            playback_session: urls.client.session.clone().unwrap_or_default(),
+            expires_at: Utc.timestamp(urls.expires, 0),
+            renditions: media.renditions.iter().map(|f| PlaybackRenditionType::new(f, urls)).collect(),
+            audio_tracks: media.audio_tracks.iter().map(|f| PlaybackAudioTrackType::new(f, urls)).collect(),
+            text_tracks: media.text_tracks.iter().map(|f| PlaybackTextTrackType::new(f, urls)).collect(),
+        }
+    }
+}
+
+fn request_plan(ctx: &Context<'_>) -> Plan { 
+    ctx.data_opt::<RequestPlan>().cloned().unwrap_or_default().plan
+}
+
+/// The caller, who has to be signed in. Every `playbackInfo` starts a new playback session, which
+/// the URLs are bound to with `MEDIA_URL_BINDING=session`
+fn playback_client(ctx: &Context<'_>) -> FieldResult<PlaybackClient> { 
+    let client = ctx.data_opt::<PlaybackClient>().cloned().unwrap_or_default();
+    if client.subject.is_none() {
+        return Err(ServiceError::Unauthorized.extend())
+    }
+    Ok(PlaybackClient { session: Some(new_session()), ..client })
+}
+
+/// URLs of `media` valid for the signer's TTL from now
+fn signed_playback_info(ctx: &Context<'_>, owner: MediaOwner, owner_id: i64, owner_path: &str, media: &MediaAsset, client: &PlaybackClient) -> FieldResult<PlaybackInfoType> { 
+    let signer = ctx.data::<UrlSigner>()?;
+    let urls = MediaUrls { signer, client, expires: (Utc::now() + Duration::seconds(signer.ttl)).timestamp() };
+    let region = ctx.data_opt::<RequestRegion>().and_then(|region| region.region.clone());
+    Ok(PlaybackInfoType::new(owner, owner_id, owner_path, request_plan(ctx), region.as_deref(), media, &urls))
+}
+
+#[Object(extends)]
+impl<R: PlaybackResolver, M: MovieResolver, S: SeriesResolver> PlaybackQuery<R, M, S> { 
+    /// Renditions and tracks of the movie for the caller's plan, see `RequestPlan::new`, with signed
+    /// URLs. Unauthorized for anonymous callers, not found when the movie can't be watched in the
+    /// request's region
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "playbackInfo")]
+    async fn playback_info(&self, ctx: &Context<'_>, movie_id: ID) -> FieldResult<PlaybackInfoType> { 
+        let client = playback_client(ctx)?;
+        let movie = Movie::get_movie_by_id::<M>(to_bigint(movie_id), get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        let movie = ensure_available(ctx, movie).await?;
+        let media = MediaAsset::get_media::<R>(MediaOwner::Movie, movie.movie_id, get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        let owner_path = format!("movies/{}", movie.movie_id);
+        signed_playback_info(ctx, MediaOwner::Movie, movie.movie_id, &owner_path, &media, &client)
+    }
+    /// Renditions and tracks of an episode for the caller's plan, with signed URLs. Not found when
+    /// its series can't be watched in the request's region
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "episodePlaybackInfo")]
+    async fn episode_playback_info(&self, ctx: &Context<'_>, series_id: ID, season_number: i32, episode_number: i32) -> FieldResult<PlaybackInfoType> { 
+        let client = playback_client(ctx)?;
+        let series_id = to_bigint(series_id);
+        ensure_series_available(ctx, series_id).await?;
+        let episode = Episode::get_episodes::<S>(series_id, season_number, get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?
+            .into_iter()
+            .find(|episode| episode.episode_number == episode_number)
+            .ok_or_else(|| ServiceError::NotFound.extend())?;
+        let media = MediaAsset::get_media::<R>(MediaOwner::Episode, episode.episode_id, get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        let owner_path = format!("episodes/{}/{}/{}", series_id, season_number, episode_number);
+        signed_playback_info(ctx, MediaOwner::Episode, episode.episode_id, &owner_path, &media, &client)
+    }
+}
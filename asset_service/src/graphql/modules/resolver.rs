@@ -1,28 +1,39 @@
 use async_trait::async_trait;
 use chrono::naive::NaiveDate;
-use common_utils::QueryResult;
+use common_utils::{QueryResult, error::ServiceError};
 use scylla::IntoTypedRows;
 use crate::{db::CachedSession, kafka};
-use super::model::{Movie};
+use super::model::{Movie, MovieKey};
 use futures::stream::StreamExt;
 
 
 
+/// Read side of the catalogue, served from `Store`. Soft deleted movies are left out of every read
 #[async_trait]
-pub trait MovieResolver { 
-    async fn get_all_movie(session: &'static CachedSession, page_size: Option<i32>) -> QueryResult<Vec<Movie>>;
-    async fn get_movie_by_id_title(title: String, movie_id: i64, session: &'static CachedSession) -> QueryResult<Movie>;
+pub trait MovieResolver: Send + Sync + 'static { 
+    type Store: Send + Sync + 'static;
+    async fn get_all_movie(session: &'static Self::Store, page_size: Option<i32>) -> QueryResult<Vec<Movie>>;
+    async fn get_movie_by_id_title(title: String, movie_id: i64, session: &'static Self::Store) -> QueryResult<Movie>;
+    async fn get_movie_by_id(movie_id: i64, session: &'static Self::Store) -> QueryResult<Movie>;
 
 }
 
+#[derive(Default)]
 pub struct MovieDatabase;
 
 static GET_ALL_MOVIES: &str = "select * from movie_keyspace.movies_object;";
 static GET_MOVIE_BY_ID_AND_TITLE: &str = "SELECT * FROM movie_keyspace.movies_object WHERE title = ? AND movie_id = ? ;";
+static GET_MOVIE_KEY: &str = "SELECT movie_id, title, year FROM movie_keyspace.movies_by_id WHERE movie_id = ?;";
+static GET_MOVIE_BY_KEY: &str = "SELECT * FROM movie_keyspace.movies_object WHERE movie_id = ? AND title = ? AND year = ?;";
+// Movies stored before `movies_by_id` existed have no entry until the ingestion service's
+// `backfillMovieKeys` has run, they are found by scanning for the id as they were before
+static GET_LEGACY_MOVIE: &str = "SELECT * FROM movie_keyspace.movies_object WHERE movie_id = ? ALLOW FILTERING;";
 
 
 #[async_trait]
 impl MovieResolver for MovieDatabase {  
+    type Store = CachedSession;
+
     /// 'Get All Movies' Runs a prepared query with paging. This method will query all pages of the result
     /// 
     /// Returns an async iterator (stream) over all received rows
@@ -46,6 +57,7 @@ impl MovieResolver for MovieDatabase {
             .rows_or_empty()
             .into_typed::<Movie>()
             .map(|v| v.expect(""))
+            .filter(|movie| !movie.is_deleted())
             .collect();
 
         Ok(rows)
@@ -62,7 +74,39 @@ impl MovieResolver for MovieDatabase {
             .next()
             .unwrap()
             .unwrap();
-        Ok(res)
+        match res.is_deleted() { 
+            true => Err(ServiceError::NotFound),
+            false => Ok(res),
+        }
+    }
+    /// `movies_by_id` holds the rest of the primary key, which keeps this a single partition read.
+    /// Movies without an entry there yet are scanned for
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.movies_by_id"))]
+    async fn get_movie_by_id(movie_id: i64, session: &'static CachedSession) -> QueryResult<Movie> { 
+        let key = session
+            .query_prepared(GET_MOVIE_KEY, (movie_id,))
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
+            .into_typed::<MovieKey>()
+            .next()
+            .transpose()
+            .map_err(|e| ServiceError::ServerError(e.to_string()))?;
+        let res = match key { 
+            Some(key) => session.query_prepared(GET_MOVIE_BY_KEY, key).await,
+            None => session.query_prepared(GET_LEGACY_MOVIE, (movie_id,)).await,
+        };
+        let res = res
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
+            .into_typed::<Movie>()
+            .next()
+            .ok_or(ServiceError::NotFound)?
+            .map_err(|e| ServiceError::ServerError(e.to_string()))?;
+        match res.is_deleted() { 
+            true => Err(ServiceError::NotFound),
+            false => Ok(res),
+        }
     }
 }
 
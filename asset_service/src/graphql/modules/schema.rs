@@ -3,9 +3,15 @@ use async_graphql_actix_web::*;
 use chrono::NaiveDate;
 use rdkafka::producer::FutureProducer;
 use strum_macros::{Display, EnumString};
-use super::{model::{Movie, Status, BusinessData, MovieRating}, resolver::MovieDatabase};
-use crate::{graphql::{config::get_conn_from_ctx}, to_bigint, to_int, kafka};
+use super::{model::{Movie, Status, BusinessData, MovieRating}, resolver::{MovieDatabase, MovieResolver}};
+use super::credits::schema::{movie_cast, movie_crew, CreditType};
+use super::translations::schema::{localized, movie_translations};
+use super::availability::schema::{ensure_available, movie_availability, retain_available, AvailabilityWindowType};
+use super::artwork::schema::{owner_images, ImageType};
+use crate::{graphql::{config::get_store_from_ctx}, to_bigint, to_int, kafka};
 use serde::{Deserialize, Serialize};
+use common_utils::{QueryResult, artwork::{ImageFormat, ImageKind, ImageOwner, ImageSize}, events::CatalogEvent, locale::normalise_locale};
+use std::marker::PhantomData;
 
 
 
@@ -19,11 +25,16 @@ impl ProductionCompanyType {
     pub async fn company_id(&self) -> &ID { &self.company_id }
 }
 
+/// `R` defaults to the Scylla backed `MovieDatabase`
 #[derive(Default)]
-pub struct MovieQuery;
+pub struct MovieQuery<R = MovieDatabase>(PhantomData<R>);
+/// `title`, `overview` and `poster` hold the canonical values, the fields served in their place
+/// are localized in `translations::schema`. `cast` and `crew` are resolved in `credits::schema`
 #[derive(Debug, Clone, SimpleObject, Deserialize, Serialize)]
+#[graphql(complex)]
 pub struct MovieType { 
     pub movie_id: ID,
+    #[graphql(skip)]
     pub title: String,
     pub year: i32,
     pub awards: Vec<String>,
@@ -38,43 +49,129 @@ pub struct MovieType {
     pub movie_company: Vec<String>,
     pub movie_director: Vec<String>,
     pub movie_writer: Vec<String>,
+    #[graphql(skip)]
     pub overview: String,
+    #[graphql(skip)]
     pub poster: String,
     pub rated: String,
     pub rating: MovieRating,
     pub release_date: NaiveDate,
     pub runtime: i64,
     pub status: String,
+    #[graphql(deprecation = "Unsigned and never expires, use the signed URLs of `playbackInfo`")]
     pub video_file: String,
+    /// The `locale` argument of the query that returned the movie, it takes the place of the
+    /// request's own preferences
+    #[graphql(skip)]
+    #[serde(default)]
+    pub requested_locale: Option<String>,
+}
+
+#[ComplexObject]
+impl MovieType { 
+    /// In the request's locale when the movie is translated into it, otherwise the canonical title
+    async fn title(&self, ctx: &Context<'_>) -> FieldResult<String> { 
+        let title = localized(ctx, self, |f| f.title.as_ref()).await?;
+        Ok(title.map_or_else(|| self.title.clone(), |(_, title)| title))
+    }
+    async fn overview(&self, ctx: &Context<'_>) -> FieldResult<String> { 
+        let overview = localized(ctx, self, |f| f.overview.as_ref()).await?;
+        Ok(overview.map_or_else(|| self.overview.clone(), |(_, overview)| overview))
+    }
+    /// Localized artwork when there is some, e.g. a poster with a translated title on it
+    async fn poster(&self, ctx: &Context<'_>) -> FieldResult<String> { 
+        let poster = localized(ctx, self, |f| f.poster.as_ref()).await?;
+        Ok(poster.map_or_else(|| self.poster.clone(), |(_, poster)| poster))
+    }
+    /// Posters, backdrops, logos and thumbnails, only those of `type` when set. URLs are sized to
+    /// `size`, the original when it is left out, and encoded in `format` where there is a rendition in it
+    async fn images(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(name = "type")] kind: Option<ImageKind>,
+        size: Option<ImageSize>,
+        format: Option<ImageFormat>,
+    ) -> FieldResult<Vec<ImageType>> { 
+        owner_images(ctx, ImageOwner::Movie, &self.movie_id, kind, size.unwrap_or(ImageSize::Original), format).await
+    }
+    /// Only translations carry a tagline
+    async fn tagline(&self, ctx: &Context<'_>) -> FieldResult<Option<String>> { 
+        let tagline = localized(ctx, self, |f| f.tagline.as_ref()).await?;
+        Ok(tagline.map(|(_, tagline)| tagline))
+    }
+    /// Locale `title` is served in, `null` when it is the canonical one
+    async fn locale(&self, ctx: &Context<'_>) -> FieldResult<Option<String>> { 
+        let title = localized(ctx, self, |f| f.title.as_ref()).await?;
+        Ok(title.map(|(locale, _)| locale))
+    }
+    /// Every locale the movie has a translation in
+    async fn available_locales(&self, ctx: &Context<'_>) -> FieldResult<Vec<String>> { 
+        let translations = movie_translations(ctx, &self.movie_id).await?;
+        Ok(translations.into_iter().map(|f| f.locale).collect())
+    }
+    /// Licensing windows in the request's region, empty for a movie available everywhere
+    async fn availability(&self, ctx: &Context<'_>) -> FieldResult<Vec<AvailabilityWindowType>> { 
+        movie_availability(ctx, &self.movie_id).await
+    }
+    /// In billing order
+    async fn cast(&self, ctx: &Context<'_>) -> FieldResult<Vec<CreditType>> { 
+        movie_cast(ctx, &self.movie_id).await
+    }
+    /// Only the given department when set, e.g. "Directing", otherwise grouped by department
+    async fn crew(&self, ctx: &Context<'_>, department: Option<String>) -> FieldResult<Vec<CreditType>> { 
+        movie_crew(ctx, &self.movie_id, department).await
+    }
+}
+
+/// The movie as served in `locale`, `None` keeps the request's preferences
+fn in_locale(movie: &Movie, locale: Option<String>) -> MovieType { 
+    MovieType { requested_locale: locale, ..MovieType::from(movie) }
+}
+
+/// Rejects a `locale` argument that is not a language tag
+fn locale_argument(locale: Option<String>) -> FieldResult<Option<String>> { 
+    locale
+        .map(|locale| normalise_locale(&locale))
+        .transpose()
+        .map_err(|e| e.extend())
 }
 
 #[Object(extends, cache_control(max_age = 180))]
-impl MovieQuery  {
+impl<R: MovieResolver> MovieQuery<R>  {
 
     #[graphql(entity)]
     async fn get_company_by_id(&self, #[graphql(key)] company_id: ID) -> ProductionCompanyType { 
         ProductionCompanyType { company_id }
     }
 
+    /// Only the movies that can be watched in the request's region
     #[tracing::instrument(skip(self, ctx))]
     #[graphql(name = "getAllMovies")]
-    async fn get_all(&self, ctx: &Context<'_>, page_size: Option<i32>) -> FieldResult<Vec<MovieType>> { 
-        let res = Movie::get_all_movie::<MovieDatabase>(get_conn_from_ctx(ctx), page_size)
+    async fn get_all(&self, ctx: &Context<'_>, page_size: Option<i32>, locale: Option<String>) -> FieldResult<Vec<MovieType>> { 
+        let locale = locale_argument(locale)?;
+        let movies = Movie::get_all_movie::<R>(get_store_from_ctx(ctx), page_size)
             .await
-            .expect("")
+            .expect("");
+        let res = retain_available(ctx, movies)
+            .await?
             .iter()
-            .map(|g| MovieType::from(g))
+            .map(|g| in_locale(g, locale.clone()))
             .collect();
         Ok(res)
     }
+    /// `locale` overrides the `Accept-Language` and profile preferences of the request. Not
+    /// found when the movie can't be watched in the request's region
     #[graphql(name = "getMovieById")]
-    async fn get_by_movie_id(&self, ctx: &Context<'_>, title: String, id: ID) -> FieldResult<MovieType> { 
-        let movie = find_movie_internally(ctx, title, id).await.expect("");
-        Ok(MovieType::from(&movie))
+    async fn get_by_movie_id(&self, ctx: &Context<'_>, id: ID, locale: Option<String>) -> FieldResult<MovieType> { 
+        let locale = locale_argument(locale)?;
+        let movie = find_movie_internally::<R>(ctx, id).await?;
+        let movie = ensure_available(ctx, movie).await?;
+        Ok(in_locale(&movie, locale))
     }
+    /// Resolves `MovieType @key(fields: "movieId")` for the other subgraphs
     #[graphql(entity, name = "getMovieByIdEntitity")]
-    async fn get_by_movie_entity(&self, ctx: &Context<'_>, title: String, #[graphql(key)] movie_id: ID) -> FieldResult<MovieType> { 
-        let movie = find_movie_internally(ctx, title, movie_id).await.expect("");
+    async fn get_by_movie_entity(&self, ctx: &Context<'_>, #[graphql(key)] movie_id: ID) -> FieldResult<MovieType> { 
+        let movie = find_movie_internally::<R>(ctx, movie_id).await?;
         Ok(MovieType::from(&movie))
     }
 
@@ -84,7 +181,7 @@ impl MovieQuery  {
     #[tracing::instrument(skip(self, ctx))]
     #[graphql(name = "ForcebatchIndexIntoElasticsearch")]
     async fn force_batch_indexing_into_es(&self, ctx: &Context<'_>, page_size: Option<i32>) -> FieldResult<Vec<MovieType>> { 
-        let res = Movie::get_all_movie::<MovieDatabase>(get_conn_from_ctx(ctx), page_size)
+        let res = Movie::get_all_movie::<R>(get_store_from_ctx(ctx), page_size)
             .await
             .expect("Unable to Get all the items inside the database");
         let response = res
@@ -93,35 +190,37 @@ impl MovieQuery  {
             .collect();
 
         //  Batching based on Ranges 
-        for i in res { 
-            // Publish new message to a specific topic 
-            // This goes into Kafka to be sent to the Elastic Search where Movies can be indexed
-            log::info!("🚢🚢 Received Client Request to Sync Data back into Elasticsearch: {:#?}", i);
-            let message = serde_json::to_string(&i).expect("Unable to serialize movie");
-            kafka::send_message(&message).await;
-        }
+        // Publish new message to a specific topic 
+        // This goes into Kafka to be sent to the Elastic Search where Movies can be indexed
+        log::info!("🚢🚢 Received Client Request to Sync {} movies back into Elasticsearch", res.len());
+        let events = res
+            .into_iter()
+            .map(CatalogEvent::MovieReindexRequested)
+            .collect();
+        kafka::send_events(events)
+            .await
+            .into_iter()
+            .collect::<QueryResult<Vec<_>>>()
+            .map_err(|e| e.extend())?;
         Ok(response)
     }
     /// Our search indexing platform is more reliable if the search service can call the movie to be indexed
     /// the incremental indexing pseed helps refresh data faster and appears more promptly in our consumer applications 
     #[tracing::instrument(skip(self, ctx))]
     #[graphql(name = "ForceIndexMovieByID")]
-    async fn force_index_movie_by_id(&self, ctx: &Context<'_>, movie_id: ID, movie_name: String) -> FieldResult<MovieType> { 
-        let movie = find_movie_internally(ctx, movie_name, movie_id)
-            .await
-            .expect("");
+    async fn force_index_movie_by_id(&self, ctx: &Context<'_>, movie_id: ID) -> FieldResult<MovieType> { 
+        let movie = find_movie_internally::<R>(ctx, movie_id).await?;
         log::info!("🚢🚢 Received Client Request to Sync Data back into Elasticsearch: {:#?}", movie);
-        let message = serde_json::to_string(&movie).expect("Unable to serialize movie");
-        kafka::send_message(&message).await;
+        kafka::send_event(CatalogEvent::MovieReindexRequested(movie.clone()))
+            .await
+            .map_err(|e| e.extend())?;
         Ok(MovieType::from(&movie))
     }
 
 }
 
-async fn find_movie_internally(ctx: &Context<'_>, title: String, id: ID) -> FieldResult<Movie> {
-    let res = Movie::get_movie_id_title::<MovieDatabase>(
-        title, to_bigint(id), get_conn_from_ctx(ctx))
+async fn find_movie_internally<R: MovieResolver>(ctx: &Context<'_>, id: ID) -> FieldResult<Movie> {
+    Movie::get_movie_by_id::<R>(to_bigint(id), get_store_from_ctx(ctx))
         .await
-        .expect("");
-    Ok(res)
+        .map_err(|e| e.extend())
 } 
@@ -0,0 +1,154 @@
+use chrono::naive::NaiveDate;
+use common_utils::QueryResult;
+use scylla::macros::FromRow;
+use serde::{Deserialize, Serialize};
+use super::resolver::SeriesResolver;
+use super::schema::{SeriesType, SeasonType, EpisodeType};
+
+// Rows of `series`, `seasons` and `episodes`, key columns first and the rest alphabetical
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize)]
+#[serde(rename_all(serialize = "snake_case", deserialize = "camelCase"))]
+pub struct Series { 
+    pub series_id: i64,
+    pub created_by: Vec<String>,
+    pub first_air_date: NaiveDate,
+    pub genres: Vec<String>,
+    pub homepage: String,
+    pub languages: Vec<String>,
+    pub last_air_date: Option<NaiveDate>,
+    pub overview: String,
+    pub poster: String,
+    pub rated: String,
+    pub status: String,
+    pub title: String,
+}
+
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize)]
+#[serde(rename_all(serialize = "snake_case", deserialize = "camelCase"))]
+pub struct Season { 
+    pub series_id: i64,
+    pub season_number: i32,
+    pub air_date: Option<NaiveDate>,
+    pub name: String,
+    pub overview: String,
+    pub poster: String,
+    pub season_id: i64,
+}
+
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize)]
+#[serde(rename_all(serialize = "snake_case", deserialize = "camelCase"))]
+pub struct Episode { 
+    pub series_id: i64,
+    pub season_number: i32,
+    pub episode_number: i32,
+    pub air_date: Option<NaiveDate>,
+    pub episode_id: i64,
+    pub overview: String,
+    pub runtime: i64,
+    pub still_path: String,
+    pub title: String,
+    pub video_file: String,
+}
+
+impl SeriesType { 
+    pub fn new(f: &Series, seasons: &[Season]) -> Self { 
+        Self { 
+            series_id: f.series_id.into(),
+            title: f.title.clone(),
+            overview: f.overview.clone(),
+            poster: f.poster.clone(),
+            homepage: f.homepage.clone(),
+            rated: f.rated.clone(),
+            status: f.status.clone(),
+            first_air_date: f.first_air_date,
+            last_air_date: f.last_air_date,
+            genres: f.genres.clone(),
+            languages: f.languages.clone(),
+            created_by: f.created_by.clone(),
+            seasons: seasons.iter().map(|season| SeasonType::new(season, None)).collect(),
+        }
+    }
+}
+
+impl SeasonType { 
+    pub fn new(f: &Season, episodes: Option<&[Episode]>) -> Self { 
+        Self { 
+            season_id: f.season_id.into(),
+            series_id: f.series_id.into(),
+            season_number: f.season_number,
+            name: f.name.clone(),
+            overview: f.overview.clone(),
+            poster: f.poster.clone(),
+            air_date: f.air_date,
+            episodes: episodes.map(|episodes| episodes.iter().map(EpisodeType::from).collect()),
+        }
+    }
+}
+
+impl From<&Episode> for EpisodeType { 
+    fn from(f: &Episode) -> Self {
+        Self { 
+            episode_id: f.episode_id.into(),
+            series_id: f.series_id.into(),
+            season_number: f.season_number,
+            episode_number: f.episode_number,
+            title: f.title.clone(),
+            overview: f.overview.clone(),
+            air_date: f.air_date,
+            runtime: f.runtime,
+            still_path: f.still_path.clone(),
+            video_file: f.video_file.clone(),
+        }
+    }
+}
+
+impl Series { 
+    #[tracing::instrument(skip(session))]
+    pub async fn get_series<SeriesDatabase: SeriesResolver>(series_id: i64, session: &'static SeriesDatabase::Store) -> QueryResult<Series> {
+        SeriesDatabase::get_series(series_id, session).await
+    }
+}
+
+impl Season { 
+    #[tracing::instrument(skip(session))]
+    pub async fn get_seasons<SeriesDatabase: SeriesResolver>(series_id: i64, session: &'static SeriesDatabase::Store) -> QueryResult<Vec<Season>> {
+        SeriesDatabase::get_seasons(series_id, session).await
+    }
+}
+
+impl Episode { 
+    #[tracing::instrument(skip(session))]
+    pub async fn get_episodes<SeriesDatabase: SeriesResolver>(series_id: i64, season_number: i32, session: &'static SeriesDatabase::Store) -> QueryResult<Vec<Episode>> {
+        SeriesDatabase::get_episodes(series_id, season_number, session).await
+    }
+    /// The episode that follows `episode_number`, rolling over to the first episode of the next
+    /// season that has any. `None` once the last aired episode of the series is reached
+    #[tracing::instrument(skip(session))]
+    pub async fn next_episode<SeriesDatabase: SeriesResolver>(series_id: i64, season_number: i32, episode_number: i32, session: &'static SeriesDatabase::Store) -> QueryResult<Option<Episode>> {
+        let next_in_season = SeriesDatabase::get_episodes(series_id, season_number, session)
+            .await?
+            .into_iter()
+            .filter(|episode| episode.episode_number > episode_number)
+            .min_by_key(|episode| episode.episode_number);
+        if next_in_season.is_some() { 
+            return Ok(next_in_season)
+        }
+        let mut later_seasons: Vec<i32> = SeriesDatabase::get_seasons(series_id, session)
+            .await?
+            .into_iter()
+            .map(|season| season.season_number)
+            .filter(|number| *number > season_number)
+            .collect();
+        later_seasons.sort_unstable();
+        for number in later_seasons { 
+            let first = SeriesDatabase::get_episodes(series_id, number, session)
+                .await?
+                .into_iter()
+                .min_by_key(|episode| episode.episode_number);
+            if first.is_some() { 
+                return Ok(first)
+            }
+        }
+        Ok(None)
+    }
+}
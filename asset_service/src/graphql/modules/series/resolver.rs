@@ -0,0 +1,62 @@
+use async_trait::async_trait;
+use common_utils::{QueryResult, error::ServiceError};
+use scylla::IntoTypedRows;
+use crate::db::CachedSession;
+use super::model::{Series, Season, Episode};
+
+/// Read side of TV series, written by the ingestion service
+#[async_trait]
+pub trait SeriesResolver: Send + Sync + 'static { 
+    type Store: Send + Sync + 'static;
+    async fn get_series(series_id: i64, session: &'static Self::Store) -> QueryResult<Series>;
+    async fn get_seasons(series_id: i64, session: &'static Self::Store) -> QueryResult<Vec<Season>>;
+    async fn get_episodes(series_id: i64, season_number: i32, session: &'static Self::Store) -> QueryResult<Vec<Episode>>;
+}
+
+#[derive(Default)]
+pub struct SeriesDatabase;
+
+static GET_SERIES: &str = "SELECT * FROM movie_keyspace.series WHERE series_id = ?;";
+static GET_SEASONS: &str = "SELECT * FROM movie_keyspace.seasons WHERE series_id = ?;";
+static GET_EPISODES: &str = "SELECT * FROM movie_keyspace.episodes WHERE series_id = ? AND season_number = ?;";
+
+#[async_trait]
+impl SeriesResolver for SeriesDatabase { 
+    type Store = CachedSession;
+
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.series"))]
+    async fn get_series(series_id: i64, session: &'static CachedSession) -> QueryResult<Series> { 
+        session
+            .query_prepared(GET_SERIES, (series_id,))
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
+            .into_typed::<Series>()
+            .next()
+            .ok_or(ServiceError::NotFound)?
+            .map_err(|e| ServiceError::ServerError(e.to_string()))
+    }
+    /// Seasons come back in season order, the clustering order of the table
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.seasons"))]
+    async fn get_seasons(series_id: i64, session: &'static CachedSession) -> QueryResult<Vec<Season>> { 
+        session
+            .query_prepared(GET_SEASONS, (series_id,))
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
+            .into_typed::<Season>()
+            .map(|row| row.map_err(|e| ServiceError::ServerError(e.to_string())))
+            .collect()
+    }
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.episodes"))]
+    async fn get_episodes(series_id: i64, season_number: i32, session: &'static CachedSession) -> QueryResult<Vec<Episode>> { 
+        session
+            .query_prepared(GET_EPISODES, (series_id, season_number))
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
+            .into_typed::<Episode>()
+            .map(|row| row.map_err(|e| ServiceError::ServerError(e.to_string())))
+            .collect()
+    }
+}
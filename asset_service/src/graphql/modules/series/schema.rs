@@ -0,0 +1,101 @@
+use async_graphql::*;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+use common_utils::error::ServiceError;
+use crate::{graphql::config::get_store_from_ctx, to_bigint};
+use super::{model::{Series, Season, Episode}, resolver::{SeriesDatabase, SeriesResolver}};
+
+/// TV series queries, served from `R`
+#[derive(Default)]
+pub struct SeriesQuery<R = SeriesDatabase>(PhantomData<R>);
+
+#[derive(Debug, Clone, SimpleObject, Deserialize, Serialize)]
+pub struct SeriesType { 
+    pub series_id: ID,
+    pub title: String,
+    pub overview: String,
+    pub poster: String,
+    pub homepage: String,
+    pub rated: String,
+    pub status: String,
+    pub first_air_date: NaiveDate,
+    pub last_air_date: Option<NaiveDate>,
+    pub genres: Vec<String>,
+    pub languages: Vec<String>,
+    pub created_by: Vec<String>,
+    pub seasons: Vec<SeasonType>,
+}
+
+#[derive(Debug, Clone, SimpleObject, Deserialize, Serialize)]
+pub struct SeasonType { 
+    pub season_id: ID,
+    pub series_id: ID,
+    pub season_number: i32,
+    pub name: String,
+    pub overview: String,
+    pub poster: String,
+    pub air_date: Option<NaiveDate>,
+    /// Only loaded by `getSeason`, `null` in the season list of `getSeries`
+    pub episodes: Option<Vec<EpisodeType>>,
+}
+
+#[derive(Debug, Clone, SimpleObject, Deserialize, Serialize)]
+pub struct EpisodeType { 
+    pub episode_id: ID,
+    pub series_id: ID,
+    pub season_number: i32,
+    pub episode_number: i32,
+    pub title: String,
+    pub overview: String,
+    pub air_date: Option<NaiveDate>,
+    pub runtime: i64,
+    pub still_path: String,
+    #[graphql(deprecation = "Unsigned and never expires, use the signed URLs of `episodePlaybackInfo`")]
+    pub video_file: String,
+}
+
+#[Object(extends, cache_control(max_age = 180))]
+impl<R: SeriesResolver> SeriesQuery<R> { 
+    /// A series with its list of seasons
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "getSeries")]
+    async fn get_series(&self, ctx: &Context<'_>, series_id: ID) -> FieldResult<SeriesType> { 
+        let session = get_store_from_ctx(ctx);
+        let series_id = to_bigint(series_id);
+        let series = Series::get_series::<R>(series_id, session)
+            .await
+            .map_err(|e| e.extend())?;
+        let seasons = Season::get_seasons::<R>(series_id, session)
+            .await
+            .map_err(|e| e.extend())?;
+        Ok(SeriesType::new(&series, &seasons))
+    }
+    /// A single season with its episodes in airing order
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "getSeason")]
+    async fn get_season(&self, ctx: &Context<'_>, series_id: ID, season_number: i32) -> FieldResult<SeasonType> { 
+        let session = get_store_from_ctx(ctx);
+        let series_id = to_bigint(series_id);
+        let season = Season::get_seasons::<R>(series_id, session)
+            .await
+            .map_err(|e| e.extend())?
+            .into_iter()
+            .find(|season| season.season_number == season_number)
+            .ok_or_else(|| ServiceError::NotFound.extend())?;
+        let mut episodes = Episode::get_episodes::<R>(series_id, season_number, session)
+            .await
+            .map_err(|e| e.extend())?;
+        episodes.sort_by_key(|episode| episode.episode_number);
+        Ok(SeasonType::new(&season, Some(&episodes)))
+    }
+    /// The episode to play after the given one, `null` after the last episode of the series
+    #[tracing::instrument(skip(self, ctx))]
+    #[graphql(name = "nextEpisode")]
+    async fn next_episode(&self, ctx: &Context<'_>, series_id: ID, season_number: i32, episode_number: i32) -> FieldResult<Option<EpisodeType>> { 
+        let next = Episode::next_episode::<R>(to_bigint(series_id), season_number, episode_number, get_store_from_ctx(ctx))
+            .await
+            .map_err(|e| e.extend())?;
+        Ok(next.as_ref().map(EpisodeType::from))
+    }
+}
@@ -0,0 +1,70 @@
+use common_utils::{QueryResult, locale::{DEFAULT_LOCALE, fallback_chain, language_of, normalise_locale, parse_accept_language}};
+use scylla::macros::FromRow;
+use serde::{Deserialize, Serialize};
+use super::resolver::TranslationResolver;
+
+// Columns after the primary key of `movie_translations` are in alphabetical order, the order `SELECT *` returns them in
+/// A movie's title, overview, tagline and poster in one locale, written by the ingestion service
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize)]
+pub struct MovieTranslation { 
+    pub movie_id: i64,
+    pub locale: String,
+    pub overview: Option<String>,
+    pub poster: Option<String>,
+    pub tagline: Option<String>,
+    pub title: Option<String>,
+}
+
+impl MovieTranslation { 
+    pub async fn get_translations_by_movies<TranslationDatabase: TranslationResolver>(movie_ids: Vec<i64>, session: &'static TranslationDatabase::Store) -> QueryResult<Vec<MovieTranslation>> {
+        TranslationDatabase::get_translations_by_movies(movie_ids, session).await
+    }
+}
+
+/// The locales a request prefers, most preferred first. Attached to every request by the `/graphql` handler
+#[derive(Debug, Clone, Default)]
+pub struct RequestLocale { 
+    pub preferred: Vec<String>,
+}
+
+impl RequestLocale { 
+    /// The profile's preferred locale, forwarded by the gateway as `x-preferred-locale`, comes
+    /// before whatever the browser sent in `Accept-Language`. Tags that don't parse are ignored
+    pub fn new(profile_locale: Option<&str>, accept_language: Option<&str>) -> Self { 
+        let mut preferred = profile_locale
+            .and_then(|locale| normalise_locale(locale).ok())
+            .into_iter()
+            .collect::<Vec<_>>();
+        preferred.extend(accept_language.map(parse_accept_language).unwrap_or_default());
+        Self { preferred }
+    }
+    /// `locale` replaces the request's preferences when a query asks for one explicitly
+    pub fn chain(&self, locale: Option<&String>) -> Vec<String> { 
+        match locale { 
+            Some(locale) => fallback_chain(std::slice::from_ref(locale)),
+            None => fallback_chain(&self.preferred),
+        }
+    }
+}
+
+/// Walks `chain` and returns the first translation with `field` set, and the locale it is in.
+/// A bare language also accepts a regional variant, `pt` is served `pt-PT` when there is no
+/// plain `pt`. Reaching `DEFAULT_LOCALE` stops the walk, the canonical movie is already in it
+pub fn pick<F>(translations: &[MovieTranslation], chain: &[String], field: F) -> Option<(String, String)>
+where
+    F: Fn(&MovieTranslation) -> Option<&String>,
+{
+    let translated = || translations.iter().filter(|translation| field(translation).is_some());
+    for locale in chain { 
+        if locale == DEFAULT_LOCALE { 
+            return None
+        }
+        let found = translated()
+            .find(|translation| translation.locale == *locale)
+            .or_else(|| translated().find(|translation| !locale.contains('-') && language_of(&translation.locale) == locale.as_str()));
+        if let Some(translation) = found { 
+            return field(translation).map(|value| (translation.locale.clone(), value.clone()))
+        }
+    }
+    None
+}
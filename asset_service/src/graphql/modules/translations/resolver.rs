@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+use async_graphql::dataloader::Loader;
+use async_trait::async_trait;
+use common_utils::{QueryResult, error::ServiceError};
+use futures::future::BoxFuture;
+use scylla::IntoTypedRows;
+use crate::db::CachedSession;
+use super::model::MovieTranslation;
+
+/// Read side of movie translations, written by the ingestion service
+#[async_trait]
+pub trait TranslationResolver: Send + Sync + 'static { 
+    type Store: Send + Sync + 'static;
+    /// Translations of every movie in `movie_ids`, each movie's in locale order
+    async fn get_translations_by_movies(movie_ids: Vec<i64>, session: &'static Self::Store) -> QueryResult<Vec<MovieTranslation>>;
+}
+
+#[derive(Default)]
+pub struct TranslationDatabase;
+
+static GET_TRANSLATIONS_BY_MOVIES: &str = "SELECT * FROM movie_keyspace.movie_translations WHERE movie_id IN ?;";
+
+#[async_trait]
+impl TranslationResolver for TranslationDatabase { 
+    type Store = CachedSession;
+
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.movie_translations"))]
+    async fn get_translations_by_movies(movie_ids: Vec<i64>, session: &'static CachedSession) -> QueryResult<Vec<MovieTranslation>> { 
+        session
+            .query_prepared(GET_TRANSLATIONS_BY_MOVIES, (movie_ids,))
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
+            .into_typed::<MovieTranslation>()
+            .map(|row| row.map_err(|e| ServiceError::ServerError(e.to_string())))
+            .collect()
+    }
+}
+
+type LoadFn = Box<dyn Fn(Vec<i64>) -> BoxFuture<'static, QueryResult<Vec<MovieTranslation>>> + Send + Sync>;
+
+/// Loads the translations of every movie in a response with one query, the same way `CreditLoader` does credits
+pub struct TranslationLoader { 
+    load: LoadFn,
+}
+
+impl TranslationLoader { 
+    pub fn new<R: TranslationResolver>(session: &'static R::Store) -> Self { 
+        Self { load: Box::new(move |movie_ids| R::get_translations_by_movies(movie_ids, session)) }
+    }
+}
+
+#[async_trait]
+impl Loader<i64> for TranslationLoader { 
+    type Value = Vec<MovieTranslation>;
+    type Error = ServiceError;
+
+    async fn load(&self, keys: &[i64]) -> Result<HashMap<i64, Self::Value>, Self::Error> { 
+        let mut translations = keys
+            .iter()
+            .map(|movie_id| (*movie_id, Vec::new()))
+            .collect::<HashMap<_, _>>();
+        for translation in (self.load)(keys.to_vec()).await? { 
+            translations.entry(translation.movie_id).or_default().push(translation);
+        }
+        translations.values_mut().for_each(|translations| translations.sort_by(|a, b| a.locale.cmp(&b.locale)));
+        Ok(translations)
+    }
+}
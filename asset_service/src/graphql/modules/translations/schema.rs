@@ -0,0 +1,33 @@
+use async_graphql::{*, dataloader::DataLoader};
+use common_utils::locale::DEFAULT_LOCALE;
+use crate::to_bigint;
+use super::super::schema::MovieType;
+use super::{model::{pick, MovieTranslation, RequestLocale}, resolver::TranslationLoader};
+
+/// Translations of the movie in locale order, batched across the whole response
+pub(crate) async fn movie_translations(ctx: &Context<'_>, movie_id: &ID) -> FieldResult<Vec<MovieTranslation>> { 
+    let translations = ctx.data::<DataLoader<TranslationLoader>>()?
+        .load_one(to_bigint(movie_id.clone()))
+        .await
+        .map_err(|e| e.extend())?;
+    Ok(translations.unwrap_or_default())
+}
+
+/// `field` of `movie` in the first locale of its fallback chain that has it, with that locale.
+/// `None` means the canonical value should be served. Schemas built without a `RequestLocale`,
+/// like the ones in tests, fall back straight to the default locale
+pub(crate) async fn localized<F>(ctx: &Context<'_>, movie: &MovieType, field: F) -> FieldResult<Option<(String, String)>>
+where
+    F: Fn(&MovieTranslation) -> Option<&String>,
+{
+    let chain = ctx.data_opt::<RequestLocale>()
+        .cloned()
+        .unwrap_or_default()
+        .chain(movie.requested_locale.as_ref());
+    // Nothing to read when the canonical movie is already in the preferred locale
+    if chain.first().map(String::as_str) == Some(DEFAULT_LOCALE) { 
+        return Ok(None)
+    }
+    let translations = movie_translations(ctx, &movie.movie_id).await?;
+    Ok(pick(&translations, &chain, field))
+}
@@ -1,9 +1,12 @@
 use async_graphql::{EmptySubscription, 
     MergedObject, Schema, SchemaBuilder, EmptyMutation};
 use super::modules::schema::{MovieQuery};
+use super::modules::series::schema::SeriesQuery;
+use super::modules::credits::schema::CreditQuery;
+use super::modules::playback::schema::PlaybackQuery;
 
 #[derive(MergedObject, Default)]
-pub struct Query(MovieQuery);
+pub struct Query(MovieQuery, SeriesQuery, CreditQuery, PlaybackQuery);
 
 #[derive(MergedObject, Default)]
 pub struct Mutation;
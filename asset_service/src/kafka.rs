@@ -1,48 +1,46 @@
-use std::sync::Mutex;
-use std::time::Duration;
-
-use futures::Future;
 use lazy_static::lazy_static;
 use rdkafka::config::RDKafkaLogLevel;
 use rdkafka::consumer::{Consumer, StreamConsumer};
-use rdkafka::error::KafkaError;
-use rdkafka::producer::{FutureProducer, FutureRecord, DeliveryFuture};
-use rdkafka::util::Timeout;
-use rdkafka::{ClientConfig, ClientContext};
+use rdkafka::producer::FutureProducer;
+use rdkafka::ClientConfig;
 use once_cell::sync::OnceCell;
+use common_utils::{QueryResult, events::Event, kafka::{Delivery, EventProducer}};
 
 lazy_static! {
     static ref KAFKA_BROKER: String = std::env::var("KAFKA_BROKER").expect("Can't read Kafka broker address");
     static ref KAFKA_TOPIC: String = std::env::var("KAFKA_TOPIC").expect("Can't read Kafka topic name");
-    static ref MESSAGE_KEY: String = std::env::var("MESSAGE_KEY").expect("Unable to get a valid message key");
+    /// Setting a transactional id turns every batch of events into a single Kafka transaction
+    static ref KAFKA_TRANSACTIONAL_ID: Option<String> = std::env::var("KAFKA_TRANSACTIONAL_ID").ok();
     pub static ref REINDEX_TO_ELASTIC_SEARCH: bool = std::env::var("REINDEX_DATA").expect("Unable to read REINDEX_TO_ELASTIC_SEARCH").parse().unwrap();
 }
 
-
 pub static KAFKACONN: OnceCell<KafkaProvider> = OnceCell::new();
 
 #[inline]
 pub(crate) fn kafka_producer() -> &'static KafkaProvider { 
     KAFKACONN.get().expect("Missing Session for Kafka")
 }
-pub struct KafkaProvider(pub FutureProducer);
+pub struct KafkaProvider(pub EventProducer);
 
-// Create the `FutureProducer` to produce asynchronously.
+// Create the `EventProducer` to produce asynchronously.
 #[tracing::instrument(level = "debug")]
 pub fn create_producer() -> FutureProducer {
     log::info!("🚧🚧 Running Kafka Producer at {}", KAFKA_BROKER.as_str());
 
-    let producer: FutureProducer = ClientConfig::new()
-        .set("bootstrap.servers", KAFKA_BROKER.as_str())
-        .set("message.timeout.ms", "5000")
-        .create()
+    let producer = EventProducer::new(
+        env!("CARGO_PKG_NAME"),
+        KAFKA_BROKER.as_str(),
+        KAFKA_TOPIC.as_str(),
+        KAFKA_TRANSACTIONAL_ID.as_deref()
+    )
         .expect("Producer creation failed");
-    let _ = KAFKACONN.set(KafkaProvider::from(producer.clone()));
-    producer
+    let client = producer.producer().clone();
+    let _ = KAFKACONN.set(KafkaProvider::from(producer));
+    client
 }
 
-impl From<FutureProducer> for KafkaProvider { 
-    fn from(f: FutureProducer) -> Self {
+impl From<EventProducer> for KafkaProvider { 
+    fn from(f: EventProducer) -> Self {
         Self(f)
     }
 }
@@ -72,32 +70,12 @@ pub fn create_consumer(group_id: String) -> StreamConsumer {
 //     *counter += 1;
 //     format!("graphql-group-{}", *counter)
 // }
-#[tracing::instrument(level = "debug")]
-pub async fn send_message(message: &str) {
-    let futures: Vec<_> = (0..5).map(|_| async move { 
-        let delivery_status = kafka_producer()
-            .0
-            .send(
-                FutureRecord::to(&KAFKA_TOPIC)
-                .payload(message)
-                .key(MESSAGE_KEY.as_str()),
-                Timeout::After(Duration::from_secs(0)),
-            )
-            .await;
-            log::info!("Delivery Status {:?} for {:#?}", delivery_status, message);
-            delivery_status
-    }).collect();    
+/// Publishes a single event, keyed by its partition key
+pub async fn send_event<E: Event>(event: E) -> QueryResult<Delivery> {
+    kafka_producer().0.send_event(event).await
+}
 
-    // This loop will wait until all delivery statuses have been received.
-    for future in futures {
-        let delivery_status = future.await;
-        match delivery_status {
-            Ok((a, b)) => {
-                log::info!("👏👏 Success message is sent! {}, {}", a, b);
-            }
-            Err((e, _)) => {
-                log::info!("❌❌ Something went wrong: error {}", e);
-            }
-        }
-    }
-}
\ No newline at end of file
+/// Publishes a batch of events and returns the delivery report of each one, see `EventProducer::send_events`
+pub async fn send_events<E: Event>(events: Vec<E>) -> Vec<QueryResult<Delivery>> {
+    kafka_producer().0.send_events(events).await
+}
@@ -7,6 +7,7 @@ extern crate serde_derive;
 
 pub mod graphql;
 pub mod server;
+pub mod health;
 pub mod db;
 pub mod telemetry;
 pub mod kafka;
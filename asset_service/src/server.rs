@@ -6,6 +6,10 @@ use crate::db::{establish_connection, session, DBCONN};
 use crate::kafka::{create_producer};
 use crate::telemetry::init_telemetry;
 use tracing_actix_web::TracingLogger;
+use common_utils::health::configure_health;
+use crate::health::readiness;
+use crate::graphql::modules::playback::routes::configure_manifests;
+use common_utils::signing::UrlSigner;
 use std::fs::File;
 use std::io::Write;
 
@@ -30,6 +34,7 @@ pub async fn new_server(port: u32) -> std::io::Result<()> {
     //     .expect("Error Received from Batch Indexing Kafka");
     //  Automate writing new subgraphs
     let schema = web::Data::new(create_schema(db_pool));
+    let signer = web::Data::new(UrlSigner::from_env());
     let app_name = format!("{}.graphql", env!("CARGO_PKG_NAME"));
     let mut subgraph = File::create(app_name.clone())
         .expect(format!("Unable to create a subgraph file for {}", app_name.clone().as_str()).as_str())
@@ -54,7 +59,10 @@ pub async fn new_server(port: u32) -> std::io::Result<()> {
         App::new()
             .app_data(kafka_producer.clone())
             .app_data(schema.clone())
+            .app_data(signer.clone())
             .configure(configure_service)
+            .configure(configure_health(readiness()))
+            .configure(configure_manifests)
             .wrap(Cors::permissive())
             .wrap(Logger::default())
             // .wrap(TracingLogger::default())
@@ -0,0 +1,122 @@
+//! Images of titles and people, shared by the ingestion service that manages them and the
+//! catalogue that serves them in the size a client asks for
+use std::{collections::HashMap, env::var};
+use async_graphql::Enum;
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumString};
+
+lazy_static! {
+    /// Where image paths imported from TMDB are served from, sized by a path segment like `w500`
+    static ref IMAGE_BASE_URL: String = var("IMAGE_BASE_URL").unwrap_or_else(|_| "https://image.tmdb.org/t/p".to_string());
+}
+
+/// What an image is used as
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumString, Serialize, Deserialize)]
+#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ImageKind {
+    Poster,
+    Backdrop,
+    /// Title treatment, usually a transparent PNG
+    Logo,
+    /// Still shown in rows and while browsing
+    Thumbnail,
+    /// Headshot of a person
+    Profile,
+}
+
+/// Width an image is served at, each kind has its own set of widths
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumString, Serialize, Deserialize)]
+#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ImageSize {
+    Small,
+    Medium,
+    Large,
+    /// As uploaded
+    Original,
+}
+
+/// What an image belongs to
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumString, Serialize, Deserialize)]
+#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ImageOwner {
+    Movie,
+    Person,
+}
+
+/// Encoding of a rendition of an uploaded image
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumString, Serialize, Deserialize)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ImageFormat {
+    Jpeg,
+    /// Kept for images with transparency, such as logos
+    Png,
+    Webp,
+    Avif,
+}
+
+impl ImageFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ImageFormat::Jpeg => "image/jpeg",
+            ImageFormat::Png => "image/png",
+            ImageFormat::Webp => "image/webp",
+            ImageFormat::Avif => "image/avif",
+        }
+    }
+}
+
+/// Key of a rendition in an image's `variants`, e.g. `w500.webp`
+pub fn variant_key(segment: &str, format: ImageFormat) -> String {
+    format!("{}.{}", segment, format)
+}
+
+impl ImageKind {
+    /// The TMDB size segment of `size`, e.g. `w500` for a medium poster
+    pub fn size_segment(self, size: ImageSize) -> &'static str {
+        use ImageKind::*;
+        use ImageSize::*;
+        match (self, size) {
+            (_, Original) => "original",
+            (Poster, Small) | (Logo, Small) | (Thumbnail, Small) => "w92",
+            (Poster, Medium) | (Logo, Medium) | (Thumbnail, Medium) | (Profile, Medium) => "w185",
+            (Poster, Large) | (Logo, Large) => "w500",
+            (Thumbnail, Large) | (Backdrop, Small) => "w300",
+            (Backdrop, Medium) => "w780",
+            (Backdrop, Large) => "w1280",
+            (Profile, Small) => "w45",
+            (Profile, Large) => "h632",
+        }
+    }
+}
+
+/// Width over height, rounded to three decimals the way TMDB reports it. `0` when either is unknown
+pub fn aspect_ratio(width: i32, height: i32) -> f32 {
+    match width > 0 && height > 0 {
+        true => (width as f32 / height as f32 * 1000.0).round() / 1000.0,
+        false => 0.0,
+    }
+}
+
+/// URL of the image at `size`. Relative paths are TMDB's and are sized by the image server,
+/// absolute URLs are served as they are
+pub fn sized_url(file_path: &str, kind: ImageKind, size: ImageSize) -> String {
+    if file_path.starts_with("http://") || file_path.starts_with("https://") {
+        return file_path.to_string()
+    }
+    format!("{}/{}/{}", IMAGE_BASE_URL.as_str(), kind.size_segment(size), file_path.trim_start_matches('/'))
+}
+
+/// URL of the rendition of an uploaded image at `size`, in `format` when it was encoded in it and
+/// otherwise in the format the image was stored in. `None` for images without renditions
+pub fn variant_url(variants: &HashMap<String, String>, kind: ImageKind, size: ImageSize, format: Option<ImageFormat>) -> Option<String> {
+    let segment = kind.size_segment(size);
+    format
+        .into_iter()
+        .chain([ImageFormat::Jpeg, ImageFormat::Png])
+        .find_map(|format| variants.get(&variant_key(segment, format)))
+        .cloned()
+}
@@ -0,0 +1,90 @@
+//! Licensing windows of titles, shared by the service that schedules them and the ones that
+//! enforce them: the catalogue, search and the recommender
+use actix_web::HttpRequest;
+use async_graphql::Enum;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumString};
+use crate::{decode_token, error::ServiceError, QueryResult};
+
+/// Territory of a window that is open everywhere
+pub const WORLDWIDE: &str = "WW";
+
+/// How a title can be watched while its window is open
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumString, Serialize, Deserialize)]
+#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Offering {
+    Subscription,
+    Rent,
+    Buy,
+    Free,
+}
+
+/// ISO 3166-1 alpha-2 code in uppercase, e.g. `gb` becomes `GB`. `WW` stands for every territory
+pub fn normalise_territory(territory: &str) -> QueryResult<String> {
+    let territory = territory.trim();
+    match territory.len() == 2 && territory.chars().all(|c| c.is_ascii_alphabetic()) {
+        true => Ok(territory.to_ascii_uppercase()),
+        false => Err(ServiceError::BadRequest(format!("`{}` is not a territory code like GB, or WW for worldwide", territory))),
+    }
+}
+
+/// Epoch millis, the unit windows are stored in
+pub fn now_millis() -> i64 {
+    Utc::now().timestamp_millis()
+}
+
+/// A window opens at `starts_at` and closes at `ends_at`, either end left open is unbounded
+pub fn is_open(starts_at: Option<i64>, ends_at: Option<i64>, now: i64) -> bool {
+    starts_at.map_or(true, |starts_at| starts_at <= now) && ends_at.map_or(true, |ends_at| now < ends_at)
+}
+
+/// A caller whose region is unknown only sees worldwide windows
+pub fn covers(territory: &str, region: Option<&str>) -> bool {
+    territory == WORLDWIDE || Some(territory) == region
+}
+
+/// Whether a title with these windows, as `(territory, starts_at, ends_at)`, can be watched in
+/// `region` at `now`. A title without any window predates licensing and is available everywhere
+pub fn is_available<'a, I>(windows: I, region: Option<&str>, now: i64) -> bool
+where
+    I: IntoIterator<Item = (&'a str, Option<i64>, Option<i64>)>,
+{
+    let mut windows = windows.into_iter().peekable();
+    if windows.peek().is_none() {
+        return true
+    }
+    windows.any(|(territory, starts_at, ends_at)| covers(territory, region) && is_open(starts_at, ends_at, now))
+}
+
+fn header<'r>(req: &'r HttpRequest, name: &str) -> Option<&'r str> {
+    req.headers().get(name).and_then(|value| value.to_str().ok())
+}
+
+/// The region a request is served for, attached to it by each service's `/graphql` handler.
+/// `None` when the caller's region is unknown, see `covers`
+#[derive(Debug, Clone, Default)]
+pub struct RequestRegion {
+    pub region: Option<String>,
+}
+
+impl RequestRegion {
+    /// The `region` claim of the verified bearer token. Headers are never trusted for this, any
+    /// caller could send one naming a territory they aren't licensed in. Codes that don't parse are ignored
+    pub fn new(req: &HttpRequest) -> Self {
+        let region = header(req, "Authorization")
+            .and_then(|value| value.strip_prefix("Bearer"))
+            .and_then(|token| decode_token(token.trim()).ok())
+            .and_then(|token| token.claims.region)
+            .and_then(|region| normalise_territory(&region).ok());
+        Self { region }
+    }
+    /// `is_available` for this region, right now
+    pub fn allows<'a, I>(&self, windows: I) -> bool
+    where
+        I: IntoIterator<Item = (&'a str, Option<i64>, Option<i64>)>,
+    {
+        is_available(windows, self.region.as_deref(), now_millis())
+    }
+}
@@ -1,10 +1,12 @@
 use actix_web::{error::ResponseError, HttpResponse};
 use async_graphql::ErrorExtensions;
 use serde::Serialize;
+use strum_macros::IntoStaticStr;
 use thiserror::Error;
 use tracing::error;
+use crate::metrics::RESOLVER_ERRORS;
 
-#[derive(Debug, Error, PartialEq, Clone)]
+#[derive(Debug, Error, PartialEq, Clone, IntoStaticStr)]
 pub enum ServiceError {
     #[error("Could not find resource")]
     NotFound,
@@ -50,10 +52,15 @@ pub enum ServiceError {
 
     #[error(transparent)]
     StrConversion(#[from] std::str::Utf8Error),
+
+    #[error("Message broker error: {0}")]
+    MessageBrokerError(String),
 }
 
 impl ErrorExtensions for ServiceError {
     fn extend(&self) -> async_graphql::Error {
+        let variant: &'static str = self.into();
+        RESOLVER_ERRORS.with_label_values(&[variant]).inc();
         async_graphql::Error::new(format!("{}", self)).extend_with(|_, e| match self {
             Self::BadRequest(error) => {
                 e.set("status", 400);
@@ -82,6 +89,11 @@ impl ErrorExtensions for ServiceError {
                 e.set("statusText", "SERVER_ERROR");
                 e.set("context", error.to_string());
             }
+            Self::MessageBrokerError(error) => {
+                e.set("status", 503);
+                e.set("statusText", "MESSAGE_BROKER_ERROR");
+                e.set("context", error.to_string());
+            }
             Self::UnexpectedError | Self::PoisonConcurrencyError(_) => {
                 e.set("status", 500);
                 e.set("statusText", "SERVER_ERROR");
@@ -108,6 +120,7 @@ impl ResponseError for ServiceError {
                 HttpResponse::Unauthorized().finish()
             }
             Self::Forbidden => HttpResponse::Forbidden().finish(),
+            Self::BadRequest(error) => HttpResponse::BadRequest().json::<Messages>(vec![error].into()),
             // Self::InvalidToken(error) => {
             //     HttpResponse::Unauthorized().json::<Messages>(vec![error].into())
             // }
@@ -0,0 +1,352 @@
+use chrono::{DateTime, Utc};
+use opentelemetry::trace::TraceContextExt;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use uuid::Uuid;
+use crate::{error::ServiceError, QueryResult};
+
+/// Schema version given to payloads that were published before the envelope existed
+pub const LEGACY_SCHEMA_VERSION: u16 = 0;
+
+/// Trace identifiers of the span that produced an event, so consumers can
+/// stitch their own spans onto the producer's trace
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TraceContext {
+    pub trace_id: Option<String>,
+    pub span_id: Option<String>,
+}
+
+impl TraceContext {
+    /// Captures the OpenTelemetry context of the current `tracing` span
+    pub fn current() -> Self {
+        let context = tracing::Span::current().context();
+        let span = context.span();
+        let span_context = span.span_context();
+        if !span_context.is_valid() {
+            return Self::default()
+        }
+        Self {
+            trace_id: Some(span_context.trace_id().to_string()),
+            span_id: Some(span_context.span_id().to_string()),
+        }
+    }
+    /// W3C `traceparent` representation of the context, if there is one
+    pub fn traceparent(&self) -> Option<String> {
+        match (&self.trace_id, &self.span_id) {
+            (Some(trace_id), Some(span_id)) => Some(format!("00-{}-{}-01", trace_id, span_id)),
+            _ => None
+        }
+    }
+}
+
+/// Models that decide which partition their events are written to.
+/// Events sharing a key are delivered in order
+pub trait PartitionKey {
+    fn partition_key(&self) -> String;
+}
+
+/// A payload that can travel inside an `EventEnvelope`
+pub trait Event: Serialize {
+    /// Version of the payload schema that producers currently write
+    const SCHEMA_VERSION: u16;
+    /// Dotted name of the event, e.g. `catalog.movie.created`
+    fn event_type(&self) -> &'static str;
+    /// Kafka message key for the event
+    fn partition_key(&self) -> String;
+    /// Rewrites a payload written at `version` into the shape of `SCHEMA_VERSION`.
+    /// `LEGACY_SCHEMA_VERSION` payloads are the bare json documents sent before the envelope
+    fn upcast(version: u16, payload: Value) -> QueryResult<Value>;
+}
+
+/// Every message on every topic is wrapped in this envelope
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EventEnvelope<T> {
+    pub event_id: Uuid,
+    pub event_type: String,
+    pub schema_version: u16,
+    pub occurred_at: DateTime<Utc>,
+    pub producer: String,
+    #[serde(default)]
+    pub trace_context: TraceContext,
+    pub payload: T,
+}
+
+impl<T: Event> EventEnvelope<T> {
+    pub fn new(producer: &str, payload: T) -> Self {
+        Self {
+            event_id: Uuid::new_v4(),
+            event_type: payload.event_type().to_string(),
+            schema_version: T::SCHEMA_VERSION,
+            occurred_at: Utc::now(),
+            producer: producer.to_string(),
+            trace_context: TraceContext::current(),
+            payload,
+        }
+    }
+    pub fn to_json(&self) -> QueryResult<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+}
+
+impl<T: Event + DeserializeOwned> EventEnvelope<T> {
+    /// Decodes a raw message, upcasting older schema versions and bare legacy
+    /// payloads into the current version of `T`
+    #[tracing::instrument(level = "debug", skip(raw), err)]
+    pub fn decode(raw: &str) -> QueryResult<Self> {
+        let value: Value = serde_json::from_str(raw)?;
+        let is_envelope = value.get("schema_version").is_some() && value.get("payload").is_some();
+        let envelope: EventEnvelope<Value> = if is_envelope {
+            serde_json::from_value(value)?
+        } else {
+            EventEnvelope {
+                event_id: Uuid::new_v4(),
+                event_type: String::new(),
+                schema_version: LEGACY_SCHEMA_VERSION,
+                occurred_at: Utc::now(),
+                producer: String::from("unknown"),
+                trace_context: TraceContext::default(),
+                payload: value,
+            }
+        };
+        if envelope.schema_version > T::SCHEMA_VERSION {
+            return Err(ServiceError::BadRequest(format!(
+                "{} schema version {} is newer than the supported version {}",
+                envelope.event_type,
+                envelope.schema_version,
+                T::SCHEMA_VERSION
+            )))
+        }
+        let payload: T = serde_json::from_value(T::upcast(envelope.schema_version, envelope.payload)?)?;
+        Ok(EventEnvelope {
+            event_id: envelope.event_id,
+            event_type: payload.event_type().to_string(),
+            schema_version: T::SCHEMA_VERSION,
+            occurred_at: envelope.occurred_at,
+            producer: envelope.producer,
+            trace_context: envelope.trace_context,
+            payload,
+        })
+    }
+}
+
+/// Events published on the movie topics, generic over each service's own copy of the movie model
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "data", rename_all = "snake_case")]
+pub enum CatalogEvent<M> {
+    MovieCreated(M),
+    MovieUpdated(M),
+    /// Sent by an operator to force a movie back into the search index
+    MovieReindexRequested(M),
+    /// The movie is gone from the catalogue for good
+    MovieDeleted { movie_id: i64 },
+    /// The movie stays in the catalogue but is hidden until it is restored, consumers drop it
+    /// just like a deleted one
+    MovieSoftDeleted { movie_id: i64, deleted_at: i64 },
+    /// A soft deleted movie is back, as it was when it was hidden
+    MovieRestored(M),
+}
+
+impl<M: Serialize + PartitionKey> Event for CatalogEvent<M> {
+    const SCHEMA_VERSION: u16 = 1;
+
+    fn event_type(&self) -> &'static str {
+        match self {
+            Self::MovieCreated(_) => "catalog.movie.created",
+            Self::MovieUpdated(_) => "catalog.movie.updated",
+            Self::MovieReindexRequested(_) => "catalog.movie.reindex_requested",
+            Self::MovieDeleted { .. } => "catalog.movie.deleted",
+            Self::MovieSoftDeleted { .. } => "catalog.movie.soft_deleted",
+            Self::MovieRestored(_) => "catalog.movie.restored",
+        }
+    }
+    fn partition_key(&self) -> String {
+        match self {
+            Self::MovieCreated(movie)
+            | Self::MovieUpdated(movie)
+            | Self::MovieReindexRequested(movie)
+            | Self::MovieRestored(movie) => movie.partition_key(),
+            //  Movies are keyed by their id, so a delete is never applied before the writes it follows
+            Self::MovieDeleted { movie_id }
+            | Self::MovieSoftDeleted { movie_id, .. } => movie_id.to_string(),
+        }
+    }
+    fn upcast(version: u16, payload: Value) -> QueryResult<Value> {
+        match version {
+            //  Legacy messages were a bare movie that consumers always upserted
+            LEGACY_SCHEMA_VERSION => Ok(json!({ "kind": "movie_updated", "data": payload })),
+            _ => Ok(payload)
+        }
+    }
+}
+
+/// Events for TV series, published on the movie topic next to `CatalogEvent`.
+/// Generic over each service's copy of the series, season and episode models
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "data", rename_all = "snake_case")]
+pub enum SeriesEvent<S, N, E> {
+    SeriesUpserted(S),
+    SeriesDeleted { series_id: i64 },
+    SeasonUpserted(N),
+    SeasonDeleted { series_id: i64, season_number: i32 },
+    EpisodeUpserted(E),
+    EpisodeDeleted { series_id: i64, season_number: i32, episode_number: i32 },
+}
+
+impl<S, N, E> Event for SeriesEvent<S, N, E>
+where
+    S: Serialize + PartitionKey,
+    N: Serialize + PartitionKey,
+    E: Serialize + PartitionKey,
+{
+    const SCHEMA_VERSION: u16 = 1;
+
+    fn event_type(&self) -> &'static str {
+        match self {
+            Self::SeriesUpserted(_) => "catalog.series.upserted",
+            Self::SeriesDeleted { .. } => "catalog.series.deleted",
+            Self::SeasonUpserted(_) => "catalog.series.season_upserted",
+            Self::SeasonDeleted { .. } => "catalog.series.season_deleted",
+            Self::EpisodeUpserted(_) => "catalog.series.episode_upserted",
+            Self::EpisodeDeleted { .. } => "catalog.series.episode_deleted",
+        }
+    }
+    //  Everything about a series shares its partition, so an episode is never indexed before its season
+    fn partition_key(&self) -> String {
+        match self {
+            Self::SeriesUpserted(series) => series.partition_key(),
+            Self::SeasonUpserted(season) => season.partition_key(),
+            Self::EpisodeUpserted(episode) => episode.partition_key(),
+            Self::SeriesDeleted { series_id }
+            | Self::SeasonDeleted { series_id, .. }
+            | Self::EpisodeDeleted { series_id, .. } => series_id.to_string(),
+        }
+    }
+    fn upcast(_version: u16, payload: Value) -> QueryResult<Value> {
+        //  Series events were introduced with the envelope, there is no legacy shape
+        Ok(payload)
+    }
+}
+
+/// Published on the movie topic whenever a movie's translations change. It carries every
+/// translation the movie has, so a consumer can replace what it holds without reading anything back
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "data", rename_all = "snake_case")]
+pub enum TranslationEvent<T> {
+    MovieTranslated { movie_id: i64, translations: Vec<T> },
+}
+
+impl<T: Serialize> Event for TranslationEvent<T> {
+    const SCHEMA_VERSION: u16 = 1;
+
+    fn event_type(&self) -> &'static str {
+        match self {
+            Self::MovieTranslated { .. } => "catalog.translations.movie_translated",
+        }
+    }
+    //  Same key as the movie's own events, so translations are never indexed before the movie
+    fn partition_key(&self) -> String {
+        match self {
+            Self::MovieTranslated { movie_id, .. } => movie_id.to_string(),
+        }
+    }
+    fn upcast(_version: u16, payload: Value) -> QueryResult<Value> {
+        Ok(payload)
+    }
+}
+
+/// Published on the movie topic when a movie's licensing windows change, and again by the
+/// scheduler when one of them opens or closes. Consumers work out what is open when they read it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "data", rename_all = "snake_case")]
+pub enum AvailabilityEvent<W> {
+    AvailabilityChanged { movie_id: i64, windows: Vec<W> },
+}
+
+impl<W: Serialize> Event for AvailabilityEvent<W> {
+    const SCHEMA_VERSION: u16 = 1;
+
+    fn event_type(&self) -> &'static str {
+        match self {
+            Self::AvailabilityChanged { .. } => "catalog.availability.changed",
+        }
+    }
+    fn partition_key(&self) -> String {
+        match self {
+            Self::AvailabilityChanged { movie_id, .. } => movie_id.to_string(),
+        }
+    }
+    fn upcast(_version: u16, payload: Value) -> QueryResult<Value> {
+        Ok(payload)
+    }
+}
+
+/// Reads the `event_type` of a raw envelope without decoding its payload, for consumers of
+/// topics that carry more than one kind of event. Legacy payloads have none
+pub fn peek_event_type(raw: &str) -> Option<String> {
+    serde_json::from_str::<Value>(raw)
+        .ok()?
+        .get("event_type")?
+        .as_str()
+        .map(str::to_string)
+}
+
+/// Events published by the activity tracker, generic over the watch record sent to the recommender
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "data", rename_all = "snake_case")]
+pub enum ActivityEvent<W> {
+    WatchRecorded(Vec<W>),
+}
+
+impl<W: Serialize + PartitionKey> Event for ActivityEvent<W> {
+    const SCHEMA_VERSION: u16 = 1;
+
+    fn event_type(&self) -> &'static str {
+        match self {
+            Self::WatchRecorded(_) => "activity.watch.recorded",
+        }
+    }
+    fn partition_key(&self) -> String {
+        match self {
+            //  A recording always belongs to a single user
+            Self::WatchRecorded(records) => records
+                .first()
+                .map(|record| record.partition_key())
+                .unwrap_or_default(),
+        }
+    }
+    fn upcast(version: u16, payload: Value) -> QueryResult<Value> {
+        match version {
+            LEGACY_SCHEMA_VERSION => Ok(json!({ "kind": "watch_recorded", "data": payload })),
+            _ => Ok(payload)
+        }
+    }
+}
+
+/// Events published by the tensorflow model once it has scored a user
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "data", rename_all = "snake_case")]
+pub enum RecommendationEvent<R> {
+    RecommendationGenerated(R),
+}
+
+impl<R: Serialize + PartitionKey> Event for RecommendationEvent<R> {
+    const SCHEMA_VERSION: u16 = 1;
+
+    fn event_type(&self) -> &'static str {
+        match self {
+            Self::RecommendationGenerated(_) => "recommendation.generated",
+        }
+    }
+    fn partition_key(&self) -> String {
+        match self {
+            Self::RecommendationGenerated(recommendation) => recommendation.partition_key(),
+        }
+    }
+    fn upcast(version: u16, payload: Value) -> QueryResult<Value> {
+        match version {
+            LEGACY_SCHEMA_VERSION => Ok(json!({ "kind": "recommendation_generated", "data": payload })),
+            _ => Ok(payload)
+        }
+    }
+}
@@ -0,0 +1,214 @@
+use std::{fmt::Display, future::Future, sync::{Arc, atomic::{AtomicI64, Ordering}}, time::{Duration, Instant}};
+use actix_web::{web, HttpResponse};
+use chrono::Utc;
+use elasticsearch::Elasticsearch;
+use futures::future::{join_all, FutureExt, LocalBoxFuture};
+use rdkafka::producer::{FutureProducer, Producer};
+use scylla::CachingSession;
+use serde::Serialize;
+use serde_json::json;
+use crate::metrics::metrics;
+
+/// A dependency that does not answer within this window is reported as down
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+const NEVER_POLLED: i64 = i64::MIN;
+
+/// Result of probing a single downstream dependency
+#[derive(Debug, Clone, Serialize)]
+pub struct DependencyStatus {
+    pub name: &'static str,
+    pub healthy: bool,
+    pub latency_ms: u128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// Body returned by `/readyz`
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthReport {
+    pub service: &'static str,
+    pub ready: bool,
+    pub dependencies: Vec<DependencyStatus>,
+}
+
+/// Runs and times a dependency check. The check resolves to an optional detail
+/// message when healthy, or the reason it failed
+pub async fn check_dependency<F>(name: &'static str, check: F) -> DependencyStatus
+where
+    F: Future<Output = Result<Option<String>, String>>,
+{
+    let started = Instant::now();
+    let outcome = tokio::time::timeout(CHECK_TIMEOUT, check)
+        .await
+        .unwrap_or_else(|_| Err(format!("No response after {}ms", CHECK_TIMEOUT.as_millis())));
+    let latency_ms = started.elapsed().as_millis();
+    if let Err(e) = &outcome {
+        tracing::warn!(dependency = name, err = %e, "Dependency is unavailable");
+    }
+    DependencyStatus {
+        name,
+        healthy: outcome.is_ok(),
+        latency_ms,
+        detail: outcome.unwrap_or_else(Some),
+    }
+}
+
+impl HealthReport {
+    pub fn new(service: &'static str, dependencies: Vec<DependencyStatus>) -> Self {
+        let ready = dependencies.iter().all(|dependency| dependency.healthy);
+        Self { service, ready, dependencies }
+    }
+    /// `200` when every dependency is healthy, `503` otherwise so the pod is taken out of rotation
+    pub fn into_response(self) -> HttpResponse {
+        match self.ready {
+            true => HttpResponse::Ok().json(self),
+            false => HttpResponse::ServiceUnavailable().json(self),
+        }
+    }
+}
+
+/// Liveness probe, answers as long as the actix workers are serving requests
+pub async fn healthz() -> HttpResponse {
+    HttpResponse::Ok().json(json!({ "status": "ok" }))
+}
+
+type Probe = Arc<dyn Fn() -> LocalBoxFuture<'static, DependencyStatus> + Send + Sync>;
+
+/// The dependencies a service checks on `/readyz`. Built once at startup and shared by every worker
+#[derive(Clone)]
+pub struct Readiness {
+    service: &'static str,
+    probes: Vec<Probe>,
+    before_scrape: Option<Arc<dyn Fn() + Send + Sync>>,
+}
+
+impl Readiness {
+    pub fn new(service: &'static str) -> Self {
+        Self { service, probes: Vec::new(), before_scrape: None }
+    }
+    /// Adds a dependency, `check` runs on every `/readyz` request, see `check_dependency`
+    pub fn probe<F, Fut>(mut self, name: &'static str, check: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Option<String>, String>> + 'static,
+    {
+        self.probes.push(Arc::new(move || check_dependency(name, check()).boxed_local()));
+        self
+    }
+    /// Runs `refresh` off the workers before `/metrics` is rendered, for gauges that are only
+    /// brought up to date on demand
+    pub fn before_scrape<F: Fn() + Send + Sync + 'static>(mut self, refresh: F) -> Self {
+        self.before_scrape = Some(Arc::new(refresh));
+        self
+    }
+    /// Runs every probe at once
+    pub async fn report(&self) -> HealthReport {
+        let dependencies = join_all(self.probes.iter().map(|probe| probe())).await;
+        HealthReport::new(self.service, dependencies)
+    }
+}
+
+/// Registers the liveness probe `/healthz`, the readiness probe `/readyz` answered from
+/// `readiness`, and the Prometheus scrape endpoint `/metrics`
+pub fn configure_health(readiness: Readiness) -> impl FnOnce(&mut web::ServiceConfig) {
+    move |cfg| {
+        cfg
+        .app_data(web::Data::new(readiness))
+        .route("/healthz", web::get().to(healthz))
+        .route("/readyz", web::get().to(readyz))
+        .route("/metrics", web::get().to(scrape));
+    }
+}
+
+async fn readyz(readiness: web::Data<Readiness>) -> HttpResponse {
+    readiness.report().await.into_response()
+}
+
+async fn scrape(readiness: web::Data<Readiness>) -> HttpResponse {
+    if let Some(refresh) = readiness.before_scrape.clone() {
+        if let Err(e) = web::block(move || refresh()).await {
+            tracing::warn!(err = %e, "Unable to refresh gauges before the scrape");
+        }
+    }
+    metrics().await
+}
+
+/// Scylla answers a query
+pub async fn check_scylla(session: &CachingSession) -> Result<Option<String>, String> {
+    session
+        .execute("SELECT now() FROM system.local", ())
+        .await
+        .map(|_| None)
+        .map_err(|e| e.to_string())
+}
+
+/// The Elasticsearch cluster answers a ping
+pub async fn check_elasticsearch(client: &Elasticsearch) -> Result<Option<String>, String> {
+    let response = client
+        .ping()
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    match response.status_code().is_success() {
+        true => Ok(None),
+        false => Err(format!("Ping returned {}", response.status_code()))
+    }
+}
+
+/// The Kafka brokers can be reached. Fetching metadata blocks, so it runs off the workers
+pub async fn check_kafka_brokers(producer: &'static FutureProducer) -> Result<Option<String>, String> {
+    web::block(move || {
+        producer
+            .client()
+            .fetch_metadata(None, Duration::from_secs(1))
+            .map(|metadata| metadata.brokers().len())
+    })
+        .await
+        .map_err(|e| e.to_string())?
+        .map(|brokers| Some(format!("{} brokers", brokers)))
+        .map_err(|e| e.to_string())
+}
+
+/// A consume loop is keeping up with its topics. `lag` blocks on the brokers, so it runs off the workers
+pub async fn check_consumer<F, E>(lag: F, probe: &'static ConsumerProbe, max_lag: i64, max_poll_age: i64) -> Result<Option<String>, String>
+where
+    F: FnOnce() -> Result<i64, E> + Send + 'static,
+    E: Display + Send + 'static,
+{
+    let lag = web::block(lag)
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+    probe.check(lag, max_lag, max_poll_age)
+}
+
+/// Records when a Kafka consume loop last came back from polling the broker
+pub struct ConsumerProbe {
+    last_poll: AtomicI64,
+}
+
+impl ConsumerProbe {
+    pub const fn new() -> Self {
+        Self { last_poll: AtomicI64::new(NEVER_POLLED) }
+    }
+    pub fn record_poll(&self) {
+        self.last_poll.store(Utc::now().timestamp(), Ordering::Relaxed);
+    }
+    /// Seconds since the last poll, `None` if the loop has not polled yet
+    pub fn last_poll_age(&self) -> Option<i64> {
+        match self.last_poll.load(Ordering::Relaxed) {
+            NEVER_POLLED => None,
+            last_poll => Some(Utc::now().timestamp() - last_poll),
+        }
+    }
+    /// A consumer is stuck when it is behind and has stopped polling, or is too far behind
+    pub fn check(&self, lag: i64, max_lag: i64, max_poll_age: i64) -> Result<Option<String>, String> {
+        let age = self.last_poll_age();
+        let detail = format!("lag: {}, last poll: {}s ago", lag, age.map_or(String::from("never"), |age| age.to_string()));
+        let stalled = lag > 0 && age.map_or(true, |age| age > max_poll_age);
+        match stalled || lag > max_lag {
+            true => Err(detail),
+            false => Ok(Some(detail)),
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use std::time::Duration;
+use rdkafka::ClientConfig;
+use rdkafka::error::{KafkaError, KafkaResult};
+use rdkafka::message::OwnedHeaders;
+use rdkafka::producer::{DeliveryFuture, FutureProducer, FutureRecord, Producer};
+use rdkafka::util::Timeout;
+use tokio::sync::Mutex;
+use crate::{QueryResult, error::ServiceError, events::{Event, EventEnvelope}, metrics::KAFKA_PRODUCED};
+
+const TRANSACTION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Partition and offset acknowledged by the broker
+pub type Delivery = (i32, i64);
+
+/// An event serialised into its envelope, ready to be produced. Outbox entries are stored like this
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub event_id: String,
+    pub key: String,
+    pub event_type: String,
+    pub schema_version: u16,
+    /// The `EventEnvelope` as json
+    pub payload: String,
+}
+
+impl Message {
+    /// `producer` is the service the envelope says the event came from
+    pub fn new<E: Event>(producer: &str, event: E) -> QueryResult<Self> {
+        let key = event.partition_key();
+        let envelope = EventEnvelope::new(producer, event);
+        Ok(Self {
+            event_id: envelope.event_id.to_string(),
+            key,
+            event_type: envelope.event_type.clone(),
+            schema_version: envelope.schema_version,
+            payload: envelope.to_json()?,
+        })
+    }
+}
+
+/// Idempotent producer of keyed events on a single topic, shared by every service that publishes
+pub struct EventProducer {
+    producer: FutureProducer,
+    topic: String,
+    /// Name of the service, written on every envelope
+    name: &'static str,
+    transactional: bool,
+    /// A producer can only have one transaction open at a time
+    transaction_lock: Mutex<()>,
+}
+
+impl EventProducer {
+    /// Setting `transactional_id` turns every batch of events into a single Kafka transaction
+    pub fn new(name: &'static str, broker: &str, topic: &str, transactional_id: Option<&str>) -> KafkaResult<Self> {
+        let mut config = ClientConfig::new();
+        config
+            .set("bootstrap.servers", broker)
+            .set("message.timeout.ms", "5000")
+            //  The broker drops retried duplicates and keeps the order of each partition
+            .set("enable.idempotence", "true")
+            .set("acks", "all");
+        if let Some(transactional_id) = transactional_id {
+            config.set("transactional.id", transactional_id);
+        }
+        let producer: FutureProducer = config.create()?;
+        if transactional_id.is_some() {
+            producer.init_transactions(Timeout::After(TRANSACTION_TIMEOUT))?;
+        }
+        Ok(Self {
+            producer,
+            topic: topic.to_string(),
+            name,
+            transactional: transactional_id.is_some(),
+            transaction_lock: Mutex::new(()),
+        })
+    }
+    /// The underlying client, for metadata requests such as the readiness probe
+    pub fn producer(&self) -> &FutureProducer {
+        &self.producer
+    }
+    pub fn topic(&self) -> &str {
+        &self.topic
+    }
+    /// `event` in its envelope, stamped with the name of this producer
+    pub fn message<E: Event>(&self, event: E) -> QueryResult<Message> {
+        Message::new(self.name, event)
+    }
+    /// Publishes a single event, keyed by its partition key
+    #[tracing::instrument(level = "debug", skip(self, event), fields(event_type = event.event_type()), err)]
+    pub async fn send_event<E: Event>(&self, event: E) -> QueryResult<Delivery> {
+        self.send_events(vec![event])
+            .await
+            .pop()
+            .unwrap_or(Err(ServiceError::UnexpectedError))
+    }
+    /// Publishes a batch of events and returns the delivery report of each one.
+    /// A transactional producer commits the batch atomically, so a single failed
+    /// delivery aborts the whole batch
+    #[tracing::instrument(level = "debug", skip(self, events), fields(batch_size = events.len()))]
+    pub async fn send_events<E: Event>(&self, events: Vec<E>) -> Vec<QueryResult<Delivery>> {
+        let messages: Vec<QueryResult<Message>> = events.into_iter().map(|event| self.message(event)).collect();
+        let ready: Vec<Message> = messages.iter().filter_map(|message| message.as_ref().ok().cloned()).collect();
+        let mut deliveries = self.send_messages(ready).await.into_iter();
+        // An event that couldn't be serialised keeps its error in place
+        messages
+            .into_iter()
+            .map(|message| message.and_then(|_| deliveries.next().unwrap_or(Err(ServiceError::UnexpectedError))))
+            .collect()
+    }
+    /// `send_events` for messages serialised earlier, such as the entries of an outbox
+    #[tracing::instrument(level = "debug", skip(self, messages), fields(batch_size = messages.len()))]
+    pub async fn send_messages(&self, messages: Vec<Message>) -> Vec<QueryResult<Delivery>> {
+        let transaction_guard = match self.transactional {
+            true => Some(self.transaction_lock.lock().await),
+            false => None
+        };
+        if transaction_guard.is_some() {
+            if let Err(e) = self.producer.begin_transaction() {
+                let e = broker_error(e);
+                return messages.iter().map(|_| Err(e.clone())).collect()
+            }
+        }
+        //  Enqueue the whole batch before waiting on any delivery report
+        let pending: Vec<QueryResult<DeliveryFuture>> = messages
+            .iter()
+            .map(|message| {
+                let headers = OwnedHeaders::new()
+                    .add("event_type", message.event_type.as_str())
+                    .add("schema_version", &message.schema_version.to_string());
+                self.producer
+                    .send_result(
+                        FutureRecord::to(&self.topic)
+                            .payload(&message.payload)
+                            .key(&message.key)
+                            .headers(headers)
+                    )
+                    .map_err(|(e, _)| broker_error(e))
+            })
+            .collect();
+
+        let mut deliveries = Vec::with_capacity(pending.len());
+        for delivery in pending {
+            let status = match delivery {
+                Ok(future) => match future.await {
+                    Ok(Ok((partition, offset))) => Ok((partition, offset)),
+                    Ok(Err((e, _))) => Err(broker_error(e)),
+                    Err(_) => Err(ServiceError::MessageBrokerError("Delivery was cancelled".to_string()))
+                },
+                Err(e) => Err(e)
+            };
+            let outcome = if status.is_ok() { "delivered" } else { "failed" };
+            KAFKA_PRODUCED.with_label_values(&[self.topic.as_str(), outcome]).inc();
+            deliveries.push(status);
+        }
+
+        if transaction_guard.is_some() {
+            let committed = deliveries.iter().all(Result::is_ok) && self.producer
+                .commit_transaction(Timeout::After(TRANSACTION_TIMEOUT))
+                .map_err(broker_error)
+                .is_ok();
+            if !committed {
+                if let Err(e) = self.producer.abort_transaction(Timeout::After(TRANSACTION_TIMEOUT)) {
+                    tracing::error!("❌❌ Unable to abort transaction: {}", e);
+                }
+                let aborted = ServiceError::MessageBrokerError("Transaction was aborted".to_string());
+                return deliveries
+                    .into_iter()
+                    .map(|delivery| delivery.and(Err(aborted.clone())))
+                    .collect()
+            }
+        }
+        deliveries
+    }
+}
+
+fn broker_error(e: KafkaError) -> ServiceError {
+    tracing::error!("❌❌ Something went wrong: error {}", e);
+    ServiceError::MessageBrokerError(e.to_string())
+}
@@ -4,9 +4,19 @@ extern crate lazy_static;
 #[macro_use]
 extern crate thiserror;
 
+pub mod artwork;
+pub mod availability;
 pub mod error;
-
-use std::{env::var, str::FromStr};
+pub mod events;
+pub mod health;
+pub mod kafka;
+pub mod locale;
+pub mod metrics;
+pub mod playback;
+pub mod shutdown;
+pub mod signing;
+
+use std::{collections::HashMap, env::var, str::FromStr};
 use actix_web::{HttpResponse, HttpRequest};
 use chrono::{Duration, Local, NaiveDate};
 use error::ServiceError;
@@ -25,28 +35,25 @@ use parking_lot::Mutex;
 use serial_int::SerialGenerator;
 
 
+lazy_static! {
+    /// One generator per machine and node, a fresh one would restart its sequence and hand out
+    /// the same id twice within a millisecond
+    static ref SNOWFLAKES: Mutex<HashMap<(i32, i32), SnowflakeIdGenerator>> = Mutex::new(HashMap::new());
+}
+
 /// Generate Unique identifier for Movies and Companies 
 pub fn unique_id(machine_id: i32, node_id: i32) -> i64 { 
-    let snow = Mutex::new(SnowflakeIdGenerator::new(machine_id, node_id));
-    let id = snow.lock().real_time_generate();
-    id
+    SNOWFLAKES
+        .lock()
+        .entry((machine_id, node_id))
+        .or_insert_with(|| SnowflakeIdGenerator::new(machine_id, node_id))
+        .real_time_generate()
 }
 ///  Generate a new int value for simple ids
 lazy_static! {
-    static ref GENRE_ID_GEN: Mutex<SerialGenerator> = Mutex::new(SerialGenerator::new());
-    static ref PERSON_ID_GEN: Mutex<SerialGenerator> = Mutex::new(SerialGenerator::new());
     static ref COUNTRY_ID_GEN: Mutex<SerialGenerator> = Mutex::new(SerialGenerator::new());
     pub static ref KAFKA_CONSUMER_COUNTER: Mutex<SerialGenerator> = Mutex::new(SerialGenerator::new());
 }
-/// Autoincrements id for new genre
-pub fn inc_genre() -> i32 { 
-    GENRE_ID_GEN.lock().generate() as i32
-}
-/// autogenerates the person id 
-pub fn inc_person_id() -> i32 { 
-    PERSON_ID_GEN.lock().generate() as i32
-}
-
 pub fn inc_country() -> i32 { 
     COUNTRY_ID_GEN.lock().generate() as i32
 }
@@ -73,7 +80,20 @@ pub struct Claim {
     // Timestamp of when these tokens were issued
     issued_at: i64,
     expiry: i64, 
-    login_session: String
+    login_session: String,
+    /// ISO 3166-1 code of the territory the account is licensed in, see `availability::RequestRegion`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    region: Option<String>,
+    /// Subscription plan of the account, see `playback::RequestPlan`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    plan: Option<String>,
+}
+
+impl Claim { 
+    /// Username the token was issued to
+    pub fn subject(&self) -> &str { 
+        &self.subject
+    }
 }
 
 #[derive(Eq, PartialEq, Display, EnumString, Copy, Clone)]
@@ -97,6 +117,8 @@ pub fn generate_token(username: String, role: Role) -> String {
         issued_at: now,
         expiry,
         login_session: role.to_string(),
+        region: None,
+        plan: None,
     };
     encode(
         &Header::default(),
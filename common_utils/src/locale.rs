@@ -0,0 +1,69 @@
+//! BCP 47 language tags, shared by the service that stores translations and the one that serves them
+use crate::{error::ServiceError, QueryResult};
+
+/// What the canonical catalogue is written in, the last step of every fallback chain
+pub const DEFAULT_LOCALE: &str = "en";
+
+/// Canonical form of a tag: the language in lowercase, a script in title case and a region in
+/// uppercase, e.g. `pt_br` becomes `pt-BR` and `zh-hant-tw` becomes `zh-Hant-TW`
+pub fn normalise_locale(locale: &str) -> QueryResult<String> {
+    let invalid = || ServiceError::BadRequest(format!("`{}` is not a language tag like en or pt-BR", locale));
+    let alphabetic = |subtag: &str| subtag.chars().all(|c| c.is_ascii_alphabetic());
+    let mut subtags = locale.trim().split(|c: char| c == '-' || c == '_');
+    let language = subtags
+        .next()
+        .filter(|language| (2..=3).contains(&language.len()) && alphabetic(language))
+        .ok_or_else(invalid)?;
+    let mut tag = language.to_ascii_lowercase();
+    for subtag in subtags {
+        let subtag = match subtag.len() {
+            4 if alphabetic(subtag) => subtag[..1].to_ascii_uppercase() + &subtag[1..].to_ascii_lowercase(),
+            2 if alphabetic(subtag) => subtag.to_ascii_uppercase(),
+            3 if subtag.chars().all(|c| c.is_ascii_digit()) => subtag.to_string(),
+            _ => return Err(invalid()),
+        };
+        tag.push('-');
+        tag.push_str(&subtag);
+    }
+    Ok(tag)
+}
+
+/// The language subtag alone, `pt` for `pt-BR`
+pub fn language_of(locale: &str) -> &str {
+    locale.split('-').next().unwrap_or(locale)
+}
+
+/// Tags of an `Accept-Language` header, most preferred first. Wildcards, tags that don't parse
+/// and anything weighted `q=0` are dropped, equal weights keep the order they were sent in
+pub fn parse_accept_language(header: &str) -> Vec<String> {
+    let mut tags = header
+        .split(',')
+        .filter_map(|range| {
+            let mut parts = range.split(';');
+            let tag = normalise_locale(parts.next()?).ok()?;
+            let quality = parts
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            Some((tag, quality)).filter(|(_, quality)| *quality > 0.0)
+        })
+        .collect::<Vec<_>>();
+    tags.sort_by(|a, b| b.1.total_cmp(&a.1));
+    tags.into_iter().map(|(tag, _)| tag).collect()
+}
+
+/// Locales to try in order for `preferred`: each tag followed by its language, then
+/// `DEFAULT_LOCALE`, e.g. `pt-BR` → `pt` → `en`
+pub fn fallback_chain(preferred: &[String]) -> Vec<String> {
+    let mut chain: Vec<String> = Vec::with_capacity(preferred.len() * 2 + 1);
+    let candidates = preferred
+        .iter()
+        .flat_map(|tag| [tag.clone(), language_of(tag).to_string()])
+        .chain(std::iter::once(DEFAULT_LOCALE.to_string()));
+    for locale in candidates {
+        if !chain.contains(&locale) {
+            chain.push(locale);
+        }
+    }
+    chain
+}
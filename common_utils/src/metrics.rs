@@ -0,0 +1,111 @@
+use std::{sync::Arc, time::Instant};
+use actix_web::HttpResponse;
+use async_graphql::{
+    async_trait::async_trait,
+    extensions::{Extension, ExtensionContext, ExtensionFactory, NextExecute},
+    Response,
+};
+use prometheus::{
+    register_histogram_vec, register_int_counter, register_int_counter_vec, register_int_gauge_vec,
+    Encoder, HistogramTimer, HistogramVec, IntCounter, IntCounterVec, IntGaugeVec, TextEncoder,
+};
+
+lazy_static! {
+    pub static ref GRAPHQL_REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "graphql_requests_total",
+        "GraphQL operations executed, by operation name and outcome",
+        &["operation", "status"]
+    ).expect("Unable to register graphql_requests_total");
+    pub static ref GRAPHQL_LATENCY: HistogramVec = register_histogram_vec!(
+        "graphql_request_duration_seconds",
+        "Time taken to execute a GraphQL operation",
+        &["operation"]
+    ).expect("Unable to register graphql_request_duration_seconds");
+    pub static ref RESOLVER_ERRORS: IntCounterVec = register_int_counter_vec!(
+        "resolver_errors_total",
+        "Errors returned to clients, by ServiceError variant",
+        &["variant"]
+    ).expect("Unable to register resolver_errors_total");
+    pub static ref DATASTORE_LATENCY: HistogramVec = register_histogram_vec!(
+        "datastore_query_duration_seconds",
+        "Latency of queries against Scylla, Elasticsearch, InfluxDB and Postgres",
+        &["store", "operation"]
+    ).expect("Unable to register datastore_query_duration_seconds");
+    pub static ref KAFKA_PRODUCED: IntCounterVec = register_int_counter_vec!(
+        "kafka_messages_produced_total",
+        "Messages published to Kafka, by topic and delivery outcome",
+        &["topic", "status"]
+    ).expect("Unable to register kafka_messages_produced_total");
+    pub static ref KAFKA_CONSUMED: IntCounterVec = register_int_counter_vec!(
+        "kafka_messages_consumed_total",
+        "Messages received from Kafka, by topic and processing outcome",
+        &["topic", "status"]
+    ).expect("Unable to register kafka_messages_consumed_total");
+    pub static ref KAFKA_CONSUMER_LAG: IntGaugeVec = register_int_gauge_vec!(
+        "kafka_consumer_lag",
+        "Messages the consumer group is behind across its assigned partitions",
+        &["group"]
+    ).expect("Unable to register kafka_consumer_lag");
+    pub static ref ELASTIC_BULK_FAILURES: IntCounter = register_int_counter!(
+        "elasticsearch_bulk_failures_total",
+        "Documents rejected by Elasticsearch bulk index requests"
+    ).expect("Unable to register elasticsearch_bulk_failures_total");
+    pub static ref CACHE_REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "cache_requests_total",
+        "Cache lookups, by cache and hit or miss",
+        &["cache", "result"]
+    ).expect("Unable to register cache_requests_total");
+}
+
+/// Starts a timer that records the query latency of `store` once it is dropped
+pub fn datastore_timer(store: &str, operation: &str) -> HistogramTimer {
+    DATASTORE_LATENCY
+        .with_label_values(&[store, operation])
+        .start_timer()
+}
+
+/// Records a cache lookup, the hit ratio is `hit / (hit + miss)`
+pub fn record_cache_lookup(cache: &str, hit: bool) {
+    let result = if hit { "hit" } else { "miss" };
+    CACHE_REQUESTS.with_label_values(&[cache, result]).inc();
+}
+
+/// Renders every registered metric in the Prometheus text format
+pub async fn metrics() -> HttpResponse {
+    let encoder = TextEncoder::new();
+    let mut buffer = Vec::new();
+    match encoder.encode(&prometheus::gather(), &mut buffer) {
+        Ok(_) => HttpResponse::Ok()
+            .content_type(encoder.format_type())
+            .body(buffer),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+/// Schema extension that counts and times each GraphQL operation
+pub struct GraphQLMetrics;
+
+impl ExtensionFactory for GraphQLMetrics {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(GraphQLMetricsExtension)
+    }
+}
+
+struct GraphQLMetricsExtension;
+
+#[async_trait]
+impl Extension for GraphQLMetricsExtension {
+    async fn execute(&self, ctx: &ExtensionContext<'_>, operation_name: Option<&str>, next: NextExecute<'_>) -> Response {
+        let operation = operation_name.unwrap_or("anonymous").to_string();
+        let started = Instant::now();
+        let response = next.run(ctx, operation_name).await;
+        let status = if response.is_ok() { "ok" } else { "error" };
+        GRAPHQL_LATENCY
+            .with_label_values(&[&operation])
+            .observe(started.elapsed().as_secs_f64());
+        GRAPHQL_REQUESTS
+            .with_label_values(&[&operation, status])
+            .inc();
+        response
+    }
+}
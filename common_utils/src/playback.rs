@@ -0,0 +1,214 @@
+//! Video, audio and text tracks of titles, shared by the ingestion service that registers them
+//! and the catalogue that picks the ones a caller may play
+use actix_web::HttpRequest;
+use async_graphql::Enum;
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumString};
+use crate::{decode_token, error::ServiceError, QueryResult};
+
+/// What a media asset belongs to
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumString, Serialize, Deserialize)]
+#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum MediaOwner {
+    Movie,
+    Episode,
+}
+
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumString, Serialize, Deserialize)]
+#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum VideoCodec {
+    H264,
+    Hevc,
+    Vp9,
+    Av1,
+}
+
+impl VideoCodec {
+    /// RFC 6381 `codecs` value of an 8 bit encode. Only the codec family is stored, so the profile
+    /// and level are the ones a rendition `height` lines tall is usually encoded at
+    pub fn codecs(self, height: i32) -> &'static str {
+        let uhd = height > 1080;
+        match (self, uhd) {
+            (VideoCodec::H264, false) => "avc1.640028",
+            (VideoCodec::H264, true) => "avc1.640033",
+            (VideoCodec::Hevc, false) => "hvc1.1.6.L120.90",
+            (VideoCodec::Hevc, true) => "hvc1.1.6.L153.90",
+            (VideoCodec::Vp9, false) => "vp09.00.40.08",
+            (VideoCodec::Vp9, true) => "vp09.00.51.08",
+            (VideoCodec::Av1, false) => "av01.0.08M.08",
+            (VideoCodec::Av1, true) => "av01.0.12M.08",
+        }
+    }
+}
+
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumString, Serialize, Deserialize)]
+#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AudioCodec {
+    Aac,
+    Ac3,
+    Eac3,
+    Opus,
+}
+
+impl AudioCodec {
+    /// RFC 6381 `codecs` value, AAC is AAC-LC
+    pub fn codecs(self) -> &'static str {
+        match self {
+            AudioCodec::Aac => "mp4a.40.2",
+            AudioCodec::Ac3 => "ac-3",
+            AudioCodec::Eac3 => "ec-3",
+            AudioCodec::Opus => "opus",
+        }
+    }
+}
+
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumString, Serialize, Deserialize)]
+#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Container {
+    Mp4,
+    /// Fragmented MP4, the segments HLS and DASH share
+    Cmaf,
+    Webm,
+    /// MPEG transport stream
+    Ts,
+}
+
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumString, Serialize, Deserialize)]
+#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ChannelLayout {
+    Mono,
+    Stereo,
+    /// 5.1
+    Surround51,
+    /// 7.1
+    Surround71,
+    /// Object based, on top of a 7.1 bed
+    Atmos,
+}
+
+impl ChannelLayout {
+    /// HLS `CHANNELS` value, Atmos is signalled as Dolby Digital Plus with joint object coding
+    pub fn hls_channels(self) -> &'static str {
+        match self {
+            ChannelLayout::Mono => "1",
+            ChannelLayout::Stereo => "2",
+            ChannelLayout::Surround51 => "6",
+            ChannelLayout::Surround71 => "8",
+            ChannelLayout::Atmos => "16/JOC",
+        }
+    }
+    /// `ChannelConfiguration` of ISO/IEC 23091-3 (CICP) for DASH, Atmos is described by its 7.1 bed
+    pub fn cicp(self) -> u8 {
+        match self {
+            ChannelLayout::Mono => 1,
+            ChannelLayout::Stereo => 2,
+            ChannelLayout::Surround51 => 6,
+            ChannelLayout::Surround71 | ChannelLayout::Atmos => 12,
+        }
+    }
+    pub fn label(self) -> &'static str {
+        match self {
+            ChannelLayout::Mono => "Mono",
+            ChannelLayout::Stereo => "Stereo",
+            ChannelLayout::Surround51 => "5.1",
+            ChannelLayout::Surround71 => "7.1",
+            ChannelLayout::Atmos => "Atmos",
+        }
+    }
+}
+
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Display, EnumString, Serialize, Deserialize)]
+#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TextTrackKind {
+    Subtitles,
+    /// Subtitles for the deaf and hard of hearing, sounds included
+    Captions,
+    /// Only the foreign dialogue, shown even with subtitles off
+    Forced,
+}
+
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumString, Serialize, Deserialize)]
+#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TextTrackFormat {
+    Webvtt,
+    Ttml,
+}
+
+/// Subscription plan of the caller, which caps the quality it is served
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumString, Serialize, Deserialize)]
+#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Plan {
+    Basic,
+    Standard,
+    Premium,
+}
+
+/// What callers without a plan are served
+impl Default for Plan {
+    fn default() -> Self {
+        Plan::Basic
+    }
+}
+
+impl Plan {
+    /// Tallest rendition the plan may play, in lines: SD, full HD and 4K
+    pub fn max_height(self) -> i32 {
+        match self {
+            Plan::Basic => 480,
+            Plan::Standard => 1080,
+            Plan::Premium => 2160,
+        }
+    }
+}
+
+/// `algorithm:hex digest` in lowercase, e.g. `sha256:9f86d0…`. Only SHA-256, SHA-1 and MD5 are known
+pub fn normalise_checksum(checksum: &str) -> QueryResult<String> {
+    let checksum = checksum.trim().to_ascii_lowercase();
+    let valid = match checksum.split_once(':') {
+        Some((algorithm, digest)) => {
+            let length = match algorithm {
+                "sha256" => 64,
+                "sha1" => 40,
+                "md5" => 32,
+                _ => 0,
+            };
+            digest.len() == length && digest.chars().all(|c| c.is_ascii_hexdigit())
+        }
+        None => false,
+    };
+    match valid {
+        true => Ok(checksum),
+        false => Err(ServiceError::BadRequest(format!("`{}` is not a checksum like sha256:<64 hex digits>", checksum))),
+    }
+}
+
+/// The plan a request is served for, attached to it by the `/graphql` handler
+#[derive(Debug, Clone, Default)]
+pub struct RequestPlan {
+    pub plan: Plan,
+}
+
+impl RequestPlan {
+    /// The `plan` claim of the verified bearer token. Headers are never trusted for this, any caller
+    /// could send one naming a plan they don't pay for. Anonymous callers and plans that don't parse
+    /// get `Plan::Basic`
+    pub fn new(req: &HttpRequest) -> Self {
+        let plan = req.headers()
+            .get("Authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer"))
+            .and_then(|token| decode_token(token.trim()).ok())
+            .and_then(|token| token.claims.plan)
+            .and_then(|plan| plan.trim().to_ascii_uppercase().parse().ok())
+            .unwrap_or_default();
+        Self { plan }
+    }
+}
@@ -0,0 +1,46 @@
+use actix_web::dev::ServerHandle;
+use tokio::{sync::watch, task::JoinHandle};
+use crate::QueryResult;
+
+/// Resolves once the process is asked to stop with SIGTERM or SIGINT
+pub async fn termination_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut terminate = signal(SignalKind::terminate()).expect("Unable to listen for SIGTERM");
+        tokio::select! {
+            _ = terminate.recv() => {},
+            _ = tokio::signal::ctrl_c() => {},
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
+/// Coordinates the shutdown of a consumer service. On a termination signal the consume
+/// loop is told to stop polling and is awaited while it flushes its batch and commits,
+/// and only then is the actix server stopped. If the loop exits on its own the server
+/// is stopped as well, so the pod gets restarted instead of serving a dead consumer
+pub async fn drain_on_termination(
+    consumer: JoinHandle<QueryResult<()>>,
+    shutdown: watch::Sender<bool>,
+    server: ServerHandle,
+) {
+    let mut consumer = consumer;
+    let finished = tokio::select! {
+        _ = termination_signal() => {
+            tracing::info!("Received termination signal, draining the consumer");
+            let _ = shutdown.send(true);
+            (&mut consumer).await
+        }
+        finished = &mut consumer => finished,
+    };
+    match finished {
+        Ok(Ok(())) => tracing::info!("Consumer drained and offsets committed"),
+        Ok(Err(e)) => tracing::error!(err = %e, "Consumer stopped with an error"),
+        Err(e) => tracing::error!(err = %e, "Consumer task did not complete"),
+    }
+    server.stop(true).await;
+}
@@ -0,0 +1,289 @@
+//! Signed, expiring media URLs. `asset_service` issues them from an authorised `playbackInfo`,
+//! `UrlSigner::verify` is all an edge or a local test server needs to check one
+use std::{env::var, net::IpAddr};
+use actix_web::HttpRequest;
+use chrono::{TimeZone, Utc};
+use hmac::{Hmac, Mac};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use strum_macros::{Display, EnumString};
+use thiserror::Error;
+use crate::decode_token;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Sent by players with every media request when URLs are bound to a playback session
+pub const PLAYBACK_SESSION_HEADER: &str = "x-playback-session";
+
+lazy_static! {
+    /// `TRUSTED_PROXIES`, comma separated. Only requests from these addresses have their
+    /// `X-Forwarded-For` read, each of them has to append the address it received the request from
+    static ref TRUSTED_PROXIES: Vec<IpAddr> = var("TRUSTED_PROXIES")
+        .map(|proxies| proxies.split(',').filter_map(|proxy| proxy.trim().parse().ok()).collect())
+        .unwrap_or_default();
+}
+
+/// Characters AWS leaves unencoded, everything else in a SigV4 URI or query is percent encoded
+const UNRESERVED: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'.').remove(b'_').remove(b'~');
+/// The same, less `/` so that keys keep their segments
+const PATH: &AsciiSet = &UNRESERVED.remove(b'/');
+
+/// Presigned S3 URLs can be valid for a week at most
+const S3_MAX_EXPIRES: i64 = 7 * 24 * 60 * 60;
+
+/// What a token is tied to besides its expiry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumString)]
+#[strum(serialize_all = "lowercase")]
+pub enum Binding {
+    None,
+    /// The address the URL was issued to, URLs can't be passed on to another network
+    Ip,
+    /// The `x-playback-session` of the `playbackInfo` call, players have to send it back
+    Session,
+}
+
+/// How `s3://` locations are delivered. HTTP locations and the manifests are always HMAC signed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumString)]
+#[strum(serialize_all = "lowercase")]
+pub enum SigningMode {
+    /// From `MEDIA_BASE_URL` by key, with a token the edge verifies
+    Hmac,
+    /// Presigned for the bucket itself, AWS Signature Version 4. These can't be bound
+    S3,
+}
+
+#[derive(Debug, Error, PartialEq, Eq, Clone)]
+pub enum SignatureError {
+    #[error("The URL is not signed")]
+    Missing,
+    #[error("The signature doesn't match")]
+    Invalid,
+    #[error("The URL has expired")]
+    Expired,
+    #[error("The token doesn't cover this path")]
+    OutOfScope,
+    #[error("The URL was issued to another client")]
+    WrongClient,
+}
+
+/// Who a URL is issued to or presented by
+#[derive(Debug, Clone, Default)]
+pub struct PlaybackClient {
+    /// Subject of a valid bearer token, `None` for anonymous callers
+    pub subject: Option<String>,
+    /// The caller's address, see `client_ip`
+    pub ip: Option<String>,
+    pub session: Option<String>,
+}
+
+impl PlaybackClient {
+    pub fn new(req: &HttpRequest) -> Self {
+        let subject = req.headers()
+            .get("Authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer"))
+            .and_then(|token| decode_token(token.trim()).ok())
+            .map(|token| token.claims.subject().to_string());
+        let ip = client_ip(req, &TRUSTED_PROXIES).map(|ip| ip.to_string());
+        let session = req.headers()
+            .get(PLAYBACK_SESSION_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        Self { subject, ip, session }
+    }
+}
+
+/// The address the request came from. When that is one of the `trusted` proxies, the first address
+/// of `X-Forwarded-For` from the right that isn't a trusted proxy. Entries left of it were written
+/// by the client and are never read, so a client can't claim another address
+pub fn client_ip(req: &HttpRequest, trusted: &[IpAddr]) -> Option<IpAddr> {
+    let peer = req.peer_addr()?.ip();
+    if !trusted.contains(&peer) {
+        return Some(peer)
+    }
+    let forwarded_for = req.headers()
+        .get_all("X-Forwarded-For")
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|hop| hop.trim().parse::<IpAddr>().ok())
+        .collect::<Vec<_>>();
+    let mut client = peer;
+    for hop in forwarded_for.into_iter().rev() {
+        match hop {
+            Some(hop) if trusted.contains(&hop) => client = hop,
+            Some(hop) => return Some(hop),
+            // Nothing left of an entry that doesn't parse can be relied on
+            None => break,
+        }
+    }
+    Some(client)
+}
+
+/// Credentials and bucket endpoint of `SigningMode::S3`
+#[derive(Debug, Clone)]
+pub struct S3Credentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub region: String,
+    /// Path style endpoint such as `http://localhost:9000` for S3 compatible stores, buckets are
+    /// addressed as `https://{bucket}.s3.{region}.amazonaws.com` without one
+    pub endpoint: Option<String>,
+}
+
+/// Issues and verifies HMAC-SHA256 tokens, and presigns S3 URLs in `SigningMode::S3`.
+///
+/// A token is appended to the URL as `acl`, `exp`, optionally `bind`, and `sig` last. It covers
+/// every path that starts with `acl`, the URL's path less its extension, followed by `.`, `_`,
+/// `-` or `/`. The segments and playlist a packager writes next to `video_1080.mp4` are named like
+/// that, and other renditions are not. The signature is over the query before `sig`, so other
+/// parameters such as a manifest's `plan` can't be changed either, and the bound address or session
+#[derive(Clone)]
+pub struct UrlSigner {
+    secret: Vec<u8>,
+    /// How long issued URLs are valid for, in seconds
+    pub ttl: i64,
+    pub binding: Binding,
+    pub mode: SigningMode,
+    pub s3: Option<S3Credentials>,
+}
+
+impl UrlSigner {
+    pub fn new(secret: impl Into<Vec<u8>>, ttl: i64, binding: Binding) -> Self {
+        Self { secret: secret.into(), ttl, binding, mode: SigningMode::Hmac, s3: None }
+    }
+    /// `MEDIA_SIGNING_SECRET` has to be set. `MEDIA_URL_TTL` defaults to 4 hours and
+    /// `MEDIA_URL_BINDING` to `none`. `MEDIA_SIGNING_MODE=s3` presigns `s3://` locations with
+    /// `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY`, `AWS_REGION` and the optional `MEDIA_S3_ENDPOINT`
+    pub fn from_env() -> Self {
+        let secret = var("MEDIA_SIGNING_SECRET").expect("MEDIA_SIGNING_SECRET Error");
+        let ttl = var("MEDIA_URL_TTL").ok().and_then(|ttl| ttl.parse().ok()).unwrap_or(4 * 60 * 60);
+        let binding = var("MEDIA_URL_BINDING").ok().and_then(|binding| binding.parse().ok()).unwrap_or(Binding::None);
+        let mode = var("MEDIA_SIGNING_MODE").ok().and_then(|mode| mode.parse().ok()).unwrap_or(SigningMode::Hmac);
+        let s3 = match mode {
+            SigningMode::S3 => Some(S3Credentials {
+                access_key_id: var("AWS_ACCESS_KEY_ID").expect("AWS_ACCESS_KEY_ID Error"),
+                secret_access_key: var("AWS_SECRET_ACCESS_KEY").expect("AWS_SECRET_ACCESS_KEY Error"),
+                region: var("AWS_REGION").unwrap_or_else(|_| "us-east-1".to_string()),
+                endpoint: var("MEDIA_S3_ENDPOINT").ok(),
+            }),
+            SigningMode::Hmac => None,
+        };
+        Self { secret: secret.into_bytes(), ttl, binding, mode, s3 }
+    }
+    /// The bound address or session of `client`, empty when URLs aren't bound
+    fn bound_value<'a>(&self, binding: Binding, client: &'a PlaybackClient) -> &'a str {
+        match binding {
+            Binding::None => "",
+            Binding::Ip => client.ip.as_deref().unwrap_or_default(),
+            Binding::Session => client.session.as_deref().unwrap_or_default(),
+        }
+    }
+    fn signature(&self, signed: &str, bound: &str) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.secret).expect("HMAC takes keys of any size");
+        mac.update(signed.as_bytes());
+        mac.update(b"\n");
+        mac.update(bound.as_bytes());
+        mac
+    }
+    /// `url` with a token valid until `expires`, in seconds since the epoch, for `client`
+    pub fn sign(&self, url: &str, expires: i64, client: &PlaybackClient) -> String {
+        let (base, query) = url.split_once('?').unwrap_or((url, ""));
+        let path = url_path(base);
+        let acl = path.rfind('.').filter(|dot| *dot > path.rfind('/').unwrap_or(0)).map(|dot| &path[..dot]).unwrap_or(path);
+        let mut signed = match query.is_empty() {
+            true => String::new(),
+            false => format!("{}&", query),
+        };
+        signed.push_str(&format!("acl={}&exp={}", utf8_percent_encode(acl, UNRESERVED), expires));
+        if self.binding != Binding::None {
+            signed.push_str(&format!("&bind={}", self.binding));
+        }
+        let sig = hex::encode(self.signature(&signed, self.bound_value(self.binding, client)).finalize().into_bytes());
+        format!("{}?{}&sig={}", base, signed, sig)
+    }
+    /// Checks a request for `path_and_query`, e.g. `/movies/1/video_1080_00001.m4s?acl=...&sig=...`,
+    /// made by `client` at `now`, in seconds since the epoch
+    pub fn verify(&self, path_and_query: &str, client: &PlaybackClient, now: i64) -> Result<(), SignatureError> {
+        let (path, query) = path_and_query.split_once('?').ok_or(SignatureError::Missing)?;
+        let (signed, sig) = query.rsplit_once("&sig=").ok_or(SignatureError::Missing)?;
+        let params: Vec<(&str, &str)> = signed.split('&').filter_map(|param| param.split_once('=')).collect();
+        let param = |name: &str| params.iter().rev().find(|(key, _)| *key == name).map(|(_, value)| *value);
+        let binding = match param("bind") {
+            Some(binding) => binding.parse().map_err(|_| SignatureError::Invalid)?,
+            None => Binding::None,
+        };
+        let sig = hex::decode(sig).map_err(|_| SignatureError::Invalid)?;
+        self.signature(signed, self.bound_value(binding, client))
+            .verify_slice(&sig)
+            .map_err(|_| match binding {
+                Binding::None => SignatureError::Invalid,
+                _ => SignatureError::WrongClient,
+            })?;
+        let expires: i64 = param("exp").and_then(|exp| exp.parse().ok()).ok_or(SignatureError::Invalid)?;
+        if now >= expires {
+            return Err(SignatureError::Expired)
+        }
+        let acl = percent_decode_str(param("acl").ok_or(SignatureError::Invalid)?).decode_utf8_lossy();
+        let path = percent_decode_str(path).decode_utf8_lossy();
+        // The edge normalises the path before serving it, `acl/../other` would reach another file
+        if path.split('/').any(|segment| segment == "." || segment == "..") {
+            return Err(SignatureError::OutOfScope)
+        }
+        match path.strip_prefix(acl.as_ref()) {
+            Some(rest) if rest.contains("/..") => Err(SignatureError::OutOfScope),
+            Some(rest) if rest.is_empty() || rest.starts_with(|c| matches!(c, '.' | '_' | '-' | '/')) => Ok(()),
+            _ => Err(SignatureError::OutOfScope),
+        }
+    }
+    /// Presigned GET of `key` in `bucket`, valid until `expires` or a week, whichever is sooner
+    pub fn presign_s3(&self, bucket: &str, key: &str, expires: i64, now: i64) -> Option<String> {
+        let s3 = self.s3.as_ref()?;
+        let (origin, host, path) = match &s3.endpoint {
+            Some(endpoint) => {
+                let endpoint = endpoint.trim_end_matches('/');
+                let host = endpoint.split_once("://").map(|(_, host)| host).unwrap_or(endpoint);
+                (endpoint.to_string(), host.to_string(), format!("/{}/{}", bucket, key))
+            }
+            None => {
+                let host = format!("{}.s3.{}.amazonaws.com", bucket, s3.region);
+                (format!("https://{}", host), host, format!("/{}", key))
+            }
+        };
+        let at = Utc.timestamp(now, 0);
+        let (date, amz_date) = (at.format("%Y%m%d").to_string(), at.format("%Y%m%dT%H%M%SZ").to_string());
+        let scope = format!("{}/{}/s3/aws4_request", date, s3.region);
+        let query = format!(
+            "X-Amz-Algorithm=AWS4-HMAC-SHA256&X-Amz-Credential={}&X-Amz-Date={}&X-Amz-Expires={}&X-Amz-SignedHeaders=host",
+            utf8_percent_encode(&format!("{}/{}", s3.access_key_id, scope), UNRESERVED),
+            amz_date,
+            (expires - now).clamp(1, S3_MAX_EXPIRES),
+        );
+        let path = utf8_percent_encode(&path, PATH).to_string();
+        let canonical = format!("GET\n{}\n{}\nhost:{}\n\nhost\nUNSIGNED-PAYLOAD", path, query, host);
+        let to_sign = format!("AWS4-HMAC-SHA256\n{}\n{}\n{}", amz_date, scope, hex::encode(Sha256::digest(canonical.as_bytes())));
+        let key = [date.as_str(), s3.region.as_str(), "s3", "aws4_request"]
+            .iter()
+            .fold(format!("AWS4{}", s3.secret_access_key).into_bytes(), |key, part| hmac(&key, part.as_bytes()));
+        Some(format!("{}{}?{}&X-Amz-Signature={}", origin, path, query, hex::encode(hmac(&key, to_sign.as_bytes()))))
+    }
+}
+
+/// Random id of one `playbackInfo` response, what `Binding::Session` ties URLs to
+pub fn new_session() -> String {
+    hex::encode(rand::thread_rng().gen::<[u8; 16]>())
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC takes keys of any size");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Path of an absolute or root relative URL
+fn url_path(url: &str) -> &str {
+    match url.split_once("://") {
+        Some((_, rest)) => rest.find('/').map(|slash| &rest[slash..]).unwrap_or("/"),
+        None => url,
+    }
+}
@@ -0,0 +1,19 @@
+use actix_web::test::TestRequest;
+use common_utils::availability::RequestRegion;
+
+#[test]
+fn region_header_sent_by_the_client_is_ignored() {
+    let req = TestRequest::default().insert_header(("x-region", "GB")).to_http_request();
+
+    assert_eq!(RequestRegion::new(&req).region, None);
+}
+
+#[test]
+fn invalid_bearer_token_leaves_the_region_unknown() {
+    let req = TestRequest::default()
+        .insert_header(("Authorization", "Bearer not-a-token"))
+        .insert_header(("x-region", "GB"))
+        .to_http_request();
+
+    assert_eq!(RequestRegion::new(&req).region, None);
+}
@@ -0,0 +1,138 @@
+use common_utils::error::ServiceError;
+use common_utils::events::{
+    ActivityEvent, CatalogEvent, EventEnvelope, PartitionKey, RecommendationEvent, SeriesEvent, LEGACY_SCHEMA_VERSION,
+    peek_event_type,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Movie {
+    movie_id: i64,
+    title: String,
+}
+
+impl PartitionKey for Movie {
+    fn partition_key(&self) -> String {
+        self.movie_id.to_string()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Watch {
+    user_id: i32,
+    movie_id: i64,
+}
+
+impl PartitionKey for Watch {
+    fn partition_key(&self) -> String {
+        self.user_id.to_string()
+    }
+}
+
+type SeriesCatalogEvent = SeriesEvent<Movie, Movie, Movie>;
+
+fn movie() -> Movie {
+    Movie { movie_id: 42, title: String::from("Heat") }
+}
+
+#[test]
+fn legacy_movie_payload_is_upcast_to_an_update() {
+    let raw = json!({ "movie_id": 42, "title": "Heat" }).to_string();
+
+    let envelope = EventEnvelope::<CatalogEvent<Movie>>::decode(&raw).unwrap();
+
+    assert_eq!(envelope.payload, CatalogEvent::MovieUpdated(movie()));
+    assert_eq!(envelope.event_type, "catalog.movie.updated");
+    assert_eq!(envelope.schema_version, 1);
+    assert_eq!(envelope.producer, "unknown");
+}
+
+#[test]
+fn legacy_activity_and_recommendation_payloads_are_upcast() {
+    let raw = json!([{ "user_id": 7, "movie_id": 42 }]).to_string();
+    let activity = EventEnvelope::<ActivityEvent<Watch>>::decode(&raw).unwrap();
+    assert_eq!(activity.payload, ActivityEvent::WatchRecorded(vec![Watch { user_id: 7, movie_id: 42 }]));
+
+    let raw = json!({ "user_id": 7, "movie_id": 42 }).to_string();
+    let recommendation = EventEnvelope::<RecommendationEvent<Watch>>::decode(&raw).unwrap();
+    assert_eq!(recommendation.payload, RecommendationEvent::RecommendationGenerated(Watch { user_id: 7, movie_id: 42 }));
+}
+
+#[test]
+fn explicit_version_zero_envelope_is_upcast() {
+    let raw = json!({
+        "event_id": "2f1c9a52-8f0e-4a4a-9d1c-6b0f3b6f2a10",
+        "event_type": "",
+        "schema_version": LEGACY_SCHEMA_VERSION,
+        "occurred_at": "2022-06-01T12:00:00Z",
+        "producer": "asset_ingestion_service",
+        "payload": { "movie_id": 42, "title": "Heat" }
+    }).to_string();
+
+    let envelope = EventEnvelope::<CatalogEvent<Movie>>::decode(&raw).unwrap();
+
+    assert_eq!(envelope.payload, CatalogEvent::MovieUpdated(movie()));
+    assert_eq!(envelope.event_id.to_string(), "2f1c9a52-8f0e-4a4a-9d1c-6b0f3b6f2a10");
+    assert_eq!(envelope.producer, "asset_ingestion_service");
+    assert_eq!(envelope.trace_context.trace_id, None);
+}
+
+#[test]
+fn current_envelope_round_trips() {
+    let sent = EventEnvelope::new("asset_ingestion_service", CatalogEvent::MovieCreated(movie()));
+
+    let received = EventEnvelope::<CatalogEvent<Movie>>::decode(&sent.to_json().unwrap()).unwrap();
+
+    assert_eq!(received, sent);
+    assert_eq!(received.event_type, "catalog.movie.created");
+}
+
+#[test]
+fn id_only_events_round_trip() {
+    for event in [
+        CatalogEvent::<Movie>::MovieDeleted { movie_id: 42 },
+        CatalogEvent::MovieSoftDeleted { movie_id: 42, deleted_at: 1_654_084_800_000 },
+    ] {
+        let sent = EventEnvelope::new("asset_ingestion_service", event);
+        let received = EventEnvelope::<CatalogEvent<Movie>>::decode(&sent.to_json().unwrap()).unwrap();
+        assert_eq!(received.payload, sent.payload);
+    }
+}
+
+#[test]
+fn series_events_round_trip() {
+    let sent = EventEnvelope::new("asset_ingestion_service", SeriesCatalogEvent::SeasonDeleted { series_id: 3, season_number: 2 });
+
+    let received = EventEnvelope::<SeriesCatalogEvent>::decode(&sent.to_json().unwrap()).unwrap();
+
+    assert_eq!(received.payload, sent.payload);
+    assert_eq!(received.event_type, "catalog.series.season_deleted");
+}
+
+#[test]
+fn newer_schema_version_is_rejected() {
+    let mut envelope = serde_json::to_value(EventEnvelope::new("asset_ingestion_service", CatalogEvent::MovieCreated(movie()))).unwrap();
+    envelope["schema_version"] = json!(2);
+
+    let decoded = EventEnvelope::<CatalogEvent<Movie>>::decode(&envelope.to_string());
+
+    assert!(matches!(decoded, Err(ServiceError::BadRequest(_))));
+}
+
+#[test]
+fn unknown_event_kind_is_rejected() {
+    let mut envelope = serde_json::to_value(EventEnvelope::new("asset_ingestion_service", CatalogEvent::MovieCreated(movie()))).unwrap();
+    envelope["payload"]["kind"] = json!("movie_archived");
+
+    assert!(EventEnvelope::<CatalogEvent<Movie>>::decode(&envelope.to_string()).is_err());
+}
+
+#[test]
+fn event_type_is_peeked_without_decoding() {
+    let sent = EventEnvelope::new("asset_ingestion_service", SeriesCatalogEvent::SeriesDeleted { series_id: 3 });
+
+    assert_eq!(peek_event_type(&sent.to_json().unwrap()).as_deref(), Some("catalog.series.deleted"));
+    assert_eq!(peek_event_type(&json!({ "movie_id": 42 }).to_string()), None);
+    assert_eq!(peek_event_type("not json"), None);
+}
@@ -0,0 +1,20 @@
+use std::collections::HashSet;
+use std::thread;
+use common_utils::unique_id;
+
+#[test]
+fn ids_generated_within_the_same_millisecond_are_unique() {
+    let ids: Vec<i64> = (0..10_000).map(|_| unique_id(1, 1)).collect();
+
+    assert_eq!(ids.iter().collect::<HashSet<_>>().len(), ids.len());
+}
+
+#[test]
+fn ids_generated_across_threads_are_unique() {
+    let handles: Vec<_> = (0..4)
+        .map(|_| thread::spawn(|| (0..2_500).map(|_| unique_id(2, 1)).collect::<Vec<i64>>()))
+        .collect();
+    let ids: Vec<i64> = handles.into_iter().flat_map(|handle| handle.join().unwrap()).collect();
+
+    assert_eq!(ids.iter().collect::<HashSet<_>>().len(), 10_000);
+}
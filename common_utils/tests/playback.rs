@@ -0,0 +1,19 @@
+use actix_web::test::TestRequest;
+use common_utils::playback::{Plan, RequestPlan};
+
+#[test]
+fn plan_header_sent_by_the_client_is_ignored() {
+    let req = TestRequest::default().insert_header(("x-plan", "PREMIUM")).to_http_request();
+
+    assert_eq!(RequestPlan::new(&req).plan, Plan::Basic);
+}
+
+#[test]
+fn invalid_bearer_token_gets_the_default_plan() {
+    let req = TestRequest::default()
+        .insert_header(("Authorization", "Bearer not-a-token"))
+        .insert_header(("x-plan", "PREMIUM"))
+        .to_http_request();
+
+    assert_eq!(RequestPlan::new(&req).plan, Plan::Basic);
+}
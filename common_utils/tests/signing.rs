@@ -0,0 +1,76 @@
+use std::net::{IpAddr, SocketAddr};
+use actix_web::test::TestRequest;
+use common_utils::signing::{client_ip, Binding, PlaybackClient, SignatureError, UrlSigner};
+
+fn ip(addr: &str) -> IpAddr {
+    addr.parse().unwrap()
+}
+
+fn from(peer: &str) -> TestRequest {
+    TestRequest::default().peer_addr(SocketAddr::new(ip(peer), 40000))
+}
+
+#[test]
+fn forwarded_for_from_an_untrusted_peer_is_ignored() {
+    let req = from("203.0.113.7").insert_header(("X-Forwarded-For", "198.51.100.1")).to_http_request();
+
+    assert_eq!(client_ip(&req, &[]), Some(ip("203.0.113.7")));
+}
+
+#[test]
+fn trusted_proxy_gives_the_address_it_appended() {
+    let router = ip("10.0.0.2");
+    let req = from("10.0.0.2").insert_header(("X-Forwarded-For", "198.51.100.1, 203.0.113.7")).to_http_request();
+
+    assert_eq!(client_ip(&req, &[router]), Some(ip("203.0.113.7")));
+}
+
+#[test]
+fn every_trusted_hop_is_skipped() {
+    let trusted = [ip("10.0.0.2"), ip("10.0.0.3")];
+    let req = from("10.0.0.2").insert_header(("X-Forwarded-For", "198.51.100.1, 203.0.113.7, 10.0.0.3")).to_http_request();
+
+    assert_eq!(client_ip(&req, &trusted), Some(ip("203.0.113.7")));
+}
+
+#[test]
+fn garbage_in_forwarded_for_stops_the_walk() {
+    let router = ip("10.0.0.2");
+    let req = from("10.0.0.2").insert_header(("X-Forwarded-For", "198.51.100.1, unknown")).to_http_request();
+
+    assert_eq!(client_ip(&req, &[router]), Some(router));
+}
+
+/// The query of a URL signed for `/movies/1/video_1080.mp4`, which covers every `video_1080` file
+fn signed_query(signer: &UrlSigner) -> String {
+    let url = signer.sign("https://cdn.example.com/movies/1/video_1080.mp4", 2_000_000_000, &PlaybackClient::default());
+    url.split_once('?').unwrap().1.to_string()
+}
+
+#[test]
+fn signed_url_covers_the_segments_of_its_file() {
+    let signer = UrlSigner::new("secret", 3600, Binding::None);
+    let uri = format!("/movies/1/video_1080_00001.m4s?{}", signed_query(&signer));
+
+    assert_eq!(signer.verify(&uri, &PlaybackClient::default(), 1_700_000_000), Ok(()));
+}
+
+#[test]
+fn dot_segments_cannot_leave_the_signed_path() {
+    let signer = UrlSigner::new("secret", 3600, Binding::None);
+    let query = signed_query(&signer);
+
+    for path in ["/movies/1/video_1080/../../2/video_2160.mp4", "/movies/1/video_1080/./../../2/video_2160.mp4"] {
+        assert_eq!(signer.verify(&format!("{}?{}", path, query), &PlaybackClient::default(), 1_700_000_000), Err(SignatureError::OutOfScope));
+    }
+}
+
+#[test]
+fn percent_encoded_dot_segments_cannot_leave_the_signed_path() {
+    let signer = UrlSigner::new("secret", 3600, Binding::None);
+    let query = signed_query(&signer);
+
+    for path in ["/movies/1/video_1080/%2e%2e/%2E%2E/2/video_2160.mp4", "/movies/1/video_1080%2F..%2F..%2F2%2Fvideo_2160.mp4"] {
+        assert_eq!(signer.verify(&format!("{}?{}", path, query), &PlaybackClient::default(), 1_700_000_000), Err(SignatureError::OutOfScope));
+    }
+}
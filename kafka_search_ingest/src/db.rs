@@ -3,7 +3,8 @@ use elasticsearch::http::response::Response;
 use elasticsearch::{BulkParts, BulkUpdateOperation, BulkOperation, Reindex};
 use elasticsearch::auth::Credentials;
 use elasticsearch::http::request::JsonBody;
-use elasticsearch::indices::{IndicesExistsParts, IndicesDeleteParts};
+use elasticsearch::indices::{IndicesExistsParts, IndicesDeleteParts, IndicesPutMappingParts};
+use elasticsearch::DeleteByQueryParts;
 use elasticsearch::{Elasticsearch, Error, 
     IndexParts, GetParts, 
     DeleteParts, UpdateParts,
@@ -15,8 +16,11 @@ use url::Url;
 use elasticsearch::cert::CertificateValidation;
 use once_cell::sync::OnceCell;
 use serde_json::{json, Value};
-use crate::module::model::{Movie, MOVIE_MAPPING};
+use crate::module::model::{Movie, MovieTranslation, AvailabilityWindow, Series, Season, Episode, MOVIE_MAPPING, TRANSLATION_TEMPLATES, AVAILABILITY_MAPPING, open_territories};
+use common_utils::availability::now_millis;
+use common_utils::events::{CatalogEvent, SeriesEvent};
 use crate::server::recreate_index;
+use common_utils::metrics::{datastore_timer, ELASTIC_BULK_FAILURES};
 pub static ELASTIC_CLIENT: OnceCell<ElasticClient> = OnceCell::new();
 
 #[inline]
@@ -40,6 +44,10 @@ lazy_static! {
     static ref CLOUD_CLUSTER_URL: String = std::env::var("CLOUD_CLUSTER_URL").expect("Unable to validate Cloud Url");
     static ref INDEX_NAME: String = std::env::var("INDEX_NAME").expect("Invalid index name ");
     static ref ELASTIC_INDEX: String = std::env::var("ELASTIC_INDEX").expect("Invalid Index ");
+    // Series indices are created with dynamic mappings on their first write
+    static ref SERIES_INDEX: String = std::env::var("SERIES_INDEX").unwrap_or_else(|_| "series".into());
+    static ref SEASON_INDEX: String = std::env::var("SEASON_INDEX").unwrap_or_else(|_| "seasons".into());
+    static ref EPISODE_INDEX: String = std::env::var("EPISODE_INDEX").unwrap_or_else(|_| "episodes".into());
     // static ref RECREATE_INDEX: bool = std::env::var("RECREATE_INDEX").expect("Unable to read RECREATE_INDEX").parse().unwrap();
 
 }
@@ -91,18 +99,44 @@ pub async fn intiliase_index(client: Elasticsearch) -> Result<bool, Error> {
     // After several attempts to prevent "EOF while parsing a value" Error,
     // This is the simplest solution I could get, the error originated from attempting to 
     // deserialize an empty string. To solve this, It is best to return boolean rather than serde_json::Value 
-    match exist { 
+    let ready = match exist { 
         true => {
             log::info!("🚅 Running an existing INDEX");
-            Ok(true)
+            true
         },
         _ => {
             create_index()
                 .await
-                .map(|_| true)
+                .map(|_| true)?
         } 
-    }
-   
+    };
+    put_translation_templates().await?;
+    put_availability_mapping().await?;
+    Ok(ready)
+}
+/// Adds the per-language templates of `translations` to the index. Fields that were already
+/// mapped keep their mapping, only locales indexed from now on pick up the templates
+#[tracing::instrument(level = "debug", err)]
+pub async fn put_translation_templates() -> Result<Response, Error> { 
+    let response = elastisearch_client().0.indices()
+        .put_mapping(IndicesPutMappingParts::Index(&[&INDEX_NAME]))
+        .body(&*TRANSLATION_TEMPLATES)
+        .send()
+        .await?
+        .error_for_status_code()?;
+    Ok(response)
+}
+/// Maps the availability fields before any movie is indexed with them, a dynamic mapping would
+/// analyse the territory codes
+#[tracing::instrument(level = "debug", err)]
+pub async fn put_availability_mapping() -> Result<Response, Error> { 
+    let response = elastisearch_client().0.indices()
+        .put_mapping(IndicesPutMappingParts::Index(&[&INDEX_NAME]))
+        .body(&*AVAILABILITY_MAPPING)
+        .send()
+        .await?
+        .error_for_status_code()?;
+    Ok(response)
 }
 // Create a new Index
 #[tracing::instrument(level = "debug", err)]
@@ -126,38 +160,74 @@ pub async fn delete_index() -> Result<Response, Error> {
     Ok(response)
 }
 
+/// Movie writes waiting for the next bulk request, kept in the order they arrived so a movie
+/// deleted after an update in the same batch ends up deleted
+#[derive(Default)]
+pub struct MovieBatch { 
+    operations: Vec<BulkOperation<Value>>,
+}
+
+impl MovieBatch { 
+    /// Queues the write of `event`, returns the id of the movie when it leaves the index
+    pub fn push(&mut self, event: CatalogEvent<Movie>) -> Option<i64> { 
+        let (operation, removed) = match event { 
+            CatalogEvent::MovieCreated(movie) 
+            | CatalogEvent::MovieUpdated(movie) 
+            | CatalogEvent::MovieReindexRequested(movie) 
+            | CatalogEvent::MovieRestored(movie) => (upsert_movie_operation(&movie), None),
+            CatalogEvent::MovieDeleted { movie_id } 
+            | CatalogEvent::MovieSoftDeleted { movie_id, .. } => { 
+                let id = movie_id.to_string();
+                (BulkOperation::delete(&id).routing(&id).into(), Some(movie_id))
+            }
+        };
+        self.operations.push(operation);
+        removed
+    }
+    pub fn len(&self) -> usize { 
+        self.operations.len()
+    }
+    pub fn is_empty(&self) -> bool { 
+        self.operations.is_empty()
+    }
+}
+
+/// An update rather than an index, so the translations stored on the document are kept
+fn upsert_movie_operation(movie: &Movie) -> BulkOperation<Value> { 
+    let id = movie.movie_id.to_string();
+    BulkOperation::update(&id, json!({ "doc": movie, "doc_as_upsert": true })).routing(&id).into()
+}
+
 /// Index Movies collected from the Message Queue
 /// Bulk inserting the messages from Kafka -- This is mainly done for performance reasons 
 /// Assuming the volume of new movies is large, the bulk api makes it possible to perform many 
-/// index operations in a singel API call. This can greatly increase the indexing speed
-#[tracing::instrument(skip(movies), level = "debug", err)]
-pub async fn index_movie(movies: Vec<Movie>) -> Result<Vec<Movie>, Error> { 
-    log::info!("👏👏 Indexing movies from Apache Kafka: {:?}", movies);
-    if movies.is_empty() { return  Ok(Vec::new()) }
-    let body: Vec<BulkOperation<_>> = movies
-        .iter()
-        .map(|p| {
-            let id = p.movie_id.to_string();
-            BulkOperation::index(p).id(&id).routing(&id).into()
-        })
-        .collect();
+/// index operations in a singel API call. This can greatly increase the indexing speed.
+/// Deleted and soft deleted movies are removed in the same request, a delete of a document
+/// that is already gone only counts as a failure in the logs
+#[tracing::instrument(skip(batch), fields(operations = batch.len()), level = "debug", err)]
+pub async fn index_movie(batch: MovieBatch) -> Result<(), Error> { 
+    if batch.is_empty() { return Ok(()) }
+    let MovieBatch { operations } = batch;
+    let count = operations.len();
 
     //  We can also create add a Pipeline API 
+    let timer = datastore_timer("elasticsearch", "bulk_index");
     let bulk_insert = elastisearch_client()
         .0
         .bulk(BulkParts::Index(&INDEX_NAME))
-        .body(body)
+        .body(operations)
         .error_trace(true)
         .send()
-        .await
-        .expect("Unable to perform bulk insertion");
+        .await?;
     let response_body = bulk_insert.json::<Value>().await?;
+    timer.observe_duration();
     let err = response_body["errors"].as_bool().unwrap() == false;
 
     if err { 
-        log::info!("🚀 Successfully imported {}", movies.len());
+        log::info!("🚀 Successfully applied {} movie operations", count);
     } else { 
         log::info!("Failed Bulk operation: {:?}", response_body);
+        ELASTIC_BULK_FAILURES.inc_by(bulk_failures(&response_body) as u64);
         // client.bulk(BulkParts::(&INDEX_NAME)
         //     .retry_on_conflict(3))
         //     .body(body)
@@ -165,10 +235,243 @@ pub async fn index_movie(movies: Vec<Movie>) -> Result<Vec<Movie>, Error> {
         //     .send()
         //     .await;
     }
-    log::info!("🏁🏁 Finished Indexing: {:?}", movies);
+    log::info!("🏁🏁 Finished Indexing {} movie operations", count);
+
+    Ok(())
+}
+/// Number of items of a bulk response that failed, whatever their action
+fn bulk_failures(response_body: &Value) -> usize { 
+    //  Each item is keyed by its action, `index`, `update` or `delete`
+    response_body["items"]
+        .as_array()
+        .map(|items| items
+            .iter()
+            .filter_map(|item| item.as_object().and_then(|action| action.values().next()))
+            .filter(|result| !result["error"].is_null())
+            .count())
+        .unwrap_or_default()
+}
+
+/// Replaces the translations stored on each movie's document with the ones it was sent,
+/// keyed by locale so every language lands in its own `translations.<locale>.*` fields.
+/// A movie that isn't indexed yet gets a document holding only its translations
+#[tracing::instrument(skip(translations), fields(movies = translations.len()), level = "debug", err)]
+pub async fn index_translations(translations: Vec<(i64, Vec<MovieTranslation>)>) -> Result<(), Error> { 
+    if translations.is_empty() { return Ok(()) }
+    let body: Vec<BulkOperation<Value>> = translations
+        .iter()
+        .map(|(movie_id, translations)| { 
+            let id = movie_id.to_string();
+            let by_locale = translations
+                .iter()
+                .map(|f| (f.locale.clone(), json!({ "title": f.title, "overview": f.overview, "tagline": f.tagline, "poster": f.poster })))
+                .collect::<serde_json::Map<_, _>>();
+            let update = json!({ 
+                "script": { 
+                    "source": "ctx._source.translations = params.translations",
+                    "params": { "translations": by_locale }
+                },
+                "upsert": { "movie_id": movie_id, "translations": by_locale }
+            });
+            BulkOperation::update(&id, update).routing(&id).into()
+        })
+        .collect();
 
-    Ok(movies)
+    let timer = datastore_timer("elasticsearch", "bulk_translations");
+    let response_body = elastisearch_client()
+        .0
+        .bulk(BulkParts::Index(&INDEX_NAME))
+        .body(body)
+        .error_trace(true)
+        .send()
+        .await?
+        .json::<Value>()
+        .await?;
+    timer.observe_duration();
+    if response_body["errors"].as_bool().unwrap_or_default() { 
+        let failures = bulk_failures(&response_body);
+        log::warn!("{} of {} translation updates failed: {:?}", failures, translations.len(), response_body);
+        ELASTIC_BULK_FAILURES.inc_by(failures as u64);
+    } else { 
+        log::info!("🌐 Indexed the translations of {} movies", translations.len());
+    }
+    Ok(())
 }
+
+/// Sets `available_in` and `restricted` on each movie's document from the windows it was sent,
+/// as they stand now. The scheduler republishes a movie whenever one of its windows opens or
+/// closes, which is what keeps `available_in` current. A movie without windows is unrestricted
+#[tracing::instrument(skip(availability), fields(movies = availability.len()), level = "debug", err)]
+pub async fn index_availability(availability: Vec<(i64, Vec<AvailabilityWindow>)>) -> Result<(), Error> { 
+    if availability.is_empty() { return Ok(()) }
+    let now = now_millis();
+    let body: Vec<BulkOperation<Value>> = availability
+        .iter()
+        .map(|(movie_id, windows)| { 
+            let id = movie_id.to_string();
+            let params = json!({ "available_in": open_territories(windows, now), "restricted": !windows.is_empty() });
+            let update = json!({ 
+                "script": { 
+                    "source": "ctx._source.available_in = params.available_in; ctx._source.restricted = params.restricted",
+                    "params": params
+                },
+                "upsert": { "movie_id": movie_id, "available_in": params["available_in"], "restricted": params["restricted"] }
+            });
+            BulkOperation::update(&id, update).routing(&id).into()
+        })
+        .collect();
+
+    let timer = datastore_timer("elasticsearch", "bulk_availability");
+    let response_body = elastisearch_client()
+        .0
+        .bulk(BulkParts::Index(&INDEX_NAME))
+        .body(body)
+        .error_trace(true)
+        .send()
+        .await?
+        .json::<Value>()
+        .await?;
+    timer.observe_duration();
+    if response_body["errors"].as_bool().unwrap_or_default() { 
+        let failures = bulk_failures(&response_body);
+        log::warn!("{} of {} availability updates failed: {:?}", failures, availability.len(), response_body);
+        ELASTIC_BULK_FAILURES.inc_by(failures as u64);
+    } else { 
+        log::info!("🗺️ Indexed the availability of {} movies", availability.len());
+    }
+    Ok(())
+}
+
+pub type SeriesCatalogEvent = SeriesEvent<Series, Season, Episode>;
+
+fn season_document_id(series_id: i64, season_number: i32) -> String { 
+    format!("{}-{}", series_id, season_number)
+}
+
+fn episode_document_id(series_id: i64, season_number: i32, episode_number: i32) -> String { 
+    format!("{}-{}-{}", series_id, season_number, episode_number)
+}
+
+/// Series writes waiting for the next bulk request. Every document of a series is routed
+/// by its `series_id`, so they share a shard and are applied in the order they arrived
+#[derive(Default)]
+pub struct SeriesBatch { 
+    operations: Vec<BulkOperation<Value>>,
+    /// Deleted series, or seasons when the number is set, whose children still have to go
+    cascades: Vec<(i64, Option<i32>)>,
+}
+
+impl SeriesBatch { 
+    pub fn push(&mut self, event: SeriesCatalogEvent) { 
+        let operation = match event { 
+            SeriesEvent::SeriesUpserted(series) => index_operation(&SERIES_INDEX, series.series_id.to_string(), series.series_id, json!(series)),
+            SeriesEvent::SeasonUpserted(season) => index_operation(
+                &SEASON_INDEX, season_document_id(season.series_id, season.season_number), season.series_id, json!(season)),
+            SeriesEvent::EpisodeUpserted(episode) => index_operation(
+                &EPISODE_INDEX, episode_document_id(episode.series_id, episode.season_number, episode.episode_number), episode.series_id, json!(episode)),
+            SeriesEvent::SeriesDeleted { series_id } => { 
+                self.cascades.push((series_id, None));
+                delete_operation(&SERIES_INDEX, series_id.to_string(), series_id)
+            }
+            SeriesEvent::SeasonDeleted { series_id, season_number } => { 
+                self.cascades.push((series_id, Some(season_number)));
+                delete_operation(&SEASON_INDEX, season_document_id(series_id, season_number), series_id)
+            }
+            SeriesEvent::EpisodeDeleted { series_id, season_number, episode_number } => 
+                delete_operation(&EPISODE_INDEX, episode_document_id(series_id, season_number, episode_number), series_id),
+        };
+        self.operations.push(operation);
+    }
+    pub fn len(&self) -> usize { 
+        self.operations.len()
+    }
+    pub fn is_empty(&self) -> bool { 
+        self.operations.is_empty()
+    }
+    /// Cascading deletes run after the bulk request, so nothing that follows them may share the batch
+    pub fn has_cascade(&self) -> bool { 
+        !self.cascades.is_empty()
+    }
+}
+
+fn index_operation(index: &str, id: String, series_id: i64, document: Value) -> BulkOperation<Value> { 
+    BulkOperation::index(document)
+        .index(index)
+        .id(id)
+        .routing(series_id.to_string())
+        .into()
+}
+
+fn delete_operation(index: &str, id: String, series_id: i64) -> BulkOperation<Value> { 
+    BulkOperation::delete(id)
+        .index(index)
+        .routing(series_id.to_string())
+        .into()
+}
+
+/// Applies the pending series writes in a single bulk request across the series indices,
+/// then removes the seasons and episodes left behind by deleted series and seasons
+#[tracing::instrument(skip(batch), fields(operations = batch.len()), level = "debug", err)]
+pub async fn index_series(batch: SeriesBatch) -> Result<(), Error> { 
+    if batch.is_empty() { return Ok(()) }
+    let SeriesBatch { operations, cascades } = batch;
+    let count = operations.len();
+
+    let timer = datastore_timer("elasticsearch", "bulk_series");
+    let response_body = elastisearch_client()
+        .0
+        .bulk(BulkParts::None)
+        .body(operations)
+        .error_trace(true)
+        .send()
+        .await?
+        .json::<Value>()
+        .await?;
+    timer.observe_duration();
+    if response_body["errors"].as_bool().unwrap_or_default() { 
+        let failures = bulk_failures(&response_body);
+        log::warn!("{} of {} series operations failed: {:?}", failures, count, response_body);
+        ELASTIC_BULK_FAILURES.inc_by(failures as u64);
+    } else { 
+        log::info!("🚀 Applied {} series operations", count);
+    }
+
+    for (series_id, season_number) in cascades { 
+        delete_series_children(series_id, season_number).await?;
+    }
+    Ok(())
+}
+
+/// Deletes every season and episode of a series, or only the episodes of one season
+#[tracing::instrument(level = "debug", err)]
+async fn delete_series_children(series_id: i64, season_number: Option<i32>) -> Result<(), Error> { 
+    let (indices, query) = match season_number { 
+        Some(season_number) => (
+            vec![EPISODE_INDEX.as_str()],
+            json!({ "query": { "bool": { "filter": [
+                { "term": { "series_id": series_id } },
+                { "term": { "season_number": season_number } }
+            ]}}})
+        ),
+        None => (
+            vec![SEASON_INDEX.as_str(), EPISODE_INDEX.as_str()],
+            json!({ "query": { "term": { "series_id": series_id } } })
+        ),
+    };
+    let routing = series_id.to_string();
+    let _timer = datastore_timer("elasticsearch", "delete_by_query");
+    elastisearch_client()
+        .0
+        .delete_by_query(DeleteByQueryParts::Index(&indices))
+        .routing(&[routing.as_str()])
+        .ignore_unavailable(true)
+        .body(query)
+        .send()
+        .await?
+        .error_for_status_code()?;
+    Ok(())
+}
+
 /// Get APIedit
 /// Retrieves the specified JSON document from an index.
 #[tracing::instrument(level = "debug", err)]
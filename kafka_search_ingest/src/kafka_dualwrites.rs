@@ -1,29 +1,72 @@
-use std::sync::Mutex;
-use futures::StreamExt;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use async_trait::async_trait;
 use lazy_static::lazy_static;
 use rdkafka::config::RDKafkaLogLevel;
 use rdkafka::consumer::{Consumer, StreamConsumer, CommitMode};
 use rdkafka::error::KafkaError;
-use rdkafka::message::BorrowedMessage;
-use rdkafka::{ClientConfig, ClientContext, Message};
+use rdkafka::message::{BorrowedMessage, OwnedHeaders};
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use rdkafka::{ClientConfig, Message, TopicPartitionList};
 use once_cell::sync::OnceCell;
-use crate::db::index_movie;
-use crate::module::model::Movie;
+use rdkafka::Offset;
+use common_utils::{health::ConsumerProbe, metrics::{KAFKA_CONSUMED, KAFKA_CONSUMER_LAG}};
+use crate::db::{index_availability, index_movie, index_series, index_translations, MovieBatch, SeriesBatch, SeriesCatalogEvent};
+use crate::module::model::{AvailabilityWindow, Movie, MovieTranslation};
+use common_utils::events::{AvailabilityEvent, CatalogEvent, Event, EventEnvelope, TranslationEvent, peek_event_type};
+use common_utils::{QueryResult, error::ServiceError};
+use serde::de::DeserializeOwned;
+use tokio::sync::watch;
 
 lazy_static! {
     pub static ref KAFKA_BROKER: String = std::env::var("KAFKA_BROKER").expect("Can't read Kafka broker address");
     static ref KAFKA_TOPIC: String = std::env::var("KAFKA_TOPIC").expect("Can't read Kafka topic name");
-    static ref MESSAGE_KEY: String = std::env::var("MESSAGE_KEY").expect("Unable to get a valid message key");
     static ref CONSUMER_GROUP_ID: String = std::env::var("CONSUMER_GROUP_ID").expect("Expected a valid group id for consumers");
+    /// Readiness fails once the consumer falls this many messages behind
+    pub static ref KAFKA_MAX_LAG: i64 = std::env::var("KAFKA_MAX_LAG")
+        .ok()
+        .and_then(|lag| lag.parse::<i64>().ok())
+        .unwrap_or(10_000);
+    /// Readiness fails when the consumer is behind and has not polled for this many seconds
+    pub static ref KAFKA_MAX_POLL_AGE: i64 = std::env::var("KAFKA_MAX_POLL_AGE")
+        .ok()
+        .and_then(|age| age.parse::<i64>().ok())
+        .unwrap_or(60);
+    /// Largest number of movie and series writes flushed in a single batch
+    static ref CONSUMER_BATCH_SIZE: usize = std::env::var("CONSUMER_BATCH_SIZE")
+        .ok()
+        .and_then(|size| size.parse::<usize>().ok())
+        .unwrap_or(100);
+    /// Longest time a partially filled batch waits before it is flushed
+    static ref CONSUMER_BATCH_TIMEOUT: Duration = std::env::var("CONSUMER_BATCH_TIMEOUT_MS")
+        .ok()
+        .and_then(|timeout| timeout.parse::<u64>().ok())
+        .map(Duration::from_millis)
+        .unwrap_or(Duration::from_secs(1));
+    /// Times a batch is sent to Elasticsearch before it is dead lettered
+    static ref INDEX_ATTEMPTS: u32 = std::env::var("INDEX_ATTEMPTS")
+        .ok()
+        .and_then(|attempts| attempts.parse::<u32>().ok())
+        .unwrap_or(3);
+    /// Wait before the first retry of a batch, doubled on every attempt
+    static ref INDEX_RETRY_BACKOFF: Duration = std::env::var("INDEX_RETRY_BACKOFF_MS")
+        .ok()
+        .and_then(|backoff| backoff.parse::<u64>().ok())
+        .map(Duration::from_millis)
+        .unwrap_or(Duration::from_millis(500));
+    /// Batches Elasticsearch keeps rejecting are parked here, with the position they were read at
+    static ref DEAD_LETTER_TOPIC: String = std::env::var("DEAD_LETTER_TOPIC").unwrap_or_else(|_| String::from("search_ingest_dead_letters"));
 }
 
 lazy_static! {
     pub static ref BATCH_KAFKA_TOPIC: String = std::env::var("BATCH_KAFKA_TOPIC").expect("Can't read Kafka topic name");
-    static ref BATCH_MESSAGE_KEY: String = std::env::var("BATCH_MESSAGE_KEY").expect("Unable to get a valid message key");
     static ref BATCH_CONSUMER_GROUP_ID: String = std::env::var("BATCH_CONSUMER_GROUP_ID").expect("Expected a valid group id for consumers");
 }
 
 pub static KAFKACONN: OnceCell<KafkaClientContext> = OnceCell::new();
+static DEAD_LETTERS: OnceCell<FutureProducer> = OnceCell::new();
+/// Last time the consume loop came back from the broker, reported by `/readyz`
+pub static CONSUMER_PROBE: ConsumerProbe = ConsumerProbe::new();
 
 #[inline]
 pub(crate) fn kafka_client() -> &'static KafkaClientContext { 
@@ -58,6 +101,267 @@ impl<'a> From<&'a BorrowedMessage<'a>> for MessagePayload {
     }
 }
 
+/// A message taken off one of the topics, owned so it outlives the poll and can be retried
+#[derive(Debug, Clone)]
+pub struct Received {
+    pub topic: String,
+    pub partition: i32,
+    pub offset: i64,
+    pub payload: MessagePayload,
+}
+
+impl Received {
+    pub fn new(topic: &str, partition: i32, offset: i64, payload: &str) -> Self {
+        Self { topic: topic.to_string(), partition, offset, payload: MessagePayload(payload.to_string()) }
+    }
+}
+
+impl<'a> From<&'a BorrowedMessage<'a>> for Received {
+    fn from(bm: &'a BorrowedMessage) -> Self {
+        Self {
+            topic: bm.topic().to_string(),
+            partition: bm.partition(),
+            offset: bm.offset(),
+            payload: MessagePayload::from(bm),
+        }
+    }
+}
+
+/// Where the consume loop reads from and commits to
+#[async_trait]
+pub trait MessageSource: Sync {
+    /// Waits for the next message. An error is logged and polling carries on
+    async fn recv(&self) -> QueryResult<Received>;
+    /// Commits the position right after the last of `messages` on each partition
+    fn commit(&self, messages: &[Received]) -> QueryResult<()>;
+    /// Parks messages that could not be indexed, so committing them doesn't lose them
+    async fn dead_letter(&self, messages: &[Received], reason: &str) -> QueryResult<()>;
+}
+
+/// Where the decoded batches are written
+#[async_trait]
+pub trait SearchIndex: Sync {
+    async fn index(&self, batch: Batch) -> QueryResult<()>;
+}
+
+/// Size and retry limits of the consume loop
+#[derive(Debug, Clone)]
+pub struct BatchSettings {
+    /// Largest number of writes flushed at once
+    pub size: usize,
+    /// Longest time a partially filled batch waits before it is flushed
+    pub timeout: Duration,
+    /// Times a batch is indexed before it is dead lettered
+    pub attempts: u32,
+    /// Wait before the first retry, doubled on every attempt
+    pub backoff: Duration,
+}
+
+impl BatchSettings {
+    pub fn from_env() -> Self {
+        Self {
+            size: *CONSUMER_BATCH_SIZE,
+            timeout: *CONSUMER_BATCH_TIMEOUT,
+            attempts: *INDEX_ATTEMPTS,
+            backoff: *INDEX_RETRY_BACKOFF,
+        }
+    }
+}
+
+/// Every write decoded from one batch of messages, indexed together
+#[derive(Default)]
+pub struct Batch {
+    pub movies: MovieBatch,
+    pub series: SeriesBatch,
+    pub translations: Vec<(i64, Vec<MovieTranslation>)>,
+    pub availability: Vec<(i64, Vec<AvailabilityWindow>)>,
+    /// Movies whose last write in the batch takes them out of the index
+    removed: HashSet<i64>,
+}
+
+impl Batch {
+    /// Decodes the messages again, for a retry of a batch that was already sent
+    pub fn from_messages(messages: &[Received]) -> Self {
+        let mut batch = Self::default();
+        for message in messages {
+            batch.push(message);
+        }
+        batch
+    }
+    pub fn len(&self) -> usize {
+        self.movies.len() + self.series.len() + self.translations.len() + self.availability.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Queues the write carried by `message`, a message that can't be decoded is skipped.
+    /// Returns true when nothing else may join the batch
+    pub fn push(&mut self, message: &Received) -> bool {
+        let payload = message.payload.as_str();
+        if is_series_event(payload) {
+            if let Some(event) = decode::<SeriesCatalogEvent>(message) {
+                self.series.push(event);
+            }
+            return self.series.has_cascade()
+        }
+        if is_translation_event(payload) {
+            //  Every event carries the full set, only the latest one of a movie needs applying
+            if let Some(TranslationEvent::<MovieTranslation>::MovieTranslated { movie_id, translations }) = decode(message) {
+                if self.removed.contains(&movie_id) {
+                    return false
+                }
+                self.translations.retain(|(id, _)| *id != movie_id);
+                self.translations.push((movie_id, translations));
+            }
+            return false
+        }
+        if is_availability_event(payload) {
+            if let Some(AvailabilityEvent::<AvailabilityWindow>::AvailabilityChanged { movie_id, windows }) = decode(message) {
+                if self.removed.contains(&movie_id) {
+                    return false
+                }
+                self.availability.retain(|(id, _)| *id != movie_id);
+                self.availability.push((movie_id, windows));
+            }
+            return false
+        }
+        if let Some(event) = decode::<CatalogEvent<Movie>>(message) {
+            //  Translations and availability are flushed after the movies and upsert their document,
+            //  whatever is pending for a movie that leaves the index, or arrives after it left,
+            //  would bring a stub of it back
+            let movie_id = catalog_movie_id(&event);
+            if self.movies.push(event).is_some() {
+                self.translations.retain(|(id, _)| *id != movie_id);
+                self.availability.retain(|(id, _)| *id != movie_id);
+                self.removed.insert(movie_id);
+            } else {
+                self.removed.remove(&movie_id);
+            }
+        }
+        false
+    }
+}
+
+fn catalog_movie_id(event: &CatalogEvent<Movie>) -> i64 {
+    match event {
+        CatalogEvent::MovieCreated(movie)
+        | CatalogEvent::MovieUpdated(movie)
+        | CatalogEvent::MovieReindexRequested(movie)
+        | CatalogEvent::MovieRestored(movie) => movie.movie_id,
+        CatalogEvent::MovieDeleted { movie_id }
+        | CatalogEvent::MovieSoftDeleted { movie_id, .. } => *movie_id,
+    }
+}
+
+fn decode<T: Event + DeserializeOwned>(message: &Received) -> Option<T> {
+    match EventEnvelope::<T>::decode(message.payload.as_str()) {
+        Ok(event) => {
+            log::info!("📨 {} v{} from {}", event.event_type, event.schema_version, event.producer);
+            Some(event.payload)
+        }
+        Err(e) => {
+            log::error!("❌ Skipping undecodable event at offset {}: {}", message.offset, e);
+            KAFKA_CONSUMED.with_label_values(&[message.topic.as_str(), "malformed"]).inc();
+            None
+        }
+    }
+}
+
+/// The Kafka consumer and the producer of the dead letter topic
+pub struct KafkaSource;
+
+#[async_trait]
+impl MessageSource for KafkaSource {
+    async fn recv(&self) -> QueryResult<Received> {
+        let message = kafka_client()
+            .0
+            .recv()
+            .await
+            .map_err(|e| ServiceError::MessageBrokerError(e.to_string()))?;
+        let received = Received::from(&message);
+        log::info!("👷‍♂️👷‍♂️ key: '{:?}', payload: '{:?}', topic: {}, partition: {}, offset: {}, timestamp: {:?}",
+            message.key(),
+            received.payload,
+            received.topic,
+            received.partition,
+            received.offset,
+            message.timestamp());
+        Ok(received)
+    }
+    fn commit(&self, messages: &[Received]) -> QueryResult<()> {
+        let mut offsets = TopicPartitionList::new();
+        for ((topic, partition), offset) in last_offsets(messages) {
+            offsets
+                .add_partition_offset(topic, partition, Offset::Offset(offset + 1))
+                .map_err(|e| ServiceError::MessageBrokerError(e.to_string()))?;
+        }
+        kafka_client()
+            .0
+            .commit(&offsets, CommitMode::Sync)
+            .map_err(|e| ServiceError::MessageBrokerError(e.to_string()))
+    }
+    async fn dead_letter(&self, messages: &[Received], reason: &str) -> QueryResult<()> {
+        let producer = dead_letter_producer()?;
+        for message in messages {
+            let headers = OwnedHeaders::new()
+                .add("source_topic", message.topic.as_str())
+                .add("source_partition", &message.partition.to_string())
+                .add("source_offset", &message.offset.to_string())
+                .add("error", reason);
+            producer
+                .send(
+                    FutureRecord::<(), _>::to(&DEAD_LETTER_TOPIC).payload(message.payload.as_str()).headers(headers),
+                    Duration::from_secs(5))
+                .await
+                .map_err(|(e, _)| ServiceError::MessageBrokerError(e.to_string()))?;
+        }
+        log::warn!("📪 Dead lettered {} messages to {}: {}", messages.len(), DEAD_LETTER_TOPIC.as_str(), reason);
+        Ok(())
+    }
+}
+
+fn dead_letter_producer() -> QueryResult<&'static FutureProducer> {
+    DEAD_LETTERS.get_or_try_init(|| ClientConfig::new()
+        .set("bootstrap.servers", KAFKA_BROKER.as_str())
+        .set("message.timeout.ms", "5000")
+        .set("enable.idempotence", "true")
+        .create())
+        .map_err(|e| ServiceError::MessageBrokerError(e.to_string()))
+}
+
+/// Highest offset read from each partition
+pub fn last_offsets(messages: &[Received]) -> HashMap<(&str, i32), i64> {
+    let mut offsets: HashMap<(&str, i32), i64> = HashMap::new();
+    for message in messages {
+        let offset = offsets.entry((message.topic.as_str(), message.partition)).or_insert(message.offset);
+        *offset = (*offset).max(message.offset);
+    }
+    offsets
+}
+
+/// The Elasticsearch indices of movies and series
+pub struct ElasticIndex;
+
+#[async_trait]
+impl SearchIndex for ElasticIndex {
+    async fn index(&self, batch: Batch) -> QueryResult<()> {
+        let Batch { movies, series, translations, availability, .. } = batch;
+        log::info!("🛬 Received {} movie writes, Sending them over to Elasticsearch Cluster ", movies.len());
+        index_movie(movies)
+            .await
+            .map_err(|e| ServiceError::ServerError(e.to_string()))?;
+        index_series(series)
+            .await
+            .map_err(|e| ServiceError::ServerError(e.to_string()))?;
+        index_translations(translations)
+            .await
+            .map_err(|e| ServiceError::ServerError(e.to_string()))?;
+        index_availability(availability)
+            .await
+            .map_err(|e| ServiceError::ServerError(e.to_string()))
+    }
+}
+
 // Create the `StreamConsumer`, to receive the messages from the topic in form of a `Stream`.
 #[tracing::instrument(level = "debug", err)]
 pub fn create_consumer_dual_writes() -> Result<(), KafkaError> {
@@ -69,9 +373,8 @@ pub fn create_consumer_dual_writes() -> Result<(), KafkaError> {
         .set("auto.offset.reset", "latest")
         .set("enable.partition.eof", "true")
         .set("session.timeout.ms", "6000")
-        .set("enable.auto.commit", "true")
-        //  Commit every 5 seconds
-        .set("auto.commit.interval.ms", "5000")
+        //  Offsets are committed by the consume loop once a batch has been indexed
+        .set("enable.auto.commit", "false")
         .set_log_level(RDKafkaLogLevel::Debug)
         .create()
         .expect("Consumer creation failed");
@@ -83,40 +386,126 @@ pub fn create_consumer_dual_writes() -> Result<(), KafkaError> {
     Ok(())
 }
 /// Consumer group for the Asset Service where the payload 
-///  is sent to ElasticSearch to be indexed
-#[tracing::instrument(level = "debug")]
-pub async fn run_consumer_group_dual_writes() -> Result<(), KafkaError> { 
-    //  Create Kafka Consumer
+///  is sent to ElasticSearch to be indexed.
+#[tracing::instrument(level = "debug", skip(shutdown), err)]
+pub async fn run_consumer_group_dual_writes(shutdown: watch::Receiver<bool>) -> QueryResult<()> { 
     log::info!("🚦 Spawning consumer group: {}", CONSUMER_GROUP_ID.as_str());
-    let stream = kafka_client();
-    loop {
-        match stream.0.recv().await {
-            Err(e) => {
-                log::warn!("Kafka error: {}", e);
-            }
-            
-            Ok(message) => {
-                let payload = MessagePayload::from(&message);
-                let mut index_movies: Vec<Movie> = Vec::new();
-                log::info!("👷‍♂️👷‍♂️ key: '{:?}', payload: '{:?}', topic: {}, partition: {}, offset: {}, timestamp: {:?}",
-                    message.key(), 
-                    payload, 
-                    message.topic(), 
-                    message.partition(), 
-                    message.offset(), 
-                    message.timestamp());
-                
-                log::info!("MOVIE STRUCT {:#?}", index_movies);
-                // Pass this dataset to Elasticsearch to be indexed 
-                log::info!("🛬 Received Payload from {}, Sending it over to Elasticsearch Cluster ", KAFKA_BROKER.as_str());
-                let movies_struct: Movie = serde_json::from_str(payload.as_str()).expect("Something went wrong in the payoad");
-                index_movies.push(movies_struct);
-                
-                index_movie(index_movies)
-                    .await
-                    .expect("Unable to index the movie at {payload:?}");
+    consume_batches(&KafkaSource, &ElasticIndex, &BatchSettings::from_env(), shutdown).await?;
+    log::info!("🛑 Consumer group {} stopped polling, offsets committed", CONSUMER_GROUP_ID.as_str());
+    Ok(())
+}
+
+/// Messages are collected into batches that are bulk indexed before their offsets are 
+/// committed synchronously, so a restart never skips a movie that was not indexed. Once 
+/// `shutdown` fires the loop stops polling, flushes the current batch and returns
+pub async fn consume_batches<S: MessageSource, I: SearchIndex>(
+    source: &S,
+    index: &I,
+    settings: &BatchSettings,
+    mut shutdown: watch::Receiver<bool>,
+) -> QueryResult<()> { 
+    let mut stopping = false;
+    while !stopping {
+        let mut batch = Batch::default();
+        let mut messages: Vec<Received> = Vec::new();
+        let window = tokio::time::sleep(settings.timeout);
+        tokio::pin!(window);
 
+        while batch.len() < settings.size {
+            let message = tokio::select! {
+                //  Stop polling, whatever was already received is still flushed below
+                _ = shutdown.changed() => {
+                    stopping = true;
+                    break
+                }
+                _ = &mut window => break,
+                message = source.recv() => message,
+            };
+            CONSUMER_PROBE.record_poll();
+            let message = match message {
+                Err(e) => {
+                    log::warn!("Kafka error: {}", e);
+                    continue
+                }
+                Ok(message) => message
+            };
+            KAFKA_CONSUMED.with_label_values(&[message.topic.as_str(), "received"]).inc();
+            let full = batch.push(&message);
+            messages.push(message);
+            if full {
+                break
             }
+        }
+        if messages.is_empty() { 
+            continue
+        }
+        flush(source, index, settings, batch, &messages).await?;
+        //  Only commit once the whole batch has been indexed, or parked on the dead letter topic
+        source.commit(&messages)?;
+    }
+    Ok(())
+}
+
+/// Indexes a batch, retrying with a backoff. A batch that still fails is dead lettered,
+/// and only when that fails too is the error returned, leaving the offsets uncommitted
+async fn flush<S: MessageSource, I: SearchIndex>(
+    source: &S,
+    index: &I,
+    settings: &BatchSettings,
+    batch: Batch,
+    messages: &[Received],
+) -> QueryResult<()> {
+    let mut batch = Some(batch);
+    let mut backoff = settings.backoff;
+    let mut attempt = 1;
+    loop {
+        //  A batch is consumed by indexing it, retries decode the messages again
+        let pending = batch.take().unwrap_or_else(|| Batch::from_messages(messages));
+        let error = match index.index(pending).await {
+            Ok(()) => return Ok(()),
+            Err(e) => e
         };
+        if attempt >= settings.attempts.max(1) {
+            log::error!("❌ Unable to index {} messages after {} attempts: {}", messages.len(), attempt, error);
+            for message in messages {
+                KAFKA_CONSUMED.with_label_values(&[message.topic.as_str(), "dead_lettered"]).inc();
+            }
+            return source.dead_letter(messages, &error.to_string()).await
+        }
+        log::warn!("Indexing attempt {} of {} failed, retrying in {:?}: {}", attempt, settings.attempts, backoff, error);
+        tokio::time::sleep(backoff).await;
+        backoff *= 2;
+        attempt += 1;
     }
-}
\ No newline at end of file
+}
+
+/// Series events share the movie topic, everything else on it is a `CatalogEvent`
+fn is_series_event(raw: &str) -> bool { 
+    peek_event_type(raw).map_or(false, |event_type| event_type.starts_with("catalog.series."))
+}
+
+/// Translation events share the movie topic too, they update the movie documents in place
+fn is_translation_event(raw: &str) -> bool { 
+    peek_event_type(raw).map_or(false, |event_type| event_type.starts_with("catalog.translations."))
+}
+
+/// Availability events are on the movie topic as well, they set which territories a movie is searchable in
+fn is_availability_event(raw: &str) -> bool { 
+    peek_event_type(raw).map_or(false, |event_type| event_type.starts_with("catalog.availability."))
+}
+
+/// Total number of messages the consumer is behind across its assigned partitions.
+/// Fetching the watermarks blocks, so call this off the async workers
+pub fn consumer_lag() -> Result<i64, KafkaError> { 
+    let consumer = &kafka_client().0;
+    let positions = consumer.position()?;
+    let mut lag = 0;
+    for partition in positions.elements() { 
+        let (_, high) = consumer.fetch_watermarks(partition.topic(), partition.partition(), Duration::from_secs(1))?;
+        if let Offset::Offset(position) = partition.offset() { 
+            lag += (high - position).max(0);
+        }
+    }
+    KAFKA_CONSUMER_LAG.with_label_values(&[CONSUMER_GROUP_ID.as_str()]).set(lag);
+    Ok(lag)
+}
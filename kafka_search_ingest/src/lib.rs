@@ -4,6 +4,7 @@ extern crate serde_json;
 extern crate serde_derive;
 
 pub mod server;
+pub mod health;
 pub mod db;
 pub mod kafka_dualwrites;
 pub mod telemetry;
@@ -2,6 +2,7 @@ use serde::{Serialize, Deserialize};
 use chrono::NaiveDate;
 use lazy_static::lazy_static;
 use serde_json::json;
+use common_utils::{availability::is_open, events::PartitionKey};
 
 // These are temporary values, they are subject to change as the project progresses 
 lazy_static! { 
@@ -167,6 +168,67 @@ lazy_static! {
     });
 }
 
+/// Analyzer of each language a translation can be written in, by language subtag.
+/// Languages without a built-in analyzer are indexed with the index's default one
+const TRANSLATION_ANALYZERS: &[(&str, &str)] = &[
+    ("ar", "arabic"), ("bg", "bulgarian"), ("ca", "catalan"), ("cs", "czech"), ("da", "danish"),
+    ("de", "german"), ("el", "greek"), ("en", "english"), ("es", "spanish"), ("eu", "basque"),
+    ("fa", "persian"), ("fi", "finnish"), ("fr", "french"), ("ga", "irish"), ("gl", "galician"),
+    ("hi", "hindi"), ("hu", "hungarian"), ("hy", "armenian"), ("id", "indonesian"), ("it", "italian"),
+    ("ja", "cjk"), ("ko", "cjk"), ("lt", "lithuanian"), ("lv", "latvian"), ("nl", "dutch"),
+    ("no", "norwegian"), ("pt", "portuguese"), ("ro", "romanian"), ("ru", "russian"), ("sv", "swedish"),
+    ("th", "thai"), ("tr", "turkish"), ("zh", "cjk"),
+];
+
+fn dynamic_template(name: String, path_match: String, mapping: serde_json::Value) -> serde_json::Value { 
+    let mut template = serde_json::Map::new();
+    template.insert(name, json!({ "path_match": path_match, "match_mapping_type": "string", "mapping": mapping }));
+    serde_json::Value::Object(template)
+}
+
+fn translated_text(analyzer: Option<&str>) -> serde_json::Value { 
+    let mut mapping = json!({ 
+        "type": "text",
+        "fields": { "raw": { "type": "keyword", "ignore_above": 256 } }
+    });
+    if let Some(analyzer) = analyzer { 
+        mapping["analyzer"] = json!(analyzer);
+    }
+    mapping
+}
+
+lazy_static! { 
+    /// Dynamic templates for `translations.<locale>.*`, each locale's text is analysed in its own
+    /// language, e.g. `translations.pt-BR.title` with the portuguese analyzer. Templates are
+    /// tried in order, so the poster is matched before the text templates
+    pub(crate) static ref TRANSLATION_TEMPLATES: serde_json::Value = { 
+        let mut templates = vec![dynamic_template(
+            "translation_poster".to_string(),
+            "translations.*.poster".to_string(),
+            json!({ "type": "keyword", "index": false }),
+        )];
+        for &(language, analyzer) in TRANSLATION_ANALYZERS { 
+            //  `pt` itself and every regional `pt-XX`, without catching a language that merely starts with `pt`
+            for (i, path_match) in [format!("translations.{}.*", language), format!("translations.{}-*", language)].into_iter().enumerate() { 
+                templates.push(dynamic_template(format!("translation_{}_{}", language, i), path_match, translated_text(Some(analyzer))));
+            }
+        }
+        templates.push(dynamic_template("translation_text".to_string(), "translations.*".to_string(), translated_text(None)));
+        json!({ "dynamic_templates": templates })
+    };
+}
+
+lazy_static! { 
+    /// `available_in` holds the territories a movie's open windows cover, `WW` included, and
+    /// `restricted` whether it has windows at all. Both are matched exactly, never analysed
+    pub(crate) static ref AVAILABILITY_MAPPING: serde_json::Value = json!({
+        "properties": {
+            "available_in": { "type": "keyword" },
+            "restricted": { "type": "boolean" }
+        }
+    });
+}
+
 // Define custom struct that matches User Defined Type created earlier
 // wrapping field in Option will gracefully handle null field values
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -197,6 +259,75 @@ pub struct Movie {
     pub video_file: String,
 }
 
+impl PartitionKey for Movie { 
+    fn partition_key(&self) -> String {
+        self.movie_id.to_string()
+    }
+}
+
+/// Series, season and episode documents, as published by the ingestion service
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all(deserialize = "snake_case"))]
+pub struct Series { 
+    pub series_id: i64,
+    pub created_by: Vec<String>,
+    pub first_air_date: NaiveDate,
+    pub genres: Vec<String>,
+    pub homepage: String,
+    pub languages: Vec<String>,
+    pub last_air_date: Option<NaiveDate>,
+    pub overview: String,
+    pub poster: String,
+    pub rated: String,
+    pub status: String,
+    pub title: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all(deserialize = "snake_case"))]
+pub struct Season { 
+    pub series_id: i64,
+    pub season_number: i32,
+    pub air_date: Option<NaiveDate>,
+    pub name: String,
+    pub overview: String,
+    pub poster: String,
+    pub season_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all(deserialize = "snake_case"))]
+pub struct Episode { 
+    pub series_id: i64,
+    pub season_number: i32,
+    pub episode_number: i32,
+    pub air_date: Option<NaiveDate>,
+    pub episode_id: i64,
+    pub overview: String,
+    pub runtime: i64,
+    pub still_path: String,
+    pub title: String,
+    pub video_file: String,
+}
+
+impl PartitionKey for Series { 
+    fn partition_key(&self) -> String {
+        self.series_id.to_string()
+    }
+}
+
+impl PartitionKey for Season { 
+    fn partition_key(&self) -> String {
+        self.series_id.to_string()
+    }
+}
+
+impl PartitionKey for Episode { 
+    fn partition_key(&self) -> String {
+        self.series_id.to_string()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all(deserialize = "snake_case"))]
 pub struct BusinessData { 
@@ -213,3 +344,38 @@ pub struct MovieRating {
     pub vote_count: i64, 
     pub vote_average: f32
 }
+
+/// A movie's title, overview, tagline and poster in one locale, as published by the ingestion service
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all(deserialize = "snake_case"))]
+pub struct MovieTranslation { 
+    pub movie_id: i64,
+    pub locale: String,
+    pub overview: Option<String>,
+    pub poster: Option<String>,
+    pub tagline: Option<String>,
+    pub title: Option<String>,
+}
+
+/// A licensing window of a movie, as published by the ingestion service. Times are epoch millis
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AvailabilityWindow { 
+    pub movie_id: i64,
+    pub window_id: i64,
+    pub ends_at: Option<i64>,
+    pub offering: String,
+    pub starts_at: Option<i64>,
+    pub territory: String,
+}
+
+/// Territories covered by the windows open at `now`, sorted and without repeats
+pub fn open_territories(windows: &[AvailabilityWindow], now: i64) -> Vec<String> { 
+    let mut territories = windows
+        .iter()
+        .filter(|window| is_open(window.starts_at, window.ends_at, now))
+        .map(|window| window.territory.clone())
+        .collect::<Vec<_>>();
+    territories.sort();
+    territories.dedup();
+    territories
+}
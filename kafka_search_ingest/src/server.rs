@@ -5,6 +5,10 @@ use crate::{db::create_client};
 use crate::kafka_dualwrites::{KAFKA_BROKER, create_consumer_dual_writes, run_consumer_group_dual_writes};
 use tracing_actix_web::TracingLogger;
 use crate::telemetry::init_telemetry;
+use common_utils::health::configure_health;
+use crate::health::readiness;
+use common_utils::shutdown::drain_on_termination;
+use tokio::sync::watch;
 use std::fs::File;
 use std::io::Write;
 
@@ -26,19 +30,25 @@ pub async fn new_server(port: u32) -> std::io::Result<()> {
     log::info!("🎢 Welcome to Apache Kafka {} ", KAFKA_BROKER.as_str());
     
     let _ = create_consumer_dual_writes().expect("Unable to create a consumer for Kafka");
-    let _ = run_consumer_group_dual_writes().await;
+    //  The consume loop runs alongside the HTTP server so its health can be probed
+    let (shutdown, shutdown_signal) = watch::channel(false);
+    let consumer = actix_web::rt::spawn(run_consumer_group_dual_writes(shutdown_signal));
 
     opentelemetry::global::shutdown_tracer_provider();
-    HttpServer::new(move || {
+    let server = HttpServer::new(move || {
         App::new()
+            .configure(configure_health(readiness()))
             .wrap(Cors::permissive())
             .wrap(Logger::default())
     })
     
     .workers(3)
     .bind(format!("127.0.0.1:{}", port))?
-    .run()
-    .await
+    //  Signals are handled below so the consumer is drained before the server stops
+    .disable_signals()
+    .run();
+    actix_web::rt::spawn(drain_on_termination(consumer, shutdown, server.handle()));
+    server.await
 }
 
 /// Helper function to automate setting new databases
@@ -0,0 +1,207 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+use async_trait::async_trait;
+use common_utils::{QueryResult, error::ServiceError, events::{AvailabilityEvent, CatalogEvent, EventEnvelope, TranslationEvent}};
+use kafka_search_ingest::kafka_dualwrites::{consume_batches, last_offsets, Batch, BatchSettings, MessageSource, Received, SearchIndex};
+use kafka_search_ingest::module::model::{AvailabilityWindow, Movie, MovieTranslation};
+use tokio::sync::watch;
+
+const TOPIC: &str = "movie_topic";
+
+/// Hands out its messages, then asks the loop to stop as the termination signal would
+struct FakeSource {
+    pending: Mutex<VecDeque<Received>>,
+    shutdown: watch::Sender<bool>,
+    committed: Mutex<Vec<Received>>,
+    dead_letters: Mutex<Vec<Received>>,
+    dead_letter_fails: bool,
+}
+
+impl FakeSource {
+    fn new(messages: Vec<Received>, shutdown: watch::Sender<bool>) -> Self {
+        Self {
+            pending: Mutex::new(messages.into()),
+            shutdown,
+            committed: Mutex::new(Vec::new()),
+            dead_letters: Mutex::new(Vec::new()),
+            dead_letter_fails: false,
+        }
+    }
+    fn committed_offsets(&self) -> Vec<((String, i32), i64)> {
+        let committed = self.committed.lock().unwrap();
+        let mut offsets: Vec<((String, i32), i64)> = last_offsets(&committed)
+            .into_iter()
+            .map(|((topic, partition), offset)| ((topic.to_string(), partition), offset))
+            .collect();
+        offsets.sort();
+        offsets
+    }
+}
+
+#[async_trait]
+impl MessageSource for FakeSource {
+    async fn recv(&self) -> QueryResult<Received> {
+        let next = self.pending.lock().unwrap().pop_front();
+        match next {
+            Some(message) => Ok(message),
+            None => {
+                let _ = self.shutdown.send(true);
+                std::future::pending().await
+            }
+        }
+    }
+    fn commit(&self, messages: &[Received]) -> QueryResult<()> {
+        self.committed.lock().unwrap().extend_from_slice(messages);
+        Ok(())
+    }
+    async fn dead_letter(&self, messages: &[Received], _reason: &str) -> QueryResult<()> {
+        if self.dead_letter_fails {
+            return Err(ServiceError::MessageBrokerError(String::from("Broker is down")))
+        }
+        self.dead_letters.lock().unwrap().extend_from_slice(messages);
+        Ok(())
+    }
+}
+
+/// Rejects the first `failures` batches, records the size of every batch it is sent
+struct FakeIndex {
+    failures: AtomicU32,
+    batches: Mutex<Vec<usize>>,
+}
+
+impl FakeIndex {
+    fn failing(failures: u32) -> Self {
+        Self { failures: AtomicU32::new(failures), batches: Mutex::new(Vec::new()) }
+    }
+}
+
+#[async_trait]
+impl SearchIndex for FakeIndex {
+    async fn index(&self, batch: Batch) -> QueryResult<()> {
+        self.batches.lock().unwrap().push(batch.len());
+        match self.failures.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| left.checked_sub(1)) {
+            Ok(_) => Err(ServiceError::ServerError(String::from("Bulk request timed out"))),
+            Err(_) => Ok(()),
+        }
+    }
+}
+
+fn settings() -> BatchSettings {
+    BatchSettings {
+        size: 100,
+        timeout: Duration::from_secs(60),
+        attempts: 3,
+        backoff: Duration::from_millis(1),
+    }
+}
+
+fn availability_changed(partition: i32, offset: i64, movie_id: i64) -> Received {
+    let event = AvailabilityEvent::<AvailabilityWindow>::AvailabilityChanged { movie_id, windows: Vec::new() };
+    Received::new(TOPIC, partition, offset, &EventEnvelope::new("asset_ingestion_service", event).to_json().unwrap())
+}
+
+fn movie_translated(partition: i32, offset: i64, movie_id: i64) -> Received {
+    let event = TranslationEvent::<MovieTranslation>::MovieTranslated { movie_id, translations: Vec::new() };
+    Received::new(TOPIC, partition, offset, &EventEnvelope::new("asset_ingestion_service", event).to_json().unwrap())
+}
+
+fn movie_deleted(partition: i32, offset: i64, movie_id: i64) -> Received {
+    let event = CatalogEvent::<Movie>::MovieDeleted { movie_id };
+    Received::new(TOPIC, partition, offset, &EventEnvelope::new("asset_ingestion_service", event).to_json().unwrap())
+}
+
+async fn run(source: &FakeSource, index: &FakeIndex, shutdown: watch::Receiver<bool>) -> QueryResult<()> {
+    tokio::time::timeout(Duration::from_secs(5), consume_batches(source, index, &settings(), shutdown))
+        .await
+        .expect("The consume loop did not stop after the termination signal")
+}
+
+#[tokio::test]
+async fn termination_mid_batch_commits_only_the_indexed_messages() {
+    let (shutdown, shutdown_signal) = watch::channel(false);
+    let source = FakeSource::new(vec![
+        availability_changed(0, 10, 1),
+        availability_changed(1, 4, 2),
+        availability_changed(0, 11, 3),
+    ], shutdown);
+    let index = FakeIndex::failing(0);
+
+    run(&source, &index, shutdown_signal).await.unwrap();
+
+    assert_eq!(*index.batches.lock().unwrap(), vec![3]);
+    assert_eq!(source.committed_offsets(), vec![((TOPIC.to_string(), 0), 11), ((TOPIC.to_string(), 1), 4)]);
+    assert!(source.dead_letters.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn undecodable_messages_are_committed_with_their_batch() {
+    let (shutdown, shutdown_signal) = watch::channel(false);
+    let source = FakeSource::new(vec![
+        availability_changed(0, 10, 1),
+        Received::new(TOPIC, 0, 11, "not json"),
+    ], shutdown);
+    let index = FakeIndex::failing(0);
+
+    run(&source, &index, shutdown_signal).await.unwrap();
+
+    assert_eq!(*index.batches.lock().unwrap(), vec![1]);
+    assert_eq!(source.committed_offsets(), vec![((TOPIC.to_string(), 0), 11)]);
+}
+
+#[tokio::test]
+async fn failed_index_is_retried_before_committing() {
+    let (shutdown, shutdown_signal) = watch::channel(false);
+    let source = FakeSource::new(vec![availability_changed(0, 10, 1), availability_changed(0, 11, 2)], shutdown);
+    let index = FakeIndex::failing(2);
+
+    run(&source, &index, shutdown_signal).await.unwrap();
+
+    //  Retries decode the messages again into the same batch
+    assert_eq!(*index.batches.lock().unwrap(), vec![2, 2, 2]);
+    assert_eq!(source.committed_offsets(), vec![((TOPIC.to_string(), 0), 11)]);
+    assert!(source.dead_letters.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn batch_that_keeps_failing_is_dead_lettered_then_committed() {
+    let (shutdown, shutdown_signal) = watch::channel(false);
+    let source = FakeSource::new(vec![availability_changed(0, 10, 1), availability_changed(0, 11, 2)], shutdown);
+    let index = FakeIndex::failing(u32::MAX);
+
+    run(&source, &index, shutdown_signal).await.unwrap();
+
+    assert_eq!(index.batches.lock().unwrap().len(), 3);
+    let dead_letters: Vec<i64> = source.dead_letters.lock().unwrap().iter().map(|message| message.offset).collect();
+    assert_eq!(dead_letters, vec![10, 11]);
+    assert_eq!(source.committed_offsets(), vec![((TOPIC.to_string(), 0), 11)]);
+}
+
+#[tokio::test]
+async fn nothing_is_committed_when_dead_lettering_fails() {
+    let (shutdown, shutdown_signal) = watch::channel(false);
+    let mut source = FakeSource::new(vec![availability_changed(0, 10, 1)], shutdown);
+    source.dead_letter_fails = true;
+    let index = FakeIndex::failing(u32::MAX);
+
+    let stopped = run(&source, &index, shutdown_signal).await;
+
+    assert!(matches!(stopped, Err(ServiceError::MessageBrokerError(_))));
+    assert!(source.committed.lock().unwrap().is_empty());
+}
+
+#[test]
+fn partial_updates_after_a_delete_in_the_same_batch_are_dropped() {
+    let batch = Batch::from_messages(&[
+        availability_changed(0, 1, 1),
+        movie_deleted(0, 2, 1),
+        movie_translated(0, 3, 1),
+        availability_changed(0, 4, 1),
+        availability_changed(0, 5, 2),
+    ]);
+
+    assert_eq!(batch.movies.len(), 1);
+    assert!(batch.translations.is_empty());
+    assert_eq!(batch.availability.iter().map(|(movie_id, _)| *movie_id).collect::<Vec<i64>>(), vec![2]);
+}
@@ -15,6 +15,7 @@ use diesel::{result::Error as DbError, QueryDsl};
 use diesel_migrations::{MigrationError, embed_migrations};
 use redis::{aio::ConnectionManager as RedisManager, 
     Client as RedisClient, aio::Connection as RedisConnection};
+use common_utils::metrics::GraphQLMetrics;
 
 
 
@@ -74,6 +75,7 @@ pub fn create_schema(
     // Add a global data that can be accessed in the Schema
     .data(pool)
     .extension(ApolloTracing)
+    .extension(GraphQLMetrics)
     .finish()
 }
 pub fn run_migrations(pool: &DbPool) { 
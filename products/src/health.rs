@@ -0,0 +1,35 @@
+use actix_web::web;
+use common_utils::health::Readiness;
+use diesel::{sql_query, RunQueryDsl};
+use redis::aio::ConnectionManager;
+use crate::db::DbPool;
+
+/// Ready only when the Postgres pool hands out a working connection and Redis answers a ping
+pub fn readiness(pool: DbPool, redis_manager: ConnectionManager) -> Readiness { 
+    Readiness::new(env!("CARGO_PKG_NAME"))
+        .probe("postgres", move || { 
+            let pool = pool.clone();
+            async move { 
+                web::block(move || { 
+                    let conn = pool.get().map_err(|e| e.to_string())?;
+                    sql_query("SELECT 1")
+                        .execute(&conn)
+                        .map_err(|e| e.to_string())?;
+                    let state = pool.state();
+                    Ok(Some(format!("{} connections, {} idle", state.connections, state.idle_connections)))
+                })
+                    .await
+                    .map_err(|e| e.to_string())?
+            }
+        })
+        .probe("redis", move || { 
+            let mut connection = redis_manager.clone();
+            async move { 
+                redis::cmd("PING")
+                    .query_async::<_, String>(&mut connection)
+                    .await
+                    .map(|_| None)
+                    .map_err(|e| e.to_string())
+            }
+        })
+}
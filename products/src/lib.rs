@@ -11,6 +11,7 @@ extern crate serde_derive;
 
 pub mod graphql;
 pub mod server;
+pub mod health;
 pub mod db;
 pub mod schema;
 pub mod redis;
\ No newline at end of file
@@ -8,6 +8,8 @@ use async_graphql::{
 use async_graphql_actix_web::{GraphQLRequest, GraphQLResponse};
 use crate::{graphql::config::{graphql, graphql_playground, create_schema, run_migrations, configure_service}, redis::{RedisDatabase, create_client}};
 use crate::db::{DatabaseKind, establish_connection};
+use common_utils::health::configure_health;
+use crate::health::readiness;
 use std::fs::File;
 use std::io::Write;
 
@@ -26,6 +28,7 @@ pub async fn new_server(port: u32) -> std::io::Result<()> {
         .await
         .expect("Cannot Create Redis Connection Manager");
     //  GraphQl Schema
+    let health = readiness(db_pool.clone(), redis_connection_manager.clone());
     let schema = web::Data::new(create_schema(
         db_pool, 
         redis_client.clone(), 
@@ -47,6 +50,7 @@ pub async fn new_server(port: u32) -> std::io::Result<()> {
         App::new()
             .app_data(schema.clone())
             .configure(configure_service)
+            .configure(configure_health(health.clone()))
             .wrap(Cors::permissive())
             .wrap(Logger::default())
     })
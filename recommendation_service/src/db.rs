@@ -4,6 +4,7 @@ use std::{env, time::Duration, ffi::FromBytesWithNulError, sync::Arc};
 use scylla::{self,IntoTypedRows, query::Query, Session, SessionBuilder, batch::Consistency, load_balancing::{DcAwareRoundRobinPolicy, TokenAwarePolicy}, CachingSession, frame::value::ValueList, QueryResult, SessionConfig, transport::iterator::RowIterator};
 use lazy_static::lazy_static;
 use once_cell::sync::OnceCell;
+use common_utils::metrics::datastore_timer;
 use std::fmt::Debug;
 
 
@@ -136,6 +137,7 @@ impl From<Session> for CachedSession {
 impl CachedSession { 
     /// Simple query
     pub async fn query(&self, query: &str, values: impl ValueList + Debug) -> Result<QueryResult> { 
+        let _timer = datastore_timer("scylla", "query");
         log::info!("Executing query from Scylla Database!");
         let result = self.0.execute(query, &values)
             .await
@@ -152,6 +154,7 @@ impl CachedSession {
     /// and the background task run concurrently, so one of them can fetch new rows while the other consumes them 
     /// By adding paging to the app, you reduce memory usage and increase the applicaiton performance ``
     pub async fn query_iter(&self, query: &str, values: impl ValueList + Debug, page_size: Option<i32>) -> Result<RowIterator> { 
+        let _timer = datastore_timer("scylla", "query_iter");
         log::info!("Preparing and paging new statemet");
         //  Set the page size: 'Display n Number of query results'
         let mut query = Query::from(query);
@@ -166,6 +169,7 @@ impl CachedSession {
     }
     /// Executes a prepared query 
     pub async fn query_prepared(&self, query: &str, values: impl ValueList + Debug) -> Result<QueryResult> { 
+        let _timer = datastore_timer("scylla", "query_prepared");
         log::info!("Preparing and executing statement: {}", query);
         let result = self
             .0
@@ -179,6 +183,7 @@ impl CachedSession {
     /// This extracts the paging state from the resul and manually pass it on to the next query
     /// In doing so, the next query will stat fetching the results form where the previous one left off
     pub async fn query_paged(&self, query: &str, values: impl ValueList + Debug, page_size: Option<i32>) -> Result<QueryResult> { 
+        let _timer = datastore_timer("scylla", "query_paged");
         log::info!("📖 Manually fetching a single page");
 
         let mut query = Query::from(query);
@@ -9,12 +9,13 @@ use async_graphql::{
     EmptyMutation, EmptySubscription, Schema, Context, extensions::ApolloTracing,
 };
 use async_graphql_actix_web::{GraphQLRequest, GraphQLResponse, GraphQLSubscription};
-use common_utils::KAFKA_CONSUMER_COUNTER;
+use common_utils::{KAFKA_CONSUMER_COUNTER, availability::RequestRegion};
 use scylla::Session;
 use crate::{db::{CachedSession, session}};
 
 
 use super::root_schema::{Mutation, Query, AppSchema, AppSchemaBuilder};
+use common_utils::metrics::GraphQLMetrics;
 
 pub fn configure_service(cfg: &mut web::ServiceConfig) { 
     cfg
@@ -29,10 +30,10 @@ pub fn configure_service(cfg: &mut web::ServiceConfig) {
     );
 }
 
-/// GraphQL endpoint
+/// GraphQL endpoint, recommendations of movies not licensed in the caller's region are left out, see `RequestRegion::new`
 #[route("/graphql", method = "GET", method = "POST")]
-pub async fn graphql(schema: web::Data<AppSchema>, req: GraphQLRequest) -> GraphQLResponse {
-    schema.execute(req.into_inner()).await.into()
+pub async fn graphql(schema: web::Data<AppSchema>, http: HttpRequest, req: GraphQLRequest) -> GraphQLResponse {
+    schema.execute(req.into_inner().data(RequestRegion::new(&http))).await.into()
 }
 /// GraphiQL playground UI
 #[get("/graphiql")]
@@ -60,11 +61,13 @@ pub fn create_schema(pool: &'static CachedSession) -> AppSchema {
     // Add a global data that can be accessed in the Schema
     .data(pool)
     .extension(ApolloTracing)
+    .extension(GraphQLMetrics)
     .finish()
 }
-/// Global access to the applications's contextual data, accessible at runtime 
-pub fn get_conn_from_ctx(ctx: &Context<'_>) -> &'static CachedSession { 
-    let pool = ctx.data::<&'static CachedSession>()
+/// Global access to the applications's contextual data, accessible at runtime.
+/// Resolves to the `CachedSession` unless the schema was built around another store
+pub fn get_store_from_ctx<S: Send + Sync + 'static>(ctx: &Context<'_>) -> &'static S { 
+    let pool = ctx.data::<&'static S>()
         .expect("Failed to Connect to Database");
-    pool.clone()
+    *pool
 }
\ No newline at end of file
@@ -3,9 +3,8 @@ use scylla::{FromRow, ValueList};
 use serde::{Serialize, Deserialize};
 use chrono::NaiveDate;
 
-use crate::db::CachedSession;
 
-use super::{resolver::RecommendedTrait, schema::RecommendedType};
+use super::{resolver::{AvailabilityTrait, CatalogTrait, RecommendedTrait}, schema::RecommendedType};
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow, ValueList)]
 #[serde(rename_all(serialize = "snake_case", deserialize = "camelCase"))]
@@ -28,74 +27,52 @@ impl From<&RecommendedMovies> for RecommendedType {
 }
 
 impl RecommendedMovies { 
-    pub async fn get_most_recent<Rn: RecommendedTrait>(user_id: i32, session: &'static CachedSession) -> QueryResult<Vec<RecommendedMovies>> {
+    pub async fn get_most_recent<Rn: RecommendedTrait>(user_id: i32, session: &'static Rn::Store) -> QueryResult<Vec<RecommendedMovies>> {
         Rn::get_most_recent(user_id, session).await
     }
-    pub async fn get_all_recommendations<Rn: RecommendedTrait>(session: &'static CachedSession) -> QueryResult<Vec<RecommendedMovies>> {
+    pub async fn get_all_recommendations<Rn: RecommendedTrait>(session: &'static Rn::Store) -> QueryResult<Vec<RecommendedMovies>> {
         Rn::get_all_recommendations(session).await
     }
-    pub async fn get_user_recommendations<Rn: RecommendedTrait>(user_id: i32, session: &'static CachedSession) -> QueryResult<Vec<RecommendedMovies>> {
+    pub async fn get_user_recommendations<Rn: RecommendedTrait>(user_id: i32, session: &'static Rn::Store) -> QueryResult<Vec<RecommendedMovies>> {
         Rn::get_user_recommendations(user_id, session).await
     }
 
 }
 
+// Columns after the primary key of `movie_keyspace.movie_availability` are in alphabetical order
+/// A licensing window of a movie, written by the ingestion service. Times are epoch millis
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct AvailabilityWindow { 
+    pub movie_id: i64,
+    pub window_id: i64,
+    pub ends_at: Option<i64>,
+    pub offering: String,
+    pub starts_at: Option<i64>,
+    pub territory: String,
+}
 
+impl AvailabilityWindow { 
+    pub async fn get_windows_by_movies<A: AvailabilityTrait>(movie_ids: Vec<i64>, session: &'static A::Store) -> QueryResult<Vec<AvailabilityWindow>> {
+        A::get_windows_by_movies(movie_ids, session).await
+    }
+    /// `(territory, starts_at, ends_at)`, what `common_utils::availability` decides on
+    pub fn span(&self) -> (&str, Option<i64>, Option<i64>) { 
+        (self.territory.as_str(), self.starts_at, self.ends_at)
+    }
+}
 
+/// The entry of a movie in `movie_keyspace.movies_by_id`, `deleted_at` is set while it is soft deleted
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct CatalogMovie { 
+    pub movie_id: i64,
+    pub deleted_at: Option<i64>,
+}
 
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
+impl CatalogMovie { 
+    pub async fn get_movies_by_ids<C: CatalogTrait>(movie_ids: Vec<i64>, session: &'static C::Store) -> QueryResult<Vec<CatalogMovie>> {
+        C::get_movies_by_ids(movie_ids, session).await
+    }
+    pub fn is_deleted(&self) -> bool { 
+        self.deleted_at.is_some()
+    }
+}
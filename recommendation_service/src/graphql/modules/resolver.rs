@@ -1,14 +1,17 @@
 use async_trait::async_trait;
-use common_utils::QueryResult;
+use common_utils::{QueryResult, error::ServiceError};
 use scylla::IntoTypedRows;
 use crate::db::CachedSession;
-use super::model::RecommendedMovies;
+use super::model::{AvailabilityWindow, CatalogMovie, RecommendedMovies};
+/// `Store` is the session recommendations are read from
 #[async_trait]
-pub trait RecommendedTrait { 
-    async fn get_most_recent(user_id: i32, session: &'static CachedSession) -> QueryResult<Vec<RecommendedMovies>>;
-    async fn get_all_recommendations(session: &'static CachedSession) -> QueryResult<Vec<RecommendedMovies>>;
-    async fn get_user_recommendations(user_id: i32, session: &'static CachedSession) -> QueryResult<Vec<RecommendedMovies>>;
+pub trait RecommendedTrait: Send + Sync + 'static { 
+    type Store: Send + Sync + 'static;
+    async fn get_most_recent(user_id: i32, session: &'static Self::Store) -> QueryResult<Vec<RecommendedMovies>>;
+    async fn get_all_recommendations(session: &'static Self::Store) -> QueryResult<Vec<RecommendedMovies>>;
+    async fn get_user_recommendations(user_id: i32, session: &'static Self::Store) -> QueryResult<Vec<RecommendedMovies>>;
 }
+#[derive(Default)]
 pub struct RecommendedDatabase;
 
 static GET_MOST_RECENT: &str = "SELECT * FROM recommended_movies.user_recommendations WHERE user_id = ? ORDER BY time DESC LIMIT 50";
@@ -17,6 +20,8 @@ static GET_USER_RECOMMENDATIONS: &str = "SELECT * FROM recommended_movies.user_r
 
 #[async_trait]
 impl RecommendedTrait for RecommendedDatabase { 
+    type Store = CachedSession;
+
     async fn get_most_recent(user_id: i32, session: &'static CachedSession) -> QueryResult<Vec<RecommendedMovies>> { 
         let res = session 
             .query_prepared(GET_MOST_RECENT, (user_id,))
@@ -50,4 +55,61 @@ impl RecommendedTrait for RecommendedDatabase {
             .collect();
         Ok(res)
     }
-}
\ No newline at end of file
+}
+
+/// Licensing windows of the recommended movies, read from the catalogue's keyspace
+#[async_trait]
+pub trait AvailabilityTrait: Send + Sync + 'static { 
+    type Store: Send + Sync + 'static;
+    async fn get_windows_by_movies(movie_ids: Vec<i64>, session: &'static Self::Store) -> QueryResult<Vec<AvailabilityWindow>>;
+}
+#[derive(Default)]
+pub struct AvailabilityDatabase;
+
+static GET_WINDOWS_BY_MOVIES: &str = "SELECT * FROM movie_keyspace.movie_availability WHERE movie_id IN ?";
+
+#[async_trait]
+impl AvailabilityTrait for AvailabilityDatabase { 
+    type Store = CachedSession;
+
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.movie_availability"))]
+    async fn get_windows_by_movies(movie_ids: Vec<i64>, session: &'static CachedSession) -> QueryResult<Vec<AvailabilityWindow>> { 
+        session 
+            .query_prepared(GET_WINDOWS_BY_MOVIES, (movie_ids,))
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
+            .into_typed::<AvailabilityWindow>()
+            .map(|row| row.map_err(|e| ServiceError::ServerError(e.to_string())))
+            .collect()
+    }
+}
+
+/// Whether the recommended movies are still in the catalogue, read from its keyspace
+#[async_trait]
+pub trait CatalogTrait: Send + Sync + 'static { 
+    type Store: Send + Sync + 'static;
+    /// Ids that are no longer in the catalogue are left out
+    async fn get_movies_by_ids(movie_ids: Vec<i64>, session: &'static Self::Store) -> QueryResult<Vec<CatalogMovie>>;
+}
+#[derive(Default)]
+pub struct CatalogDatabase;
+
+static GET_MOVIES_BY_IDS: &str = "SELECT movie_id, deleted_at FROM movie_keyspace.movies_by_id WHERE movie_id IN ?";
+
+#[async_trait]
+impl CatalogTrait for CatalogDatabase { 
+    type Store = CachedSession;
+
+    #[tracing::instrument(skip(session), fields(repository = "movie_keyspace.movies_by_id"))]
+    async fn get_movies_by_ids(movie_ids: Vec<i64>, session: &'static CachedSession) -> QueryResult<Vec<CatalogMovie>> { 
+        session 
+            .query_prepared(GET_MOVIES_BY_IDS, (movie_ids,))
+            .await
+            .map_err(|_| ServiceError::DatabaseError)?
+            .rows_or_empty()
+            .into_typed::<CatalogMovie>()
+            .map(|row| row.map_err(|e| ServiceError::ServerError(e.to_string())))
+            .collect()
+    }
+}
@@ -1,12 +1,17 @@
 use async_graphql::*;
 use chrono::NaiveDate;
-use crate::graphql::config::get_conn_from_ctx;
+use common_utils::availability::RequestRegion;
+use crate::graphql::config::get_store_from_ctx;
+use std::{collections::{HashMap, HashSet}, marker::PhantomData};
 
-use super::model::RecommendedMovies;
-use super::resolver::{RecommendedTrait, RecommendedDatabase};
+use super::model::{AvailabilityWindow, CatalogMovie, RecommendedMovies};
+use super::resolver::{AvailabilityDatabase, AvailabilityTrait, CatalogDatabase, CatalogTrait, RecommendedTrait, RecommendedDatabase};
 
+/// Recommendations are read through `R`, `RecommendedDatabase` in production, the
+/// licensing windows of the movies they point at through `A` and whether those movies
+/// are still in the catalogue through `C`
 #[derive(Default)]
-pub struct RecommendedQuery;
+pub struct RecommendedQuery<R = RecommendedDatabase, A = AvailabilityDatabase, C = CatalogDatabase>(PhantomData<(R, A, C)>);
 #[derive(SimpleObject, Debug, Clone)]
 pub struct RecommendedType { 
     pub user_id: i32, 
@@ -26,12 +31,12 @@ impl UserType {
     }
     // #[graphql(name = "getUserRecommendation")]
     // pub async fn get_recommended(&self, ctx: &Context<'_>, #[graphql(key)] id: ID) -> FieldResult<Vec<RecommendedType>> { 
-    //     get_user_recommended_movies(ctx, id.parse::<i32>().unwrap()).await
+    //     get_user_recommended_movies::<R>(ctx, id.parse::<i32>().unwrap()).await
     // }
 }
 
 #[Object]
-impl RecommendedQuery { 
+impl<R: RecommendedTrait, A: AvailabilityTrait, C: CatalogTrait> RecommendedQuery<R, A, C> { 
 
     #[graphql(entity, name = "getUserByID")]
     async fn get_user(&self, #[graphql(key)] id: ID) -> UserType {
@@ -41,48 +46,69 @@ impl RecommendedQuery {
     #[tracing::instrument(skip(self, ctx))]
     #[graphql(name = "getUserRecentRecommendations")]
     async fn get_most_recent_movies(&self, ctx: &Context<'_>, user_id: i32) -> FieldResult<Vec<RecommendedType>> { 
-        let res: Vec<RecommendedType> = RecommendedMovies::get_most_recent::<RecommendedDatabase>(user_id, get_conn_from_ctx(ctx))
+        let res = RecommendedMovies::get_most_recent::<R>(user_id, get_store_from_ctx(ctx))
             .await
-            .expect("")
-            .into_iter()
-            .map(|f| RecommendedType::from(&f))
-            .collect();
-        Ok(res)
+            .expect("");
+        hydrate::<A, C>(ctx, res).await
     }
     #[tracing::instrument(skip(self, ctx))]
     #[graphql(name = "getAllRecommendedMovies")]
     async fn get_all_recommended(&self, ctx: &Context<'_>) -> FieldResult<Vec<RecommendedType>> { 
-        let res: Vec<RecommendedType> = RecommendedMovies::get_all_recommendations::<RecommendedDatabase>(get_conn_from_ctx(ctx))
+        let res = RecommendedMovies::get_all_recommendations::<R>(get_store_from_ctx(ctx))
             .await
-            .expect("")
-            .into_iter()
-            .map(|f| RecommendedType::from(&f))
-            .collect();
-        Ok(res)
+            .expect("");
+        hydrate::<A, C>(ctx, res).await
     }
     #[tracing::instrument(skip(self, ctx))]
     #[graphql(name = "getUserRecommendation")]
     async fn get_user_recommended(&self, ctx: &Context<'_>, user_id: i32) -> FieldResult<Vec<RecommendedType>> { 
-        let res: Vec<RecommendedType> = RecommendedMovies::get_user_recommendations::<RecommendedDatabase>(user_id, get_conn_from_ctx(ctx))
+        let res = RecommendedMovies::get_user_recommendations::<R>(user_id, get_store_from_ctx(ctx))
             .await
-            .expect("")
-            .into_iter()
-            .map(|f| RecommendedType::from(&f))
-            .collect();
-        Ok(res)
+            .expect("");
+        hydrate::<A, C>(ctx, res).await
     }
     #[graphql(entity, name = "getUserRecommendations")]
     async fn get_user_recommended_entity(&self, ctx: &Context<'_>, user_id: i32) -> FieldResult<Vec<RecommendedType>> { 
-        get_user_recommended_movies(ctx, user_id).await
+        get_user_recommended_movies::<R, A, C>(ctx, user_id).await
     }
 }
 #[tracing::instrument(skip(ctx), level = "Debug")]
-async fn get_user_recommended_movies(ctx: &Context<'_>, user_id: i32) -> FieldResult<Vec<RecommendedType>> { 
-    let res: Vec<RecommendedType> = RecommendedMovies::get_user_recommendations::<RecommendedDatabase>(user_id, get_conn_from_ctx(ctx))
+async fn get_user_recommended_movies<R: RecommendedTrait, A: AvailabilityTrait, C: CatalogTrait>(ctx: &Context<'_>, user_id: i32) -> FieldResult<Vec<RecommendedType>> { 
+    let res = RecommendedMovies::get_user_recommendations::<R>(user_id, get_store_from_ctx(ctx))
         .await
-        .expect("")
+        .expect("");
+    hydrate::<A, C>(ctx, res).await
+}
+
+/// Turns recommendations into `RecommendedType`, leaving out the movies that can't be watched
+/// in the caller's region and those that were deleted or soft deleted since they were recommended.
+/// A schema built without a `RequestRegion` serves an unknown region
+async fn hydrate<A: AvailabilityTrait, C: CatalogTrait>(ctx: &Context<'_>, recommendations: Vec<RecommendedMovies>) -> FieldResult<Vec<RecommendedType>> { 
+    if recommendations.is_empty() { 
+        return Ok(Vec::new())
+    }
+    let region = ctx.data_opt::<RequestRegion>().cloned().unwrap_or_default();
+    let mut movie_ids = recommendations.iter().map(|f| f.movie_id).collect::<Vec<_>>();
+    movie_ids.sort_unstable();
+    movie_ids.dedup();
+    let live = CatalogMovie::get_movies_by_ids::<C>(movie_ids.clone(), get_store_from_ctx(ctx))
+        .await
+        .map_err(|e| e.extend())?
         .into_iter()
-        .map(|f| RecommendedType::from(&f))
-        .collect();
-    Ok(res)
-}
\ No newline at end of file
+        .filter(|movie| !movie.is_deleted())
+        .map(|movie| movie.movie_id)
+        .collect::<HashSet<_>>();
+    let windows = AvailabilityWindow::get_windows_by_movies::<A>(movie_ids, get_store_from_ctx(ctx))
+        .await
+        .map_err(|e| e.extend())?;
+    let mut by_movie: HashMap<i64, Vec<AvailabilityWindow>> = HashMap::new();
+    for window in windows { 
+        by_movie.entry(window.movie_id).or_default().push(window);
+    }
+    Ok(recommendations
+        .iter()
+        .filter(|f| live.contains(&f.movie_id))
+        .filter(|f| region.allows(by_movie.get(&f.movie_id).into_iter().flatten().map(AvailabilityWindow::span)))
+        .map(RecommendedType::from)
+        .collect())
+}
@@ -0,0 +1,8 @@
+use common_utils::health::{check_scylla, Readiness};
+use crate::db::session;
+
+/// Ready only when Scylla answers a query
+pub fn readiness() -> Readiness { 
+    Readiness::new(env!("CARGO_PKG_NAME"))
+        .probe("scylla", || check_scylla(&session().0))
+}
@@ -5,5 +5,6 @@ extern crate serde_json;
 extern crate serde_derive;
 pub mod graphql;
 pub mod server;
+pub mod health;
 pub mod db;
 pub mod telemetry;
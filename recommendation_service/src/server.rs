@@ -5,6 +5,8 @@ use crate::db::{establish_connection, session, DBCONN};
 // use crate::graphql::modules::resolver::batch_indexing_into_es;
 use crate::telemetry::init_telemetry;
 use tracing_actix_web::TracingLogger;
+use common_utils::health::configure_health;
+use crate::health::readiness;
 use std::fs::File;
 use std::io::Write;
 
@@ -48,6 +50,7 @@ pub async fn new_server(port: u32) -> std::io::Result<()> {
         App::new()
             .app_data(schema.clone())
             .configure(configure_service)
+            .configure(configure_health(readiness()))
             .wrap(Cors::permissive())
             .wrap(Logger::default())
             // .wrap(TracingLogger::default())
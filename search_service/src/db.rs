@@ -13,6 +13,7 @@ use elasticsearch::http::transport::{
     DEFAULT_ADDRESS, TransportBuilder, Transport, SingleNodeConnectionPool, BuildError};
 use lazy_static::lazy_static;
 use once_cell::sync::OnceCell;
+use common_utils::metrics::datastore_timer;
 use url::Url;
 use elasticsearch::cert::CertificateValidation;
 use serde_json::{json, Value};
@@ -106,6 +107,7 @@ pub async fn search_api(
         None => 10,
     };
 
+    let _timer = datastore_timer("elasticsearch", "search");
     let response: Value = client
         .search(SearchParts::Index(&[index]))
         .from(0)
@@ -11,6 +11,7 @@ use elasticsearch::Elasticsearch;
 use crate::db::ElasticClient;
 
 use super::root_schema::{Mutation, Query, AppSchema, AppSchemaBuilder};
+use common_utils::{availability::RequestRegion, metrics::GraphQLMetrics};
 
 pub fn configure_service(cfg: &mut web::ServiceConfig) { 
     cfg
@@ -25,10 +26,10 @@ pub fn configure_service(cfg: &mut web::ServiceConfig) {
     );
 }
 
-/// GraphQL endpoint
+/// GraphQL endpoint, results are narrowed to the movies licensed in the caller's region, see `RequestRegion::new`
 #[route("/graphql", method = "GET", method = "POST")]
-pub async fn graphql(schema: web::Data<AppSchema>, req: GraphQLRequest) -> GraphQLResponse {
-    schema.execute(req.into_inner()).await.into()
+pub async fn graphql(schema: web::Data<AppSchema>, http: HttpRequest, req: GraphQLRequest) -> GraphQLResponse {
+    schema.execute(req.into_inner().data(RequestRegion::new(&http))).await.into()
 }
 /// GraphiQL playground UI
 #[get("/graphiql")]
@@ -55,11 +56,12 @@ pub fn create_schema(elastic_pool: Elasticsearch) -> AppSchema {
     // Add a global data that can be accessed in the Schema
     .data(elastic_pool)
     .extension(ApolloTracing)
+    .extension(GraphQLMetrics)
     .finish()
 }
-/// Elasticsearch connection pool 
-pub fn get_conn_from_ctx(ctx: &Context<'_>) -> Elasticsearch { 
-    ctx.data::<Elasticsearch>()
+/// Elasticsearch connection pool, or whichever store the schema was built with
+pub fn get_store_from_ctx<S: Clone + Send + Sync + 'static>(ctx: &Context<'_>) -> S { 
+    ctx.data::<S>()
         .expect("Failed to get Db Pool")
         .clone()
 }
\ No newline at end of file
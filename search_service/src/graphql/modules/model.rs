@@ -1,7 +1,6 @@
 use async_graphql::SimpleObject;
 use chrono::NaiveDate;
-use common_utils::QueryResult;
-use elasticsearch::Elasticsearch;
+use common_utils::{QueryResult, availability::covers};
 use serde::{Serialize, Deserialize};
 use super::{resolver::ElasticResolver, schema::{MovieType, SearchTextInput, AggregatedQuery}};
 
@@ -43,6 +42,19 @@ pub struct Movie {
     pub runtime: i64,
     pub status: String,
     pub video_file: String,
+    /// Territories the movie's open licensing windows cover, kept current by the indexer
+    #[serde(default)]
+    pub available_in: Vec<String>,
+    /// `false` for a movie without licensing windows, which is available everywhere
+    #[serde(default)]
+    pub restricted: bool,
+}
+
+impl Movie { 
+    /// The document side of `resolver::available_in`
+    pub fn is_available_in(&self, region: Option<&str>) -> bool { 
+        !self.restricted || self.available_in.iter().any(|territory| covers(territory, region))
+    }
 }
 #[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
 #[serde(rename_all(serialize = "snake_case", deserialize = "snake_case"))]
@@ -129,7 +141,9 @@ pub struct FilterQueryWithMultipleFields {
     pub index_name: String,
     pub fields: Vec<String>,
     pub sort_by: String, 
-    pub order: String
+    pub order: String,
+    /// Only movies available in this region are returned, see `resolver::available_in`
+    pub region: Option<String>,
 }
 
 impl FilterQueryWithMultipleFields { 
@@ -151,7 +165,8 @@ impl FilterQueryWithMultipleFields {
             index_name,
             fields: fields.unwrap_or(vec![String::new()]),
             sort_by: sort_by.unwrap_or("rating.popularity".to_string()),
-            order: order.unwrap_or("asc".to_string())
+            order: order.unwrap_or("asc".to_string()),
+            region: None,
         }
     }
 }
@@ -171,8 +186,9 @@ pub struct SimpleSearchNew {
     pub agg_size: i32,
 
     pub filter_by: String,
-    pub filter_value: String 
+    pub filter_value: String,
 
+    pub region: Option<String>,
 }
 
 
@@ -187,42 +203,44 @@ impl From<&SearchTextInput> for SimpleSearchNew {
             agg_field: f.agg_field.clone().unwrap_or(String::new()),
             agg_size: f.agg_size.clone().unwrap_or(1),
             filter_by: f.filter_by.clone().unwrap_or("genres".to_string()),
-            filter_value: f.filter_value.clone().unwrap_or(String::new())
+            filter_value: f.filter_value.clone().unwrap_or(String::new()),
+            region: None,
         }
     }
 }
 
 impl Movie { 
     #[tracing::instrument(skip(client))]
-    pub async fn search_indexed<Elastic: ElasticResolver>(client: Elasticsearch, total_result: Option<i64>, index_name: String
+    pub async fn search_indexed<Elastic: ElasticResolver>(client: Elastic::Store, total_result: Option<i64>, index_name: String, region: Option<String>
     ) -> QueryResult<Vec<Movie>> { 
-        Elastic::search_indexed(client, total_result, index_name).await
+        Elastic::search_indexed(client, total_result, index_name, region).await
     }
     #[tracing::instrument(skip(client))]
-    pub async fn search_phrase_prefix<Elastic: ElasticResolver>(client: Elasticsearch, query: SimpleSearchNew ) -> Option<AggregatedQuery> { 
+    pub async fn search_phrase_prefix<Elastic: ElasticResolver>(client: Elastic::Store, query: SimpleSearchNew ) -> Option<AggregatedQuery> { 
         Elastic::search_phrase_prefix(client, query).await
     }
     #[tracing::instrument(skip(client))]
-    pub async fn delete_document<Elastic: ElasticResolver>(movie_id: &str, client: Elasticsearch) -> QueryResult<bool> { 
+    pub async fn delete_document<Elastic: ElasticResolver>(movie_id: &str, client: Elastic::Store) -> QueryResult<bool> { 
         Elastic::delete_document(movie_id, client).await
     }
     #[tracing::instrument(skip(client))]
-    pub async fn filter_by<Elastic: ElasticResolver>(term: String, term_value: String, client: Elasticsearch, total_result: Option<i64>,  index_name: String) -> QueryResult<Vec<Movie>> {
-        Elastic::filter_by(term, term_value, client, total_result, index_name).await
+    pub async fn filter_by<Elastic: ElasticResolver>(term: String, term_value: String, client: Elastic::Store, total_result: Option<i64>,  index_name: String, region: Option<String>) -> QueryResult<Vec<Movie>> {
+        Elastic::filter_by(term, term_value, client, total_result, index_name, region).await
     }
     #[tracing::instrument(skip(client))]
-    pub async fn filter_or_aggregate_query<Elastic: ElasticResolver>(query: FilterQueryWithMultipleFields, client: Elasticsearch) -> QueryResult<Vec<Movie>> {
+    pub async fn filter_or_aggregate_query<Elastic: ElasticResolver>(query: FilterQueryWithMultipleFields, client: Elastic::Store) -> QueryResult<Vec<Movie>> {
         Elastic::filter_or_aggregate_query(query, client).await
     }  
     #[tracing::instrument(skip(client))]
     pub async fn sort_movies_by<Elastic: ElasticResolver>(
         term_name: Option<String>, 
         order: Option<String>, 
-        client: Elasticsearch,
+        client: Elastic::Store,
         total_result: Option<i64>, 
-        index_name: String 
+        index_name: String,
+        region: Option<String>
     ) -> QueryResult<Vec<Movie>> { 
-        Elastic::sort_movies_by(term_name, order, client, total_result, index_name).await
+        Elastic::sort_movies_by(term_name, order, client, total_result, index_name, region).await
     }
 
 }
\ No newline at end of file
@@ -4,7 +4,7 @@ use serde_json::{json, Value};
 use crate::graphql::modules::model::Movie;
 use crate::db::{search_api, INDEX_NAME, index_name};
 use crate::graphql::modules::schema::MovieType;
-use common_utils::QueryResult;
+use common_utils::{QueryResult, availability::WORLDWIDE};
 use super::model::{FilterQueryWithMultipleFields, Genre, SimpleSearchNew};
 use super::schema::AggregatedQuery;
 
@@ -17,6 +17,37 @@ use super::schema::AggregatedQuery;
     client decides which api to use, hence the 
 */
 
+/// Movies that can be watched in `region`: the ones without licensing windows and the ones with
+/// an open window there or worldwide. `available_in` is maintained by the indexer
+pub fn available_in(region: Option<String>) -> Value { 
+    let territories: Vec<String> = std::iter::once(WORLDWIDE.to_string()).chain(region).collect();
+    json!({
+        "bool": {
+            "should": [
+                { "terms": { "available_in": territories } },
+                { "bool": { "must_not": { "term": { "restricted": true } } } }
+            ],
+            "minimum_should_match": 1
+        }
+    })
+}
+
+/// Narrows the `query` of `body` to the movies available in `region`. Aggregations are computed
+/// over the narrowed hits, so facets never count a movie that can't be watched
+pub fn in_region(mut body: Value, region: Option<String>) -> Value { 
+    let query = match body["query"].take() { 
+        Value::Null => json!({ "match_all": {} }),
+        query => query,
+    };
+    body["query"] = json!({
+        "bool": {
+            "must": [query],
+            "filter": [available_in(region)]
+        }
+    });
+    body
+}
+
 /// field name: ratings.popularity, order: asc
 pub fn sort_by(field_name: String, order: String) -> Value { 
     json!(
@@ -415,47 +446,56 @@ pub fn search_filter_aggregate(
 
 
 
+/// The client is cloned into every query, so `Store` has to be `Clone` like `Elasticsearch`.
+/// Every search only returns the movies available in the caller's region
 #[async_trait]
-pub trait ElasticResolver { 
+pub trait ElasticResolver: Send + Sync + 'static { 
+    type Store: Clone + Send + Sync + 'static;
     async fn search_indexed(
-        client: Elasticsearch, 
+        client: Self::Store, 
         total_result: Option<i64>, 
-        index_name: String
+        index_name: String,
+        region: Option<String>
     ) -> QueryResult<Vec<Movie>>;
     async fn search_phrase_prefix(
-        client: Elasticsearch, 
+        client: Self::Store, 
         query: SimpleSearchNew
     ) -> Option<AggregatedQuery>;
-    async fn delete_document(id: &str, client: Elasticsearch) -> QueryResult<bool>;
+    async fn delete_document(id: &str, client: Self::Store) -> QueryResult<bool>;
     async fn filter_by(
         term: String, 
         term_value: String, 
-        client: Elasticsearch,
+        client: Self::Store,
         total_result: Option<i64>, 
-        index_name: String
+        index_name: String,
+        region: Option<String>
     ) -> QueryResult<Vec<Movie>>;
     async fn filter_or_aggregate_query(
         query: FilterQueryWithMultipleFields,
-        client: Elasticsearch,
+        client: Self::Store,
     ) -> QueryResult<Vec<Movie>>; 
     async fn sort_movies_by(
         term_name: Option<String>, 
         order: Option<String>, 
-        client: Elasticsearch,
+        client: Self::Store,
         total_result: Option<i64>, 
-        index_name: String 
+        index_name: String,
+        region: Option<String>
     ) -> QueryResult<Vec<Movie>>;
 
 }
+#[derive(Default)]
 pub struct ElasticDatabase;
 
 #[async_trait]
 impl ElasticResolver for ElasticDatabase { 
+    type Store = Elasticsearch;
+
     #[tracing::instrument(skip(client), err, level = "debug")]
-    async fn search_indexed(client: Elasticsearch, total_result: Option<i64>, index_name: String) -> QueryResult<Vec<Movie>> { 
+    async fn search_indexed(client: Elasticsearch, total_result: Option<i64>, index_name: String, region: Option<String>) -> QueryResult<Vec<Movie>> { 
         let payload = search_api(
             client,
-            match_all(), 
+            in_region(match_all(), region), 
             total_result, 
             &index_name
         ).await;
@@ -482,12 +522,13 @@ impl ElasticResolver for ElasticDatabase {
             agg_field,
             agg_size,
             filter_by,
-            filter_value
+            filter_value,
+            region
         } = query;
         log::info!("👏 Received Order!");
         let payload = search_api(
             client,
-            search_movie_val(
+            in_region(search_movie_val(
                 query, 
                 sort_by, 
                 order, 
@@ -495,7 +536,7 @@ impl ElasticResolver for ElasticDatabase {
                 agg_size,
                 filter_by,
                 filter_value
-            ), 
+            ), region), 
             Some(total_result), 
             &index_name
         ).await;
@@ -557,11 +598,12 @@ impl ElasticResolver for ElasticDatabase {
         term_value: String, 
         client: Elasticsearch,
         total_result: Option<i64>, 
-        index_name: String
+        index_name: String,
+        region: Option<String>
     ) -> QueryResult<Vec<Movie>> { 
         let payload = search_api(
             client, 
-            only_filter_val(term, term_value),
+            in_region(only_filter_val(term, term_value), region),
             total_result, 
             &index_name
         ).await;
@@ -593,18 +635,19 @@ impl ElasticResolver for ElasticDatabase {
             fields,
             sort_by, 
             order,
+            region,
         } = query;
         
         let payload = search_api(
             client, 
-            filter_search_query(
+            in_region(filter_search_query(
                 query, 
                 term_name, 
                 term_value, 
                 fields,
                 sort_by, 
                 order
-            ),
+            ), region),
             Some(total_result), 
             &index_name
         ).await;
@@ -624,12 +667,13 @@ impl ElasticResolver for ElasticDatabase {
         order: Option<String>, 
         client: Elasticsearch,
         total_result: Option<i64>, 
-        index_name: String 
+        index_name: String,
+        region: Option<String>
     ) -> QueryResult<Vec<Movie>> { 
         log::info!("👀 Entering Filter or Aggregated Query API");
         let payload = search_api(
             client, 
-            sort_all_movies(term_name.unwrap_or_default(), order.unwrap_or("desc".to_string())),
+            in_region(sort_all_movies(term_name.unwrap_or_default(), order.unwrap_or("desc".to_string())), region),
             total_result, 
             &index_name
         ).await;
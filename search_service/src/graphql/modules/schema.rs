@@ -1,18 +1,20 @@
 use async_graphql_actix_web::*;
 use async_graphql::*;
 use chrono::NaiveDate;
-use common_utils::QueryResult;
+use common_utils::{QueryResult, availability::RequestRegion};
 use serde::{Deserialize, Serialize};
 use crate::db::index_name;
-use crate::graphql::config::get_conn_from_ctx;
+use crate::graphql::config::get_store_from_ctx;
+use std::marker::PhantomData;
 
 use super::{model::{Movie, BusinessData, MovieRating, FilterQueryWithMultipleFields, Genre, SimpleSearchNew}, resolver::{ElasticResolver, ElasticDatabase}};
 
 
 
 
+/// Generic over the resolver so the schema can be served from an in-memory index
 #[derive(Default)]
-pub struct ElasticQuery;
+pub struct ElasticQuery<R = ElasticDatabase>(PhantomData<R>);
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProductionCompanyType { 
@@ -101,8 +103,14 @@ pub struct SearchTextInput {
 }
 
 
+/// Region every search is narrowed to, `None` for a caller whose region is unknown or a schema
+/// built without a `RequestRegion`, like the ones in tests
+fn request_region(ctx: &Context<'_>) -> Option<String> { 
+    ctx.data_opt::<RequestRegion>().and_then(|region| region.region.clone())
+}
+
 #[Object]
-impl ElasticQuery { 
+impl<R: ElasticResolver> ElasticQuery<R> { 
     #[graphql(entity)]
     async fn get_company_by_id(&self, #[graphql(key)] company_id: ID) -> ProductionCompanyType { 
         ProductionCompanyType { company_id }
@@ -116,7 +124,7 @@ impl ElasticQuery {
         #[graphql(default = "index_name()")]
         index_name: String
     ) -> Vec<MovieType> { 
-        let res = Movie::search_indexed::<ElasticDatabase>(get_conn_from_ctx(ctx), total_result, index_name)
+        let res = Movie::search_indexed::<R>(get_store_from_ctx(ctx), total_result, index_name, request_region(ctx))
             .await
             .expect("Unable to get the movie using genre_id")
             .iter()
@@ -127,9 +135,9 @@ impl ElasticQuery {
     /// Retrieve all indexed movies
     #[graphql(name = "searchMovie")]
     async fn search_phrase_prefix(&self, ctx: &Context<'_>, input: SearchTextInput) -> Option<AggregatedQuery> { 
-        let res = Movie::search_phrase_prefix::<ElasticDatabase>(
-            get_conn_from_ctx(ctx), 
-            SimpleSearchNew::from(&input))
+        let res = Movie::search_phrase_prefix::<R>(
+            get_store_from_ctx(ctx), 
+            SimpleSearchNew { region: request_region(ctx), ..SimpleSearchNew::from(&input) })
             .await
             .expect("Unable to get any result for both");
         log::info!("📦 Genre List {:#?}, MovieList {:#?}", res.genres, res.movie_list);
@@ -144,12 +152,13 @@ impl ElasticQuery {
         ctx: &Context<'_>,
         filter: FilterQuery
     ) -> Vec<MovieType> { 
-        let res = Movie::filter_by::<ElasticDatabase>(
+        let res = Movie::filter_by::<R>(
             filter.term_name.unwrap_or_default(), 
             filter.term_value.unwrap_or_default(), 
-            get_conn_from_ctx(ctx),
+            get_store_from_ctx(ctx),
             filter.total_result,
-            filter.index_name
+            filter.index_name,
+            request_region(ctx)
         ) 
             .await
             .expect("Unable to retrieve the items")
@@ -174,8 +183,9 @@ impl ElasticQuery {
         sort_by: Option<String>,
         order: Option<String>
     ) -> Vec<MovieType> { 
-        let res = Movie::filter_or_aggregate_query::<ElasticDatabase>(
-            FilterQueryWithMultipleFields::new(
+        let query = FilterQueryWithMultipleFields { 
+            region: request_region(ctx),
+            ..FilterQueryWithMultipleFields::new(
                 query, 
                 term_name, 
                 term_value, 
@@ -184,7 +194,9 @@ impl ElasticQuery {
                 fields,
                 sort_by,
                 order
-            ),get_conn_from_ctx(ctx))
+            )
+        };
+        let res = Movie::filter_or_aggregate_query::<R>(query, get_store_from_ctx(ctx))
             .await
             .expect("Unable to retrieve the items")
             .iter()
@@ -195,12 +207,13 @@ impl ElasticQuery {
     /// Default Values of sort is Descending
     #[graphql(name = "sortMoviesAccordingly")]
     async fn sort_movie_based(&self, ctx: &Context<'_>, input: SortAllMovies) -> Vec<MovieType> { 
-        let res = Movie::sort_movies_by::<ElasticDatabase>( 
+        let res = Movie::sort_movies_by::<R>( 
             input.term_name,
             input.order,
-            get_conn_from_ctx(ctx),
+            get_store_from_ctx(ctx),
             input.total_result,
-            input.index_name
+            input.index_name,
+            request_region(ctx)
         )
             .await
             .expect("")
@@ -214,14 +227,14 @@ impl ElasticQuery {
 }
 
 #[derive(Default)]
-pub struct ElasticMutate;
+pub struct ElasticMutate<R = ElasticDatabase>(PhantomData<R>);
 
 #[Object]
-impl ElasticMutate { 
+impl<R: ElasticResolver> ElasticMutate<R> { 
     /// Deletes the index under this id 
     #[graphql(name = "deleteMovieDocByID")]
     async fn delete_document_by_id(&self, ctx: &Context<'_>, movie_id: ID) -> QueryResult<bool> { 
-        let res = Movie::delete_document::<ElasticDatabase>(movie_id.as_str(), get_conn_from_ctx(ctx))
+        let res = Movie::delete_document::<R>(movie_id.as_str(), get_store_from_ctx(ctx))
             .await
             .expect("");
         Ok(res)
@@ -0,0 +1,8 @@
+use common_utils::health::{check_elasticsearch, Readiness};
+use crate::db::elastisearch_client;
+
+/// Ready only when the Elasticsearch cluster answers a ping
+pub fn readiness() -> Readiness { 
+    Readiness::new(env!("CARGO_PKG_NAME"))
+        .probe("elasticsearch", || check_elasticsearch(&elastisearch_client().0))
+}
@@ -5,4 +5,5 @@ extern crate serde_derive;
 
 pub mod graphql;
 pub mod server;
+pub mod health;
 pub mod db;
\ No newline at end of file
@@ -7,6 +7,8 @@ use async_graphql::{
 };
 use async_graphql_actix_web::{GraphQLRequest, GraphQLResponse};
 use crate::{db::establish_connection, graphql::config::{create_schema, configure_service}};
+use common_utils::health::configure_health;
+use crate::health::readiness;
 use std::fs::File;
 use std::io::Write;
 
@@ -36,6 +38,7 @@ pub async fn new_server(port: u32) -> std::io::Result<()> {
         App::new()
             .app_data(schema.clone())
             .configure(configure_service)
+            .configure(configure_health(readiness()))
             .wrap(Cors::permissive())
             .wrap(Logger::default())
     })
@@ -5,6 +5,7 @@ use std::{env, time::Duration, ffi::FromBytesWithNulError, sync::Arc};
 use scylla::{self,IntoTypedRows, query::Query, Session, SessionBuilder, batch::Consistency, load_balancing::{DcAwareRoundRobinPolicy, TokenAwarePolicy}, CachingSession, frame::value::ValueList, QueryResult, SessionConfig, transport::{iterator::RowIterator, session::KnownNode}, ValueList};
 use lazy_static::lazy_static;
 use once_cell::sync::OnceCell;
+use common_utils::metrics::datastore_timer;
 use std::fmt::Debug;
 
 
@@ -207,6 +208,7 @@ impl From<Session> for CachedSession {
 impl CachedSession { 
     /// Simple query
     pub async fn query(&self, query: &str, values: impl ValueList + Debug) -> Result<QueryResult> { 
+        let _timer = datastore_timer("scylla", "query");
         log::info!("Executing query from Scylla Database!");
 
         let mut simple_query = Query::new(query);
@@ -230,6 +232,7 @@ impl CachedSession {
     }
     ///  Executes a paged query 
     pub async fn query_iter(&self, query: &str, values: impl ValueList + Debug) -> Result<RowIterator> { 
+        let _timer = datastore_timer("scylla", "query_iter");
         log::info!("Preparing and paging new statemet");
         let result = self
             .0
@@ -243,6 +246,7 @@ impl CachedSession {
     /// When using a prepared statements the client does a prepare phase, where the request is parsed and upon 
     /// execution only binds the values to the statemetn identifier
     pub async fn query_prepared(&self, query: &str, values: impl ValueList + Debug) -> Result<QueryResult> { 
+        let _timer = datastore_timer("scylla", "query_prepared");
         log::info!("Preparing and executing statement: {:#?}", query);
 
         let mut prepared = Query::from(query);
@@ -0,0 +1,15 @@
+use common_utils::health::{check_consumer, check_scylla, Readiness};
+use crate::{db::session, kafka::{consumer_lag, CONSUMER_PROBE, KAFKA_MAX_LAG, KAFKA_MAX_POLL_AGE}};
+
+/// Ready only when Scylla answers a query and the consume loop is keeping up with the topic.
+/// The consumer lag gauge is refreshed before every scrape
+pub fn readiness() -> Readiness { 
+    Readiness::new(env!("CARGO_PKG_NAME"))
+        .probe("scylla", || check_scylla(&session().0))
+        .probe("kafka", || check_consumer(consumer_lag, &CONSUMER_PROBE, *KAFKA_MAX_LAG, *KAFKA_MAX_POLL_AGE))
+        .before_scrape(|| { 
+            if let Err(e) = consumer_lag() { 
+                log::warn!("Unable to refresh consumer lag: {}", e);
+            }
+        })
+}
@@ -1,24 +1,54 @@
+use std::collections::HashMap;
 use std::sync::Mutex;
+use std::time::Duration;
+use async_trait::async_trait;
 use futures::StreamExt;
 use lazy_static::lazy_static;
 use rdkafka::config::RDKafkaLogLevel;
 use rdkafka::consumer::{Consumer, StreamConsumer, CommitMode};
 use rdkafka::error::KafkaError;
 use rdkafka::message::BorrowedMessage;
-use rdkafka::{ClientConfig, ClientContext, Message};
+use rdkafka::{ClientConfig, ClientContext, Message, TopicPartitionList};
 use once_cell::sync::OnceCell;
+use rdkafka::Offset;
+use common_utils::{health::ConsumerProbe, metrics::{KAFKA_CONSUMED, KAFKA_CONSUMER_LAG}};
 
 use crate::db;
 use crate::modules::model::{NewRecommendedMovies, RecommendedMovies};
+use common_utils::events::{EventEnvelope, RecommendationEvent};
+use common_utils::{QueryResult, error::ServiceError};
+use tokio::sync::watch;
 
 lazy_static! {
     pub static ref KAFKA_BROKER: String = std::env::var("KAFKA_BROKER").expect("Can't read Kafka broker address");
     static ref KAFKA_TOPIC: String = std::env::var("KAFKA_TOPIC").expect("Can't read Kafka topic name");
-    static ref MESSAGE_KEY: String = std::env::var("MESSAGE_KEY").expect("Unable to get a valid message key");
     static ref CONSUMER_GROUP_ID: String = std::env::var("CONSUMER_GROUP_ID").expect("Expected a valid group id for consumers");
+    /// Readiness fails once the consumer falls this many messages behind
+    pub static ref KAFKA_MAX_LAG: i64 = std::env::var("KAFKA_MAX_LAG")
+        .ok()
+        .and_then(|lag| lag.parse::<i64>().ok())
+        .unwrap_or(10_000);
+    /// Readiness fails when the consumer is behind and has not polled for this many seconds
+    pub static ref KAFKA_MAX_POLL_AGE: i64 = std::env::var("KAFKA_MAX_POLL_AGE")
+        .ok()
+        .and_then(|age| age.parse::<i64>().ok())
+        .unwrap_or(60);
+    /// Largest number of recommendations written in a single batch
+    static ref CONSUMER_BATCH_SIZE: usize = std::env::var("CONSUMER_BATCH_SIZE")
+        .ok()
+        .and_then(|size| size.parse::<usize>().ok())
+        .unwrap_or(100);
+    /// Longest time a partially filled batch waits before it is flushed
+    static ref CONSUMER_BATCH_TIMEOUT: Duration = std::env::var("CONSUMER_BATCH_TIMEOUT_MS")
+        .ok()
+        .and_then(|timeout| timeout.parse::<u64>().ok())
+        .map(Duration::from_millis)
+        .unwrap_or(Duration::from_secs(1));
 }
 
 pub static KAFKACONN: OnceCell<KafkaClientContext> = OnceCell::new();
+/// Last time the consume loop came back from the broker, reported by `/readyz`
+pub static CONSUMER_PROBE: ConsumerProbe = ConsumerProbe::new();
 
 #[inline]
 pub(crate) fn kafka_client() -> &'static KafkaClientContext { 
@@ -53,6 +83,121 @@ impl<'a> From<&'a BorrowedMessage<'a>> for MessagePayload {
     }
 }
 
+/// A message taken off the topic, owned so it outlives the poll
+#[derive(Debug, Clone)]
+pub struct Received {
+    pub topic: String,
+    pub partition: i32,
+    pub offset: i64,
+    pub payload: MessagePayload,
+}
+
+impl Received {
+    pub fn new(topic: &str, partition: i32, offset: i64, payload: &str) -> Self {
+        Self { topic: topic.to_string(), partition, offset, payload: MessagePayload(payload.to_string()) }
+    }
+}
+
+impl<'a> From<&'a BorrowedMessage<'a>> for Received {
+    fn from(bm: &'a BorrowedMessage) -> Self {
+        Self {
+            topic: bm.topic().to_string(),
+            partition: bm.partition(),
+            offset: bm.offset(),
+            payload: MessagePayload::from(bm),
+        }
+    }
+}
+
+/// Where the consume loop reads from and commits to
+#[async_trait]
+pub trait MessageSource: Sync {
+    /// Waits for the next message. An error is logged and polling carries on
+    async fn recv(&self) -> QueryResult<Received>;
+    /// Commits the position right after the last of `messages` on each partition
+    fn commit(&self, messages: &[Received]) -> QueryResult<()>;
+}
+
+/// Where the decoded batches are written
+#[async_trait]
+pub trait RecommendationStore: Sync {
+    async fn write(&self, recommendations: Vec<RecommendedMovies>) -> QueryResult<()>;
+}
+
+/// Size limits of the consume loop
+#[derive(Debug, Clone)]
+pub struct BatchSettings {
+    /// Largest number of recommendations written at once
+    pub size: usize,
+    /// Longest time a partially filled batch waits before it is written
+    pub timeout: Duration,
+}
+
+impl BatchSettings {
+    pub fn from_env() -> Self {
+        Self { size: *CONSUMER_BATCH_SIZE, timeout: *CONSUMER_BATCH_TIMEOUT }
+    }
+}
+
+/// The Kafka consumer
+pub struct KafkaSource;
+
+#[async_trait]
+impl MessageSource for KafkaSource {
+    async fn recv(&self) -> QueryResult<Received> {
+        let message = kafka_client()
+            .0
+            .recv()
+            .await
+            .map_err(|e| ServiceError::MessageBrokerError(e.to_string()))?;
+        let received = Received::from(&message);
+        log::info!("👷‍♂️👷‍♂️ key: '{:?}', payload: '{:?}', topic: {}, partition: {}, offset: {}, timestamp: {:?}",
+            message.key(), 
+            received.payload, 
+            received.topic, 
+            received.partition, 
+            received.offset, 
+            message.timestamp());
+        Ok(received)
+    }
+    fn commit(&self, messages: &[Received]) -> QueryResult<()> {
+        let mut offsets = TopicPartitionList::new();
+        for ((topic, partition), offset) in last_offsets(messages) {
+            offsets
+                .add_partition_offset(topic, partition, Offset::Offset(offset + 1))
+                .map_err(|e| ServiceError::MessageBrokerError(e.to_string()))?;
+        }
+        kafka_client()
+            .0
+            .commit(&offsets, CommitMode::Sync)
+            .map_err(|e| ServiceError::MessageBrokerError(e.to_string()))
+    }
+}
+
+/// Highest offset read from each partition
+pub fn last_offsets(messages: &[Received]) -> HashMap<(&str, i32), i64> {
+    let mut offsets: HashMap<(&str, i32), i64> = HashMap::new();
+    for message in messages {
+        let offset = offsets.entry((message.topic.as_str(), message.partition)).or_insert(message.offset);
+        *offset = (*offset).max(message.offset);
+    }
+    offsets
+}
+
+/// The `user_recommendations` table in Scylla
+pub struct ScyllaStore;
+
+#[async_trait]
+impl RecommendationStore for ScyllaStore {
+    async fn write(&self, recommendations: Vec<RecommendedMovies>) -> QueryResult<()> {
+        log::info!("🛬 Writing {} recommendations into Scylla", recommendations.len());
+        db::stream_insert(recommendations, db::session())
+            .await
+            .map(|_| ())
+            .map_err(|e| ServiceError::ServerError(e.to_string()))
+    }
+}
+
 // Create the `StreamConsumer`, to receive the messages from the topic in form of a `Stream`.
 #[tracing::instrument(level = "debug", err)]
 pub fn create_consumer_dual_writes() -> Result<(), KafkaError> {
@@ -64,9 +209,8 @@ pub fn create_consumer_dual_writes() -> Result<(), KafkaError> {
         .set("auto.offset.reset", "latest")
         .set("enable.partition.eof", "true")
         .set("session.timeout.ms", "6000")
-        .set("enable.auto.commit", "true")
-        //  Commit every 5 seconds
-        .set("auto.commit.interval.ms", "5000")
+        //  Offsets are committed by the consume loop once a batch has been written
+        .set("enable.auto.commit", "false")
         .set_log_level(RDKafkaLogLevel::Debug)
         .create()
         .expect("Consumer creation failed");
@@ -77,44 +221,88 @@ pub fn create_consumer_dual_writes() -> Result<(), KafkaError> {
     
     Ok(())
 }
-/// Consumer group for the Asset Service where the payload 
-///  is sent to ElasticSearch to be indexed
-#[tracing::instrument(level = "debug")]
-pub async fn run_consumer_group_dual_writes() -> Result<(), KafkaError> { 
-    //  Create Kafka Consumer
+/// Consumer group for the recommendations produced by the tensorflow model
+#[tracing::instrument(level = "debug", skip(shutdown), err)]
+pub async fn run_consumer_group_dual_writes(shutdown: watch::Receiver<bool>) -> QueryResult<()> { 
     log::info!("🚦 Spawning consumer group: {}", CONSUMER_GROUP_ID.as_str());
-    let stream = kafka_client();
-    loop {
-        match stream.0.recv().await {
-            Err(e) => {
-                log::warn!("Kafka error: {}", e);
-            }
-            
-            Ok(message) => {
-                let payload = MessagePayload::from(&message);
-                log::info!("{:#?}", payload);
-                let mut index_movies: Vec<_> = Vec::new();
-                log::info!("👷‍♂️👷‍♂️ key: '{:?}', payload: '{:?}', topic: {}, partition: {}, offset: {}, timestamp: {:?}",
-                    message.key(), 
-                    payload, 
-                    message.topic(), 
-                    message.partition(), 
-                    message.offset(), 
-                    message.timestamp());
-                
-                log::info!("MOVIE STRUCT {:#?}", index_movies);
-                // Pass this dataset to Elasticsearch to be indexed 
-                log::info!("🛬 Received Payload from {}, Sending it over to Elasticsearch Cluster ", KAFKA_BROKER.as_str());
-                
-                // Add 'created_at' row to the payload so we can sort the messages for the user 
-                let new_recommendations: NewRecommendedMovies = serde_json::from_str(payload.as_str()).expect("Something went wrong in the payoad");
-                //  Convert NewRecommendations with 'created_at' column
-                index_movies.push(RecommendedMovies::from(new_recommendations));
-                //  Write new recommendations into database
-                db::stream_insert(index_movies, db::session())
-                    .await
-                    .expect("Unable to index the movie at {payload:?}");
+    consume_batches(&KafkaSource, &ScyllaStore, &BatchSettings::from_env(), shutdown).await?;
+    log::info!("🛑 Consumer group {} stopped polling, offsets committed", CONSUMER_GROUP_ID.as_str());
+    Ok(())
+}
+
+/// Messages are collected into batches that are written to Scylla before their offsets are 
+/// committed synchronously. Once `shutdown` fires the loop stops polling, flushes the 
+/// current batch and returns
+pub async fn consume_batches<S: MessageSource, R: RecommendationStore>(
+    source: &S,
+    store: &R,
+    settings: &BatchSettings,
+    mut shutdown: watch::Receiver<bool>,
+) -> QueryResult<()> { 
+    let mut stopping = false;
+    while !stopping {
+        let mut index_movies: Vec<RecommendedMovies> = Vec::new();
+        let mut messages: Vec<Received> = Vec::new();
+        let window = tokio::time::sleep(settings.timeout);
+        tokio::pin!(window);
+
+        while index_movies.len() < settings.size {
+            let message = tokio::select! {
+                //  Stop polling, whatever was already received is still flushed below
+                _ = shutdown.changed() => {
+                    stopping = true;
+                    break
+                }
+                _ = &mut window => break,
+                message = source.recv() => message,
+            };
+            CONSUMER_PROBE.record_poll();
+            let message = match message {
+                Err(e) => {
+                    log::warn!("Kafka error: {}", e);
+                    continue
+                }
+                Ok(message) => message
+            };
+            KAFKA_CONSUMED.with_label_values(&[message.topic.as_str(), "received"]).inc();
+            match EventEnvelope::<RecommendationEvent<NewRecommendedMovies>>::decode(message.payload.as_str()) {
+                Ok(event) => match event.payload {
+                    // Add 'created_at' row to the payload so we can sort the messages for the user 
+                    RecommendationEvent::RecommendationGenerated(new_recommendations) => {
+                        index_movies.push(RecommendedMovies::from(new_recommendations))
+                    }
+                },
+                Err(e) => {
+                    log::error!("❌ Skipping undecodable event at offset {}: {}", message.offset, e);
+                    KAFKA_CONSUMED.with_label_values(&[message.topic.as_str(), "malformed"]).inc();
+                }
             }
-        };
+            messages.push(message);
+        }
+        if messages.is_empty() { 
+            continue
+        }
+        if !index_movies.is_empty() {
+            store.write(index_movies).await?;
+        }
+        //  Only commit once the whole batch has been written
+        source.commit(&messages)?;
     }
-}
\ No newline at end of file
+    Ok(())
+}
+
+/// Total number of messages the consumer is behind across its assigned partitions.
+/// Fetching the watermarks blocks, so call this off the async workers
+pub fn consumer_lag() -> Result<i64, KafkaError> { 
+    let consumer = &kafka_client().0;
+    let positions = consumer.position()?;
+    let mut lag = 0;
+    for partition in positions.elements() { 
+        let (_, high) = consumer.fetch_watermarks(partition.topic(), partition.partition(), Duration::from_secs(1))?;
+        if let Offset::Offset(position) = partition.offset() { 
+            lag += (high - position).max(0);
+        }
+    }
+    KAFKA_CONSUMER_LAG.with_label_values(&[CONSUMER_GROUP_ID.as_str()]).set(lag);
+    Ok(lag)
+}
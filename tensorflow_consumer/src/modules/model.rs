@@ -1,6 +1,7 @@
 use scylla::ValueList;
 use serde::{Serialize, Deserialize};
 use chrono::{NaiveDate, Utc};
+use common_utils::events::PartitionKey;
 
 
 
@@ -21,6 +22,12 @@ pub struct NewRecommendedMovies {
     pub movie_id: i64,
 }
 
+impl PartitionKey for NewRecommendedMovies { 
+    fn partition_key(&self) -> String {
+        self.user_id.to_string()
+    }
+}
+
 impl From<NewRecommendedMovies> for RecommendedMovies { 
     fn from(f: NewRecommendedMovies) -> Self {
         let created_at =  Utc::today().naive_local();
@@ -1,8 +1,12 @@
 
 use actix_cors::Cors;
 use actix_web::{middleware::Logger, web, App, HttpServer};
-use crate::kafka::create_consumer_dual_writes;
+use crate::kafka::{create_consumer_dual_writes, run_consumer_group_dual_writes};
 use crate::telemetry::init_telemetry;
+use common_utils::health::configure_health;
+use crate::health::readiness;
+use common_utils::shutdown::drain_on_termination;
+use tokio::sync::watch;
 use tracing_actix_web::TracingLogger;
 use crate::db::{establish_connection, initialise_pool, session, DBCONN};
 
@@ -18,6 +22,9 @@ pub async fn new_server(port: u32) -> std::io::Result<()> {
         .expect("Unable to establish ScyllaDB connection");
     
     let _ = create_consumer_dual_writes().expect("Unable to create a consumer for Kafka");
+    //  The consume loop runs alongside the HTTP server so its health can be probed
+    let (shutdown, shutdown_signal) = watch::channel(false);
+    let consumer = actix_web::rt::spawn(run_consumer_group_dual_writes(shutdown_signal));
 
 
     log::info!("🚀 Starting HTTP server on port {} ", port);
@@ -31,15 +38,19 @@ pub async fn new_server(port: u32) -> std::io::Result<()> {
 
     // Ensure all spans have been shipped to Jaeger.
     opentelemetry::global::shutdown_tracer_provider();
-    HttpServer::new(move || {
+    let server = HttpServer::new(move || {
         App::new()
+            .configure(configure_health(readiness()))
             .wrap(Cors::permissive())
             .wrap(Logger::default())
     })
     .workers(3)
     .bind(format!("127.0.0.1:{}", port))?
-    .run()
-    .await
+    //  Signals are handled below so the consumer is drained before the server stops
+    .disable_signals()
+    .run();
+    actix_web::rt::spawn(drain_on_termination(consumer, shutdown, server.handle()));
+    server.await
 }
 
 /// Helper function to automate setting new databases
@@ -0,0 +1,132 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+use async_trait::async_trait;
+use common_utils::{QueryResult, error::ServiceError};
+use serde_json::json;
+use tensorflow_consumer::kafka::{consume_batches, last_offsets, BatchSettings, MessageSource, Received, RecommendationStore};
+use tensorflow_consumer::modules::model::RecommendedMovies;
+use tokio::sync::watch;
+
+const TOPIC: &str = "recommended_movies";
+
+/// Hands out its messages, then asks the loop to stop as the termination signal would
+struct FakeSource {
+    pending: Mutex<VecDeque<Received>>,
+    shutdown: watch::Sender<bool>,
+    committed: Mutex<Vec<Received>>,
+}
+
+impl FakeSource {
+    fn new(messages: Vec<Received>, shutdown: watch::Sender<bool>) -> Self {
+        Self { pending: Mutex::new(messages.into()), shutdown, committed: Mutex::new(Vec::new()) }
+    }
+    fn committed_offsets(&self) -> Vec<((String, i32), i64)> {
+        let committed = self.committed.lock().unwrap();
+        let mut offsets: Vec<((String, i32), i64)> = last_offsets(&committed)
+            .into_iter()
+            .map(|((topic, partition), offset)| ((topic.to_string(), partition), offset))
+            .collect();
+        offsets.sort();
+        offsets
+    }
+}
+
+#[async_trait]
+impl MessageSource for FakeSource {
+    async fn recv(&self) -> QueryResult<Received> {
+        let next = self.pending.lock().unwrap().pop_front();
+        match next {
+            Some(message) => Ok(message),
+            None => {
+                let _ = self.shutdown.send(true);
+                std::future::pending().await
+            }
+        }
+    }
+    fn commit(&self, messages: &[Received]) -> QueryResult<()> {
+        self.committed.lock().unwrap().extend_from_slice(messages);
+        Ok(())
+    }
+}
+
+/// Records the movie ids of every batch it is sent, failing when `fails` is set
+#[derive(Default)]
+struct FakeStore {
+    batches: Mutex<Vec<Vec<i64>>>,
+    fails: bool,
+}
+
+#[async_trait]
+impl RecommendationStore for FakeStore {
+    async fn write(&self, recommendations: Vec<RecommendedMovies>) -> QueryResult<()> {
+        if self.fails {
+            return Err(ServiceError::ServerError(String::from("Scylla is down")))
+        }
+        self.batches.lock().unwrap().push(recommendations.into_iter().map(|recommendation| recommendation.movie_id).collect());
+        Ok(())
+    }
+}
+
+fn settings() -> BatchSettings {
+    BatchSettings { size: 100, timeout: Duration::from_secs(60) }
+}
+
+fn recommendation(partition: i32, offset: i64, movie_id: i64) -> Received {
+    let envelope = json!({
+        "event_id": "6f1c2a3e-8b7d-4e5f-9a0b-1c2d3e4f5a6b",
+        "event_type": "recommendation.generated",
+        "schema_version": 1,
+        "occurred_at": "2022-08-01T12:00:00Z",
+        "producer": "tensorflow_ml",
+        "payload": { "kind": "recommendation_generated", "data": { "userId": 42, "title": "Heat", "movieId": movie_id } },
+    });
+    Received::new(TOPIC, partition, offset, &envelope.to_string())
+}
+
+async fn run(source: &FakeSource, store: &FakeStore, shutdown: watch::Receiver<bool>) -> QueryResult<()> {
+    tokio::time::timeout(Duration::from_secs(5), consume_batches(source, store, &settings(), shutdown))
+        .await
+        .expect("The consume loop did not stop after the termination signal")
+}
+
+#[tokio::test]
+async fn termination_mid_batch_writes_and_commits_the_received_messages() {
+    let (shutdown, shutdown_signal) = watch::channel(false);
+    let source = FakeSource::new(vec![
+        recommendation(0, 10, 1),
+        recommendation(1, 4, 2),
+        recommendation(0, 11, 3),
+    ], shutdown);
+    let store = FakeStore::default();
+
+    run(&source, &store, shutdown_signal).await.unwrap();
+
+    assert_eq!(*store.batches.lock().unwrap(), vec![vec![1, 2, 3]]);
+    assert_eq!(source.committed_offsets(), vec![((TOPIC.to_string(), 0), 11), ((TOPIC.to_string(), 1), 4)]);
+}
+
+#[tokio::test]
+async fn undecodable_messages_are_committed_with_their_batch() {
+    let (shutdown, shutdown_signal) = watch::channel(false);
+    let source = FakeSource::new(vec![
+        recommendation(0, 1, 1),
+        Received::new(TOPIC, 0, 2, "not json"),
+    ], shutdown);
+    let store = FakeStore::default();
+
+    run(&source, &store, shutdown_signal).await.unwrap();
+
+    assert_eq!(*store.batches.lock().unwrap(), vec![vec![1]]);
+    assert_eq!(source.committed_offsets(), vec![((TOPIC.to_string(), 0), 2)]);
+}
+
+#[tokio::test]
+async fn nothing_is_committed_when_the_write_fails() {
+    let (shutdown, shutdown_signal) = watch::channel(false);
+    let source = FakeSource::new(vec![recommendation(0, 1, 1)], shutdown);
+    let store = FakeStore { fails: true, ..FakeStore::default() };
+
+    assert!(run(&source, &store, shutdown_signal).await.is_err());
+    assert!(source.committed.lock().unwrap().is_empty());
+}
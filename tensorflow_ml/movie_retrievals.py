@@ -10,7 +10,9 @@ import pprint
 import tempfile
 from typing import Dict, Text
 import numpy as np
-from json import loads
+from json import loads, dumps
+import uuid
+from datetime import datetime, timezone
 import tensorflow as tf
 import tensorflow_recommenders as tfrs
 import tensorflow_datasets as tfds
@@ -28,6 +30,34 @@ KAFKA_TOPIC_NAME_USER = "user_analytics"
 KAFKA_CLIENT = "localhost:9092"
 KAFKA_PRODUCER = "localhost:9093"
 KAFKA_PRODUCER_TOPIC = "recommended_movies"
+EVENT_SCHEMA_VERSION = 1
+# The catalog events carrying a whole movie, deletes and the other types are skipped
+MOVIE_EVENT_TYPES = ('catalog.movie.created', 'catalog.movie.updated', 'catalog.movie.reindex_requested')
+
+# Every topic carries a versioned envelope, older producers sent the bare payload. An enveloped
+# event whose type isn't in `event_types` comes back as None
+def unwrap_event(raw, event_types=None):
+  message = loads(raw.decode('utf-8'))
+  if isinstance(message, dict) and 'schema_version' in message and 'payload' in message:
+    if event_types is not None and message.get('event_type') not in event_types:
+      return None
+    return message['payload']['data']
+  return message
+
+def unwrap_movie_event(raw):
+  return unwrap_event(raw, MOVIE_EVENT_TYPES)
+
+def wrap_event(payload):
+  return dumps({
+    'event_id': str(uuid.uuid4()),
+    'event_type': 'recommendation.generated',
+    'schema_version': EVENT_SCHEMA_VERSION,
+    'occurred_at': datetime.now(timezone.utc).isoformat(),
+    'producer': 'tensorflow_ml',
+    'trace_context': {},
+    'payload': { 'kind': 'recommendation_generated', 'data': payload },
+  })
+
 # Consumer for Movies Dataset
 # When a new movie is added, the mvoie is added to a queue
 movie_consumer = KafkaConsumer(
@@ -39,7 +69,7 @@ movie_consumer = KafkaConsumer(
     # auto_commit_interval_ms=5000,
     session_timeout_ms=6000,
     # Decode the message comming from the producer
-    value_deserializer=lambda x: loads(x.decode('utf-8'))
+    value_deserializer=unwrap_movie_event
 )
 # Consumer for User Analytics Service
 user_rating_consumer = KafkaConsumer(
@@ -49,7 +79,7 @@ user_rating_consumer = KafkaConsumer(
     group_id=KAFKA_GROUP_ID,
     auto_offset_reset='earliest',
     session_timeout_ms=6000,
-    value_deserializer=lambda x: loads(x.decode('utf-8'))
+    value_deserializer=unwrap_event
 )
 
 # User Ratings
@@ -66,6 +96,8 @@ for msg in user_rating_consumer:
 # The movies dataset contains the movie id, movie title and data on what
 # genres it belongs to.
 for msg in movie_consumer:
+    if msg.value is None:
+      continue
     movies = msg.value
 
 movies = tf.data.Dataset.from_tensor_slices(dict(movies)).map(lambda x: { x["title"]}).batch(4)
@@ -209,8 +241,9 @@ def write_to_kafka(topic_name, items):
   count = 0
   producer = KafkaProducer(bootstrap_servers=[KAFKA_PRODUCER])
   for msg, key in items:
+    # `msg` is already json, the envelope carries it as an object
     producer \
-      .send(topic_name, key=key.encode('utf-8'), value=msg.encode('utf-8')) \
+      .send(topic_name, key=key.encode('utf-8'), value=wrap_event(loads(msg)).encode('utf-8')) \
       .add_errback(error_callback)
     count +=1
   producer.flush()
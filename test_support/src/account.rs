@@ -0,0 +1,139 @@
+use async_graphql::{EmptySubscription, MergedObject, Schema};
+use async_trait::async_trait;
+use chrono::Utc;
+use uuid::Uuid;
+use account_service::graphql::{
+    utils::hash_password,
+    user_module::{model::{NewUser, Users}, resolver::UserResolver, schema::{UserMutation, UserQuery}},
+    profile_module::{model::{NewProfile, Profiles}, resolvers::ProfileResolver, schema::{ProfileMutation, ProfileQuery}},
+};
+use common_utils::{QueryResult, error::ServiceError};
+use crate::MemoryTable;
+
+/// `users` table keyed by id
+pub type UserStore = MemoryTable<Uuid, Users>;
+/// `profiles` table keyed by profile id
+pub type ProfileStore = MemoryTable<Uuid, Profiles>;
+
+#[derive(Default)]
+pub struct InMemoryUserDatabase;
+
+#[async_trait]
+impl UserResolver for InMemoryUserDatabase {
+    type Store = UserStore;
+
+    async fn get_all_users(conn: &UserStore) -> QueryResult<Vec<Users>> {
+        Ok(conn.rows())
+    }
+    async fn get_user_by_id(id: Uuid, conn: &UserStore) -> QueryResult<Option<Users>> {
+        Ok(conn.get(&id))
+    }
+    async fn get_user_by_email(email: String, conn: &UserStore) -> QueryResult<Option<Users>> {
+        Ok(conn.find(|user| user.email == email))
+    }
+    async fn get_user_by_username(username: String, conn: &UserStore) -> QueryResult<Option<Users>> {
+        Ok(conn.find(|user| user.username == username))
+    }
+    async fn create_user(new_user: NewUser, conn: &UserStore) -> QueryResult<Users> {
+        let user = Users {
+            id: Uuid::new_v4(),
+            email: new_user.email,
+            hash: hash_password(&new_user.hash).map_err(|e| ServiceError::ServerError(e.to_string()))?,
+            created_at: new_user.created_at,
+            updated_at: new_user.updated_at,
+            username: new_user.username,
+            first_name: new_user.first_name,
+            last_name: new_user.last_name,
+            image_url: new_user.image_url,
+            last_login_at: Some(new_user.last_login_at),
+            role: new_user.role,
+        };
+        conn.insert(user.id, user.clone());
+        Ok(user)
+    }
+    async fn delete_user(user_id: Uuid, conn: &UserStore) -> QueryResult<bool> {
+        Ok(conn.remove(&user_id).is_some())
+    }
+    async fn update_user(user_id: Uuid, new_user: NewUser, conn: &UserStore) -> QueryResult<Option<Users>> {
+        let hash = hash_password(&new_user.hash).map_err(|e| ServiceError::ServerError(e.to_string()))?;
+        Ok(conn.update(&user_id, |user| {
+            user.email = new_user.email;
+            user.hash = hash;
+            user.updated_at = Some(Utc::now().naive_utc());
+            user.username = new_user.username;
+            user.first_name = new_user.first_name;
+            user.last_name = new_user.last_name;
+            user.image_url = new_user.image_url;
+        }))
+    }
+    async fn update_password(user_id: Uuid, password: String, conn: &UserStore) -> QueryResult<Option<Users>> {
+        let hash = hash_password(&password).map_err(|e| ServiceError::ServerError(e.to_string()))?;
+        Ok(conn.update(&user_id, |user| user.hash = hash))
+    }
+    async fn update_last_login(user_id: Uuid, conn: &UserStore) -> QueryResult<bool> {
+        let updated = conn.update(&user_id, |user| user.last_login_at = Some(Utc::now().naive_utc()));
+        Ok(updated.is_some())
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryProfileDatabase;
+
+#[async_trait]
+impl ProfileResolver for InMemoryProfileDatabase {
+    type Store = ProfileStore;
+
+    async fn get_profiles_by_owner(user_id: Uuid, conn: &ProfileStore) -> QueryResult<Vec<Profiles>> {
+        Ok(conn.filter(|profile| profile.id == user_id))
+    }
+    async fn get_profile_by_id(profile_id: Uuid, conn: &ProfileStore) -> QueryResult<Profiles> {
+        conn.get(&profile_id).ok_or(ServiceError::NotFound)
+    }
+    async fn get_profile_by_name(username: String, conn: &ProfileStore) -> QueryResult<Profiles> {
+        conn.find(|profile| profile.username == username).ok_or(ServiceError::NotFound)
+    }
+    async fn create_new_profile(new_profile: NewProfile, conn: &ProfileStore) -> QueryResult<Profiles> {
+        let profile = Profiles {
+            profile_id: Uuid::new_v4(),
+            id: new_profile.id,
+            username: new_profile.username,
+            created_at: Utc::now().naive_utc(),
+            updated_at: new_profile.updated_at,
+        };
+        conn.insert(profile.profile_id, profile.clone());
+        Ok(profile)
+    }
+    async fn update_profile_user(user_id: Uuid, profile_id: Uuid, new_profile: NewProfile, conn: &ProfileStore) -> QueryResult<Option<Profiles>> {
+        match conn.get(&profile_id) {
+            Some(profile) if profile.id == user_id => Ok(conn.update(&profile_id, |profile| {
+                profile.username = new_profile.username;
+                profile.updated_at = new_profile.updated_at;
+            })),
+            _ => Ok(None),
+        }
+    }
+    async fn delete_profile_by_user(user_id: Uuid, profile_id: Uuid, conn: &ProfileStore) -> QueryResult<bool> {
+        match conn.get(&profile_id) {
+            Some(profile) if profile.id == user_id => Ok(conn.remove(&profile_id).is_some()),
+            _ => Ok(false),
+        }
+    }
+}
+
+#[derive(MergedObject, Default)]
+pub struct Query(UserQuery<InMemoryUserDatabase>, ProfileQuery<InMemoryProfileDatabase>);
+
+#[derive(MergedObject, Default)]
+pub struct Mutation(UserMutation<InMemoryUserDatabase>, ProfileMutation<InMemoryProfileDatabase>);
+
+pub type AccountSchema = Schema<Query, Mutation, EmptySubscription>;
+
+/// Account schema served from the given tables. Fields that go through the
+/// Redis cache still need a Redis client registered with `.data(..)`
+pub fn schema(users: UserStore, profiles: ProfileStore) -> AccountSchema {
+    Schema::build(Query::default(), Mutation::default(), EmptySubscription)
+        .enable_federation()
+        .data(users)
+        .data(profiles)
+        .finish()
+}
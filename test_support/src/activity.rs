@@ -0,0 +1,49 @@
+use std::sync::Arc;
+use async_graphql::{EmptySubscription, MergedObject, Schema};
+use async_trait::async_trait;
+use activity_tracker::graphql::modules::{
+    model::UserWatchTime,
+    resolver::AnalyticsResolver,
+    schema::{AnalyticsMutation, AnalyticsQuery},
+};
+use common_utils::QueryResult;
+use crate::MemoryTable;
+
+/// `user_activity` measurement. Points sharing a user, session and timestamp overwrite each
+/// other like they do in Influx. Cloned into every resolver call, so the table sits behind an `Arc`
+#[derive(Clone, Default)]
+pub struct ActivityStore(pub Arc<MemoryTable<(i64, i64, i64), UserWatchTime>>);
+
+#[derive(Default)]
+pub struct InMemoryAnalyticsDatabase;
+
+#[async_trait]
+impl AnalyticsResolver for InMemoryAnalyticsDatabase {
+    type Store = ActivityStore;
+
+    async fn get_all_records(client: ActivityStore) -> QueryResult<Vec<UserWatchTime>> {
+        Ok(client.0.rows())
+    }
+    async fn get_user_records(user_id: i64, client: ActivityStore) -> QueryResult<Vec<UserWatchTime>> {
+        Ok(client.0.filter(|record| record.user_id == user_id))
+    }
+    async fn record_user_watchtime(user_info: UserWatchTime, client: ActivityStore) -> QueryResult<Vec<UserWatchTime>> {
+        let key = (user_info.user_id, user_info.session, user_info.time);
+        client.0.insert(key, user_info.clone());
+        Self::get_user_records(user_info.user_id, client).await
+    }
+}
+
+#[derive(MergedObject, Default)]
+pub struct Query(AnalyticsQuery<InMemoryAnalyticsDatabase>);
+
+#[derive(MergedObject, Default)]
+pub struct Mutation(AnalyticsMutation<InMemoryAnalyticsDatabase>);
+
+pub type ActivitySchema = Schema<Query, Mutation, EmptySubscription>;
+
+pub fn schema(store: ActivityStore) -> ActivitySchema {
+    Schema::build(Query::default(), Mutation::default(), EmptySubscription)
+        .data(store)
+        .finish()
+}
@@ -0,0 +1,832 @@
+use std::{sync::{Arc, atomic::{AtomicI32, Ordering}}, time::Duration};
+use async_graphql::{MergedObject, MergedSubscription, Schema};
+use async_trait::async_trait;
+use asset_ingestion_service::graphql::modules::types::{
+    availability::{model::{AvailabilityWindow, boundary_day}, resolver::AvailabilityResolver, schema::AvailabilityMutation},
+    credits::{model::Credit, resolver::CreditResolver, schema::CreditMutation},
+    entity_resolution::schema::{EntityResolutionMutation, EntityResolutionQuery},
+    genres::{model::{Genre, default_genres}, resolver::GenreResolver, schema::{GenreMutation, GenreQuery}},
+    ingestion_jobs::{
+        model::{IngestionJob, JobStatus}, 
+        resolver::JobResolver, 
+        schema::{IngestionJobMutation, IngestionJobQuery, IngestionJobSubscription}, 
+        source::{CatalogSource, SharedSource},
+    },
+    movies::{model::{Movie, MoviePatch, NewMovie, Upserted}, resolver::MovieResolver, schema::MovieMutation},
+    people_module::{model::{NewPerson, Person, PersonExternalId}, resolver::PersonResolver, schema::{PersonMutation, PersonQuery}},
+    prod_company::{model::{CompanyExternalId, NewProductionComp, ProductionCompany}, resolver::ProdCompanyResolver, schema::{ProductionCompanyMutation, ProductionCompanyQuery}},
+    series::{model::{CatalogSeriesEvent, Episode, Season, Series}, resolver::SeriesResolver, schema::SeriesMutation},
+    translations::{model::MovieTranslation, resolver::TranslationResolver, schema::TranslationMutation},
+    release::{model::{ReleaseSchedule, StatusChange}, resolver::ReleaseResolver, schema::{ReleaseMutation, ReleaseQuery}},
+    artwork::{model::Artwork, resolver::ArtworkResolver, schema::{ArtworkMutation, ArtworkQuery}, storage::{ImageStore, SharedImageStore}},
+    media::{model::{AudioTrack, MediaAsset, Rendition, TextTrack, TrackType}, resolver::MediaResolver, schema::{MediaMutation, MediaQuery}},
+    outbox::{model::{OutboxEntry, SentEntry}, resolver::OutboxResolver},
+    movies::schema::BulkStreamInsertData,
+};
+use common_utils::kafka::Delivery;
+use common_utils::{QueryResult, artwork::ImageOwner, availability::now_millis, error::ServiceError, events::{AvailabilityEvent, CatalogEvent, Event, TranslationEvent}, playback::MediaOwner};
+use crate::MemoryTable;
+
+/// Keyspace of the ingestion service, one table per resolver
+#[derive(Default)]
+pub struct CatalogStore {
+    pub movies: MemoryTable<i64, Movie>,
+    pub companies: MemoryTable<i64, ProductionCompany>,
+    pub people: MemoryTable<i32, Person>,
+    /// The last person id handed out, like the `person` row of `id_counters`
+    pub person_ids: AtomicI32,
+    pub series: MemoryTable<i64, Series>,
+    /// Keyed by series id and season number
+    pub seasons: MemoryTable<(i64, i32), Season>,
+    /// Keyed by series id, season number and episode number
+    pub episodes: MemoryTable<(i64, i32, i32), Episode>,
+    /// Catalogue import ids mapped to the movie they created
+    pub external_ids: MemoryTable<String, i64>,
+    pub jobs: MemoryTable<i64, IngestionJob>,
+    /// Keyed by movie id and credit id, like `credits_by_movie`
+    pub credits: MemoryTable<(i64, String), Credit>,
+    /// Keyed by external id and person id, like `people_by_external_id`
+    pub person_external_ids: MemoryTable<(String, i32), PersonExternalId>,
+    pub company_external_ids: MemoryTable<(String, i64), CompanyExternalId>,
+    pub genres: MemoryTable<i32, Genre>,
+    /// Keyed by movie id and locale, like `movie_translations`
+    pub translations: MemoryTable<(i64, String), MovieTranslation>,
+    /// Keyed by movie id and window id, like `movie_availability`
+    pub availability: MemoryTable<(i64, i64), AvailabilityWindow>,
+    /// `availability_boundaries` keyed by day, time and movie id, holding the movie id
+    pub boundaries: MemoryTable<(String, i64, i64), i64>,
+    pub release_schedules: MemoryTable<i64, ReleaseSchedule>,
+    /// `release_boundaries` keyed by day, time and movie id, holding the movie id
+    pub release_boundaries: MemoryTable<(String, i64, i64), i64>,
+    /// Keyed by movie id and change id, like `movie_status_history`
+    pub status_history: MemoryTable<(i64, i64), StatusChange>,
+    /// Keyed by owner type, owner id and image id, like `artwork`
+    pub artwork: MemoryTable<(String, i64, i64), Artwork>,
+    /// Keyed by owner type, owner id and rendition id, like `media_renditions`
+    pub renditions: MemoryTable<(String, i64, i64), Rendition>,
+    /// Keyed by owner type, owner id and track id, like `media_audio_tracks`
+    pub audio_tracks: MemoryTable<(String, i64, i64), AudioTrack>,
+    /// Keyed by owner type, owner id and track id, like `media_text_tracks`
+    pub text_tracks: MemoryTable<(String, i64, i64), TextTrack>,
+    /// Renditions of uploaded images, shared with the schema as its `SharedImageStore`
+    pub image_objects: Arc<InMemoryImageStore>,
+    /// Keyed by bucket and entry id, like `event_outbox`. The fake resolvers write to it
+    pub outbox: MemoryTable<(i32, i64), OutboxEntry>,
+    /// Keyed by event id, like `event_outbox_sent`
+    pub outbox_sent: MemoryTable<String, SentEntry>,
+    /// Keyed by bucket and entry id, like `event_outbox_dead`
+    pub outbox_dead: MemoryTable<(i32, i64), OutboxEntry>,
+    /// The owner of each bucket and when its lease runs out, like the TTL of `event_outbox_leases`
+    pub outbox_leases: MemoryTable<i32, (String, i64)>,
+}
+
+impl CatalogStore {
+    /// What `enqueue` adds to the logged batch of a Scylla write
+    fn enqueue<E: Event>(&self, event: E) -> QueryResult<()> {
+        let entry = OutboxEntry::from_event(event)?;
+        self.outbox.insert((entry.bucket, entry.entry_id), entry);
+        Ok(())
+    }
+}
+
+/// Stored renditions by key, served from `memory://` URLs
+#[derive(Default)]
+pub struct InMemoryImageStore {
+    pub objects: MemoryTable<String, Vec<u8>>,
+}
+
+#[async_trait]
+impl ImageStore for InMemoryImageStore {
+    async fn put(&self, key: &str, _content_type: &str, bytes: Vec<u8>) -> QueryResult<String> {
+        self.objects.insert(key.to_string(), bytes);
+        Ok(format!("memory://{}", key))
+    }
+    async fn delete_prefix(&self, prefix: &str) -> QueryResult<()> {
+        let prefix = format!("{}/", prefix);
+        for (key, _) in self.objects.entries().into_iter().filter(|(key, _)| key.starts_with(&prefix)) {
+            self.objects.remove(&key);
+        }
+        Ok(())
+    }
+}
+
+fn movie_row(new_movie: NewMovie) -> Movie {
+    Movie {
+        movie_id: new_movie.movie_id,
+        title: new_movie.title,
+        year: new_movie.year,
+        awards: new_movie.awards,
+        business: new_movie.business,
+        countries: new_movie.countries,
+        deleted_at: None,
+        genres: new_movie.genres,
+        homepage: new_movie.homepage,
+        keywords: new_movie.keywords,
+        languages: new_movie.languages,
+        media_type: new_movie.media_type,
+        movie_casts: new_movie.movie_casts,
+        movie_company: new_movie.movie_company,
+        movie_director: new_movie.movie_director,
+        movie_writer: new_movie.movie_writer,
+        overview: new_movie.overview,
+        poster: new_movie.poster,
+        rated: new_movie.rated,
+        rating: new_movie.rating,
+        release_date: new_movie.release_date,
+        runtime: new_movie.runtime,
+        status: new_movie.status,
+        video_file: new_movie.video_file,
+    }
+}
+
+fn company_row(new_company: NewProductionComp) -> ProductionCompany {
+    ProductionCompany {
+        company_id: new_company.company_id,
+        name: new_company.name,
+        description: new_company.description,
+        headquarter: new_company.headquarter,
+        homepage: new_company.homepage,
+        logo_path: new_company.logo_path,
+        movie_id: new_company.movie_id,
+        origin_country: new_company.origin_country,
+        parent_company: new_company.parent_company,
+    }
+}
+
+fn person_row(new_person: NewPerson) -> Person {
+    Person {
+        person_id: new_person.person_id,
+        name: new_person.name,
+        awards: new_person.awards,
+        biography: new_person.biography,
+        birthday: new_person.birthday,
+        death_date: new_person.death_date,
+        gender: new_person.gender,
+        homepage: new_person.homepage,
+        known_for: new_person.known_for,
+        place_of_birth: new_person.place_of_birth,
+        profile_path: new_person.profile_path,
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryMovieDatabase;
+
+#[async_trait]
+impl MovieResolver for InMemoryMovieDatabase {
+    type Store = CatalogStore;
+
+    async fn get_movie_id(id: i64, session: &'static CatalogStore) -> QueryResult<Movie> {
+        session.movies.get(&id).ok_or(ServiceError::NotFound)
+    }
+    async fn get_movies(session: &'static CatalogStore) -> QueryResult<Vec<Movie>> {
+        Ok(session.movies.rows())
+    }
+    async fn create_movie(new_movie: NewMovie, session: &'static CatalogStore) -> QueryResult<Movie> {
+        let movie = movie_row(new_movie);
+        session.movies.insert(movie.movie_id, movie.clone());
+        session.enqueue(CatalogEvent::MovieCreated(movie.clone()))?;
+        Ok(movie)
+    }
+    async fn update_movie(id: i64, new_movie: NewMovie, session: &'static CatalogStore) -> QueryResult<Movie> {
+        Self::patch_movie(id, MoviePatch::from(new_movie), session).await
+    }
+    async fn patch_movie(id: i64, patch: MoviePatch, session: &'static CatalogStore) -> QueryResult<Movie> {
+        let movie = session.movies
+            .update(&id, |movie| *movie = patch.apply(movie))
+            .ok_or(ServiceError::NotFound)?;
+        if !movie.is_deleted() {
+            session.enqueue(CatalogEvent::MovieUpdated(movie.clone()))?;
+        }
+        Ok(movie)
+    }
+    async fn delete_movie(id: i64, session: &'static CatalogStore) -> QueryResult<bool> {
+        match session.movies.remove(&id) {
+            Some(_) => {
+                session.enqueue(CatalogEvent::MovieDeleted { movie_id: id })?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+    async fn set_deleted(id: i64, deleted_at: Option<i64>, session: &'static CatalogStore) -> QueryResult<Movie> {
+        let current = session.movies.get(&id).ok_or(ServiceError::NotFound)?;
+        if current.is_deleted() == deleted_at.is_some() {
+            return Ok(current);
+        }
+        let movie = Movie { deleted_at, ..current };
+        session.movies.insert(id, movie.clone());
+        match deleted_at {
+            Some(deleted_at) => session.enqueue(CatalogEvent::MovieSoftDeleted { movie_id: id, deleted_at })?,
+            None => session.enqueue(CatalogEvent::MovieRestored(movie.clone()))?,
+        }
+        Ok(movie)
+    }
+    async fn set_genres(movie: Movie, genres: Vec<String>, session: &'static CatalogStore) -> QueryResult<Movie> {
+        let movie = Movie { genres, ..movie };
+        session.movies.insert(movie.movie_id, movie.clone());
+        if !movie.is_deleted() {
+            session.enqueue(CatalogEvent::MovieUpdated(movie.clone()))?;
+        }
+        Ok(movie)
+    }
+    async fn bulk_insert(movie: Vec<Movie>, session: &'static CatalogStore) -> QueryResult<bool> {
+        for movie in movie {
+            session.movies.insert(movie.movie_id, movie.clone());
+            session.enqueue(CatalogEvent::MovieCreated(movie))?;
+        }
+        Ok(true)
+    }
+    async fn stream_insert(movie: Vec<Movie>, session: &'static CatalogStore) -> QueryResult<bool> {
+        Self::bulk_insert(movie, session).await
+    }
+    async fn upsert_by_external_id(external_id: String, new_movie: NewMovie, session: &'static CatalogStore) -> QueryResult<Upserted> {
+        if let Some(id) = session.external_ids.get(&external_id) {
+            return Self::patch_movie(id, MoviePatch::from(new_movie), session).await.map(Upserted::Updated);
+        }
+        let movie = Self::create_movie(new_movie, session).await?;
+        session.external_ids.insert(external_id, movie.movie_id);
+        Ok(Upserted::Created(movie))
+    }
+    async fn request_reindex(movies: Vec<Movie>, session: &'static CatalogStore) -> QueryResult<()> {
+        for movie in movies {
+            session.enqueue(CatalogEvent::MovieReindexRequested(movie))?;
+        }
+        Ok(())
+    }
+    async fn backfill_movie_keys(_session: &'static CatalogStore) -> QueryResult<i32> {
+        Ok(0)
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryCompanyDatabase;
+
+#[async_trait]
+impl ProdCompanyResolver for InMemoryCompanyDatabase {
+    type Store = CatalogStore;
+
+    async fn get_company_id(id: i64, session: &'static CatalogStore) -> QueryResult<ProductionCompany> {
+        session.companies.get(&id).ok_or(ServiceError::NotFound)
+    }
+    async fn get_all_companies(session: &'static CatalogStore) -> QueryResult<Vec<ProductionCompany>> {
+        Ok(session.companies.rows())
+    }
+    async fn get_company_details(name: String, session: &'static CatalogStore) -> QueryResult<ProductionCompany> {
+        session.companies.find(|company| company.name == name).ok_or(ServiceError::NotFound)
+    }
+    async fn get_companies_movie(session: &'static CatalogStore, movie_id: i64) -> QueryResult<Vec<ProductionCompany>> {
+        Ok(session.companies.filter(|company| company.movie_id == movie_id))
+    }
+    async fn create_movie_company(new_company: NewProductionComp, session: &'static CatalogStore) -> QueryResult<ProductionCompany> {
+        let company = company_row(new_company);
+        session.companies.insert(company.company_id, company.clone());
+        Ok(company)
+    }
+    async fn update_movie_company(new_company: NewProductionComp, id: i64, session: &'static CatalogStore) -> QueryResult<ProductionCompany> {
+        session.companies.get(&id).ok_or(ServiceError::NotFound)?;
+        let company = ProductionCompany { company_id: id, ..company_row(new_company) };
+        session.companies.insert(id, company.clone());
+        Ok(company)
+    }
+    async fn delete_movie_company(id: i64, company_name: String, session: &'static CatalogStore) -> QueryResult<bool> {
+        match session.companies.get(&id) {
+            Some(company) if company.name == company_name => Ok(session.companies.remove(&id).is_some()),
+            _ => Ok(false),
+        }
+    }
+    async fn get_external_ids(session: &'static CatalogStore) -> QueryResult<Vec<CompanyExternalId>> {
+        Ok(session.company_external_ids.rows())
+    }
+    async fn find_by_external_ids(external_ids: Vec<String>, session: &'static CatalogStore) -> QueryResult<Vec<CompanyExternalId>> {
+        Ok(session.company_external_ids.filter(|row| external_ids.contains(&row.external_id)))
+    }
+    async fn add_external_ids(company_id: i64, external_ids: Vec<String>, session: &'static CatalogStore) -> QueryResult<()> {
+        for external_id in external_ids {
+            session.company_external_ids.insert((external_id.clone(), company_id), CompanyExternalId { external_id, company_id });
+        }
+        Ok(())
+    }
+    async fn merge_companies(source_id: i64, target: NewProductionComp, session: &'static CatalogStore) -> QueryResult<Vec<ProductionCompany>> {
+        let removed = session.companies.remove(&source_id).into_iter().collect();
+        let company = company_row(target);
+        session.companies.insert(company.company_id, company.clone());
+        for row in session.company_external_ids.filter(|row| row.company_id == source_id) {
+            session.company_external_ids.remove(&(row.external_id.clone(), source_id));
+            Self::add_external_ids(company.company_id, vec![row.external_id], session).await?;
+        }
+        Ok(removed)
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryPersonDatabase;
+
+#[async_trait]
+impl PersonResolver for InMemoryPersonDatabase {
+    type Store = CatalogStore;
+
+    async fn get_person_by_id(session: &'static CatalogStore, person_id: i32) -> QueryResult<Person> {
+        session.people.get(&person_id).ok_or(ServiceError::NotFound)
+    }
+    async fn get_all_person(session: &'static CatalogStore) -> QueryResult<Vec<Person>> {
+        Ok(session.people.rows())
+    }
+    async fn get_person_by_name(session: &'static CatalogStore, name: String) -> QueryResult<Person> {
+        session.people.find(|person| person.name == name).ok_or(ServiceError::NotFound)
+    }
+    async fn next_person_id(session: &'static CatalogStore) -> QueryResult<i32> {
+        Ok(session.person_ids.fetch_add(1, Ordering::SeqCst) + 1)
+    }
+    async fn create_movie_person(session: &'static CatalogStore, new_person: NewPerson) -> QueryResult<Person> {
+        let person = person_row(new_person);
+        session.people.insert(person.person_id, person.clone());
+        Ok(person)
+    }
+    async fn update_movie_person(session: &'static CatalogStore, person_id: i32, new_person: NewPerson) -> QueryResult<Person> {
+        session.people.get(&person_id).ok_or(ServiceError::NotFound)?;
+        let person = Person { person_id, ..person_row(new_person) };
+        session.people.insert(person_id, person.clone());
+        Ok(person)
+    }
+    async fn delete_movie_person(session: &'static CatalogStore, person_id: i32, person_name: String) -> QueryResult<bool> {
+        match session.people.get(&person_id) {
+            Some(person) if person.name == person_name => Ok(session.people.remove(&person_id).is_some()),
+            _ => Ok(false),
+        }
+    }
+    async fn get_external_ids(session: &'static CatalogStore) -> QueryResult<Vec<PersonExternalId>> {
+        Ok(session.person_external_ids.rows())
+    }
+    async fn find_by_external_ids(session: &'static CatalogStore, external_ids: Vec<String>) -> QueryResult<Vec<PersonExternalId>> {
+        Ok(session.person_external_ids.filter(|row| external_ids.contains(&row.external_id)))
+    }
+    async fn add_external_ids(session: &'static CatalogStore, person_id: i32, external_ids: Vec<String>) -> QueryResult<()> {
+        for external_id in external_ids {
+            session.person_external_ids.insert((external_id.clone(), person_id), PersonExternalId { external_id, person_id });
+        }
+        Ok(())
+    }
+    async fn merge_people(session: &'static CatalogStore, source: Person, target: NewPerson) -> QueryResult<Person> {
+        session.people.remove(&source.person_id);
+        let person = person_row(target);
+        session.people.insert(person.person_id, person.clone());
+        for row in session.person_external_ids.filter(|row| row.person_id == source.person_id) {
+            session.person_external_ids.remove(&(row.external_id.clone(), source.person_id));
+            Self::add_external_ids(session, person.person_id, vec![row.external_id]).await?;
+        }
+        Ok(person)
+    }
+}
+
+#[derive(Default)]
+pub struct InMemorySeriesDatabase;
+
+#[async_trait]
+impl SeriesResolver for InMemorySeriesDatabase {
+    type Store = CatalogStore;
+
+    async fn get_series(series_id: i64, session: &'static CatalogStore) -> QueryResult<Series> {
+        session.series.get(&series_id).ok_or(ServiceError::NotFound)
+    }
+    async fn get_all_series(session: &'static CatalogStore) -> QueryResult<Vec<Series>> {
+        Ok(session.series.rows())
+    }
+    async fn create_series(series: Series, session: &'static CatalogStore) -> QueryResult<Series> {
+        session.series.insert(series.series_id, series.clone());
+        session.enqueue(CatalogSeriesEvent::SeriesUpserted(series.clone()))?;
+        Ok(series)
+    }
+    async fn update_series(series: Series, session: &'static CatalogStore) -> QueryResult<Series> {
+        session.series.get(&series.series_id).ok_or(ServiceError::NotFound)?;
+        Self::create_series(series, session).await
+    }
+    async fn delete_series(series_id: i64, session: &'static CatalogStore) -> QueryResult<bool> {
+        session.series.remove(&series_id).ok_or(ServiceError::NotFound)?;
+        for season in Self::get_seasons(series_id, session).await? {
+            session.seasons.remove(&(series_id, season.season_number));
+            for episode in Self::get_episodes(series_id, season.season_number, session).await? {
+                session.episodes.remove(&(series_id, season.season_number, episode.episode_number));
+            }
+        }
+        session.enqueue(CatalogSeriesEvent::SeriesDeleted { series_id })?;
+        Ok(true)
+    }
+    async fn get_seasons(series_id: i64, session: &'static CatalogStore) -> QueryResult<Vec<Season>> {
+        Ok(session.seasons.filter(|season| season.series_id == series_id))
+    }
+    async fn upsert_season(season: Season, session: &'static CatalogStore) -> QueryResult<Season> {
+        session.series.get(&season.series_id).ok_or(ServiceError::NotFound)?;
+        session.seasons.insert((season.series_id, season.season_number), season.clone());
+        session.enqueue(CatalogSeriesEvent::SeasonUpserted(season.clone()))?;
+        Ok(season)
+    }
+    async fn delete_season(series_id: i64, season_number: i32, session: &'static CatalogStore) -> QueryResult<bool> {
+        session.seasons.remove(&(series_id, season_number));
+        for episode in Self::get_episodes(series_id, season_number, session).await? {
+            session.episodes.remove(&(series_id, season_number, episode.episode_number));
+        }
+        session.enqueue(CatalogSeriesEvent::SeasonDeleted { series_id, season_number })?;
+        Ok(true)
+    }
+    async fn get_episodes(series_id: i64, season_number: i32, session: &'static CatalogStore) -> QueryResult<Vec<Episode>> {
+        Ok(session.episodes.filter(|episode| episode.series_id == series_id && episode.season_number == season_number))
+    }
+    async fn upsert_episode(episode: Episode, session: &'static CatalogStore) -> QueryResult<Episode> {
+        session.seasons.get(&(episode.series_id, episode.season_number)).ok_or(ServiceError::NotFound)?;
+        session.episodes.insert((episode.series_id, episode.season_number, episode.episode_number), episode.clone());
+        session.enqueue(CatalogSeriesEvent::EpisodeUpserted(episode.clone()))?;
+        Ok(episode)
+    }
+    async fn delete_episode(series_id: i64, season_number: i32, episode_number: i32, session: &'static CatalogStore) -> QueryResult<bool> {
+        session.episodes.remove(&(series_id, season_number, episode_number));
+        session.enqueue(CatalogSeriesEvent::EpisodeDeleted { series_id, season_number, episode_number })?;
+        Ok(true)
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryJobDatabase;
+
+#[async_trait]
+impl JobResolver for InMemoryJobDatabase {
+    type Store = CatalogStore;
+
+    async fn get_job(job_id: i64, session: &'static CatalogStore) -> QueryResult<IngestionJob> {
+        session.jobs.get(&job_id).ok_or(ServiceError::NotFound)
+    }
+    async fn get_jobs(session: &'static CatalogStore) -> QueryResult<Vec<IngestionJob>> {
+        Ok(session.jobs.rows())
+    }
+    async fn create_job(job: IngestionJob, session: &'static CatalogStore) -> QueryResult<IngestionJob> {
+        session.jobs.insert(job.job_id, job.clone());
+        Ok(job)
+    }
+    async fn claim_job(job: IngestionJob, expected: JobStatus, expected_owner: Option<String>, session: &'static CatalogStore) -> QueryResult<bool> {
+        let mut applied = false;
+        session.jobs.update(&job.job_id, |row| {
+            if row.status() == expected && row.lease_owner == expected_owner {
+                row.lease_expires_at = job.lease_expires_at;
+                row.lease_owner = job.lease_owner;
+                row.status = job.status;
+                row.updated_at = job.updated_at;
+                applied = true;
+            }
+        });
+        Ok(applied)
+    }
+    async fn save_job(job: IngestionJob, expected: JobStatus, session: &'static CatalogStore) -> QueryResult<bool> {
+        let mut applied = false;
+        session.jobs.update(&job.job_id, |row| {
+            if row.status() == expected && row.lease_owner == job.lease_owner {
+                *row = job;
+                applied = true;
+            }
+        });
+        Ok(applied)
+    }
+    async fn cancel_job(job_id: i64, session: &'static CatalogStore) -> QueryResult<bool> {
+        let mut applied = false;
+        session.jobs.update(&job_id, |row| {
+            if !row.status().is_finished() {
+                row.set_status(JobStatus::Cancelled);
+                applied = true;
+            }
+        });
+        Ok(applied)
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryCreditDatabase;
+
+#[async_trait]
+impl CreditResolver for InMemoryCreditDatabase {
+    type Store = CatalogStore;
+
+    async fn get_movie_credits(movie_id: i64, session: &'static CatalogStore) -> QueryResult<Vec<Credit>> {
+        Ok(session.credits.filter(|credit| credit.movie_id == movie_id))
+    }
+    async fn get_person_credits(person_id: i32, session: &'static CatalogStore) -> QueryResult<Vec<Credit>> {
+        Ok(session.credits.filter(|credit| credit.person_id == person_id))
+    }
+    async fn set_movie_credits(movie_id: i64, credits: Vec<Credit>, session: &'static CatalogStore) -> QueryResult<Vec<Credit>> {
+        for credit in Self::get_movie_credits(movie_id, session).await? {
+            session.credits.remove(&(movie_id, credit.credit_id));
+        }
+        let credits = credits.into_iter().map(|credit| Credit { movie_id, ..credit }).collect::<Vec<_>>();
+        credits.iter().for_each(|credit| { session.credits.insert((movie_id, credit.credit_id.clone()), credit.clone()); });
+        Ok(credits)
+    }
+    async fn delete_credit(movie_id: i64, credit_id: String, session: &'static CatalogStore) -> QueryResult<bool> {
+        session.credits.remove(&(movie_id, credit_id)).map(|_| true).ok_or(ServiceError::NotFound)
+    }
+    async fn reassign_person(source_id: i32, target_id: i32, session: &'static CatalogStore) -> QueryResult<Vec<Credit>> {
+        let credits = Self::get_person_credits(source_id, session)
+            .await?
+            .into_iter()
+            .map(|credit| Credit { person_id: target_id, ..credit })
+            .collect::<Vec<_>>();
+        credits.iter().for_each(|credit| { session.credits.insert((credit.movie_id, credit.credit_id.clone()), credit.clone()); });
+        Ok(credits)
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryGenreDatabase;
+
+#[async_trait]
+impl GenreResolver for InMemoryGenreDatabase {
+    type Store = CatalogStore;
+
+    async fn get_genres(session: &'static CatalogStore) -> QueryResult<Vec<Genre>> {
+        Ok(session.genres.rows())
+    }
+    async fn create_genre(genre: Genre, session: &'static CatalogStore) -> QueryResult<bool> {
+        if session.genres.get(&genre.genre_id).is_some() {
+            return Ok(false)
+        }
+        session.genres.insert(genre.genre_id, genre);
+        Ok(true)
+    }
+    async fn update_genre(genre: Genre, session: &'static CatalogStore) -> QueryResult<Genre> {
+        session.genres.get(&genre.genre_id).ok_or(ServiceError::NotFound)?;
+        session.genres.insert(genre.genre_id, genre.clone());
+        Ok(genre)
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryTranslationDatabase;
+
+#[async_trait]
+impl TranslationResolver for InMemoryTranslationDatabase {
+    type Store = CatalogStore;
+
+    async fn get_translations(movie_id: i64, session: &'static CatalogStore) -> QueryResult<Vec<MovieTranslation>> {
+        Ok(session.translations.filter(|translation| translation.movie_id == movie_id))
+    }
+    async fn upsert_translation(translation: MovieTranslation, session: &'static CatalogStore) -> QueryResult<MovieTranslation> {
+        session.translations.insert((translation.movie_id, translation.locale.clone()), translation.clone());
+        Ok(translation)
+    }
+    async fn delete_translation(movie_id: i64, locale: String, session: &'static CatalogStore) -> QueryResult<bool> {
+        session.translations.remove(&(movie_id, locale)).map(|_| true).ok_or(ServiceError::NotFound)
+    }
+    async fn publish_translations(movie_id: i64, translations: Vec<MovieTranslation>, session: &'static CatalogStore) -> QueryResult<()> {
+        session.enqueue(TranslationEvent::MovieTranslated { movie_id, translations })
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryAvailabilityDatabase;
+
+#[async_trait]
+impl AvailabilityResolver for InMemoryAvailabilityDatabase {
+    type Store = CatalogStore;
+
+    async fn get_windows(movie_id: i64, session: &'static CatalogStore) -> QueryResult<Vec<AvailabilityWindow>> {
+        Ok(session.availability.filter(|window| window.movie_id == movie_id))
+    }
+    async fn add_window(window: AvailabilityWindow, session: &'static CatalogStore) -> QueryResult<AvailabilityWindow> {
+        for at in window.boundaries() {
+            session.boundaries.insert((boundary_day(at), at, window.movie_id), window.movie_id);
+        }
+        session.availability.insert((window.movie_id, window.window_id), window.clone());
+        Ok(window)
+    }
+    async fn delete_window(movie_id: i64, window_id: i64, session: &'static CatalogStore) -> QueryResult<bool> {
+        session.availability.remove(&(movie_id, window_id)).map(|_| true).ok_or(ServiceError::NotFound)
+    }
+    async fn get_boundaries(day: String, after: i64, until: i64, session: &'static CatalogStore) -> QueryResult<Vec<i64>> {
+        Ok(session.boundaries
+            .entries()
+            .into_iter()
+            .filter(|((boundary_day, at, _), _)| *boundary_day == day && after < *at && *at <= until)
+            .map(|(_, movie_id)| movie_id)
+            .collect())
+    }
+    async fn publish_windows(movie_id: i64, windows: Vec<AvailabilityWindow>, session: &'static CatalogStore) -> QueryResult<()> {
+        session.enqueue(AvailabilityEvent::AvailabilityChanged { movie_id, windows })
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryReleaseDatabase;
+
+#[async_trait]
+impl ReleaseResolver for InMemoryReleaseDatabase {
+    type Store = CatalogStore;
+
+    async fn get_schedule(movie_id: i64, session: &'static CatalogStore) -> QueryResult<Option<ReleaseSchedule>> {
+        Ok(session.release_schedules.get(&movie_id))
+    }
+    async fn set_schedule(schedule: ReleaseSchedule, session: &'static CatalogStore) -> QueryResult<ReleaseSchedule> {
+        for at in schedule.boundaries() {
+            session.release_boundaries.insert((boundary_day(at), at, schedule.movie_id), schedule.movie_id);
+        }
+        session.release_schedules.insert(schedule.movie_id, schedule.clone());
+        Ok(schedule)
+    }
+    async fn get_boundaries(day: String, after: i64, until: i64, session: &'static CatalogStore) -> QueryResult<Vec<i64>> {
+        Ok(session.release_boundaries
+            .entries()
+            .into_iter()
+            .filter(|((boundary_day, at, _), _)| *boundary_day == day && after < *at && *at <= until)
+            .map(|(_, movie_id)| movie_id)
+            .collect())
+    }
+    async fn record_change(change: StatusChange, session: &'static CatalogStore) -> QueryResult<StatusChange> {
+        session.status_history.insert((change.movie_id, change.change_id), change.clone());
+        Ok(change)
+    }
+    async fn get_history(movie_id: i64, session: &'static CatalogStore) -> QueryResult<Vec<StatusChange>> {
+        let mut history = session.status_history.filter(|change| change.movie_id == movie_id);
+        history.reverse();
+        Ok(history)
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryArtworkDatabase;
+
+#[async_trait]
+impl ArtworkResolver for InMemoryArtworkDatabase {
+    type Store = CatalogStore;
+
+    async fn get_images(owner: ImageOwner, owner_id: i64, session: &'static CatalogStore) -> QueryResult<Vec<Artwork>> {
+        let owner_type = owner.to_string();
+        Ok(session.artwork.filter(|image| image.owner_type == owner_type && image.owner_id == owner_id))
+    }
+    async fn save_images(images: Vec<Artwork>, session: &'static CatalogStore) -> QueryResult<Vec<Artwork>> {
+        for image in &images {
+            session.artwork.insert((image.owner_type.clone(), image.owner_id, image.image_id), image.clone());
+        }
+        Ok(images)
+    }
+    async fn delete_image(owner: ImageOwner, owner_id: i64, image_id: i64, session: &'static CatalogStore) -> QueryResult<bool> {
+        session.artwork.remove(&(owner.to_string(), owner_id, image_id))
+            .map(|_| true)
+            .ok_or(ServiceError::NotFound)
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryMediaDatabase;
+
+#[async_trait]
+impl MediaResolver for InMemoryMediaDatabase {
+    type Store = CatalogStore;
+
+    async fn get_media(owner: MediaOwner, owner_id: i64, session: &'static CatalogStore) -> QueryResult<MediaAsset> {
+        let owner_type = owner.to_string();
+        Ok(MediaAsset {
+            renditions: session.renditions.filter(|f| f.owner_type == owner_type && f.owner_id == owner_id),
+            audio_tracks: session.audio_tracks.filter(|f| f.owner_type == owner_type && f.owner_id == owner_id),
+            text_tracks: session.text_tracks.filter(|f| f.owner_type == owner_type && f.owner_id == owner_id),
+        })
+    }
+    async fn save_rendition(rendition: Rendition, session: &'static CatalogStore) -> QueryResult<Rendition> {
+        session.renditions.insert((rendition.owner_type.clone(), rendition.owner_id, rendition.rendition_id), rendition.clone());
+        Ok(rendition)
+    }
+    async fn save_audio_track(track: AudioTrack, session: &'static CatalogStore) -> QueryResult<AudioTrack> {
+        session.audio_tracks.insert((track.owner_type.clone(), track.owner_id, track.track_id), track.clone());
+        Ok(track)
+    }
+    async fn save_text_track(track: TextTrack, session: &'static CatalogStore) -> QueryResult<TextTrack> {
+        session.text_tracks.insert((track.owner_type.clone(), track.owner_id, track.track_id), track.clone());
+        Ok(track)
+    }
+    async fn delete_track(track_type: TrackType, owner: MediaOwner, owner_id: i64, track_id: i64, session: &'static CatalogStore) -> QueryResult<bool> {
+        let key = (owner.to_string(), owner_id, track_id);
+        let removed = match track_type {
+            TrackType::Video => session.renditions.remove(&key).is_some(),
+            TrackType::Audio => session.audio_tracks.remove(&key).is_some(),
+            TrackType::Text => session.text_tracks.remove(&key).is_some(),
+        };
+        match removed {
+            true => Ok(true),
+            false => Err(ServiceError::NotFound),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryOutboxDatabase;
+
+#[async_trait]
+impl OutboxResolver for InMemoryOutboxDatabase {
+    type Store = CatalogStore;
+
+    async fn get_pending(bucket: i32, limit: i32, session: &'static CatalogStore) -> QueryResult<Vec<OutboxEntry>> {
+        Ok(session.outbox
+            .filter(|entry| entry.bucket == bucket)
+            .into_iter()
+            .take(limit.max(0) as usize)
+            .collect())
+    }
+    async fn mark_sent(entry: &OutboxEntry, delivery: Delivery, session: &'static CatalogStore) -> QueryResult<SentEntry> {
+        let sent = SentEntry::new(entry, delivery);
+        session.outbox.remove(&(entry.bucket, entry.entry_id));
+        session.outbox_sent.insert(sent.event_id.clone(), sent.clone());
+        Ok(sent)
+    }
+    async fn record_failure(entry: &OutboxEntry, session: &'static CatalogStore) -> QueryResult<()> {
+        session.outbox.update(&(entry.bucket, entry.entry_id), |stored| {
+            stored.attempts = entry.attempts;
+            stored.last_error = entry.last_error.clone();
+            stored.retry_at = entry.retry_at;
+        });
+        Ok(())
+    }
+    async fn dead_letter(entry: &OutboxEntry, session: &'static CatalogStore) -> QueryResult<()> {
+        session.outbox.remove(&(entry.bucket, entry.entry_id));
+        session.outbox_dead.insert((entry.bucket, entry.entry_id), entry.clone());
+        Ok(())
+    }
+    async fn claim_bucket(bucket: i32, owner: &str, lease: Duration, session: &'static CatalogStore) -> QueryResult<bool> {
+        let now = now_millis();
+        let held_elsewhere = session.outbox_leases
+            .get(&bucket)
+            .map_or(false, |(holder, expires_at)| holder != owner && expires_at > now);
+        if !held_elsewhere {
+            session.outbox_leases.insert(bucket, (owner.to_string(), now + lease.as_millis() as i64));
+        }
+        Ok(!held_elsewhere)
+    }
+}
+
+/// Stands in for TMDB, every movie in the table is listed in key order. Their genres are TMDB
+/// genre ids, as `fetch_movie` returns them
+#[derive(Default)]
+pub struct FixtureSource {
+    pub movies: MemoryTable<i64, Movie>,
+    pub credits: MemoryTable<(i64, String), Credit>,
+}
+
+#[async_trait]
+impl CatalogSource for FixtureSource {
+    async fn list_movie_ids(&self, request: &BulkStreamInsertData) -> QueryResult<Vec<i64>> {
+        let limit = request.number_of_batch.unwrap_or(2).max(0) as usize;
+        Ok(self.movies.entries().into_iter().map(|(movie_id, _)| movie_id).take(limit).collect())
+    }
+    async fn fetch_movie(&self, movie_id: i64, _language: Option<String>) -> QueryResult<(Movie, Vec<Credit>)> {
+        let movie = self.movies.get(&movie_id).ok_or(ServiceError::NotFound)?;
+        Ok((movie, self.credits.filter(|credit| credit.movie_id == movie_id)))
+    }
+}
+
+#[derive(MergedObject, Default)]
+pub struct Query(
+    ProductionCompanyQuery<InMemoryCompanyDatabase>,
+    PersonQuery<InMemoryPersonDatabase>,
+    IngestionJobQuery<InMemoryJobDatabase>,
+    EntityResolutionQuery<InMemoryPersonDatabase, InMemoryCompanyDatabase>,
+    GenreQuery<InMemoryGenreDatabase>,
+    ReleaseQuery<InMemoryReleaseDatabase, InMemoryMovieDatabase>,
+    ArtworkQuery<InMemoryArtworkDatabase, InMemoryMovieDatabase, InMemoryPersonDatabase>,
+    MediaQuery<InMemoryMediaDatabase, InMemoryMovieDatabase, InMemorySeriesDatabase>,
+);
+
+#[derive(MergedObject, Default)]
+pub struct Mutation(
+    ProductionCompanyMutation<InMemoryCompanyDatabase>,
+    MovieMutation<InMemoryMovieDatabase, InMemoryCreditDatabase, InMemoryGenreDatabase, InMemoryReleaseDatabase, InMemoryTranslationDatabase, InMemoryAvailabilityDatabase, InMemoryPersonDatabase>,
+    PersonMutation<InMemoryPersonDatabase>,
+    SeriesMutation<InMemorySeriesDatabase, InMemoryGenreDatabase>,
+    IngestionJobMutation<InMemoryJobDatabase, InMemoryMovieDatabase, InMemoryCreditDatabase, InMemoryPersonDatabase, InMemoryGenreDatabase>,
+    CreditMutation<InMemoryCreditDatabase>,
+    EntityResolutionMutation<InMemoryPersonDatabase, InMemoryCompanyDatabase, InMemoryCreditDatabase, InMemoryMovieDatabase>,
+    GenreMutation<InMemoryGenreDatabase, InMemoryMovieDatabase, InMemorySeriesDatabase>,
+    TranslationMutation<InMemoryTranslationDatabase, InMemoryMovieDatabase>,
+    AvailabilityMutation<InMemoryAvailabilityDatabase, InMemoryMovieDatabase>,
+    ReleaseMutation<InMemoryReleaseDatabase, InMemoryMovieDatabase>,
+    ArtworkMutation<InMemoryArtworkDatabase, InMemoryMovieDatabase, InMemoryPersonDatabase>,
+    MediaMutation<InMemoryMediaDatabase, InMemoryMovieDatabase, InMemorySeriesDatabase>,
+);
+
+#[derive(MergedSubscription, Default)]
+pub struct Subscription(IngestionJobSubscription<InMemoryJobDatabase>);
+
+pub type IngestionSchema = Schema<Query, Mutation, Subscription>;
+
+/// Ingestion schema over `store`, `leak` it the same way the session is in production and keep
+/// the reference to look at the rows a request wrote. Import jobs read their movies from `source`. Movie and series mutations, import jobs,
+/// merges, the genre migration, translation, availability, status and poster changes still publish through the global Kafka producer.
+/// Requests carry no `Editor`, so status changes are recorded as anonymous.
+/// Default genres missing from `store` are added, as the server seeds them on start
+pub fn schema(store: &'static CatalogStore, source: FixtureSource) -> IngestionSchema {
+    for genre in default_genres() {
+        if store.genres.get(&genre.genre_id).is_none() {
+            store.genres.insert(genre.genre_id, genre);
+        }
+    }
+    let source: SharedSource = Arc::new(source);
+    let images: SharedImageStore = store.image_objects.clone();
+    Schema::build(Query::default(), Mutation::default(), Subscription::default())
+        .data(store)
+        .data(source)
+        .data(images)
+        .finish()
+}
@@ -0,0 +1,195 @@
+use async_graphql::{EmptyMutation, EmptySubscription, MergedObject, Schema, dataloader::DataLoader};
+use async_trait::async_trait;
+use asset_service::graphql::modules::{
+    model::Movie, resolver::MovieResolver, schema::MovieQuery,
+    credits::{model::{Credit, Person}, resolver::{CreditLoader, CreditResolver, PersonLoader}, schema::CreditQuery},
+    series::{model::{Episode, Season, Series}, resolver::SeriesResolver, schema::SeriesQuery},
+    translations::{model::MovieTranslation, resolver::{TranslationLoader, TranslationResolver}},
+    availability::{model::AvailabilityWindow, resolver::{AvailabilityLoader, AvailabilityResolver}},
+    artwork::{model::Artwork, resolver::{ArtworkLoader, ArtworkResolver}},
+    playback::{model::{AudioTrack, MediaAsset, Rendition, TextTrack}, resolver::PlaybackResolver, schema::PlaybackQuery},
+};
+use common_utils::{QueryResult, artwork::ImageOwner, error::ServiceError, playback::MediaOwner, signing::{Binding, UrlSigner}};
+use crate::{leak, MemoryTable};
+
+/// `movie_keyspace.movies_object` keyed by movie id
+pub type MovieStore = MemoryTable<i64, Movie>;
+
+/// The `series`, `seasons` and `episodes` tables, keyed like their primary keys
+#[derive(Default)]
+pub struct SeriesStore {
+    pub series: MemoryTable<i64, Series>,
+    pub seasons: MemoryTable<(i64, i32), Season>,
+    pub episodes: MemoryTable<(i64, i32, i32), Episode>,
+}
+
+/// `credits_by_movie` keyed by (movie id, credit id) and `person_object` keyed by person id
+#[derive(Default)]
+pub struct CreditStore {
+    pub credits: MemoryTable<(i64, String), Credit>,
+    pub people: MemoryTable<i32, Person>,
+}
+
+/// `movie_translations` keyed by (movie id, locale)
+pub type TranslationStore = MemoryTable<(i64, String), MovieTranslation>;
+
+/// `movie_availability` keyed by (movie id, window id)
+pub type AvailabilityStore = MemoryTable<(i64, i64), AvailabilityWindow>;
+
+/// `artwork` keyed by (owner type, owner id, image id)
+pub type ArtworkStore = MemoryTable<(String, i64, i64), Artwork>;
+
+/// The `media_renditions`, `media_audio_tracks` and `media_text_tracks` tables, keyed by
+/// (owner type, owner id, rendition or track id)
+#[derive(Default)]
+pub struct MediaStore {
+    pub renditions: MemoryTable<(String, i64, i64), Rendition>,
+    pub audio_tracks: MemoryTable<(String, i64, i64), AudioTrack>,
+    pub text_tracks: MemoryTable<(String, i64, i64), TextTrack>,
+}
+
+#[derive(Default)]
+pub struct InMemoryMovieDatabase;
+
+#[async_trait]
+impl MovieResolver for InMemoryMovieDatabase {
+    type Store = MovieStore;
+
+    async fn get_all_movie(session: &'static MovieStore, page_size: Option<i32>) -> QueryResult<Vec<Movie>> {
+        let movies = session.filter(|movie| !movie.is_deleted());
+        match page_size {
+            Some(size) => Ok(movies.into_iter().take(size.max(0) as usize).collect()),
+            None => Ok(movies),
+        }
+    }
+    async fn get_movie_by_id_title(title: String, movie_id: i64, session: &'static MovieStore) -> QueryResult<Movie> {
+        session.get(&movie_id)
+            .filter(|movie| movie.title == title && !movie.is_deleted())
+            .ok_or(ServiceError::NotFound)
+    }
+    async fn get_movie_by_id(movie_id: i64, session: &'static MovieStore) -> QueryResult<Movie> {
+        session.get(&movie_id)
+            .filter(|movie| !movie.is_deleted())
+            .ok_or(ServiceError::NotFound)
+    }
+}
+
+#[derive(Default)]
+pub struct InMemorySeriesDatabase;
+
+#[async_trait]
+impl SeriesResolver for InMemorySeriesDatabase {
+    type Store = SeriesStore;
+
+    async fn get_series(series_id: i64, session: &'static SeriesStore) -> QueryResult<Series> {
+        session.series.get(&series_id).ok_or(ServiceError::NotFound)
+    }
+    async fn get_seasons(series_id: i64, session: &'static SeriesStore) -> QueryResult<Vec<Season>> {
+        Ok(session.seasons.filter(|season| season.series_id == series_id))
+    }
+    async fn get_episodes(series_id: i64, season_number: i32, session: &'static SeriesStore) -> QueryResult<Vec<Episode>> {
+        Ok(session.episodes.filter(|episode| episode.series_id == series_id && episode.season_number == season_number))
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryCreditDatabase;
+
+#[async_trait]
+impl CreditResolver for InMemoryCreditDatabase {
+    type Store = CreditStore;
+
+    async fn get_credits_by_movies(movie_ids: Vec<i64>, session: &'static CreditStore) -> QueryResult<Vec<Credit>> {
+        Ok(session.credits.filter(|credit| movie_ids.contains(&credit.movie_id)))
+    }
+    async fn get_person_credits(person_id: i32, session: &'static CreditStore) -> QueryResult<Vec<Credit>> {
+        Ok(session.credits.filter(|credit| credit.person_id == person_id))
+    }
+    async fn get_people(person_ids: Vec<i32>, session: &'static CreditStore) -> QueryResult<Vec<Person>> {
+        Ok(session.people.filter(|person| person_ids.contains(&person.person_id)))
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryTranslationDatabase;
+
+#[async_trait]
+impl TranslationResolver for InMemoryTranslationDatabase {
+    type Store = TranslationStore;
+
+    async fn get_translations_by_movies(movie_ids: Vec<i64>, session: &'static TranslationStore) -> QueryResult<Vec<MovieTranslation>> {
+        Ok(session.filter(|translation| movie_ids.contains(&translation.movie_id)))
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryAvailabilityDatabase;
+
+#[async_trait]
+impl AvailabilityResolver for InMemoryAvailabilityDatabase {
+    type Store = AvailabilityStore;
+
+    async fn get_windows_by_movies(movie_ids: Vec<i64>, session: &'static AvailabilityStore) -> QueryResult<Vec<AvailabilityWindow>> {
+        Ok(session.filter(|window| movie_ids.contains(&window.movie_id)))
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryArtworkDatabase;
+
+#[async_trait]
+impl ArtworkResolver for InMemoryArtworkDatabase {
+    type Store = ArtworkStore;
+
+    async fn get_images_by_owners(owner: ImageOwner, owner_ids: Vec<i64>, session: &'static ArtworkStore) -> QueryResult<Vec<Artwork>> {
+        let owner_type = owner.to_string();
+        Ok(session.filter(|image| image.owner_type == owner_type && owner_ids.contains(&image.owner_id)))
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryPlaybackDatabase;
+
+#[async_trait]
+impl PlaybackResolver for InMemoryPlaybackDatabase {
+    type Store = MediaStore;
+
+    async fn get_media(owner: MediaOwner, owner_id: i64, session: &'static MediaStore) -> QueryResult<MediaAsset> {
+        let owner_type = owner.to_string();
+        Ok(MediaAsset {
+            renditions: session.renditions.filter(|f| f.owner_type == owner_type && f.owner_id == owner_id),
+            audio_tracks: session.audio_tracks.filter(|f| f.owner_type == owner_type && f.owner_id == owner_id),
+            text_tracks: session.text_tracks.filter(|f| f.owner_type == owner_type && f.owner_id == owner_id),
+        })
+    }
+}
+
+#[derive(MergedObject, Default)]
+pub struct Query(
+    MovieQuery<InMemoryMovieDatabase>,
+    SeriesQuery<InMemorySeriesDatabase>,
+    CreditQuery<InMemoryCreditDatabase, InMemoryMovieDatabase>,
+    PlaybackQuery<InMemoryPlaybackDatabase, InMemoryMovieDatabase, InMemorySeriesDatabase>,
+);
+
+pub type CatalogSchema = Schema<Query, EmptyMutation, EmptySubscription>;
+
+/// Requests are served in the default locale unless a query passes `locale`, for a caller whose
+/// region is unknown and on the basic plan. A test can add a `RequestLocale`, `RequestRegion` or
+/// `RequestPlan` to its request to stand in for the headers. Playback needs a `PlaybackClient`
+/// with a subject, its URLs are signed with an unbound `test_support` key
+pub fn schema(movies: MovieStore, series: SeriesStore, credits: CreditStore, translations: TranslationStore, availability: AvailabilityStore, artwork: ArtworkStore, media: MediaStore) -> CatalogSchema {
+    let credits = leak(credits);
+    Schema::build(Query::default(), EmptyMutation, EmptySubscription)
+        .data(DataLoader::new(CreditLoader::new::<InMemoryCreditDatabase>(credits), tokio::spawn))
+        .data(DataLoader::new(PersonLoader::new::<InMemoryCreditDatabase>(credits), tokio::spawn))
+        .data(DataLoader::new(TranslationLoader::new::<InMemoryTranslationDatabase>(leak(translations)), tokio::spawn))
+        .data(DataLoader::new(AvailabilityLoader::new::<InMemoryAvailabilityDatabase>(leak(availability)), tokio::spawn))
+        .data(DataLoader::new(ArtworkLoader::new::<InMemoryArtworkDatabase>(leak(artwork)), tokio::spawn))
+        .data(leak(movies))
+        .data(leak(series))
+        .data(leak(media))
+        .data(UrlSigner::new("test_support", 3600, Binding::None))
+        .data(credits)
+        .finish()
+}
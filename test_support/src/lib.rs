@@ -0,0 +1,18 @@
+//! In-memory implementations of every resolver trait, so the GraphQL schemas can be
+//! exercised with `cargo test` without Scylla, Postgres, InfluxDB or Elasticsearch running.
+//! Each module mirrors a service and exposes its fake resolvers, their stores and a
+//! `schema` builder wired to them.
+pub mod table;
+pub mod account;
+pub mod asset_ingestion;
+pub mod asset_service;
+pub mod activity;
+pub mod recommendation;
+pub mod search;
+
+pub use table::MemoryTable;
+
+/// Scylla resolvers take a `&'static` store, tests hand over a leaked one instead
+pub fn leak<T>(store: T) -> &'static T {
+    Box::leak(Box::new(store))
+}
@@ -0,0 +1,74 @@
+use async_graphql::{EmptyMutation, EmptySubscription, MergedObject, Schema};
+use async_trait::async_trait;
+use recommendation_service::graphql::modules::{model::{AvailabilityWindow, CatalogMovie, RecommendedMovies}, resolver::{AvailabilityTrait, CatalogTrait, RecommendedTrait}, schema::RecommendedQuery};
+use common_utils::QueryResult;
+use crate::{leak, MemoryTable};
+
+/// `recommended_movies.user_recommendations`, keyed by user then movie
+pub type RecommendationStore = MemoryTable<(i32, i64), RecommendedMovies>;
+
+/// `movie_keyspace.movie_availability`, keyed by movie then window
+pub type AvailabilityStore = MemoryTable<(i64, i64), AvailabilityWindow>;
+
+/// `movie_keyspace.movies_by_id`, keyed by movie. Recommendations of movies missing from it are left out
+pub type CatalogStore = MemoryTable<i64, CatalogMovie>;
+
+#[derive(Default)]
+pub struct InMemoryRecommendedDatabase;
+
+#[async_trait]
+impl RecommendedTrait for InMemoryRecommendedDatabase {
+    type Store = RecommendationStore;
+
+    async fn get_most_recent(user_id: i32, session: &'static RecommendationStore) -> QueryResult<Vec<RecommendedMovies>> {
+        let mut res = session.filter(|rec| rec.user_id == user_id);
+        res.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        res.truncate(50);
+        Ok(res)
+    }
+    async fn get_all_recommendations(session: &'static RecommendationStore) -> QueryResult<Vec<RecommendedMovies>> {
+        Ok(session.rows().into_iter().take(100).collect())
+    }
+    async fn get_user_recommendations(user_id: i32, session: &'static RecommendationStore) -> QueryResult<Vec<RecommendedMovies>> {
+        Ok(session.filter(|rec| rec.user_id == user_id))
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryAvailabilityDatabase;
+
+#[async_trait]
+impl AvailabilityTrait for InMemoryAvailabilityDatabase {
+    type Store = AvailabilityStore;
+
+    async fn get_windows_by_movies(movie_ids: Vec<i64>, session: &'static AvailabilityStore) -> QueryResult<Vec<AvailabilityWindow>> {
+        Ok(session.filter(|window| movie_ids.contains(&window.movie_id)))
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryCatalogDatabase;
+
+#[async_trait]
+impl CatalogTrait for InMemoryCatalogDatabase {
+    type Store = CatalogStore;
+
+    async fn get_movies_by_ids(movie_ids: Vec<i64>, session: &'static CatalogStore) -> QueryResult<Vec<CatalogMovie>> {
+        Ok(session.filter(|movie| movie_ids.contains(&movie.movie_id)))
+    }
+}
+
+#[derive(MergedObject, Default)]
+pub struct Query(RecommendedQuery<InMemoryRecommendedDatabase, InMemoryAvailabilityDatabase, InMemoryCatalogDatabase>);
+
+pub type RecommendationSchema = Schema<Query, EmptyMutation, EmptySubscription>;
+
+/// Recommendations are served for a caller whose region is unknown, a test can add a
+/// `RequestRegion` to its request to stand in for the headers
+pub fn schema(recommendations: RecommendationStore, availability: AvailabilityStore, catalog: CatalogStore) -> RecommendationSchema {
+    Schema::build(Query::default(), EmptyMutation, EmptySubscription)
+        .data(leak(recommendations))
+        .data(leak(availability))
+        .data(leak(catalog))
+        .finish()
+}
@@ -0,0 +1,165 @@
+use std::{cmp::Ordering, collections::BTreeMap, sync::Arc};
+use async_graphql::{EmptySubscription, MergedObject, Schema};
+use async_trait::async_trait;
+use serde_json::Value;
+use search_service::graphql::modules::{
+    model::{FilterQueryWithMultipleFields, Genre, Movie, SimpleSearchNew},
+    resolver::ElasticResolver,
+    schema::{AggregatedQuery, ElasticMutate, ElasticQuery, MovieType},
+};
+use common_utils::QueryResult;
+use crate::MemoryTable;
+
+/// Indexed documents keyed by index name then movie id. Elasticsearch clients are
+/// cloned per request, so the documents are shared through an `Arc`
+#[derive(Clone, Default)]
+pub struct SearchStore(pub Arc<MemoryTable<(String, i64), Movie>>);
+
+impl SearchStore {
+    pub fn index(&self, index_name: &str, movie: Movie) {
+        self.0.insert((index_name.to_string(), movie.movie_id), movie);
+    }
+    /// Documents of the index that can be watched in `region`, as the `available_in` filter would leave them
+    fn documents(&self, index_name: &str, region: Option<&str>) -> Vec<Movie> {
+        self.0.entries()
+            .into_iter()
+            .filter(|((index, _), movie)| index == index_name && movie.is_available_in(region))
+            .map(|(_, movie)| movie)
+            .collect()
+    }
+}
+
+/// Resolves a dotted field path such as `rating.popularity`, ignoring the `.keyword` suffix
+fn field(movie: &Movie, path: &str) -> Value {
+    let doc = serde_json::to_value(movie).unwrap_or_default();
+    path.trim_end_matches(".keyword")
+        .split('.')
+        .fold(doc, |value, key| value[key].clone())
+}
+
+/// Term match, where an array field matches when any of its elements does
+fn matches(movie: &Movie, term: &str, term_value: &str) -> bool {
+    match field(movie, term) {
+        Value::Array(values) => values.iter().any(|v| v.as_str() == Some(term_value)),
+        Value::String(value) => value == term_value,
+        value => value.to_string() == term_value,
+    }
+}
+
+fn compare(a: &Value, b: &Value) -> Ordering {
+    match (a.as_f64(), b.as_f64()) {
+        (Some(a), Some(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+        _ => a.to_string().cmp(&b.to_string()),
+    }
+}
+
+fn sort(movies: &mut [Movie], sort_by: &str, order: &str) {
+    if sort_by.is_empty() {
+        return;
+    }
+    movies.sort_by(|a, b| {
+        let ordering = compare(&field(a, sort_by), &field(b, sort_by));
+        if order == "desc" { ordering.reverse() } else { ordering }
+    });
+}
+
+fn phrase_prefix(movie: &Movie, query: &str, fields: &[&str]) -> bool {
+    let query = query.to_lowercase();
+    query.is_empty() || fields.iter().any(|path| match field(movie, path) {
+        Value::String(value) => value.to_lowercase().contains(&query),
+        Value::Array(values) => values.iter().any(|v| v.as_str().map_or(false, |v| v.to_lowercase().contains(&query))),
+        _ => false,
+    })
+}
+
+fn limit(movies: Vec<Movie>, total_result: Option<i64>) -> Vec<Movie> {
+    movies.into_iter().take(total_result.unwrap_or(10).max(0) as usize).collect()
+}
+
+#[derive(Default)]
+pub struct InMemoryElasticDatabase;
+
+#[async_trait]
+impl ElasticResolver for InMemoryElasticDatabase {
+    type Store = SearchStore;
+
+    async fn search_indexed(client: SearchStore, total_result: Option<i64>, index_name: String, region: Option<String>) -> QueryResult<Vec<Movie>> {
+        Ok(limit(client.documents(&index_name, region.as_deref()), total_result))
+    }
+    async fn search_phrase_prefix(client: SearchStore, query: SimpleSearchNew) -> Option<AggregatedQuery> {
+        let mut hits: Vec<Movie> = client.documents(&query.index_name, query.region.as_deref())
+            .into_iter()
+            .filter(|movie| phrase_prefix(movie, &query.query, &["title", "overview"]))
+            .filter(|movie| query.filter_value.is_empty() || matches(movie, &query.filter_by, &query.filter_value))
+            .collect();
+        sort(&mut hits, &query.sort_by, &query.order);
+
+        let mut buckets = BTreeMap::<String, i32>::new();
+        if !query.agg_field.is_empty() {
+            for movie in &hits {
+                match field(movie, &query.agg_field) {
+                    Value::Array(values) => values.iter()
+                        .filter_map(|v| v.as_str())
+                        .for_each(|key| *buckets.entry(key.to_string()).or_default() += 1),
+                    Value::String(key) => *buckets.entry(key).or_default() += 1,
+                    _ => {}
+                }
+            }
+        }
+        let mut genres: Vec<Genre> = buckets.into_iter()
+            .map(|(key, doc_count)| Genre { doc_count, key })
+            .collect();
+        genres.sort_by(|a, b| b.doc_count.cmp(&a.doc_count));
+        genres.truncate(query.agg_size.max(0) as usize);
+
+        let movie_list = limit(hits, Some(query.total_result))
+            .iter()
+            .map(MovieType::from)
+            .collect();
+        Some(AggregatedQuery { genres: Some(genres), movie_list: Some(movie_list) })
+    }
+    async fn delete_document(id: &str, client: SearchStore) -> QueryResult<bool> {
+        let deleted = client.0.entries()
+            .into_iter()
+            .filter(|((_, movie_id), _)| movie_id.to_string() == id)
+            .filter_map(|(key, _)| client.0.remove(&key))
+            .count();
+        Ok(deleted > 0)
+    }
+    async fn filter_by(term: String, term_value: String, client: SearchStore, total_result: Option<i64>, index_name: String, region: Option<String>) -> QueryResult<Vec<Movie>> {
+        let hits = client.documents(&index_name, region.as_deref())
+            .into_iter()
+            .filter(|movie| matches(movie, &term, &term_value))
+            .collect();
+        Ok(limit(hits, total_result))
+    }
+    async fn filter_or_aggregate_query(query: FilterQueryWithMultipleFields, client: SearchStore) -> QueryResult<Vec<Movie>> {
+        let fields: Vec<&str> = query.fields.iter().map(String::as_str).collect();
+        let mut hits: Vec<Movie> = client.documents(&query.index_name, query.region.as_deref())
+            .into_iter()
+            .filter(|movie| phrase_prefix(movie, &query.query, &fields))
+            .filter(|movie| query.term_value.is_empty() || matches(movie, &query.term_name, &query.term_value))
+            .collect();
+        sort(&mut hits, &query.sort_by, &query.order);
+        Ok(limit(hits, Some(query.total_result)))
+    }
+    async fn sort_movies_by(term_name: Option<String>, order: Option<String>, client: SearchStore, total_result: Option<i64>, index_name: String, region: Option<String>) -> QueryResult<Vec<Movie>> {
+        let mut hits = client.documents(&index_name, region.as_deref());
+        sort(&mut hits, &term_name.unwrap_or_default(), &order.unwrap_or_else(|| "desc".to_string()));
+        Ok(limit(hits, total_result))
+    }
+}
+
+#[derive(MergedObject, Default)]
+pub struct Query(ElasticQuery<InMemoryElasticDatabase>);
+
+#[derive(MergedObject, Default)]
+pub struct Mutation(ElasticMutate<InMemoryElasticDatabase>);
+
+pub type SearchSchema = Schema<Query, Mutation, EmptySubscription>;
+
+pub fn schema(store: SearchStore) -> SearchSchema {
+    Schema::build(Query::default(), Mutation::default(), EmptySubscription)
+        .data(store)
+        .finish()
+}
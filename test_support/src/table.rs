@@ -0,0 +1,59 @@
+use std::{collections::BTreeMap, sync::Mutex};
+
+/// Rows of a single table, keyed the same way as the primary key of the real one
+pub struct MemoryTable<K, V> {
+    rows: Mutex<BTreeMap<K, V>>,
+}
+
+impl<K: Ord + Clone, V: Clone> MemoryTable<K, V> {
+    pub fn new() -> Self {
+        Self { rows: Mutex::new(BTreeMap::new()) }
+    }
+    /// Seeds the table, the way a fixture would be loaded before a test
+    pub fn with_rows(rows: impl IntoIterator<Item = (K, V)>) -> Self {
+        Self { rows: Mutex::new(rows.into_iter().collect()) }
+    }
+    /// Upserts a row, returning the one it replaced
+    pub fn insert(&self, key: K, row: V) -> Option<V> {
+        self.rows.lock().expect("Poisoned table").insert(key, row)
+    }
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.rows.lock().expect("Poisoned table").get(key).cloned()
+    }
+    pub fn remove(&self, key: &K) -> Option<V> {
+        self.rows.lock().expect("Poisoned table").remove(key)
+    }
+    /// Every row, ordered by key
+    pub fn rows(&self) -> Vec<V> {
+        self.rows.lock().expect("Poisoned table").values().cloned().collect()
+    }
+    /// Every row alongside its key
+    pub fn entries(&self) -> Vec<(K, V)> {
+        self.rows.lock().expect("Poisoned table").iter().map(|(k, v)| (k.clone(), v.clone())).collect()
+    }
+    pub fn find(&self, predicate: impl Fn(&V) -> bool) -> Option<V> {
+        self.rows.lock().expect("Poisoned table").values().find(|row| predicate(row)).cloned()
+    }
+    pub fn filter(&self, predicate: impl Fn(&V) -> bool) -> Vec<V> {
+        self.rows.lock().expect("Poisoned table").values().filter(|row| predicate(row)).cloned().collect()
+    }
+    /// Applies `update` to the row under `key`, returning the updated row if there was one
+    pub fn update(&self, key: &K, update: impl FnOnce(&mut V)) -> Option<V> {
+        let mut rows = self.rows.lock().expect("Poisoned table");
+        let row = rows.get_mut(key)?;
+        update(row);
+        Some(row.clone())
+    }
+    pub fn len(&self) -> usize {
+        self.rows.lock().expect("Poisoned table").len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> Default for MemoryTable<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
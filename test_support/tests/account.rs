@@ -0,0 +1,52 @@
+use async_graphql::Request;
+use account_service::graphql::utils::verify_password;
+use serde_json::{json, Value};
+use test_support::account::{schema, AccountSchema, UserStore};
+
+async fn execute(schema: &AccountSchema, query: &str) -> Value {
+    let response = schema.execute(Request::new(query)).await;
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    response.data.into_json().unwrap()
+}
+
+const CREATE_USER: &str = r#"mutation {
+    createNewUsers(newUser: { email: "neil@example.com", hash: "Correct-Horse-Battery-9", username: "mccauley", firstName: "Neil", lastName: "McCauley" }) { username role }
+}"#;
+
+#[tokio::test]
+async fn created_user_is_listed_with_a_hashed_password() {
+    let schema = schema(UserStore::new(), Default::default());
+
+    let created = execute(&schema, CREATE_USER).await;
+    let listed = execute(&schema, "{ getAllUsers { email hash } }").await;
+
+    assert_eq!(created["createNewUsers"]["username"], json!("mccauley"));
+    assert_eq!(listed["getAllUsers"][0]["email"], json!("neil@example.com"));
+    let hash = listed["getAllUsers"][0]["hash"].as_str().unwrap();
+    assert_ne!(hash, "Correct-Horse-Battery-9");
+    assert!(verify_password(hash, "Correct-Horse-Battery-9").unwrap());
+}
+
+#[tokio::test]
+async fn invalid_email_is_rejected_before_reaching_the_store() {
+    let schema = schema(UserStore::new(), Default::default());
+
+    let response = schema.execute(Request::new(r#"mutation {
+        createNewUsers(newUser: { email: "not-an-email", hash: "Correct-Horse-Battery-9", username: "mccauley", firstName: "Neil", lastName: "McCauley" }) { username }
+    }"#)).await;
+
+    assert_eq!(response.errors.len(), 1);
+    assert_eq!(execute(&schema, "{ getAllUsers { email } }").await, json!({ "getAllUsers": [] }));
+}
+
+#[tokio::test]
+async fn login_with_the_wrong_password_is_refused() {
+    let schema = schema(UserStore::new(), Default::default());
+    execute(&schema, CREATE_USER).await;
+
+    let response = schema.execute(Request::new(r#"mutation {
+        loginUser(user: { email: "neil@example.com", password: "wrong-password" })
+    }"#)).await;
+
+    assert_eq!(response.errors.len(), 1);
+}
@@ -0,0 +1,33 @@
+use async_graphql::Request;
+use activity_tracker::graphql::modules::model::UserWatchTime;
+use serde_json::json;
+use test_support::activity::{schema, ActivityStore};
+
+fn watched(user_id: i64, session: i64, time: i64, title: &str) -> ((i64, i64, i64), UserWatchTime) {
+    let record = UserWatchTime {
+        time,
+        liked: false,
+        movie_id: 10,
+        session,
+        title: title.to_string(),
+        user_id,
+    };
+    ((user_id, session, time), record)
+}
+
+#[tokio::test]
+async fn user_records_only_hold_that_users_sessions() {
+    let store = ActivityStore::default();
+    for (key, record) in vec![watched(1, 100, 1_000, "Heat"), watched(1, 101, 2_000, "Ronin"), watched(2, 200, 1_500, "Thief")] {
+        store.0.insert(key, record);
+    }
+    let schema = schema(store);
+
+    let response = schema.execute(Request::new("{ getUserRecords(userId: 1) { title session userId } }")).await;
+
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    assert_eq!(response.data.into_json().unwrap(), json!({ "getUserRecords": [
+        { "title": "Heat", "session": 100, "userId": "1" },
+        { "title": "Ronin", "session": 101, "userId": "1" },
+    ] }));
+}
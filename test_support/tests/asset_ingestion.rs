@@ -0,0 +1,158 @@
+use async_graphql::Request;
+use serde_json::{json, Value};
+use test_support::{asset_ingestion::{schema, CatalogStore, FixtureSource, IngestionSchema}, leak};
+
+fn catalog() -> (&'static CatalogStore, IngestionSchema) {
+    let store = leak(CatalogStore::default());
+    (store, schema(store, FixtureSource::default()))
+}
+
+async fn execute(schema: &IngestionSchema, query: &str) -> Value {
+    let response = schema.execute(Request::new(query)).await;
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    response.data.into_json().unwrap()
+}
+
+async fn create_movie(schema: &IngestionSchema, title: &str) -> String {
+    let query = format!(r#"mutation {{ createMovie(newMovie: {{ title: "{}", genres: ["Crime"] }}) {{ movieId }} }}"#, title);
+    let created = execute(schema, &query).await;
+    created["createMovie"]["movieId"].as_str().unwrap().to_string()
+}
+
+fn outbox_events(store: &CatalogStore) -> Vec<String> {
+    store.outbox.rows().into_iter().map(|entry| entry.event_type).collect()
+}
+
+#[tokio::test]
+async fn create_movie_writes_the_row_and_its_event() {
+    let (store, schema) = catalog();
+
+    let created = execute(&schema, r#"mutation {
+        createMovie(newMovie: { title: "Heat", year: 1995, genres: ["Crime"] }) { title year genres }
+    }"#).await;
+
+    assert_eq!(created, json!({ "createMovie": { "title": "Heat", "year": 1995, "genres": ["crime"] } }));
+    assert_eq!(store.movies.len(), 1);
+    assert_eq!(outbox_events(store), vec!["catalog.movie.created"]);
+}
+
+#[tokio::test]
+async fn unknown_genres_are_rejected() {
+    let (store, schema) = catalog();
+
+    let response = schema.execute(Request::new(r#"mutation {
+        createMovie(newMovie: { title: "Heat", genres: ["heist noir"] }) { movieId }
+    }"#)).await;
+
+    assert_eq!(response.errors.len(), 1);
+    assert!(store.movies.is_empty());
+    assert!(store.outbox.is_empty());
+}
+
+#[tokio::test]
+async fn patch_movie_keeps_the_fields_left_out() {
+    let (store, schema) = catalog();
+    let movie_id = create_movie(&schema, "Heat").await;
+
+    let query = format!(r#"mutation {{ patchMovie(movieId: "{}", patch: {{ overview: "A heist in Los Angeles" }}) {{ title overview genres }} }}"#, movie_id);
+    let patched = execute(&schema, &query).await;
+
+    assert_eq!(patched, json!({ "patchMovie": { "title": "Heat", "overview": "A heist in Los Angeles", "genres": ["crime"] } }));
+    assert_eq!(outbox_events(store), vec!["catalog.movie.created", "catalog.movie.updated"]);
+}
+
+#[tokio::test]
+async fn soft_deleting_twice_publishes_once() {
+    let (store, schema) = catalog();
+    let movie_id = create_movie(&schema, "Heat").await;
+    let query = format!(r#"mutation {{ softDeleteMovie(movieId: "{}") {{ movieId }} }}"#, movie_id);
+
+    execute(&schema, &query).await;
+    execute(&schema, &query).await;
+
+    assert_eq!(outbox_events(store), vec!["catalog.movie.created", "catalog.movie.soft_deleted"]);
+}
+
+#[tokio::test]
+async fn person_known_by_an_external_id_is_not_created_twice() {
+    let (store, schema) = catalog();
+    let query = r#"mutation { createPerson(newPerson: { name: "Al Pacino", externalIds: ["TMDB:1158"] }) { personId name } }"#;
+
+    let first = execute(&schema, query).await;
+    let second = execute(&schema, query).await;
+
+    assert_eq!(first, second);
+    assert_eq!(store.people.len(), 1);
+    let external_ids: Vec<String> = store.person_external_ids.rows().into_iter().map(|row| row.external_id).collect();
+    assert_eq!(external_ids, vec!["tmdb:1158"]);
+}
+
+#[tokio::test]
+async fn delete_movie_finds_the_row_by_id_alone() {
+    let (store, schema) = catalog();
+    let movie_id = create_movie(&schema, "Heat").await;
+    let query = format!(r#"mutation {{ deleteMovie(movieId: "{}") }}"#, movie_id);
+
+    let deleted = execute(&schema, &query).await;
+    let deleted_again = execute(&schema, &query).await;
+
+    assert_eq!(deleted, json!({ "deleteMovie": true }));
+    assert_eq!(deleted_again, json!({ "deleteMovie": false }));
+    assert!(store.movies.is_empty());
+    assert_eq!(outbox_events(store), vec!["catalog.movie.created", "catalog.movie.deleted"]);
+}
+
+#[tokio::test]
+async fn people_get_an_id_nobody_had_before() {
+    let (store, schema) = catalog();
+    let create = |name: &str| format!(r#"mutation {{ createPerson(newPerson: {{ name: "{}" }}) {{ personId }} }}"#, name);
+
+    let first = execute(&schema, &create("Al Pacino")).await;
+    let second = execute(&schema, &create("Robert De Niro")).await;
+
+    assert_ne!(first["createPerson"]["personId"], second["createPerson"]["personId"]);
+    assert_eq!(store.people.len(), 2);
+}
+
+#[tokio::test]
+async fn series_writes_queue_their_events_in_the_order_they_happen() {
+    let (store, schema) = catalog();
+    let created = execute(&schema, r#"mutation {
+        createSeries(newSeries: { title: "The Wire", firstAirDate: "2002-06-02" }) { seriesId }
+    }"#).await;
+    let series_id = created["createSeries"]["seriesId"].as_str().unwrap().to_string();
+
+    execute(&schema, &format!(r#"mutation {{ upsertSeason(seriesId: "{}", season: {{ seasonNumber: 1 }}) {{ seasonNumber }} }}"#, series_id)).await;
+    execute(&schema, &format!(r#"mutation {{ deleteSeries(seriesId: "{}") }}"#, series_id)).await;
+
+    assert_eq!(outbox_events(store), vec!["catalog.series.upserted", "catalog.series.season_upserted", "catalog.series.deleted"]);
+}
+
+#[tokio::test]
+async fn translations_are_published_through_the_outbox() {
+    let (store, schema) = catalog();
+    let movie_id = create_movie(&schema, "Heat").await;
+
+    execute(&schema, &format!(r#"mutation {{ upsertMovieTranslation(movieId: "{}", translation: {{ locale: "de", title: "Heat" }}) {{ locale }} }}"#, movie_id)).await;
+
+    let queued = store.outbox.find(|entry| entry.event_type == "catalog.translations.movie_translated").unwrap();
+    assert_eq!(queued.partition_key, movie_id);
+}
+
+#[tokio::test]
+async fn genre_migration_rewrites_legacy_values_and_reports_unknown_ones() {
+    let (store, schema) = catalog();
+    let heat: i64 = create_movie(&schema, "Heat").await.parse().unwrap();
+    let ronin: i64 = create_movie(&schema, "Ronin").await.parse().unwrap();
+    // Stored before the taxonomy existed, a TMDB id and a free-text name
+    store.movies.update(&heat, |movie| movie.genres = vec![String::from("28"), String::from("Sci-Fi")]);
+    store.movies.update(&ronin, |movie| movie.genres = vec![String::from("heist noir")]);
+
+    let report = execute(&schema, "mutation { migrateGenres { moviesScanned moviesRewritten unmapped errors } }").await;
+
+    assert_eq!(report, json!({ "migrateGenres": { "moviesScanned": 2, "moviesRewritten": 1, "unmapped": ["heist noir"], "errors": [] } }));
+    assert_eq!(store.movies.get(&heat).unwrap().genres, vec!["action", "science-fiction"]);
+    assert_eq!(store.movies.get(&ronin).unwrap().genres, vec!["heist noir"]);
+    let updated = store.outbox.filter(|entry| entry.event_type == "catalog.movie.updated");
+    assert_eq!(updated.into_iter().map(|entry| entry.partition_key).collect::<Vec<_>>(), vec![heat.to_string()]);
+}
@@ -0,0 +1,217 @@
+use async_graphql::Request;
+use asset_service::graphql::modules::{
+    availability::model::AvailabilityWindow,
+    credits::model::{Credit, Person},
+    model::Movie,
+    series::model::{Episode, Season, Series},
+};
+use chrono::NaiveDate;
+use common_utils::{availability::RequestRegion, signing::PlaybackClient};
+use serde_json::{json, Value};
+use test_support::{asset_service::{schema, AvailabilityStore, CatalogSchema, CreditStore, MovieStore, SeriesStore}, MemoryTable};
+
+fn movie(movie_id: i64, title: &str) -> Movie {
+    Movie {
+        movie_id,
+        title: title.to_string(),
+        year: 1995,
+        awards: Vec::new(),
+        business: Default::default(),
+        countries: Vec::new(),
+        deleted_at: None,
+        genres: vec![String::from("crime")],
+        homepage: String::new(),
+        keywords: Vec::new(),
+        languages: Vec::new(),
+        media_type: String::from("Movie"),
+        movie_casts: Vec::new(),
+        movie_company: Vec::new(),
+        movie_director: Vec::new(),
+        movie_writer: Vec::new(),
+        overview: String::new(),
+        poster: String::new(),
+        rated: String::from("R"),
+        rating: Default::default(),
+        release_date: NaiveDate::from_ymd(1995, 12, 15),
+        runtime: 170,
+        status: String::from("Released"),
+        video_file: String::new(),
+    }
+}
+
+fn window(movie_id: i64, window_id: i64, territory: &str) -> AvailabilityWindow {
+    AvailabilityWindow {
+        movie_id,
+        window_id,
+        ends_at: None,
+        offering: String::from("SUBSCRIPTION"),
+        starts_at: None,
+        territory: territory.to_string(),
+    }
+}
+
+fn catalog(movies: Vec<Movie>, series: SeriesStore, credits: CreditStore, availability: Vec<AvailabilityWindow>) -> CatalogSchema {
+    let movies: MovieStore = MemoryTable::with_rows(movies.into_iter().map(|movie| (movie.movie_id, movie)));
+    let availability: AvailabilityStore = MemoryTable::with_rows(availability.into_iter().map(|window| ((window.movie_id, window.window_id), window)));
+    schema(movies, series, credits, MemoryTable::new(), availability, MemoryTable::new(), Default::default())
+}
+
+async fn execute(schema: &CatalogSchema, request: Request) -> Value {
+    let response = schema.execute(request).await;
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    response.data.into_json().unwrap()
+}
+
+#[tokio::test]
+async fn movie_is_served_with_its_cast() {
+    let credits = CreditStore {
+        credits: MemoryTable::with_rows(vec![(
+            (1, String::from("c1")),
+            Credit {
+                movie_id: 1,
+                credit_id: String::from("c1"),
+                billing_order: Some(0),
+                character_name: Some(String::from("Vincent Hanna")),
+                department: String::from("Acting"),
+                job: String::from("Actor"),
+                name: String::from("Al Pacino"),
+                person_id: 7,
+            },
+        )]),
+        people: MemoryTable::with_rows(vec![(
+            7,
+            Person {
+                person_id: 7,
+                name: String::from("Al Pacino"),
+                awards: Vec::new(),
+                biography: String::new(),
+                birthday: NaiveDate::from_ymd(1940, 4, 25),
+                death_date: NaiveDate::from_ymd(2015, 9, 8),
+                gender: String::from("Male"),
+                homepage: String::new(),
+                known_for: Vec::new(),
+                place_of_birth: String::from("New York City"),
+                profile_path: Vec::new(),
+            },
+        )]),
+    };
+    let schema = catalog(vec![movie(1, "Heat")], SeriesStore::default(), credits, Vec::new());
+
+    let served = execute(&schema, Request::new(r#"{ getMovieById(id: "1") { title cast { name character person { placeOfBirth } } } }"#)).await;
+
+    assert_eq!(served, json!({ "getMovieById": {
+        "title": "Heat",
+        "cast": [{ "name": "Al Pacino", "character": "Vincent Hanna", "person": { "placeOfBirth": "New York City" } }],
+    } }));
+}
+
+#[tokio::test]
+async fn soft_deleted_movie_is_not_found() {
+    let deleted = Movie { deleted_at: Some(1_600_000_000_000), ..movie(1, "Heat") };
+    let schema = catalog(vec![deleted, movie(2, "Ronin")], SeriesStore::default(), CreditStore::default(), Vec::new());
+
+    let response = schema.execute(Request::new(r#"{ getMovieById(id: "1") { title } }"#)).await;
+    assert_eq!(response.errors.len(), 1);
+
+    let listed = execute(&schema, Request::new("{ getAllMovies { title } }")).await;
+    assert_eq!(listed, json!({ "getAllMovies": [{ "title": "Ronin" }] }));
+}
+
+#[tokio::test]
+async fn movies_are_only_listed_where_a_window_covers_the_region() {
+    let schema = catalog(
+        vec![movie(1, "Heat"), movie(2, "Ronin"), movie(3, "Thief")],
+        SeriesStore::default(),
+        CreditStore::default(),
+        vec![window(1, 10, "GB"), window(2, 20, "WW")],
+    );
+    let query = "{ getAllMovies { title } }";
+
+    let in_gb = execute(&schema, Request::new(query).data(RequestRegion { region: Some(String::from("GB")) })).await;
+    let in_us = execute(&schema, Request::new(query).data(RequestRegion { region: Some(String::from("US")) })).await;
+    let unknown = execute(&schema, Request::new(query)).await;
+
+    assert_eq!(in_gb, json!({ "getAllMovies": [{ "title": "Heat" }, { "title": "Ronin" }, { "title": "Thief" }] }));
+    assert_eq!(in_us, json!({ "getAllMovies": [{ "title": "Ronin" }, { "title": "Thief" }] }));
+    assert_eq!(unknown, in_us);
+}
+
+fn the_wire(episodes: MemoryTable<(i64, i32, i32), Episode>) -> SeriesStore {
+    SeriesStore {
+        series: MemoryTable::with_rows(vec![(
+            5,
+            Series {
+                series_id: 5,
+                created_by: vec![String::from("David Simon")],
+                first_air_date: NaiveDate::from_ymd(2002, 6, 2),
+                genres: vec![String::from("crime")],
+                homepage: String::new(),
+                languages: vec![String::from("en")],
+                last_air_date: Some(NaiveDate::from_ymd(2008, 3, 9)),
+                overview: String::new(),
+                poster: String::new(),
+                rated: String::from("TV-MA"),
+                status: String::from("Ended"),
+                title: String::from("The Wire"),
+            },
+        )]),
+        seasons: MemoryTable::with_rows((1..=2).map(|season_number| (
+            (5, season_number),
+            Season {
+                series_id: 5,
+                season_number,
+                air_date: None,
+                name: format!("Season {}", season_number),
+                overview: String::new(),
+                poster: String::new(),
+                season_id: 50 + season_number as i64,
+            },
+        ))),
+        episodes,
+    }
+}
+
+#[tokio::test]
+async fn series_lists_its_seasons() {
+    let series = the_wire(MemoryTable::new());
+    let schema = catalog(Vec::new(), series, CreditStore::default(), Vec::new());
+
+    let served = execute(&schema, Request::new(r#"{ getSeries(seriesId: "5") { title seasons { seasonNumber name episodes } } }"#)).await;
+
+    assert_eq!(served, json!({ "getSeries": {
+        "title": "The Wire",
+        "seasons": [
+            { "seasonNumber": 1, "name": "Season 1", "episodes": null },
+            { "seasonNumber": 2, "name": "Season 2", "episodes": null },
+        ],
+    } }));
+}
+
+#[tokio::test]
+async fn episodes_only_play_where_a_window_of_their_series_covers_the_region() {
+    let pilot = Episode {
+        series_id: 5,
+        season_number: 1,
+        episode_number: 1,
+        air_date: None,
+        episode_id: 501,
+        overview: String::new(),
+        runtime: 62,
+        still_path: String::new(),
+        title: String::from("The Target"),
+        video_file: String::new(),
+    };
+    let series = the_wire(MemoryTable::with_rows(vec![((5, 1, 1), pilot)]));
+    let schema = catalog(Vec::new(), series, CreditStore::default(), vec![window(5, 10, "GB")]);
+    let playback = |region: &str| {
+        Request::new(r#"{ episodePlaybackInfo(seriesId: "5", seasonNumber: 1, episodeNumber: 1) { ownerId } }"#)
+            .data(PlaybackClient { subject: Some(String::from("mcnulty")), ..PlaybackClient::default() })
+            .data(RequestRegion { region: Some(region.to_string()) })
+    };
+
+    let in_gb = execute(&schema, playback("GB")).await;
+    let in_us = schema.execute(playback("US")).await;
+
+    assert_eq!(in_gb, json!({ "episodePlaybackInfo": { "ownerId": "501" } }));
+    assert_eq!(in_us.errors.len(), 1);
+}
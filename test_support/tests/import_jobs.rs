@@ -0,0 +1,271 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+use async_graphql::Request;
+use async_trait::async_trait;
+use asset_ingestion_service::graphql::modules::types::{
+    credits::model::{Credit, ACTING},
+    ingestion_jobs::{model::{IngestionJob, JobStatus}, resolver::JobResolver, worker::{resume_jobs, spawn_job_sweeper}},
+    movies::{model::Movie, schema::BulkStreamInsertData},
+    people_module::model::PersonExternalId,
+};
+use chrono::{NaiveDate, Utc};
+use common_utils::{QueryResult, error::ServiceError};
+use test_support::{asset_ingestion::{schema, CatalogStore, FixtureSource, InMemoryCreditDatabase, InMemoryGenreDatabase, InMemoryJobDatabase, InMemoryMovieDatabase, InMemoryPersonDatabase}, leak, MemoryTable};
+
+fn fixture_movie(movie_id: i64, title: &str) -> Movie {
+    Movie {
+        movie_id,
+        title: title.to_string(),
+        year: 1995,
+        awards: Vec::new(),
+        business: Default::default(),
+        countries: Vec::new(),
+        deleted_at: None,
+        genres: vec![String::from("80"), String::from("10759")],
+        homepage: String::new(),
+        keywords: Vec::new(),
+        languages: Vec::new(),
+        media_type: String::from("Movie"),
+        movie_casts: Vec::new(),
+        movie_company: Vec::new(),
+        movie_director: Vec::new(),
+        movie_writer: Vec::new(),
+        overview: String::new(),
+        poster: String::new(),
+        rated: String::from("R"),
+        rating: Default::default(),
+        release_date: NaiveDate::from_ymd(1995, 12, 15),
+        runtime: 170,
+        status: String::from("Released"),
+        video_file: String::new(),
+    }
+}
+
+/// A cast credit as TMDB sends it, `tmdb_id` is TMDB's person id
+fn tmdb_credit(movie_id: i64, tmdb_id: i32, name: &str) -> ((i64, String), Credit) {
+    let credit_id = format!("{}-{}", movie_id, tmdb_id);
+    let credit = Credit {
+        movie_id,
+        credit_id: credit_id.clone(),
+        billing_order: Some(0),
+        character_name: None,
+        department: ACTING.to_string(),
+        job: String::from("Actor"),
+        name: name.to_string(),
+        person_id: tmdb_id,
+    };
+    ((movie_id, credit_id), credit)
+}
+
+fn source() -> FixtureSource {
+    FixtureSource {
+        movies: MemoryTable::with_rows(vec![(1, fixture_movie(1, "Heat")), (2, fixture_movie(2, "Ronin"))]),
+        credits: MemoryTable::new(),
+    }
+}
+
+fn request() -> BulkStreamInsertData {
+    BulkStreamInsertData {
+        discover_api: String::from("discover/movie"),
+        endpoint_popular: String::from("movie/popular"),
+        language: None,
+        included_with: None,
+        number_of_batch: Some(10),
+    }
+}
+
+/// A job left running by a worker whose lease runs out `expires_in` from now
+fn leased_job(owner: &str, expires_in: i64) -> IngestionJob {
+    let mut job = IngestionJob::new(&request()).unwrap();
+    job.status = JobStatus::Running.to_string();
+    job.lease_owner = Some(owner.to_string());
+    job.lease_expires_at = Some(Utc::now().timestamp_millis() + expires_in);
+    job
+}
+
+async fn finished(store: &CatalogStore, job_id: i64) -> IngestionJob {
+    for _ in 0..500 {
+        let job = store.jobs.get(&job_id).unwrap();
+        if job.status().is_finished() {
+            return job;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("Import job {} did not finish", job_id)
+}
+
+async fn resume<J: JobResolver<Store = CatalogStore>>(store: &'static CatalogStore) -> usize {
+    resume_jobs::<J, InMemoryMovieDatabase, InMemoryCreditDatabase, InMemoryPersonDatabase, InMemoryGenreDatabase>(store, store, store, store, store, Arc::new(source()))
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn import_job_imports_every_fixture_movie() {
+    let store = leak(CatalogStore::default());
+    let schema = schema(store, source());
+
+    let response = schema.execute(Request::new(r#"mutation {
+        startImportJob(request: { discoverApi: "discover/movie", endpointPopular: "movie/popular", numberOfBatch: 10 }) { jobId }
+    }"#)).await;
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    let job_id = response.data.into_json().unwrap()["startImportJob"]["jobId"].as_str().unwrap().parse().unwrap();
+
+    let job = finished(store, job_id).await;
+    assert_eq!(job.status(), JobStatus::Completed);
+    assert_eq!((job.total, job.processed, job.failed, job.cursor), (2, 2, 0, 2));
+    assert_eq!(store.movies.len(), 2);
+}
+
+#[tokio::test]
+async fn imported_genres_are_resolved_through_the_stored_taxonomy() {
+    let store = leak(CatalogStore::default());
+    let schema = schema(store, source());
+    // 10759 is mapped onto action and adventure by default, an edited taxonomy maps it elsewhere
+    store.genres.update(&1, |action| action.tmdb_ids = Some(vec![28]));
+    store.genres.update(&2, |adventure| adventure.tmdb_ids = Some(vec![12]));
+
+    let response = schema.execute(Request::new(r#"mutation {
+        startImportJob(request: { discoverApi: "discover/movie", endpointPopular: "movie/popular", numberOfBatch: 1 }) { jobId }
+    }"#)).await;
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    let job_id = response.data.into_json().unwrap()["startImportJob"]["jobId"].as_str().unwrap().parse().unwrap();
+    finished(store, job_id).await;
+
+    assert_eq!(store.movies.get(&1).unwrap().genres, vec![String::from("crime")]);
+}
+
+#[tokio::test]
+async fn credits_point_at_local_people_found_or_created_through_their_tmdb_id() {
+    let store = leak(CatalogStore::default());
+    store.person_external_ids.insert((String::from("tmdb:1158"), 7), PersonExternalId { external_id: String::from("tmdb:1158"), person_id: 7 });
+    let source = FixtureSource {
+        credits: MemoryTable::with_rows(vec![
+            tmdb_credit(1, 1158, "Al Pacino"),
+            tmdb_credit(1, 380, "Robert De Niro"),
+            tmdb_credit(2, 380, "Robert De Niro"),
+        ]),
+        ..source()
+    };
+    let job = IngestionJob::new(&request()).unwrap();
+    store.jobs.insert(job.job_id, job.clone());
+
+    resume_jobs::<InMemoryJobDatabase, InMemoryMovieDatabase, InMemoryCreditDatabase, InMemoryPersonDatabase, InMemoryGenreDatabase>(store, store, store, store, store, Arc::new(source))
+        .await
+        .unwrap();
+    finished(store, job.job_id).await;
+
+    let de_niro = store.person_external_ids.find(|row| row.external_id == "tmdb:380").unwrap().person_id;
+    let person_of = |movie_id: i64, tmdb_id: i32| store.credits.get(&(movie_id, format!("{}-{}", movie_id, tmdb_id))).unwrap().person_id;
+    assert_eq!(person_of(1, 1158), 7);
+    assert_eq!(person_of(1, 380), de_niro);
+    assert_eq!(person_of(2, 380), de_niro);
+    assert_eq!(store.people.rows().into_iter().map(|person| (person.person_id, person.name)).collect::<Vec<_>>(), vec![(de_niro, String::from("Robert De Niro"))]);
+}
+
+#[tokio::test]
+async fn job_leased_by_a_live_worker_is_not_resumed() {
+    let store = leak(CatalogStore::default());
+    let job = leased_job("another instance", 60_000);
+    store.jobs.insert(job.job_id, job.clone());
+
+    resume::<InMemoryJobDatabase>(store).await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let untouched = store.jobs.get(&job.job_id).unwrap();
+    assert_eq!(untouched.lease_owner, job.lease_owner);
+    assert_eq!(untouched.cursor, 0);
+    assert!(store.movies.is_empty());
+}
+
+#[tokio::test]
+async fn job_whose_lease_ran_out_is_taken_over() {
+    let store = leak(CatalogStore::default());
+    let job = leased_job("another instance", -1);
+    store.jobs.insert(job.job_id, job.clone());
+
+    resume::<InMemoryJobDatabase>(store).await;
+
+    let job = finished(store, job.job_id).await;
+    assert_eq!(job.status(), JobStatus::Completed);
+    assert_ne!(job.lease_owner.as_deref(), Some("another instance"));
+    assert_eq!(store.movies.len(), 2);
+}
+
+#[tokio::test]
+async fn sweeper_takes_over_a_job_once_the_lease_of_its_dead_worker_runs_out() {
+    let store = leak(CatalogStore::default());
+    let job = leased_job("an instance that crashed", 300);
+    store.jobs.insert(job.job_id, job.clone());
+
+    spawn_job_sweeper::<InMemoryJobDatabase, InMemoryMovieDatabase, InMemoryCreditDatabase, InMemoryPersonDatabase, InMemoryGenreDatabase>(store, store, store, store, store, Arc::new(source()), Duration::from_millis(50));
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    assert_eq!(store.jobs.get(&job.job_id).unwrap().lease_owner, job.lease_owner);
+    assert!(store.movies.is_empty());
+
+    let job = finished(store, job.job_id).await;
+    assert_eq!(job.status(), JobStatus::Completed);
+    assert_ne!(job.lease_owner.as_deref(), Some("an instance that crashed"));
+    assert_eq!(store.movies.len(), 2);
+}
+
+#[tokio::test]
+async fn a_job_already_running_here_is_not_started_twice() {
+    let store = leak(CatalogStore::default());
+    let job = IngestionJob::new(&request()).unwrap();
+    store.jobs.insert(job.job_id, job.clone());
+
+    let first = resume::<InMemoryJobDatabase>(store).await;
+    let second = resume::<InMemoryJobDatabase>(store).await;
+
+    assert_eq!((first, second), (1, 0));
+    assert_eq!(finished(store, job.job_id).await.processed, 2);
+}
+
+/// Fails the first write of a job, the way a database that is briefly unreachable would
+struct FlakyJobDatabase;
+
+const SAVE_FAILURES: u32 = 1;
+static SAVES: AtomicU32 = AtomicU32::new(0);
+
+#[async_trait]
+impl JobResolver for FlakyJobDatabase {
+    type Store = CatalogStore;
+
+    async fn get_job(job_id: i64, session: &'static CatalogStore) -> QueryResult<IngestionJob> {
+        InMemoryJobDatabase::get_job(job_id, session).await
+    }
+    async fn get_jobs(session: &'static CatalogStore) -> QueryResult<Vec<IngestionJob>> {
+        InMemoryJobDatabase::get_jobs(session).await
+    }
+    async fn create_job(job: IngestionJob, session: &'static CatalogStore) -> QueryResult<IngestionJob> {
+        InMemoryJobDatabase::create_job(job, session).await
+    }
+    async fn claim_job(job: IngestionJob, expected: JobStatus, expected_owner: Option<String>, session: &'static CatalogStore) -> QueryResult<bool> {
+        InMemoryJobDatabase::claim_job(job, expected, expected_owner, session).await
+    }
+    async fn save_job(job: IngestionJob, expected: JobStatus, session: &'static CatalogStore) -> QueryResult<bool> {
+        if SAVES.fetch_add(1, Ordering::SeqCst) < SAVE_FAILURES {
+            return Err(ServiceError::DatabaseError);
+        }
+        InMemoryJobDatabase::save_job(job, expected, session).await
+    }
+    async fn cancel_job(job_id: i64, session: &'static CatalogStore) -> QueryResult<bool> {
+        InMemoryJobDatabase::cancel_job(job_id, session).await
+    }
+}
+
+#[tokio::test]
+async fn job_is_marked_failed_when_its_progress_cannot_be_saved() {
+    let store = leak(CatalogStore::default());
+    let job = IngestionJob::new(&request()).unwrap();
+    store.jobs.insert(job.job_id, job.clone());
+
+    resume::<FlakyJobDatabase>(store).await;
+
+    let job = finished(store, job.job_id).await;
+    assert_eq!(job.status(), JobStatus::Failed);
+    assert_eq!(job.failed, 1);
+    assert!(store.movies.is_empty());
+}
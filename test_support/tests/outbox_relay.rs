@@ -0,0 +1,206 @@
+use std::sync::Mutex;
+use asset_ingestion_service::graphql::modules::types::outbox::{model::{OutboxEntry, OUTBOX_BUCKETS, OUTBOX_MAX_ATTEMPTS}, relay::{relay_once, Sweep}};
+use common_utils::{QueryResult, error::ServiceError, kafka::{Delivery, Message}};
+use test_support::{asset_ingestion::{CatalogStore, InMemoryOutboxDatabase}, leak};
+
+/// Writes an entry for `key` straight to the outbox, as the logged batch of a write would
+fn pending(store: &CatalogStore, key: &str, event_id: &str) {
+    let entry = OutboxEntry::new(Message {
+        event_id: event_id.to_string(),
+        key: key.to_string(),
+        event_type: String::from("catalog.movie.updated"),
+        schema_version: 1,
+        payload: String::from("{}"),
+    });
+    store.outbox.insert((entry.bucket, entry.entry_id), entry);
+}
+
+/// Stands in for Kafka. Every batch handed to it is recorded, the events in `failing` are
+/// refused, and only the first `reported` of a batch get a delivery report
+struct Broker {
+    batches: Mutex<Vec<Vec<String>>>,
+    failing: Vec<&'static str>,
+    reported: usize,
+}
+
+impl Broker {
+    fn new(failing: Vec<&'static str>) -> Self {
+        Self { batches: Mutex::new(Vec::new()), failing, reported: usize::MAX }
+    }
+    fn publish(&self, messages: Vec<Message>) -> Vec<QueryResult<Delivery>> {
+        self.batches.lock().unwrap().push(messages.iter().map(|message| message.event_id.clone()).collect());
+        messages
+            .iter()
+            .take(self.reported)
+            .enumerate()
+            .map(|(offset, message)| match self.failing.contains(&message.event_id.as_str()) {
+                true => Err(ServiceError::ServerError(String::from("Broker unavailable"))),
+                false => Ok((0, offset as i64)),
+            })
+            .collect()
+    }
+    /// Every event handed to the broker, in the order it got them
+    fn published(&self) -> Vec<String> {
+        self.batches.lock().unwrap().concat()
+    }
+}
+
+async fn sweep_as(relay_id: &str, store: &'static CatalogStore, broker: &Broker) -> Sweep {
+    let publish = |messages: Vec<Message>| {
+        let deliveries = broker.publish(messages);
+        async move { deliveries }
+    };
+    relay_once::<InMemoryOutboxDatabase, _, _>(&publish, relay_id, store).await.unwrap()
+}
+
+async fn sweep(store: &'static CatalogStore, broker: &Broker) -> Sweep {
+    sweep_as("relay-1", store, broker).await
+}
+
+/// Lets the entries backing off from a failed delivery be retried straight away
+fn backoff_passed(store: &CatalogStore) {
+    for (key, _) in store.outbox.entries() {
+        store.outbox.update(&key, |entry| entry.retry_at = entry.retry_at.map(|_| 0));
+    }
+}
+
+fn outbox(store: &CatalogStore) -> Vec<(String, i32)> {
+    store.outbox.rows().into_iter().map(|entry| (entry.event_id, entry.attempts)).collect()
+}
+
+#[tokio::test]
+async fn entries_of_a_key_are_published_one_after_the_other() {
+    let store = leak(CatalogStore::default());
+    pending(store, "movie-1", "a1");
+    pending(store, "movie-1", "a2");
+    pending(store, "movie-2", "b1");
+    let broker = Broker::new(Vec::new());
+
+    let sweep = sweep(store, &broker).await;
+
+    assert_eq!(sweep, Sweep { published: 3, ..Sweep::default() });
+    assert!(broker.batches.lock().unwrap().iter().all(|batch| !(batch.contains(&String::from("a1")) && batch.contains(&String::from("a2")))));
+    let published = broker.published();
+    let position = |event_id: &str| published.iter().position(|published| published == event_id).unwrap();
+    assert!(position("a1") < position("a2"));
+    assert!(store.outbox.is_empty());
+    assert_eq!(store.outbox_sent.len(), 3);
+}
+
+#[tokio::test]
+async fn a_failure_holds_back_the_later_entries_of_its_key_only() {
+    let store = leak(CatalogStore::default());
+    pending(store, "movie-1", "a1");
+    pending(store, "movie-1", "a2");
+    pending(store, "movie-2", "b1");
+    pending(store, "movie-2", "b2");
+    let broker = Broker::new(vec!["a1"]);
+
+    let sweep = sweep(store, &broker).await;
+
+    assert_eq!(sweep, Sweep { published: 2, pending: 2, ..Sweep::default() });
+    assert!(!broker.published().contains(&String::from("a2")));
+    let mut left = outbox(store);
+    left.sort();
+    assert_eq!(left, vec![(String::from("a1"), 1), (String::from("a2"), 0)]);
+    assert_eq!(store.outbox.find(|entry| entry.event_id == "a1").unwrap().last_error.as_deref(), Some("Broker unavailable"));
+}
+
+#[tokio::test]
+async fn held_back_entries_go_out_in_order_once_the_broker_recovers() {
+    let store = leak(CatalogStore::default());
+    pending(store, "movie-1", "a1");
+    pending(store, "movie-1", "a2");
+    sweep(store, &Broker::new(vec!["a1"])).await;
+    backoff_passed(store);
+
+    let broker = Broker::new(Vec::new());
+    let sweep = sweep(store, &broker).await;
+
+    assert_eq!(sweep, Sweep { published: 2, ..Sweep::default() });
+    assert_eq!(broker.published(), vec!["a1", "a2"]);
+    assert!(store.outbox.is_empty());
+}
+
+#[tokio::test]
+async fn entries_missing_from_a_short_report_stay_pending() {
+    let store = leak(CatalogStore::default());
+    pending(store, "movie-1", "a1");
+    pending(store, "movie-1", "a2");
+    let broker = Broker { reported: 0, ..Broker::new(Vec::new()) };
+
+    let sweep = sweep(store, &broker).await;
+
+    assert_eq!(sweep, Sweep { pending: 2, ..Sweep::default() });
+    assert_eq!(broker.published(), vec!["a1"]);
+    assert_eq!(outbox(store).len(), 2);
+    assert!(store.outbox_sent.is_empty());
+}
+
+#[tokio::test]
+async fn a_failed_entry_backs_off_without_holding_back_other_keys() {
+    let store = leak(CatalogStore::default());
+    pending(store, "movie-1", "a1");
+    pending(store, "movie-1", "a2");
+    sweep(store, &Broker::new(vec!["a1"])).await;
+    pending(store, "movie-2", "b1");
+
+    let broker = Broker::new(Vec::new());
+    let sweep = sweep(store, &broker).await;
+
+    assert_eq!(sweep, Sweep { published: 1, pending: 2, ..Sweep::default() });
+    assert_eq!(broker.published(), vec!["b1"]);
+    assert!(store.outbox.find(|entry| entry.event_id == "a1").unwrap().retry_at.is_some());
+}
+
+#[tokio::test]
+async fn an_entry_failing_too_often_is_dead_lettered_and_the_rest_of_its_key_goes_out() {
+    let store = leak(CatalogStore::default());
+    pending(store, "movie-1", "a1");
+    pending(store, "movie-1", "a2");
+    for (key, _) in store.outbox.entries() {
+        store.outbox.update(&key, |entry| if entry.event_id == "a1" { entry.attempts = *OUTBOX_MAX_ATTEMPTS - 1 });
+    }
+
+    let first = sweep(store, &Broker::new(vec!["a1"])).await;
+    let broker = Broker::new(Vec::new());
+    let second = sweep(store, &broker).await;
+
+    assert_eq!(first, Sweep { pending: 1, dead_lettered: 1, ..Sweep::default() });
+    assert_eq!(second, Sweep { published: 1, ..Sweep::default() });
+    assert_eq!(broker.published(), vec!["a2"]);
+    let dead = store.outbox_dead.rows();
+    assert_eq!(dead.len(), 1);
+    assert_eq!((dead[0].event_id.as_str(), dead[0].attempts), ("a1", *OUTBOX_MAX_ATTEMPTS));
+    assert!(store.outbox.is_empty());
+}
+
+#[tokio::test]
+async fn a_bucket_is_relayed_only_by_the_relay_holding_its_lease() {
+    let store = leak(CatalogStore::default());
+    sweep_as("relay-1", store, &Broker::new(Vec::new())).await;
+    pending(store, "movie-1", "a1");
+
+    let broker = Broker::new(Vec::new());
+    let elsewhere = sweep_as("relay-2", store, &broker).await;
+
+    assert_eq!(elsewhere, Sweep { leased_elsewhere: *OUTBOX_BUCKETS as usize, ..Sweep::default() });
+    assert!(broker.published().is_empty());
+    assert_eq!(sweep_as("relay-1", store, &broker).await, Sweep { published: 1, ..Sweep::default() });
+}
+
+#[tokio::test]
+async fn a_bucket_is_taken_over_once_the_lease_of_its_relay_runs_out() {
+    let store = leak(CatalogStore::default());
+    sweep_as("relay-1", store, &Broker::new(Vec::new())).await;
+    pending(store, "movie-1", "a1");
+    for (bucket, _) in store.outbox_leases.entries() {
+        store.outbox_leases.update(&bucket, |(_, expires_at)| *expires_at = 0);
+    }
+
+    let broker = Broker::new(Vec::new());
+    let sweep = sweep_as("relay-2", store, &broker).await;
+
+    assert_eq!(sweep, Sweep { published: 1, ..Sweep::default() });
+    assert!(store.outbox_leases.rows().iter().all(|(owner, _)| owner == "relay-2"));
+}
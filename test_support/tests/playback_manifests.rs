@@ -0,0 +1,125 @@
+use asset_service::graphql::modules::playback::{
+    manifest::{dash_mpd, hls_master_playlist},
+    model::{AudioTrack, MediaAsset, MediaUrls, Rendition, TextTrack},
+};
+use common_utils::{playback::Plan, signing::{Binding, PlaybackClient, UrlSigner}};
+
+//  The golden files were signed with this secret and expiry, so their URLs are compared as they are
+const SECRET: &str = "golden-secret";
+const EXPIRES: i64 = 1_700_000_000;
+const BASE: &str = "https://cdn.example.com/movies/42";
+
+fn rendition(rendition_id: i64, codec: &str, container: &str, width: i32, height: i32, bitrate: i32, file: &str) -> Rendition {
+    Rendition {
+        owner_type: String::from("MOVIE"),
+        owner_id: 42,
+        rendition_id,
+        bitrate,
+        checksum: String::new(),
+        codec: codec.to_string(),
+        container: container.to_string(),
+        height,
+        location: format!("{}/{}", BASE, file),
+        width,
+    }
+}
+
+fn audio(track_id: i64, language: &str, audio_description: bool, codec: &str, channel_layout: &str, bitrate: i32, file: &str) -> AudioTrack {
+    AudioTrack {
+        owner_type: String::from("MOVIE"),
+        owner_id: 42,
+        track_id,
+        audio_description,
+        bitrate,
+        channel_layout: channel_layout.to_string(),
+        checksum: String::new(),
+        codec: codec.to_string(),
+        language: language.to_string(),
+        location: format!("{}/{}", BASE, file),
+    }
+}
+
+fn text(track_id: i64, language: &str, kind: &str, format: &str, file: &str) -> TextTrack {
+    TextTrack {
+        owner_type: String::from("MOVIE"),
+        owner_id: 42,
+        track_id,
+        checksum: String::new(),
+        format: format.to_string(),
+        kind: kind.to_string(),
+        language: language.to_string(),
+        location: format!("{}/{}", BASE, file),
+    }
+}
+
+/// Renditions HLS and DASH both play plus a WebM one only DASH lists, two audio codecs with an
+/// audio description track, and subtitles in both text formats. Stored out of order on purpose
+fn movie_media() -> MediaAsset {
+    MediaAsset {
+        renditions: vec![
+            rendition(3, "HEVC", "CMAF", 1920, 1080, 6000, "video_1080_hevc.mp4"),
+            rendition(1, "H264", "CMAF", 640, 360, 800, "video_360.mp4"),
+            rendition(4, "VP9", "WEBM", 1280, 720, 2500, "video_720.webm"),
+            rendition(2, "H264", "CMAF", 1280, 720, 3000, "video_720.mp4"),
+        ],
+        audio_tracks: vec![
+            audio(12, "fr", false, "EAC3", "SURROUND51", 384, "audio_fr.mp4"),
+            audio(11, "en", true, "AAC", "STEREO", 96, "audio_en_ad.mp4"),
+            audio(10, "en", false, "AAC", "STEREO", 128, "audio_en.mp4"),
+        ],
+        text_tracks: vec![
+            text(22, "fr", "FORCED", "TTML", "forced_fr.ttml"),
+            text(21, "en", "CAPTIONS", "WEBVTT", "cc_en.vtt"),
+            text(20, "en", "SUBTITLES", "WEBVTT", "subs_en.vtt"),
+        ],
+    }
+}
+
+fn signer() -> UrlSigner {
+    UrlSigner::new(SECRET, 3600, Binding::None)
+}
+
+fn anonymous() -> PlaybackClient {
+    PlaybackClient { subject: None, ip: None, session: None }
+}
+
+#[test]
+fn hls_master_playlist_matches_the_golden_file() {
+    let (signer, client) = (signer(), anonymous());
+    let urls = MediaUrls { signer: &signer, client: &client, expires: EXPIRES };
+
+    let playlist = hls_master_playlist(&movie_media().for_plan(Plan::Premium), &urls).unwrap();
+
+    assert_eq!(playlist, include_str!("golden/master.m3u8"));
+}
+
+#[test]
+fn hls_variants_without_audio_groups_are_listed_once() {
+    let (signer, client) = (signer(), anonymous());
+    let urls = MediaUrls { signer: &signer, client: &client, expires: EXPIRES };
+    let media = MediaAsset { audio_tracks: Vec::new(), text_tracks: Vec::new(), ..movie_media() };
+
+    let playlist = hls_master_playlist(&media.for_plan(Plan::Basic), &urls).unwrap();
+
+    assert_eq!(playlist, include_str!("golden/master_video_only.m3u8"));
+}
+
+#[test]
+fn dash_mpd_matches_the_golden_file() {
+    let (signer, client) = (signer(), anonymous());
+    let urls = MediaUrls { signer: &signer, client: &client, expires: EXPIRES };
+
+    let mpd = dash_mpd(&movie_media().for_plan(Plan::Premium), &urls, 7200).unwrap();
+
+    assert_eq!(mpd, include_str!("golden/manifest.mpd"));
+}
+
+#[test]
+fn no_manifest_without_a_playable_rendition() {
+    let (signer, client) = (signer(), anonymous());
+    let urls = MediaUrls { signer: &signer, client: &client, expires: EXPIRES };
+    let webm_only = MediaAsset { renditions: vec![rendition(4, "VP9", "WEBM", 1280, 720, 2500, "video_720.webm")], ..movie_media() };
+
+    assert_eq!(hls_master_playlist(&webm_only, &urls), None);
+    assert_eq!(dash_mpd(&MediaAsset::default(), &urls, 7200), None);
+}
@@ -0,0 +1,84 @@
+use async_graphql::Request;
+use chrono::NaiveDate;
+use common_utils::availability::RequestRegion;
+use recommendation_service::graphql::modules::model::{AvailabilityWindow, CatalogMovie, RecommendedMovies};
+use serde_json::{json, Value};
+use test_support::{recommendation::{schema, RecommendationSchema}, MemoryTable};
+
+fn recommended(user_id: i32, movie_id: i64, title: &str) -> ((i32, i64), RecommendedMovies) {
+    let recommendation = RecommendedMovies {
+        user_id,
+        movie_id,
+        created_at: NaiveDate::from_ymd(2022, 7, 1),
+        title: title.to_string(),
+    };
+    ((user_id, movie_id), recommendation)
+}
+
+fn catalog_movie(movie_id: i64, deleted_at: Option<i64>) -> (i64, CatalogMovie) {
+    (movie_id, CatalogMovie { movie_id, deleted_at })
+}
+
+fn recommender() -> RecommendationSchema {
+    let recommendations = MemoryTable::with_rows(vec![
+        recommended(1, 10, "Heat"),
+        recommended(1, 20, "Ronin"),
+        recommended(1, 30, "Thief"),
+        recommended(1, 40, "Collateral"),
+        recommended(2, 10, "Heat"),
+    ]);
+    //  Collateral was removed from the catalogue after it was recommended
+    let catalog = MemoryTable::with_rows(vec![
+        catalog_movie(10, None),
+        catalog_movie(20, Some(1_600_000_000_000)),
+        catalog_movie(30, None),
+    ]);
+    let availability = MemoryTable::with_rows(vec![(
+        (30, 1),
+        AvailabilityWindow {
+            movie_id: 30,
+            window_id: 1,
+            ends_at: None,
+            offering: String::from("SUBSCRIPTION"),
+            starts_at: None,
+            territory: String::from("GB"),
+        },
+    )]);
+    schema(recommendations, availability, catalog)
+}
+
+async fn titles(schema: &RecommendationSchema, request: Request) -> Value {
+    let response = schema.execute(request).await;
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    response.data.into_json().unwrap()
+}
+
+#[tokio::test]
+async fn deleted_and_missing_movies_are_left_out() {
+    let schema = recommender();
+
+    let served = titles(&schema, Request::new("{ getUserRecommendation(userId: 1) { movieId title } }")).await;
+
+    assert_eq!(served, json!({ "getUserRecommendation": [{ "movieId": 10, "title": "Heat" }] }));
+}
+
+#[tokio::test]
+async fn movies_outside_the_callers_region_are_left_out() {
+    let schema = recommender();
+    let query = "{ getUserRecommendation(userId: 1) { title } }";
+
+    let in_gb = titles(&schema, Request::new(query).data(RequestRegion { region: Some(String::from("GB")) })).await;
+    let in_us = titles(&schema, Request::new(query).data(RequestRegion { region: Some(String::from("US")) })).await;
+
+    assert_eq!(in_gb, json!({ "getUserRecommendation": [{ "title": "Heat" }, { "title": "Thief" }] }));
+    assert_eq!(in_us, json!({ "getUserRecommendation": [{ "title": "Heat" }] }));
+}
+
+#[tokio::test]
+async fn user_without_recommendations_gets_an_empty_list() {
+    let schema = recommender();
+
+    let served = titles(&schema, Request::new("{ getUserRecommendation(userId: 3) { title } }")).await;
+
+    assert_eq!(served, json!({ "getUserRecommendation": [] }));
+}
@@ -0,0 +1,74 @@
+use std::sync::atomic::{AtomicI64, Ordering};
+use async_graphql::Request;
+use async_trait::async_trait;
+use asset_ingestion_service::graphql::modules::types::{
+    movies::model::Status,
+    release::{model::{ReleaseSchedule, StatusChange}, resolver::ReleaseResolver, scheduler::apply_schedules},
+};
+use common_utils::{QueryResult, availability::now_millis, error::ServiceError};
+use test_support::{asset_ingestion::{schema, CatalogStore, FixtureSource, InMemoryMovieDatabase, InMemoryReleaseDatabase}, leak};
+
+/// Fails to read the schedule of `UNREADABLE_MOVIE`, the way a timed out query would
+struct PartlyUnreadableReleaseDatabase;
+
+static UNREADABLE_MOVIE: AtomicI64 = AtomicI64::new(0);
+
+#[async_trait]
+impl ReleaseResolver for PartlyUnreadableReleaseDatabase {
+    type Store = CatalogStore;
+
+    async fn get_schedule(movie_id: i64, session: &'static CatalogStore) -> QueryResult<Option<ReleaseSchedule>> {
+        if movie_id == UNREADABLE_MOVIE.load(Ordering::SeqCst) {
+            return Err(ServiceError::DatabaseError);
+        }
+        InMemoryReleaseDatabase::get_schedule(movie_id, session).await
+    }
+    async fn set_schedule(schedule: ReleaseSchedule, session: &'static CatalogStore) -> QueryResult<ReleaseSchedule> {
+        InMemoryReleaseDatabase::set_schedule(schedule, session).await
+    }
+    async fn get_boundaries(day: String, after: i64, until: i64, session: &'static CatalogStore) -> QueryResult<Vec<i64>> {
+        InMemoryReleaseDatabase::get_boundaries(day, after, until, session).await
+    }
+    async fn record_change(change: StatusChange, session: &'static CatalogStore) -> QueryResult<StatusChange> {
+        InMemoryReleaseDatabase::record_change(change, session).await
+    }
+    async fn get_history(movie_id: i64, session: &'static CatalogStore) -> QueryResult<Vec<StatusChange>> {
+        InMemoryReleaseDatabase::get_history(movie_id, session).await
+    }
+}
+
+/// Creates a movie due to be released `publish_in` millis from now
+async fn scheduled_movie(store: &'static CatalogStore, title: &str, publish_in: i64) -> i64 {
+    let query = format!(r#"mutation {{ createMovie(newMovie: {{ title: "{}", genres: ["Crime"] }}) {{ movieId }} }}"#, title);
+    let response = schema(store, FixtureSource::default()).execute(Request::new(query)).await;
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    let movie_id = response.data.into_json().unwrap()["createMovie"]["movieId"].as_str().unwrap().parse().unwrap();
+    let schedule = ReleaseSchedule::new(movie_id, Some(now_millis() + publish_in), None).unwrap();
+    InMemoryReleaseDatabase::set_schedule(schedule, store).await.unwrap();
+    movie_id
+}
+
+fn status(store: &CatalogStore, movie_id: i64) -> Status {
+    store.movies.get(&movie_id).unwrap().status()
+}
+
+#[tokio::test]
+async fn a_movie_that_fails_does_not_hold_up_the_others_and_is_retried() {
+    let store = leak(CatalogStore::default());
+    let after = now_millis();
+    let unreadable = scheduled_movie(store, "Heat", 1).await;
+    let readable = scheduled_movie(store, "Ronin", 2).await;
+    UNREADABLE_MOVIE.store(unreadable, Ordering::SeqCst);
+    let until = now_millis() + 10;
+
+    let sweep = apply_schedules::<InMemoryMovieDatabase, PartlyUnreadableReleaseDatabase>(after, until, store, store).await;
+    assert!(matches!(sweep, Err(ServiceError::DatabaseError)));
+    assert_eq!(status(store, readable), Status::Released);
+    assert_ne!(status(store, unreadable), Status::Released);
+
+    // The scheduler retries the sweep from the same point, the movie already released is left alone
+    let retried = apply_schedules::<InMemoryMovieDatabase, InMemoryReleaseDatabase>(after, until, store, store).await;
+    assert_eq!(retried.unwrap(), 1);
+    assert_eq!(status(store, unreadable), Status::Released);
+    assert_eq!(store.status_history.len(), 2);
+}
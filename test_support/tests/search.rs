@@ -0,0 +1,96 @@
+use async_graphql::Request;
+use common_utils::availability::RequestRegion;
+use search_service::graphql::modules::model::Movie;
+use serde_json::{json, Value};
+use test_support::search::{schema, SearchSchema, SearchStore};
+
+const INDEX: &str = "movies";
+
+/// A document as the indexer writes it, `available_in` left empty when `restricted` is false
+fn document(movie_id: i64, title: &str, genres: &[&str], available_in: &[&str]) -> Movie {
+    serde_json::from_value(json!({
+        "movie_id": movie_id,
+        "title": title,
+        "year": 1995,
+        "awards": [],
+        "business": { "budget": 0, "revenue": 0 },
+        "countries": [],
+        "genres": genres,
+        "homepage": "",
+        "keywords": [],
+        "languages": [],
+        "media_type": "Movie",
+        "movie_casts": [],
+        "movie_company": [],
+        "movie_director": [],
+        "movie_writer": [],
+        "overview": "",
+        "poster": "",
+        "rated": "R",
+        "rating": { "imdb_id": "", "metascore": 0, "popularity": 0.0, "vote_count": 0, "vote_average": 0.0 },
+        "release_date": "1995-12-15",
+        "runtime": 170,
+        "status": "Released",
+        "video_file": "",
+        "available_in": available_in,
+        "restricted": !available_in.is_empty(),
+    }))
+    .unwrap()
+}
+
+fn search() -> (SearchStore, SearchSchema) {
+    let store = SearchStore::default();
+    store.index(INDEX, document(1, "Heat", &["crime"], &["GB"]));
+    store.index(INDEX, document(2, "Ronin", &["action"], &["WW"]));
+    store.index(INDEX, document(3, "Thief", &["crime"], &[]));
+    store.index("movies_v2", document(4, "Collateral", &["crime"], &[]));
+    let schema = schema(store.clone());
+    (store, schema)
+}
+
+async fn execute(schema: &SearchSchema, request: Request) -> Value {
+    let response = schema.execute(request).await;
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    response.data.into_json().unwrap()
+}
+
+fn in_region(query: &str, region: &str) -> Request {
+    Request::new(query).data(RequestRegion { region: Some(region.to_string()) })
+}
+
+#[tokio::test]
+async fn search_only_returns_documents_available_in_the_region() {
+    let (_, schema) = search();
+    let query = r#"{ searchAll(indexName: "movies") { title } }"#;
+
+    let in_gb = execute(&schema, in_region(query, "GB")).await;
+    let in_us = execute(&schema, in_region(query, "US")).await;
+    let unknown = execute(&schema, Request::new(query)).await;
+
+    assert_eq!(in_gb, json!({ "searchAll": [{ "title": "Heat" }, { "title": "Ronin" }, { "title": "Thief" }] }));
+    assert_eq!(in_us, json!({ "searchAll": [{ "title": "Ronin" }, { "title": "Thief" }] }));
+    assert_eq!(unknown, in_us);
+}
+
+#[tokio::test]
+async fn filter_matches_any_element_of_an_array_field() {
+    let (_, schema) = search();
+
+    let filtered = execute(&schema, in_region(r#"{
+        filterBy(filter: { termName: "genres.keyword", termValue: "crime", indexName: "movies" }) { title }
+    }"#, "GB")).await;
+
+    assert_eq!(filtered, json!({ "filterBy": [{ "title": "Heat" }, { "title": "Thief" }] }));
+}
+
+#[tokio::test]
+async fn deleted_document_is_no_longer_found() {
+    let (store, schema) = search();
+
+    let deleted = execute(&schema, Request::new(r#"mutation { deleteMovieDocByID(movieId: "3") }"#)).await;
+    let remaining = execute(&schema, Request::new(r#"{ searchAll(indexName: "movies") { title } }"#)).await;
+
+    assert_eq!(deleted, json!({ "deleteMovieDocByID": true }));
+    assert_eq!(remaining, json!({ "searchAll": [{ "title": "Ronin" }] }));
+    assert_eq!(store.0.len(), 3);
+}